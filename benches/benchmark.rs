@@ -1,8 +1,14 @@
+use std::sync::Arc;
+
 use criterion::BatchSize::SmallInput;
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
 use nalgebra::Rotation3;
+use ray_tracing_in_one_weekend::arena::{SphereArena, Spheres};
 use ray_tracing_in_one_weekend::color::BLACK;
+use ray_tracing_in_one_weekend::hittable::{Bvh, BvhBuildStrategy};
 use ray_tracing_in_one_weekend::materials::Lambertian;
+use ray_tracing_in_one_weekend::ray::Ray;
+use ray_tracing_in_one_weekend::scenes::{self, SceneSettings};
 use ray_tracing_in_one_weekend::shapes::{Cuboid, Movable, Sphere};
 use ray_tracing_in_one_weekend::*;
 
@@ -28,9 +34,120 @@ fn criterion_benchmark(c: &mut Criterion) {
     raytracer.world.push(cuboid);
 
     c.bench_function("World", |b| {
-        b.iter_batched(|| raytracer.clone(), |rt| rt.render(), SmallInput);
+        b.iter_batched(|| raytracer.clone(), |rt| rt.render().unwrap(), SmallInput);
+    });
+}
+
+fn kernel_benchmark(c: &mut Criterion) {
+    let sphere = Sphere::new(
+        vector![0., 0., -1.],
+        0.5,
+        Lambertian::solid_color(color![1., 1., 1.]),
+    );
+    let ray = Ray::new(vector![0., 0., 0.], vector![0.1, 0.1, -1.]);
+    c.bench_function("Sphere hit", |b| {
+        b.iter(|| black_box(&sphere).hit(black_box(ray), 0.001, Float::INFINITY));
+    });
+
+    let world = scenes::random_spheres(&SceneSettings::default()).world;
+    c.bench_function("BVH build", |b| {
+        b.iter_batched(
+            || world.clone(),
+            |world| Bvh::new(world, 0., 0.).unwrap(),
+            SmallInput,
+        );
+    });
+    c.bench_function("BVH build (SAH)", |b| {
+        b.iter_batched(
+            || world.clone(),
+            |world| Bvh::build(world, 0., 0., BvhBuildStrategy::Sah { bins: 16 }).unwrap(),
+            SmallInput,
+        );
     });
+
+    let bvh = Bvh::new(world, 0., 0.).unwrap();
+    let rays: Vec<Ray> = (0..1000)
+        .map(|i| {
+            let angle = i as Float / 1000. * float::consts::TAU;
+            Ray::new(
+                vector![13., 2., 3.],
+                vector![angle.cos() - 13., -2., angle.sin() - 3.],
+            )
+        })
+        .collect();
+    c.bench_function("BVH traversal (1000 rays)", |b| {
+        b.iter(|| {
+            rays.iter()
+                .filter(|&&ray| bvh.hit(ray, 0.001, Float::INFINITY).is_some())
+                .count()
+        });
+    });
+
+    // The same spheres boxed in a `Bvh` and stored in an arena
+    let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+    let mut boxed = HittableList::default();
+    let mut spheres = Spheres::default();
+    for i in 0..100_000 {
+        let center = vector![
+            (i % 100) as Float * 0.2 - 10.,
+            (i / 10_000) as Float * 0.2 - 1.,
+            (i / 100 % 100) as Float * 0.2 - 10.
+        ];
+        boxed.push(Sphere::new(center, 0.08, material.clone()));
+        spheres.push(center, 0.08, 0);
+    }
+    let boxed = Bvh::new(boxed, 0., 0.).unwrap();
+    let arena = SphereArena::new(vector![0., 0., 0.], spheres, vec![Arc::new(material)]).unwrap();
+    c.bench_function("Boxed spheres traversal (1000 rays)", |b| {
+        b.iter(|| {
+            rays.iter()
+                .filter(|&&ray| boxed.hit(ray, 0.001, Float::INFINITY).is_some())
+                .count()
+        });
+    });
+    c.bench_function("Sphere arena traversal (1000 rays)", |b| {
+        b.iter(|| {
+            rays.iter()
+                .filter(|&&ray| arena.hit(ray, 0.001, Float::INFINITY).is_some())
+                .count()
+        });
+    });
+
+    let cornell = scenes::cornell_box(&SceneSettings::new(64, 64).with_samples(4, 8));
+    c.bench_function("Cornell box", |b| {
+        b.iter_batched(|| cornell.clone(), |rt| rt.render().unwrap(), SmallInput);
+    });
+}
+
+/// Scenes with millions of primitives built on instancing, reported in rays per second.
+fn stress_benchmark(c: &mut Criterion) {
+    let settings = SceneSettings::new(64, 36).with_samples(1, 4);
+    let mut group = c.benchmark_group("Stress");
+    group.sample_size(10);
+    for (name, raytracer) in [
+        (
+            "Sphere flake (5.3M spheres)",
+            scenes::instanced_sphere_flake(&settings, 7),
+        ),
+        (
+            "Menger sponge (3.2M cuboids)",
+            scenes::menger_sponge(&settings, 5),
+        ),
+    ] {
+        let stats = *raytracer.clone().render().unwrap().render_stats();
+        println!("{name}: {:.2} Mrays/s", stats.rays_per_second() / 1e6);
+        group.throughput(Throughput::Elements(stats.rays));
+        group.bench_function(name, |b| {
+            b.iter_batched(|| raytracer.clone(), |rt| rt.render().unwrap(), SmallInput);
+        });
+    }
+    group.finish();
 }
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(
+    benches,
+    criterion_benchmark,
+    kernel_benchmark,
+    stress_benchmark
+);
 criterion_main!(benches);
@@ -0,0 +1,97 @@
+#ifndef RAY_TRACING_IN_ONE_WEEKEND_H
+#define RAY_TRACING_IN_ONE_WEEKEND_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+/* Define RT_FLOAT_DOUBLE if the library was built with the `f64` feature. */
+#ifdef RT_FLOAT_DOUBLE
+typedef double rt_float;
+#else
+typedef float rt_float;
+#endif
+
+/* Result of a function of the C interface. */
+typedef enum RtStatus {
+  RT_STATUS_OK = 0,
+  /* A pointer was null. */
+  RT_STATUS_NULL_POINTER = 1,
+  /* An argument was out of range, e.g. a buffer that is too small. */
+  RT_STATUS_INVALID_ARGUMENT = 2,
+  /* Rendering failed or panicked. */
+  RT_STATUS_RENDER_FAILED = 3,
+} RtStatus;
+
+/* Kind of an RtMaterial, which stores it as a `uint32_t`. */
+typedef enum RtMaterialKind {
+  RT_MATERIAL_KIND_LAMBERTIAN = 0,
+  /* Uses `parameter` as the fuzz. */
+  RT_MATERIAL_KIND_METAL = 1,
+  /* Uses `parameter` as the index of refraction and ignores `color`. */
+  RT_MATERIAL_KIND_DIELECTRIC = 2,
+  /* Emits `color`. */
+  RT_MATERIAL_KIND_DIFFUSE_LIGHT = 3,
+} RtMaterialKind;
+
+/* A scene and its settings. */
+typedef struct RtRaytracer RtRaytracer;
+
+/* A vector or color. */
+typedef struct RtVec3 {
+  rt_float x;
+  rt_float y;
+  rt_float z;
+} RtVec3;
+
+/* A material of the C interface. */
+typedef struct RtMaterial {
+  /* Which material to create, an RtMaterialKind. */
+  uint32_t kind;
+  /* Albedo or emitted color. */
+  RtVec3 color;
+  /* Fuzz of a metal or index of refraction of a dielectric. */
+  rt_float parameter;
+} RtMaterial;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Create an empty scene with a black background and a camera with the aspect ratio of the image,
+ * or return NULL if the image has no pixels or no samples are taken. */
+RtRaytracer *rt_raytracer_new(uint32_t image_width,
+                              uint32_t image_height,
+                              uint16_t samples_per_pixel,
+                              uint16_t max_depth);
+
+/* Free a scene created by rt_raytracer_new. NULL is ignored. */
+void rt_raytracer_free(RtRaytracer *raytracer);
+
+/* Place the camera at `lookfrom` facing `lookat`. The field of view is in degrees. */
+RtStatus rt_raytracer_set_camera(RtRaytracer *raytracer,
+                                 RtVec3 lookfrom,
+                                 RtVec3 lookat,
+                                 RtVec3 vup,
+                                 rt_float vertical_fov,
+                                 rt_float aperture,
+                                 rt_float focus_distance);
+
+/* Set the color of the background. */
+RtStatus rt_raytracer_set_background(RtRaytracer *raytracer, RtVec3 background);
+
+/* Add a sphere. Spheres with a diffuse light are sampled as lights.
+ * Returns RT_STATUS_INVALID_ARGUMENT if the radius is not positive or the kind of the material is unknown. */
+RtStatus rt_raytracer_add_sphere(RtRaytracer *raytracer,
+                                 RtVec3 center,
+                                 rt_float radius,
+                                 RtMaterial material);
+
+/* Render into `buffer` as 8 bit RGBA, row by row from the top.
+ * `buffer_length` has to be at least 4 * image_width * image_height. */
+RtStatus rt_raytracer_render(const RtRaytracer *raytracer, uint8_t *buffer, size_t buffer_length);
+
+#ifdef __cplusplus
+}  /* extern "C" */
+#endif
+
+#endif /* RAY_TRACING_IN_ONE_WEEKEND_H */
@@ -0,0 +1,207 @@
+//! Python bindings of the ray tracer, built with [maturin](https://www.maturin.rs) (`maturin develop` in this directory).
+//!
+//! A [`Scene`] collects the camera, the settings, and the objects, and [`render`](Scene::render) returns the linear colors as a NumPy array of shape `(height, width, 3)`:
+//! ```python
+//! import ray_tracing_in_one_weekend as rt
+//!
+//! scene = rt.Scene(400, 225, samples_per_pixel=100, background=(0.7, 0.8, 1.0))
+//! scene.set_camera((0, 1, 3), (0, 0, -1), vertical_fov=40)
+//! scene.add_sphere((0, -100.5, -1), 100, rt.Lambertian((0.5, 0.5, 0.5)))
+//! scene.add_sphere((0, 0, -1), 0.5, rt.Dielectric(1.5))
+//! image = scene.render()
+//! ```
+
+use std::sync::Arc;
+
+use numpy::{PyArray1, PyArray3};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use ray_tracing_in_one_weekend::error::Error;
+use ray_tracing_in_one_weekend::materials::{
+    Dielectric, DiffuseLight, Lambertian, Material, Metal,
+};
+use ray_tracing_in_one_weekend::mesh::{Mesh, Shading};
+use ray_tracing_in_one_weekend::shapes::Sphere;
+use ray_tracing_in_one_weekend::*;
+
+type Triple = (Float, Float, Float);
+
+fn vector((x, y, z): Triple) -> Vector3<Float> {
+    vector![x, y, z]
+}
+
+fn color((r, g, b): Triple) -> Color {
+    color![r, g, b]
+}
+
+fn to_py_err(error: Error) -> PyErr {
+    match error {
+        Error::Io(error) => PyIOError::new_err(error.to_string()),
+        error => PyValueError::new_err(error.to_string()),
+    }
+}
+
+/// A material that can be shared by several objects. It is created by the constructors below.
+#[pyclass(name = "Material", subclass)]
+#[derive(Clone)]
+struct PyMaterial {
+    material: Arc<dyn Material>,
+}
+
+/// A diffuse material.
+#[pyfunction(name = "Lambertian")]
+fn lambertian(albedo: Triple) -> PyMaterial {
+    PyMaterial {
+        material: Arc::new(Lambertian::solid_color(color(albedo))),
+    }
+}
+
+/// A reflective material, which gets blurrier with `fuzz`.
+#[pyfunction(name = "Metal")]
+#[pyo3(signature = (albedo, fuzz = 0.))]
+fn metal(albedo: Triple, fuzz: Float) -> PyMaterial {
+    PyMaterial {
+        material: Arc::new(Metal::solid_color(color(albedo), fuzz)),
+    }
+}
+
+/// A transparent material like glass or water.
+#[pyfunction(name = "Dielectric")]
+fn dielectric(index_of_refraction: Float) -> PyMaterial {
+    PyMaterial {
+        material: Arc::new(Dielectric::new(index_of_refraction)),
+    }
+}
+
+/// An emissive material. Spheres with it are sampled as lights.
+#[pyfunction(name = "DiffuseLight")]
+fn diffuse_light(color: Triple) -> PyMaterial {
+    PyMaterial {
+        material: Arc::new(DiffuseLight::solid_color(self::color(color))),
+    }
+}
+
+/// A scene with a camera, its objects, and the render settings.
+#[pyclass]
+struct Scene {
+    camera: Camera,
+    background: Color,
+    image_width: u32,
+    image_height: u32,
+    samples_per_pixel: u16,
+    max_depth: u16,
+    world: HittableList,
+    lights: Vec<Sphere<Arc<dyn Material>>>,
+}
+
+#[pymethods]
+impl Scene {
+    #[new]
+    #[pyo3(signature = (image_width, image_height, samples_per_pixel = 100, max_depth = 50, background = (0., 0., 0.)))]
+    fn new(
+        image_width: u32,
+        image_height: u32,
+        samples_per_pixel: u16,
+        max_depth: u16,
+        background: Triple,
+    ) -> PyResult<Self> {
+        if image_width == 0 || image_height == 0 {
+            return Err(PyValueError::new_err("the image needs at least one pixel"));
+        }
+        Ok(Self {
+            camera: Camera::default(),
+            background: color(background),
+            image_width,
+            image_height,
+            samples_per_pixel,
+            max_depth,
+            world: HittableList::default(),
+            lights: Vec::new(),
+        })
+    }
+
+    /// Place the camera at `lookfrom` facing `lookat`. The field of view is in degrees.
+    #[pyo3(signature = (lookfrom, lookat, vup = (0., 1., 0.), vertical_fov = 40., aperture = 0., focus_distance = None))]
+    fn set_camera(
+        &mut self,
+        lookfrom: Triple,
+        lookat: Triple,
+        vup: Triple,
+        vertical_fov: Float,
+        aperture: Float,
+        focus_distance: Option<Float>,
+    ) {
+        let (lookfrom, lookat) = (vector(lookfrom), vector(lookat));
+        self.camera = Camera::new(
+            lookfrom,
+            lookat,
+            vector(vup),
+            vertical_fov.to_radians(),
+            self.image_width as Float / self.image_height as Float,
+            aperture,
+            focus_distance.unwrap_or_else(|| (lookat - lookfrom).norm()),
+        );
+    }
+
+    /// Add a sphere. Spheres with an emissive material are sampled as lights.
+    fn add_sphere(&mut self, center: Triple, radius: Float, material: PyMaterial) {
+        let is_emissive = material.material.is_emissive();
+        let sphere = Sphere::new(vector(center), radius, material.material);
+        if is_emissive {
+            self.lights.push(sphere);
+        } else {
+            self.world.push(sphere);
+        }
+    }
+
+    /// Load a Wavefront OBJ file, moved to `center`.
+    #[pyo3(signature = (path, material, center = (0., 0., 0.), smooth = true))]
+    fn add_mesh(
+        &mut self,
+        path: &str,
+        material: PyMaterial,
+        center: Triple,
+        smooth: bool,
+    ) -> PyResult<()> {
+        let shading = if smooth {
+            Shading::Smooth
+        } else {
+            Shading::Flat
+        };
+        let mesh = Mesh::open(vector(center), path, shading, material.material)?;
+        self.world.push(mesh);
+        Ok(())
+    }
+
+    /// Render the scene without holding the GIL and return the linear colors as an array of shape `(height, width, 3)`.
+    fn render<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray3<Float>> {
+        let mut raytracer = Raytracer::new(
+            self.camera.clone(),
+            self.background,
+            self.image_width,
+            self.image_height,
+            self.samples_per_pixel,
+            self.max_depth,
+        );
+        raytracer.world = self.world.clone();
+        for light in &self.lights {
+            raytracer.push_light(light.clone());
+        }
+
+        let image = py.allow_threads(|| raytracer.render()).map_err(to_py_err)?;
+        let (width, height) = image.dimensions();
+        let samples: Vec<Float> = image.colors().iter().flat_map(|&color| color).collect();
+        PyArray1::from_vec(py, samples).reshape([height as usize, width as usize, 3])
+    }
+}
+
+#[pymodule]
+fn ray_tracing_in_one_weekend(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyMaterial>()?;
+    module.add_class::<Scene>()?;
+    module.add_function(wrap_pyfunction!(lambertian, module)?)?;
+    module.add_function(wrap_pyfunction!(metal, module)?)?;
+    module.add_function(wrap_pyfunction!(dielectric, module)?)?;
+    module.add_function(wrap_pyfunction!(diffuse_light, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,595 @@
+//! Acceleration structures as alternatives to the [`Bvh`].
+//!
+//! All of them implement [`Hittable`] and can be selected for a render via [`Raytracer::with_accelerator`](crate::raytracer::Raytracer::with_accelerator), so their traversal performance can be compared on a scene (see also [`TraversalCost`]).
+
+use crate::hitrecord::HitRecord;
+use crate::hittable::{Aabb, BoundingBoxError, Bvh, HittableArc, TraversalCost};
+use crate::ray::Ray;
+use crate::shapes::Offset;
+use crate::stats::SceneStats;
+use crate::*;
+
+/// Acceleration structure built from the `world` of a [`Raytracer`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Accelerator {
+    /// [`Bvh`] built with the [`BvhBuildStrategy`](crate::hittable::BvhBuildStrategy) of the [`Raytracer`].
+    #[default]
+    Bvh,
+    /// [`KdTree`], which sometimes outperforms the [`Bvh`] for triangle-heavy scenes.
+    KdTree,
+    /// [`Grid`], which is fast for many similarly sized objects and cheap to rebuild.
+    Grid,
+}
+
+/// Intersect a [`Ray`] with an [`Aabb`] and return the parameter range inside of it.
+fn slab(aabb: &Aabb, ray: Ray, t_min: Float, t_max: Float) -> Option<(Float, Float)> {
+    let (mut t0, mut t1) = (t_min, t_max);
+    for axis in 0..3 {
+        let inverse_direction = 1. / ray.direction()[axis];
+        let mut near = (aabb.minimum[axis] - ray.origin()[axis]) * inverse_direction;
+        let mut far = (aabb.maximum[axis] - ray.origin()[axis]) * inverse_direction;
+        if near > far {
+            (near, far) = (far, near);
+        }
+        // NaN (ray in the plane of a face) leaves the range unchanged.
+        if near > t0 {
+            t0 = near;
+        }
+        if far < t1 {
+            t1 = far;
+        }
+        if t0 > t1 {
+            return None;
+        }
+    }
+    Some((t0, t1))
+}
+
+/// Estimated cost of testing a [`Hittable`] relative to traversing a node.
+const INTERSECTION_COST: Float = 80.;
+
+/// Estimated cost of traversing a node.
+const TRAVERSAL_COST: Float = 1.;
+
+/// Fraction of the cost saved for splits with an empty side.
+const EMPTY_BONUS: Float = 0.5;
+
+/// Node of a [`KdTree`], referencing other nodes and [`Hittable`]s by their index.
+#[derive(Clone, Debug)]
+enum KdNode {
+    Leaf(Vec<u32>),
+    Interior {
+        axis: usize,
+        split: Float,
+        below: u32,
+        above: u32,
+    },
+}
+
+/// KD-tree.
+///
+/// Space is recursively split by axis-aligned planes placed by the surface area heuristic. Unlike a [`Bvh`], the cells do not overlap, so
+/// the traversal visits them front to back and stops at the first cell containing a hit. [`Hittable`]s straddling a plane are referenced on both sides.
+///
+/// # Fields
+/// - `aabb`: [`Aabb`] of all [`Hittable`]s.
+/// - `hittables`: All [`Hittable`]s.
+/// - `nodes`: Nodes of the tree with the root first.
+/// - `depth`: Number of levels of the tree.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, accelerator::KdTree, materials::Lambertian, ray::Ray, shapes::Sphere};
+/// let mut world = HittableList::default();
+/// for i in 0..100 {
+///     world.push(Sphere::new(vector![i as Float, 0., 0.], 0.4, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+/// }
+/// let kd_tree = KdTree::new(world, 0., 0.).unwrap();
+///
+/// let hit = kd_tree.hit(Ray::new(vector![-5., 0., 0.], vector![1., 0., 0.]), 0.001, Float::INFINITY).unwrap();
+/// assert!((hit.t - 4.6).abs() < 1e-4);
+/// ```
+#[derive(Clone, Debug)]
+pub struct KdTree {
+    center: Offset,
+    aabb: Aabb,
+    hittables: Vec<HittableArc>,
+    nodes: Vec<KdNode>,
+    depth: usize,
+}
+
+impl KdTree {
+    /// Create a new [`KdTree`] from a [`HittableList`] that will be consumed as well as a time range.
+    ///
+    /// # Parameters
+    /// - `hittables`: [`HittableList`] to sort into the tree (consumed).
+    /// - `time0`: Starting time.
+    /// - `time1`: Ending time.
+    pub fn new(
+        hittables: HittableList,
+        time0: Float,
+        time1: Float,
+    ) -> Result<Self, BoundingBoxError> {
+        if !Bvh::check_hittable_list(&hittables) {
+            return Err(BoundingBoxError);
+        }
+
+        let hittables: Vec<HittableArc> = hittables.iter().cloned().collect();
+        let boxes: Vec<Aabb> = hittables
+            .iter()
+            .map(|hittable| hittable.bounding_box(time0, time1).unwrap())
+            .collect();
+        let aabb = boxes
+            .iter()
+            .skip(1)
+            .fold(boxes[0], |aabb, other| aabb.surrounding(other));
+
+        let max_depth = (8. + 1.3 * (hittables.len() as Float).log2()).round() as usize;
+        let mut kd_tree = Self {
+            center: Offset::default(),
+            aabb,
+            hittables,
+            nodes: Vec::new(),
+            depth: 0,
+        };
+        let items = (0..boxes.len() as u32).collect();
+        kd_tree.build(&boxes, items, aabb, max_depth, 0);
+        kd_tree.depth = kd_tree.node_depth(0);
+        Ok(kd_tree)
+    }
+
+    /// Number of levels of the subtree starting at `node`.
+    fn node_depth(&self, node: u32) -> usize {
+        match &self.nodes[node as usize] {
+            KdNode::Leaf(_) => 1,
+            KdNode::Interior { below, above, .. } => {
+                1 + self.node_depth(*below).max(self.node_depth(*above))
+            }
+        }
+    }
+
+    /// Number of levels of the tree.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Recursively build the subtree containing `items` inside `bounds` and return the index of its root node.
+    ///
+    /// # Parameters
+    /// - `boxes`: [`Aabb`]s of all [`Hittable`]s.
+    /// - `depth_left`: How many more levels may be created.
+    /// - `bad_refines`: How many of the parent splits did not reduce the expected cost.
+    fn build(
+        &mut self,
+        boxes: &[Aabb],
+        items: Vec<u32>,
+        bounds: Aabb,
+        depth_left: usize,
+        bad_refines: usize,
+    ) -> u32 {
+        let index = self.nodes.len() as u32;
+        self.nodes.push(KdNode::Leaf(Vec::new()));
+
+        let leaf_cost = INTERSECTION_COST * items.len() as Float;
+        let split = match items.len() <= 2 || depth_left == 0 {
+            true => None,
+            false => Self::find_split(boxes, &items, &bounds),
+        };
+        let bad_refines = match split {
+            Some((_, _, cost)) if cost > leaf_cost => bad_refines + 1,
+            _ => bad_refines,
+        };
+        let Some((axis, split, _)) = split.filter(|_| bad_refines < 3) else {
+            self.nodes[index as usize] = KdNode::Leaf(items);
+            return index;
+        };
+
+        let (below_items, above_items): (Vec<u32>, Vec<u32>) = (
+            items
+                .iter()
+                .copied()
+                .filter(|&item| {
+                    let aabb = &boxes[item as usize];
+                    aabb.minimum[axis] < split || aabb.maximum[axis] <= split
+                })
+                .collect(),
+            items
+                .iter()
+                .copied()
+                .filter(|&item| boxes[item as usize].maximum[axis] > split)
+                .collect(),
+        );
+        let (mut below_bounds, mut above_bounds) = (bounds, bounds);
+        below_bounds.maximum[axis] = split;
+        above_bounds.minimum[axis] = split;
+
+        let below = self.build(
+            boxes,
+            below_items,
+            below_bounds,
+            depth_left - 1,
+            bad_refines,
+        );
+        let above = self.build(
+            boxes,
+            above_items,
+            above_bounds,
+            depth_left - 1,
+            bad_refines,
+        );
+        self.nodes[index as usize] = KdNode::Interior {
+            axis,
+            split,
+            below,
+            above,
+        };
+        index
+    }
+
+    /// Find the split plane with the lowest expected cost by sweeping over the faces of the [`Aabb`]s on all axes.
+    ///
+    /// Returns the axis, the position of the plane, and the cost.
+    fn find_split(boxes: &[Aabb], items: &[u32], bounds: &Aabb) -> Option<(usize, Float, Float)> {
+        let size = bounds.maximum - bounds.minimum;
+        let inverse_area = 1. / bounds.surface_area();
+        let mut best: Option<(usize, Float, Float)> = None;
+
+        for axis in 0..3 {
+            let (other0, other1) = ((axis + 1) % 3, (axis + 2) % 3);
+            // Ends sort before starts at the same position, so flat boxes are not counted on both sides.
+            let mut edges: Vec<(Float, bool)> = items
+                .iter()
+                .flat_map(|&item| {
+                    let aabb = &boxes[item as usize];
+                    [(aabb.minimum[axis], true), (aabb.maximum[axis], false)]
+                })
+                .collect();
+            edges.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+            let (mut below, mut above) = (0, items.len());
+            for (position, start) in edges {
+                if !start {
+                    above -= 1;
+                }
+                if position > bounds.minimum[axis] && position < bounds.maximum[axis] {
+                    let side_area = |length: Float| {
+                        2. * (size[other0] * size[other1] + length * (size[other0] + size[other1]))
+                    };
+                    let below_probability =
+                        side_area(position - bounds.minimum[axis]) * inverse_area;
+                    let above_probability =
+                        side_area(bounds.maximum[axis] - position) * inverse_area;
+                    let bonus = match below == 0 || above == 0 {
+                        true => EMPTY_BONUS,
+                        false => 0.,
+                    };
+                    let cost = TRAVERSAL_COST
+                        + INTERSECTION_COST
+                            * (1. - bonus)
+                            * (below_probability * below as Float
+                                + above_probability * above as Float);
+                    if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                        best = Some((axis, position, cost));
+                    }
+                }
+                if start {
+                    below += 1;
+                }
+            }
+        }
+
+        best
+    }
+}
+
+impl Hittable for KdTree {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let (mut t0, mut t1) = slab(&self.aabb, ray, t_min, t_max)?;
+
+        let mut stack: Vec<(u32, Float, Float)> = Vec::with_capacity(self.depth);
+        let mut node = 0;
+        let mut closest = t_max;
+        let mut hit_record_final = None;
+        loop {
+            // All remaining cells lie behind the closest hit.
+            if closest < t0 {
+                break;
+            }
+
+            match &self.nodes[node as usize] {
+                KdNode::Interior {
+                    axis,
+                    split,
+                    below,
+                    above,
+                } => {
+                    TraversalCost::record(1, 0);
+                    let origin = ray.origin()[*axis];
+                    let direction = ray.direction()[*axis];
+                    let t_split = (split - origin) / direction;
+                    let below_first = origin < *split || (origin == *split && direction <= 0.);
+                    let (near, far) = match below_first {
+                        true => (*below, *above),
+                        false => (*above, *below),
+                    };
+
+                    if t_split.is_nan() || t_split > t1 || t_split <= 0. {
+                        node = near;
+                    } else if t_split < t0 {
+                        node = far;
+                    } else {
+                        stack.push((far, t_split, t1));
+                        node = near;
+                        t1 = t_split;
+                    }
+                }
+                KdNode::Leaf(items) => {
+                    TraversalCost::record(1, items.len() as u32);
+                    for &item in items {
+                        if let Some(hit_record) =
+                            self.hittables[item as usize].hit(ray, t_min, closest)
+                        {
+                            closest = hit_record.t;
+                            hit_record_final = Some(hit_record);
+                        }
+                    }
+
+                    match stack.pop() {
+                        Some((next, next_t0, next_t1)) => {
+                            (node, t0, t1) = (next, next_t0, next_t1);
+                        }
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        hit_record_final
+    }
+
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
+        Some(self.aabb)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        for hittable in &self.hittables {
+            hittable.collect_stats(stats);
+        }
+    }
+}
+
+/// Average number of [`Hittable`]s per cell of a [`Grid`].
+const GRID_DENSITY: Float = 2.;
+
+/// Maximum number of cells of a [`Grid`] along an axis.
+const GRID_MAX_RESOLUTION: usize = 128;
+
+/// [`Hittable`]s whose [`Aabb`] diagonal is longer than this factor times the median are not sorted into a [`Grid`].
+const GRID_LARGE_FACTOR: Float = 16.;
+
+/// Uniform grid.
+///
+/// The [`Aabb`] of all [`Hittable`]s is divided into equally sized cells, each storing which [`Hittable`]s overlap it. [`Ray`]s step through the cells
+/// they pass front to back and stop at the first cell containing a hit. Building is a single linear pass, so it is much cheaper to rebuild than a [`Bvh`], e.g. for animations.
+///
+/// Very large [`Hittable`]s (like a huge sphere as the ground) would make the cells too coarse, so they are kept in a separate list that is tested for every [`Ray`].
+///
+/// # Fields
+/// - `aabb`: [`Aabb`] of the cells.
+/// - `hittables`: All [`Hittable`]s.
+/// - `large`: Indices of the large [`Hittable`]s that are not sorted into cells.
+/// - `resolution`: Number of cells along each axis.
+/// - `cells`: Indices of the [`Hittable`]s overlapping each cell, with x varying fastest.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, accelerator::Grid, materials::Lambertian, ray::Ray, shapes::Sphere};
+/// let mut world = HittableList::default();
+/// world.push(Sphere::new(vector![0., -1000., 0.], 1000., Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+/// for x in 0..10 {
+///     for z in 0..10 {
+///         world.push(Sphere::new(vector![x as Float, 0.5, z as Float], 0.4, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+///     }
+/// }
+/// let grid = Grid::new(world.clone(), 0., 0.).unwrap();
+///
+/// for origin in [vector![0., 5., 0.], vector![0.5, 5., 0.5]] {
+///     let ray = Ray::new(origin, vector![0., -1., 0.]);
+///     let t = |hittable: &dyn Hittable| hittable.hit(ray, 0.001, Float::INFINITY).map(|hit| hit.t);
+///     assert_eq!(t(&grid), t(&world));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Grid {
+    center: Offset,
+    aabb: Aabb,
+    hittables: Vec<HittableArc>,
+    large: Vec<u32>,
+    resolution: [usize; 3],
+    cells: Vec<Vec<u32>>,
+}
+
+impl Grid {
+    /// Create a new [`Grid`] from a [`HittableList`] that will be consumed as well as a time range.
+    ///
+    /// # Parameters
+    /// - `hittables`: [`HittableList`] to sort into the cells (consumed).
+    /// - `time0`: Starting time.
+    /// - `time1`: Ending time.
+    pub fn new(
+        hittables: HittableList,
+        time0: Float,
+        time1: Float,
+    ) -> Result<Self, BoundingBoxError> {
+        if !Bvh::check_hittable_list(&hittables) {
+            return Err(BoundingBoxError);
+        }
+
+        let hittables: Vec<HittableArc> = hittables.iter().cloned().collect();
+        let boxes: Vec<Aabb> = hittables
+            .iter()
+            .map(|hittable| hittable.bounding_box(time0, time1).unwrap())
+            .collect();
+
+        let mut diagonals: Vec<Float> = boxes
+            .iter()
+            .map(|aabb| (aabb.maximum - aabb.minimum).norm())
+            .collect();
+        diagonals.sort_by(Float::total_cmp);
+        let large_diagonal = GRID_LARGE_FACTOR * diagonals[diagonals.len() / 2];
+        let (large, small): (Vec<u32>, Vec<u32>) = (0..boxes.len() as u32).partition(|&item| {
+            (boxes[item as usize].maximum - boxes[item as usize].minimum).norm() > large_diagonal
+        });
+
+        let aabb = small
+            .iter()
+            .map(|&item| boxes[item as usize])
+            .reduce(|aabb, other| aabb.surrounding(&other))
+            .unwrap_or(Aabb::new(Vector3::zeros(), Vector3::zeros()));
+
+        // Cells as close to cubes as possible with GRID_DENSITY Hittables each on average.
+        let size = (aabb.maximum - aabb.minimum).map(|length| length.max(Float::EPSILON));
+        let cells_per_length =
+            (GRID_DENSITY * small.len() as Float / (size.x * size.y * size.z)).cbrt();
+        let resolution = [0, 1, 2].map(|axis| {
+            ((size[axis] * cells_per_length).round() as usize).clamp(1, GRID_MAX_RESOLUTION)
+        });
+
+        let mut grid = Self {
+            center: Offset::default(),
+            aabb,
+            hittables,
+            large,
+            resolution,
+            cells: vec![Vec::new(); resolution.iter().product()],
+        };
+        for item in small {
+            let aabb = &boxes[item as usize];
+            let minimum = grid.cell_of(aabb.minimum);
+            let maximum = grid.cell_of(aabb.maximum);
+            for z in minimum[2]..=maximum[2] {
+                for y in minimum[1]..=maximum[1] {
+                    for x in minimum[0]..=maximum[0] {
+                        let index = grid.index([x, y, z]);
+                        grid.cells[index].push(item);
+                    }
+                }
+            }
+        }
+        Ok(grid)
+    }
+
+    /// Number of cells along each axis.
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    /// Size of a cell.
+    fn cell_size(&self) -> Vector3<Float> {
+        Vector3::from_fn(|axis, _| {
+            (self.aabb.maximum[axis] - self.aabb.minimum[axis]) / self.resolution[axis] as Float
+        })
+    }
+
+    /// Coordinates of the cell containing `point`, clamped to the grid.
+    fn cell_of(&self, point: Vector3<Float>) -> [usize; 3] {
+        let cell_size = self.cell_size();
+        [0, 1, 2].map(|axis| {
+            let cell = (point[axis] - self.aabb.minimum[axis]) / cell_size[axis];
+            match cell.is_finite() {
+                true => (cell.max(0.) as usize).min(self.resolution[axis] - 1),
+                false => 0,
+            }
+        })
+    }
+
+    /// Index of a cell in `cells`.
+    fn index(&self, [x, y, z]: [usize; 3]) -> usize {
+        x + self.resolution[0] * (y + self.resolution[1] * z)
+    }
+}
+
+impl Hittable for Grid {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let mut closest = t_max;
+        let mut hit_record_final = None;
+        for &item in &self.large {
+            if let Some(hit_record) = self.hittables[item as usize].hit(ray, t_min, closest) {
+                closest = hit_record.t;
+                hit_record_final = Some(hit_record);
+            }
+        }
+
+        let Some((t0, t1)) = slab(&self.aabb, ray, t_min, closest) else {
+            return hit_record_final;
+        };
+
+        // 3D digital differential analyzer by Amanatides and Woo.
+        let cell_size = self.cell_size();
+        let mut cell = self.cell_of(ray.at(t0));
+        let mut t_next = [Float::INFINITY; 3];
+        let mut t_delta = [Float::INFINITY; 3];
+        let mut step = [0isize; 3];
+        for axis in 0..3 {
+            let direction = ray.direction()[axis];
+            if direction == 0. {
+                continue;
+            }
+            step[axis] = direction.signum() as isize;
+            let boundary = self.aabb.minimum[axis]
+                + (cell[axis] + usize::from(direction > 0.)) as Float * cell_size[axis];
+            t_next[axis] = (boundary - ray.origin()[axis]) / direction;
+            t_delta[axis] = cell_size[axis] / direction.abs();
+        }
+
+        loop {
+            let items = &self.cells[self.index(cell)];
+            TraversalCost::record(1, items.len() as u32);
+            for &item in items {
+                if let Some(hit_record) = self.hittables[item as usize].hit(ray, t_min, closest) {
+                    closest = hit_record.t;
+                    hit_record_final = Some(hit_record);
+                }
+            }
+
+            let axis = (0..3)
+                .min_by(|&a, &b| t_next[a].total_cmp(&t_next[b]))
+                .unwrap();
+            // Hits in later cells cannot be closer.
+            if closest <= t_next[axis] || t_next[axis] > t1 {
+                break;
+            }
+            let next = cell[axis] as isize + step[axis];
+            if next < 0 || next >= self.resolution[axis] as isize {
+                break;
+            }
+            cell[axis] = next as usize;
+            t_next[axis] += t_delta[axis];
+        }
+
+        hit_record_final
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        Some(
+            self.large
+                .iter()
+                .filter_map(|&item| self.hittables[item as usize].bounding_box(time0, time1))
+                .fold(self.aabb, |aabb, other| aabb.surrounding(&other)),
+        )
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        for hittable in &self.hittables {
+            hittable.collect_stats(stats);
+        }
+    }
+}
@@ -0,0 +1,631 @@
+//! Contiguous storage for large numbers of simple primitives.
+//!
+//! A [`HittableList`] boxes every primitive as its own [`Hittable`] with its own material, and a [`Bvh`](crate::hittable::Bvh) over it is a tree of boxed nodes.
+//! An [`Arena`] instead stores [`Spheres`] or [`Triangles`] in structure-of-arrays layout (one array per coordinate) and references their materials by index into a shared palette.
+//! Its hierarchy is a flat array of nodes whose leaves cover ranges of the primitives, which are reordered so that the primitives of each leaf lie next to each other.
+//! This saves an allocation per primitive, keeps the traversal in cache, and the plain arrays can be handed to vectorized or GPU intersection code as they are.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use nalgebra::Rotation3;
+
+use crate::error::Error;
+use crate::hitrecord::HitRecord;
+use crate::hittable::{Aabb, TraversalCost};
+use crate::materials::Material;
+use crate::ray::Ray;
+use crate::shapes::{Movable, Offset, UvMapping};
+use crate::stats::SceneStats;
+use crate::*;
+
+/// Primitives stored in structure-of-arrays layout, see [`Spheres`] and [`Triangles`].
+pub trait Primitives: Clone + Debug + Send + Sync {
+    /// Number of primitives.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Index of the material of the primitive at `index` in the palette of the [`Arena`].
+    fn material(&self, index: usize) -> u32;
+
+    /// [`Aabb`] of the primitive at `index`.
+    fn bounding_box(&self, index: usize) -> Aabb;
+
+    /// Parameter of the hit of `ray` with the primitive at `index` between `t_min` and `t_max`, and two coordinates on its surface that [`hit_record`](Primitives::hit_record) needs.
+    ///
+    /// This only reads the positions, so it is cheap enough to be called for every primitive of a visited leaf.
+    fn intersect(
+        &self,
+        index: usize,
+        ray: Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(Float, Float, Float)>;
+
+    /// [`HitRecord`] of the closest hit found by [`intersect`](Primitives::intersect).
+    fn hit_record<'a>(
+        &self,
+        index: usize,
+        ray: Ray,
+        hit: (Float, Float, Float),
+        material: &'a dyn Material,
+    ) -> HitRecord<'a>;
+
+    /// Why the primitive at `index` is degenerate, if it is.
+    fn degenerate(&self, index: usize) -> Option<&'static str>;
+
+    /// Memory used by one primitive in bytes.
+    fn size_of_primitive(&self) -> usize;
+
+    /// Reorder the primitives, so that the one at `order[i]` moves to `i`.
+    fn reorder(&mut self, order: &[usize]);
+}
+
+/// Reorder `values`, so that the one at `order[i]` moves to `i`.
+fn reorder<T: Clone>(values: &mut Vec<T>, order: &[usize]) {
+    *values = order.iter().map(|&index| values[index].clone()).collect();
+}
+
+/// Spheres in structure-of-arrays layout.
+///
+/// Their surface coordinates are [spherical](UvMapping::Spherical).
+///
+/// # Fields
+/// - `centers`: x, y, and z coordinates of the centers.
+/// - `radii`: Radii.
+/// - `materials`: Index of the material of each sphere.
+#[derive(Clone, Debug, Default)]
+pub struct Spheres {
+    centers: [Vec<Float>; 3],
+    radii: Vec<Float>,
+    materials: Vec<u32>,
+}
+
+impl Spheres {
+    /// Add a sphere with the material at index `material` of the [`Arena`].
+    pub fn push(&mut self, center: Vector3<Float>, radius: Float, material: u32) {
+        for (axis, coordinates) in self.centers.iter_mut().enumerate() {
+            coordinates.push(center[axis]);
+        }
+        self.radii.push(radius);
+        self.materials.push(material);
+    }
+
+    /// x, y, and z coordinates of the centers.
+    pub fn centers(&self) -> &[Vec<Float>; 3] {
+        &self.centers
+    }
+
+    pub fn radii(&self) -> &[Float] {
+        &self.radii
+    }
+
+    fn center(&self, index: usize) -> Vector3<Float> {
+        Vector3::from_fn(|axis, _| self.centers[axis][index])
+    }
+}
+
+impl Primitives for Spheres {
+    fn len(&self) -> usize {
+        self.radii.len()
+    }
+
+    fn material(&self, index: usize) -> u32 {
+        self.materials[index]
+    }
+
+    fn bounding_box(&self, index: usize) -> Aabb {
+        let center = self.center(index);
+        let radius = Vector3::repeat(self.radii[index].abs());
+        Aabb::new(center - radius, center + radius)
+    }
+
+    fn intersect(
+        &self,
+        index: usize,
+        ray: Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(Float, Float, Float)> {
+        let oc = ray.origin() - self.center(index);
+        let a = ray.direction().norm_squared();
+        let b_halves = oc.dot(&ray.direction());
+        let c = oc.norm_squared() - self.radii[index].powi(2);
+        let discriminant = b_halves.powi(2) - a * c;
+        if discriminant < 0. {
+            return None;
+        }
+        let discriminant_sqrt = discriminant.sqrt();
+
+        [-discriminant_sqrt, discriminant_sqrt]
+            .into_iter()
+            .map(|sign| (-b_halves + sign) / a)
+            .find(|root| (t_min..=t_max).contains(root))
+            .map(|root| (root, 0., 0.))
+    }
+
+    fn hit_record<'a>(
+        &self,
+        index: usize,
+        ray: Ray,
+        (t, _, _): (Float, Float, Float),
+        material: &'a dyn Material,
+    ) -> HitRecord<'a> {
+        let point = ray.at(t);
+        let normal = (point - self.center(index)) / self.radii[index];
+        let (u, v) = UvMapping::Spherical.map(normal);
+        HitRecord::from_ray(point, u, v, normal, t, material, ray)
+            .with_tangent(vector![normal.z, 0., -normal.x])
+    }
+
+    fn degenerate(&self, index: usize) -> Option<&'static str> {
+        (!self.radii[index].is_normal()).then_some("sphere with zero or invalid radius")
+    }
+
+    fn size_of_primitive(&self) -> usize {
+        4 * std::mem::size_of::<Float>() + std::mem::size_of::<u32>()
+    }
+
+    fn reorder(&mut self, order: &[usize]) {
+        for coordinates in &mut self.centers {
+            reorder(coordinates, order);
+        }
+        reorder(&mut self.radii, order);
+        reorder(&mut self.materials, order);
+    }
+}
+
+/// Triangles in structure-of-arrays layout, like [`Triangle`](crate::shapes::Triangle)s without texture coordinates.
+///
+/// The surface coordinates (u, v) are the barycentric coordinates of the hit point with respect to the second and third vertex.
+///
+/// # Fields
+/// - `vertices`: x, y, and z coordinates of the first vertices.
+/// - `edges`: x, y, and z coordinates of the edges from the first to the second and to the third vertices.
+/// - `normals`: Normals at the vertices for smooth shading, if there are any. They are only read for the closest hit, so they are stored per triangle.
+/// - `materials`: Index of the material of each triangle.
+#[derive(Clone, Debug, Default)]
+pub struct Triangles {
+    vertices: [Vec<Float>; 3],
+    edges: [[Vec<Float>; 3]; 2],
+    normals: Vec<Option<[Vector3<Float>; 3]>>,
+    materials: Vec<u32>,
+}
+
+impl Triangles {
+    /// Add a triangle with the material at index `material` of the [`Arena`].
+    ///
+    /// The order of the `vertices` defines the front face (counterclockwise). If there are `normals`, they are interpolated for the shading normal.
+    pub fn push(
+        &mut self,
+        vertices: [Vector3<Float>; 3],
+        normals: Option<[Vector3<Float>; 3]>,
+        material: u32,
+    ) {
+        let [a, b, c] = vertices;
+        for axis in 0..3 {
+            self.vertices[axis].push(a[axis]);
+            self.edges[0][axis].push(b[axis] - a[axis]);
+            self.edges[1][axis].push(c[axis] - a[axis]);
+        }
+        self.normals
+            .push(normals.map(|normals| normals.map(|normal| normal.normalize())));
+        self.materials.push(material);
+    }
+
+    /// x, y, and z coordinates of the first vertices.
+    pub fn vertices(&self) -> &[Vec<Float>; 3] {
+        &self.vertices
+    }
+
+    /// x, y, and z coordinates of the edges from the first to the second vertices and from the first to the third vertices.
+    pub fn edges(&self) -> &[[Vec<Float>; 3]; 2] {
+        &self.edges
+    }
+
+    /// First vertex and both edges of the triangle at `index`.
+    fn triangle(&self, index: usize) -> [Vector3<Float>; 3] {
+        [
+            Vector3::from_fn(|axis, _| self.vertices[axis][index]),
+            Vector3::from_fn(|axis, _| self.edges[0][axis][index]),
+            Vector3::from_fn(|axis, _| self.edges[1][axis][index]),
+        ]
+    }
+}
+
+impl Primitives for Triangles {
+    fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    fn material(&self, index: usize) -> u32 {
+        self.materials[index]
+    }
+
+    fn bounding_box(&self, index: usize) -> Aabb {
+        let [a, edge1, edge2] = self.triangle(index);
+        let (b, c) = (a + edge1, a + edge2);
+        let padding = vector![0.0001, 0.0001, 0.0001];
+        Aabb::new(a.inf(&b).inf(&c) - padding, a.sup(&b).sup(&c) + padding)
+    }
+
+    /// Möller–Trumbore intersection
+    fn intersect(
+        &self,
+        index: usize,
+        ray: Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(Float, Float, Float)> {
+        let [a, edge1, edge2] = self.triangle(index);
+
+        let p = ray.direction().cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < 1e-8 {
+            return None;
+        }
+        let inverse_determinant = 1. / determinant;
+
+        let s = ray.origin() - a;
+        let u = s.dot(&p) * inverse_determinant;
+        if !(0. ..=1.).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let v = ray.direction().dot(&q) * inverse_determinant;
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+
+        let t = edge2.dot(&q) * inverse_determinant;
+        (t_min..=t_max).contains(&t).then_some((t, u, v))
+    }
+
+    fn hit_record<'a>(
+        &self,
+        index: usize,
+        ray: Ray,
+        (t, u, v): (Float, Float, Float),
+        material: &'a dyn Material,
+    ) -> HitRecord<'a> {
+        let [_, edge1, edge2] = self.triangle(index);
+        let normal = edge1.cross(&edge2);
+        let area_squared = normal.norm_squared();
+        // Gradients of the barycentric coordinates of the second and third vertex along the surface
+        let gradient_b = edge2.cross(&normal) / area_squared;
+        let gradient_c = normal.cross(&edge1) / area_squared;
+        let hit = HitRecord::from_ray(ray.at(t), u, v, normal.normalize(), t, material, ray)
+            .with_tangent(edge1)
+            .with_uv_gradient(gradient_b, gradient_c);
+
+        match self.normals[index] {
+            Some([na, nb, nc]) => hit.with_shading_normal((1. - u - v) * na + u * nb + v * nc),
+            None => hit,
+        }
+    }
+
+    fn degenerate(&self, index: usize) -> Option<&'static str> {
+        let [a, edge1, edge2] = self.triangle(index);
+        if [a, edge1, edge2]
+            .iter()
+            .any(|vector| !vector.iter().all(|x| x.is_finite()))
+        {
+            Some("triangle with invalid vertices")
+        } else if edge1.cross(&edge2).norm_squared() == 0. {
+            Some("triangle with zero area")
+        } else {
+            None
+        }
+    }
+
+    fn size_of_primitive(&self) -> usize {
+        9 * std::mem::size_of::<Float>()
+            + std::mem::size_of::<Option<[Vector3<Float>; 3]>>()
+            + std::mem::size_of::<u32>()
+    }
+
+    fn reorder(&mut self, order: &[usize]) {
+        for coordinates in self
+            .vertices
+            .iter_mut()
+            .chain(self.edges.iter_mut().flatten())
+        {
+            reorder(coordinates, order);
+        }
+        reorder(&mut self.normals, order);
+        reorder(&mut self.materials, order);
+    }
+}
+
+/// Node of the hierarchy of an [`Arena`].
+///
+/// # Fields
+/// - `aabb`: [`Aabb`] of all primitives below the node.
+/// - `offset`: Index of the first primitive for a leaf, otherwise of the second child. The first child directly follows the node.
+/// - `count`: Number of primitives of a leaf, zero for interior nodes.
+/// - `axis`: Axis the children of an interior node are split along.
+#[derive(Clone, Copy, Debug)]
+struct ArenaNode {
+    aabb: Aabb,
+    offset: u32,
+    count: u16,
+    axis: u8,
+}
+
+/// [`Primitives`] with a palette of materials, sorted into a flat bounding volume hierarchy.
+///
+/// # Fields
+/// - `center`: Its [`Offset`].
+/// - `primitives`: The [`Primitives`], reordered by the leaves of the hierarchy.
+/// - `materials`: Palette the [`Primitives`] reference by index.
+/// - `nodes`: Nodes of the hierarchy with the root first.
+/// - `depth`: Number of levels of the hierarchy.
+///
+/// # Example
+/// ```
+/// # use std::sync::Arc;
+/// # use ray_tracing_in_one_weekend::{*, arena::{Arena, Spheres}, materials::{Lambertian, Material}, ray::Ray};
+/// let materials: Vec<Arc<dyn Material>> = vec![
+///     Arc::new(Lambertian::solid_color(color![0.8, 0.2, 0.2])),
+///     Arc::new(Lambertian::solid_color(color![0.2, 0.2, 0.8])),
+/// ];
+/// let mut spheres = Spheres::default();
+/// for i in 0..1000 {
+///     spheres.push(vector![(i % 10) as Float, (i / 100) as Float, (i / 10 % 10) as Float], 0.4, i % 2);
+/// }
+/// let arena = Arena::new(vector![0., 0., 0.], spheres, materials).unwrap();
+///
+/// let hit = arena.hit(Ray::new(vector![0., 0., -5.], vector![0., 0., 1.]), 0.001, Float::INFINITY).unwrap();
+/// assert!((hit.t - 4.6).abs() < 1e-4);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Arena<P: Primitives> {
+    center: Offset,
+    primitives: P,
+    materials: Vec<Arc<dyn Material>>,
+    nodes: Vec<ArenaNode>,
+    depth: usize,
+}
+
+/// An [`Arena`] of [`Spheres`].
+pub type SphereArena = Arena<Spheres>;
+
+/// An [`Arena`] of [`Triangles`].
+pub type TriangleArena = Arena<Triangles>;
+
+impl<P: Primitives> Arena<P> {
+    /// Largest number of primitives in a leaf.
+    const LEAF_SIZE: usize = 4;
+
+    /// Sort `primitives` relative to `center` into the hierarchy. They reference the `materials` by index.
+    ///
+    /// Returns an [`Error::Config`] if there are no primitives or one references a material that does not exist.
+    pub fn new(
+        center: Vector3<Float>,
+        mut primitives: P,
+        materials: Vec<Arc<dyn Material>>,
+    ) -> Result<Self, Error> {
+        if primitives.is_empty() {
+            return Err(Error::Config("arena without primitives".to_string()));
+        }
+        if let Some(index) = (0..primitives.len())
+            .find(|&index| primitives.material(index) as usize >= materials.len())
+        {
+            return Err(Error::Config(format!(
+                "primitive {index} references material {} of {}",
+                primitives.material(index),
+                materials.len()
+            )));
+        }
+
+        let boxes: Vec<Aabb> = (0..primitives.len())
+            .map(|index| primitives.bounding_box(index))
+            .collect();
+        let mut order: Vec<usize> = (0..primitives.len()).collect();
+        let mut nodes = Vec::with_capacity(2 * primitives.len() / Self::LEAF_SIZE + 1);
+        let depth = Self::build(&boxes, &mut order, 0, &mut nodes);
+        primitives.reorder(&order);
+
+        Ok(Self {
+            center: Offset::new(center),
+            primitives,
+            materials,
+            nodes,
+            depth,
+        })
+    }
+
+    /// Append the nodes of the subtree over `order` (the primitives starting at `first`) and return its depth.
+    ///
+    /// Interior nodes split the primitives at the median of their centers along the axis in which the centers spread the most.
+    fn build(
+        boxes: &[Aabb],
+        order: &mut [usize],
+        first: usize,
+        nodes: &mut Vec<ArenaNode>,
+    ) -> usize {
+        let aabb = order
+            .iter()
+            .map(|&index| boxes[index])
+            .reduce(|a, b| a.surrounding(&b))
+            .expect("subtrees are not empty");
+        let index = nodes.len();
+        nodes.push(ArenaNode {
+            aabb,
+            offset: first as u32,
+            count: 0,
+            axis: 0,
+        });
+        if order.len() <= Self::LEAF_SIZE {
+            nodes[index].count = order.len() as u16;
+            return 1;
+        }
+
+        let center = |index: usize| boxes[index].minimum + boxes[index].maximum;
+        let (minimum, maximum) = order.iter().fold(
+            (
+                Vector3::repeat(Float::INFINITY),
+                Vector3::repeat(Float::NEG_INFINITY),
+            ),
+            |(minimum, maximum), &index| (minimum.inf(&center(index)), maximum.sup(&center(index))),
+        );
+        let axis = (maximum - minimum).imax();
+        let mid = order.len() / 2;
+        order.select_nth_unstable_by(mid, |&a, &b| center(a)[axis].total_cmp(&center(b)[axis]));
+
+        let (left, right) = order.split_at_mut(mid);
+        let left_depth = Self::build(boxes, left, first, nodes);
+        nodes[index].offset = nodes.len() as u32;
+        let right_depth = Self::build(boxes, right, first + mid, nodes);
+        nodes[index].axis = axis as u8;
+        1 + left_depth.max(right_depth)
+    }
+
+    /// The [`Primitives`], reordered by the leaves of the hierarchy.
+    pub fn primitives(&self) -> &P {
+        &self.primitives
+    }
+
+    /// Palette the [`Primitives`] reference by index.
+    pub fn materials(&self) -> &[Arc<dyn Material>] {
+        &self.materials
+    }
+
+    /// Number of levels of the hierarchy.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+impl<P: Primitives> Hittable for Arena<P> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, mut t_max: Float) -> Option<HitRecord<'_>> {
+        let mut closest = None;
+        let mut stack = Vec::with_capacity(2 * self.depth);
+        stack.push(0);
+        let (mut visited, mut tested) = (0, 0);
+
+        while let Some(index) = stack.pop() {
+            let node: &ArenaNode = &self.nodes[index];
+            visited += 1;
+            if !node.aabb.hit(ray, t_min, t_max) {
+                continue;
+            }
+
+            if node.count > 0 {
+                let first = node.offset as usize;
+                for primitive in first..first + node.count as usize {
+                    tested += 1;
+                    if let Some(hit) = self.primitives.intersect(primitive, ray, t_min, t_max) {
+                        t_max = hit.0;
+                        closest = Some((primitive, hit));
+                    }
+                }
+            } else {
+                // Visit the child closer to the origin of the ray first, so the other one is more likely to be skipped.
+                let (first, second) = (index + 1, node.offset as usize);
+                if ray.direction()[node.axis as usize] < 0. {
+                    stack.extend([first, second]);
+                } else {
+                    stack.extend([second, first]);
+                }
+            }
+        }
+        TraversalCost::record(visited, tested);
+
+        let (primitive, hit) = closest?;
+        let material = &*self.materials[self.primitives.material(primitive) as usize];
+        Some(self.primitives.hit_record(primitive, ray, hit, material))
+    }
+
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
+        Some(self.nodes[0].aabb)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        for index in 0..self.primitives.len() {
+            let material = &*self.materials[self.primitives.material(index) as usize];
+            stats.add_primitive(
+                self.primitives.size_of_primitive(),
+                Some(material),
+                self.primitives.degenerate(index),
+            );
+        }
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.materials.iter().any(|material| material.is_emissive())
+    }
+}
+
+impl<P: Primitives> Movable for Arena<P> {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::hittable::Bvh;
+    use crate::materials::Lambertian;
+    use crate::shapes::{Sphere, Triangle};
+
+    #[test]
+    fn same_hits_as_bvh() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+        let mut spheres = Spheres::default();
+        let mut triangles = Triangles::default();
+        let mut list = HittableList::default();
+        for _ in 0..200 {
+            let center = Vector3::from_fn(|_, _| rng.gen_range(-5. ..5.));
+            let radius = rng.gen_range(0.05..0.3);
+            spheres.push(center, radius, 0);
+            list.push(Sphere::new(center, radius, material.clone()));
+
+            let vertices =
+                [0; 3].map(|_| center + Vector3::from_fn(|_, _| rng.gen_range(-0.5..0.5)));
+            triangles.push(vertices, None, 0);
+            let [a, b, c] = vertices;
+            list.push(Triangle::new(a, b, c, material.clone()));
+        }
+        let materials: Vec<Arc<dyn Material>> = vec![Arc::new(material)];
+        let mut arenas = HittableList::default();
+        arenas.push(SphereArena::new(vector![0., 0., 0.], spheres, materials.clone()).unwrap());
+        arenas.push(TriangleArena::new(vector![0., 0., 0.], triangles, materials).unwrap());
+        let arenas = Bvh::new(arenas, 0., 0.).unwrap();
+        let bvh = Bvh::new(list, 0., 0.).unwrap();
+
+        for _ in 0..1000 {
+            let origin = Vector3::from_fn(|_, _| rng.gen_range(-8. ..8.));
+            let direction = Vector3::from_fn(|_, _| rng.gen_range(-1. ..1.));
+            let ray = Ray::new(origin, direction);
+            let expected = bvh.hit(ray, 0.001, Float::INFINITY);
+            let hit = arenas.hit(ray, 0.001, Float::INFINITY);
+            assert_eq!(hit.is_some(), expected.is_some());
+            if let (Some(hit), Some(expected)) = (hit, expected) {
+                assert!((hit.t - expected.t).abs() < 1e-4);
+                assert!((hit.normal - expected.normal).norm() < 1e-3);
+            }
+        }
+
+        assert!(Arena::new(vector![0., 0., 0.], Spheres::default(), Vec::new()).is_err());
+    }
+}
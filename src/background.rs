@@ -0,0 +1,566 @@
+//! What [`Ray`](crate::ray::Ray)s see if they do not hit anything.
+//!
+//! Besides a constant color and a simple gradient, there is a procedural [`Sky`] for outdoor scenes and an [`EnvironmentMap`] for HDR photographs of real surroundings.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use image::io::Reader as ImageReader;
+use image::{ImageError, Rgb32FImage};
+use rand::Rng;
+
+use crate::float::consts::PI;
+
+use crate::lights::DirectionalLight;
+use crate::*;
+
+/// Background of a scene.
+///
+/// Everything that implements `Into<Background>` (i.e. [`Color`], [`Sky`], and [`EnvironmentMap`]) can be passed to [`Raytracer::new`].
+#[derive(Clone, Debug)]
+pub enum Background {
+    /// The same color in every direction.
+    Color(Color),
+    /// Linear interpolation from `bottom` (straight down) to `top` (straight up), as in the book.
+    Gradient { bottom: Color, top: Color },
+    /// A physically based [`Sky`].
+    Sky(Sky),
+    /// An [`EnvironmentMap`], which the path tracer samples directly.
+    Environment(EnvironmentMap),
+}
+
+impl Background {
+    /// Color seen in `direction`.
+    pub fn color(&self, direction: Vector3<Float>) -> Color {
+        match self {
+            Background::Color(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.normalize().y + 1.);
+                (1. - t) * *bottom + t * *top
+            }
+            Background::Sky(sky) => sky.color(direction),
+            Background::Environment(environment) => environment.color(direction),
+        }
+    }
+
+    /// Whether the background is black in every direction, so it does not light the scene.
+    pub fn is_black(&self) -> bool {
+        let black = |color: &Color| color.into_iter().all(|c| c <= 0.);
+        match self {
+            Background::Color(color) => black(color),
+            Background::Gradient { bottom, top } => black(bottom) && black(top),
+            Background::Sky(_) => false,
+            Background::Environment(environment) => environment.is_black(),
+        }
+    }
+
+    /// The sun of a [`Sky`] as a light source that can be sampled, if there is one.
+    pub fn sun(&self) -> Option<DirectionalLight> {
+        match self {
+            Background::Sky(sky) => sky.sun(),
+            _ => None,
+        }
+    }
+
+    /// The [`EnvironmentMap`], if the background is one.
+    pub fn environment(&self) -> Option<&EnvironmentMap> {
+        match self {
+            Background::Environment(environment) => Some(environment),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Color(color![0., 0., 0.])
+    }
+}
+
+impl From<Color> for Background {
+    fn from(color: Color) -> Self {
+        Background::Color(color)
+    }
+}
+
+impl From<Sky> for Background {
+    fn from(sky: Sky) -> Self {
+        Background::Sky(sky)
+    }
+}
+
+impl From<EnvironmentMap> for Background {
+    fn from(environment: EnvironmentMap) -> Self {
+        Background::Environment(environment)
+    }
+}
+
+/// Coefficients A to E of the Perez sky luminance distribution.
+type Perez = [Float; 5];
+
+/// Angular radius of the sun in radians.
+const SUN_ANGULAR_RADIUS: Float = 0.0047;
+
+/// Daylight sky following the analytic model by Preetham, Shirley, and Smits (1999).
+///
+/// The sky itself does not contain the disk of the sun. Instead, the sun is added to the scene as a [`DirectionalLight`] (see [`sun`](Sky::sun)), so it is sampled directly and does not cause fireflies.
+/// Its color is attenuated by the atmosphere, so it turns red close to the horizon.
+/// The model is only valid for the sun above the horizon.
+///
+/// # Fields
+/// - `sun_direction`: Unit vector pointing towards the sun.
+/// - `turbidity`: Haziness of the atmosphere, from 2 (clear) to about 10 (hazy).
+/// - `intensity`: Factor the luminance of the sky (in kcd/m²) is multiplied with.
+/// - `sun_strength`: Radiance of the sun outside of the atmosphere.
+/// - `perez`: Perez coefficients for the luminance Y and the chromaticities x and y.
+/// - `zenith`: Luminance Y and chromaticities x and y at the zenith.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, background::Sky};
+/// let sky = Sky::new(vector![1., 1., 0.], 3.);
+/// let zenith = sky.color(vector![0., 1., 0.]);
+/// assert!(zenith.b() > zenith.r());
+/// assert!(sky.sun().is_some());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Sky {
+    sun_direction: Vector3<Float>,
+    turbidity: Float,
+    intensity: Float,
+    sun_strength: Float,
+    perez: [Perez; 3],
+    zenith: [Float; 3],
+}
+
+impl Sky {
+    pub fn new(sun_direction: Vector3<Float>, turbidity: Float) -> Self {
+        let sun_direction = sun_direction.normalize();
+        let turbidity = turbidity.max(1.);
+        let t = turbidity;
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let theta = Self::sun_zenith_angle(&sun_direction);
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * theta);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let chromaticity = |matrix: [[Float; 4]; 3]| {
+            let angles = [theta * theta * theta, theta * theta, theta, 1.];
+            let turbidities = [t * t, t, 1.];
+            turbidities
+                .iter()
+                .zip(matrix)
+                .map(|(turbidity, row)| {
+                    turbidity * row.iter().zip(angles).map(|(m, a)| m * a).sum::<Float>()
+                })
+                .sum::<Float>()
+        };
+        let x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        Self {
+            sun_direction,
+            turbidity,
+            intensity: 0.1,
+            sun_strength: 3.,
+            perez,
+            zenith: [luminance, x, y],
+        }
+    }
+
+    /// Consume `self` and set the factor the luminance of the sky (in kcd/m²) is multiplied with.
+    ///
+    /// The default of 0.1 gives a zenith luminance of about one at noon.
+    pub fn with_intensity(mut self, intensity: Float) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Consume `self` and set the radiance of the sun outside of the atmosphere.
+    pub fn with_sun_strength(mut self, sun_strength: Float) -> Self {
+        self.sun_strength = sun_strength;
+        self
+    }
+
+    pub fn sun_direction(&self) -> Vector3<Float> {
+        self.sun_direction
+    }
+
+    pub fn turbidity(&self) -> Float {
+        self.turbidity
+    }
+
+    /// Angle between the sun and the zenith, clamped to the horizon.
+    fn sun_zenith_angle(sun_direction: &Vector3<Float>) -> Float {
+        sun_direction.y.clamp(0.01, 1.).acos()
+    }
+
+    /// Perez distribution for a direction with cosine `cos_theta` to the zenith and angle `gamma` to the sun.
+    fn perez(coefficients: &Perez, cos_theta: Float, gamma: Float) -> Float {
+        let [a, b, c, d, e] = *coefficients;
+        let cos_gamma = gamma.cos();
+        (1. + a * (b / cos_theta).exp()) * (1. + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+    }
+
+    /// Color of the sky (without the sun) seen in `direction`.
+    ///
+    /// Directions below the horizon get the color of the horizon.
+    pub fn color(&self, direction: Vector3<Float>) -> Color {
+        let direction = direction.normalize();
+        let cos_theta = direction.y.max(0.01);
+        let gamma = direction.dot(&self.sun_direction).clamp(-1., 1.).acos();
+        let theta_sun = Self::sun_zenith_angle(&self.sun_direction);
+
+        let [luminance, x, y] = std::array::from_fn(|i| {
+            self.zenith[i] * Self::perez(&self.perez[i], cos_theta, gamma)
+                / Self::perez(&self.perez[i], 1., theta_sun)
+        });
+
+        xyy_to_rgb(self.intensity * luminance.max(0.), x, y)
+    }
+
+    /// The sun as a [`DirectionalLight`], attenuated by the atmosphere.
+    ///
+    /// Returns [`None`] if the sun is below the horizon.
+    pub fn sun(&self) -> Option<DirectionalLight> {
+        if self.sun_direction.y <= 0. {
+            return None;
+        }
+
+        Some(
+            DirectionalLight::new(
+                -self.sun_direction,
+                self.sun_transmittance() * self.sun_strength,
+            )
+            .with_angular_radius(SUN_ANGULAR_RADIUS),
+        )
+    }
+
+    /// Fraction of the sunlight that passes through the atmosphere due to Rayleigh and aerosol scattering.
+    fn sun_transmittance(&self) -> Color {
+        let theta = self.sun_direction.y.clamp(0., 1.).acos();
+        // Relative optical air mass (Kasten and Young, 1989).
+        let air_mass = 1. / (theta.cos() + 0.50572 * (96.07995 - theta.to_degrees()).powf(-1.6364));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+
+        // Wavelengths of red, green, and blue in micrometers.
+        let wavelengths: [Float; 3] = [0.68, 0.55, 0.44];
+        wavelengths
+            .into_iter()
+            .map(|lambda| {
+                let rayleigh = 0.008735 * lambda.powf(-4.08);
+                let aerosol = beta * lambda.powf(-1.3);
+                (-(rayleigh + aerosol) * air_mass).exp()
+            })
+            .collect()
+    }
+}
+
+/// An HDR image of the surroundings in the equirectangular (latitude-longitude) projection.
+///
+/// The top row of the image is straight up (+y) and the bottom row straight down. From left to right, the image goes once around the horizon, starting at -x and passing -z, +x, and +z.
+///
+/// Small bright features like the sun in a photograph light most of the scene, but are rarely hit by scattered [`Ray`](crate::ray::Ray)s.
+/// Therefore, the path tracer samples directions with a probability proportional to the luminance of the texels and combines this with following the scattered [`Ray`](crate::ray::Ray)s via multiple importance sampling.
+///
+/// # Fields
+/// - `width`, `height`: Size of the image.
+/// - `texels`: Colors of the image, row by row from the top.
+/// - `intensity`: Factor the colors are multiplied with.
+/// - `black`: Whether all texels are black.
+/// - `row_cdf`: Cumulative distribution of the rows for sampling.
+/// - `column_cdfs`: Cumulative distribution of the columns within each row, `width + 1` values per row.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, background::EnvironmentMap};
+/// // Black, except for one bright texel at the top
+/// let mut texels = vec![color![0., 0., 0.]; 8 * 4];
+/// texels[2] = color![100., 100., 100.];
+/// let environment = EnvironmentMap::new(8, 4, texels);
+///
+/// let (direction, pdf) = environment.sample();
+/// assert!(direction.y > 0.);
+/// assert_eq!(environment.color(direction), color![100., 100., 100.]);
+/// assert!((environment.pdf(direction) - pdf).abs() < 1e-3 * pdf);
+/// assert_eq!(environment.pdf(vector![0., -1., 0.]), 0.);
+/// ```
+#[derive(Clone, Debug)]
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    texels: Arc<[Color]>,
+    intensity: Float,
+    black: bool,
+    row_cdf: Arc<[Float]>,
+    column_cdfs: Arc<[Float]>,
+}
+
+impl EnvironmentMap {
+    /// Create an [`EnvironmentMap`] from the colors of the image of `width` × `height` texels, row by row from the top.
+    ///
+    /// # Panics
+    /// If the image is empty or `texels` does not have `width * height` values.
+    pub fn new(width: u32, height: u32, texels: Vec<Color>) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        assert!(width > 0 && height > 0, "the environment map is empty");
+        assert_eq!(
+            texels.len(),
+            width * height,
+            "the environment map needs width * height texels"
+        );
+
+        // Texels near the poles cover a smaller solid angle.
+        let weights: Vec<Float> = texels
+            .iter()
+            .enumerate()
+            .map(|(index, &color)| {
+                let theta = PI * ((index / width) as Float + 0.5) / height as Float;
+                color.luminance().max(0.) * theta.sin()
+            })
+            .collect();
+        let mut column_cdfs = Vec::with_capacity(height * (width + 1));
+        let mut row_weights = Vec::with_capacity(height);
+        for row in weights.chunks_exact(width) {
+            row_weights.push(row.iter().sum());
+            column_cdfs.extend(cumulative_distribution(row));
+        }
+
+        Self {
+            width,
+            height,
+            black: texels
+                .iter()
+                .all(|color| color.into_iter().all(|c| c <= 0.)),
+            texels: texels.into(),
+            intensity: 1.,
+            row_cdf: cumulative_distribution(&row_weights).collect(),
+            column_cdfs: column_cdfs.into(),
+        }
+    }
+
+    /// Create an [`EnvironmentMap`] from an image with linear colors.
+    pub fn from_image(image: &Rgb32FImage) -> Self {
+        let texels = image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b] = pixel.0;
+                #[allow(clippy::unnecessary_cast)]
+                Color::new(r as Float, g as Float, b as Float)
+            })
+            .collect();
+        Self::new(image.width(), image.height(), texels)
+    }
+
+    /// Open an image (e.g. Radiance HDR or OpenEXR) and create an [`EnvironmentMap`] via [`from_image`](EnvironmentMap::from_image).
+    ///
+    /// Images with 8 bits per channel are used as they are, without undoing their gamma.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ImageError> {
+        let image = ImageReader::open(path)?.decode()?.into_rgb32f();
+        Ok(Self::from_image(&image))
+    }
+
+    /// Consume `self` and set the factor the colors are multiplied with.
+    pub fn with_intensity(mut self, intensity: Float) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width as u32, self.height as u32)
+    }
+
+    pub fn intensity(&self) -> Float {
+        self.intensity
+    }
+
+    /// Whether the environment is black in every direction.
+    pub fn is_black(&self) -> bool {
+        self.intensity <= 0. || self.black
+    }
+
+    /// Texture coordinates (u, v) of `direction` in \[0, 1\], with v from the top.
+    fn uv(direction: Vector3<Float>) -> (Float, Float) {
+        let direction = direction.normalize();
+        let u = (direction.z.atan2(direction.x) + PI) / (2. * PI);
+        let v = direction.y.clamp(-1., 1.).acos() / PI;
+        (u, v)
+    }
+
+    /// Column and row of the texel at the texture coordinates (`u`, `v`).
+    fn texel(&self, (u, v): (Float, Float)) -> (usize, usize) {
+        let column = ((u * self.width as Float) as usize).min(self.width - 1);
+        let row = ((v * self.height as Float) as usize).min(self.height - 1);
+        (column, row)
+    }
+
+    /// Color seen in `direction`.
+    pub fn color(&self, direction: Vector3<Float>) -> Color {
+        let (column, row) = self.texel(Self::uv(direction));
+        self.texels[row * self.width + column] * self.intensity
+    }
+
+    /// Sample a unit direction with a probability roughly proportional to the luminance seen in it.
+    ///
+    /// Returns the direction and its probability density with respect to the solid angle.
+    pub fn sample(&self) -> (Vector3<Float>, Float) {
+        let mut rng = crate::random::rng();
+        let row = sample_cumulative_distribution(&self.row_cdf, rng.gen());
+        let column_cdf = &self.column_cdfs[row * (self.width + 1)..(row + 1) * (self.width + 1)];
+        let column = sample_cumulative_distribution(column_cdf, rng.gen());
+
+        let u = (column as Float + rng.gen::<Float>()) / self.width as Float;
+        let v = (row as Float + rng.gen::<Float>()) / self.height as Float;
+        let (phi, theta) = (2. * PI * u - PI, PI * v);
+        let direction = vector![
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin()
+        ];
+        (direction, self.pdf(direction))
+    }
+
+    /// Probability density of [`sample`](EnvironmentMap::sample) returning `direction`, with respect to the solid angle.
+    pub fn pdf(&self, direction: Vector3<Float>) -> Float {
+        let (u, v) = Self::uv(direction);
+        let (column, row) = self.texel((u, v));
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0. {
+            return 0.;
+        }
+        let column_cdf = &self.column_cdfs[row * (self.width + 1)..];
+        let probability = (self.row_cdf[row + 1] - self.row_cdf[row])
+            * (column_cdf[column + 1] - column_cdf[column]);
+        // The texel covers 1 / (width * height) of the image, which maps to 2π² sin θ of solid angle per unit area.
+        probability * (self.width * self.height) as Float / (2. * PI * PI * sin_theta)
+    }
+}
+
+/// Normalized cumulative sums of `weights`, starting with zero. Without any weight, the distribution is uniform.
+fn cumulative_distribution(weights: &[Float]) -> impl Iterator<Item = Float> + '_ {
+    let total: Float = weights.iter().sum();
+    let uniform = total <= 0.;
+    std::iter::once(0.).chain(
+        weights
+            .iter()
+            .enumerate()
+            .scan(0., move |sum, (index, &weight)| {
+                *sum += weight;
+                Some(match uniform {
+                    true => (index + 1) as Float / weights.len() as Float,
+                    false => *sum / total,
+                })
+            }),
+    )
+}
+
+/// Index of the interval of the cumulative distribution `cdf` that contains `xi` in \[0, 1).
+///
+/// Intervals without probability are never chosen.
+fn sample_cumulative_distribution(cdf: &[Float], xi: Float) -> usize {
+    let intervals = cdf.len() - 1;
+    cdf[1..]
+        .partition_point(|&value| value <= xi)
+        .min(intervals - 1)
+}
+
+/// Convert luminance Y and chromaticity (x, y) to linear sRGB.
+fn xyy_to_rgb(luminance: Float, x: Float, y: Float) -> Color {
+    if y <= 0. {
+        return color![0., 0., 0.];
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1. - x - y) / y * luminance;
+
+    color![
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sky_is_blue_and_sunset_is_red() {
+        let noon = Sky::new(vector![0., 1., 0.], 2.5);
+        let zenith = noon.color(vector![0., 1., 0.]);
+        assert!(zenith.b() > zenith.r());
+        assert!(zenith.g() > 0.1 && zenith.g() < 10.);
+
+        let sunset = Sky::new(vector![1., 0.05, 0.], 2.5);
+        let sun = sunset.sun_transmittance();
+        assert!(sun.r() > 2. * sun.b());
+        assert!(sunset.sun().is_some());
+
+        let night = Sky::new(vector![1., -0.5, 0.], 2.5);
+        assert!(night.sun().is_none());
+    }
+
+    #[test]
+    fn gradient() {
+        let background = Background::Gradient {
+            bottom: color![1., 1., 1.],
+            top: color![0.5, 0.7, 1.],
+        };
+        assert_eq!(background.color(vector![0., 2., 0.]), color![0.5, 0.7, 1.]);
+        assert_eq!(background.color(vector![0., -1., 0.]), color![1., 1., 1.]);
+    }
+
+    #[test]
+    fn environment_pdf_is_normalized() {
+        let texels = (0..16 * 8)
+            .map(|index| Color::new(index as Float, 1., (index % 3) as Float))
+            .collect();
+        let environment = EnvironmentMap::new(16, 8, texels);
+
+        // The integral of the density over the sphere is one.
+        let samples = 100_000;
+        let integral = (0..samples)
+            .map(|_| environment.pdf(crate::vec3::random_unit_vector()))
+            .sum::<Float>()
+            * 4.
+            * PI
+            / samples as Float;
+        assert!((integral - 1.).abs() < 0.02, "{integral}");
+
+        // Without any light, directions are sampled uniformly over the image.
+        let black = EnvironmentMap::new(4, 2, vec![color![0., 0., 0.]; 8]);
+        assert!(black.is_black());
+        let (direction, pdf) = black.sample();
+        assert!((direction.norm() - 1.).abs() < 1e-4 && pdf > 0.);
+    }
+}
@@ -0,0 +1,378 @@
+//! Baking the lighting of a surface into a texture in its UV layout (a lightmap), e.g. to precompute the lighting of a game level.
+//!
+//! [`Raytracer::bake`] finds the points of a [`UvSurface`] that lie in each texel and traces paths from there into the hemisphere above the surface.
+//! The resulting lightmap has the cosine-weighted average of the incoming light, i.e. the light a white diffuse surface would reflect, so the game multiplies it with the albedo.
+//! The surface only defines where the texels are; it should also be part of the world, so it shadows itself.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use rand::Rng;
+
+use crate::color::BLACK;
+use crate::integrator::{path_trace, IntegratorSettings, Scene};
+use crate::mesh::read_obj;
+use crate::platform::*;
+use crate::progress::RenderProgress;
+use crate::ray::{Ray, RayKind};
+use crate::vec3::{near_zero, random_unit_vector_in_unit_sphere};
+use crate::*;
+
+/// Distance along the normal by which the paths start above the surface.
+const SURFACE_OFFSET: Float = 1e-4;
+
+/// A triangle with texture coordinates (u, v) at its corners.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct UvTriangle {
+    positions: [Vector3<Float>; 3],
+    normals: [Vector3<Float>; 3],
+    uvs: [(Float, Float); 3],
+}
+
+impl UvTriangle {
+    /// Barycentric coordinates of the point with the texture coordinates `uv`, if it lies inside the triangle.
+    fn barycentric(&self, (u, v): (Float, Float)) -> Option<[Float; 3]> {
+        let [(ua, va), (ub, vb), (uc, vc)] = self.uvs;
+        let determinant = (vb - vc) * (ua - uc) + (uc - ub) * (va - vc);
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let a = ((vb - vc) * (u - uc) + (uc - ub) * (v - vc)) / determinant;
+        let b = ((vc - va) * (u - uc) + (ua - uc) * (v - vc)) / determinant;
+        let weights = [a, b, 1. - a - b];
+        weights
+            .iter()
+            .all(|&weight| weight >= 0.)
+            .then_some(weights)
+    }
+
+    /// Point and unit normal at the barycentric coordinates `weights`.
+    fn point(&self, weights: [Float; 3]) -> (Vector3<Float>, Vector3<Float>) {
+        let interpolate = |values: [Vector3<Float>; 3]| {
+            weights[0] * values[0] + weights[1] * values[1] + weights[2] * values[2]
+        };
+        let normal = interpolate(self.normals).normalize();
+        (interpolate(self.positions), normal)
+    }
+}
+
+/// A surface with a UV layout to bake a lightmap for.
+///
+/// # Fields
+/// - `triangles`: The triangles of the surface with their texture coordinates, which should not overlap.
+/// - `padding`: Number of texels by which the baked texels are extended into the empty texels around them, so that filtering does not blend in the empty texels at the seams (2 by default).
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, baking::UvSurface};
+/// // A unit square facing up, covering the whole texture
+/// let positions = [vector![0., 0., 0.], vector![1., 0., 0.], vector![1., 0., -1.], vector![0., 0., -1.]];
+/// let uvs = [(0., 0.), (1., 0.), (1., 1.), (0., 1.)];
+/// let square = UvSurface::new(&positions, &[], &uvs, &[[0, 1, 2], [0, 2, 3]]);
+///
+/// // Under a uniform sky, every texel receives the color of the sky.
+/// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 8, 8, 4, 4);
+/// let lightmap = raytracer.bake(&square).unwrap();
+/// assert!(lightmap.colors().iter().all(|&color| color == color![0.5, 0.7, 1.]));
+/// assert!(lightmap.alpha().unwrap().iter().all(|&alpha| alpha == 1.));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct UvSurface {
+    triangles: Vec<UvTriangle>,
+    padding: u32,
+}
+
+impl UvSurface {
+    /// Create a surface from vertices with positions, normals, and texture coordinates, and three indices of vertices per triangle.
+    ///
+    /// If `normals` is empty, each triangle uses its own normal, which points to the side from which its corners appear counterclockwise.
+    ///
+    /// # Panics
+    /// If an index is out of bounds or `normals` is neither empty nor as long as `positions`.
+    pub fn new(
+        positions: &[Vector3<Float>],
+        normals: &[Vector3<Float>],
+        uvs: &[(Float, Float)],
+        faces: &[[usize; 3]],
+    ) -> Self {
+        assert!(
+            normals.is_empty() || normals.len() == positions.len(),
+            "there are {} normals for {} positions",
+            normals.len(),
+            positions.len()
+        );
+        let triangles = faces
+            .iter()
+            .map(|&face| {
+                let positions = face.map(|index| positions[index]);
+                UvTriangle {
+                    positions,
+                    normals: if normals.is_empty() {
+                        [face_normal(&positions); 3]
+                    } else {
+                        face.map(|index| normals[index])
+                    },
+                    uvs: face.map(|index| uvs[index]),
+                }
+            })
+            .collect();
+        Self {
+            triangles,
+            padding: 2,
+        }
+    }
+
+    /// Read a surface from a Wavefront OBJ file in which every face has texture coordinates (`vt`).
+    ///
+    /// Polygons are split into a fan of triangles. Faces without normals use their own normal.
+    pub fn from_obj<R: BufRead>(center: Vector3<Float>, reader: R) -> io::Result<Self> {
+        let obj = read_obj(reader)?;
+        let mut triangles = Vec::new();
+        for (polygon, texture_polygon) in obj.polygons.iter().zip(&obj.texture_polygons) {
+            let Some(uvs) = texture_polygon
+                .iter()
+                .map(|&index| Some(obj.texture_coordinates[index?]))
+                .collect::<Option<Vec<_>>>()
+            else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "face without texture coordinates",
+                ));
+            };
+            for i in 1..polygon.len() - 1 {
+                let corners = [0, i, i + 1];
+                let positions = corners.map(|corner| center + obj.vertices[polygon[corner].0]);
+                let normals = match corners.map(|corner| polygon[corner].1) {
+                    [Some(a), Some(b), Some(c)] => [a, b, c].map(|index| obj.normals[index]),
+                    _ => [face_normal(&positions); 3],
+                };
+                triangles.push(UvTriangle {
+                    positions,
+                    normals,
+                    uvs: corners.map(|corner| uvs[corner]),
+                });
+            }
+        }
+        Ok(Self {
+            triangles,
+            padding: 2,
+        })
+    }
+
+    /// Open a Wavefront OBJ file and create a [`UvSurface`] via [`from_obj`](UvSurface::from_obj).
+    pub fn open<P: AsRef<Path>>(center: Vector3<Float>, path: P) -> io::Result<Self> {
+        Self::from_obj(center, BufReader::new(File::open(path)?))
+    }
+
+    /// Consume `self` and set the number of texels by which the baked texels are extended.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    /// Indices of the triangles that may cover each texel of a lightmap of `size`, row by row from the top.
+    fn texel_triangles(&self, (width, height): (u32, u32)) -> Vec<Vec<u32>> {
+        let mut texels = vec![Vec::new(); width as usize * height as usize];
+        for (index, triangle) in self.triangles.iter().enumerate() {
+            let range = |coordinates: [Float; 3], size: u32| {
+                let texels = coordinates
+                    .map(|coordinate| (coordinate * size as Float).clamp(0., size as Float));
+                let min = texels.iter().copied().fold(Float::INFINITY, Float::min) as u32;
+                let max = texels.iter().copied().fold(0., Float::max).ceil() as u32;
+                let min = min.min(size - 1);
+                min..max.clamp(min + 1, size)
+            };
+            let columns = range(triangle.uvs.map(|(u, _)| u), width);
+            // Rows count from the top, but v from the bottom.
+            let rows = range(triangle.uvs.map(|(_, v)| 1. - v), height);
+            for row in rows {
+                for column in columns.clone() {
+                    texels[row as usize * width as usize + column as usize].push(index as u32);
+                }
+            }
+        }
+        texels
+    }
+}
+
+/// Unit normal of a triangle, to the side from which its corners appear counterclockwise.
+fn face_normal([a, b, c]: &[Vector3<Float>; 3]) -> Vector3<Float> {
+    (b - a)
+        .cross(&(c - a))
+        .try_normalize(0.)
+        .unwrap_or_else(Vector3::y)
+}
+
+/// Settings of a bake.
+///
+/// # Fields
+/// - `samples_per_pixel`: Paths traced per texel.
+/// - `max_depth`: How often a [`Ray`] bounces at most.
+/// - `settings`: [`IntegratorSettings`] for Russian roulette and clamping.
+pub(crate) struct Baker<'a> {
+    pub samples_per_pixel: u16,
+    pub max_depth: u16,
+    pub settings: &'a IntegratorSettings,
+}
+
+impl Baker<'_> {
+    /// Bake the light arriving at `surface` into a lightmap of `size` (width and height).
+    ///
+    /// Returns the colors of the texels, row by row from the top, and which fraction of each texel the surface covers.
+    pub fn bake(
+        &self,
+        scene: &Scene,
+        surface: &UvSurface,
+        (width, height): (u32, u32),
+        progress: Option<&dyn RenderProgress>,
+    ) -> (Vec<Color>, Vec<Float>) {
+        let texel_triangles = surface.texel_triangles((width, height));
+        if let Some(progress) = progress {
+            progress.start(texel_triangles.len() as u64);
+        }
+
+        let (mut colors, coverage): (Vec<_>, Vec<_>) = texel_triangles
+            .par_iter()
+            .enumerate()
+            .map(|(index, triangles)| {
+                let texel = self.bake_texel(scene, surface, triangles, index, (width, height));
+                if let Some(progress) = progress {
+                    progress.advance(1);
+                }
+                texel
+            })
+            .unzip();
+
+        dilate(&mut colors, &coverage, (width, height), surface.padding);
+        (colors, coverage)
+    }
+
+    /// Average light and coverage of the texel at `index`, which may be covered by `triangles`.
+    fn bake_texel(
+        &self,
+        scene: &Scene,
+        surface: &UvSurface,
+        triangles: &[u32],
+        index: usize,
+        (width, height): (u32, u32),
+    ) -> (Color, Float) {
+        if triangles.is_empty() {
+            return (BLACK, 0.);
+        }
+        let mut rng = crate::random::rng();
+        let column = (index % width as usize) as Float;
+        let row = (index / width as usize) as Float;
+
+        let mut radiance = BLACK;
+        let mut hits = 0;
+        for _ in 0..self.samples_per_pixel {
+            let uv = (
+                (column + rng.gen::<Float>()) / width as Float,
+                1. - (row + rng.gen::<Float>()) / height as Float,
+            );
+            let Some((triangle, weights)) = triangles.iter().find_map(|&triangle| {
+                let triangle = &surface.triangles[triangle as usize];
+                Some((triangle, triangle.barycentric(uv)?))
+            }) else {
+                continue;
+            };
+            let (point, normal) = triangle.point(weights);
+
+            let mut direction = normal + random_unit_vector_in_unit_sphere();
+            if near_zero(&direction) {
+                direction = normal;
+            }
+            let ray = Ray::new(point + SURFACE_OFFSET * normal, direction.normalize())
+                .with_kind(RayKind::Indirect);
+            let sample = path_trace(scene, ray, self.max_depth, self.settings);
+            radiance += self.settings.clamp(sample);
+            hits += 1;
+        }
+
+        match hits {
+            0 => (BLACK, 0.),
+            _ => (
+                radiance / hits as Float,
+                hits as Float / self.samples_per_pixel as Float,
+            ),
+        }
+    }
+}
+
+/// Fill the empty texels next to covered ones with the average of their neighbors, `padding` times.
+fn dilate(colors: &mut [Color], coverage: &[Float], (width, height): (u32, u32), padding: u32) {
+    let mut filled: Vec<bool> = coverage.iter().map(|&coverage| coverage > 0.).collect();
+    for _ in 0..padding {
+        let mut next = filled.clone();
+        for index in 0..colors.len() {
+            if filled[index] {
+                continue;
+            }
+            let (column, row) = (index % width as usize, index / width as usize);
+            let mut sum = BLACK;
+            let mut count = 0;
+            for neighbor_row in row.saturating_sub(1)..(row + 2).min(height as usize) {
+                for neighbor_column in column.saturating_sub(1)..(column + 2).min(width as usize) {
+                    let neighbor = neighbor_row * width as usize + neighbor_column;
+                    if filled[neighbor] {
+                        sum += colors[neighbor];
+                        count += 1;
+                    }
+                }
+            }
+            if count > 0 {
+                colors[index] = sum / count as Float;
+                next[index] = true;
+            }
+        }
+        filled = next;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::WHITE;
+
+    #[test]
+    fn texel_coverage() {
+        // A triangle covering the lower left half of the texture, with flat normals from the OBJ file
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nf 1/1 2/2 3/3\n";
+        let surface = UvSurface::from_obj(vector![0., 0., 0.], obj.as_bytes())
+            .unwrap()
+            .with_padding(1);
+        assert_eq!(surface.triangle_count(), 1);
+        let triangle = surface.triangles[0];
+        assert_eq!(triangle.normals[0], vector![0., 0., 1.]);
+        let (point, _) = triangle.point(triangle.barycentric((0.25, 0.5)).unwrap());
+        assert!((point - vector![0.25, 0.5, 0.]).norm() < 1e-5);
+        assert!(triangle.barycentric((0.75, 0.75)).is_none());
+
+        // Faces without texture coordinates cannot be baked.
+        assert!(UvSurface::from_obj(
+            vector![0., 0., 0.],
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n".as_bytes()
+        )
+        .is_err());
+
+        // The bounding box of the triangle covers the whole texture.
+        let texels = surface.texel_triangles((4, 4));
+        assert!(texels.iter().all(|triangles| triangles == &[0]));
+        // The padding fills the empty top right texel.
+        let mut colors = vec![WHITE; 16];
+        colors[3] = BLACK;
+        let mut coverage = vec![1.; 16];
+        coverage[3] = 0.;
+        dilate(&mut colors, &coverage, (4, 4), 1);
+        assert_eq!(colors[3], WHITE);
+    }
+}
@@ -0,0 +1,364 @@
+//! Parametric Bézier surfaces and curves.
+//!
+//! [`BezierSurface`]s are made of bicubic patches (e.g. the Utah teapot) that are tessellated into [`Triangle`]s at construction.
+//! [`BezierCurve`]s are thin cubic ribbons that are intersected directly, e.g. for hair-like geometry.
+
+use std::sync::Arc;
+
+use nalgebra::Rotation3;
+
+use crate::error::Error;
+use crate::hitrecord::HitRecord;
+use crate::hittable::{Aabb, Bvh};
+use crate::materials::Material;
+use crate::ray::Ray;
+use crate::shapes::{Movable, Offset, Triangle};
+use crate::*;
+
+/// Control points of a bicubic Bézier patch, stored row by row.
+pub type Patch = [Vector3<Float>; 16];
+
+/// Evaluate the cubic Bernstein polynomials at `t`.
+fn bernstein(t: Float) -> [Float; 4] {
+    let s = 1. - t;
+    [s * s * s, 3. * s * s * t, 3. * s * t * t, t * t * t]
+}
+
+/// Evaluate a cubic Bézier curve at `t`.
+fn cubic(points: &[Vector3<Float>; 4], t: Float) -> Vector3<Float> {
+    bernstein(t).iter().zip(points).map(|(b, p)| *b * *p).sum()
+}
+
+/// Evaluate a bicubic Bézier patch at (u, v).
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, bezier::*};
+/// let patch: Patch = std::array::from_fn(|i| vector![(i % 4) as Float, 0., (i / 4) as Float]);
+/// assert!((evaluate_patch(&patch, 0.5, 0.5) - vector![1.5, 0., 1.5]).norm() < 1e-5);
+/// ```
+pub fn evaluate_patch(patch: &Patch, u: Float, v: Float) -> Vector3<Float> {
+    let bu = bernstein(u);
+    let bv = bernstein(v);
+    let mut point = Vector3::zeros();
+    for (j, bv) in bv.iter().enumerate() {
+        for (i, bu) in bu.iter().enumerate() {
+            point += *bu * *bv * patch[4 * j + i];
+        }
+    }
+    point
+}
+
+/// Number of subdivisions per parameter direction so that the tessellation of `patch` deviates at most `tolerance` from it.
+///
+/// This uses the bound `h^2 / 8 * max|P''|` of linear interpolation with step `h`, where the second derivative of a cubic is bounded by six times the largest second difference of its control points.
+pub fn subdivisions_for_tolerance(patch: &Patch, tolerance: Float) -> usize {
+    let mut max_second_difference: Float = 0.;
+    for k in 0..4 {
+        for l in 0..2 {
+            let row = patch[4 * k + l] - 2. * patch[4 * k + l + 1] + patch[4 * k + l + 2];
+            let column = patch[4 * l + k] - 2. * patch[4 * (l + 1) + k] + patch[4 * (l + 2) + k];
+            max_second_difference = max_second_difference.max(row.norm()).max(column.norm());
+        }
+    }
+
+    ((6. * max_second_difference / (8. * tolerance.max(1e-6)))
+        .sqrt()
+        .ceil() as usize)
+        .clamp(1, 256)
+}
+
+/// A surface made of bicubic Bézier patches.
+///
+/// All patches are tessellated into [`Triangle`]s and sorted into a [`Bvh`], so the surface can be used like any other [`Hittable`].
+///
+/// # Fields
+/// - `center`: Its [`Offset`].
+/// - `triangles`: [`Bvh`] of the tessellated patches.
+#[derive(Clone, Debug)]
+pub struct BezierSurface {
+    center: Offset,
+    triangles: Bvh,
+}
+
+impl BezierSurface {
+    /// Create a [`BezierSurface`] with a fixed number of subdivisions per patch and parameter direction (at least one).
+    ///
+    /// Returns [`Error::Config`] if the patches give no triangles, i.e. if there are none or all are degenerate.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, bezier::*, materials::Lambertian};
+    /// let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+    /// assert!(BezierSurface::new(&[], 4, material.clone()).is_err());
+    /// let patch: Patch = std::array::from_fn(|i| vector![(i % 4) as Float, 0., (i / 4) as Float]);
+    /// assert!(BezierSurface::new(&[patch], 0, material).is_ok());
+    /// ```
+    pub fn new<M: Material + 'static>(
+        patches: &[Patch],
+        subdivisions: usize,
+        material: M,
+    ) -> Result<Self, Error> {
+        Self::tessellate(patches, |_| subdivisions.max(1), material)
+    }
+
+    /// Create a [`BezierSurface`] that chooses the subdivisions of each patch adaptively so that they deviate at most `tolerance` from the exact surface.
+    ///
+    /// Returns [`Error::Config`] if the patches give no triangles, like [`new`](BezierSurface::new).
+    pub fn adaptive<M: Material + 'static>(
+        patches: &[Patch],
+        tolerance: Float,
+        material: M,
+    ) -> Result<Self, Error> {
+        Self::tessellate(
+            patches,
+            |patch| subdivisions_for_tolerance(patch, tolerance),
+            material,
+        )
+    }
+
+    fn tessellate<M: Material + 'static>(
+        patches: &[Patch],
+        subdivisions: impl Fn(&Patch) -> usize,
+        material: M,
+    ) -> Result<Self, Error> {
+        let material = Arc::new(material);
+        let mut triangles = HittableList::default();
+
+        for patch in patches {
+            let n = subdivisions(patch);
+            let point = |i: usize, j: usize| {
+                evaluate_patch(patch, i as Float / n as Float, j as Float / n as Float)
+            };
+
+            for j in 0..n {
+                for i in 0..n {
+                    let (a, b, c, d) = (
+                        point(i, j),
+                        point(i + 1, j),
+                        point(i, j + 1),
+                        point(i + 1, j + 1),
+                    );
+                    // Degenerate triangles appear at collapsed patch edges (e.g. the top of the teapot lid).
+                    for (p, q, r) in [(a, b, d), (a, d, c)] {
+                        if (q - p).cross(&(r - p)).norm_squared() > 1e-12 {
+                            triangles.push(Triangle::new(p, q, r, material.clone()));
+                        }
+                    }
+                }
+            }
+        }
+
+        if triangles.is_empty() {
+            return Err(Error::Config(
+                "Bézier surface without triangles".to_string(),
+            ));
+        }
+        Ok(Self {
+            center: Offset::default(),
+            triangles: Bvh::new(triangles, 0., 0.)?,
+        })
+    }
+}
+
+impl Hittable for BezierSurface {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        self.triangles.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.triangles.bounding_box(time0, time1)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+}
+
+impl Movable for BezierSurface {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+/// A thin cubic Bézier curve with a round cross-section of varying width.
+///
+/// The curve is approximated by `segments` straight pieces. A [`Ray`] hits the curve if it passes closer than half the width to one of them.
+/// The normal is chosen like on a tube around the curve, so curves shade like thin cylinders.
+/// The surface coordinate u runs along the curve, v across it, and the tangent of a hit points along the curve (e.g. for the [`Hair`](crate::materials::Hair) material).
+///
+/// # Fields
+/// - `center`: Its [`Offset`].
+/// - `control_points`: Its four control points.
+/// - `width`: Width at the start and the end. It is interpolated linearly in between.
+/// - `segments`: Number of straight pieces.
+/// - `material`: Its material.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, bezier::BezierCurve, materials::Lambertian, ray::Ray};
+/// let strand = BezierCurve::new(
+///     [vector![0., 0., 0.], vector![0., 1., 0.], vector![0., 2., 0.], vector![0., 3., 0.]],
+///     (0.2, 0.1),
+///     Lambertian::solid_color(color![0.3, 0.2, 0.1]),
+/// );
+///
+/// let ray = Ray::new(vector![0., 1.5, 5.], vector![0., 0., -1.]);
+/// assert!(strand.hit(ray, 0., Float::INFINITY).is_some());
+/// let ray = Ray::new(vector![0.2, 1.5, 5.], vector![0., 0., -1.]);
+/// assert!(strand.hit(ray, 0., Float::INFINITY).is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct BezierCurve<M: Material> {
+    center: Offset,
+    control_points: [Vector3<Float>; 4],
+    width: (Float, Float),
+    segments: u16,
+    material: M,
+}
+
+impl<M: Material> BezierCurve<M> {
+    pub fn new(control_points: [Vector3<Float>; 4], width: (Float, Float), material: M) -> Self {
+        Self {
+            center: Offset::default(),
+            control_points,
+            width,
+            segments: 16,
+            material,
+        }
+    }
+
+    /// Consume `self` and set the number of straight pieces the curve is approximated by.
+    pub fn with_segments(mut self, segments: u16) -> Self {
+        self.segments = segments.max(1);
+        self
+    }
+
+    pub fn control_points(&self) -> &[Vector3<Float>; 4] {
+        &self.control_points
+    }
+
+    pub fn material(&self) -> &M {
+        &self.material
+    }
+
+    /// Evaluate the curve at `t` in \[0,1\].
+    pub fn point_at(&self, t: Float) -> Vector3<Float> {
+        cubic(&self.control_points, t)
+    }
+
+    /// Width of the curve at `t` in \[0,1\].
+    pub fn width_at(&self, t: Float) -> Float {
+        self.width.0 + t * (self.width.1 - self.width.0)
+    }
+}
+
+impl<M: Material + Clone + 'static> Hittable for BezierCurve<M> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let direction = ray.direction();
+        let a = direction.norm_squared();
+
+        let mut closest: Option<(Float, Float, Vector3<Float>, Vector3<Float>)> = None;
+        let mut closest_tangent = Vector3::zeros();
+        let mut closest_t = t_max;
+
+        let segments = self.segments as usize;
+        for i in 0..segments {
+            let s0 = i as Float / segments as Float;
+            let s1 = (i + 1) as Float / segments as Float;
+            let p0 = self.point_at(s0);
+            let p1 = self.point_at(s1);
+
+            // Closest points between the ray and the segment.
+            let segment = p1 - p0;
+            let w = ray.origin() - p0;
+            let b = direction.dot(&segment);
+            let c = segment.norm_squared();
+            let d = direction.dot(&w);
+            let e = segment.dot(&w);
+            let denominator = a * c - b * b;
+
+            let mut s = if denominator.abs() > 1e-12 {
+                ((a * e - b * d) / denominator).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let t = (b * s - d) / a;
+            if c > 0. {
+                s = ((ray.at(t) - p0).dot(&segment) / c).clamp(0., 1.);
+            }
+
+            let on_curve = p0 + s * segment;
+            let offset = ray.at(t) - on_curve;
+            let curve_parameter = s0 + s * (s1 - s0);
+            let radius = self.width_at(curve_parameter) / 2.;
+            if offset.norm_squared() > radius * radius {
+                continue;
+            }
+
+            // Move back to the surface of the tube around the curve.
+            let tangent = segment.normalize();
+            let perpendicular = direction - direction.dot(&tangent) * tangent;
+            let depth = (radius * radius - offset.norm_squared()).max(0.).sqrt();
+            let (towards_origin, t) = if perpendicular.norm_squared() > 1e-12 {
+                (-perpendicular.normalize(), t - depth / perpendicular.norm())
+            } else {
+                (-direction.normalize(), t)
+            };
+            let normal = offset + depth * towards_origin;
+
+            if t > t_min && t < closest_t {
+                closest_t = t;
+                let across = offset.dot(&tangent.cross(&towards_origin)) / radius;
+                closest = Some((curve_parameter, 0.5 + 0.5 * across, normal, ray.at(t)));
+                closest_tangent = tangent;
+            }
+        }
+
+        let (u, v, normal, point) = closest?;
+        let normal = if normal.norm_squared() > 0. {
+            normal.normalize()
+        } else {
+            -direction.normalize()
+        };
+
+        // The tangent along the curve is used by hair materials.
+        Some(
+            HitRecord::from_ray(point, u, v, normal, closest_t, &self.material, ray)
+                .with_tangent(closest_tangent),
+        )
+    }
+
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
+        // The curve lies inside the convex hull of its control points.
+        let [a, b, c, d] = self.control_points;
+        let radius = self.width.0.max(self.width.1).abs() / 2.;
+        let padding = vector![radius, radius, radius];
+        Some(Aabb::new(
+            a.inf(&b).inf(&c).inf(&d) - padding,
+            a.sup(&b).sup(&c).sup(&d) + padding,
+        ))
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+}
+
+impl<M: Material + Clone + 'static> Movable for BezierCurve<M> {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
@@ -1,6 +1,7 @@
 use std::path::Path;
 
 use ray_tracing_in_one_weekend::color::{GREEN, RED};
+use ray_tracing_in_one_weekend::error::Error;
 use ray_tracing_in_one_weekend::materials::*;
 use ray_tracing_in_one_weekend::shapes::*;
 use ray_tracing_in_one_weekend::*;
@@ -40,11 +41,11 @@ fn amogus(world: &mut HittableList) {
     world.push(inner_glass_sphere);
 }
 
-fn main() {
+fn main() -> Result<(), Error> {
     // Image
     let aspect_ratio = 16. / 9.;
-    let image_width: u16 = 800;
-    let image_height = (image_width as f32 / aspect_ratio) as u16;
+    let image_width: u32 = 800;
+    let image_height = (image_width as Float / aspect_ratio) as u32;
     let samples_per_pixel: u16 = 100;
     let max_depth = 10;
 
@@ -56,7 +57,7 @@ fn main() {
         lookfrom,
         lookat,
         vup,
-        std::f32::consts::FRAC_PI_4,
+        float::consts::FRAC_PI_4,
         aspect_ratio,
         0.,
         1.,
@@ -73,8 +74,6 @@ fn main() {
     .with_progressbar();
     amogus(&mut raytracer.world);
 
-    raytracer
-        .render()
-        .save(Path::new("images/amogus.png"))
-        .unwrap();
+    raytracer.render()?.save(Path::new("images/amogus.png"))?;
+    Ok(())
 }
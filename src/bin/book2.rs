@@ -4,6 +4,7 @@ use nalgebra::Rotation3;
 #[allow(unused_imports)]
 use rand::Rng;
 use ray_tracing_in_one_weekend::color::{BLACK, GREEN, RED, WHITE};
+use ray_tracing_in_one_weekend::error::Error;
 use ray_tracing_in_one_weekend::materials::*;
 use ray_tracing_in_one_weekend::shapes::*;
 use ray_tracing_in_one_weekend::textures::*;
@@ -12,9 +13,9 @@ use ray_tracing_in_one_weekend::*;
 
 #[allow(dead_code)]
 fn random_world(
-    aspect_ratio: f32,
-    image_width: u16,
-    image_height: u16,
+    aspect_ratio: Float,
+    image_width: u32,
+    image_height: u32,
     samples_per_pixel: u16,
     max_depth: u16,
 ) -> Raytracer {
@@ -28,7 +29,7 @@ fn random_world(
         lookfrom,
         lookat,
         vup,
-        std::f32::consts::FRAC_PI_6,
+        float::consts::FRAC_PI_6,
         aspect_ratio,
         0.1,
         10.,
@@ -52,11 +53,11 @@ fn random_world(
 
     for a in -11..11 {
         for b in -11..11 {
-            let choose_material: f32 = rng.gen();
+            let choose_material: Float = rng.gen();
             let center = vector![
-                a as f32 + 0.9 * rng.gen::<f32>(),
+                a as Float + 0.9 * rng.gen::<Float>(),
                 0.2,
-                b as f32 + 0.9 * rng.gen::<f32>()
+                b as Float + 0.9 * rng.gen::<Float>()
             ];
 
             if (center - vector![4., 0.2, 0.]).norm() > 0.9 {
@@ -66,7 +67,7 @@ fn random_world(
                     world.push(Sphere::new(center, 0.2, sphere_material));
                 } else if choose_material < 0.9 {
                     let albedo = Color::random_in_range(0.5, 1.);
-                    let fuzz = 0.5 * rng.gen::<f32>();
+                    let fuzz = 0.5 * rng.gen::<Float>();
                     let sphere_material = Metal::solid_color(albedo, fuzz);
                     world.push(Sphere::new(center, 0.2, sphere_material));
                 } else {
@@ -95,9 +96,9 @@ fn random_world(
 
 #[allow(dead_code)]
 fn checkerboard_world(
-    aspect_ratio: f32,
-    image_width: u16,
-    image_height: u16,
+    aspect_ratio: Float,
+    image_width: u32,
+    image_height: u32,
     samples_per_pixel: u16,
     max_depth: u16,
 ) -> Raytracer {
@@ -109,7 +110,7 @@ fn checkerboard_world(
         lookfrom,
         lookat,
         vup,
-        std::f32::consts::FRAC_PI_6,
+        float::consts::FRAC_PI_6,
         aspect_ratio,
         0.1,
         10.,
@@ -143,9 +144,9 @@ fn checkerboard_world(
 
 #[allow(dead_code)]
 fn perlin(
-    aspect_ratio: f32,
-    image_width: u16,
-    image_height: u16,
+    aspect_ratio: Float,
+    image_width: u32,
+    image_height: u32,
     samples_per_pixel: u16,
     max_depth: u16,
 ) -> Raytracer {
@@ -157,7 +158,7 @@ fn perlin(
         lookfrom,
         lookat,
         vup,
-        std::f32::consts::FRAC_PI_8,
+        float::consts::FRAC_PI_8,
         aspect_ratio,
         0.1,
         10.,
@@ -187,9 +188,9 @@ fn perlin(
 
 #[allow(dead_code)]
 fn image(
-    aspect_ratio: f32,
-    image_width: u16,
-    image_height: u16,
+    aspect_ratio: Float,
+    image_width: u32,
+    image_height: u32,
     samples_per_pixel: u16,
     max_depth: u16,
 ) -> Raytracer {
@@ -201,7 +202,7 @@ fn image(
         lookfrom,
         lookat,
         vup,
-        std::f32::consts::FRAC_PI_8,
+        float::consts::FRAC_PI_8,
         aspect_ratio,
         0.1,
         10.,
@@ -228,9 +229,9 @@ fn image(
 
 #[allow(dead_code)]
 fn light(
-    aspect_ratio: f32,
-    image_width: u16,
-    image_height: u16,
+    aspect_ratio: Float,
+    image_width: u32,
+    image_height: u32,
     samples_per_pixel: u16,
     max_depth: u16,
 ) -> Raytracer {
@@ -242,7 +243,7 @@ fn light(
         lookfrom,
         lookat,
         vup,
-        std::f32::consts::FRAC_PI_6,
+        float::consts::FRAC_PI_6,
         aspect_ratio,
         0.,
         1.,
@@ -290,9 +291,9 @@ fn light(
 
 #[allow(dead_code)]
 fn cornell(
-    aspect_ratio: f32,
-    image_width: u16,
-    image_height: u16,
+    aspect_ratio: Float,
+    image_width: u32,
+    image_height: u32,
     samples_per_pixel: u16,
     max_depth: u16,
 ) -> Raytracer {
@@ -304,7 +305,7 @@ fn cornell(
         lookfrom,
         lookat,
         vup,
-        std::f32::consts::FRAC_PI_2,
+        float::consts::FRAC_PI_2,
         aspect_ratio,
         0.,
         1.,
@@ -335,10 +336,10 @@ fn cornell(
     let light_rect = Rectangle::xz(vector![0., 200., 0.], 200., 200., light);
 
     let box1 = Cuboid::new(vector![30., -75., -50.], 100., 150., 100., white.clone())
-        .with_rotation(Rotation3::new((15f32).to_radians() * Vector3::y()));
+        .with_rotation(Rotation3::new(Float::to_radians(15.) * Vector3::y()));
     let dust_box1 = ConstantMedium::solid_color(box1, WHITE, 0.01);
     let box2 = Cuboid::new(vector![-20., -50., -100.], 120., 300., 120., white.clone())
-        .with_rotation(Rotation3::new((-18f32).to_radians() * Vector3::y()));
+        .with_rotation(Rotation3::new(Float::to_radians(-18.) * Vector3::y()));
     let dust_box2 = ConstantMedium::solid_color(box2, BLACK, 0.01);
 
     world.push(floor);
@@ -355,9 +356,9 @@ fn cornell(
 
 #[allow(dead_code)]
 fn final_scene(
-    aspect_ratio: f32,
-    image_width: u16,
-    image_height: u16,
+    aspect_ratio: Float,
+    image_width: u32,
+    image_height: u32,
     samples_per_pixel: u16,
     max_depth: u16,
 ) -> Raytracer {
@@ -369,7 +370,7 @@ fn final_scene(
         lookfrom,
         lookat,
         vup,
-        (40f32).to_radians(),
+        Float::to_radians(40.),
         aspect_ratio,
         0.,
         1.,
@@ -395,9 +396,9 @@ fn final_scene(
     for i in 0..boxes_per_side {
         for j in 0..boxes_per_side {
             let w = 100.;
-            let x0 = -1000. + i as f32 * w;
+            let x0 = -1000. + i as Float * w;
             let y0 = 0.;
-            let z0 = -1000. + j as f32 * w;
+            let z0 = -1000. + j as Float * w;
             let x1 = x0 + w / 2.;
             let y1 = rng.gen_range(1.0..101.) / 2.;
             let z1 = z0 + w / 2.;
@@ -465,7 +466,7 @@ fn final_scene(
             white.clone(),
         ));
     }
-    boxes2 = boxes2.with_rotation(Rotation3::new((15f32).to_radians() * Vector3::y()));
+    boxes2 = boxes2.with_rotation(Rotation3::new(Float::to_radians(15.) * Vector3::y()));
 
     world.push(boxes2);
 
@@ -483,11 +484,11 @@ enum Scene {
     Final,
 }
 
-fn main() {
+fn main() -> Result<(), Error> {
     // Image
     let aspect_ratio = 1.;
-    let image_width: u16 = 800;
-    let image_height = (image_width as f32 / aspect_ratio) as u16;
+    let image_width: u32 = 800;
+    let image_height = (image_width as Float / aspect_ratio) as u32;
     let samples_per_pixel: u16 = 100;
     let max_depth = 20;
 
@@ -567,5 +568,6 @@ fn main() {
         }
     };
 
-    raytracer.render().save(path).unwrap();
+    raytracer.render()?.save(path)?;
+    Ok(())
 }
@@ -1,8 +1,14 @@
 //! A camera that receives [`Ray`]s.
 
+use nalgebra::Matrix4;
 use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use crate::ray::Ray;
+use crate::hittable::Aabb;
+use crate::lens::LensSystem;
+use crate::ray::{Ray, RayDifferentials, RayKind};
+use crate::shapes::Offset;
 use crate::vec3::random_vector_in_unit_disk;
 use crate::*;
 
@@ -20,19 +26,76 @@ use crate::*;
 /// - `w`: Unit direction the camera is facing.
 /// - `lens_radius` Radius of the lense for the purpose of depth-of-field (half the aperture).
 /// - `time`: Optional exposure time.
+/// - `near_clip`: Distance in front of the lens at which camera [`Ray`]s start.
+/// - `rolling_shutter`: Optional [`RollingShutter`] that exposes the rows or columns one after another.
+/// - `lens`: Optional [`RealisticLens`] that replaces the thin lens.
+///
+/// With the `serde` feature, [`Camera`]s can be serialized, e.g. to store camera paths.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Camera {
-    origin: Vector3<f32>,
-    lower_left_corner: Vector3<f32>,
-    horizontal: Vector3<f32>,
-    vertical: Vector3<f32>,
-    u: Vector3<f32>,
-    v: Vector3<f32>,
-    _w: Vector3<f32>,
-    lens_radius: f32,
-    time: Option<(f32, f32)>,
+    origin: Vector3<Float>,
+    lower_left_corner: Vector3<Float>,
+    horizontal: Vector3<Float>,
+    vertical: Vector3<Float>,
+    u: Vector3<Float>,
+    v: Vector3<Float>,
+    w: Vector3<Float>,
+    lens_radius: Float,
+    time: Option<(Float, Float)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    near_clip: Float,
+    #[cfg_attr(feature = "serde", serde(default))]
+    rolling_shutter: Option<RollingShutter>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    lens: Option<RealisticLens>,
+}
+
+/// Order in which a [rolling shutter](Camera::with_rolling_shutter) exposes the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ShutterDirection {
+    /// Row by row, starting at the top (like most CMOS sensors).
+    TopToBottom,
+    /// Row by row, starting at the bottom.
+    BottomToTop,
+    /// Column by column, starting at the left.
+    LeftToRight,
+    /// Column by column, starting at the right.
+    RightToLeft,
 }
 
+/// A shutter that exposes the rows or columns of the image one after another, see [`Camera::with_rolling_shutter`].
+///
+/// # Fields
+/// - `direction`: [`ShutterDirection`] of the readout.
+/// - `exposure`: Fraction of the exposure time of the [`Camera`] during which each row or column is exposed.
+/// - `v_offset`: Position of `v` = 0 of the [`Camera`] on the whole image, which differs for [bands](Camera::band).
+/// - `v_scale`: Height of the viewport of the [`Camera`] relative to the whole image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct RollingShutter {
+    direction: ShutterDirection,
+    exposure: Float,
+    v_offset: Float,
+    v_scale: Float,
+}
+
+/// A [`LensSystem`] in front of the film, see [`Camera::with_lens_system`].
+///
+/// # Fields
+/// - `system`: [`LensSystem`] focused at the focus distance of the [`Camera`].
+/// - `film_scale`: Size of the film relative to the viewport of the [`Camera`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct RealisticLens {
+    system: LensSystem,
+    film_scale: Float,
+}
+
+/// How often a [`Ray`] blocked by a [`LensSystem`] is drawn again by the methods that have to return one.
+const LENS_ATTEMPTS: usize = 64;
+
 impl Camera {
     /// Create a new camera.
     ///
@@ -45,13 +108,13 @@ impl Camera {
     /// - `aperture`: Aperture for the purpose of depth-of-field (double the radius of the lense).
     /// - `focus_distance`: Distance at which objects appear in focus.
     pub fn new(
-        lookfrom: Vector3<f32>,
-        lookat: Vector3<f32>,
-        vup: Vector3<f32>,
-        vertical_fov: f32,
-        aspect_ratio: f32,
-        aperture: f32,
-        focus_distance: f32,
+        lookfrom: Vector3<Float>,
+        lookat: Vector3<Float>,
+        vup: Vector3<Float>,
+        vertical_fov: Float,
+        aspect_ratio: Float,
+        aperture: Float,
+        focus_distance: Float,
     ) -> Self {
         let h = (vertical_fov / 2.).tan();
         let viewport_height = 2. * h;
@@ -72,39 +135,535 @@ impl Camera {
             vertical,
             u,
             v,
-            _w: w,
+            w,
             lens_radius: aperture / 2.,
             time: None,
+            near_clip: 0.,
+            rolling_shutter: None,
+            lens: None,
         }
     }
 
+    /// Create a new camera without depth-of-field from a view matrix, e.g. one exported from Blender or another DCC tool.
+    ///
+    /// `view` transforms world coordinates to camera coordinates, in which the camera looks along -z with y pointing up (as in OpenGL and Blender).
+    /// It is given row by row, so `view[row][column]`, with the translation in the last column.
+    /// Returns [`None`] if `view` is not invertible.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::*;
+    /// // Camera at (0, 0, 5) looking at the origin
+    /// let view = [
+    ///     [1., 0., 0., 0.],
+    ///     [0., 1., 0., 0.],
+    ///     [0., 0., 1., -5.],
+    ///     [0., 0., 0., 1.],
+    /// ];
+    /// let camera = Camera::from_matrix(view, float::consts::FRAC_PI_4, 1.).unwrap();
+    /// let ray = camera.get_center_ray(0.5, 0.5);
+    /// assert!((ray.origin() - vector![0., 0., 5.]).norm() < 1e-5);
+    /// assert!((ray.direction().normalize() - vector![0., 0., -1.]).norm() < 1e-5);
+    /// ```
+    pub fn from_matrix(
+        view: [[Float; 4]; 4],
+        vertical_fov: Float,
+        aspect_ratio: Float,
+    ) -> Option<Self> {
+        let view = Matrix4::from_fn(|row, column| view[row][column]);
+        let camera_to_world = view.try_inverse()?;
+
+        let origin = (camera_to_world * vector![0., 0., 0., 1.]).xyz();
+        let forward = (camera_to_world * vector![0., 0., -1., 0.]).xyz();
+        let up = (camera_to_world * vector![0., 1., 0., 0.]).xyz();
+        Some(Self::new(
+            origin,
+            origin + forward,
+            up,
+            vertical_fov,
+            aspect_ratio,
+            0.,
+            1.,
+        ))
+    }
+
+    /// Create a new camera without depth-of-field that looks in `direction` and sees all of `world`, e.g. a model imported at an unknown scale.
+    ///
+    /// The camera looks at the center of the bounding box of all bounded objects in `world` (infinite ones like planes are left out) and steps back just far enough that all corners of the box are in view.
+    /// Its up direction is +y, or -z when looking straight up or down. The focus distance is the distance to the center.
+    /// Returns [`None`] if `world` has no bounded objects or `direction` is zero.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, shapes::Sphere};
+    /// let mut world = HittableList::default();
+    /// world.push(Sphere::new(vector![100., 20., 0.], 5., Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+    ///
+    /// let camera = Camera::frame_world(&world, float::consts::FRAC_PI_4, 16. / 9., vector![0., 0., -1.]).unwrap();
+    /// assert!(world.hit(camera.get_center_ray(0.5, 0.5), 0.001, Float::INFINITY).is_some());
+    /// // The sphere stays inside of the image.
+    /// assert!(world.hit(camera.get_center_ray(0.5, 0.01), 0.001, Float::INFINITY).is_none());
+    /// ```
+    pub fn frame_world(
+        world: &HittableList,
+        vertical_fov: Float,
+        aspect_ratio: Float,
+        direction: Vector3<Float>,
+    ) -> Option<Self> {
+        let aabb = world
+            .iter()
+            .filter_map(|hittable| hittable.bounding_box(0., 0.))
+            .reduce(|aabb, other| aabb.surrounding(&other))?;
+        let forward = direction.try_normalize(Float::EPSILON)?;
+        let vup = match forward.cross(&Vector3::y()).norm_squared() > 1e-6 {
+            true => Vector3::y(),
+            false => -Vector3::z(),
+        };
+        let u = forward.cross(&vup).normalize();
+        let v = u.cross(&forward);
+
+        // Every corner (x, y, z) in the frame of the camera, with z along `forward`, needs |x| ≤ (distance + z) tan of the half field of view.
+        let tan_v = (vertical_fov / 2.).tan();
+        let tan_h = aspect_ratio * tan_v;
+        let center = (aabb.minimum + aabb.maximum) / 2.;
+        let half = (aabb.maximum - aabb.minimum) / 2.;
+        let distance = (0..8)
+            .map(|corner| {
+                let sign = |bit: usize| if corner & bit == 0 { -1. } else { 1. };
+                let offset = vector![sign(1) * half.x, sign(2) * half.y, sign(4) * half.z];
+                let z = offset.dot(&forward);
+                (offset.dot(&u).abs() / tan_h - z)
+                    .max(offset.dot(&v).abs() / tan_v - z)
+                    .max(-z)
+            })
+            .fold(0., Float::max)
+            .max(Float::EPSILON);
+
+        Some(Self::new(
+            center - distance * forward,
+            center,
+            vup,
+            vertical_fov,
+            aspect_ratio,
+            0.,
+            distance,
+        ))
+    }
+
+    /// Cameras for the left and the right eye of a stereo image.
+    ///
+    /// The eyes are moved apart by `interpupillary_distance` along the horizontal of the viewport, but keep looking in the same direction.
+    /// Instead of turning them inwards, their viewports are shifted, so that both views coincide at the distance `convergence` (off-axis stereo).
+    /// Objects closer than `convergence` appear in front of the screen, objects farther away behind it.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::*;
+    /// let camera = Camera::new(vector![0., 0., 0.], vector![0., 0., -1.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+    /// let (left, right) = camera.stereo_pair(0.064, 2.);
+    ///
+    /// // Both center rays meet at the convergence distance
+    /// let (left, right) = (left.get_center_ray(0.5, 0.5), right.get_center_ray(0.5, 0.5));
+    /// let (left, right) = (left.at(2. / left.direction().norm()), right.at(2. / right.direction().norm()));
+    /// assert!((left - right).norm() < 1e-3);
+    /// ```
+    pub fn stereo_pair(&self, interpupillary_distance: Float, convergence: Float) -> (Self, Self) {
+        let center = self.lower_left_corner + self.horizontal / 2. + self.vertical / 2.;
+        let focus_distance = (self.origin - center).dot(&self.w);
+
+        let eye = |side: Float| {
+            let offset = side * interpupillary_distance / 2.;
+            let mut camera = self.clone();
+            camera.origin += offset * self.u;
+            camera.lower_left_corner += offset * (1. - focus_distance / convergence) * self.u;
+            camera
+        };
+        (eye(-1.), eye(1.))
+    }
+
     /// Consume `self` and create a [`Camera`] with a non-zero exposure.
-    pub fn with_time(mut self, time_start: f32, time_end: f32) -> Self {
+    pub fn with_time(mut self, time_start: Float, time_end: Float) -> Self {
         self.time = Some((time_start, time_end));
         self
     }
 
+    /// Consume `self` and expose the rows or columns of the image one after another in `direction` instead of all at once.
+    ///
+    /// Each row (or column) is exposed for the fraction `exposure` (in \[0, 1\]) of the exposure time given by [`with_time`](Camera::with_time), and the first and the last one at its start and end.
+    /// Fast moving objects are therefore skewed (like propellers filmed with a phone) and only blurred by the shorter exposure of each row.
+    /// An `exposure` of 1 is a global shutter. Without an exposure time, this has no effect.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, camera::ShutterDirection};
+    /// let camera = Camera::default()
+    ///     .with_time(0., 1.)
+    ///     .with_rolling_shutter(ShutterDirection::TopToBottom, 0.);
+    /// assert_eq!(camera.get_ray(0.5, 1.).time(), 0.);
+    /// assert_eq!(camera.get_ray(0.2, 0.25).time(), 0.75);
+    /// ```
+    pub fn with_rolling_shutter(mut self, direction: ShutterDirection, exposure: Float) -> Self {
+        self.rolling_shutter = Some(RollingShutter {
+            direction,
+            exposure: exposure.clamp(0., 1.),
+            v_offset: 0.,
+            v_scale: 1.,
+        });
+        self
+    }
+
+    /// Consume `self` and trace camera [`Ray`]s through the realistic `lens` instead of a thin lens.
+    ///
+    /// The film lies at the position of the camera and has the aspect ratio of the viewport and the diagonal `film_diagonal` (in the units of `lens`, e.g. 43.3 mm for 35 mm film).
+    /// The lens is [focused](LensSystem::focused) at the focus distance of the camera, measured from the film; the field of view and the aperture of the camera are replaced by those of the lens.
+    /// Besides its depth of field, this reproduces the distortion and the optical vignetting of the lens: [`Ray`]s blocked inside of it are black.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, lens::LensSystem};
+    /// let camera = Camera::new(vector![0., 0., 0.], vector![0., 0., -1.], vector![0., 1., 0.], 1., 1.5, 0., 3.)
+    ///     .with_lens_system(LensSystem::double_gauss().scaled(0.001), 0.0433);
+    /// let ray = camera.get_center_ray(0.5, 0.5);
+    /// assert!((ray.direction().normalize() - vector![0., 0., -1.]).norm() < 1e-3);
+    /// ```
+    pub fn with_lens_system(mut self, lens: LensSystem, film_diagonal: Float) -> Self {
+        let (width, height) = (self.horizontal.norm(), self.vertical.norm());
+        let film_width = film_diagonal * width / width.hypot(height);
+        let center = self.lower_left_corner + self.horizontal / 2. + self.vertical / 2.;
+        let focus_distance = (self.origin - center).dot(&self.w);
+        self.lens = Some(RealisticLens {
+            system: lens.focused(focus_distance),
+            film_scale: film_width / width,
+        });
+        self
+    }
+
+    /// Consume `self` and let camera [`Ray`]s start at a plane `distance` in front of the lens, so everything closer is invisible to the camera.
+    ///
+    /// This cuts open closed scenes, e.g. the front wall of a box, while the cut away objects still cast shadows and are seen in reflections. To cut objects for all [`Ray`]s, use [`Clipped`](crate::clipping::Clipped).
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::*;
+    /// let camera = Camera::default().with_near_clip(2.);
+    /// let ray = camera.get_center_ray(0.5, 0.5);
+    /// assert!((ray.origin() - vector![0., 0., -2.]).norm() < 1e-5);
+    /// ```
+    pub fn with_near_clip(mut self, distance: Float) -> Self {
+        self.near_clip = distance;
+        self
+    }
+
+    /// The camera moved from coordinates relative to `transform` into world coordinates, e.g. for a camera in a [scene graph](crate::scenegraph).
+    ///
+    /// Motion of `transform` is ignored; the camera is placed where `transform` is at the start of the exposure.
+    pub fn transformed(&self, transform: &Offset) -> Self {
+        let time = self.time.map_or(0., |(time_start, _)| time_start);
+        Self {
+            origin: transform.to_world(self.origin, time),
+            lower_left_corner: transform.to_world(self.lower_left_corner, time),
+            horizontal: transform.normal_to_world(self.horizontal),
+            vertical: transform.normal_to_world(self.vertical),
+            u: transform.normal_to_world(self.u),
+            v: transform.normal_to_world(self.v),
+            w: transform.normal_to_world(self.w),
+            ..self.clone()
+        }
+    }
+
     /// Emit a [`Ray`] from the camera.
-    pub fn get_ray(&self, u: f32, v: f32) -> Ray {
-        let mut rng = rand::thread_rng();
+    ///
+    /// [`Ray`]s blocked by a [lens system](Camera::with_lens_system) are drawn again, see [`try_get_ray_with_differentials`](Camera::try_get_ray_with_differentials) to keep its vignetting.
+    pub fn get_ray(&self, u: Float, v: Float) -> Ray {
+        match &self.lens {
+            Some(lens) => self.unblocked(|| self.realistic_ray(lens, u, v, None), u, v),
+            None => self.clip(self.lens_ray(u, v)),
+        }
+    }
 
+    /// Emit a [`Ray`] from a random point of the thin lens, before clipping.
+    fn lens_ray(&self, u: Float, v: Float) -> Ray {
         let random_disk = self.lens_radius * random_vector_in_unit_disk();
         let offset = self.u * random_disk.x + self.v * random_disk.y;
 
         let ray = Ray::new(
             self.origin + offset,
             self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin - offset,
-        );
-        if let Some((time1, time2)) = self.time {
-            ray.with_time(time1 + rng.gen::<f32>() * (time2 - time1))
+        )
+        .with_kind(RayKind::Camera);
+        self.timed(ray, u, v)
+    }
+
+    /// Give `ray` through the point (`u`, `v`) of the viewport a random time of its exposure.
+    fn timed(&self, ray: Ray, u: Float, v: Float) -> Ray {
+        if let Some((time1, time2)) = self.exposure(u, v) {
+            ray.with_time(time1 + crate::random::rng().gen::<Float>() * (time2 - time1))
         } else {
             ray
         }
     }
 
-    pub fn time(&self) -> Option<(f32, f32)> {
+    /// Emit a [`Ray`] through a random point of the rear surface of `lens`, with [`RayDifferentials`] if `pixel_size` is given, before clipping.
+    ///
+    /// Returns [`None`] if the [`Ray`] is blocked by the lens.
+    fn realistic_ray(
+        &self,
+        lens: &RealisticLens,
+        u: Float,
+        v: Float,
+        pixel_size: Option<(Float, Float)>,
+    ) -> Option<Ray> {
+        let rear = random_vector_in_unit_disk();
+        let trace = |u: Float, v: Float| {
+            let (origin, direction) = lens
+                .system
+                .ray_from_film(self.film_point(lens, u, v), (rear.x, rear.y))?;
+            Some((
+                self.lens_to_world(origin),
+                self.lens_direction_to_world(direction),
+            ))
+        };
+
+        let (origin, direction) = trace(u, v)?;
+        let ray = self.timed(Ray::new(origin, direction).with_kind(RayKind::Camera), u, v);
+        // The auxiliary rays go through the same point of the rear surface, if they pass the lens.
+        let Some((du, dv)) = pixel_size else {
+            return Some(ray);
+        };
+        Some(match (trace(u + du, v), trace(u, v + dv)) {
+            (Some((x_origin, x_direction)), Some((y_origin, y_direction))) => ray
+                .with_differentials(RayDifferentials {
+                    x_origin,
+                    x_direction,
+                    y_origin,
+                    y_direction,
+                }),
+            _ => ray,
+        })
+    }
+
+    /// Draw [`Ray`]s from `sample` until one passes the lens system, or fall back to the [center ray](Camera::get_center_ray).
+    fn unblocked(&self, sample: impl Fn() -> Option<Ray>, u: Float, v: Float) -> Ray {
+        (0..LENS_ATTEMPTS)
+            .find_map(|_| sample())
+            .map(|ray| self.clip(ray))
+            .unwrap_or_else(|| self.get_center_ray(u, v))
+    }
+
+    /// Point of the film of `lens` (in its coordinates) that sees the point (`u`, `v`) of the viewport.
+    fn film_point(&self, lens: &RealisticLens, u: Float, v: Float) -> (Float, Float) {
+        let point = self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin;
+        // The lens turns the image upside down.
+        (
+            -lens.film_scale * point.dot(&self.u),
+            -lens.film_scale * point.dot(&self.v),
+        )
+    }
+
+    fn lens_to_world(&self, point: Vector3<Float>) -> Vector3<Float> {
+        self.origin + self.lens_direction_to_world(point)
+    }
+
+    fn lens_direction_to_world(&self, direction: Vector3<Float>) -> Vector3<Float> {
+        direction.x * self.u + direction.y * self.v - direction.z * self.w
+    }
+
+    /// Interval during which the point (`u`, `v`) of the viewport is exposed, see [`with_rolling_shutter`](Camera::with_rolling_shutter).
+    fn exposure(&self, u: Float, v: Float) -> Option<(Float, Float)> {
+        let (start, end) = self.time?;
+        let Some(shutter) = self.rolling_shutter else {
+            return Some((start, end));
+        };
+        let v = shutter.v_offset + shutter.v_scale * v;
+        let position = match shutter.direction {
+            ShutterDirection::TopToBottom => 1. - v,
+            ShutterDirection::BottomToTop => v,
+            ShutterDirection::LeftToRight => u,
+            ShutterDirection::RightToLeft => 1. - u,
+        }
+        .clamp(0., 1.);
+        let duration = end - start;
+        let line_start = start + position * (1. - shutter.exposure) * duration;
+        Some((line_start, line_start + shutter.exposure * duration))
+    }
+
+    /// Move the origin of a camera [`Ray`] forward to the near clipping plane.
+    fn clip(&self, ray: Ray) -> Ray {
+        if self.near_clip <= 0. {
+            return ray;
+        }
+        let t = self.near_clip / ray.direction().dot(&-self.w);
+        let clipped = Ray::new(ray.at(t), ray.direction())
+            .with_time(ray.time())
+            .with_kind(RayKind::Camera);
+        match ray.differentials() {
+            Some(differentials) => clipped.with_differentials(differentials),
+            None => clipped,
+        }
+    }
+
+    /// Emit a [`Ray`] like [`get_ray`](Camera::get_ray) with [`RayDifferentials`] towards (`u` + `du`, `v`) and (`u`, `v` + `dv`).
+    ///
+    /// (`du`, `dv`) should be the size of a pixel, so that textures are filtered over its footprint.
+    pub fn get_ray_with_differentials(&self, u: Float, v: Float, (du, dv): (Float, Float)) -> Ray {
+        if let Some(lens) = &self.lens {
+            return self.unblocked(|| self.realistic_ray(lens, u, v, Some((du, dv))), u, v);
+        }
+        let ray = self.lens_ray(u, v);
+        // The auxiliary rays go through the same point of the lens.
+        let origin = ray.origin();
+        let direction = |u: Float, v: Float| {
+            self.lower_left_corner + u * self.horizontal + v * self.vertical - origin
+        };
+        self.clip(ray.with_differentials(RayDifferentials {
+            x_origin: origin,
+            x_direction: direction(u + du, v),
+            y_origin: origin,
+            y_direction: direction(u, v + dv),
+        }))
+    }
+
+    /// Emit a [`Ray`] like [`get_ray_with_differentials`](Camera::get_ray_with_differentials), or [`None`] if it is blocked by a [lens system](Camera::with_lens_system).
+    ///
+    /// Counting blocked [`Ray`]s as black reproduces the optical vignetting of the lens.
+    pub fn try_get_ray_with_differentials(
+        &self,
+        u: Float,
+        v: Float,
+        pixel_size: (Float, Float),
+    ) -> Option<Ray> {
+        match &self.lens {
+            Some(lens) => self
+                .realistic_ray(lens, u, v, Some(pixel_size))
+                .map(|ray| self.clip(ray)),
+            None => Some(self.get_ray_with_differentials(u, v, pixel_size)),
+        }
+    }
+
+    /// Emit a [`Ray`] from the center of the lens at the start of the exposure (of its row or column with a [rolling shutter](Camera::with_rolling_shutter)).
+    ///
+    /// Unlike [`get_ray`](Camera::get_ray), this is deterministic, e.g. for picking objects.
+    ///
+    /// With a [lens system](Camera::with_lens_system), the [`Ray`] passes through the center of its rear surface, or the first unblocked of a few points around it.
+    pub fn get_center_ray(&self, u: Float, v: Float) -> Ray {
+        let through_lens = self.lens.as_ref().and_then(|lens| {
+            let film = self.film_point(lens, u, v);
+            let mut rear = std::iter::once((0., 0.)).chain((0..16).map(|index| {
+                let radius = if index < 8 { 0.5 } else { 0.9 };
+                let angle = index as Float * float::consts::FRAC_PI_4;
+                (radius * angle.cos(), radius * angle.sin())
+            }));
+            rear.find_map(|rear| lens.system.ray_from_film(film, rear))
+        });
+        let ray = match through_lens {
+            Some((origin, direction)) => Ray::new(
+                self.lens_to_world(origin),
+                self.lens_direction_to_world(direction),
+            ),
+            None => Ray::new(
+                self.origin,
+                self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin,
+            ),
+        }
+        .with_kind(RayKind::Camera);
+        self.clip(match self.exposure(u, v) {
+            Some((time1, _)) => ray.with_time(time1),
+            None => ray,
+        })
+    }
+
+    /// Distance in front of the lens at which camera [`Ray`]s start, see [`with_near_clip`](Camera::with_near_clip).
+    pub fn near_clip(&self) -> Float {
+        self.near_clip
+    }
+
+    pub fn time(&self) -> Option<(Float, Float)> {
         self.time
     }
+
+    /// Range of `u` and `v` (like in [`get_ray`](Camera::get_ray)) in which points of `aabb` can be seen through the lens, as (`u_min`, `v_min`, `u_max`, `v_max`).
+    ///
+    /// Returns [`None`] if a part of `aabb` is not in front of the camera, or for a [lens system](Camera::with_lens_system).
+    pub(crate) fn footprint(&self, aabb: &Aabb) -> Option<(Float, Float, Float, Float)> {
+        if self.lens.is_some() {
+            return None;
+        }
+        let corner = self.lower_left_corner - self.origin;
+        let focus_distance = -corner.dot(&self.w);
+        let (horizontal, vertical) = (self.horizontal.norm(), self.vertical.norm());
+
+        let mut footprint = (
+            Float::INFINITY,
+            Float::INFINITY,
+            Float::NEG_INFINITY,
+            Float::NEG_INFINITY,
+        );
+        let mut blur: Float = 0.;
+        for index in 0..8 {
+            let point = vector![
+                if index & 1 == 0 {
+                    aabb.minimum.x
+                } else {
+                    aabb.maximum.x
+                },
+                if index & 2 == 0 {
+                    aabb.minimum.y
+                } else {
+                    aabb.maximum.y
+                },
+                if index & 4 == 0 {
+                    aabb.minimum.z
+                } else {
+                    aabb.maximum.z
+                }
+            ];
+            let direction = point - self.origin;
+            let depth = -direction.dot(&self.w);
+            if depth <= Float::EPSILON {
+                return None;
+            }
+            // Where the ray from the center of the lens through the corner meets the focus plane
+            let scale = focus_distance / depth;
+            let offset = scale * direction - corner;
+            let u = offset.dot(&self.horizontal) / (horizontal * horizontal);
+            let v = offset.dot(&self.vertical) / (vertical * vertical);
+            footprint = (
+                footprint.0.min(u),
+                footprint.1.min(v),
+                footprint.2.max(u),
+                footprint.3.max(v),
+            );
+            // Rays from the rim of the lens meet the focus plane up to this far away for points out of focus.
+            blur = blur.max(self.lens_radius * (1. - scale).abs());
+        }
+        let (du, dv) = (blur / horizontal, blur / vertical);
+        Some((
+            footprint.0 - du,
+            footprint.1 - dv,
+            footprint.2 + du,
+            footprint.3 + dv,
+        ))
+    }
+
+    /// The camera that sees only the rows `y0..y1` (counted from the top) of an image with `image_height` rows, as an image of its own.
+    ///
+    /// Both images need at least two rows.
+    pub(crate) fn band(&self, (y0, y1): (u32, u32), image_height: u32) -> Self {
+        let scale = (image_height - 1) as Float;
+        let offset = (image_height - y1) as Float / scale;
+        let height = (y1 - y0 - 1) as Float / scale;
+        Self {
+            lower_left_corner: self.lower_left_corner + self.vertical * offset,
+            vertical: self.vertical * height,
+            // The rows keep their time of exposure.
+            rolling_shutter: self.rolling_shutter.map(|shutter| RollingShutter {
+                v_offset: shutter.v_offset + shutter.v_scale * offset,
+                v_scale: shutter.v_scale * height,
+                ..shutter
+            }),
+            ..self.clone()
+        }
+    }
 }
 
 impl Default for Camera {
@@ -113,10 +672,95 @@ impl Default for Camera {
             vector![0., 0., 0.],
             vector![0., 0., -1.],
             vector![0., 1., 0.],
-            std::f32::consts::FRAC_PI_6,
+            float::consts::FRAC_PI_6,
             16. / 9.,
             0.,
             1.,
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rolling_shutter_bands() {
+        let camera = Camera::default()
+            .with_time(0., 1.)
+            .with_rolling_shutter(ShutterDirection::TopToBottom, 0.2);
+        // Rows 10..20 of an image with 21 rows
+        let band = camera.band((10, 20), 21);
+        for (v, band_v) in [(0.5, 1.), (0.05, 0.)] {
+            let time = camera.get_center_ray(0.3, v).time();
+            assert!((band.get_center_ray(0.3, band_v).time() - time).abs() < 1e-6);
+        }
+        assert!((camera.get_center_ray(0.3, 0.).time() - 0.8).abs() < 1e-6);
+        let ray = camera.get_ray(0.3, 0.);
+        assert!(ray.time() >= 0.8 - 1e-6 && ray.time() <= 1.);
+    }
+
+    #[test]
+    fn lens_system_vignetting() {
+        let camera = Camera::new(
+            vector![0., 0., 0.],
+            vector![0., 0., -1.],
+            vector![0., 1., 0.],
+            1.,
+            1.5,
+            0.,
+            2.,
+        )
+        .with_lens_system(LensSystem::double_gauss().scaled(0.001), 0.0433);
+        let passed = |u: Float, v: Float| {
+            (0..1000)
+                .filter(|_| {
+                    camera
+                        .try_get_ray_with_differentials(u, v, (1e-3, 1e-3))
+                        .is_some()
+                })
+                .count()
+        };
+        let (center, corner) = (passed(0.5, 0.5), passed(0., 0.));
+        assert!(center > 500, "{center}");
+        assert!(corner < center, "{corner} {center}");
+
+        // The image is not mirrored and the lens is focused at the focus distance.
+        let ray = camera.get_center_ray(0.9, 0.5);
+        assert!(ray.direction().x > 0.);
+        let focus = |ray: Ray| ray.at((-2. - ray.origin().z) / ray.direction().z);
+        let samples: Vec<_> = (0..20).map(|_| focus(camera.get_ray(0.7, 0.6))).collect();
+        for sample in &samples {
+            assert!(
+                (sample - samples[0]).norm() < 1e-2,
+                "{sample} {}",
+                samples[0]
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let camera = Camera::new(
+            vector![1., 2., 3.],
+            vector![0., 0., 0.],
+            vector![0., 1., 0.],
+            1.,
+            1.5,
+            0.1,
+            4.,
+        )
+        .with_time(0., 1.);
+        let json = serde_json::to_string(&camera).unwrap();
+        let deserialized: Camera = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized.time(), camera.time());
+        let (ray, expected) = (
+            deserialized.get_center_ray(0.2, 0.7),
+            camera.get_center_ray(0.2, 0.7),
+        );
+        assert_eq!(ray.origin(), expected.origin());
+        assert_eq!(ray.direction(), expected.direction());
+    }
+}
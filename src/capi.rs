@@ -0,0 +1,345 @@
+//! A small C interface to embed the renderer in C or C++ applications.
+//!
+//! The declarations are in `include/ray_tracing_in_one_weekend.h`, which can be regenerated with `cbindgen --config cbindgen.toml --output include/ray_tracing_in_one_weekend.h`.
+//! Build a shared or static library with `cargo rustc --release --features capi --crate-type cdylib` (or `staticlib`).
+//!
+//! A scene is created with [`rt_raytracer_new`], filled with [`rt_raytracer_add_sphere`], rendered with [`rt_raytracer_render`] as often as needed, and freed with [`rt_raytracer_free`].
+//! Functions that can fail return an [`RtStatus`].
+
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+use std::sync::Arc;
+
+use crate::materials::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::shapes::Sphere;
+use crate::*;
+
+/// Result of a function of the C interface.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RtStatus {
+    Ok = 0,
+    /// A pointer was null.
+    NullPointer = 1,
+    /// An argument was out of range, e.g. a buffer that is too small.
+    InvalidArgument = 2,
+    /// Rendering failed or panicked.
+    RenderFailed = 3,
+}
+
+/// A vector or color.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RtVec3 {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl From<RtVec3> for Vector3<Float> {
+    fn from(vector: RtVec3) -> Self {
+        vector![vector.x, vector.y, vector.z]
+    }
+}
+
+impl From<RtVec3> for Color {
+    fn from(color: RtVec3) -> Self {
+        color![color.x, color.y, color.z]
+    }
+}
+
+/// Kind of an [`RtMaterial`], which stores it as a `u32`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RtMaterialKind {
+    Lambertian = 0,
+    /// Uses `parameter` as the fuzz.
+    Metal = 1,
+    /// Uses `parameter` as the index of refraction and ignores `color`.
+    Dielectric = 2,
+    /// Emits `color`.
+    DiffuseLight = 3,
+}
+
+impl TryFrom<u32> for RtMaterialKind {
+    type Error = u32;
+
+    fn try_from(kind: u32) -> Result<Self, u32> {
+        match kind {
+            0 => Ok(RtMaterialKind::Lambertian),
+            1 => Ok(RtMaterialKind::Metal),
+            2 => Ok(RtMaterialKind::Dielectric),
+            3 => Ok(RtMaterialKind::DiffuseLight),
+            _ => Err(kind),
+        }
+    }
+}
+
+/// A material of the C interface.
+///
+/// # Fields
+/// - `kind`: Which material to create, an [`RtMaterialKind`]. It is a plain integer, because C may pass any value.
+/// - `color`: Albedo or emitted color.
+/// - `parameter`: Fuzz of a metal or index of refraction of a dielectric.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RtMaterial {
+    pub kind: u32,
+    pub color: RtVec3,
+    pub parameter: Float,
+}
+
+impl RtMaterial {
+    /// The material and its kind, or [`None`] if `kind` is unknown.
+    fn material(&self) -> Option<(RtMaterialKind, Arc<dyn Material>)> {
+        let kind = RtMaterialKind::try_from(self.kind).ok()?;
+        let color = self.color.into();
+        let material: Arc<dyn Material> = match kind {
+            RtMaterialKind::Lambertian => Arc::new(Lambertian::solid_color(color)),
+            RtMaterialKind::Metal => Arc::new(Metal::solid_color(color, self.parameter)),
+            RtMaterialKind::Dielectric => Arc::new(Dielectric::new(self.parameter)),
+            RtMaterialKind::DiffuseLight => Arc::new(DiffuseLight::solid_color(color)),
+        };
+        Some((kind, material))
+    }
+}
+
+/// A scene and its settings, which is opaque to C.
+pub struct RtRaytracer {
+    camera: Camera,
+    background: Color,
+    image_width: u32,
+    image_height: u32,
+    samples_per_pixel: u16,
+    max_depth: u16,
+    world: HittableList,
+    lights: Vec<Sphere<Arc<dyn Material>>>,
+}
+
+/// Create an empty scene with a black background and the default [`Camera`] with the aspect ratio of the image, or return null if the image has no pixels or no samples are taken.
+#[no_mangle]
+pub extern "C" fn rt_raytracer_new(
+    image_width: u32,
+    image_height: u32,
+    samples_per_pixel: u16,
+    max_depth: u16,
+) -> *mut RtRaytracer {
+    if image_width == 0 || image_height == 0 || samples_per_pixel == 0 {
+        return std::ptr::null_mut();
+    }
+    Box::into_raw(Box::new(RtRaytracer {
+        camera: Camera::new(
+            vector![0., 0., 0.],
+            vector![0., 0., -1.],
+            vector![0., 1., 0.],
+            crate::float::consts::FRAC_PI_6,
+            image_width as Float / image_height as Float,
+            0.,
+            1.,
+        ),
+        background: color![0., 0., 0.],
+        image_width,
+        image_height,
+        samples_per_pixel,
+        max_depth,
+        world: HittableList::default(),
+        lights: Vec::new(),
+    }))
+}
+
+/// Free a scene created by [`rt_raytracer_new`]. Null is ignored.
+///
+/// # Safety
+/// `raytracer` has to be null or come from [`rt_raytracer_new`] and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rt_raytracer_free(raytracer: *mut RtRaytracer) {
+    if !raytracer.is_null() {
+        drop(Box::from_raw(raytracer));
+    }
+}
+
+/// Place the camera at `lookfrom` facing `lookat`, see [`Camera::new`]. The field of view is in degrees and the aspect ratio is that of the image.
+///
+/// # Safety
+/// `raytracer` has to be null or a valid scene.
+#[no_mangle]
+pub unsafe extern "C" fn rt_raytracer_set_camera(
+    raytracer: *mut RtRaytracer,
+    lookfrom: RtVec3,
+    lookat: RtVec3,
+    vup: RtVec3,
+    vertical_fov: Float,
+    aperture: Float,
+    focus_distance: Float,
+) -> RtStatus {
+    let Some(raytracer) = raytracer.as_mut() else {
+        return RtStatus::NullPointer;
+    };
+    if !(vertical_fov > 0. && vertical_fov < 180.) || focus_distance <= 0. {
+        return RtStatus::InvalidArgument;
+    }
+    raytracer.camera = Camera::new(
+        lookfrom.into(),
+        lookat.into(),
+        vup.into(),
+        vertical_fov.to_radians(),
+        raytracer.image_width as Float / raytracer.image_height as Float,
+        aperture,
+        focus_distance,
+    );
+    RtStatus::Ok
+}
+
+/// Set the color of the background.
+///
+/// # Safety
+/// `raytracer` has to be null or a valid scene.
+#[no_mangle]
+pub unsafe extern "C" fn rt_raytracer_set_background(
+    raytracer: *mut RtRaytracer,
+    background: RtVec3,
+) -> RtStatus {
+    let Some(raytracer) = raytracer.as_mut() else {
+        return RtStatus::NullPointer;
+    };
+    raytracer.background = background.into();
+    RtStatus::Ok
+}
+
+/// Add a sphere. Spheres with a [`DiffuseLight`](RtMaterialKind::DiffuseLight) are sampled as lights.
+///
+/// Returns [`InvalidArgument`](RtStatus::InvalidArgument) if the radius is not positive or the kind of the material is unknown.
+///
+/// # Safety
+/// `raytracer` has to be null or a valid scene.
+#[no_mangle]
+pub unsafe extern "C" fn rt_raytracer_add_sphere(
+    raytracer: *mut RtRaytracer,
+    center: RtVec3,
+    radius: Float,
+    material: RtMaterial,
+) -> RtStatus {
+    let Some(raytracer) = raytracer.as_mut() else {
+        return RtStatus::NullPointer;
+    };
+    let Some((kind, material)) = material.material() else {
+        return RtStatus::InvalidArgument;
+    };
+    if radius <= 0. || radius.is_nan() {
+        return RtStatus::InvalidArgument;
+    }
+    let sphere = Sphere::new(center.into(), radius, material);
+    if kind == RtMaterialKind::DiffuseLight {
+        raytracer.lights.push(sphere);
+    } else {
+        raytracer.world.push(sphere);
+    }
+    RtStatus::Ok
+}
+
+/// Render the scene into `buffer` as 8 bit RGBA, row by row from the top.
+///
+/// `buffer_length` has to be at least `4 * image_width * image_height`. The scene is kept, so it can be changed and rendered again.
+///
+/// # Safety
+/// `raytracer` has to be null or a valid scene, and `buffer` has to be null or valid for writing `buffer_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rt_raytracer_render(
+    raytracer: *const RtRaytracer,
+    buffer: *mut u8,
+    buffer_length: usize,
+) -> RtStatus {
+    let Some(scene) = raytracer.as_ref() else {
+        return RtStatus::NullPointer;
+    };
+    if buffer.is_null() {
+        return RtStatus::NullPointer;
+    }
+    let length = 4 * scene.image_width as usize * scene.image_height as usize;
+    if buffer_length < length {
+        return RtStatus::InvalidArgument;
+    }
+
+    // Panics must not unwind into C.
+    let pixels = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut raytracer = Raytracer::new(
+            scene.camera.clone(),
+            scene.background,
+            scene.image_width,
+            scene.image_height,
+            scene.samples_per_pixel,
+            scene.max_depth,
+        );
+        raytracer.world = scene.world.clone();
+        for light in &scene.lights {
+            raytracer.push_light(light.clone());
+        }
+        raytracer.render_to_rgba_buffer()
+    }));
+    match pixels {
+        Ok(Ok(pixels)) => {
+            slice::from_raw_parts_mut(buffer, length).copy_from_slice(&pixels);
+            RtStatus::Ok
+        }
+        _ => RtStatus::RenderFailed,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_through_c_interface() {
+        let white = RtVec3 {
+            x: 1.,
+            y: 1.,
+            z: 1.,
+        };
+        unsafe {
+            assert!(rt_raytracer_new(0, 10, 1, 1).is_null());
+            let raytracer = rt_raytracer_new(8, 6, 2, 4);
+            assert!(!raytracer.is_null());
+            let light = RtMaterial {
+                kind: RtMaterialKind::DiffuseLight as u32,
+                color: white,
+                parameter: 0.,
+            };
+            let center = RtVec3 {
+                z: -3.,
+                ..Default::default()
+            };
+            assert_eq!(
+                rt_raytracer_add_sphere(raytracer, center, 10., light),
+                RtStatus::Ok
+            );
+            assert_eq!(
+                rt_raytracer_add_sphere(raytracer, center, -1., light),
+                RtStatus::InvalidArgument
+            );
+            let unknown = RtMaterial { kind: 4, ..light };
+            assert_eq!(
+                rt_raytracer_add_sphere(raytracer, center, 1., unknown),
+                RtStatus::InvalidArgument
+            );
+
+            let mut buffer = vec![0; 8 * 6 * 4];
+            assert_eq!(
+                rt_raytracer_render(raytracer, buffer.as_mut_ptr(), 10),
+                RtStatus::InvalidArgument
+            );
+            assert_eq!(
+                rt_raytracer_render(raytracer, buffer.as_mut_ptr(), buffer.len()),
+                RtStatus::Ok
+            );
+            // The camera is inside the emissive sphere.
+            assert!(buffer.iter().all(|&channel| channel == 255));
+
+            rt_raytracer_free(raytracer);
+            assert_eq!(
+                rt_raytracer_render(std::ptr::null(), buffer.as_mut_ptr(), buffer.len()),
+                RtStatus::NullPointer
+            );
+        }
+    }
+}
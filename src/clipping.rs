@@ -0,0 +1,279 @@
+//! Clipping planes that cut away parts of objects, e.g. to render a section through a building or to look into a closed box.
+//!
+//! A [`Clipped`] object keeps only the part behind all of its [`ClipPlane`]s. Without a cap, the cut leaves the object open, so one can look inside.
+//! With a cap, the cut surface of a closed object is filled with a flat [`Material`], like the section of a technical drawing.
+//!
+//! To remove what lies between the camera and the scene without changing the lighting, set a near clipping distance on the [`Camera`] instead (see [`Camera::with_near_clip`]).
+
+use std::sync::Arc;
+
+use crate::hitrecord::HitRecord;
+use crate::hittable::Aabb;
+use crate::materials::Material;
+use crate::ray::Ray;
+use crate::shapes::Offset;
+use crate::stats::SceneStats;
+use crate::vec3::orthonormal_basis;
+use crate::*;
+
+/// Maximum number of surface crossings counted to find out whether a point is inside an object.
+const MAX_CROSSINGS: usize = 64;
+
+/// A plane that cuts away everything in front of it.
+///
+/// # Fields
+/// - `point`: A point on the plane.
+/// - `normal`: Unit normal of the plane, pointing towards the side that is cut away.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipPlane {
+    point: Vector3<Float>,
+    normal: Vector3<Float>,
+}
+
+impl ClipPlane {
+    /// Create a plane through `point` that cuts away the side `normal` points to.
+    pub fn new(point: Vector3<Float>, normal: Vector3<Float>) -> Self {
+        Self {
+            point,
+            normal: normal.normalize(),
+        }
+    }
+
+    pub fn point(&self) -> Vector3<Float> {
+        self.point
+    }
+
+    pub fn normal(&self) -> Vector3<Float> {
+        self.normal
+    }
+
+    /// Signed distance of `point` from the plane, positive on the side that is cut away.
+    pub fn distance(&self, point: Vector3<Float>) -> Float {
+        (point - self.point).dot(&self.normal)
+    }
+}
+
+/// A wrapper around a [`Hittable`] without the parts in front of any of its [`ClipPlane`]s.
+///
+/// If a cap is set, the cut surface is closed with it where the plane passes through the inside of the object. This only gives sensible results for closed objects (see [`csg`](crate::csg)).
+/// A clipped object is not sampled as a light.
+///
+/// # Fields
+/// - `planes`: The [`ClipPlane`]s.
+/// - `cap`: Material of the cut surface.
+/// - `hittable`: The wrapped [`Hittable`].
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, clipping::{ClipPlane, Clipped}, materials::Lambertian, ray::Ray, shapes::Sphere};
+/// let sphere = Sphere::new(vector![0., 0., 0.], 1., Lambertian::solid_color(color![0.5, 0.5, 0.5]));
+/// // Cut away the front half of the sphere
+/// let plane = ClipPlane::new(vector![0., 0., 0.], vector![0., 0., 1.]);
+/// let ray = Ray::new(vector![0., 0., 5.], vector![0., 0., -1.]);
+///
+/// let open = Clipped::new(sphere.clone(), vec![plane]);
+/// // The ray passes the cut and hits the back of the sphere from the inside.
+/// let hit = open.hit(ray, 0.001, Float::INFINITY).unwrap();
+/// assert!((hit.t - 6.).abs() < 1e-4 && !hit.front_face);
+///
+/// let capped = Clipped::new(sphere, vec![plane]).with_cap(Lambertian::solid_color(color![0.8, 0.1, 0.1]));
+/// // The ray hits the cap in the plane.
+/// let hit = capped.hit(ray, 0.001, Float::INFINITY).unwrap();
+/// assert!((hit.t - 5.).abs() < 1e-4 && hit.front_face);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Clipped<H: Hittable> {
+    center: Offset,
+    planes: Vec<ClipPlane>,
+    cap: Option<Arc<dyn Material>>,
+    hittable: H,
+}
+
+impl<H: Hittable> Clipped<H> {
+    pub fn new(hittable: H, planes: Vec<ClipPlane>) -> Self {
+        Self {
+            center: Offset::default(),
+            planes,
+            cap: None,
+            hittable,
+        }
+    }
+
+    /// Consume `self` and close the cut surface with `material`.
+    pub fn with_cap<M: Material + 'static>(mut self, material: M) -> Self {
+        self.cap = Some(Arc::new(material));
+        self
+    }
+
+    pub fn planes(&self) -> &[ClipPlane] {
+        &self.planes
+    }
+
+    pub fn hittable(&self) -> &H {
+        &self.hittable
+    }
+
+    /// Parameters where `ray` enters and leaves the part behind all planes, together with the planes it crosses there.
+    fn kept_interval(&self, ray: Ray) -> Option<[(Float, Option<&ClipPlane>); 2]> {
+        let mut entry = (-Float::INFINITY, None);
+        let mut exit = (Float::INFINITY, None);
+        for plane in &self.planes {
+            let distance = plane.distance(ray.origin());
+            let speed = ray.direction().dot(&plane.normal);
+            if speed == 0. {
+                if distance > 0. {
+                    return None;
+                }
+                continue;
+            }
+            let t = -distance / speed;
+            if speed < 0. && t > entry.0 {
+                entry = (t, Some(plane));
+            } else if speed > 0. && t < exit.0 {
+                exit = (t, Some(plane));
+            }
+        }
+        (entry.0 < exit.0).then_some([entry, exit])
+    }
+
+    /// Whether the point of `ray` at `t` lies inside the wrapped object, i.e. the rest of `ray` crosses its surface an odd number of times.
+    ///
+    /// Counting the crossings does not depend on the orientation of the surfaces, which e.g. the sides of a [`Cuboid`](crate::shapes::Cuboid) do not have.
+    fn is_inside(&self, ray: Ray, mut t: Float) -> bool {
+        let mut crossings = 0;
+        while crossings < MAX_CROSSINGS {
+            let Some(hit) = self.hittable.hit(ray, t, Float::INFINITY) else {
+                break;
+            };
+            crossings += 1;
+            // Step over the crossing, scaled so it works at any distance.
+            t = hit.t + Float::EPSILON * hit.t.abs().max(1.) * 16.;
+        }
+        crossings % 2 == 1
+    }
+
+    fn cap_hit<'a>(
+        &self,
+        cap: &'a dyn Material,
+        plane: &ClipPlane,
+        ray: Ray,
+        t: Float,
+    ) -> HitRecord<'a> {
+        let point = ray.at(t);
+        let (tangent, bitangent) = orthonormal_basis(&plane.normal);
+        let offset = point - plane.point;
+        HitRecord::from_ray(
+            point,
+            offset.dot(&tangent).rem_euclid(1.),
+            offset.dot(&bitangent).rem_euclid(1.),
+            plane.normal,
+            t,
+            cap,
+            ray,
+        )
+        .with_tangent(tangent)
+    }
+}
+
+impl<H: Hittable> Hittable for Clipped<H> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let [(t_entry, entry), (t_exit, exit)] = self.kept_interval(ray)?;
+        let (t_start, t_end) = (t_entry.max(t_min), t_exit.min(t_max));
+        if t_start >= t_end {
+            return None;
+        }
+        let Some(cap) = &self.cap else {
+            return self.hittable.hit(ray, t_start, t_end);
+        };
+
+        if let Some(plane) = entry {
+            if t_entry > t_min && self.is_inside(ray, t_entry) {
+                return Some(self.cap_hit(cap.as_ref(), plane, ray, t_entry));
+            }
+        }
+        if let Some(hit) = self.hittable.hit(ray, t_start, t_end) {
+            return Some(hit);
+        }
+        match exit {
+            Some(plane) if t_exit < t_max && self.is_inside(ray, t_exit) => {
+                Some(self.cap_hit(cap.as_ref(), plane, ray, t_exit))
+            }
+            _ => None,
+        }
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.hittable.bounding_box(time0, time1)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn object_id(&self) -> Option<u32> {
+        self.hittable.object_id()
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.hittable.is_emissive()
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        self.hittable.collect_stats(stats);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::shapes::Cuboid;
+
+    #[test]
+    fn capped_box() {
+        let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+        let cuboid = Cuboid::new(vector![0., 0., 0.], 2., 2., 2., material.clone());
+        // Keep only the corner with x < 0.5 and y < 0.5
+        let planes = vec![
+            ClipPlane::new(vector![0.5, 0., 0.], vector![1., 0., 0.]),
+            ClipPlane::new(vector![0., 0.5, 0.], vector![0., 1., 0.]),
+        ];
+        let clipped = Clipped::new(cuboid, planes).with_cap(material);
+
+        // Through the cap at x = 0.5
+        let hit = clipped
+            .hit(
+                Ray::new(vector![5., 0., 0.], vector![-1., 0., 0.]),
+                0.001,
+                Float::INFINITY,
+            )
+            .unwrap();
+        assert!((hit.t - 4.5).abs() < 1e-4);
+        assert!((hit.normal - vector![1., 0., 0.]).norm() < 1e-5);
+        // Beside the box, the plane is not capped.
+        assert!(clipped
+            .hit(
+                Ray::new(vector![5., 0., 3.], vector![-1., 0., 0.]),
+                0.001,
+                Float::INFINITY
+            )
+            .is_none());
+        // From the inside, the cap is hit from behind.
+        let hit = clipped
+            .hit(
+                Ray::new(vector![0., 0., 0.], vector![0., 1., 0.]),
+                0.001,
+                Float::INFINITY,
+            )
+            .unwrap();
+        assert!((hit.t - 0.5).abs() < 1e-4 && !hit.front_face);
+        // Rays in front of a plane and parallel to it hit nothing.
+        assert!(clipped
+            .hit(
+                Ray::new(vector![0.8, 0., 5.], vector![0., 0., -1.]),
+                0.001,
+                Float::INFINITY
+            )
+            .is_none());
+    }
+}
@@ -3,7 +3,7 @@ use std::ops;
 use image::Rgb;
 use rand::Rng;
 
-use crate::*;
+use crate::Float;
 
 /// Macro for [`Color::new`]
 #[macro_export]
@@ -18,45 +18,64 @@ macro_rules! color {
 /// The colors a stored in RGB with each value between 0 and 1.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Color {
-    r: f32,
-    g: f32,
-    b: f32,
+    r: Float,
+    g: Float,
+    b: Float,
 }
 
 impl Color {
-    pub fn new(r: f32, g: f32, b: f32) -> Self {
+    pub fn new(r: Float, g: Float, b: Float) -> Self {
         Color { r, g, b }
     }
 
-    pub fn r(&self) -> f32 {
+    pub fn r(&self) -> Float {
         self.r
     }
 
-    pub fn g(&self) -> f32 {
+    pub fn g(&self) -> Float {
         self.g
     }
 
-    pub fn b(&self) -> f32 {
+    pub fn b(&self) -> Float {
         self.b
     }
 
     /// Creates a random vector with each element between 0 and 1.
     pub fn random() -> Self {
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
         Color::new(rng.gen(), rng.gen(), rng.gen())
     }
 
     /// Creates a random vector with each element in a range.
-    pub fn random_in_range(min: f32, max: f32) -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn random_in_range(min: Float, max: Float) -> Self {
+        let mut rng = crate::random::rng();
         Color::new(
-            min + rng.gen::<f32>() * (max - min),
-            min + rng.gen::<f32>() * (max - min),
-            min + rng.gen::<f32>() * (max - min),
+            min + rng.gen::<Float>() * (max - min),
+            min + rng.gen::<Float>() * (max - min),
+            min + rng.gen::<Float>() * (max - min),
         )
     }
 
-    /// Formats the [`Color`] as a [`String`], converting the `f32` RGB values to `u8`.
+    /// Relative luminance of a linear color.
+    pub(crate) fn luminance(self) -> Float {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// False color of `value` in \[0,1\] from blue over green and yellow to red.
+    pub(crate) fn heatmap(value: Float) -> Self {
+        let stops = [
+            color![0., 0., 1.],
+            color![0., 1., 0.],
+            color![1., 1., 0.],
+            color![1., 0., 0.],
+        ];
+        let position = value.clamp(0., 1.) * (stops.len() - 1) as Float;
+        let index = (position as usize).min(stops.len() - 2);
+        let s = position - index as Float;
+        stops[index] * (1. - s) + stops[index + 1] * s
+    }
+
+    /// Formats the [`Color`] as a [`String`], converting the `Float` RGB values to `u8`.
     pub(crate) fn to_color_str(self) -> String {
         let rgb: [u8; 3] = self.into();
         format!("{} {} {}", rgb[0], rgb[1], rgb[2])
@@ -82,9 +101,9 @@ impl From<Color> for Rgb<u8> {
 impl From<Rgb<u8>> for Color {
     fn from(value: Rgb<u8>) -> Self {
         color![
-            value[0] as f32 / 255.,
-            value[1] as f32 / 255.,
-            value[2] as f32 / 255.,
+            value[0] as Float / 255.,
+            value[1] as Float / 255.,
+            value[2] as Float / 255.,
         ]
     }
 }
@@ -117,15 +136,15 @@ impl ops::SubAssign for Color {
     }
 }
 
-impl ops::Mul<f32> for Color {
+impl ops::Mul<Float> for Color {
     type Output = Self;
 
-    fn mul(self, rhs: f32) -> Self::Output {
+    fn mul(self, rhs: Float) -> Self::Output {
         Color::new(rhs * self.r, rhs * self.g, rhs * self.b)
     }
 }
 
-impl ops::Mul<Color> for f32 {
+impl ops::Mul<Color> for Float {
     type Output = Color;
 
     fn mul(self, rhs: Color) -> Self::Output {
@@ -133,8 +152,8 @@ impl ops::Mul<Color> for f32 {
     }
 }
 
-impl ops::MulAssign<f32> for Color {
-    fn mul_assign(&mut self, rhs: f32) {
+impl ops::MulAssign<Float> for Color {
+    fn mul_assign(&mut self, rhs: Float) {
         *self = rhs * *self;
     }
 }
@@ -153,16 +172,16 @@ impl ops::MulAssign for Color {
     }
 }
 
-impl ops::Div<f32> for Color {
+impl ops::Div<Float> for Color {
     type Output = Self;
 
-    fn div(self, rhs: f32) -> Self::Output {
+    fn div(self, rhs: Float) -> Self::Output {
         Color::new(self.r / rhs, self.g / rhs, self.b / rhs)
     }
 }
 
-impl ops::DivAssign<f32> for Color {
-    fn div_assign(&mut self, rhs: f32) {
+impl ops::DivAssign<Float> for Color {
+    fn div_assign(&mut self, rhs: Float) {
         *self = *self / rhs;
     }
 }
@@ -176,7 +195,7 @@ impl ops::Neg for Color {
 }
 
 impl ops::Index<u8> for Color {
-    type Output = f32;
+    type Output = Float;
 
     fn index(&self, index: u8) -> &Self::Output {
         match index {
@@ -195,7 +214,7 @@ pub struct ColorIter {
 }
 
 impl IntoIterator for Color {
-    type Item = f32;
+    type Item = Float;
     type IntoIter = ColorIter;
 
     fn into_iter(self) -> Self::IntoIter {
@@ -207,7 +226,7 @@ impl IntoIterator for Color {
 }
 
 impl Iterator for ColorIter {
-    type Item = f32;
+    type Item = Float;
 
     fn next(&mut self) -> Option<Self::Item> {
         let result = match self.index {
@@ -221,8 +240,8 @@ impl Iterator for ColorIter {
     }
 }
 
-impl FromIterator<f32> for Color {
-    fn from_iter<T: IntoIterator<Item = f32>>(iter: T) -> Self {
+impl FromIterator<Float> for Color {
+    fn from_iter<T: IntoIterator<Item = Float>>(iter: T) -> Self {
         let mut iter = iter.into_iter();
         Self {
             r: iter.next().unwrap(),
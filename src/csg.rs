@@ -0,0 +1,311 @@
+//! Constructive solid geometry (CSG) of closed [`Hittable`]s.
+//!
+//! The combinators [`Union`], [`Intersection`], and [`Difference`] collect all the points where a [`Ray`] crosses the surfaces of their operands, track along the ray whether it is inside each of them, and report the first crossing where the combined solid is entered or left.
+//! This only gives sensible results for closed shapes like [spheres](crate::shapes::Sphere), [cuboids](crate::shapes::Cuboid), or other combinators.
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+use nalgebra::Rotation3;
+
+use crate::hitrecord::HitRecord;
+use crate::hittable::Aabb;
+use crate::ray::Ray;
+use crate::shapes::{Movable, Offset};
+use crate::*;
+
+/// Maximum number of surface crossings collected per operand.
+const MAX_CROSSINGS: usize = 64;
+
+/// Step along the ray after a crossing so that the same surface is not hit again.
+const CROSSING_EPSILON: Float = 1e-4;
+
+/// Collect all crossings of a [`Ray`] with the surface of a [`Hittable`] sorted by their parameter.
+///
+/// A crossing with `front_face == true` enters the object, all others leave it.
+fn crossings<H: Hittable + ?Sized>(hittable: &H, ray: Ray) -> Vec<HitRecord<'_>> {
+    let mut crossings = Vec::new();
+    let mut t = -Float::INFINITY;
+
+    while crossings.len() < MAX_CROSSINGS {
+        match hittable.hit(ray, t, Float::INFINITY) {
+            Some(hit) => {
+                t = hit.t + CROSSING_EPSILON;
+                crossings.push(hit);
+            }
+            None => break,
+        }
+    }
+
+    crossings
+}
+
+/// Find the first crossing of the combined surface of `left` and `right` inside the allowed parameter range.
+///
+/// # Parameters
+/// - `inside`: Whether a point is inside the combined solid given whether it is inside `left` and inside `right`.
+fn csg_hit<'a, A, B>(
+    left: &'a A,
+    right: &'a B,
+    ray: Ray,
+    t_min: Float,
+    t_max: Float,
+    inside: fn(bool, bool) -> bool,
+) -> Option<HitRecord<'a>>
+where
+    A: Hittable + ?Sized,
+    B: Hittable + ?Sized,
+{
+    let mut left_crossings = crossings(left, ray).into_iter().peekable();
+    let mut right_crossings = crossings(right, ray).into_iter().peekable();
+
+    let mut inside_left = false;
+    let mut inside_right = false;
+
+    loop {
+        let from_left = match (left_crossings.peek(), right_crossings.peek()) {
+            (Some(l), Some(r)) => {
+                l.t.partial_cmp(&r.t).unwrap_or(Ordering::Equal) != Ordering::Greater
+            }
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => return None,
+        };
+
+        let was_inside = inside(inside_left, inside_right);
+        let mut hit = if from_left {
+            let hit = left_crossings.next().unwrap();
+            inside_left = hit.front_face;
+            hit
+        } else {
+            let hit = right_crossings.next().unwrap();
+            inside_right = hit.front_face;
+            hit
+        };
+        let is_inside = inside(inside_left, inside_right);
+
+        if hit.t > t_max {
+            return None;
+        }
+        if was_inside != is_inside && hit.t > t_min {
+            // The normal of a `HitRecord` always points against the ray, so only the face has to be corrected.
+            hit.front_face = is_inside;
+            return Some(hit);
+        }
+    }
+}
+
+/// The union of two closed [`Hittable`]s.
+///
+/// A point is inside the union if it lies inside `left` or `right`.
+#[derive(Clone, Debug)]
+pub struct Union<A: Hittable, B: Hittable> {
+    center: Offset,
+    left: A,
+    right: B,
+}
+
+impl<A: Hittable, B: Hittable> Union<A, B> {
+    pub fn new(left: A, right: B) -> Self {
+        Self {
+            center: Offset::default(),
+            left,
+            right,
+        }
+    }
+}
+
+impl<A: Hittable, B: Hittable> Hittable for Union<A, B> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        csg_hit(&self.left, &self.right, ray, t_min, t_max, |l, r| l || r)
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        let left = self.left.bounding_box(time0, time1)?;
+        let right = self.right.bounding_box(time0, time1)?;
+        Some(left.surrounding(&right))
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+}
+
+impl<A: Hittable + Clone, B: Hittable + Clone> Movable for Union<A, B> {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+/// The intersection of two closed [`Hittable`]s.
+///
+/// A point is inside the intersection if it lies inside both `left` and `right`.
+#[derive(Clone, Debug)]
+pub struct Intersection<A: Hittable, B: Hittable> {
+    center: Offset,
+    left: A,
+    right: B,
+}
+
+impl<A: Hittable, B: Hittable> Intersection<A, B> {
+    pub fn new(left: A, right: B) -> Self {
+        Self {
+            center: Offset::default(),
+            left,
+            right,
+        }
+    }
+}
+
+impl<A: Hittable, B: Hittable> Hittable for Intersection<A, B> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        csg_hit(&self.left, &self.right, ray, t_min, t_max, |l, r| l && r)
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        let left = self.left.bounding_box(time0, time1)?;
+        let right = self.right.bounding_box(time0, time1)?;
+        Some(Aabb::new(
+            left.minimum().sup(&right.minimum()),
+            left.maximum().inf(&right.maximum()),
+        ))
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+}
+
+impl<A: Hittable + Clone, B: Hittable + Clone> Movable for Intersection<A, B> {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+/// The difference of two closed [`Hittable`]s.
+///
+/// A point is inside the difference if it lies inside `left`, but not inside `right`. This allows carving holes into objects.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, csg::Difference, materials::Lambertian, ray::Ray, shapes::Sphere};
+/// let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+/// let sphere = Sphere::new(vector![0., 0., 0.], 1., material.clone());
+/// let hole = Sphere::new(vector![0., 0., 1.], 0.5, material);
+/// let carved = Difference::new(sphere, hole);
+///
+/// let ray = Ray::new(vector![0., 0., 5.], vector![0., 0., -1.]);
+/// let hit = carved.hit(ray, 0., Float::INFINITY).unwrap();
+/// assert!((hit.point.z - 0.5).abs() < 1e-3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Difference<A: Hittable, B: Hittable> {
+    center: Offset,
+    left: A,
+    right: B,
+}
+
+impl<A: Hittable, B: Hittable> Difference<A, B> {
+    pub fn new(left: A, right: B) -> Self {
+        Self {
+            center: Offset::default(),
+            left,
+            right,
+        }
+    }
+}
+
+impl<A: Hittable, B: Hittable> Hittable for Difference<A, B> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        csg_hit(&self.left, &self.right, ray, t_min, t_max, |l, r| l && !r)
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.left.bounding_box(time0, time1)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+}
+
+impl<A: Hittable + Clone, B: Hittable + Clone> Movable for Difference<A, B> {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::shapes::Sphere;
+
+    fn spheres() -> (
+        Sphere<Lambertian<textures::SolidColor>>,
+        Sphere<Lambertian<textures::SolidColor>>,
+    ) {
+        let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+        (
+            Sphere::new(vector![-0.5, 0., 0.], 1., material.clone()),
+            Sphere::new(vector![0.5, 0., 0.], 1., material),
+        )
+    }
+
+    #[test]
+    fn union() {
+        let (left, right) = spheres();
+        let union = Union::new(left, right);
+        let ray = Ray::new(vector![5., 0., 0.], vector![-1., 0., 0.]);
+
+        let hit = union.hit(ray, 0., Float::INFINITY).unwrap();
+        assert!((hit.point.x - 1.5).abs() < 1e-3);
+        assert!(hit.front_face);
+
+        let hit = union.hit(ray, hit.t + 1e-3, Float::INFINITY).unwrap();
+        assert!((hit.point.x + 1.5).abs() < 1e-3);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn intersection() {
+        let (left, right) = spheres();
+        let intersection = Intersection::new(left, right);
+        let ray = Ray::new(vector![5., 0., 0.], vector![-1., 0., 0.]);
+
+        let hit = intersection.hit(ray, 0., Float::INFINITY).unwrap();
+        assert!((hit.point.x - 0.5).abs() < 1e-3);
+
+        let miss = Ray::new(vector![1.2, 5., 0.], vector![0., -1., 0.]);
+        assert!(intersection.hit(miss, 0., Float::INFINITY).is_none());
+    }
+
+    #[test]
+    fn difference() {
+        let (left, right) = spheres();
+        let difference = Difference::new(left, right);
+        let ray = Ray::new(vector![5., 0., 0.], vector![-1., 0., 0.]);
+
+        let hit = difference.hit(ray, 0., Float::INFINITY).unwrap();
+        assert!((hit.point.x + 0.5).abs() < 1e-3);
+        assert!(hit.front_face);
+    }
+}
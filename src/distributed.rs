@@ -0,0 +1,328 @@
+//! Rendering an image on several machines.
+//!
+//! A [`Coordinator`] splits the image into tiles and hands them out over TCP to [`Worker`]s, which render them and send the pixels back.
+//! Workers that disconnect lose their tile, which is then given to another worker. The other workers therefore only stop once all tiles are rendered.
+//!
+//! The scene cannot be serialized, as it consists of arbitrary [`Hittable`]s and [`Material`](crate::materials::Material)s.
+//! Instead, every worker builds the scene itself with the same function, which therefore has to be deterministic (e.g. use a seeded random number generator for random scenes).
+//! The shared program is usually started once as coordinator and once per machine as worker, e.g. depending on a command line argument.
+//!
+//! # Protocol
+//! All numbers are little-endian.
+//! 1. The worker sends the width and height of its image (two `u32`).
+//! 2. The coordinator sends a tile as `x0`, `y0`, `x1`, `y1` (four `u32`, see [`RenderRegion`]) or four times `u32::MAX` if there are no tiles left.
+//! 3. The worker sends the colors of the pixels of the tile row by row (three [`Float`]s each, so both sides have to use the same precision) and continues with 2.
+
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::color::BLACK;
+use crate::error::Error;
+use crate::raytracer::{RaytracedImage, RenderRegion};
+use crate::*;
+
+/// Tile that tells a [`Worker`] to stop.
+const DONE: [u32; 4] = [u32::MAX; 4];
+
+/// Hands out tiles of an image to [`Worker`]s and assembles their results.
+///
+/// # Fields
+/// - `image_width`: Width of the image.
+/// - `image_height`: Height of the image.
+/// - `tile_size`: Width and height of the tiles (smaller at the right and bottom edges).
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, distributed::{Coordinator, Worker}, materials::Lambertian, shapes::Sphere};
+/// fn scene() -> Raytracer {
+///     let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+///     let mut raytracer = Raytracer::new(camera, color![0.5, 0.7, 1.], 16, 16, 4, 4);
+///     raytracer.world.push(Sphere::new(vector![0., 0., -1.], 0.5, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+///     raytracer
+/// }
+///
+/// let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// let address = listener.local_addr().unwrap();
+/// let worker = std::thread::spawn(move || Worker::new(scene()).connect(address));
+///
+/// let image = Coordinator::new(16, 16).with_tile_size(8).serve(listener).unwrap();
+/// worker.join().unwrap().unwrap();
+/// assert!(image.into_image().is_some());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Coordinator {
+    image_width: u32,
+    image_height: u32,
+    tile_size: u32,
+}
+
+impl Coordinator {
+    pub fn new(image_width: u32, image_height: u32) -> Self {
+        Self {
+            image_width,
+            image_height,
+            tile_size: 32,
+        }
+    }
+
+    /// Consume `self` and set the width and height of the tiles.
+    pub fn with_tile_size(mut self, tile_size: u32) -> Self {
+        self.tile_size = tile_size.max(1);
+        self
+    }
+
+    /// Split the image into tiles, row by row from the top left.
+    pub fn tiles(&self) -> Vec<RenderRegion> {
+        let mut tiles = Vec::new();
+        for y0 in (0..self.image_height).step_by(self.tile_size as usize) {
+            for x0 in (0..self.image_width).step_by(self.tile_size as usize) {
+                tiles.push(RenderRegion::new(
+                    x0,
+                    y0,
+                    x0.saturating_add(self.tile_size).min(self.image_width),
+                    y0.saturating_add(self.tile_size).min(self.image_height),
+                ));
+            }
+        }
+        tiles
+    }
+
+    /// Accept [`Worker`]s on `listener` until all tiles are rendered and return the image.
+    ///
+    /// Each [`Worker`] is served by its own thread. Workers can connect at any time while tiles are left.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<RaytracedImage> {
+        let tiles = self.tiles();
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                remaining: tiles.len(),
+                pending: tiles.into_iter().rev().collect(),
+                colors: vec![BLACK; self.image_width as usize * self.image_height as usize],
+            }),
+            changed: Condvar::new(),
+        });
+
+        listener.set_nonblocking(true)?;
+        let mut handles = Vec::new();
+        while shared.state.lock().unwrap().remaining > 0 {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    let shared = shared.clone();
+                    let coordinator = self.clone();
+                    handles.push(thread::spawn(move || {
+                        coordinator.serve_worker(stream, &shared)
+                    }));
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        for handle in handles {
+            // Errors of single workers do not matter as long as all tiles were rendered.
+            let _ = handle.join();
+        }
+
+        let colors = std::mem::take(&mut shared.state.lock().unwrap().colors);
+        Ok(RaytracedImage::new(
+            colors,
+            self.image_width,
+            self.image_height,
+        ))
+    }
+
+    /// Hand out tiles to a single [`Worker`] until all are rendered.
+    ///
+    /// While the last tiles are rendered by other workers, the worker waits, so it can take over their tiles if they disconnect.
+    fn serve_worker(&self, stream: TcpStream, shared: &Shared) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+
+        let [width, height] = read_u32s(&mut reader)?;
+        if (width, height) != (self.image_width, self.image_height) {
+            write_u32s(&mut writer, &DONE)?;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("worker renders an image of size {width}x{height}"),
+            ));
+        }
+
+        loop {
+            let Some(tile) = shared.next_tile() else {
+                write_u32s(&mut writer, &DONE)?;
+                return Ok(());
+            };
+            write_u32s(&mut writer, &[tile.x0, tile.y0, tile.x1, tile.y1])?;
+
+            let mut pixels = Vec::with_capacity(tile.pixel_count());
+            let result = (0..tile.pixel_count()).try_for_each(|_| {
+                let [r, g, b] = read_floats(&mut reader)?;
+                pixels.push(color![r, g, b]);
+                Ok::<_, io::Error>(())
+            });
+            if let Err(error) = result {
+                // Give the tile to another worker.
+                shared.state.lock().unwrap().pending.push(tile);
+                shared.changed.notify_all();
+                return Err(error);
+            }
+
+            let mut state = shared.state.lock().unwrap();
+            for (index, color) in pixels.into_iter().enumerate() {
+                let x = tile.x0 as usize + index % tile.width() as usize;
+                let y = tile.y0 as usize + index / tile.width() as usize;
+                state.colors[y * self.image_width as usize + x] = color;
+            }
+            state.remaining -= 1;
+            shared.changed.notify_all();
+        }
+    }
+}
+
+/// [`State`] shared by the threads of a [`Coordinator`], and a [`Condvar`] that is notified when a tile is rendered or returned.
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Shared {
+    /// Take the next pending tile, waiting while the other workers still render some. Returns [`None`] once all tiles are rendered.
+    fn next_tile(&self) -> Option<RenderRegion> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(tile) = state.pending.pop() {
+                return Some(tile);
+            }
+            if state.remaining == 0 {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+}
+
+/// Tiles and pixels of a [`Coordinator`].
+struct State {
+    pending: Vec<RenderRegion>,
+    remaining: usize,
+    colors: Vec<Color>,
+}
+
+/// Renders tiles for a [`Coordinator`].
+///
+/// # Fields
+/// - `raytracer`: The scene, which has to be the same as for all other [`Worker`]s.
+#[derive(Clone, Debug)]
+pub struct Worker {
+    raytracer: Raytracer,
+}
+
+impl Worker {
+    pub fn new(raytracer: Raytracer) -> Self {
+        Self { raytracer }
+    }
+
+    /// Connect to a [`Coordinator`] at `address` and render tiles until it has no more.
+    ///
+    /// The scene is prepared (e.g. its [`Bvh`](crate::hittable::Bvh) is built) once per connection, and only the pixels of the tiles are traced, see [`Raytracer::render_tiles`].
+    pub fn connect<A: ToSocketAddrs>(&self, address: A) -> io::Result<()> {
+        let stream = TcpStream::connect(address)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+
+        let (width, height) = self.raytracer.image_size();
+        write_u32s(&mut writer, &[width, height])?;
+
+        let next_tile = || {
+            let tile = read_u32s(&mut reader)?;
+            let [x0, y0, x1, y1] = tile;
+            Ok((tile != DONE).then(|| RenderRegion::new(x0, y0, x1, y1)))
+        };
+        let write_tile = |tile: RaytracedImage| {
+            for &color in tile.colors() {
+                for c in color {
+                    writer.write_all(&c.to_le_bytes())?;
+                }
+            }
+            writer.flush()?;
+            Ok(())
+        };
+        match self.raytracer.clone().render_tiles(next_tile, write_tile) {
+            Ok(_) => Ok(()),
+            Err(Error::Io(error)) => Err(error),
+            Err(error) => Err(io::Error::other(error)),
+        }
+    }
+}
+
+fn write_u32s<W: Write>(writer: &mut W, values: &[u32]) -> io::Result<()> {
+    for value in values {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+fn read_u32s<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u32; N]> {
+    let mut values = [0; N];
+    for value in &mut values {
+        let mut bytes = [0; 4];
+        reader.read_exact(&mut bytes)?;
+        *value = u32::from_le_bytes(bytes);
+    }
+    Ok(values)
+}
+
+fn read_floats<R: Read, const N: usize>(reader: &mut R) -> io::Result<[Float; N]> {
+    let mut values = [0.; N];
+    for value in &mut values {
+        let mut bytes = [0; std::mem::size_of::<Float>()];
+        reader.read_exact(&mut bytes)?;
+        *value = Float::from_le_bytes(bytes);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn requeue_tile_of_disconnected_worker() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let coordinator = thread::spawn(move || {
+            Coordinator::new(4, 4)
+                .with_tile_size(4)
+                .serve(listener)
+                .unwrap()
+        });
+
+        // The first worker takes the only tile and disconnects after the second one asked for a tile.
+        let (took_tile, took_tile_receiver) = mpsc::channel();
+        let (disconnect, disconnect_receiver) = mpsc::channel::<()>();
+        let dying = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            write_u32s(&mut stream, &[4, 4]).unwrap();
+            assert_eq!(read_u32s(&mut stream).unwrap(), [0, 0, 4, 4]);
+            took_tile.send(()).unwrap();
+            disconnect_receiver.recv().unwrap();
+        });
+        took_tile_receiver.recv().unwrap();
+
+        let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 4, 4, 1, 1);
+        let worker = thread::spawn(move || Worker::new(raytracer).connect(address));
+        thread::sleep(Duration::from_millis(100));
+        disconnect.send(()).unwrap();
+        dying.join().unwrap();
+
+        worker.join().unwrap().unwrap();
+        let image = coordinator.join().unwrap();
+        assert!(image.colors().iter().all(|&color| color != BLACK));
+    }
+}
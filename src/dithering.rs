@@ -0,0 +1,203 @@
+//! Dithering when quantizing images to 8 bits per channel.
+//!
+//! Smooth gradients like the [`Sky`](crate::background::Sky) show visible bands when every pixel is rounded to one of the 256 levels.
+//! Dithering adds a threshold pattern below one level before rounding down, so neighboring pixels alternate between the two closest levels and the average matches the exact color.
+//! The pattern is repeated over the image, so the result is the same for every save.
+
+use std::sync::OnceLock;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::*;
+
+/// Side length of the tile of blue noise.
+const BLUE_NOISE_SIZE: usize = 64;
+
+/// Standard deviation of the Gaussian the void-and-cluster method measures the density of points with.
+const BLUE_NOISE_SIGMA: Float = 1.5;
+
+/// How images are dithered when they are quantized to 8 bits per channel, see [`RaytracedImage::with_dithering`](crate::raytracer::RaytracedImage::with_dithering).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dithering {
+    /// Round every pixel down, without dithering.
+    #[default]
+    None,
+    /// Ordered dithering with an 8 × 8 Bayer matrix, which leaves a regular cross-hatch pattern.
+    Ordered,
+    /// Dithering with a 64 × 64 tile of blue noise, which is hardly noticeable since it has no low frequencies.
+    BlueNoise,
+}
+
+impl Dithering {
+    /// Threshold in \[0, 1) that is added at pixel (`x`, `y`) before rounding down.
+    pub(crate) fn threshold(self, x: u32, y: u32) -> Float {
+        match self {
+            Dithering::None => 0.,
+            Dithering::Ordered => (bayer(x % 8, y % 8) as Float + 0.5) / 64.,
+            Dithering::BlueNoise => {
+                let (x, y) = (x as usize % BLUE_NOISE_SIZE, y as usize % BLUE_NOISE_SIZE);
+                let rank = blue_noise()[y * BLUE_NOISE_SIZE + x];
+                (rank as Float + 0.5) / (BLUE_NOISE_SIZE * BLUE_NOISE_SIZE) as Float
+            }
+        }
+    }
+
+    /// Quantize the color channel `c` in \[0, 1\] at pixel (`x`, `y`) to one of the 256 levels `k / 255`.
+    pub(crate) fn quantize(self, c: Float, x: u32, y: u32) -> Float {
+        match self {
+            Dithering::None => c,
+            _ => {
+                (255. * c.clamp(0., 1.) + self.threshold(x, y))
+                    .floor()
+                    .min(255.)
+                    / 255.
+            }
+        }
+    }
+}
+
+/// Entry of the 8 × 8 Bayer matrix, from 0 to 63.
+fn bayer(x: u32, y: u32) -> u32 {
+    // Interleave the bits of x ^ y and y, from the lowest level of the recursion to the highest.
+    let xor = x ^ y;
+    let mut rank = 0;
+    for bit in 0..3 {
+        rank |= ((xor >> bit) & 1) << (5 - 2 * bit);
+        rank |= ((y >> bit) & 1) << (4 - 2 * bit);
+    }
+    rank
+}
+
+/// Ranks of the pixels of a tileable blue noise pattern, generated once with the void-and-cluster method (Ulichney, 1993).
+fn blue_noise() -> &'static [u16] {
+    static BLUE_NOISE: OnceLock<Vec<u16>> = OnceLock::new();
+    BLUE_NOISE.get_or_init(|| VoidAndCluster::new(BLUE_NOISE_SIZE).ranks())
+}
+
+/// State of the void-and-cluster method on a toroidal grid.
+///
+/// # Fields
+/// - `size`: Side length of the grid.
+/// - `kernel`: Gaussian weight of every offset on the grid.
+/// - `points`: Whether each pixel is set.
+/// - `energy`: Sum of the weights of all set pixels at each pixel.
+struct VoidAndCluster {
+    size: usize,
+    kernel: Vec<Float>,
+    points: Vec<bool>,
+    energy: Vec<Float>,
+}
+
+impl VoidAndCluster {
+    fn new(size: usize) -> Self {
+        let kernel = (0..size * size)
+            .map(|index| {
+                // Shortest offset on the torus
+                let distance = |d: usize| d.min(size - d) as Float;
+                let (dx, dy) = (distance(index % size), distance(index / size));
+                (-(dx * dx + dy * dy) / (2. * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp()
+            })
+            .collect();
+        Self {
+            size,
+            kernel,
+            points: vec![false; size * size],
+            energy: vec![0.; size * size],
+        }
+    }
+
+    /// Set or clear the pixel at `index` and update the energy.
+    fn toggle(&mut self, index: usize) {
+        self.points[index] = !self.points[index];
+        let sign = if self.points[index] { 1. } else { -1. };
+        let (x, y) = (index % self.size, index / self.size);
+        for (other, energy) in self.energy.iter_mut().enumerate() {
+            let dx = (other % self.size + self.size - x) % self.size;
+            let dy = (other / self.size + self.size - y) % self.size;
+            *energy += sign * self.kernel[dy * self.size + dx];
+        }
+    }
+
+    /// The set pixel with the highest energy (`set`) or the clear pixel with the lowest energy.
+    fn extreme(&self, set: bool) -> usize {
+        let candidates = (0..self.points.len()).filter(|&index| self.points[index] == set);
+        let energy = |index: &usize| self.energy[*index];
+        if set {
+            candidates.max_by(|a, b| energy(a).total_cmp(&energy(b)))
+        } else {
+            candidates.min_by(|a, b| energy(a).total_cmp(&energy(b)))
+        }
+        .expect("the grid has set and clear pixels")
+    }
+
+    /// Rank every pixel by the order in which it is added to the pattern.
+    fn ranks(mut self) -> Vec<u16> {
+        let count = self.points.len();
+        let initial = count / 10;
+        let mut rng = StdRng::seed_from_u64(0);
+        while self.points.iter().filter(|&&point| point).count() < initial {
+            let index = rng.gen_range(0..count);
+            if !self.points[index] {
+                self.toggle(index);
+            }
+        }
+
+        // Move points from the tightest cluster into the largest void until that does not change anything.
+        loop {
+            let cluster = self.extreme(true);
+            self.toggle(cluster);
+            let void = self.extreme(false);
+            if void == cluster {
+                self.toggle(void);
+                break;
+            }
+            self.toggle(void);
+        }
+
+        let mut ranks = vec![0; count];
+        let (initial_points, initial_energy) = (self.points.clone(), self.energy.clone());
+        // The initial points are ranked by removing the tightest clusters first,
+        for rank in (0..initial).rev() {
+            let cluster = self.extreme(true);
+            self.toggle(cluster);
+            ranks[cluster] = rank as u16;
+        }
+        // and the other points by filling the largest voids.
+        (self.points, self.energy) = (initial_points, initial_energy);
+        for rank in initial..count {
+            let void = self.extreme(false);
+            self.toggle(void);
+            ranks[void] = rank as u16;
+        }
+        ranks
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn patterns_are_permutations() {
+        let mut bayer_ranks: Vec<u32> = (0..64).map(|index| bayer(index % 8, index / 8)).collect();
+        assert_eq!(&bayer_ranks[..4], &[0, 32, 8, 40]);
+        bayer_ranks.sort();
+        assert!(bayer_ranks.iter().copied().eq(0..64));
+
+        let mut blue_noise_ranks = blue_noise().to_vec();
+        blue_noise_ranks.sort();
+        assert!(blue_noise_ranks.iter().copied().eq(0..4096));
+
+        // The average of the dithered levels of a tile is the exact color.
+        for dithering in [Dithering::Ordered, Dithering::BlueNoise] {
+            let c = 100.3 / 255.;
+            let average = (0..64 * 64)
+                .map(|index| dithering.quantize(c, index % 64, index / 64))
+                .sum::<Float>()
+                / 4096.;
+            assert!((average - c).abs() < 1e-4, "{dithering:?}");
+        }
+        assert_eq!(Dithering::None.quantize(0.3, 1, 2), 0.3);
+    }
+}
@@ -0,0 +1,59 @@
+//! Crate-wide [`Error`] type returned by rendering and saving.
+
+use std::{fmt, io};
+
+use image::ImageError;
+
+use crate::hittable::BoundingBoxError;
+
+/// Errors that can occur while setting up a render, rendering, or saving the result.
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid settings, e.g. an image without pixels.
+    Config(String),
+    /// Reading or writing a file or stream failed.
+    Io(io::Error),
+    /// Encoding the image failed.
+    Image(ImageError),
+    /// A [`Hittable`](crate::Hittable) that has to be encompassed by an [`Aabb`](crate::hittable::Aabb) cannot be.
+    BoundingBox(BoundingBoxError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Config(message) => write!(f, "invalid configuration: {message}"),
+            Error::Io(error) => write!(f, "IO error: {error}"),
+            Error::Image(error) => write!(f, "image error: {error}"),
+            Error::BoundingBox(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Config(_) | Error::BoundingBox(_) => None,
+            Error::Io(error) => Some(error),
+            Error::Image(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<ImageError> for Error {
+    fn from(error: ImageError) -> Self {
+        Error::Image(error)
+    }
+}
+
+impl From<BoundingBoxError> for Error {
+    fn from(error: BoundingBoxError) -> Self {
+        Error::BoundingBox(error)
+    }
+}
@@ -0,0 +1,266 @@
+//! Reconstruction filters that weight the samples of a pixel and its neighbors.
+//!
+//! With the default [`Box`](Filter::Box) filter, every sample only counts for the pixel it was taken in.
+//! Wider filters splat each sample into all pixels whose centers are within their radius, weighted by the distance, which gives smoother edges.
+
+use crate::color::BLACK;
+use crate::float::consts::PI;
+use crate::passes::SampleImage;
+use crate::raytracer::RenderRegion;
+use crate::*;
+
+/// Reconstruction filter of the pixels.
+///
+/// The `radius` is measured in pixels. All filters except [`Box`](Filter::Box) are separable, i.e. the product of the weights in x and y direction.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, filter::Filter};
+/// let filter = Filter::Gaussian { radius: 1.5, alpha: 2. };
+/// assert_eq!(filter.radius(), 1.5);
+/// assert!(filter.evaluate(0., 0.) > filter.evaluate(1., 0.));
+/// assert_eq!(filter.evaluate(2., 0.), 0.);
+///
+/// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 16, 9, 4, 4).with_filter(filter);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Filter {
+    /// Equal weight for all samples inside the pixel.
+    #[default]
+    Box,
+    /// Weight falling off linearly to 0 at `radius` (1 is a good choice).
+    Triangle { radius: Float },
+    /// Gaussian with falloff `alpha`, shifted to reach 0 at `radius` (e.g. 1.5 and 2).
+    Gaussian { radius: Float, alpha: Float },
+    /// Mitchell-Netravali cubic with parameters `b` and `c` (e.g. a radius of 2 and 1/3 for both).
+    ///
+    /// It has small negative lobes, which sharpen edges but can cause ringing.
+    MitchellNetravali { radius: Float, b: Float, c: Float },
+    /// Four-term Blackman-Harris window (e.g. with a radius of 1.5), similar to a Gaussian with less blur.
+    BlackmanHarris { radius: Float },
+}
+
+impl Filter {
+    /// Distance from the pixel center in pixels up to which samples are counted.
+    pub fn radius(&self) -> Float {
+        match *self {
+            Filter::Box => 0.5,
+            Filter::Triangle { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::MitchellNetravali { radius, .. }
+            | Filter::BlackmanHarris { radius } => radius,
+        }
+    }
+
+    /// Weight of a sample at the offset (`x`, `y`) in pixels from the pixel center.
+    pub fn evaluate(&self, x: Float, y: Float) -> Float {
+        if let Filter::Box = self {
+            // Half-open, so that a sample on the border only counts for one pixel.
+            return if (-0.5..0.5).contains(&x) && (-0.5..0.5).contains(&y) {
+                1.
+            } else {
+                0.
+            };
+        }
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    /// Weight in one direction of the separable filters.
+    fn evaluate_1d(&self, x: Float) -> Float {
+        let radius = self.radius();
+        let x = x.abs();
+        if x > radius {
+            return 0.;
+        }
+
+        match *self {
+            Filter::Box => 1.,
+            Filter::Triangle { radius } => 1. - x / radius,
+            Filter::Gaussian { radius, alpha } => {
+                ((-alpha * x * x).exp() - (-alpha * radius * radius).exp()).max(0.)
+            }
+            Filter::MitchellNetravali { radius, b, c } => {
+                // The cubic is defined on [0,2].
+                let x = 2. * x / radius;
+                if x < 1. {
+                    ((12. - 9. * b - 6. * c) * x.powi(3)
+                        + (-18. + 12. * b + 6. * c) * x.powi(2)
+                        + (6. - 2. * b))
+                        / 6.
+                } else {
+                    ((-b - 6. * c) * x.powi(3)
+                        + (6. * b + 30. * c) * x.powi(2)
+                        + (-12. * b - 48. * c) * x
+                        + (8. * b + 24. * c))
+                        / 6.
+                }
+            }
+            Filter::BlackmanHarris { radius } => {
+                let t = 2. * PI * (x / (2. * radius) + 0.5);
+                0.35875 - 0.48829 * t.cos() + 0.14128 * (2. * t).cos() - 0.01168 * (3. * t).cos()
+            }
+        }
+    }
+}
+
+/// Weighted sums of the samples splatted into the pixels of a render.
+///
+/// # Fields
+/// - `filter`: [`Filter`] that weights the samples.
+/// - `sums`: Weighted sum of the samples of each pixel, row by row from the top.
+/// - `weights`: Sum of the weights of each pixel.
+/// - `image_width`: Width of the image.
+/// - `image_height`: Height of the image.
+/// - `region`: [`RenderRegion`] outside of which the pixels stay black.
+/// - `moments`: Number of samples taken in each pixel and the sum and sum of squares of their luminance, if they are recorded for a [`SampleImage`].
+pub(crate) struct Film {
+    filter: Filter,
+    sums: Vec<Color>,
+    weights: Vec<Float>,
+    image_width: u32,
+    image_height: u32,
+    region: RenderRegion,
+    moments: Option<Vec<(u32, Float, Float)>>,
+}
+
+impl Film {
+    pub fn new(
+        filter: Filter,
+        (image_width, image_height): (u32, u32),
+        region: RenderRegion,
+    ) -> Self {
+        let pixel_count = image_width as usize * image_height as usize;
+        Self {
+            filter,
+            sums: vec![BLACK; pixel_count],
+            weights: vec![0.; pixel_count],
+            image_width,
+            image_height,
+            region,
+            moments: None,
+        }
+    }
+
+    /// Consume `self` and record the [`SampleImage`] of the samples if `enabled`.
+    pub fn with_statistics(mut self, enabled: bool) -> Self {
+        if enabled {
+            self.moments = Some(vec![(0, 0., 0.); self.sums.len()]);
+        }
+        self
+    }
+
+    /// Splat a sample taken in the pixel with `index` (row by row from the top) into it and its neighbors.
+    ///
+    /// `offset` is the position of the sample inside the pixel in \[0,1\)², with the second coordinate pointing up like the camera's `v`.
+    pub fn add_sample(&mut self, index: usize, offset: (Float, Float), color: Color) {
+        if let Some(moments) = &mut self.moments {
+            let luminance = color.luminance();
+            let (count, sum, squares) = &mut moments[index];
+            *count += 1;
+            *sum += luminance;
+            *squares += luminance * luminance;
+        }
+
+        let width = self.image_width as i64;
+        let height = self.image_height as i64;
+        let i = index as i64 % width;
+        let row = index as i64 / width;
+        // Offset from the center of the own pixel. Everything is relative to it, so that large pixel coordinates do not lose precision.
+        let x = offset.0 - 0.5;
+        let y = offset.1 - 0.5;
+        let radius = self.filter.radius();
+        let range =
+            |offset: Float| (offset - radius).ceil() as i64..=(offset + radius).floor() as i64;
+
+        for di in range(x) {
+            // The second coordinate of the offset points up, i.e. to the previous row.
+            for dj in range(y) {
+                let (pixel_i, pixel_row) = (i + di, row - dj);
+                if !(0..width).contains(&pixel_i)
+                    || !(0..height).contains(&pixel_row)
+                    || !self.region.contains(pixel_i as u32, pixel_row as u32)
+                {
+                    continue;
+                }
+                let weight = self.filter.evaluate(x - di as Float, y - dj as Float);
+                if weight != 0. {
+                    let pixel = (pixel_row * width + pixel_i) as usize;
+                    self.sums[pixel] += weight * color;
+                    self.weights[pixel] += weight;
+                }
+            }
+        }
+    }
+
+    /// Number of samples and variance of each pixel, if they are recorded.
+    pub fn sample_image(&self) -> Option<SampleImage> {
+        let moments = self.moments.as_ref()?;
+        Some(SampleImage::from_moments(
+            moments,
+            self.image_width,
+            self.image_height,
+        ))
+    }
+
+    /// Weighted mean of each pixel, row by row from the top.
+    pub fn into_colors(self) -> Vec<Color> {
+        self.sums
+            .into_iter()
+            .zip(self.weights)
+            .map(
+                |(sum, weight)| {
+                    if weight > 0. {
+                        sum / weight
+                    } else {
+                        BLACK
+                    }
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn box_stays_in_pixel() {
+        let mut film = Film::new(Filter::Box, (3, 3), RenderRegion::new(0, 0, 3, 3));
+        film.add_sample(4, (0., 0.), color![1., 1., 1.]);
+        film.add_sample(4, (0.999, 0.999), color![0., 0., 0.]);
+        let colors = film.into_colors();
+        assert_eq!(colors[4], color![0.5, 0.5, 0.5]);
+        assert!(colors
+            .iter()
+            .enumerate()
+            .all(|(index, &color)| index == 4 || color == BLACK));
+    }
+
+    #[test]
+    fn wide_filters_reach_neighbors() {
+        for filter in [
+            Filter::Triangle { radius: 1. },
+            Filter::Gaussian {
+                radius: 1.5,
+                alpha: 2.,
+            },
+            Filter::MitchellNetravali {
+                radius: 2.,
+                b: 1. / 3.,
+                c: 1. / 3.,
+            },
+            Filter::BlackmanHarris { radius: 1.5 },
+        ] {
+            assert!(filter.evaluate(0., 0.) > 0.);
+            assert_eq!(filter.evaluate(filter.radius() + 0.1, 0.), 0.);
+
+            // Only the top right pixel is in the region, but it receives the sample taken next to it.
+            let mut film = Film::new(filter, (3, 3), RenderRegion::new(2, 0, 3, 1));
+            film.add_sample(1, (0.9, 0.5), color![1., 1., 1.]);
+            let colors = film.into_colors();
+            assert_eq!(colors[2], color![1., 1., 1.]);
+            assert_eq!(colors[1], BLACK);
+        }
+    }
+}
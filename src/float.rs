@@ -0,0 +1,19 @@
+//! Floating point type used for all computations.
+//!
+//! [`Float`] is [`f32`] by default. With the feature `f64`, it is [`f64`], which avoids self-intersection acne and precision problems with large coordinates in big scenes at the cost of speed and memory.
+
+/// Floating point type used for all computations.
+#[cfg(not(feature = "f64"))]
+pub type Float = f32;
+
+/// Floating point type used for all computations.
+#[cfg(feature = "f64")]
+pub type Float = f64;
+
+/// Mathematical constants for [`Float`].
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+
+/// Mathematical constants for [`Float`].
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
@@ -0,0 +1,271 @@
+//! Interactive exploration of a scene with a first-person camera.
+//!
+//! The crate does not open windows itself, so the input (e.g. WASD and the mouse) comes from whatever shows the frames, e.g. a window of `minifb` or `winit`, or a canvas in the browser.
+//! Every frame, the window passes its input to [`FlyThrough::step`], which renders a quick preview while the camera moves and accumulates more and more samples once it stops.
+//!
+//! # Example
+//! ```
+//! # use ray_tracing_in_one_weekend::{*, flythrough::{FlyCamera, FlyInput, FlyThrough}, shapes::Sphere, materials::Lambertian};
+//! let camera = FlyCamera::new(vector![0., 0., 3.], vector![0., 0., 0.], float::consts::FRAC_PI_4, 1.);
+//! let mut flythrough = FlyThrough::new(camera, 16, 16, |camera, samples_per_pixel| {
+//!     let mut raytracer = Raytracer::new(camera, color![0.5, 0.7, 1.], 16, 16, samples_per_pixel, 4);
+//!     raytracer.world.push(Sphere::new(vector![0., 0., 0.], 1., Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+//!     raytracer
+//! });
+//!
+//! // Holding W for a frame of 1/30 s: a preview
+//! let input = FlyInput { forward: 1., ..Default::default() };
+//! flythrough.step(&input, 1. / 30.).unwrap();
+//! assert!(flythrough.is_moving());
+//! // Releasing all keys: the samples accumulate
+//! flythrough.step(&FlyInput::default(), 1. / 30.).unwrap();
+//! flythrough.step(&FlyInput::default(), 1. / 30.).unwrap();
+//! assert_eq!(flythrough.sample_count(), 16);
+//! ```
+
+use crate::error::Error;
+use crate::framebuffer::Framebuffer;
+use crate::raytracer::RaytracedImage;
+use crate::*;
+
+/// Input of one frame, e.g. from the keyboard and mouse.
+///
+/// # Fields
+/// - `forward`: Movement along the view direction between -1 (S) and 1 (W).
+/// - `right`: Sideways movement between -1 (A) and 1 (D).
+/// - `up`: Vertical movement between -1 (Q) and 1 (E).
+/// - `yaw`: Horizontal mouse movement, positive to the right.
+/// - `pitch`: Vertical mouse movement, positive upwards.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FlyInput {
+    pub forward: Float,
+    pub right: Float,
+    pub up: Float,
+    pub yaw: Float,
+    pub pitch: Float,
+}
+
+impl FlyInput {
+    /// Whether the input moves or turns the camera.
+    pub fn is_idle(&self) -> bool {
+        [self.forward, self.right, self.up, self.yaw, self.pitch]
+            .iter()
+            .all(|&value| value == 0.)
+    }
+}
+
+/// A first-person camera with a position and a view direction given by yaw and pitch, where y points up.
+///
+/// # Fields
+/// - `position`: Position of the camera.
+/// - `yaw`: Angle around the y axis, 0 looking along -z.
+/// - `pitch`: Angle above the horizon.
+/// - `speed`: Distance moved per second at full input.
+/// - `sensitivity`: Angle turned per unit of mouse movement.
+/// - `vertical_fov`: Vertical field of view, see [`Camera::new`].
+/// - `aspect_ratio`: Aspect ratio of the image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlyCamera {
+    position: Vector3<Float>,
+    yaw: Float,
+    pitch: Float,
+    speed: Float,
+    sensitivity: Float,
+    vertical_fov: Float,
+    aspect_ratio: Float,
+}
+
+impl FlyCamera {
+    /// Create a camera at `position` looking at `lookat`, moving 1 unit per second and turning 0.005 radians per unit of mouse movement.
+    pub fn new(
+        position: Vector3<Float>,
+        lookat: Vector3<Float>,
+        vertical_fov: Float,
+        aspect_ratio: Float,
+    ) -> Self {
+        let direction = (lookat - position).normalize();
+        Self {
+            position,
+            yaw: direction.x.atan2(-direction.z),
+            pitch: direction.y.clamp(-1., 1.).asin(),
+            speed: 1.,
+            sensitivity: 0.005,
+            vertical_fov,
+            aspect_ratio,
+        }
+        .clamped()
+    }
+
+    /// Consume `self` and set the distance moved per second.
+    pub fn with_speed(mut self, speed: Float) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Consume `self` and set the angle turned per unit of mouse movement.
+    pub fn with_sensitivity(mut self, sensitivity: Float) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+
+    pub fn position(&self) -> Vector3<Float> {
+        self.position
+    }
+
+    /// Unit view direction.
+    pub fn direction(&self) -> Vector3<Float> {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        vector![sin_yaw * cos_pitch, sin_pitch, -cos_yaw * cos_pitch]
+    }
+
+    /// Apply the `input` of a frame that took `seconds` and return whether the camera changed.
+    pub fn update(&mut self, input: &FlyInput, seconds: Float) -> bool {
+        if input.is_idle() {
+            return false;
+        }
+        self.yaw += input.yaw * self.sensitivity;
+        self.pitch += input.pitch * self.sensitivity;
+        *self = self.clone().clamped();
+
+        let forward = self.direction();
+        let right = vector![self.yaw.cos(), 0., self.yaw.sin()];
+        let movement = input.forward * forward + input.right * right + input.up * Vector3::y();
+        self.position += self.speed * seconds * movement;
+        true
+    }
+
+    /// The [`Camera`] for rendering, without depth of field.
+    pub fn camera(&self) -> Camera {
+        Camera::new(
+            self.position,
+            self.position + self.direction(),
+            Vector3::y(),
+            self.vertical_fov,
+            self.aspect_ratio,
+            0.,
+            1.,
+        )
+    }
+
+    /// Keep the pitch away from the poles, where the up direction is parallel to the view direction.
+    fn clamped(mut self) -> Self {
+        let limit = float::consts::FRAC_PI_2 - 0.01;
+        self.pitch = self.pitch.clamp(-limit, limit);
+        self
+    }
+}
+
+/// Renders frames of a scene while a [`FlyCamera`] explores it.
+///
+/// While the camera moves, every frame is a fresh render with few samples. Once it stops, the frames are accumulated in a [`Framebuffer`] until `max_samples` are reached.
+///
+/// # Fields
+/// - `camera`: The [`FlyCamera`] driven by the input.
+/// - `scene`: Creates a [`Raytracer`] with the given [`Camera`] and samples per pixel. Its image has to be `image_width` × `image_height` pixels.
+/// - `framebuffer`: Accumulated samples since the camera stopped.
+/// - `preview_samples`: Samples per pixel while moving (1 by default).
+/// - `refine_samples`: Samples per pixel added by every frame while still (8 by default).
+/// - `max_samples`: Samples per pixel after which refining stops (1024 by default).
+/// - `moving`: Whether the last frame moved the camera.
+pub struct FlyThrough<F: FnMut(Camera, u16) -> Raytracer> {
+    camera: FlyCamera,
+    scene: F,
+    framebuffer: Framebuffer,
+    preview_samples: u16,
+    refine_samples: u16,
+    max_samples: u32,
+    moving: bool,
+}
+
+impl<F: FnMut(Camera, u16) -> Raytracer> FlyThrough<F> {
+    pub fn new(camera: FlyCamera, image_width: u32, image_height: u32, scene: F) -> Self {
+        Self {
+            camera,
+            scene,
+            framebuffer: Framebuffer::new(image_width, image_height),
+            preview_samples: 1,
+            refine_samples: 8,
+            max_samples: 1024,
+            moving: false,
+        }
+    }
+
+    /// Consume `self` and set the samples per pixel while moving and added per frame while still.
+    pub fn with_samples(mut self, preview_samples: u16, refine_samples: u16) -> Self {
+        self.preview_samples = preview_samples;
+        self.refine_samples = refine_samples;
+        self
+    }
+
+    /// Consume `self` and set the samples per pixel after which refining stops.
+    pub fn with_max_samples(mut self, max_samples: u32) -> Self {
+        self.max_samples = max_samples;
+        self
+    }
+
+    pub fn camera(&self) -> &FlyCamera {
+        &self.camera
+    }
+
+    /// Whether the last frame moved the camera.
+    pub fn is_moving(&self) -> bool {
+        self.moving
+    }
+
+    /// Samples per pixel accumulated since the camera stopped.
+    pub fn sample_count(&self) -> u32 {
+        match self.framebuffer.dimensions() {
+            (0, _) | (_, 0) => 0,
+            _ => self.framebuffer.sample_count(0, 0),
+        }
+    }
+
+    /// Apply the `input` of a frame that took `seconds` and render the next frame.
+    ///
+    /// When the accumulated samples reached `max_samples`, the frame is returned without rendering.
+    pub fn step(&mut self, input: &FlyInput, seconds: Float) -> Result<RaytracedImage, Error> {
+        let (image_width, image_height) = self.framebuffer.dimensions();
+        self.moving = self.camera.update(input, seconds);
+        if self.moving {
+            self.framebuffer = Framebuffer::new(image_width, image_height);
+            return (self.scene)(self.camera.camera(), self.preview_samples).render();
+        }
+
+        if self.sample_count() < self.max_samples {
+            let frame = (self.scene)(self.camera.camera(), self.refine_samples).render()?;
+            self.framebuffer.accumulate(&frame)?;
+        }
+        Ok(self.framebuffer.to_image())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fly_camera() {
+        let mut camera =
+            FlyCamera::new(vector![0., 0., 0.], vector![1., 0., 0.], 1., 1.).with_speed(2.);
+        assert!((camera.direction() - vector![1., 0., 0.]).norm() < 1e-5);
+
+        // Forward for half a second and to the right
+        let input = FlyInput {
+            forward: 1.,
+            right: 1.,
+            ..Default::default()
+        };
+        assert!(camera.update(&input, 0.5));
+        assert!((camera.position() - vector![1., 0., 1.]).norm() < 1e-5);
+
+        // Looking straight up is limited.
+        let input = FlyInput {
+            pitch: 1000.,
+            ..Default::default()
+        };
+        camera.update(&input, 0.);
+        assert!(camera.direction().y < 1.);
+        assert!(!camera.update(&FlyInput::default(), 1.));
+    }
+}
@@ -0,0 +1,145 @@
+//! Linear HDR framebuffer that keeps the sum of the samples of each pixel.
+//!
+//! Unlike an 8 bit image, a [`Framebuffer`] loses no information: it can be displayed with a different [exposure](RaytracedImage::with_exposure) or gamma, saved as an [OpenEXR](Framebuffer::save_exr) image, or refined by [accumulating](Framebuffer::accumulate) further renders of the same scene.
+
+use std::path::Path;
+
+use crate::color::BLACK;
+use crate::error::Error;
+use crate::raytracer::RaytracedImage;
+use crate::*;
+
+/// Sums of the linear radiance and sample counts of all pixels.
+///
+/// The pixels are stored row by row from the top, like in [`RaytracedImage::colors`].
+///
+/// # Fields
+/// - `sums`: Sum of the radiance of all samples of each pixel.
+/// - `samples`: Number of samples of each pixel.
+/// - `image_width`: Width of the image.
+/// - `image_height`: Height of the image.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, scenes::{self, SceneSettings}};
+/// let settings = SceneSettings::new(32, 18).with_samples(4, 4);
+/// let mut framebuffer = scenes::cornell_box(&settings).render().unwrap().into_framebuffer();
+///
+/// // Continue sampling with another render of the same scene
+/// let more = scenes::cornell_box(&settings.with_samples(12, 4)).render().unwrap();
+/// framebuffer.accumulate(&more).unwrap();
+/// assert_eq!(framebuffer.sample_count(0, 0), 16);
+///
+/// // Display it brighter without rendering it again
+/// let image = framebuffer.to_image().with_exposure(1.).into_image().unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    sums: Vec<Color>,
+    samples: Vec<u32>,
+    image_width: u32,
+    image_height: u32,
+}
+
+impl Framebuffer {
+    /// Create an empty [`Framebuffer`] without samples.
+    pub fn new(image_width: u32, image_height: u32) -> Self {
+        let pixel_count = image_width as usize * image_height as usize;
+        Self {
+            sums: vec![BLACK; pixel_count],
+            samples: vec![0; pixel_count],
+            image_width,
+            image_height,
+        }
+    }
+
+    /// Width and height of the image.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.image_width, self.image_height)
+    }
+
+    /// Index of the pixel (`x`, `y`) with `y` counted from the top.
+    ///
+    /// # Panics
+    /// If the pixel is outside of the image.
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(
+            x < self.image_width && y < self.image_height,
+            "pixel ({x}, {y}) is outside of the image"
+        );
+        y as usize * self.image_width as usize + x as usize
+    }
+
+    /// Add a single sample with radiance `color` to the pixel (`x`, `y`).
+    ///
+    /// # Panics
+    /// If the pixel is outside of the image.
+    pub fn add_sample(&mut self, x: u32, y: u32, color: Color) {
+        let index = self.index(x, y);
+        self.sums[index] += color;
+        self.samples[index] += 1;
+    }
+
+    /// Mean radiance of the pixel (`x`, `y`), or black if it has no samples.
+    ///
+    /// # Panics
+    /// If the pixel is outside of the image.
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let index = self.index(x, y);
+        mean(self.sums[index], self.samples[index])
+    }
+
+    /// Number of samples of the pixel (`x`, `y`).
+    ///
+    /// # Panics
+    /// If the pixel is outside of the image.
+    pub fn sample_count(&self, x: u32, y: u32) -> u32 {
+        self.samples[self.index(x, y)]
+    }
+
+    /// Add all samples of a render of the same size, weighted by its [`samples_per_pixel`](RaytracedImage::samples_per_pixel).
+    pub fn accumulate(&mut self, image: &RaytracedImage) -> Result<(), Error> {
+        if image.dimensions() != self.dimensions() {
+            return Err(Error::Config(format!(
+                "cannot accumulate an image of size {:?} into a framebuffer of size {:?}",
+                image.dimensions(),
+                self.dimensions()
+            )));
+        }
+
+        let region = image.render_region();
+        let samples = image.samples_per_pixel() as u32;
+        for (index, &color) in image.colors().iter().enumerate() {
+            if region.contains_index(index, self.image_width) {
+                self.sums[index] += color * samples as Float;
+                self.samples[index] += samples;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mean radiance of all pixels as a [`RaytracedImage`], which can then be tone mapped and saved.
+    pub fn to_image(&self) -> RaytracedImage {
+        let colors = self
+            .sums
+            .iter()
+            .zip(&self.samples)
+            .map(|(&sum, &samples)| mean(sum, samples))
+            .collect();
+        RaytracedImage::new(colors, self.image_width, self.image_height)
+    }
+
+    /// Save the mean linear radiance as an OpenEXR image.
+    pub fn save_exr<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.to_image().save_exr(path)
+    }
+}
+
+/// Mean of `samples` samples with a total of `sum`.
+fn mean(sum: Color, samples: u32) -> Color {
+    if samples == 0 {
+        BLACK
+    } else {
+        sum / samples as Float
+    }
+}
@@ -0,0 +1,683 @@
+//! Import of glTF 2.0 scenes (`.gltf` and `.glb` files), which requires the feature `gltf`.
+//!
+//! Triangle meshes with their normals and texture coordinates, node transforms, perspective cameras, and the metallic-roughness materials are imported.
+//! The materials are mapped onto the closest materials of this crate:
+//! - an emissive factor that is not black gives a [`DiffuseLight`],
+//! - the `KHR_materials_transmission` extension gives a [`Dielectric`] with the index of refraction of `KHR_materials_ior`,
+//! - a metallic factor of at least 0.5 gives a [`Metal`] whose fuzz is the roughness,
+//! - everything else is [`Lambertian`].
+//!
+//! The base color texture is multiplied by the base color factor. Animations, skins, morph targets, sparse accessors and primitives other than triangles are not supported.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use image::RgbImage;
+use nalgebra::{Matrix3, Matrix4, Quaternion, UnitQuaternion};
+use serde_json::Value;
+
+use crate::color::WHITE;
+use crate::error::Error;
+use crate::hittable::Bvh;
+use crate::materials::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::shapes::Triangle;
+use crate::textures::{BlendMode, BlendTexture, ImageTexture, SolidColor};
+use crate::*;
+
+/// The contents of a glTF scene in world coordinates.
+///
+/// # Fields
+/// - `world`: One [`Bvh`] of [`Triangle`]s per primitive of each mesh of each node.
+/// - `cameras`: The perspective [`Camera`]s in the order of the nodes. Cameras without an aspect ratio get 16:9.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, gltf::GltfScene, ray::Ray};
+/// // A single triangle with its vertices embedded as base64
+/// let gltf = r#"{
+///     "asset": {"version": "2.0"},
+///     "buffers": [{"byteLength": 36, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"}],
+///     "bufferViews": [{"buffer": 0, "byteLength": 36}],
+///     "accessors": [{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}],
+///     "meshes": [{"primitives": [{"attributes": {"POSITION": 0}}]}],
+///     "nodes": [{"mesh": 0, "translation": [0, 0, -1]}],
+///     "scenes": [{"nodes": [0]}]
+/// }"#;
+/// let scene = GltfScene::from_slice(gltf.as_bytes(), None).unwrap();
+/// let hit = scene.world.hit(Ray::new(vector![0.2, 0.2, 1.], vector![0., 0., -1.]), 0., Float::INFINITY).unwrap();
+/// assert!((hit.point.z + 1.).abs() < 1e-5);
+/// ```
+#[derive(Debug, Default)]
+pub struct GltfScene {
+    pub world: HittableList,
+    pub cameras: Vec<Camera>,
+}
+
+impl GltfScene {
+    /// Read a `.gltf` or `.glb` file. External buffers and images are loaded relative to it.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+        Self::from_slice(&bytes, path.parent())
+    }
+
+    /// Read a scene from the contents of a `.gltf` or `.glb` file.
+    ///
+    /// External buffers and images are loaded relative to `directory`, which fails if it is [`None`].
+    pub fn from_slice(bytes: &[u8], directory: Option<&Path>) -> Result<Self, Error> {
+        let (json, binary) = if bytes.starts_with(b"glTF") {
+            parse_glb(bytes)?
+        } else {
+            (bytes, None)
+        };
+        let json: Value = serde_json::from_slice(json)
+            .map_err(|error| invalid(format!("invalid JSON: {error}")))?;
+
+        let mut document = Document {
+            buffers: Vec::new(),
+            directory: directory.map(Path::to_path_buf),
+            textures: HashMap::new(),
+            json,
+        };
+        document.load_buffers(binary)?;
+
+        let scene_index = document.json["scene"].as_u64().unwrap_or(0) as usize;
+        let roots: Vec<usize> = match document.json["scenes"].get(scene_index) {
+            Some(scene) => indices(&scene["nodes"]),
+            // Without scenes, all nodes that are no children are roots.
+            None => {
+                let nodes = array(&document.json["nodes"]);
+                let children: Vec<usize> = nodes
+                    .iter()
+                    .flat_map(|node| indices(&node["children"]))
+                    .collect();
+                (0..nodes.len())
+                    .filter(|node| !children.contains(node))
+                    .collect()
+            }
+        };
+
+        let mut scene = Self::default();
+        let mut materials = HashMap::new();
+        for root in roots {
+            document.visit_node(root, Matrix4::identity(), &mut scene, &mut materials, 0)?;
+        }
+        Ok(scene)
+    }
+}
+
+/// The JSON part of a glTF file with its loaded buffers.
+struct Document {
+    json: Value,
+    buffers: Vec<Vec<u8>>,
+    directory: Option<PathBuf>,
+    textures: HashMap<usize, ImageTexture>,
+}
+
+impl Document {
+    /// Load all buffers, where a buffer without URI is the binary chunk of a `.glb` file.
+    fn load_buffers(&mut self, mut binary: Option<&[u8]>) -> Result<(), Error> {
+        for buffer in array(&self.json["buffers"]) {
+            let data = match buffer["uri"].as_str() {
+                Some(uri) => self.load_uri(uri)?,
+                None => binary
+                    .take()
+                    .ok_or_else(|| invalid("buffer without URI or binary chunk"))?
+                    .to_vec(),
+            };
+            self.buffers.push(data);
+        }
+        Ok(())
+    }
+
+    /// Decode a base64 data URI or read a file relative to the directory.
+    fn load_uri(&self, uri: &str) -> Result<Vec<u8>, Error> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            let (_, encoded) = data
+                .split_once(";base64,")
+                .ok_or_else(|| invalid("data URI is not base64"))?;
+            return decode_base64(encoded);
+        }
+        let directory = self
+            .directory
+            .as_ref()
+            .ok_or_else(|| invalid(format!("cannot load {uri} without a directory")))?;
+        Ok(fs::read(directory.join(uri))?)
+    }
+
+    /// Bytes of the buffer view `index`.
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), Error> {
+        let view = &self.json["bufferViews"][index];
+        let buffer = self
+            .buffers
+            .get(usize_of(&view["buffer"])?)
+            .ok_or_else(|| invalid(format!("buffer view {index} refers to a missing buffer")))?;
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let length = usize_of(&view["byteLength"])?;
+        let stride = view["byteStride"].as_u64().map(|stride| stride as usize);
+        let bytes = offset
+            .checked_add(length)
+            .and_then(|end| buffer.get(offset..end))
+            .ok_or_else(|| invalid(format!("buffer view {index} is out of bounds")))?;
+        Ok((bytes, stride))
+    }
+
+    /// Read all components of the accessor `index` as well as the number of components per element.
+    fn accessor(&self, index: usize) -> Result<(Vec<f64>, usize), Error> {
+        let accessor = &self.json["accessors"][index];
+        if accessor.get("sparse").is_some() {
+            return Err(invalid("sparse accessors are not supported"));
+        }
+        let count = usize_of(&accessor["count"])?;
+        let components = match accessor["type"].as_str() {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            kind => return Err(invalid(format!("unsupported accessor type {kind:?}"))),
+        };
+        let component_type = accessor["componentType"].as_u64();
+        let size = match component_type {
+            Some(5120 | 5121) => 1,
+            Some(5122 | 5123) => 2,
+            Some(5125 | 5126) => 4,
+            kind => return Err(invalid(format!("unsupported component type {kind:?}"))),
+        };
+        let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+
+        let (bytes, stride) = self.buffer_view(usize_of(&accessor["bufferView"])?)?;
+        let offset = accessor["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let stride = stride.unwrap_or(size * components);
+        // The last element has to end inside the buffer view, which also bounds `count` before allocating.
+        let end = match count.checked_sub(1) {
+            Some(last) => last
+                .checked_mul(stride)
+                .and_then(|start| start.checked_add(offset))
+                .and_then(|start| start.checked_add(size * components)),
+            None => Some(0),
+        };
+        if end.is_none_or(|end| end > bytes.len()) {
+            return Err(invalid(format!("accessor {index} is out of bounds")));
+        }
+
+        let mut values = Vec::with_capacity(count * components);
+        for element in 0..count {
+            for component in 0..components {
+                let start = offset + element * stride + component * size;
+                let bytes = &bytes[start..start + size];
+                let value = match component_type {
+                    Some(5120) => (bytes[0] as i8) as f64,
+                    Some(5121) => bytes[0] as f64,
+                    Some(5122) => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    Some(5123) => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    Some(5125) => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    _ => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                };
+                // Normalized integers map to [0, 1] (or [-1, 1] if signed).
+                let value = match (normalized, component_type) {
+                    (true, Some(5120)) => (value / 127.).max(-1.),
+                    (true, Some(5121)) => value / 255.,
+                    (true, Some(5122)) => (value / 32767.).max(-1.),
+                    (true, Some(5123)) => value / 65535.,
+                    _ => value,
+                };
+                values.push(value);
+            }
+        }
+        Ok((values, components))
+    }
+
+    /// Read the accessor `index` as three-dimensional vectors.
+    fn vectors(&self, index: usize) -> Result<Vec<Vector3<Float>>, Error> {
+        let (values, components) = self.accessor(index)?;
+        if components != 3 {
+            return Err(invalid(format!("accessor {index} is no VEC3")));
+        }
+        Ok(values
+            .chunks_exact(3)
+            .map(|xyz| vector![float(xyz[0]), float(xyz[1]), float(xyz[2])])
+            .collect())
+    }
+
+    /// Add the meshes and cameras of the node `index` and its children with the transform of the parent.
+    fn visit_node(
+        &mut self,
+        index: usize,
+        parent: Matrix4<Float>,
+        scene: &mut GltfScene,
+        materials: &mut HashMap<Option<usize>, Arc<dyn Material>>,
+        depth: usize,
+    ) -> Result<(), Error> {
+        // glTF forbids cycles, but a broken file should not overflow the stack.
+        if depth > 256 {
+            return Err(invalid("nodes are nested too deeply"));
+        }
+        let node = self.json["nodes"]
+            .get(index)
+            .ok_or_else(|| invalid(format!("missing node {index}")))?
+            .clone();
+        let transform = parent * node_transform(&node);
+
+        if let Some(mesh) = node["mesh"].as_u64() {
+            self.add_mesh(mesh as usize, &transform, scene, materials)?;
+        }
+        if let Some(camera) = node["camera"].as_u64() {
+            if let Some(camera) = self.camera(camera as usize, &transform) {
+                scene.cameras.push(camera);
+            }
+        }
+        for child in indices(&node["children"]) {
+            self.visit_node(child, transform, scene, materials, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Add every triangle primitive of the mesh `index`, transformed into world coordinates.
+    fn add_mesh(
+        &mut self,
+        index: usize,
+        transform: &Matrix4<Float>,
+        scene: &mut GltfScene,
+        materials: &mut HashMap<Option<usize>, Arc<dyn Material>>,
+    ) -> Result<(), Error> {
+        let primitives = array(&self.json["meshes"][index]["primitives"]).to_vec();
+        let linear: Matrix3<Float> = transform.fixed_view::<3, 3>(0, 0).into();
+        let normal_matrix = linear.try_inverse().unwrap_or(linear).transpose();
+        // Mirroring transforms flip the winding of the triangles.
+        let mirrored = linear.determinant() < 0.;
+
+        for primitive in primitives {
+            if primitive["mode"].as_u64().unwrap_or(4) != 4 {
+                continue;
+            }
+            let attributes = &primitive["attributes"];
+            let positions: Vec<_> = self
+                .vectors(usize_of(&attributes["POSITION"])?)?
+                .into_iter()
+                .map(|position| transform.transform_point(&position.into()).coords)
+                .collect();
+            let normals = match attributes["NORMAL"].as_u64() {
+                Some(normals) => Some(
+                    self.vectors(normals as usize)?
+                        .into_iter()
+                        .map(|normal| normal_matrix * normal)
+                        .collect::<Vec<_>>(),
+                ),
+                None => None,
+            };
+            let uvs = match attributes["TEXCOORD_0"].as_u64() {
+                Some(uvs) => {
+                    let (values, _) = self.accessor(uvs as usize)?;
+                    // The origin of glTF texture coordinates is the top left corner.
+                    Some(
+                        values
+                            .chunks_exact(2)
+                            .map(|uv| (float(uv[0]), 1. - float(uv[1])))
+                            .collect::<Vec<_>>(),
+                    )
+                }
+                None => None,
+            };
+            let indices: Vec<usize> = match primitive["indices"].as_u64() {
+                Some(indices) => self
+                    .accessor(indices as usize)?
+                    .0
+                    .into_iter()
+                    .map(|index| index as usize)
+                    .collect(),
+                None => (0..positions.len()).collect(),
+            };
+
+            let material_index = primitive["material"].as_u64().map(|index| index as usize);
+            let material = match materials.get(&material_index) {
+                Some(material) => material.clone(),
+                None => {
+                    let material = self.material(material_index)?;
+                    materials.insert(material_index, material.clone());
+                    material
+                }
+            };
+
+            let mut triangles = HittableList::default();
+            for corners in indices.chunks_exact(3) {
+                let mut corners = [corners[0], corners[1], corners[2]];
+                if mirrored {
+                    corners.swap(1, 2);
+                }
+                if corners.iter().any(|&corner| corner >= positions.len()) {
+                    return Err(invalid(format!("mesh {index} has an index out of bounds")));
+                }
+                let [a, b, c] = corners.map(|corner| positions[corner]);
+                let mut triangle = Triangle::new(a, b, c, material.clone());
+                if let Some(normals) = &normals {
+                    triangle = triangle.with_normals(corners.map(|corner| normals[corner]));
+                }
+                if let Some(uvs) = &uvs {
+                    if corners.iter().all(|&corner| corner < uvs.len()) {
+                        triangle = triangle.with_uvs(corners.map(|corner| uvs[corner]));
+                    }
+                }
+                triangles.push(triangle);
+            }
+            if !triangles.is_empty() {
+                scene.world.push(Bvh::new(triangles, 0., 0.)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// The perspective camera `index` placed by `transform`, which looks along its -z axis.
+    fn camera(&self, index: usize, transform: &Matrix4<Float>) -> Option<Camera> {
+        let perspective = &self.json["cameras"][index]["perspective"];
+        let vertical_fov = float(perspective["yfov"].as_f64()?);
+        let aspect_ratio = perspective["aspectRatio"].as_f64().map_or(16. / 9., float);
+
+        let origin = transform
+            .transform_point(&vector![0., 0., 0.].into())
+            .coords;
+        let forward = transform.transform_vector(&vector![0., 0., -1.]);
+        let up = transform.transform_vector(&vector![0., 1., 0.]);
+        Some(Camera::new(
+            origin,
+            origin + forward,
+            up,
+            vertical_fov,
+            aspect_ratio,
+            0.,
+            1.,
+        ))
+    }
+
+    /// Map the glTF material `index` onto a material of this crate, see the [module documentation](self).
+    ///
+    /// Primitives without material are white [`Lambertian`]s.
+    fn material(&mut self, index: Option<usize>) -> Result<Arc<dyn Material>, Error> {
+        let Some(index) = index else {
+            return Ok(Arc::new(Lambertian::solid_color(0.8 * WHITE)));
+        };
+        let material = self.json["materials"][index].clone();
+        let color_of = |value: &Value| -> Option<Color> {
+            let value = value.as_array()?;
+            Some(color![
+                float(value.first()?.as_f64()?),
+                float(value.get(1)?.as_f64()?),
+                float(value.get(2)?.as_f64()?)
+            ])
+        };
+
+        let extensions = &material["extensions"];
+        if let Some(emissive) = color_of(&material["emissiveFactor"]) {
+            let strength = extensions["KHR_materials_emissive_strength"]["emissiveStrength"]
+                .as_f64()
+                .map_or(1., float);
+            if emissive != color![0., 0., 0.] {
+                return Ok(Arc::new(DiffuseLight::solid_color(strength * emissive)));
+            }
+        }
+        let transmission = extensions["KHR_materials_transmission"]["transmissionFactor"]
+            .as_f64()
+            .unwrap_or(0.);
+        if transmission > 0. {
+            let ior = extensions["KHR_materials_ior"]["ior"]
+                .as_f64()
+                .map_or(1.5, float);
+            return Ok(Arc::new(Dielectric::new(ior)));
+        }
+
+        let pbr = &material["pbrMetallicRoughness"];
+        let base_color = color_of(&pbr["baseColorFactor"]).unwrap_or(WHITE);
+        let metallic = pbr["metallicFactor"].as_f64().map_or(1., float);
+        let roughness = pbr["roughnessFactor"].as_f64().map_or(1., float);
+        let texture = match pbr["baseColorTexture"]["index"].as_u64() {
+            Some(texture) => Some(self.texture(texture as usize)?),
+            None => None,
+        };
+
+        Ok(match (texture, metallic >= 0.5) {
+            (Some(texture), metallic) => {
+                let texture = BlendTexture::with_factor(texture, SolidColor::new(base_color), 1.)
+                    .with_mode(BlendMode::Multiply);
+                if metallic {
+                    Arc::new(Metal::new(texture, roughness))
+                } else {
+                    Arc::new(Lambertian::new(texture))
+                }
+            }
+            (None, true) => Arc::new(Metal::solid_color(base_color, roughness)),
+            (None, false) => Arc::new(Lambertian::solid_color(base_color)),
+        })
+    }
+
+    /// The image of the texture `index`, which is decoded only once.
+    fn texture(&mut self, index: usize) -> Result<ImageTexture, Error> {
+        let source = usize_of(&self.json["textures"][index]["source"])?;
+        if let Some(texture) = self.textures.get(&source) {
+            return Ok(texture.clone());
+        }
+
+        let image = &self.json["images"][source];
+        let bytes = match (image["uri"].as_str(), image["bufferView"].as_u64()) {
+            (Some(uri), _) => self.load_uri(uri)?,
+            (None, Some(view)) => self.buffer_view(view as usize)?.0.to_vec(),
+            (None, None) => return Err(invalid(format!("image {source} has no data"))),
+        };
+        let image: RgbImage = image::load_from_memory(&bytes)?.into_rgb8();
+        let texture = ImageTexture::new(image);
+        self.textures.insert(source, texture.clone());
+        Ok(texture)
+    }
+}
+
+/// The local transform of a node, given either as a matrix or as translation, rotation and scale.
+fn node_transform(node: &Value) -> Matrix4<Float> {
+    let numbers = |value: &Value| -> Vec<Float> {
+        array(value)
+            .iter()
+            .filter_map(Value::as_f64)
+            .map(float)
+            .collect()
+    };
+
+    let matrix = numbers(&node["matrix"]);
+    if matrix.len() == 16 {
+        return Matrix4::from_column_slice(&matrix);
+    }
+
+    let mut transform = Matrix4::identity();
+    if let [x, y, z] = numbers(&node["translation"])[..] {
+        transform *= Matrix4::new_translation(&vector![x, y, z]);
+    }
+    if let [x, y, z, w] = numbers(&node["rotation"])[..] {
+        transform *= UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z)).to_homogeneous();
+    }
+    if let [x, y, z] = numbers(&node["scale"])[..] {
+        transform *= Matrix4::new_nonuniform_scaling(&vector![x, y, z]);
+    }
+    transform
+}
+
+/// Split a `.glb` file into its JSON and binary chunk.
+fn parse_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), Error> {
+    let word = |offset: usize| -> Result<usize, Error> {
+        let bytes = bytes
+            .get(offset..offset + 4)
+            .ok_or_else(|| invalid("truncated GLB file"))?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+    if word(4)? != 2 {
+        return Err(invalid("only glTF 2.0 is supported"));
+    }
+
+    let length = word(8)?.min(bytes.len());
+    let mut offset = 12;
+    let mut json = None;
+    let mut binary = None;
+    while offset + 8 <= length {
+        let chunk_length = word(offset)?;
+        let chunk = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or_else(|| invalid("truncated GLB chunk"))?;
+        match word(offset + 4)? {
+            0x4E4F534A => json = Some(chunk),
+            0x004E4942 => binary = Some(chunk),
+            // Unknown chunks are skipped.
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+    Ok((
+        json.ok_or_else(|| invalid("GLB file without JSON"))?,
+        binary,
+    ))
+}
+
+/// Decode standard base64 with optional padding.
+fn decode_base64(encoded: &str) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for character in encoded.bytes().filter(|&character| character != b'=') {
+        let value = match character {
+            b'A'..=b'Z' => character - b'A',
+            b'a'..=b'z' => character - b'a' + 26,
+            b'0'..=b'9' => character - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(invalid("invalid base64")),
+        };
+        bits = bits << 6 | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::Config(format!("invalid glTF: {}", message.into()))
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map_or(&[], Vec::as_slice)
+}
+
+fn indices(value: &Value) -> Vec<usize> {
+    array(value)
+        .iter()
+        .filter_map(Value::as_u64)
+        .map(|index| index as usize)
+        .collect()
+}
+
+fn usize_of(value: &Value) -> Result<usize, Error> {
+    value
+        .as_u64()
+        .map(|value| value as usize)
+        .ok_or_else(|| invalid(format!("expected an index, found {value}")))
+}
+
+// The cast is only necessary if `Float` is `f32`.
+#[allow(clippy::unnecessary_cast)]
+fn float(value: f64) -> Float {
+    value as Float
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn glb_with_hierarchy() {
+        // Two triangles sharing an edge, indexed with u16
+        let mut binary = Vec::new();
+        for value in [0f32, 0., 0., 1., 0., 0., 1., 1., 0., 0., 1., 0.] {
+            binary.extend(value.to_le_bytes());
+        }
+        for index in [0u16, 1, 2, 0, 2, 3] {
+            binary.extend(index.to_le_bytes());
+        }
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "buffers": [{"byteLength": 60}],
+            "bufferViews": [{"buffer": 0, "byteLength": 48}, {"buffer": 0, "byteOffset": 48, "byteLength": 12}],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3"},
+                {"bufferView": 1, "componentType": 5123, "count": 6, "type": "SCALAR"}
+            ],
+            "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [1, 0, 0, 1], "metallicFactor": 0}}],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "indices": 1, "material": 0}]}],
+            "cameras": [{"type": "perspective", "perspective": {"yfov": 0.8, "znear": 0.1}}],
+            "nodes": [
+                {"translation": [10, 0, 0], "scale": [2, 2, 2], "children": [1, 2]},
+                {"mesh": 0, "translation": [0, 0, -1]},
+                {"camera": 0, "translation": [0, 0, 5]}
+            ]
+        }"#;
+
+        let mut glb = b"glTF".to_vec();
+        let padding = (4 - json.len() % 4) % 4;
+        let length = 12 + 8 + json.len() + padding + 8 + binary.len();
+        for word in [2, length as u32, (json.len() + padding) as u32, 0x4E4F534A] {
+            glb.extend(word.to_le_bytes());
+        }
+        glb.extend(json.as_bytes());
+        glb.extend(std::iter::repeat_n(b' ', padding));
+        glb.extend((binary.len() as u32).to_le_bytes());
+        glb.extend(0x004E4942u32.to_le_bytes());
+        glb.extend(&binary);
+
+        let scene = GltfScene::from_slice(&glb, None).unwrap();
+        assert_eq!(scene.cameras.len(), 1);
+        // The camera is at (10, 0, 10) and looks at the quad, which spans [10, 12]² at z = -2.
+        let ray = scene.cameras[0].get_center_ray(0.5, 0.5);
+        assert!((ray.origin() - vector![10., 0., 10.]).norm() < 1e-4);
+        let ray = ray::Ray::new(vector![11.5, 1.5, 0.], vector![0., 0., -1.]);
+        let hit = scene.world.hit(ray, 0., Float::INFINITY).unwrap();
+        assert!((hit.point.z + 2.).abs() < 1e-4);
+        assert!(hit.front_face);
+        assert_eq!(hit.material.albedo(), Some(color![1., 0., 0.]));
+    }
+
+    #[test]
+    fn accessor_out_of_bounds() {
+        let gltf = |accessor: &str, view_offset: &str| {
+            let json = format!(
+                r#"{{
+                    "asset": {{"version": "2.0"}},
+                    "buffers": [{{"byteLength": 12, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAA"}}],
+                    "bufferViews": [{{"buffer": 0, "byteOffset": {view_offset}, "byteLength": 12}}],
+                    "accessors": [{accessor}],
+                    "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}}}]}}],
+                    "nodes": [{{"mesh": 0}}]
+                }}"#
+            );
+            GltfScene::from_slice(json.as_bytes(), None)
+        };
+        let position = |count: &str, offset: &str| {
+            format!(
+                r#"{{"bufferView": 0, "byteOffset": {offset}, "componentType": 5126, "count": {count}, "type": "VEC3"}}"#
+            )
+        };
+
+        assert!(gltf(&position("1", "0"), "0").is_ok());
+        for (count, offset) in [
+            ("2", "0"),
+            ("1", "4"),
+            ("18446744073709551615", "0"),
+            ("1", "18446744073709551615"),
+        ] {
+            assert!(
+                matches!(gltf(&position(count, offset), "0"), Err(Error::Config(_))),
+                "count {count}, offset {offset}"
+            );
+        }
+        assert!(matches!(
+            gltf(&position("1", "0"), "18446744073709551615"),
+            Err(Error::Config(_))
+        ));
+    }
+}
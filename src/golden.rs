@@ -0,0 +1,104 @@
+//! Golden-image tests: small canonical scenes are rendered and compared against reference images in `tests/golden`.
+//!
+//! The scenes are built and rendered with a fixed [seed](Raytracer::with_seed), so the same samples are drawn on every run.
+//! The comparison still uses the [mean squared error](metrics::mse) per channel with a small tolerance instead of exact equality, because the results of floating-point math differ slightly between platforms.
+//!
+//! A missing reference fails the test. To add a reference or accept an intended change of the output, rerun with `UPDATE_GOLDEN=1` and check the new images.
+
+use std::env;
+use std::path::PathBuf;
+
+use crate::integrator::Integrator;
+use crate::metrics;
+use crate::raytracer::RaytracedImage;
+use crate::scenes::{self, SceneSettings};
+use crate::*;
+
+/// Seed of the samples of all renders.
+const SEED: u64 = 1;
+
+/// Path of the reference image `name`.
+///
+/// Random scenes differ between the precisions, so there is a separate set of references for the `f64` feature.
+fn reference_path(name: &str) -> PathBuf {
+    let suffix = if cfg!(feature = "f64") { "_f64" } else { "" };
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{name}{suffix}.png"))
+}
+
+/// Render `raytracer` and compare it to the reference image `name`, allowing a mean squared error of `tolerance` per channel.
+fn check(name: &str, raytracer: Raytracer, tolerance: Float) {
+    let image = raytracer
+        .with_seed(SEED)
+        .render()
+        .unwrap()
+        .into_image()
+        .unwrap();
+    let path = reference_path(name);
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image.save(&path).unwrap();
+        eprintln!("wrote reference image {}", path.display());
+        return;
+    }
+
+    assert!(
+        path.exists(),
+        "reference image {} is missing, rerun with UPDATE_GOLDEN=1 to write it",
+        path.display()
+    );
+    let reference = image::open(&path).unwrap().into_rgb8();
+    assert_eq!(
+        image.dimensions(),
+        reference.dimensions(),
+        "size of {name} differs from the reference"
+    );
+    // Compare the quantized render, so that both images went through the same conversion.
+    let mse = metrics::mse(
+        &RaytracedImage::from_rgb_image(&image),
+        &RaytracedImage::from_rgb_image(&reference),
+    )
+    .unwrap();
+    assert!(
+        mse.into_iter().all(|mse| mse <= tolerance),
+        "{name} differs from the reference: MSE per channel {mse:?} > {tolerance}"
+    );
+}
+
+#[test]
+fn random_spheres_whitted() {
+    let settings = SceneSettings::new(64, 36).with_samples(64, 8).with_seed(1);
+    let raytracer = scenes::random_spheres(&settings).with_integrator(Integrator::Whitted);
+    check("random_spheres_whitted", raytracer, 1e-4);
+}
+
+#[test]
+fn cornell_box_whitted() {
+    let settings = SceneSettings::new(48, 48).with_samples(64, 8);
+    let raytracer = scenes::cornell_box(&settings).with_integrator(Integrator::Whitted);
+    check("cornell_box_whitted", raytracer, 1e-4);
+}
+
+#[test]
+fn cornell_box_path_traced() {
+    let settings = SceneSettings::new(32, 32).with_samples(256, 8);
+    check(
+        "cornell_box_path_traced",
+        scenes::cornell_box(&settings),
+        1e-4,
+    );
+}
+
+#[test]
+fn sphere_grid_ambient_occlusion() {
+    let settings = SceneSettings::new(64, 36).with_samples(16, 4);
+    let raytracer =
+        scenes::sphere_grid(&settings, 4).with_integrator(Integrator::AmbientOcclusion {
+            radius: 1.,
+            samples: 4,
+        });
+    check("sphere_grid_ambient_occlusion", raytracer, 1e-4);
+}
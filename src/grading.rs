@@ -0,0 +1,265 @@
+//! Color grading of the displayable image, so renders can get their final look without a round trip through other tools.
+//!
+//! A [`ColorGrading`] is attached to an image with [`RaytracedImage::with_grading`](crate::raytracer::RaytracedImage::with_grading).
+//! It is applied after the exposure, the white point, and the [gamma](crate::raytracer::RaytracedImage::with_gamma), i.e. to the display colors in \[0, 1\], and before the quantization to 8 or 16 bits:
+//! first the contrast, then the saturation, and then a 3D [`Lut`] as exported by most grading tools in the `.cube` format.
+//! The linear radiance of the [`colors`](crate::raytracer::RaytracedImage::colors) and the float images stays unchanged.
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::*;
+
+/// Adjustments of the display colors.
+///
+/// # Fields
+/// - `contrast`: Factor of the distance of each channel from middle gray 0.5 (1 leaves the image unchanged).
+/// - `saturation`: Factor of the distance of each color from its gray of the same luminance (0 gives a grayscale image).
+/// - `lut`: 3D [`Lut`] that is applied last.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, grading::ColorGrading};
+/// let raytracer = Raytracer::new(Camera::default(), color![0.25, 0.25, 0.25], 4, 4, 1, 1);
+/// let image = raytracer.render().unwrap();
+/// let image = image.with_grading(ColorGrading::default().with_contrast(2.)).into_image().unwrap();
+/// // 0.25 is 0.5 after gamma correction, which the contrast keeps.
+/// assert_eq!(image.get_pixel(0, 0).0, [128, 128, 128]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorGrading {
+    pub contrast: Float,
+    pub saturation: Float,
+    pub lut: Option<Lut>,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        Self {
+            contrast: 1.,
+            saturation: 1.,
+            lut: None,
+        }
+    }
+}
+
+impl ColorGrading {
+    /// Consume `self` and set the contrast.
+    pub fn with_contrast(mut self, contrast: Float) -> Self {
+        self.contrast = contrast;
+        self
+    }
+
+    /// Consume `self` and set the saturation.
+    pub fn with_saturation(mut self, saturation: Float) -> Self {
+        self.saturation = saturation;
+        self
+    }
+
+    /// Consume `self` and apply `lut` after the other adjustments.
+    pub fn with_lut(mut self, lut: Lut) -> Self {
+        self.lut = Some(lut);
+        self
+    }
+
+    /// Grade the display color `color`, returning channels in \[0, 1\].
+    pub fn apply(&self, color: Color) -> Color {
+        let contrasted: Color = color
+            .into_iter()
+            .map(|c| (c - 0.5) * self.contrast + 0.5)
+            .collect();
+        let gray = contrasted.luminance();
+        let saturated: Color = contrasted
+            .into_iter()
+            .map(|c| (gray + (c - gray) * self.saturation).clamp(0., 1.))
+            .collect();
+        match &self.lut {
+            Some(lut) => lut.apply(saturated),
+            None => saturated,
+        }
+    }
+}
+
+/// A 3D lookup table that maps colors to colors, interpolated trilinearly between its entries.
+///
+/// # Fields
+/// - `size`: Number of entries along each axis.
+/// - `entries`: Output colors of the `size`³ entries, with red changing fastest, then green, then blue.
+/// - `domain_min`: Input color of the first entry.
+/// - `domain_max`: Input color of the last entry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut {
+    size: usize,
+    entries: Vec<Color>,
+    domain_min: Color,
+    domain_max: Color,
+}
+
+impl Lut {
+    /// The identity with `size` entries along each axis.
+    ///
+    /// # Panics
+    /// If `size` is less than 2.
+    pub fn identity(size: usize) -> Self {
+        assert!(
+            size >= 2,
+            "a LUT needs at least two entries along each axis"
+        );
+        let step = |index: usize| index as Float / (size - 1) as Float;
+        let entries = (0..size * size * size)
+            .map(|index| {
+                color![
+                    step(index % size),
+                    step(index / size % size),
+                    step(index / (size * size))
+                ]
+            })
+            .collect();
+        Self {
+            size,
+            entries,
+            domain_min: color![0., 0., 0.],
+            domain_max: color![1., 1., 1.],
+        }
+    }
+
+    /// Read a LUT from a `.cube` file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse a LUT in the `.cube` format of Adobe and Resolve.
+    ///
+    /// Returns [`Error::Config`] for invalid lines, 1D LUTs, and a wrong number of entries.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut size = None;
+        let mut entries = Vec::new();
+        let (mut domain_min, mut domain_max) = (color![0., 0., 0.], color![1., 1., 1.]);
+        for (number, line) in text.lines().enumerate() {
+            let invalid = |message: &str| {
+                Error::Config(format!("invalid LUT: line {}: {message}", number + 1))
+            };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let mut words = line.split_whitespace();
+            let Some(first) = words.next() else {
+                continue;
+            };
+            let parse_color = |words: std::str::SplitWhitespace| -> Result<Color, Error> {
+                let channels = words
+                    .map(|word| word.parse::<Float>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| invalid("invalid number"))?;
+                match channels[..] {
+                    [r, g, b] => Ok(color![r, g, b]),
+                    _ => Err(invalid("expected three numbers")),
+                }
+            };
+            match first {
+                "TITLE" => {}
+                "LUT_3D_SIZE" => {
+                    let value = words.next().and_then(|word| word.parse::<usize>().ok());
+                    size = Some(
+                        value
+                            .filter(|&size| size >= 2)
+                            .ok_or_else(|| invalid("expected a size of at least 2"))?,
+                    );
+                }
+                "LUT_1D_SIZE" => return Err(invalid("1D LUTs are not supported")),
+                "DOMAIN_MIN" => domain_min = parse_color(words)?,
+                "DOMAIN_MAX" => domain_max = parse_color(words)?,
+                _ if first.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    return Err(invalid(&format!("unknown keyword {first:?}")))
+                }
+                _ => entries.push(parse_color(line.split_whitespace())?),
+            }
+        }
+
+        let size = size.ok_or_else(|| Error::Config("invalid LUT: no LUT_3D_SIZE".to_string()))?;
+        if entries.len() != size * size * size {
+            return Err(Error::Config(format!(
+                "invalid LUT: expected {} entries, found {}",
+                size * size * size,
+                entries.len()
+            )));
+        }
+        Ok(Self {
+            size,
+            entries,
+            domain_min,
+            domain_max,
+        })
+    }
+
+    /// Number of entries along each axis.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Look up `color`, clamped to the domain.
+    pub fn apply(&self, color: Color) -> Color {
+        let last = (self.size - 1) as Float;
+        // Position of the color between the entries along each axis
+        let position: Vec<Float> = (0..3)
+            .map(|axis| {
+                let (min, max) = (self.domain_min[axis], self.domain_max[axis]);
+                ((color[axis] - min) / (max - min)).clamp(0., 1.) * last
+            })
+            .collect();
+        let lower: Vec<usize> = position
+            .iter()
+            .map(|&p| (p.floor() as usize).min(self.size - 2))
+            .collect();
+        let entry = |r: usize, g: usize, b: usize| {
+            self.entries
+                [(lower[2] + b) * self.size * self.size + (lower[1] + g) * self.size + lower[0] + r]
+        };
+
+        let mut result = color![0., 0., 0.];
+        for corner in 0..8 {
+            let (r, g, b) = (corner & 1, corner >> 1 & 1, corner >> 2 & 1);
+            let weight: Float = [r, g, b]
+                .into_iter()
+                .enumerate()
+                .map(|(axis, upper)| {
+                    let t = position[axis] - lower[axis] as Float;
+                    if upper == 1 {
+                        t
+                    } else {
+                        1. - t
+                    }
+                })
+                .product();
+            result += weight * entry(r, g, b);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cube_lut() {
+        let color = color![0.2, 0.55, 0.9];
+        let identity = Lut::identity(5).apply(color);
+        assert!((identity - color).into_iter().all(|c| c.abs() < 1e-5));
+
+        // Inverts the colors; red changes fastest.
+        let inverted = Lut::parse(
+            "TITLE \"invert\"\n# comment\nLUT_3D_SIZE 2\n\
+             1 1 1\n0 1 1\n1 0 1\n0 0 1\n1 1 0\n0 1 0\n1 0 0\n0 0 0\n",
+        )
+        .unwrap();
+        let graded = ColorGrading::default().with_lut(inverted).apply(color);
+        assert!((graded - color![0.8, 0.45, 0.1])
+            .into_iter()
+            .all(|c| c.abs() < 1e-5));
+
+        let gray = ColorGrading::default().with_saturation(0.).apply(color);
+        assert!((gray.r() - gray.b()).abs() < 1e-6);
+        assert!(Lut::parse("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+        assert!(Lut::parse("LUT_1D_SIZE 16\n").is_err());
+    }
+}
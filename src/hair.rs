@@ -0,0 +1,267 @@
+//! Hair and fur grown from the surface of a [`Mesh`].
+//!
+//! Every strand is a [`BezierCurve`] rooted on a random point of a triangle. How many strands grow and how long they get is controlled by [`Texture`]s, which are evaluated at the root like a [`Displacement`](crate::mesh::Displacement).
+//! The strands are usually rendered with the [`Hair`](crate::materials::Hair) material.
+
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::bezier::BezierCurve;
+use crate::color::WHITE;
+use crate::hittable::{BoundingBoxError, Bvh};
+use crate::materials::Material;
+use crate::mesh::{vertex_normals, Mesh};
+use crate::textures::{SolidColor, Texture};
+use crate::vec3::random_vector_in_unit_sphere;
+use crate::*;
+
+/// Parameters for growing hair from a [`Mesh`] with [`grow`](HairGrowth::grow).
+///
+/// # Fields
+/// - `density`: Average number of strands per unit area.
+/// - `density_texture`: Scales the density; the mean of its channels is the probability that a strand at a point is kept.
+/// - `length`: Length of the strands.
+/// - `length_texture`: Scales the length by the mean of its channels.
+/// - `width`: Width of the strands at the root and at the tip.
+/// - `droop`: How far the tip bends downwards (along -y) relative to the length.
+/// - `randomness`: How much the direction of each strand deviates from the normal, relative to the length.
+/// - `segments`: Number of straight pieces each strand is approximated by.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, hair::HairGrowth, materials::{Hair, Lambertian}, mesh::{Mesh, Shading}, ray::Ray};
+/// let vertices = [vector![-1., 0., -1.], vector![1., 0., -1.], vector![1., 0., 1.], vector![-1., 0., 1.]];
+/// let faces = [[0, 2, 1], [0, 3, 2]];
+/// let lawn = Mesh::new(vector![0., 0., 0.], &vertices, &faces, Shading::Flat, Lambertian::solid_color(color![0.3, 0.2, 0.1]));
+///
+/// let grass = HairGrowth::new(200., 0.5).with_droop(0.2).grow(&lawn, Hair::solid_color(color![0.3, 0.7, 0.2])).unwrap();
+/// assert!(grass.bounding_box(0., 0.).unwrap().maximum.y > 0.3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HairGrowth<D: Texture = SolidColor, L: Texture = SolidColor> {
+    density: Float,
+    density_texture: D,
+    length: Float,
+    length_texture: L,
+    width: (Float, Float),
+    droop: Float,
+    randomness: Float,
+    segments: u16,
+}
+
+impl HairGrowth {
+    /// Create the parameters with uniform density and length, strands of width 0.01 that taper to 0.002, no droop and a little randomness.
+    pub fn new(density: Float, length: Float) -> Self {
+        Self {
+            density,
+            density_texture: SolidColor::new(WHITE),
+            length,
+            length_texture: SolidColor::new(WHITE),
+            width: (0.01, 0.002),
+            droop: 0.,
+            randomness: 0.1,
+            segments: 8,
+        }
+    }
+}
+
+impl<D: Texture, L: Texture> HairGrowth<D, L> {
+    /// Consume `self` and vary the density with `texture`, e.g. for bald spots.
+    pub fn with_density_texture<T: Texture>(self, texture: T) -> HairGrowth<T, L> {
+        HairGrowth {
+            density: self.density,
+            density_texture: texture,
+            length: self.length,
+            length_texture: self.length_texture,
+            width: self.width,
+            droop: self.droop,
+            randomness: self.randomness,
+            segments: self.segments,
+        }
+    }
+
+    /// Consume `self` and vary the length with `texture`.
+    pub fn with_length_texture<T: Texture>(self, texture: T) -> HairGrowth<D, T> {
+        HairGrowth {
+            density: self.density,
+            density_texture: self.density_texture,
+            length: self.length,
+            length_texture: texture,
+            width: self.width,
+            droop: self.droop,
+            randomness: self.randomness,
+            segments: self.segments,
+        }
+    }
+
+    /// Consume `self` and set the width at the root and at the tip.
+    pub fn with_width(mut self, root: Float, tip: Float) -> Self {
+        self.width = (root, tip);
+        self
+    }
+
+    /// Consume `self` and set how far the tips bend downwards relative to the length.
+    pub fn with_droop(mut self, droop: Float) -> Self {
+        self.droop = droop;
+        self
+    }
+
+    /// Consume `self` and set how much the strands deviate from the normal relative to the length.
+    pub fn with_randomness(mut self, randomness: Float) -> Self {
+        self.randomness = randomness;
+        self
+    }
+
+    /// Consume `self` and set the number of straight pieces of each strand (8 by default).
+    pub fn with_segments(mut self, segments: u16) -> Self {
+        self.segments = segments;
+        self
+    }
+
+    /// Grow strands from the current position of `mesh` and collect them in a [`Bvh`].
+    ///
+    /// Each triangle gets on average `density` times its area strands at uniformly random points, which are kept with the probability given by the density texture.
+    /// The strands start along the interpolated vertex normal and bend by `droop` towards the tip.
+    ///
+    /// # Errors
+    /// If no strands grow at all.
+    pub fn grow<M: Material + 'static>(
+        &self,
+        mesh: &Mesh,
+        material: M,
+    ) -> Result<Bvh, BoundingBoxError> {
+        let material = Arc::new(material);
+        let vertices = mesh.vertices();
+        let normals = vertex_normals(vertices, mesh.faces());
+        let mut rng = crate::random::rng();
+        let mut strands = HittableList::default();
+
+        for face in mesh.faces() {
+            let [a, b, c] = face.map(|(vertex, _)| vertex);
+            let area = (vertices[b] - vertices[a])
+                .cross(&(vertices[c] - vertices[a]))
+                .norm()
+                / 2.;
+            // Round stochastically so that small triangles get their share as well.
+            let expected = self.density * area;
+            let count =
+                expected.floor() as usize + usize::from(rng.gen::<Float>() < expected.fract());
+
+            for _ in 0..count {
+                let (mut s, mut t): (Float, Float) = (rng.gen(), rng.gen());
+                if s + t > 1. {
+                    (s, t) = (1. - s, 1. - t);
+                }
+                let r = 1. - s - t;
+                let root = r * vertices[a] + s * vertices[b] + t * vertices[c];
+                let keep = mean(self.density_texture.color_at(0., 0., root));
+                if rng.gen::<Float>() >= keep {
+                    continue;
+                }
+                let length = self.length * mean(self.length_texture.color_at(0., 0., root));
+                if length <= 0. {
+                    continue;
+                }
+
+                let normal = (r * normals[a] + s * normals[b] + t * normals[c])
+                    .try_normalize(0.)
+                    .unwrap_or_else(Vector3::y);
+                let normal = mesh.center().normal_to_world(normal);
+                let direction = (normal + self.randomness * random_vector_in_unit_sphere())
+                    .try_normalize(0.)
+                    .unwrap_or(normal);
+                let root = mesh.center().to_world(root, 0.);
+                let droop = vector![0., -self.droop * length, 0.];
+                let control_points = [
+                    root,
+                    root + direction * length / 3.,
+                    root + direction * 2. * length / 3. + droop / 3.,
+                    root + direction * length + droop,
+                ];
+                strands.push(
+                    BezierCurve::new(control_points, self.width, material.clone())
+                        .with_segments(self.segments),
+                );
+            }
+        }
+
+        Bvh::new(strands, 0., 0.)
+    }
+}
+
+fn mean(color: Color) -> Float {
+    (color.r() + color.g() + color.b()) / 3.
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hitrecord::HitRecord;
+    use crate::materials::{Hair, Lambertian};
+    use crate::mesh::Shading;
+    use crate::ray::Ray;
+
+    #[test]
+    fn strand_count() {
+        let vertices = [
+            vector![0., 0., 0.],
+            vector![1., 0., 0.],
+            vector![1., 0., 1.],
+            vector![0., 0., 1.],
+        ];
+        let faces = [[0, 2, 1], [0, 3, 2]];
+        let mesh = Mesh::new(
+            vector![0., 0., 0.],
+            &vertices,
+            &faces,
+            Shading::Flat,
+            Lambertian::solid_color(color![0.5, 0.5, 0.5]),
+        );
+        let mut stats = crate::stats::SceneStats::default();
+        HairGrowth::new(1000., 0.1)
+            .with_density_texture(SolidColor::new(color![0.5, 0.5, 0.5]))
+            .grow(&mesh, Hair::solid_color(color![0.5, 0.4, 0.3]))
+            .unwrap()
+            .collect_stats(&mut stats);
+        assert!(
+            (400..600).contains(&stats.primitives),
+            "{}",
+            stats.primitives
+        );
+    }
+
+    #[test]
+    fn hair_scattering() {
+        let hair = Hair::solid_color(color![0.8, 0.6, 0.4]);
+        let material = Lambertian::solid_color(WHITE);
+        let ray = Ray::new(vector![0., 0.3, 2.], vector![0., -0.1, -1.]);
+        let hit = HitRecord::from_ray(
+            vector![0., 0., 0.],
+            0.,
+            0.,
+            vector![0., 0., 1.],
+            2.,
+            &material,
+            ray,
+        )
+        .with_tangent(vector![0., 1., 0.]);
+
+        let mut total = Vector3::zeros();
+        let samples = 20000;
+        for _ in 0..samples {
+            let Some((scattered, attenuation)) = hair.scatter(ray, hit.clone()) else {
+                continue;
+            };
+            let (bsdf, pdf) = hair.evaluate(ray, &hit, scattered.direction()).unwrap();
+            assert!(pdf > 0.);
+            let expected = bsdf / pdf;
+            assert!((expected.r() - attenuation.r()).abs() < 1e-3 * (1. + expected.r()));
+            total += vector![attenuation.r(), attenuation.g(), attenuation.b()];
+        }
+        // Energy is conserved, but most of it is scattered.
+        let albedo = total / samples as Float;
+        assert!(albedo.iter().all(|&channel| channel < 1.05), "{albedo}");
+        assert!(albedo.x > 0.3, "{albedo}");
+    }
+}
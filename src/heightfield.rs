@@ -0,0 +1,264 @@
+//! Terrain described by a height field.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use image::io::Reader as ImageReader;
+use image::GrayImage;
+use nalgebra::Rotation3;
+
+use crate::error::Error;
+use crate::hitrecord::HitRecord;
+use crate::hittable::{Aabb, Bvh};
+use crate::materials::Material;
+use crate::ray::Ray;
+use crate::shapes::{Movable, Offset, Triangle};
+use crate::stats::SceneStats;
+use crate::*;
+
+/// A terrain whose height y is a function of x and z.
+///
+/// The height field is sampled on a regular grid, triangulated, and sorted into its own [`Bvh`] once at construction.
+/// The surface coordinates (u, v) run along x and z over the whole terrain, so a single texture can be stretched over it.
+///
+/// # Fields
+/// - `center`: Its [`Offset`] (center of the xz footprint at height 0).
+/// - `width`: Its extent in x direction.
+/// - `depth`: Its extent in z direction.
+/// - `triangles`: [`Bvh`] of the generated [`Triangle`]s.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, heightfield::HeightField, materials::Lambertian, ray::Ray};
+/// let material = Lambertian::solid_color(color![0.3, 0.6, 0.2]);
+/// let hills = HeightField::from_function(
+///     vector![0., 0., 0.],
+///     10.,
+///     10.,
+///     (64, 64),
+///     |x, z| 0.5 * (x.sin() + z.cos()),
+///     material,
+/// );
+///
+/// let ray = Ray::new(vector![0., 10., 0.], vector![0., -1., 0.]);
+/// let hit = hills.hit(ray, 0., Float::INFINITY).unwrap();
+/// assert!((hit.point.y - 0.5).abs() < 0.05);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HeightField {
+    center: Offset,
+    width: Float,
+    depth: Float,
+    triangles: Bvh,
+}
+
+impl HeightField {
+    /// Create a [`HeightField`] by sampling a function.
+    ///
+    /// # Parameters
+    /// - `center`: Center of the xz footprint.
+    /// - `width`: Extent in x direction.
+    /// - `depth`: Extent in z direction.
+    /// - `resolution`: Number of grid cells in x and z direction.
+    /// - `height`: Function that returns the height for (x, z) relative to `center`.
+    /// - `material`: Material of the whole terrain.
+    pub fn from_function<F, M>(
+        center: Vector3<Float>,
+        width: Float,
+        depth: Float,
+        resolution: (usize, usize),
+        height: F,
+        material: M,
+    ) -> Self
+    where
+        F: Fn(Float, Float) -> Float,
+        M: Material + 'static,
+    {
+        let (nx, nz) = (resolution.0.max(1), resolution.1.max(1));
+        let heights = (0..=nz)
+            .flat_map(|k| (0..=nx).map(move |i| (i, k)))
+            .map(|(i, k)| {
+                let (x, z) = Self::grid_point(i, k, nx, nz, width, depth);
+                height(x, z)
+            })
+            .collect::<Vec<_>>();
+
+        Self::from_heights(center, width, depth, (nx, nz), &heights, material)
+    }
+
+    /// Create a [`HeightField`] from a grayscale image, with black at height 0 and white at `max_height`.
+    ///
+    /// Each pixel becomes one grid point, with the left edge of the image at -x and the top edge at -z.
+    ///
+    /// Returns [`Error::Config`] if the image has no pixels.
+    pub fn from_image<M: Material + 'static>(
+        center: Vector3<Float>,
+        image: &GrayImage,
+        width: Float,
+        depth: Float,
+        max_height: Float,
+        material: M,
+    ) -> Result<Self, Error> {
+        if image.width() == 0 || image.height() == 0 {
+            return Err(Error::Config(format!(
+                "height field image of size {}x{} has no pixels",
+                image.width(),
+                image.height()
+            )));
+        }
+        let nx = (image.width() as usize).max(2) - 1;
+        let nz = (image.height() as usize).max(2) - 1;
+        let heights = (0..=nz)
+            .flat_map(|k| (0..=nx).map(move |i| (i, k)))
+            .map(|(i, k)| {
+                let i = (i as u32).min(image.width() - 1);
+                let k = (k as u32).min(image.height() - 1);
+                max_height * image.get_pixel(i, k)[0] as Float / 255.
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Self::from_heights(
+            center,
+            width,
+            depth,
+            (nx, nz),
+            &heights,
+            material,
+        ))
+    }
+
+    /// Open a grayscale image and create a [`HeightField`] via [`from_image`](HeightField::from_image).
+    pub fn open<P: AsRef<Path>, M: Material + 'static>(
+        center: Vector3<Float>,
+        path: P,
+        width: Float,
+        depth: Float,
+        max_height: Float,
+        material: M,
+    ) -> Result<Self, Error> {
+        let image = ImageReader::open(path)?.decode()?.into_luma8();
+        Self::from_image(center, &image, width, depth, max_height, material)
+    }
+
+    pub fn width(&self) -> Float {
+        self.width
+    }
+
+    pub fn depth(&self) -> Float {
+        self.depth
+    }
+
+    /// Position (x, z) of a grid point relative to the center.
+    fn grid_point(
+        i: usize,
+        k: usize,
+        nx: usize,
+        nz: usize,
+        width: Float,
+        depth: Float,
+    ) -> (Float, Float) {
+        (
+            -width / 2. + width * i as Float / nx as Float,
+            -depth / 2. + depth * k as Float / nz as Float,
+        )
+    }
+
+    /// Triangulate a grid of `(nx + 1) * (nz + 1)` heights stored row by row.
+    fn from_heights<M: Material + 'static>(
+        center: Vector3<Float>,
+        width: Float,
+        depth: Float,
+        (nx, nz): (usize, usize),
+        heights: &[Float],
+        material: M,
+    ) -> Self {
+        let material = Arc::new(material);
+        let vertex = |i: usize, k: usize| {
+            let (x, z) = Self::grid_point(i, k, nx, nz, width, depth);
+            vector![x, heights[k * (nx + 1) + i], z]
+        };
+
+        let mut triangles = HittableList::default();
+        for k in 0..nz {
+            for i in 0..nx {
+                let (a, b, c, d) = (
+                    vertex(i, k),
+                    vertex(i + 1, k),
+                    vertex(i, k + 1),
+                    vertex(i + 1, k + 1),
+                );
+                // Counterclockwise seen from above.
+                triangles.push(Triangle::new(a, c, b, material.clone()));
+                triangles.push(Triangle::new(b, c, d, material.clone()));
+            }
+        }
+
+        Self {
+            center: Offset::new(center),
+            width,
+            depth,
+            triangles: Bvh::new(triangles, 0., 0.).expect("triangles have bounding boxes"),
+        }
+    }
+}
+
+impl Hittable for HeightField {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let mut hit = self.triangles.hit(ray, t_min, t_max)?;
+        hit.u = (hit.point.x / self.width + 0.5).clamp(0., 1.);
+        hit.v = (hit.point.z / self.depth + 0.5).clamp(0., 1.);
+        Some(hit)
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.triangles.bounding_box(time0, time1)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        self.triangles.collect_stats(stats);
+    }
+}
+
+impl Movable for HeightField {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    #[test]
+    fn from_image() {
+        let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+        let empty = HeightField::from_image(
+            vector![0., 0., 0.],
+            &GrayImage::new(0, 0),
+            1.,
+            1.,
+            1.,
+            material.clone(),
+        );
+        assert!(matches!(empty, Err(Error::Config(_))));
+
+        // A single white pixel is a flat terrain at the maximum height.
+        let image = GrayImage::from_pixel(1, 1, image::Luma([255]));
+        let plateau =
+            HeightField::from_image(vector![0., 0., 0.], &image, 2., 2., 3., material).unwrap();
+        let ray = Ray::new(vector![0.5, 10., 0.5], vector![0., -1., 0.]);
+        let hit = plateau.hit(ray, 0., Float::INFINITY).unwrap();
+        assert!((hit.point.y - 3.).abs() < 1e-4);
+    }
+}
@@ -1,7 +1,8 @@
 //! A record for when [Ray]s hit something.
 
 use crate::materials::Material;
-use crate::ray::Ray;
+use crate::ray::{Ray, RayDifferentials};
+use crate::vec3::{orthonormal_basis, reflect};
 use crate::*;
 
 /// A record for when a [Ray] hits something.
@@ -11,29 +12,40 @@ use crate::*;
 /// # Fields
 /// - `point`: Point where the hit happened.
 /// - (`u`, `v`): Coordinates on the surface submanifold (lie inside \[0,1\]).
-/// - `normal`: Normal vector to the surface.
+/// - `normal`: Shading normal, facing against the [Ray]. Materials use this one.
+/// - `geometric_normal`: Normal of the actual surface, facing against the [Ray]. It differs from `normal` e.g. on smooth shaded meshes.
+/// - `tangent`: Unit tangent perpendicular to `normal`, pointing along increasing u where the [Hittable] defines it.
 /// - `t`: Parameter of the [Ray] where the hit happened.
 /// - `front_face`: Whether the hit faces the front or the back of the [Hittable].
 /// - `material`: [Material] that was hit.
+/// - `object_id`: ID of the object that was hit, if it has one.
+/// - `uv_gradient`: Gradients of `u` and `v` along the surface, if the [Hittable] defines them.
+/// - `duv_dx`, `duv_dy`: Change of (`u`, `v`) from this pixel to the next one in x and y direction, or zero if the [Ray] has no [`RayDifferentials`].
 #[derive(Clone, Debug)]
 pub struct HitRecord<'a> {
-    pub point: Vector3<f32>,
-    pub u: f32,
-    pub v: f32,
-    pub normal: Vector3<f32>,
-    pub t: f32,
+    pub point: Vector3<Float>,
+    pub u: Float,
+    pub v: Float,
+    pub normal: Vector3<Float>,
+    pub geometric_normal: Vector3<Float>,
+    pub tangent: Vector3<Float>,
+    pub t: Float,
     pub front_face: bool,
     pub material: &'a dyn Material,
+    pub object_id: Option<u32>,
+    pub uv_gradient: Option<[Vector3<Float>; 2]>,
+    pub duv_dx: (Float, Float),
+    pub duv_dy: (Float, Float),
 }
 
 impl<'a> HitRecord<'a> {
     /// Create a hit record.
     pub fn new(
-        point: Vector3<f32>,
-        u: f32,
-        v: f32,
-        normal: Vector3<f32>,
-        t: f32,
+        point: Vector3<Float>,
+        u: Float,
+        v: Float,
+        normal: Vector3<Float>,
+        t: Float,
         front_face: bool,
         material: &'a dyn Material,
     ) -> Self {
@@ -42,9 +54,15 @@ impl<'a> HitRecord<'a> {
             u,
             v,
             normal,
+            geometric_normal: normal,
+            tangent: HitRecord::default_tangent(&normal),
             t,
             front_face,
             material,
+            object_id: None,
+            uv_gradient: None,
+            duv_dx: (0., 0.),
+            duv_dy: (0., 0.),
         }
     }
 
@@ -52,11 +70,11 @@ impl<'a> HitRecord<'a> {
     ///
     /// This uses a [Ray] and the normal to set `front_face`.
     pub fn from_ray(
-        point: Vector3<f32>,
-        u: f32,
-        v: f32,
-        normal: Vector3<f32>,
-        t: f32,
+        point: Vector3<Float>,
+        u: Float,
+        v: Float,
+        normal: Vector3<Float>,
+        t: Float,
         material: &'a dyn Material,
         ray: Ray,
     ) -> Self {
@@ -66,18 +84,176 @@ impl<'a> HitRecord<'a> {
             u,
             v,
             normal,
+            geometric_normal: normal,
+            tangent: HitRecord::default_tangent(&normal),
             t,
             front_face,
             material,
+            object_id: None,
+            uv_gradient: None,
+            duv_dx: (0., 0.),
+            duv_dy: (0., 0.),
         }
     }
 
+    /// Consume `self` and set a shading normal that differs from the geometric normal.
+    ///
+    /// The normal is flipped to the side of the geometric normal and the tangent is made perpendicular to it.
+    pub fn with_shading_normal(mut self, normal: Vector3<Float>) -> Self {
+        let normal = normal.normalize();
+        self.normal = if normal.dot(&self.geometric_normal) < 0. {
+            -normal
+        } else {
+            normal
+        };
+        let tangent = self.tangent;
+        self.with_tangent(tangent)
+    }
+
+    /// Consume `self` and set the tangent, which is made perpendicular to the shading normal.
+    pub fn with_tangent(mut self, tangent: Vector3<Float>) -> Self {
+        let tangent = tangent - tangent.dot(&self.normal) * self.normal;
+        self.tangent = if tangent.norm_squared() > 1e-12 {
+            tangent.normalize()
+        } else {
+            HitRecord::default_tangent(&self.normal)
+        };
+        self
+    }
+
+    /// Consume `self` and set the ID of the object that was hit.
+    pub fn with_object_id(mut self, object_id: u32) -> Self {
+        self.object_id = Some(object_id);
+        self
+    }
+
+    /// Consume `self` and set the gradients of `u` and `v` along the surface, i.e. the vectors whose dot product with a small step on the surface is the change of `u` and `v`.
+    pub fn with_uv_gradient(mut self, du: Vector3<Float>, dv: Vector3<Float>) -> Self {
+        self.uv_gradient = Some([du, dv]);
+        self
+    }
+
+    /// Consume `self` and compute `duv_dx` and `duv_dy` from the [`RayDifferentials`] of `ray`, if it has them and the `uv_gradient` is known.
+    pub fn with_ray_differentials(mut self, ray: Ray) -> Self {
+        let (Some(differentials), Some([du, dv])) = (ray.differentials(), self.uv_gradient) else {
+            return self;
+        };
+        let Some((x_point, y_point)) = self.offset_points(&differentials) else {
+            return self;
+        };
+        let (dx, dy) = (x_point - self.point, y_point - self.point);
+        self.duv_dx = (du.dot(&dx), dv.dot(&dx));
+        self.duv_dy = (du.dot(&dy), dv.dot(&dy));
+        self
+    }
+
+    /// Where the auxiliary [Ray]s of `differentials` hit the tangent plane at `point`.
+    fn offset_points(
+        &self,
+        differentials: &RayDifferentials,
+    ) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        let normal = self.geometric_normal;
+        let plane = normal.dot(&self.point);
+        let intersect = |origin: Vector3<Float>, direction: Vector3<Float>| {
+            let cosine = normal.dot(&direction);
+            (cosine.abs() > 1e-8)
+                .then(|| origin + (plane - normal.dot(&origin)) / cosine * direction)
+        };
+        Some((
+            intersect(differentials.x_origin, differentials.x_direction)?,
+            intersect(differentials.y_origin, differentials.y_direction)?,
+        ))
+    }
+
+    /// Give `scattered` (leaving this hit after `ray` arrived) the [`RayDifferentials`] of `ray`, carried over a specular bounce.
+    ///
+    /// The auxiliary [Ray]s start where they hit the tangent plane. A reflection mirrors them at the half vector of `ray` and `scattered` (so fuzzy reflections also work), and a transmission turns them by the same rotation as `ray`, ignoring the curvature of the surface.
+    /// This should only be used for (nearly) specular bounces, because the footprint of a diffuse bounce is not meaningful.
+    pub fn scatter_differentials(&self, ray: Ray, scattered: Ray) -> Ray {
+        let Some(differentials) = ray.differentials() else {
+            return scattered;
+        };
+        let Some((x_origin, y_origin)) = self.offset_points(&differentials) else {
+            return scattered;
+        };
+
+        let incoming = ray.direction().normalize();
+        let outgoing = scattered.direction().normalize();
+        let reflected = outgoing.dot(&self.geometric_normal) > 0.;
+        let half = (outgoing - incoming).normalize();
+        let direction = |direction: Vector3<Float>| {
+            let direction = direction.normalize();
+            if reflected {
+                reflect(&direction, &half)
+            } else {
+                outgoing + direction - incoming
+            }
+        };
+        scattered.with_differentials(RayDifferentials {
+            x_origin,
+            x_direction: direction(differentials.x_direction),
+            y_origin,
+            y_direction: direction(differentials.y_direction),
+        })
+    }
+
+    pub fn point(&self) -> Vector3<Float> {
+        self.point
+    }
+
+    pub fn uv(&self) -> (Float, Float) {
+        (self.u, self.v)
+    }
+
+    pub fn t(&self) -> Float {
+        self.t
+    }
+
+    pub fn shading_normal(&self) -> Vector3<Float> {
+        self.normal
+    }
+
+    pub fn geometric_normal(&self) -> Vector3<Float> {
+        self.geometric_normal
+    }
+
+    pub fn tangent(&self) -> Vector3<Float> {
+        self.tangent
+    }
+
+    /// Unit vector perpendicular to both the shading normal and the tangent.
+    pub fn bitangent(&self) -> Vector3<Float> {
+        self.normal.cross(&self.tangent)
+    }
+
+    /// Transform a vector from the tangent frame (tangent, bitangent, shading normal) into world coordinates.
+    pub fn to_world(&self, local: Vector3<Float>) -> Vector3<Float> {
+        local.x * self.tangent + local.y * self.bitangent() + local.z * self.normal
+    }
+
+    pub fn front_face(&self) -> bool {
+        self.front_face
+    }
+
     pub fn material(&self) -> &'a dyn Material {
         self.material
     }
 
+    pub fn object_id(&self) -> Option<u32> {
+        self.object_id
+    }
+
+    /// Some unit tangent perpendicular to `normal` (or zero if `normal` is zero).
+    fn default_tangent(normal: &Vector3<Float>) -> Vector3<Float> {
+        if normal.norm_squared() > 0. {
+            orthonormal_basis(&normal.normalize()).0
+        } else {
+            Vector3::zeros()
+        }
+    }
+
     /// Calculate whether the [Ray] hit the front or the back of the surface.
-    fn face_normal(ray: Ray, outward_normal: Vector3<f32>) -> (bool, Vector3<f32>) {
+    fn face_normal(ray: Ray, outward_normal: Vector3<Float>) -> (bool, Vector3<Float>) {
         let front_face = ray.direction().dot(&outward_normal) < 0.;
         let normal = if front_face {
             outward_normal
@@ -2,20 +2,26 @@
 //!
 //! All objects that can be hit by [`Ray`]s and encompassed by [axis-aligned bounding boxes](Aabb) should implement [`Hittable`]. This not only includes shapes, but also more abstract objects like [lists of shapes](HittableList).
 
+use std::cell::Cell;
 use std::cmp::Ordering;
 use std::fmt::{self, Debug};
+use std::io::{self, Write};
 use std::ops::Index;
 use std::sync::Arc;
 
 use nalgebra::Rotation3;
 use rand::Rng;
 
+use crate::accelerator::{Grid, KdTree};
 use crate::hitrecord::HitRecord;
-use crate::ray::Ray;
-use crate::shapes::{Movable, Offset};
+use crate::materials::{Lambertian, Material};
+use crate::random;
+use crate::ray::{Ray, RayKind};
+use crate::shapes::{Cuboid, Movable, Offset, Sphere, Triangle};
+use crate::stats::SceneStats;
 use crate::*;
 
-type HittableArc = Arc<dyn Hittable>;
+pub(crate) type HittableArc = Arc<dyn Hittable>;
 
 /// An abstraction over all objects that can be hit by [Ray]s.
 ///
@@ -30,7 +36,7 @@ pub trait Hittable: Debug + Send + Sync {
     /// - `ray`: [Ray] to check
     /// - `t_min`: Minimum allowed parameter of the ray (excluded).
     /// - `t_max`: Maximum allowed parameter of the ray (excluded).
-    fn hit_origin(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>>;
 
     /// Return the [`Aabb`] that completely encompasses the object at the origin.
     ///
@@ -39,7 +45,7 @@ pub trait Hittable: Debug + Send + Sync {
     /// # Parameters
     /// - `time0`: Start of the interval in which the object should be fully encompassed. Set to `0.` if no time resolution is desired.
     /// - `time1`: End of the interval in which the object should be fully encompassed. Set to `0.` if no time resolution is desired.
-    fn bounding_box_origin(&self, time0: f32, time1: f32) -> Option<Aabb>;
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb>;
 
     /// Check whether a [Ray] hits the object inside an allowed parameter range.
     ///
@@ -50,7 +56,7 @@ pub trait Hittable: Debug + Send + Sync {
     /// - `ray`: [Ray] to check
     /// - `t_min`: Minimum allowed parameter of the ray (excluded).
     /// - `t_max`: Maximum allowed parameter of the ray (excluded).
-    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
         self.center().hit(self, ray, t_min, t_max)
     }
 
@@ -61,7 +67,7 @@ pub trait Hittable: Debug + Send + Sync {
     /// # Parameters
     /// - `time0`: Start of the interval in which the object should be fully encompassed. Set to `0.` if no time resolution is desired.
     /// - `time1`: End of the interval in which the object should be fully encompassed. Set to `0.` if no time resolution is desired.
-    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<Aabb> {
         self.center().bounding_box(self, time0, time1)
     }
 
@@ -83,6 +89,94 @@ pub trait Hittable: Debug + Send + Sync {
 
     /// Get a reference to the center ([`Offset`]) of the [`Hittable`].
     fn center(&self) -> &Offset;
+
+    /// ID of the object if it is [`Named`].
+    fn object_id(&self) -> Option<u32> {
+        None
+    }
+
+    /// Whether the material of the [`Hittable`] emits light.
+    ///
+    /// Emissive [`Hittable`]s that can be [sampled](Hittable::sample_surface) are automatically used as [`Lights`](crate::lights::Lights).
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Surface area of the [`Hittable`] or `0.` if it cannot be sampled.
+    fn area(&self) -> Float {
+        0.
+    }
+
+    /// Sample a uniformly distributed point on the surface of the object at the origin and return it together with the outward normal there.
+    ///
+    /// **Do not manually use this function! This should only be overwritten for new [`shapes`], but not manually used! Use [`sample_surface`](Hittable::sample_surface) instead!**
+    fn sample_surface_origin(&self) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        None
+    }
+
+    /// Sample a uniformly distributed point on the surface and return it together with the outward normal there.
+    ///
+    /// # Parameters
+    /// - `time`: Time at which the object should be sampled.
+    fn sample_surface(&self, time: Float) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        let (point, normal) = self.sample_surface_origin()?;
+        Some((
+            self.center().to_world(point, time),
+            self.center().normal_to_world(normal),
+        ))
+    }
+
+    /// Probability density (with respect to solid angle) that [`random_direction`](Hittable::random_direction) returns `direction` from `origin`.
+    fn pdf_value(&self, origin: Vector3<Float>, direction: Vector3<Float>, time: Float) -> Float {
+        surface_pdf_value(self, origin, direction, time)
+    }
+
+    /// Add the statistics of the object to `stats`, see [`Raytracer::stats`](crate::raytracer::Raytracer::stats).
+    ///
+    /// The default counts the object as a single primitive with an unknown material. Shapes should overwrite this to check their material and whether they are degenerate, containers to add their children.
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        stats.add_primitive(std::mem::size_of_val(self), None, None);
+        if self.is_emissive() {
+            stats.emissive += 1;
+        }
+    }
+
+    /// Sample a direction from `origin` towards the surface of the [`Hittable`].
+    fn random_direction(&self, origin: Vector3<Float>, time: Float) -> Option<Vector3<Float>> {
+        let (point, _) = self.sample_surface(time)?;
+        Some(point - origin)
+    }
+}
+
+/// Probability density (with respect to solid angle) of sampling `direction` from `origin` by choosing a uniformly distributed point on the surface of `hittable`.
+///
+/// This is the default of [`Hittable::pdf_value`]. Shapes that sample directions differently still use it where the density of the points on the surface is needed.
+pub(crate) fn surface_pdf_value<H: Hittable + ?Sized>(
+    hittable: &H,
+    origin: Vector3<Float>,
+    direction: Vector3<Float>,
+    time: Float,
+) -> Float {
+    let area = hittable.area();
+    if area <= 0. {
+        return 0.;
+    }
+
+    match hittable.hit(
+        Ray::new(origin, direction).with_time(time),
+        0.001,
+        Float::INFINITY,
+    ) {
+        Some(hit) => {
+            let distance_squared = (hit.t * direction.norm()).powi(2);
+            let cosine = hit.normal.dot(&direction.normalize()).abs();
+            if cosine <= 0. {
+                return 0.;
+            }
+            distance_squared / (cosine * area)
+        }
+        None => 0.,
+    }
 }
 
 /// Stores a list of [`Hittable`]s.
@@ -97,7 +191,7 @@ pub struct HittableList {
 
 impl HittableList {
     /// Create an empty [`HittableList`].
-    pub fn new(center: Vector3<f32>) -> Self {
+    pub fn new(center: Vector3<Float>) -> Self {
         Self {
             center: Offset::new(center),
             hittables: Vec::new(),
@@ -109,6 +203,71 @@ impl HittableList {
         self.hittables.push(Arc::new(hittable));
     }
 
+    /// Push a [`Sphere`] to the end.
+    pub fn push_sphere<M: Material + Clone + 'static>(
+        &mut self,
+        center: Vector3<Float>,
+        radius: Float,
+        material: M,
+    ) {
+        self.push(Sphere::new(center, radius, material));
+    }
+
+    /// Push a [`Cuboid`] to the end.
+    pub fn push_cuboid<M: Material + Clone + 'static>(
+        &mut self,
+        center: Vector3<Float>,
+        size: Vector3<Float>,
+        material: M,
+    ) {
+        self.push(Cuboid::new(center, size.x, size.y, size.z, material));
+    }
+
+    /// Push a [`Triangle`] to the end.
+    pub fn push_triangle<M: Material + Clone + 'static>(
+        &mut self,
+        a: Vector3<Float>,
+        b: Vector3<Float>,
+        c: Vector3<Float>,
+        material: M,
+    ) {
+        self.push(Triangle::new(a, b, c, material));
+    }
+
+    /// Remove the object with the [`object_id`](Hittable::object_id) `id` and return it.
+    pub fn remove_by_id(&mut self, id: u32) -> Option<HittableArc> {
+        let index = self
+            .hittables
+            .iter()
+            .position(|hittable| hittable.object_id() == Some(id))?;
+        Some(self.hittables.remove(index))
+    }
+
+    /// Replace the object with the [`object_id`](Hittable::object_id) `id` by `hittable` and return whether it was found.
+    pub fn replace_by_id<H: Hittable + 'static>(&mut self, id: u32, hittable: H) -> bool {
+        match self
+            .hittables
+            .iter_mut()
+            .find(|other| other.object_id() == Some(id))
+        {
+            Some(other) => {
+                *other = Arc::new(hittable);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Push a [`Hittable`] that is shared with another container (e.g. [`Lights`](crate::lights::Lights)) to the end.
+    pub(crate) fn push_shared(&mut self, hittable: HittableArc) {
+        self.hittables.push(hittable);
+    }
+
+    /// Iterate over the stored [`Hittable`]s.
+    pub(crate) fn iter(&self) -> std::slice::Iter<'_, HittableArc> {
+        self.hittables.iter()
+    }
+
     /// Clear the [`HittableList`].
     pub fn clear(&mut self) {
         self.hittables.clear();
@@ -157,8 +316,44 @@ impl HittableList {
     }
 }
 
+impl<H: Hittable + 'static> Extend<H> for HittableList {
+    fn extend<I: IntoIterator<Item = H>>(&mut self, iter: I) {
+        self.hittables.extend(
+            iter.into_iter()
+                .map(|hittable| Arc::new(hittable) as HittableArc),
+        );
+    }
+}
+
+/// Collect [`Hittable`]s of the same type, e.g. generated in a loop.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, shapes::Sphere};
+/// let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+/// let mut world: HittableList = (0..10)
+///     .map(|i| Sphere::new(vector![i as Float, 0., 0.], 0.4, material.clone()))
+///     .collect();
+/// world.extend((0..10).map(|i| Sphere::new(vector![i as Float, 1., 0.], 0.4, material.clone())));
+/// world.push_sphere(vector![0., -1000., 0.], 1000., material);
+/// assert_eq!(world.len(), 21);
+/// ```
+impl<H: Hittable + 'static> FromIterator<H> for HittableList {
+    fn from_iter<I: IntoIterator<Item = H>>(iter: I) -> Self {
+        let mut hittable_list = Self::default();
+        hittable_list.extend(iter);
+        hittable_list
+    }
+}
+
+impl<H: Hittable + 'static> From<Vec<H>> for HittableList {
+    fn from(hittables: Vec<H>) -> Self {
+        hittables.into_iter().collect()
+    }
+}
+
 impl Hittable for HittableList {
-    fn hit_origin(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
         let mut hit_record_final: Option<HitRecord> = None;
         let mut closest_so_far = t_max;
 
@@ -172,7 +367,7 @@ impl Hittable for HittableList {
         hit_record_final
     }
 
-    fn bounding_box_origin(&self, time0: f32, time1: f32) -> Option<Aabb> {
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
         if self.hittables.is_empty() {
             return None;
         }
@@ -195,15 +390,25 @@ impl Hittable for HittableList {
     fn center(&self) -> &Offset {
         &self.center
     }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        stats.add_memory(
+            std::mem::size_of::<Self>()
+                + self.hittables.capacity() * std::mem::size_of::<HittableArc>(),
+        );
+        for hittable in &self.hittables {
+            hittable.collect_stats(stats);
+        }
+    }
 }
 
 impl Movable for HittableList {
-    fn with_rotation(mut self, rotation: Rotation3<f32>) -> Self {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
         self.center = self.center.with_rotation(rotation);
         self
     }
 
-    fn moving(mut self, offset_end: Vector3<f32>, time_start: f32, time_end: f32) -> Self {
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
         self.center = self.center.moving(offset_end, time_start, time_end);
         self
     }
@@ -217,6 +422,286 @@ impl Index<usize> for HittableList {
     }
 }
 
+/// A [`Hittable`] with an ID and a name, e.g. to find out which object is seen in a pixel (see [`Raytracer::pick`](crate::raytracer::Raytracer::pick)).
+///
+/// The ID is stored in the [`HitRecord`] of every hit. If [`Named`] objects are nested, the innermost one wins.
+///
+/// # Fields
+/// - `id`: Its ID.
+/// - `name`: Its name.
+/// - `hittable`: The wrapped [`Hittable`].
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, hittable::Named, materials::Lambertian, ray::Ray, shapes::Sphere};
+/// let sphere = Sphere::new(vector![0., 0., -1.], 0.5, Lambertian::solid_color(color![0.5, 0.5, 0.5]));
+/// let named = Named::new(7, "ball", sphere);
+///
+/// let hit = named.hit(Ray::new(vector![0., 0., 0.], vector![0., 0., -1.]), 0., Float::INFINITY).unwrap();
+/// assert_eq!(hit.object_id(), Some(7));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Named<H: Hittable> {
+    center: Offset,
+    id: u32,
+    name: String,
+    hittable: H,
+}
+
+impl<H: Hittable> Named<H> {
+    pub fn new(id: u32, name: impl Into<String>, hittable: H) -> Self {
+        Self {
+            center: Offset::default(),
+            id,
+            name: name.into(),
+            hittable,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn hittable(&self) -> &H {
+        &self.hittable
+    }
+}
+
+impl<H: Hittable> Hittable for Named<H> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let hit = self.hittable.hit(ray, t_min, t_max)?;
+        Some(match hit.object_id {
+            Some(_) => hit,
+            None => hit.with_object_id(self.id),
+        })
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.hittable.bounding_box(time0, time1)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn object_id(&self) -> Option<u32> {
+        Some(self.id)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.hittable.is_emissive()
+    }
+
+    fn area(&self) -> Float {
+        self.hittable.area()
+    }
+
+    fn sample_surface(&self, time: Float) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        self.hittable.sample_surface(time)
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        self.hittable.collect_stats(stats);
+    }
+}
+
+/// A wrapper around a [`Hittable`] that can only be hit from the front (backface culling).
+///
+/// This is useful for one-sided geometry like loaded [meshes](crate::mesh::Mesh) or walls that should not block the view from behind.
+/// [`Ray`]s hitting the back pass through and hit what lies behind.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, hittable::Culled, materials::Lambertian, ray::Ray, shapes::Sphere};
+/// let sphere = Culled::new(Sphere::new(vector![0., 0., 0.], 1., Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+/// // From the outside, the front of the sphere is hit.
+/// assert!(sphere.hit(Ray::new(vector![0., 0., 5.], vector![0., 0., -1.]), 0.001, Float::INFINITY).is_some());
+/// // From the inside, only the back is visible.
+/// assert!(sphere.hit(Ray::new(vector![0., 0., 0.], vector![0., 0., -1.]), 0.001, Float::INFINITY).is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Culled<H: Hittable> {
+    center: Offset,
+    hittable: H,
+}
+
+impl<H: Hittable> Culled<H> {
+    pub fn new(hittable: H) -> Self {
+        Self {
+            center: Offset::default(),
+            hittable,
+        }
+    }
+
+    pub fn hittable(&self) -> &H {
+        &self.hittable
+    }
+}
+
+impl<H: Hittable> Hittable for Culled<H> {
+    fn hit_origin(&self, ray: Ray, mut t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        loop {
+            let hit = self.hittable.hit(ray, t_min, t_max)?;
+            if hit.front_face {
+                return Some(hit);
+            }
+            // Step over the culled hit, scaled so it works at any distance.
+            t_min = hit.t + Float::EPSILON * hit.t.abs().max(1.) * 16.;
+        }
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.hittable.bounding_box(time0, time1)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn object_id(&self) -> Option<u32> {
+        self.hittable.object_id()
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.hittable.is_emissive()
+    }
+
+    fn area(&self) -> Float {
+        self.hittable.area()
+    }
+
+    fn sample_surface(&self, time: Float) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        self.hittable.sample_surface(time)
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        self.hittable.collect_stats(stats);
+    }
+}
+
+/// Which [kinds of `Ray`s](RayKind) see an object, see [`Visible`].
+///
+/// # Fields
+/// - `camera`: Whether the camera sees it directly.
+/// - `shadow`: Whether it casts shadows (and blocks ambient occlusion).
+/// - `specular`: Whether it is seen in reflections and refractions of specular materials.
+/// - `indirect`: Whether it is hit by diffusely scattered light, i.e. whether it contributes to global illumination.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Visibility {
+    pub camera: bool,
+    pub shadow: bool,
+    pub specular: bool,
+    pub indirect: bool,
+}
+
+impl Visibility {
+    /// Visible to all [`Ray`]s.
+    pub const ALL: Self = Self {
+        camera: true,
+        shadow: true,
+        specular: true,
+        indirect: true,
+    };
+
+    /// Whether [`Ray`]s of `kind` see the object. [`Ray`]s without a kind see everything.
+    pub fn sees(&self, kind: Option<RayKind>) -> bool {
+        match kind {
+            None => true,
+            Some(RayKind::Camera) => self.camera,
+            Some(RayKind::Shadow) => self.shadow,
+            Some(RayKind::Specular) => self.specular,
+            Some(RayKind::Indirect) => self.indirect,
+        }
+    }
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// A wrapper around a [`Hittable`] that is only seen by some [kinds of `Ray`s](RayKind), e.g. a light blocker that casts shadows but is invisible to the camera.
+///
+/// The other [`Ray`]s pass through it. Lights that are invisible to the camera are still sampled directly, but lights that cast no shadows cannot be, because shadow [`Ray`]s have to reach them.
+/// The light tracing strategies of the [bidirectional path tracer](crate::integrator::Integrator::Bdpt) ignore whether the camera sees the surfaces they connect to.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, hittable::{Visibility, Visible}, materials::Lambertian, ray::{Ray, RayKind}, shapes::Sphere};
+/// let blocker = Sphere::new(vector![0., 0., -2.], 0.5, Lambertian::solid_color(color![0.5, 0.5, 0.5]));
+/// let blocker = Visible::new(blocker, Visibility { camera: false, ..Default::default() });
+///
+/// let ray = Ray::new(vector![0., 0., 0.], vector![0., 0., -1.]);
+/// assert!(blocker.hit(ray.with_kind(RayKind::Camera), 0.001, Float::INFINITY).is_none());
+/// assert!(blocker.hit(ray.with_kind(RayKind::Shadow), 0.001, Float::INFINITY).is_some());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Visible<H: Hittable> {
+    center: Offset,
+    visibility: Visibility,
+    hittable: H,
+}
+
+impl<H: Hittable> Visible<H> {
+    pub fn new(hittable: H, visibility: Visibility) -> Self {
+        Self {
+            center: Offset::default(),
+            visibility,
+            hittable,
+        }
+    }
+
+    pub fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    pub fn hittable(&self) -> &H {
+        &self.hittable
+    }
+}
+
+impl<H: Hittable> Hittable for Visible<H> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        if !self.visibility.sees(ray.kind()) {
+            return None;
+        }
+        self.hittable.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.hittable.bounding_box(time0, time1)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn object_id(&self) -> Option<u32> {
+        self.hittable.object_id()
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.hittable.is_emissive()
+    }
+
+    fn area(&self) -> Float {
+        self.hittable.area()
+    }
+
+    fn sample_surface(&self, time: Float) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        self.hittable.sample_surface(time)
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        self.hittable.collect_stats(stats);
+    }
+}
+
 /// An axis-aligned bounding box.
 ///
 /// This allows for a simple way to calculate [Ray] hits more easily by first checking for [Aabb]s encompassing the objects.
@@ -226,12 +711,12 @@ impl Index<usize> for HittableList {
 /// - `maximum` Front top right point.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Aabb {
-    pub minimum: Vector3<f32>,
-    pub maximum: Vector3<f32>,
+    pub minimum: Vector3<Float>,
+    pub maximum: Vector3<Float>,
 }
 
 impl Aabb {
-    pub fn new(minimum: Vector3<f32>, maximum: Vector3<f32>) -> Self {
+    pub fn new(minimum: Vector3<Float>, maximum: Vector3<Float>) -> Self {
         Aabb { minimum, maximum }
     }
 
@@ -257,36 +742,52 @@ impl Aabb {
     /// ```
     pub fn surrounding(&self, aabb: &Self) -> Self {
         let minimum = vector![
-            f32::min(self.minimum().x, aabb.minimum().x),
-            f32::min(self.minimum().y, aabb.minimum().y),
-            f32::min(self.minimum().z, aabb.minimum().z)
+            Float::min(self.minimum().x, aabb.minimum().x),
+            Float::min(self.minimum().y, aabb.minimum().y),
+            Float::min(self.minimum().z, aabb.minimum().z)
         ];
         let maximum = vector![
-            f32::max(self.maximum().x, aabb.maximum().x),
-            f32::max(self.maximum().y, aabb.maximum().y),
-            f32::max(self.maximum().z, aabb.maximum().z)
+            Float::max(self.maximum().x, aabb.maximum().x),
+            Float::max(self.maximum().y, aabb.maximum().y),
+            Float::max(self.maximum().z, aabb.maximum().z)
         ];
         Aabb { minimum, maximum }
     }
 
-    pub fn minimum(&self) -> Vector3<f32> {
+    pub fn minimum(&self) -> Vector3<Float> {
         self.minimum
     }
 
-    pub fn maximum(&self) -> Vector3<f32> {
+    pub fn maximum(&self) -> Vector3<Float> {
         self.maximum
     }
 
+    /// Surface area, which is proportional to the probability of a random [`Ray`] hitting the [`Aabb`].
+    pub fn surface_area(&self) -> Float {
+        let size = (self.maximum - self.minimum).map(|length| length.max(0.));
+        2. * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
     /// Check whether a [`Ray`] hits.
     ///
     /// See [`Hittable`] for more details on a similar function with the only difference that this only return a `bool` whether the ray hit.
-    pub fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> bool {
+    pub fn hit(&self, ray: Ray, t_min: Float, t_max: Float) -> bool {
+        self.intersect(ray, t_min, t_max).is_some()
+    }
+
+    /// Parameter range (`t_min`, `t_max`) of the [`Ray`] inside the [`Aabb`], clipped to the allowed range, or [`None`] if it misses.
+    pub fn intersect(
+        &self,
+        ray: Ray,
+        mut t_min: Float,
+        mut t_max: Float,
+    ) -> Option<(Float, Float)> {
         for (((min, max), ray_direction), ray_origin) in self
             .minimum()
             .into_iter()
-            .zip(self.maximum().into_iter())
-            .zip(ray.direction().into_iter())
-            .zip(ray.origin().into_iter())
+            .zip(&self.maximum())
+            .zip(&ray.direction())
+            .zip(&ray.origin())
         {
             let inverse_distance = 1. / ray_direction;
             let mut t0 = (min - ray_origin) * inverse_distance;
@@ -295,13 +796,13 @@ impl Aabb {
                 (t0, t1) = (t1, t0);
             }
 
-            let t_min = if t0 > t_min { t0 } else { t_min };
-            let t_max = if t1 < t_max { t1 } else { t_max };
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
             if t_max <= t_min {
-                return false;
+                return None;
             }
         }
-        true
+        Some((t_min, t_max))
     }
 }
 
@@ -315,32 +816,188 @@ impl fmt::Display for BoundingBoxError {
     }
 }
 
+impl std::error::Error for BoundingBoxError {}
+
 /// Possible nodes in a [`Bvh`].
 ///
 /// [`Bvh`]s are binary trees and might therefore sometimes end with only one node. With this enum, [`Option`] is not needed.
+/// Leaves contain one or two [`Hittable`]s and all other nodes two subtrees.
 #[derive(Clone, Debug)]
 enum BvhNode {
     One(HittableArc),
     Two(HittableArc, HittableArc),
+    Nodes(Box<Bvh>, Box<Bvh>),
+}
+
+impl BvhNode {
+    /// [`Aabb`] encompassing all children.
+    fn bounding_box(&self, time0: Float, time1: Float) -> Result<Aabb, BoundingBoxError> {
+        Ok(match self {
+            BvhNode::One(child) => child.bounding_box(time0, time1).ok_or(BoundingBoxError)?,
+            BvhNode::Two(left, right) => Aabb::surrounding(
+                &left.bounding_box(time0, time1).ok_or(BoundingBoxError)?,
+                &right.bounding_box(time0, time1).ok_or(BoundingBoxError)?,
+            ),
+            BvhNode::Nodes(left, right) => Aabb::surrounding(
+                &left.bounding_box(time0, time1).ok_or(BoundingBoxError)?,
+                &right.bounding_box(time0, time1).ok_or(BoundingBoxError)?,
+            ),
+        })
+    }
+}
+
+/// How [`Bvh::build`] splits the [`Hittable`]s into the two subtrees of each node.
+///
+/// Independent of the strategy, large subtrees are built in parallel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BvhBuildStrategy {
+    /// Sort along a random axis and split in half.
+    #[default]
+    Median,
+    /// Binned surface area heuristic: sort the centers of the [`Aabb`]s into `bins` along the longest axis and split between the bins where the expected cost of a hit test is lowest.
+    ///
+    /// This takes longer to build than [`Median`](BvhBuildStrategy::Median), but results in considerably faster traversal, especially for meshes.
+    Sah { bins: usize },
+}
+
+impl BvhBuildStrategy {
+    /// Split `hittables` into two non-empty halves.
+    fn split(
+        self,
+        mut hittables: HittableList,
+        time0: Float,
+        time1: Float,
+    ) -> (HittableList, HittableList) {
+        let mid = match self {
+            BvhBuildStrategy::Median => {
+                let axis = crate::random::rng().gen_range(0..=2);
+                hittables.sort_by_box(axis);
+                hittables.len() / 2
+            }
+            BvhBuildStrategy::Sah { bins } => {
+                match Self::sah_partition(&mut hittables, bins.max(2), time0, time1) {
+                    Some(mid) => mid,
+                    None => {
+                        hittables.sort_by_box(0);
+                        hittables.len() / 2
+                    }
+                }
+            }
+        };
+        hittables.split_at(mid)
+    }
+
+    /// Reorder `hittables` by the best split found by the surface area heuristic and return the number of [`Hittable`]s in the first half.
+    ///
+    /// Returns [`None`] if all centers coincide or no split separates the [`Hittable`]s.
+    fn sah_partition(
+        hittables: &mut HittableList,
+        bins: usize,
+        time0: Float,
+        time1: Float,
+    ) -> Option<usize> {
+        let boxes: Vec<Aabb> = hittables
+            .iter()
+            .map(|hittable| hittable.bounding_box(time0, time1).unwrap())
+            .collect();
+        let centers: Vec<Vector3<Float>> = boxes
+            .iter()
+            .map(|aabb| (aabb.minimum + aabb.maximum) / 2.)
+            .collect();
+
+        let (min, max) = centers.iter().fold(
+            (
+                Vector3::repeat(Float::INFINITY),
+                Vector3::repeat(Float::NEG_INFINITY),
+            ),
+            |(min, max), center| (min.inf(center), max.sup(center)),
+        );
+        let extent = max - min;
+        let axis = extent.imax();
+        if extent[axis] <= 0. {
+            return None;
+        }
+
+        let bin_of = |center: &Vector3<Float>| {
+            (((center[axis] - min[axis]) / extent[axis] * bins as Float) as usize).min(bins - 1)
+        };
+        let mut counts = vec![0; bins];
+        let mut bin_boxes: Vec<Option<Aabb>> = vec![None; bins];
+        for (aabb, center) in boxes.iter().zip(&centers) {
+            let bin = bin_of(center);
+            counts[bin] += 1;
+            bin_boxes[bin] = Some(match bin_boxes[bin] {
+                Some(bin_box) => bin_box.surrounding(aabb),
+                None => *aabb,
+            });
+        }
+
+        // Sweep from the right to know the cost of all right halves, then from the left to find the cheapest split.
+        let surround = |a: Option<Aabb>, b: Option<Aabb>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.surrounding(&b)),
+            (a, b) => a.or(b),
+        };
+        let mut right_costs = vec![0.; bins];
+        let (mut right_box, mut right_count) = (None, 0);
+        for bin in (1..bins).rev() {
+            right_box = surround(right_box, bin_boxes[bin]);
+            right_count += counts[bin];
+            right_costs[bin] =
+                right_box.map_or(0., |aabb| aabb.surface_area()) * right_count as Float;
+        }
+        let (mut left_box, mut left_count) = (None, 0);
+        let mut best: Option<(usize, Float)> = None;
+        for bin in 0..bins - 1 {
+            left_box = surround(left_box, bin_boxes[bin]);
+            left_count += counts[bin];
+            if left_count == 0 || left_count == hittables.len() {
+                continue;
+            }
+            let cost = left_box.map_or(0., |aabb| aabb.surface_area()) * left_count as Float
+                + right_costs[bin + 1];
+            if best.is_none_or(|(_, best_cost)| cost < best_cost) {
+                best = Some((bin, cost));
+            }
+        }
+        let (split_bin, _) = best?;
+
+        let (left, right): (Vec<_>, Vec<_>) = hittables
+            .hittables
+            .drain(..)
+            .zip(&centers)
+            .partition(|(_, center)| bin_of(center) <= split_bin);
+        let mid = left.len();
+        hittables.hittables = left
+            .into_iter()
+            .chain(right)
+            .map(|(hittable, _)| hittable)
+            .collect();
+        Some(mid)
+    }
 }
 
 /// Bounding Volume Hierarchy.
 ///
-/// This sorts all [`Hittable`]s into a binary tree by a random axis per level (see ['sort_by_box'](HittableList::sort_by_box)).
+/// This sorts all [`Hittable`]s into a binary tree by a random axis per level (see ['sort_by_box'](HittableList::sort_by_box)) or another [`BvhBuildStrategy`].
 /// This enables a more efficient hit search (O(n log n) instead of O(n^2)) by checking the hit for the [`Aabb`] of each subtree first and than propagating down it.
 ///
 /// # Fields
 /// - `aabb`: [`Aabb`] of the subtree/node.
 /// - `left`: Left subtree/node.
 /// - `right`: Right subtree/node.
+/// - `depth`: Number of levels of the tree.
 #[derive(Clone, Debug)]
-pub(crate) struct Bvh {
+pub struct Bvh {
     center: Offset,
     aabb: Aabb,
     subnode: BvhNode,
+    depth: usize,
 }
 
 impl Bvh {
+    /// Number of [`Hittable`]s above which both subtrees are built in parallel.
+    const PARALLEL_THRESHOLD: usize = 1024;
+
     /// Create a new [`Bvh`] from a [`HittableList`] that will be consumed as well as a time range.
     ///
     /// This works recursively. If there is only one or two elements left in the list, they are added to the two subnodes. In all other cases, the list [is sorted by a random axis](HittableList::sort_by_box), split in half, and propagated down.
@@ -350,19 +1007,38 @@ impl Bvh {
     /// - `time0`: Starting time.
     /// - `time1`: Ending time.
     pub fn new(
+        hittables: HittableList,
+        time0: Float,
+        time1: Float,
+    ) -> Result<Self, BoundingBoxError> {
+        Self::build(hittables, time0, time1, BvhBuildStrategy::default())
+    }
+
+    /// Create a new [`Bvh`] like [`new`](Bvh::new), but split the [`Hittable`]s according to a [`BvhBuildStrategy`].
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, hittable::{Bvh, BvhBuildStrategy}, materials::Lambertian, shapes::Sphere};
+    /// let mut world = HittableList::default();
+    /// for i in 0..100 {
+    ///     world.push(Sphere::new(vector![i as Float, 0., 0.], 0.4, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+    /// }
+    /// let bvh = Bvh::build(world, 0., 0., BvhBuildStrategy::Sah { bins: 16 }).unwrap();
+    /// assert_eq!(bvh.depth(), 7);
+    /// ```
+    pub fn build(
         mut hittables: HittableList,
-        time0: f32,
-        time1: f32,
+        time0: Float,
+        time1: Float,
+        strategy: BvhBuildStrategy,
     ) -> Result<Self, BoundingBoxError> {
         if !Bvh::check_hittable_list(&hittables) {
             return Err(BoundingBoxError);
         }
 
-        let mut rand = rand::thread_rng();
-
         let center = hittables.center.clone();
         let subnode: BvhNode;
-        let axis: usize = rand.gen_range(0..=2);
+        let mut depth = 1;
 
         if hittables.len() == 1 {
             let elem = hittables.pop().unwrap();
@@ -370,6 +1046,7 @@ impl Bvh {
         } else if hittables.len() == 2 {
             let last = hittables.pop().unwrap();
             let first = hittables.pop().unwrap();
+            let axis = crate::random::rng().gen_range(0..=2);
             match first.cmp_box(last.as_ref(), axis) {
                 Ordering::Less | Ordering::Equal => {
                     subnode = BvhNode::Two(first, last);
@@ -379,29 +1056,28 @@ impl Bvh {
                 }
             }
         } else {
-            hittables.sort_by_box(axis);
-
-            let mid = hittables.len() / 2;
-            let split = hittables.split_at(mid);
-
-            let left = Arc::new(Bvh::new(split.0, time0, time1)?);
-            let right = Arc::new(Bvh::new(split.1, time0, time1)?);
-
-            subnode = BvhNode::Two(left, right);
+            let parallel = hittables.len() > Self::PARALLEL_THRESHOLD;
+            let (left, right) = strategy.split(hittables, time0, time1);
+
+            let build = |hittables| Bvh::build(hittables, time0, time1, strategy);
+            let (left, right) = if parallel {
+                random::join(|| build(left), || build(right))
+            } else {
+                (build(left), build(right))
+            };
+            let (left, right) = (left?, right?);
+            depth += left.depth.max(right.depth);
+
+            subnode = BvhNode::Nodes(Box::new(left), Box::new(right));
         }
 
-        let aabb = match &subnode {
-            BvhNode::One(child) => child.bounding_box(time0, time1).ok_or(BoundingBoxError)?,
-            BvhNode::Two(left, right) => Aabb::surrounding(
-                &left.bounding_box(time0, time1).ok_or(BoundingBoxError)?,
-                &right.bounding_box(time0, time1).ok_or(BoundingBoxError)?,
-            ),
-        };
+        let aabb = subnode.bounding_box(time0, time1)?;
 
         Ok(Self {
             center,
             aabb,
             subnode,
+            depth,
         })
     }
 
@@ -418,17 +1094,230 @@ impl Bvh {
 
         true
     }
+
+    /// Number of levels of the tree.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Number of nodes of the tree.
+    pub fn node_count(&self) -> usize {
+        match &self.subnode {
+            BvhNode::Nodes(left, right) => 1 + left.node_count() + right.node_count(),
+            BvhNode::One(_) | BvhNode::Two(..) => 1,
+        }
+    }
+
+    /// Replace the object with the [`object_id`](Hittable::object_id) `id` by `hittable` and return whether it was found.
+    ///
+    /// The [`Aabb`]s are not updated, so call [`refit`](Bvh::refit) afterwards if the object moved or changed its size.
+    pub fn replace_by_id<H: Hittable + 'static>(&mut self, id: u32, hittable: H) -> bool {
+        self.replace_arc(id, Arc::new(hittable))
+    }
+
+    fn replace_arc(&mut self, id: u32, hittable: HittableArc) -> bool {
+        match &mut self.subnode {
+            BvhNode::One(child) => {
+                if child.object_id() == Some(id) {
+                    *child = hittable;
+                    return true;
+                }
+                false
+            }
+            BvhNode::Two(left, right) => {
+                for child in [left, right] {
+                    if child.object_id() == Some(id) {
+                        *child = hittable;
+                        return true;
+                    }
+                }
+                false
+            }
+            BvhNode::Nodes(left, right) => {
+                left.replace_arc(id, hittable.clone()) || right.replace_arc(id, hittable)
+            }
+        }
+    }
+
+    /// Update the [`Aabb`]s of all nodes bottom-up without changing the structure of the tree.
+    ///
+    /// This is much faster than building a new [`Bvh`] and keeps it efficient as long as the objects only moved slightly (see [`replace_by_id`](Bvh::replace_by_id)).
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, hittable::{Bvh, Named}, materials::Lambertian, ray::Ray, shapes::Sphere};
+    /// let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+    /// let world: HittableList = (0..8)
+    ///     .map(|i| Named::new(i, "ball", Sphere::new(vector![i as Float * 3., 0., 0.], 1., material.clone())))
+    ///     .collect();
+    /// let mut bvh = Bvh::new(world, 0., 0.).unwrap();
+    ///
+    /// let ray = Ray::new(vector![0., 2.5, 5.], vector![0., 0., -1.]);
+    /// assert!(bvh.hit(ray, 0.001, Float::INFINITY).is_none());
+    ///
+    /// bvh.replace_by_id(0, Named::new(0, "ball", Sphere::new(vector![0., 2., 0.], 1., material)));
+    /// bvh.refit(0., 0.).unwrap();
+    /// assert_eq!(bvh.hit(ray, 0.001, Float::INFINITY).unwrap().object_id(), Some(0));
+    /// ```
+    pub fn refit(&mut self, time0: Float, time1: Float) -> Result<(), BoundingBoxError> {
+        if let BvhNode::Nodes(left, right) = &mut self.subnode {
+            left.refit(time0, time1)?;
+            right.refit(time0, time1)?;
+        }
+        self.aabb = self.subnode.bounding_box(time0, time1)?;
+        Ok(())
+    }
+
+    /// [`Aabb`]s of all nodes together with their level (zero for the root), parents before their children.
+    pub fn boxes(&self) -> Vec<(usize, Aabb)> {
+        let mut boxes = Vec::new();
+        let mut stack = vec![(0, self)];
+        while let Some((level, node)) = stack.pop() {
+            boxes.push((level, node.aabb));
+            if let BvhNode::Nodes(left, right) = &node.subnode {
+                stack.push((level + 1, right));
+                stack.push((level + 1, left));
+            }
+        }
+        boxes
+    }
+
+    /// Write the edges of the [`Aabb`]s of all nodes up to level `max_level` as a Wavefront OBJ file with lines.
+    ///
+    /// Each level is its own group, so e.g. Blender can show the levels separately.
+    pub fn write_obj<W: Write>(&self, mut writer: W, max_level: usize) -> io::Result<()> {
+        let mut vertices = 0;
+        let mut boxes = self.boxes();
+        boxes.retain(|(level, _)| *level <= max_level);
+        boxes.sort_by_key(|(level, _)| *level);
+
+        let mut current_level = None;
+        for (level, aabb) in boxes {
+            if current_level != Some(level) {
+                writeln!(writer, "g level{level}")?;
+                current_level = Some(level);
+            }
+            for corner in 0..8 {
+                let [x, y, z] = [0, 1, 2].map(|axis| {
+                    if corner & (1 << axis) == 0 {
+                        aabb.minimum[axis]
+                    } else {
+                        aabb.maximum[axis]
+                    }
+                });
+                writeln!(writer, "v {x} {y} {z}")?;
+            }
+            // Corners that differ in exactly one axis are connected.
+            for corner in 0..8 {
+                for axis in 0..3 {
+                    if corner & (1 << axis) == 0 {
+                        let other = corner | (1 << axis);
+                        writeln!(
+                            writer,
+                            "l {} {}",
+                            vertices + corner + 1,
+                            vertices + other + 1
+                        )?;
+                    }
+                }
+            }
+            vertices += 8;
+        }
+        Ok(())
+    }
+
+    /// Build a wireframe of the [`Aabb`]s of all nodes up to level `max_level` from thin [`Cuboid`]s, which can be pushed to the `world` (without the [`Bvh`] itself) to inspect how the tree partitions the scene.
+    ///
+    /// The edges are `thickness` wide and colored by their level.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, hittable::Bvh, materials::Lambertian, shapes::Sphere};
+    /// let mut world = HittableList::default();
+    /// for i in 0..4 {
+    ///     world.push(Sphere::new(vector![i as Float * 3., 0., 0.], 1., Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+    /// }
+    /// let bvh = Bvh::new(world.clone(), 0., 0.).unwrap();
+    ///
+    /// let mut raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 16, 9, 1, 4);
+    /// raytracer.world = world;
+    /// raytracer.world.push(bvh.to_wireframe(0.02, 2));
+    /// let image = raytracer.render().unwrap();
+    ///
+    /// let mut obj = Vec::new();
+    /// bvh.write_obj(&mut obj, 2).unwrap();
+    /// ```
+    pub fn to_wireframe(&self, thickness: Float, max_level: usize) -> HittableList {
+        let colors = [
+            color![1., 0.2, 0.2],
+            color![1., 0.6, 0.1],
+            color![0.9, 0.9, 0.1],
+            color![0.2, 0.9, 0.2],
+            color![0.2, 0.6, 1.],
+            color![0.7, 0.3, 1.],
+        ];
+
+        let mut wireframe = HittableList::default();
+        for (level, aabb) in self.boxes() {
+            if level > max_level {
+                continue;
+            }
+            let material = Arc::new(Lambertian::solid_color(colors[level % colors.len()]));
+            let size = aabb.maximum - aabb.minimum;
+            for axis in 0..3 {
+                let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+                for corner in 0..4 {
+                    let mut center = (aabb.minimum + aabb.maximum) / 2.;
+                    center[a] = if corner & 1 == 0 {
+                        aabb.minimum[a]
+                    } else {
+                        aabb.maximum[a]
+                    };
+                    center[b] = if corner & 2 == 0 {
+                        aabb.minimum[b]
+                    } else {
+                        aabb.maximum[b]
+                    };
+                    let mut dimensions = Vector3::repeat(thickness);
+                    dimensions[axis] = size[axis] + thickness;
+                    wireframe.push(Cuboid::new(
+                        center,
+                        dimensions.x,
+                        dimensions.y,
+                        dimensions.z,
+                        material.clone(),
+                    ));
+                }
+            }
+        }
+        wireframe
+    }
 }
 
 impl Hittable for Bvh {
-    fn hit_origin(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        TraversalCost::record(1, 0);
         if !self.aabb.hit(ray, t_min, t_max) {
             return None;
         }
 
         match &self.subnode {
-            BvhNode::One(child) => child.hit(ray, t_min, t_max),
+            BvhNode::One(child) => {
+                TraversalCost::record(0, 1);
+                child.hit(ray, t_min, t_max)
+            }
             BvhNode::Two(left, right) => {
+                TraversalCost::record(0, 2);
+                let hit_left = left.hit(ray, t_min, t_max);
+                let t_max = match &hit_left {
+                    Some(hit_record) => hit_record.t,
+                    None => t_max,
+                };
+                let hit_right = right.hit(ray, t_min, t_max);
+
+                hit_right.or(hit_left)
+            }
+            BvhNode::Nodes(left, right) => {
                 let hit_left = left.hit(ray, t_min, t_max);
                 let t_max = match &hit_left {
                     Some(hit_record) => hit_record.t,
@@ -441,28 +1330,116 @@ impl Hittable for Bvh {
         }
     }
 
-    fn bounding_box_origin(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
         Some(self.aabb)
     }
 
     fn center(&self) -> &Offset {
         &self.center
     }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        stats.add_memory(std::mem::size_of::<Self>());
+        match &self.subnode {
+            BvhNode::One(child) => child.collect_stats(stats),
+            BvhNode::Two(left, right) => {
+                left.collect_stats(stats);
+                right.collect_stats(stats);
+            }
+            BvhNode::Nodes(left, right) => {
+                left.collect_stats(stats);
+                right.collect_stats(stats);
+            }
+        }
+    }
+}
+
+/// Number of [`Bvh`] nodes visited and primitives tested while finding hits.
+///
+/// Every thread counts the cost of its traversals, which is e.g. used by [`Integrator::TraversalHeatmap`](crate::integrator::Integrator::TraversalHeatmap).
+///
+/// # Fields
+/// - `nodes`: Number of visited [`Bvh`] nodes (their [`Aabb`] was tested).
+/// - `primitives`: Number of [`Hittable`]s tested in the leaves of [`Bvh`]s.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, hittable::{Bvh, TraversalCost}, materials::Lambertian, ray::Ray, shapes::Sphere};
+/// let mut world = HittableList::default();
+/// for i in 0..8 {
+///     world.push(Sphere::new(vector![i as Float * 3., 0., 0.], 1., Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+/// }
+/// let bvh = Bvh::new(world, 0., 0.).unwrap();
+///
+/// TraversalCost::take();
+/// bvh.hit(Ray::new(vector![0., 0., 5.], vector![0., 0., -1.]), 0.001, Float::INFINITY);
+/// let cost = TraversalCost::take();
+/// assert!(cost.nodes > 0 && cost.primitives > 0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TraversalCost {
+    pub nodes: u32,
+    pub primitives: u32,
+}
+
+thread_local! {
+    static TRAVERSAL_COST: Cell<TraversalCost> = const {
+        Cell::new(TraversalCost {
+            nodes: 0,
+            primitives: 0,
+        })
+    };
+}
+
+impl TraversalCost {
+    /// Return the cost counted by this thread since the last call and reset it.
+    pub fn take() -> Self {
+        TRAVERSAL_COST.with(|cost| cost.take())
+    }
+
+    /// Total number of tests.
+    pub fn total(&self) -> u32 {
+        self.nodes + self.primitives
+    }
+
+    pub(crate) fn record(nodes: u32, primitives: u32) {
+        TRAVERSAL_COST.with(|cost| {
+            let mut current = cost.get();
+            current.nodes += nodes;
+            current.primitives += primitives;
+            cost.set(current);
+        });
+    }
 }
 
 /// Options to store [`Hittable`]s.
 ///
 /// Both [`HittableList`] and [`Bvh`] can store [`Hittable`]s. Latter is faster, but not always possible (see [`BoundingBoxError`], e.g. an infinite plane).
+/// The same holds for the other [`Accelerator`](crate::accelerator::Accelerator)s.
 pub(crate) enum HittableListOptions {
     HittableList(HittableList),
     Bvh(Bvh),
+    KdTree(KdTree),
+    Grid(Grid),
+}
+
+impl HittableListOptions {
+    /// Check whether a [`Ray`] hits any of the stored [`Hittable`]s (see [`Hittable::hit`]).
+    pub fn hit(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let hit = match self {
+            HittableListOptions::HittableList(world) => world.hit(ray, t_min, t_max),
+            HittableListOptions::Bvh(world) => world.hit(ray, t_min, t_max),
+            HittableListOptions::KdTree(world) => world.hit(ray, t_min, t_max),
+            HittableListOptions::Grid(world) => world.hit(ray, t_min, t_max),
+        };
+        // The footprint is computed in world space, after all transformations of the hit.
+        hit.map(|hit| hit.with_ray_differentials(ray))
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::materials::Lambertian;
-    use crate::shapes::Sphere;
     use crate::textures::SolidColor;
 
     #[test]
@@ -484,18 +1461,57 @@ mod test {
             aabb,
             subnode,
             center: Offset::default(),
+            depth: 1,
         };
 
         let ray_hit_left = Ray::new(vector![0., 0., 0.], vector![-2., 0., -1.]);
-        let hit_left = bvh.hit(ray_hit_left, 0., f32::INFINITY);
+        let hit_left = bvh.hit(ray_hit_left, 0., Float::INFINITY);
         assert!(hit_left.is_some());
 
         let ray_hit_right = Ray::new(vector![0., 0., 0.], vector![2., 0., -1.]);
-        let hit_right = bvh.hit(ray_hit_right, 0., f32::INFINITY);
+        let hit_right = bvh.hit(ray_hit_right, 0., Float::INFINITY);
         assert!(hit_right.is_some());
 
         let ray_no_hit = Ray::new(vector![0., 0., 0.], vector![0., 0., 1.]);
-        let no_hit = bvh.hit(ray_no_hit, 0., f32::INFINITY);
+        let no_hit = bvh.hit(ray_no_hit, 0., Float::INFINITY);
         assert!(no_hit.is_none());
     }
+
+    #[test]
+    fn accelerators_agree() {
+        let mut rng = crate::random::rng();
+        let mut world = HittableList::default();
+        for _ in 0..2000 {
+            let center = vector![
+                rng.gen_range(-10.0..10.),
+                rng.gen_range(-10.0..10.),
+                rng.gen_range(-10.0..10.)
+            ];
+            world.push(Sphere::new(
+                center,
+                rng.gen_range(0.05..0.3),
+                Lambertian::solid_color(color![0.5, 0.5, 0.5]),
+            ));
+        }
+        let median = Bvh::build(world.clone(), 0., 0., BvhBuildStrategy::Median).unwrap();
+        let sah = Bvh::build(world.clone(), 0., 0., BvhBuildStrategy::Sah { bins: 12 }).unwrap();
+        let kd_tree = KdTree::new(world.clone(), 0., 0.).unwrap();
+        let grid = Grid::new(world.clone(), 0., 0.).unwrap();
+
+        for _ in 0..500 {
+            let direction = vector![
+                rng.gen_range(-1.0..1.),
+                rng.gen_range(-1.0..1.),
+                rng.gen_range(-1.0..1.)
+            ];
+            let ray = Ray::new(vector![0., 0., 0.], direction);
+            let t = |hittable: &dyn Hittable| {
+                hittable.hit(ray, 0.001, Float::INFINITY).map(|hit| hit.t)
+            };
+            assert_eq!(t(&world), t(&median));
+            assert_eq!(t(&world), t(&sah));
+            assert_eq!(t(&world), t(&kd_tree));
+            assert_eq!(t(&world), t(&grid));
+        }
+    }
 }
@@ -0,0 +1,245 @@
+//! Photometric profiles of real light fixtures in the IES LM-63 format, as published by manufacturers.
+//!
+//! An [`IesProfile`] describes how the intensity of a fixture varies with the direction of emission.
+//! It is applied to a [`PointLight`](crate::lights::PointLight) or [`SpotLight`](crate::lights::SpotLight) with their `with_profile` methods, which scale their intensity by the [normalized](IesProfile::intensity) profile.
+//!
+//! Only type C photometry is supported, which is used by virtually all architectural fixtures: the vertical angle is measured from the nadir (straight down, 0°) to the zenith (180°), the horizontal angle around the vertical axis.
+//! In the coordinates of the profile, the nadir is −y and the horizontal angles 0° and 90° are +x and +z.
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::float::consts::PI;
+use crate::*;
+
+/// A photometric profile.
+///
+/// # Fields
+/// - `vertical_angles`: Increasing vertical angles (in degrees) of the measurements.
+/// - `horizontal_angles`: Increasing horizontal angles (in degrees) of the measurements.
+/// - `candela`: Measured intensities divided by the maximum, for each horizontal angle all vertical ones.
+/// - `max_intensity`: Maximum intensity in candela.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, ies::IesProfile, lights::{Light, PointLight}};
+/// // A downlight that emits nothing above the horizon
+/// let profile = IesProfile::parse(
+///     "IESNA:LM-63-2002\n[MANUFAC] Example\nTILT=NONE\n\
+///      1 1000 1 3 1 1 2 0 0 0\n1 1 10\n0 45 90\n0\n200 100 0\n",
+/// )
+/// .unwrap();
+/// assert_eq!(profile.max_intensity(), 200.);
+///
+/// let light = PointLight::new(vector![0., 2., 0.], color![1., 1., 1.])
+///     .with_profile(profile, nalgebra::Rotation3::identity());
+/// let below = light.sample(vector![0., 0., 0.]).unwrap();
+/// assert!((below.radiance.r() - 0.25).abs() < 1e-5);
+/// assert!(light.sample(vector![0., 4., 0.]).is_none());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct IesProfile {
+    vertical_angles: Vec<Float>,
+    horizontal_angles: Vec<Float>,
+    candela: Vec<Float>,
+    max_intensity: Float,
+}
+
+impl IesProfile {
+    /// Read a profile from an `.ies` file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse a profile in the IES LM-63 format (1986 to 2019).
+    ///
+    /// Returns [`Error::Config`] for missing or invalid numbers, tilt data in a separate file, and photometry other than type C.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let invalid = |message: &str| Error::Config(format!("invalid IES profile: {message}"));
+
+        let mut lines = text.lines();
+        let tilt = lines
+            .by_ref()
+            .find_map(|line| line.trim().strip_prefix("TILT="))
+            .ok_or_else(|| invalid("no TILT line"))?
+            .trim();
+        let mut numbers = lines
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+                word.parse::<Float>()
+                    .map_err(|_| invalid(&format!("invalid number {word:?}")))
+            });
+        let mut next = || {
+            numbers
+                .next()
+                .unwrap_or_else(|| Err(invalid("too few numbers")))
+        };
+        #[allow(clippy::unnecessary_cast)]
+        let count = |next: &mut dyn FnMut() -> Result<Float, Error>| -> Result<usize, Error> {
+            let value = next()?;
+            if value < 0. || value.fract() != 0. {
+                return Err(invalid("invalid count"));
+            }
+            Ok(value as usize)
+        };
+
+        match tilt {
+            "NONE" => {}
+            // The tilt only matters for lamps that are mounted at an angle, so its data is skipped.
+            "INCLUDE" => {
+                next()?;
+                let tilt_angles = count(&mut next)?;
+                for _ in 0..2 * tilt_angles {
+                    next()?;
+                }
+            }
+            _ => return Err(invalid("tilt data in a separate file is not supported")),
+        }
+
+        let _lamps = next()?;
+        let _lumens = next()?;
+        let multiplier = next()?;
+        let vertical_count = count(&mut next)?;
+        let horizontal_count = count(&mut next)?;
+        if next()? != 1. {
+            return Err(invalid("only type C photometry is supported"));
+        }
+        for _ in 0..4 {
+            // Units and size of the luminous opening
+            next()?;
+        }
+        let ballast = next()?;
+        let _ballast_lamp = next()?;
+        let _watts = next()?;
+
+        let mut read = |count: usize| (0..count).map(|_| next()).collect::<Result<Vec<_>, _>>();
+        let vertical_angles = read(vertical_count)?;
+        let horizontal_angles = read(horizontal_count)?;
+        let mut candela = read(vertical_count * horizontal_count)?;
+        if vertical_angles.is_empty() || horizontal_angles.is_empty() {
+            return Err(invalid("no angles"));
+        }
+        let increasing = |angles: &[Float]| angles.windows(2).all(|pair| pair[0] < pair[1]);
+        if !increasing(&vertical_angles) || !increasing(&horizontal_angles) {
+            return Err(invalid("angles have to be increasing"));
+        }
+
+        let max = candela.iter().copied().fold(0., Float::max);
+        if max > 0. {
+            candela.iter_mut().for_each(|value| *value /= max);
+        }
+        Ok(Self {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+            max_intensity: max * multiplier * ballast,
+        })
+    }
+
+    /// Maximum intensity in candela, e.g. for choosing the intensity of the light.
+    pub fn max_intensity(&self) -> Float {
+        self.max_intensity
+    }
+
+    /// Intensity emitted into `direction` (in the coordinates of the profile) relative to the [maximum](IesProfile::max_intensity).
+    ///
+    /// The intensity is interpolated bilinearly between the measurements and zero outside of the measured vertical angles.
+    pub fn intensity(&self, direction: Vector3<Float>) -> Float {
+        let direction = direction.normalize();
+        let vertical = (-direction.y).clamp(-1., 1.).acos().to_degrees();
+        let mut horizontal = direction.z.atan2(direction.x).to_degrees().rem_euclid(360.);
+
+        // Measurements of symmetric fixtures only cover a part of the horizontal angles.
+        let last = self.horizontal_angles[self.horizontal_angles.len() - 1];
+        if last <= 90. {
+            horizontal %= 180.;
+            if horizontal > 90. {
+                horizontal = 180. - horizontal;
+            }
+        } else if last <= 180. && horizontal > 180. {
+            horizontal = 360. - horizontal;
+        }
+
+        let (first_vertical, last_vertical) = (
+            self.vertical_angles[0],
+            self.vertical_angles[self.vertical_angles.len() - 1],
+        );
+        if vertical < first_vertical - 1e-3 || vertical > last_vertical + 1e-3 {
+            return 0.;
+        }
+        let (v0, v1, tv) = interval(&self.vertical_angles, vertical);
+        let (h0, h1, th) = interval(&self.horizontal_angles, horizontal);
+        let value = |h: usize, v: usize| self.candela[h * self.vertical_angles.len() + v];
+        (1. - th) * ((1. - tv) * value(h0, v0) + tv * value(h0, v1))
+            + th * ((1. - tv) * value(h1, v0) + tv * value(h1, v1))
+    }
+
+    /// Solid angle (in steradians) a uniform light with the [maximum](IesProfile::max_intensity) intensity would need to emit the same power.
+    ///
+    /// This is 4π for an isotropic profile and can be used to compare the brightness of profiles.
+    pub fn solid_angle(&self) -> Float {
+        let steps = 64;
+        let mut sum = 0.;
+        for i in 0..steps {
+            let cos_theta = 1. - 2. * (i as Float + 0.5) / steps as Float;
+            let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+            for j in 0..2 * steps {
+                let phi = PI * (j as Float + 0.5) / steps as Float;
+                sum += self.intensity(vector![
+                    sin_theta * phi.cos(),
+                    cos_theta,
+                    sin_theta * phi.sin()
+                ]);
+            }
+        }
+        4. * PI * sum / (2 * steps * steps) as Float
+    }
+}
+
+/// The indices of the measurements around `angle` in the increasing `angles` and the interpolation weight of the second one.
+fn interval(angles: &[Float], angle: Float) -> (usize, usize, Float) {
+    let upper = angles.partition_point(|&a| a < angle);
+    if upper == 0 {
+        return (0, 0, 0.);
+    }
+    if upper == angles.len() {
+        return (upper - 1, upper - 1, 0.);
+    }
+    let (a0, a1) = (angles[upper - 1], angles[upper]);
+    (upper - 1, upper, (angle - a0) / (a1 - a0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn symmetries() {
+        // Bilateral symmetry: measured from 0° to 180°, brighter towards +x
+        let profile = IesProfile::parse(
+            "IESNA91\nTILT=INCLUDE\n1\n2\n0 90\n1 1\n\
+             1 -1 2 2 3 1 1 0.1 0.1 0\n0.5 1 20\n\
+             0 180\n0, 90, 180\n100 100\n50 50\n10 10\n",
+        )
+        .unwrap();
+        assert_eq!(profile.max_intensity(), 100.);
+        assert!((profile.intensity(vector![1., 0., 0.]) - 1.).abs() < 1e-5);
+        assert!((profile.intensity(vector![0., 0., 1.]) - 0.5).abs() < 1e-5);
+        // Mirrored from 90° (+z)
+        assert!((profile.intensity(vector![0., 0., -1.]) - 0.5).abs() < 1e-5);
+        assert!((profile.intensity(vector![-1., 0., 0.]) - 0.1).abs() < 1e-5);
+
+        // Isotropic
+        let isotropic =
+            IesProfile::parse("TILT=NONE\n1 -1 1 2 1 1 1 0 0 0\n1 1 0\n0 180\n0\n7 7\n").unwrap();
+        assert!((isotropic.solid_angle() - 4. * PI).abs() < 1e-2);
+
+        assert!(IesProfile::parse("TILT=lamp.tlt\n").is_err());
+        assert!(
+            IesProfile::parse("TILT=NONE\n1 -1 1 2 1 3 1 0 0 0\n1 1 0\n0 90\n0\n1 1\n").is_err()
+        );
+        assert!(IesProfile::parse("TILT=NONE\n1 -1 1 2 1 1 1 0 0 0\n1 1 0\n0 90\n0\n1\n").is_err());
+    }
+}
@@ -0,0 +1,219 @@
+//! Two-level acceleration structure for instanced geometry.
+//!
+//! Each mesh or group of primitives is sorted into its own [`Bvh`] (bottom-level acceleration structure) once and shared via an [`Arc`].
+//! [`Instance`]s place it in the world with their own [`Offset`], and a [`Tlas`] (top-level acceleration structure) is a small [`Bvh`] over the instances only.
+//! Moving instances between frames therefore only requires [rebuilding the `Tlas`](Tlas::rebuild), not the geometry.
+
+use std::sync::Arc;
+
+use nalgebra::Rotation3;
+
+use crate::hitrecord::HitRecord;
+use crate::hittable::{Aabb, BoundingBoxError, Bvh};
+use crate::ray::Ray;
+use crate::shapes::{Movable, Offset};
+use crate::stats::SceneStats;
+use crate::*;
+
+/// A placement of shared geometry in the world.
+///
+/// # Fields
+/// - `center`: [`Offset`] of this placement.
+/// - `blas`: Shared [`Bvh`] of the geometry in its local coordinates.
+#[derive(Clone, Debug)]
+pub struct Instance {
+    center: Offset,
+    blas: Arc<Bvh>,
+}
+
+impl Instance {
+    /// Place `blas` at `position`.
+    pub fn new(blas: Arc<Bvh>, position: Vector3<Float>) -> Self {
+        Self {
+            center: Offset::new(position),
+            blas,
+        }
+    }
+
+    /// Replace the [`Offset`], e.g. to move the [`Instance`] for the next frame.
+    pub fn set_transform(&mut self, center: Offset) {
+        self.center = center;
+    }
+
+    /// The shared geometry.
+    pub fn blas(&self) -> &Arc<Bvh> {
+        &self.blas
+    }
+}
+
+impl Hittable for Instance {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        self.blas.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.blas.bounding_box(time0, time1)
+    }
+
+    /// Transform all corners of the local [`Aabb`], so that rotated instances are still fully encompassed.
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        let local = self.bounding_box_origin(time0, time1)?;
+
+        let mut minimum = Vector3::repeat(Float::INFINITY);
+        let mut maximum = Vector3::repeat(Float::NEG_INFINITY);
+        for time in [time0, time1] {
+            for corner in 0..8 {
+                let point = Vector3::from_fn(|axis, _| {
+                    if corner & (1 << axis) == 0 {
+                        local.minimum[axis]
+                    } else {
+                        local.maximum[axis]
+                    }
+                });
+                let point = self.center.to_world(point, time);
+                minimum = minimum.inf(&point);
+                maximum = maximum.sup(&point);
+            }
+        }
+
+        Some(Aabb::new(minimum, maximum))
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.blas.is_emissive()
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        self.blas.collect_stats(stats);
+    }
+}
+
+impl Movable for Instance {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+/// Top-level acceleration structure over [`Instance`]s.
+///
+/// After changing the [`Instance`]s via [`instance_mut`](Tlas::instance_mut) or [`push`](Tlas::push), call [`rebuild`](Tlas::rebuild) before rendering the next frame.
+/// This only sorts the instances instead of all of their geometry.
+///
+/// # Fields
+/// - `instances`: All [`Instance`]s.
+/// - `bvh`: [`Bvh`] over the `instances` or [`None`] if there are none.
+/// - `time0`: Start of the time interval used for the [`Aabb`]s.
+/// - `time1`: End of the time interval used for the [`Aabb`]s.
+///
+/// # Example
+/// ```
+/// # use std::sync::Arc;
+/// # use ray_tracing_in_one_weekend::{*, hittable::Bvh, instancing::*, materials::Lambertian, ray::Ray, shapes::{Offset, Sphere}};
+/// let mut geometry = HittableList::default();
+/// geometry.push(Sphere::new(vector![0., 0., 0.], 1., Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+/// geometry.push(Sphere::new(vector![0., 1.5, 0.], 0.5, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+/// let blas = Arc::new(Bvh::new(geometry, 0., 0.).unwrap());
+///
+/// let instances = (0..3).map(|i| Instance::new(blas.clone(), vector![i as Float * 3., 0., 0.])).collect();
+/// let mut tlas = Tlas::new(instances, 0., 0.).unwrap();
+/// let ray = Ray::new(vector![0., 0., 5.], vector![0., 0., -1.]);
+/// assert!(tlas.hit(ray, 0.001, Float::INFINITY).is_some());
+///
+/// tlas.instance_mut(0).set_transform(Offset::new(vector![0., 10., 0.]));
+/// tlas.rebuild().unwrap();
+/// assert!(tlas.hit(ray, 0.001, Float::INFINITY).is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Tlas {
+    center: Offset,
+    instances: Vec<Instance>,
+    bvh: Option<Bvh>,
+    time0: Float,
+    time1: Float,
+}
+
+impl Tlas {
+    /// Create a new [`Tlas`] over `instances` whose [`Aabb`]s encompass them from `time0` to `time1`.
+    pub fn new(
+        instances: Vec<Instance>,
+        time0: Float,
+        time1: Float,
+    ) -> Result<Self, BoundingBoxError> {
+        let mut tlas = Self {
+            center: Offset::default(),
+            instances,
+            bvh: None,
+            time0,
+            time1,
+        };
+        tlas.rebuild()?;
+        Ok(tlas)
+    }
+
+    /// Add an [`Instance`]. It is only hit after the next [`rebuild`](Tlas::rebuild).
+    pub fn push(&mut self, instance: Instance) {
+        self.instances.push(instance);
+    }
+
+    /// Get the [`Instance`] at `index` to change it. The change only takes effect after the next [`rebuild`](Tlas::rebuild).
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn instance_mut(&mut self, index: usize) -> &mut Instance {
+        &mut self.instances[index]
+    }
+
+    /// All [`Instance`]s.
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    /// Rebuild the top-level [`Bvh`] from the current [`Instance`]s while keeping their geometry.
+    pub fn rebuild(&mut self) -> Result<(), BoundingBoxError> {
+        if self.instances.is_empty() {
+            self.bvh = None;
+            return Ok(());
+        }
+
+        let mut list = HittableList::default();
+        for instance in &self.instances {
+            list.push(instance.clone());
+        }
+        self.bvh = Some(Bvh::new(list, self.time0, self.time1)?);
+        Ok(())
+    }
+}
+
+impl Hittable for Tlas {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        self.bvh.as_ref()?.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.bvh.as_ref()?.bounding_box(time0, time1)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.instances.iter().any(Instance::is_emissive)
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        for instance in &self.instances {
+            instance.collect_stats(stats);
+        }
+    }
+}
@@ -0,0 +1,791 @@
+//! Integrators that compute the light arriving along a camera [`Ray`].
+
+mod bdpt;
+mod polarized;
+pub(crate) mod sppm;
+pub(crate) mod wavefront;
+
+use crate::background::{Background, EnvironmentMap};
+use crate::color::{BLACK, WHITE};
+use crate::hittable::{HittableListOptions, TraversalCost};
+use crate::lights::Lights;
+use crate::lpe::LightPathExpression;
+use crate::ray::{Ray, RayKind};
+use crate::vec3::{near_zero, random_unit_vector_in_unit_sphere};
+use crate::volume::Fog;
+use crate::*;
+
+/// Algorithm that computes the light arriving along a camera [`Ray`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Integrator {
+    /// Unidirectional path tracing with next-event estimation.
+    #[default]
+    PathTracer,
+    /// Bidirectional path tracing.
+    ///
+    /// Traces subpaths from both the camera and the area lights and connects them.
+    /// This converges much faster for scenes with light that is only visible through glass or small openings, but each sample is more expensive.
+    Bdpt,
+    /// Stochastic progressive photon mapping.
+    ///
+    /// Each of the `samples_per_pixel` iterations emits `photons_per_iteration` photons from the area lights and gathers them at the first diffuse surface seen through each pixel.
+    /// The gathering radius starts at `initial_radius` (which should be about the size of a few pixels in the scene) and shrinks over the iterations.
+    /// This renders caustics, e.g. below glass spheres, that the path tracers leave extremely noisy.
+    Sppm {
+        photons_per_iteration: usize,
+        initial_radius: Float,
+    },
+    /// Ambient occlusion.
+    ///
+    /// Every surface is shaded white with the fraction of `samples` cosine-weighted [`Ray`]s that are not blocked within `radius`.
+    /// Materials and lights are ignored, so this is a fast preview of the geometry.
+    AmbientOcclusion { radius: Float, samples: u16 },
+    /// Classic Whitted ray tracing.
+    ///
+    /// Specular materials reflect and refract perfectly, and all other materials only receive direct light with hard shadows.
+    /// Each area light is treated like a point at its center that emits the light of the whole area, so there is no noise (apart from lights with a radius).
+    /// This is fast and clean, but misses all indirect light.
+    Whitted,
+    /// Debug view of the cost of finding the first hit of each camera [`Ray`] in the [`Bvh`](crate::hittable::Bvh).
+    ///
+    /// The pixels are colored from blue (no tests) over green and yellow to red (at least `max_cost` visited nodes and tested primitives, see [`TraversalCost`](crate::hittable::TraversalCost)).
+    /// This shows where the acceleration structure performs badly, e.g. at large overlapping objects. Without a [`Bvh`](crate::hittable::Bvh), everything is blue.
+    TraversalHeatmap { max_cost: u32 },
+    /// Path tracing of polarized light, seen through a linear polarizing filter at the angle `polarizer` (in radians), or without a filter.
+    ///
+    /// Paths follow the polarization through the Fresnel reflection and refraction of [`Dielectric`](crate::materials::Dielectric) and [`Metal`](crate::materials::Metal) materials (see [`Material::fresnel`](crate::materials::Material::fresnel)), all other materials depolarize light.
+    /// The angle of the filter is measured counterclockwise from the horizontal of a camera whose up direction is +y. Turning it suppresses reflections on glass and water, like a real polarizing filter.
+    Polarized { polarizer: Option<Float> },
+}
+
+impl Integrator {
+    /// Compute the light arriving along `ray`.
+    pub(crate) fn radiance(
+        &self,
+        scene: &Scene,
+        ray: Ray,
+        max_depth: u16,
+        settings: &IntegratorSettings,
+    ) -> Color {
+        match self {
+            Integrator::PathTracer => path_trace(scene, ray, max_depth, settings),
+            Integrator::Bdpt => bdpt::bdpt(scene, ray, max_depth, settings),
+            Integrator::Sppm { .. } => unreachable!("photon mapping renders whole images"),
+            Integrator::AmbientOcclusion { radius, samples } => {
+                ambient_occlusion(scene, ray, *radius, *samples)
+            }
+            Integrator::Whitted => whitted(scene, ray, max_depth),
+            Integrator::TraversalHeatmap { max_cost } => traversal_heatmap(scene, ray, *max_cost),
+            Integrator::Polarized { polarizer } => {
+                polarized::polarized_path_trace(scene, ray, max_depth, settings, *polarizer)
+            }
+        }
+    }
+}
+
+/// Options that trade noise for bias or speed.
+///
+/// By default, paths are only terminated at the maximum depth and samples are not clamped, so the result is unbiased.
+///
+/// # Fields
+/// - `russian_roulette_depth`: Bounce from which on paths are terminated randomly. [`None`] disables Russian roulette.
+/// - `russian_roulette_probability`: Probability that a path continues at each bounce after `russian_roulette_depth`.
+/// - `max_radiance`: Largest value a color channel of a single sample may have. Brighter samples are scaled down (keeping their hue), which removes fireflies but makes the image darker.
+/// - `path_filter`: [`LightPathExpression`] that the paths of the path tracers have to match to contribute.
+/// - `ray_offset`: [`RayOffset`] of [`Ray`]s leaving surfaces.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, color::BLACK, integrator::IntegratorSettings};
+/// let settings = IntegratorSettings::new()
+///     .with_russian_roulette(3, 0.8)
+///     .with_max_radiance(10.);
+/// let raytracer = Raytracer::new(Camera::default(), BLACK, 160, 90, 10, 50).with_integrator_settings(settings);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IntegratorSettings {
+    russian_roulette_depth: Option<u16>,
+    russian_roulette_probability: Float,
+    max_radiance: Option<Float>,
+    path_filter: Option<LightPathExpression>,
+    ray_offset: RayOffset,
+}
+
+impl IntegratorSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Settings for fast previews: Russian roulette from the third bounce on and clamping at a radiance of 10.
+    ///
+    /// Clamping removes the fireflies that make renders with few samples hard to judge, at the cost of slightly darker highlights and caustics.
+    pub fn preview() -> Self {
+        Self::new()
+            .with_russian_roulette(3, 0.8)
+            .with_max_radiance(10.)
+    }
+
+    /// Consume `self` and terminate paths randomly from bounce `depth` on, continuing with `probability`.
+    ///
+    /// Surviving paths are weighted up accordingly, so this does not introduce bias.
+    pub fn with_russian_roulette(mut self, depth: u16, probability: Float) -> Self {
+        self.russian_roulette_depth = Some(depth);
+        self.russian_roulette_probability = probability.clamp(0.01, 1.);
+        self
+    }
+
+    /// Consume `self` and clamp every color channel of a sample to `max_radiance`.
+    pub fn with_max_radiance(mut self, max_radiance: Float) -> Self {
+        self.max_radiance = Some(max_radiance.max(0.));
+        self
+    }
+
+    /// Consume `self` and only keep the light of paths that match `path_filter`, e.g. to render caustics (`LS+DE`) or direct light (`LDE`) as a separate pass.
+    ///
+    /// Only the [path tracer](Integrator::PathTracer) and the [polarized path tracer](Integrator::Polarized) follow the filter.
+    pub fn with_path_filter(mut self, path_filter: LightPathExpression) -> Self {
+        self.path_filter = Some(path_filter);
+        self
+    }
+
+    /// Consume `self` and set how [`Ray`]s leaving surfaces avoid hitting them again, e.g. a [`RayOffset::Normal`] for very large or very small scenes.
+    ///
+    /// The [bidirectional path tracer](Integrator::Bdpt) and [photon mapping](Integrator::Sppm) trace their own subpaths with the default [`RayOffset`].
+    pub fn with_ray_offset(mut self, ray_offset: RayOffset) -> Self {
+        self.ray_offset = ray_offset;
+        self
+    }
+
+    pub fn russian_roulette_depth(&self) -> Option<u16> {
+        self.russian_roulette_depth
+    }
+
+    pub fn russian_roulette_probability(&self) -> Float {
+        self.russian_roulette_probability
+    }
+
+    pub fn max_radiance(&self) -> Option<Float> {
+        self.max_radiance
+    }
+
+    pub fn path_filter(&self) -> Option<LightPathExpression> {
+        self.path_filter
+    }
+
+    pub fn ray_offset(&self) -> RayOffset {
+        self.ray_offset
+    }
+
+    /// Whether light reaching a path in the state `path` of the `path_filter` is kept.
+    pub(crate) fn keeps(&self, path: u8) -> bool {
+        self.path_filter.is_none_or(|filter| filter.accepts(path))
+    }
+
+    /// State of the `path_filter` after a diffuse or `specular` bounce in the state `path`.
+    ///
+    /// Returns [`None`] if the path cannot match anymore.
+    pub(crate) fn advance(&self, path: u8, specular: bool) -> Option<u8> {
+        match self.path_filter {
+            Some(filter) => {
+                let path = filter.step(path, specular);
+                filter.is_alive(path).then_some(path)
+            }
+            None => Some(path),
+        }
+    }
+
+    /// Continuation probability of a path at bounce `depth`.
+    pub(crate) fn continue_probability(&self, depth: u16) -> Float {
+        match self.russian_roulette_depth {
+            Some(start) if depth >= start => self.russian_roulette_probability,
+            _ => 1.,
+        }
+    }
+
+    /// Scale down a sample if it is brighter than `max_radiance`.
+    pub(crate) fn clamp(&self, sample: Color) -> Color {
+        let Some(max_radiance) = self.max_radiance else {
+            return sample;
+        };
+        let max = sample.r().max(sample.g()).max(sample.b());
+        if max > max_radiance {
+            sample * (max_radiance / max)
+        } else {
+            sample
+        }
+    }
+}
+
+impl Default for IntegratorSettings {
+    fn default() -> Self {
+        Self {
+            russian_roulette_depth: None,
+            russian_roulette_probability: 1.,
+            max_radiance: None,
+            path_filter: None,
+            ray_offset: RayOffset::default(),
+        }
+    }
+}
+
+/// Minimum parameter of [`Ray`]s leaving a surface in order to avoid hitting the same surface again.
+pub(crate) const T_MIN: Float = 0.001;
+
+/// How [`Ray`]s leaving a surface avoid hitting it again because of the rounding errors of the hit point.
+///
+/// Too small offsets cause shadow acne (surfaces shadowing themselves in speckles), too large ones let light leak through thin walls and into corners.
+/// The [`Fixed`](RayOffset::Fixed) offset only suits scenes with a size of about one, the others scale with the rounding errors.
+/// These grow with the size of a hit, which is the larger of the distance it was reached at and its largest coordinate.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, color::BLACK, integrator::{IntegratorSettings, RayOffset}};
+/// // A scene that is a thousand times larger than the default
+/// let settings = IntegratorSettings::new().with_ray_offset(RayOffset::Normal { epsilon: 1e-4 });
+/// let raytracer = Raytracer::new(Camera::default(), BLACK, 160, 90, 10, 50).with_integrator_settings(settings);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RayOffset {
+    /// Ignore hits closer than `epsilon` along the [`Ray`].
+    Fixed { epsilon: Float },
+    /// Start [`Ray`]s `epsilon` times the size of the hit further along their direction.
+    Scaled { epsilon: Float },
+    /// Start [`Ray`]s `epsilon` times the size of the hit away from the surface along its geometric normal, on the side they leave to.
+    ///
+    /// Unlike the other offsets, this keeps [`Ray`]s leaving at grazing angles off the surface, and nothing behind the offset is skipped.
+    Normal { epsilon: Float },
+}
+
+impl RayOffset {
+    /// Minimum parameter of all [`Ray`]s.
+    pub(crate) fn t_min(&self) -> Float {
+        match *self {
+            RayOffset::Fixed { epsilon } => epsilon,
+            RayOffset::Scaled { .. } | RayOffset::Normal { .. } => 0.,
+        }
+    }
+
+    /// Move the origin of `leaving`, which starts at the point of `hit` reached by `ray`, so that it does not hit the surface again.
+    pub(crate) fn leave(&self, ray: Ray, hit: &hitrecord::HitRecord, leaving: Ray) -> Ray {
+        let size = || (hit.point - ray.origin()).norm().max(hit.point.abs().max());
+        match *self {
+            RayOffset::Fixed { .. } => leaving,
+            RayOffset::Scaled { epsilon } => {
+                leaving.moved(epsilon * size() * leaving.direction().normalize())
+            }
+            RayOffset::Normal { epsilon } => {
+                let normal = hit.geometric_normal;
+                let side = if leaving.direction().dot(&normal) < 0. {
+                    -1.
+                } else {
+                    1.
+                };
+                leaving.moved(side * epsilon * size() * normal)
+            }
+        }
+    }
+}
+
+impl Default for RayOffset {
+    fn default() -> Self {
+        RayOffset::Fixed { epsilon: T_MIN }
+    }
+}
+
+/// Everything an integrator needs to know about the scene.
+///
+/// # Fields
+/// - `world`: [`Hittable`]s, possibly sorted into a [`Bvh`](crate::hittable).
+/// - `lights`: [`Lights`] that are sampled directly.
+/// - `background`: [`Background`] seen by [`Ray`]s that do not hit anything.
+/// - `camera_background`: Whether camera [`Ray`]s that do not hit anything see the `background` (it still lights the scene otherwise).
+/// - `fog`: [`Fog`] filling the scene, which only the path tracer renders.
+/// - `offset`: [`RayOffset`] of [`Ray`]s leaving surfaces.
+pub(crate) struct Scene<'a> {
+    pub world: &'a HittableListOptions,
+    pub lights: &'a Lights,
+    pub background: &'a Background,
+    pub camera_background: bool,
+    pub fog: Option<&'a Fog>,
+    pub offset: RayOffset,
+}
+
+impl<'a> Scene<'a> {
+    /// Find the first hit of `ray` in the world or the first collision with the [`Fog`] before it.
+    pub fn hit(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<hitrecord::HitRecord<'a>> {
+        let hit = self.world.hit(ray, t_min, t_max);
+        let Some(fog) = self.fog else {
+            return hit;
+        };
+        let t_hit = hit.as_ref().map_or(t_max, |hit| hit.t);
+        fog.collision(ray, t_min, t_hit).or(hit)
+    }
+
+    /// Fraction of the light that passes through the [`Fog`] along `ray` between `t_min` and `t_max`.
+    fn fog_transmittance(&self, ray: Ray, t_min: Float, t_max: Float) -> Float {
+        self.fog
+            .map_or(1., |fog| fog.transmittance(ray, t_min, t_max))
+    }
+}
+
+/// Power heuristic (with exponent 2) for multiple importance sampling.
+pub(crate) fn power_heuristic(pdf: Float, other_pdf: Float) -> Float {
+    let pdf = pdf * pdf;
+    let other_pdf = other_pdf * other_pdf;
+    if pdf + other_pdf > 0. {
+        pdf / (pdf + other_pdf)
+    } else {
+        0.
+    }
+}
+
+/// Find the first hit of `ray` that is not a [`Null`](crate::materials::Null) material, passing through those with the `offset`.
+pub(crate) fn hit_visible<'a>(
+    world: &'a HittableListOptions,
+    mut ray: Ray,
+    offset: RayOffset,
+    mut t_max: Float,
+) -> Option<hitrecord::HitRecord<'a>> {
+    loop {
+        let hit = world.hit(ray, offset.t_min(), t_max)?;
+        if !hit.material().is_null() {
+            return Some(hit);
+        }
+        t_max -= hit.t;
+        ray = offset.leave(ray, &hit, ray.continued(hit.point));
+    }
+}
+
+/// [`Ray`] of [`RayKind::Shadow`] from `hit` (reached by `ray`) into `direction`.
+pub(crate) fn shadow_ray(
+    scene: &Scene,
+    ray: Ray,
+    hit: &hitrecord::HitRecord,
+    direction: Vector3<Float>,
+) -> Ray {
+    let shadow_ray = Ray::new(hit.point, direction)
+        .with_time(ray.time())
+        .with_kind(RayKind::Shadow);
+    scene.offset.leave(ray, hit, shadow_ray)
+}
+
+/// Fraction of the light arriving at a [`ShadowCatcher`](crate::materials::ShadowCatcher) that is blocked.
+///
+/// One direction towards the area lights and every analytic light are tested. Without lights, the background is the light, so a random direction is tested instead.
+fn shadow(scene: &Scene, ray: Ray, hit: &hitrecord::HitRecord) -> Float {
+    let blocked = |direction: Vector3<Float>, distance: Float| {
+        let shadow_ray = shadow_ray(scene, ray, hit, direction);
+        hit_visible(scene.world, shadow_ray, scene.offset, distance)
+            .is_some_and(|blocker| !blocker.material().is_emissive())
+    };
+
+    let mut tested = 0;
+    let mut shadowed = 0;
+    if let Some(direction) = scene.lights.random_direction(hit.point, ray.time()) {
+        if direction.dot(&hit.normal) > 0. {
+            tested += 1;
+            shadowed += blocked(direction, Float::INFINITY) as usize;
+        }
+    }
+    for light in scene.lights.analytic_lights() {
+        if let Some(sample) = light.sample(hit.point) {
+            if sample.direction.dot(&hit.normal) > 0. {
+                tested += 1;
+                shadowed +=
+                    blocked(sample.direction, sample.distance - scene.offset.t_min()) as usize;
+            }
+        }
+    }
+
+    if tested == 0 {
+        let mut direction = hit.normal + random_unit_vector_in_unit_sphere();
+        if near_zero(&direction) {
+            direction = hit.normal;
+        }
+        return blocked(direction, Float::INFINITY) as u8 as Float;
+    }
+    shadowed as Float / tested as Float
+}
+
+/// Alpha of a camera [`Ray`] with its first hit.
+///
+/// [`ShadowCatcher`](crate::materials::ShadowCatcher)s are only covered where they are shadowed and [`Null`](crate::materials::Null) materials are looked through.
+pub(crate) fn coverage(scene: &Scene, ray: Ray, hit: Option<&hitrecord::HitRecord>) -> Float {
+    let Some(hit) = hit else {
+        return 0.;
+    };
+    if hit.material().is_null() {
+        let behind = scene.offset.leave(ray, hit, ray.continued(hit.point));
+        return coverage(
+            scene,
+            behind,
+            hit_visible(scene.world, behind, scene.offset, Float::INFINITY).as_ref(),
+        );
+    }
+    if hit.material().is_shadow_catcher() {
+        return shadow(scene, ray, hit);
+    }
+    1.
+}
+
+/// Light arriving from a [`ShadowCatcher`](crate::materials::ShadowCatcher): the background behind it, darkened by the shadows.
+pub(crate) fn shadow_catcher_radiance(
+    scene: &Scene,
+    ray: Ray,
+    hit: &hitrecord::HitRecord,
+) -> Color {
+    (1. - shadow(scene, ray, hit)) * scene.background.color(ray.direction())
+}
+
+/// Sample the [`Lights`] directly from a hit point and return the incoming light weighted by the material (next-event estimation).
+///
+/// If `mis` is true, the result is weighted for combining it with hitting the lights via scattered [`Ray`]s.
+fn sample_lights(scene: &Scene, ray: Ray, hit: &hitrecord::HitRecord, mis: bool) -> Color {
+    let Some(direction) = scene.lights.random_direction(hit.point, ray.time()) else {
+        return BLACK;
+    };
+    let Some((bsdf, bsdf_pdf)) = hit.material().evaluate(ray, hit, direction) else {
+        return BLACK;
+    };
+    let light_pdf = scene.lights.pdf_value(hit.point, direction, ray.time());
+    if light_pdf <= 0. || bsdf_pdf <= 0. {
+        return BLACK;
+    }
+
+    let shadow_ray = shadow_ray(scene, ray, hit, direction);
+    match hit_visible(scene.world, shadow_ray, scene.offset, Float::INFINITY) {
+        Some(light_hit) if light_hit.material().is_emissive() => {
+            let emitted = light_hit
+                .material()
+                .emit(light_hit.u, light_hit.v, light_hit.point);
+            let weight = if mis {
+                power_heuristic(light_pdf, bsdf_pdf)
+            } else {
+                1.
+            };
+            // The parameter of the hit is relative to the last Null material that was passed, so that of the light is recomputed.
+            let t_light = (light_hit.point - hit.point).norm() / direction.norm();
+            let transmittance = scene.fog_transmittance(shadow_ray, scene.offset.t_min(), t_light);
+            weight * transmittance / light_pdf * bsdf * emitted
+        }
+        _ => BLACK,
+    }
+}
+
+/// Sample all analytic [`Light`](crate::lights::Light)s from a hit point and return the incoming light weighted by the material.
+fn sample_analytic_lights(scene: &Scene, ray: Ray, hit: &hitrecord::HitRecord) -> Color {
+    let mut radiance = BLACK;
+
+    for light in scene.lights.analytic_lights() {
+        let Some(sample) = light.sample(hit.point) else {
+            continue;
+        };
+        let Some((bsdf, _)) = hit.material().evaluate(ray, hit, sample.direction) else {
+            continue;
+        };
+        if bsdf == BLACK {
+            continue;
+        }
+
+        let shadow_ray = shadow_ray(scene, ray, hit, sample.direction);
+        if hit_visible(
+            scene.world,
+            shadow_ray,
+            scene.offset,
+            sample.distance - scene.offset.t_min(),
+        )
+        .is_none()
+        {
+            let transmittance =
+                scene.fog_transmittance(shadow_ray, scene.offset.t_min(), sample.distance);
+            radiance += transmittance * bsdf * sample.radiance;
+        }
+    }
+
+    radiance
+}
+
+/// Sample the [`EnvironmentMap`] from a hit point and return the incoming light weighted by the material and for combining it with escaping scattered [`Ray`]s.
+fn sample_environment(
+    scene: &Scene,
+    environment: &EnvironmentMap,
+    ray: Ray,
+    hit: &hitrecord::HitRecord,
+) -> Color {
+    let (direction, environment_pdf) = environment.sample();
+    if environment_pdf <= 0. {
+        return BLACK;
+    }
+    let Some((bsdf, bsdf_pdf)) = hit.material().evaluate(ray, hit, direction) else {
+        return BLACK;
+    };
+    if bsdf == BLACK {
+        return BLACK;
+    }
+
+    let shadow_ray = shadow_ray(scene, ray, hit, direction);
+    if hit_visible(scene.world, shadow_ray, scene.offset, Float::INFINITY).is_some() {
+        return BLACK;
+    }
+    let weight = power_heuristic(environment_pdf, bsdf_pdf);
+    let transmittance = scene.fog_transmittance(shadow_ray, scene.offset.t_min(), Float::INFINITY);
+    weight * transmittance / environment_pdf * bsdf * environment.color(direction)
+}
+
+/// Fraction of the hemisphere around the first hit of `ray` that is not occluded within `radius`, weighted by the cosine.
+///
+/// [`Ray`]s that do not hit anything are not occluded at all.
+fn ambient_occlusion(scene: &Scene, ray: Ray, radius: Float, samples: u16) -> Color {
+    let Some(hit) = scene.world.hit(ray, scene.offset.t_min(), Float::INFINITY) else {
+        return WHITE;
+    };
+    let samples = samples.max(1);
+
+    let unoccluded = (0..samples)
+        .filter(|_| {
+            let mut direction = hit.normal + random_unit_vector_in_unit_sphere();
+            if near_zero(&direction) {
+                direction = hit.normal;
+            }
+            let occlusion_ray = shadow_ray(scene, ray, &hit, direction.normalize());
+            scene
+                .world
+                .hit(occlusion_ray, scene.offset.t_min(), radius)
+                .is_none()
+        })
+        .count();
+
+    unoccluded as Float / samples as Float * WHITE
+}
+
+/// Color of the cost of finding the first hit of `ray`.
+fn traversal_heatmap(scene: &Scene, ray: Ray, max_cost: u32) -> Color {
+    TraversalCost::take();
+    scene.world.hit(ray, scene.offset.t_min(), Float::INFINITY);
+    let cost = TraversalCost::take().total() as Float / max_cost.max(1) as Float;
+
+    let color = Color::heatmap(cost);
+    // Square the color, so it is exact after gamma correction.
+    color * color
+}
+
+/// Light arriving directly from the center of every area light, which produces hard shadows.
+fn area_light_centers(scene: &Scene, ray: Ray, hit: &hitrecord::HitRecord) -> Color {
+    let mut radiance = BLACK;
+
+    for light in scene.lights.area_lights() {
+        let Some(bounding_box) = light.bounding_box(ray.time(), ray.time()) else {
+            continue;
+        };
+        let offset = (bounding_box.minimum() + bounding_box.maximum()) / 2. - hit.point;
+        let distance = offset.norm();
+        let direction = offset / distance;
+        let Some((bsdf, _)) = hit.material().evaluate(ray, hit, direction) else {
+            continue;
+        };
+        if bsdf == BLACK {
+            continue;
+        }
+
+        let shadow_ray = shadow_ray(scene, ray, hit, direction);
+        if let Some(light_hit) = hit_visible(scene.world, shadow_ray, scene.offset, Float::INFINITY)
+        {
+            if light_hit.material().is_emissive() {
+                let emitted = light_hit
+                    .material()
+                    .emit(light_hit.u, light_hit.v, light_hit.point);
+                let solid_angle =
+                    light.area() * light_hit.normal.dot(&direction).abs() / (distance * distance);
+                radiance += solid_angle * bsdf * emitted;
+            }
+        }
+    }
+
+    radiance
+}
+
+/// Whitted ray tracer.
+fn whitted(scene: &Scene, ray: Ray, depth: u16) -> Color {
+    if depth == 0 {
+        return BLACK;
+    }
+    let Some(hit) = scene.world.hit(ray, scene.offset.t_min(), Float::INFINITY) else {
+        return scene.background.color(ray.direction());
+    };
+
+    let material = hit.material();
+    let mut radiance = material.emit(hit.u, hit.v, hit.point);
+    radiance += area_light_centers(scene, ray, &hit) + sample_analytic_lights(scene, ray, &hit);
+    for (specular, weight) in material.specular(ray, &hit) {
+        let specular = hit.scatter_differentials(ray, specular.with_kind(RayKind::Specular));
+        let specular = scene.offset.leave(ray, &hit, specular);
+        radiance += weight * whitted(scene, specular, depth - 1);
+    }
+
+    radiance
+}
+
+/// State of a path of the path tracer between two bounces.
+///
+/// # Fields
+/// - `ray`: [`Ray`] to follow next.
+/// - `throughput`: Product of the attenuations so far.
+/// - `radiance`: Light found so far.
+/// - `bsdf_pdf`: Probability density of the last bounce if the lights or the [`EnvironmentMap`] were sampled there as well.
+/// - `depth`: Number of bounces so far.
+/// - `path`: State of the [`LightPathExpression`] filter of the [`IntegratorSettings`].
+#[derive(Clone, Copy)]
+pub(crate) struct PathState {
+    pub ray: Ray,
+    pub throughput: Color,
+    pub radiance: Color,
+    pub bsdf_pdf: Option<Float>,
+    pub depth: u16,
+    pub path: u8,
+}
+
+impl PathState {
+    pub fn new(ray: Ray) -> Self {
+        Self {
+            ray,
+            throughput: WHITE,
+            radiance: BLACK,
+            bsdf_pdf: None,
+            depth: 0,
+            path: 0,
+        }
+    }
+}
+
+/// Shade one bounce of a path given what its [`Ray`] hit and prepare the next [`Ray`].
+///
+/// Returns whether the path continues.
+pub(crate) fn path_step(
+    scene: &Scene,
+    state: &mut PathState,
+    hit: Option<hitrecord::HitRecord>,
+    settings: &IntegratorSettings,
+) -> bool {
+    let ray = state.ray;
+    let environment = scene.background.environment();
+    let Some(hit) = hit else {
+        if (state.depth > 0 || scene.camera_background) && settings.keeps(state.path) {
+            let weight = match (state.bsdf_pdf, environment) {
+                (Some(bsdf_pdf), Some(environment)) => {
+                    power_heuristic(bsdf_pdf, environment.pdf(ray.direction()))
+                }
+                _ => 1.,
+            };
+            state.radiance += weight * state.throughput * scene.background.color(ray.direction());
+        }
+        return false;
+    };
+
+    if hit.material().is_null() {
+        // Pass through without counting as a bounce for multiple importance sampling.
+        let behind = hit.scatter_differentials(ray, ray.continued(hit.point));
+        state.ray = scene.offset.leave(ray, &hit, behind);
+        return true;
+    }
+    // Light sampled at this bounce reaches the camera on a path that scatters diffusely here.
+    let direct = settings
+        .advance(state.path, false)
+        .is_some_and(|path| settings.keeps(path));
+    if hit.material().is_shadow_catcher() {
+        if (state.depth > 0 || scene.camera_background) && direct {
+            state.radiance += state.throughput * shadow_catcher_radiance(scene, ray, &hit);
+        }
+        return false;
+    }
+
+    let emitted = hit.material().emit(hit.u, hit.v, hit.point);
+    if emitted != BLACK && settings.keeps(state.path) {
+        let weight = match state.bsdf_pdf {
+            Some(bsdf_pdf) => {
+                let light_pdf = scene
+                    .lights
+                    .pdf_value(ray.origin(), ray.direction(), ray.time());
+                power_heuristic(bsdf_pdf, light_pdf)
+            }
+            None => 1.,
+        };
+        state.radiance += weight * state.throughput * emitted;
+    }
+
+    let light_sampled = scene.lights.has_area_lights();
+    if direct {
+        if light_sampled {
+            state.radiance += state.throughput * sample_lights(scene, ray, &hit, true);
+        }
+        state.radiance += state.throughput * sample_analytic_lights(scene, ray, &hit);
+        if let Some(environment) = environment {
+            state.radiance += state.throughput * sample_environment(scene, environment, ray, &hit);
+        }
+    }
+
+    let material = hit.material();
+    let Some((scattered, attenuation)) = material.scatter(ray, hit.clone()) else {
+        return false;
+    };
+
+    let has_differentials = ray.differentials().is_some();
+    let pdf = material
+        .evaluate(ray, &hit, scattered.direction())
+        .map(|(_, pdf)| pdf);
+    state.bsdf_pdf = if light_sampled || environment.is_some() {
+        pdf
+    } else {
+        None
+    };
+    let Some(path) = settings.advance(state.path, pdf.is_none()) else {
+        return false;
+    };
+    state.path = path;
+    state.throughput *= attenuation;
+    let scattered = scattered.with_kind(match pdf {
+        Some(_) => RayKind::Indirect,
+        None => RayKind::Specular,
+    });
+    // Only specular bounces, which cannot be evaluated, keep the footprint of the camera ray.
+    let scattered = if has_differentials && pdf.is_none() {
+        hit.scatter_differentials(ray, scattered)
+    } else {
+        scattered
+    };
+    state.ray = scene.offset.leave(ray, &hit, scattered);
+    state.depth += 1;
+
+    let continue_probability = settings.continue_probability(state.depth);
+    if continue_probability < 1. {
+        if crate::random::random::<Float>() >= continue_probability {
+            return false;
+        }
+        state.throughput /= continue_probability;
+    }
+
+    true
+}
+
+/// Unidirectional path tracer with next-event estimation.
+///
+/// At every bounce on a material that can be [evaluated](materials::Material::evaluate), the [`Lights`] are sampled directly.
+/// For area lights, this is combined with the light found by following the scattered [`Ray`] via multiple importance sampling, and so is sampling an [`EnvironmentMap`] background.
+///
+/// The [`Raytracer`] renders with the [wavefront](wavefront) version of this instead.
+pub(crate) fn path_trace(
+    scene: &Scene,
+    ray: Ray,
+    max_depth: u16,
+    settings: &IntegratorSettings,
+) -> Color {
+    let mut state = PathState::new(ray);
+
+    while state.depth < max_depth {
+        let hit = scene.hit(state.ray, scene.offset.t_min(), Float::INFINITY);
+        if !path_step(scene, &mut state, hit, settings) {
+            break;
+        }
+    }
+
+    state.radiance
+}
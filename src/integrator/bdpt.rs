@@ -0,0 +1,437 @@
+//! Bidirectional path tracing.
+//!
+//! For every camera sample, one subpath is traced from the camera and one from a random area light.
+//! Every prefix of the camera subpath is then connected to every prefix of the light subpath with a shadow ray, and all these strategies are combined via multiple importance sampling (balance heuristic).
+//! Connections to the camera itself (light tracing) are not used, as they would contribute to other pixels.
+//!
+//! Only area lights in the [`Lights`](crate::lights::Lights) are traced from. Analytic lights and the background are added with next-event estimation and by escaping camera rays, respectively, just like in the path tracer.
+
+use crate::float::consts::PI;
+
+use super::{sample_analytic_lights, IntegratorSettings, Scene, T_MIN};
+use crate::color::{BLACK, WHITE};
+use crate::hitrecord::HitRecord;
+use crate::hittable::HittableListOptions;
+use crate::lights::random_emission_direction;
+use crate::ray::{Ray, RayKind};
+use crate::*;
+
+/// What a [`Vertex`] lies on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Camera,
+    Light,
+    Surface,
+}
+
+/// A vertex of a camera or light subpath.
+///
+/// # Fields
+/// - `kind`: What it lies on.
+/// - `point`: Its position.
+/// - `normal`: Normal of the surface, facing the incoming [`Ray`] (or outward on lights).
+/// - `hit`: [`HitRecord`] of surface vertices.
+/// - `incoming`: [`Ray`] along which the vertex was reached.
+/// - `emitted`: Light emitted at the vertex.
+/// - `beta`: Throughput of the subpath up to the vertex.
+/// - `pdf_fwd`: Probability density (with respect to area) of sampling the vertex from the previous one.
+/// - `pdf_rev`: Probability density (with respect to area) of sampling the vertex from the next one, i.e. from the other side.
+/// - `delta`: Whether the material only scatters into discrete directions.
+#[derive(Clone)]
+struct Vertex<'a> {
+    kind: Kind,
+    point: Vector3<Float>,
+    normal: Vector3<Float>,
+    hit: Option<HitRecord<'a>>,
+    incoming: Ray,
+    emitted: Color,
+    beta: Color,
+    pdf_fwd: Float,
+    pdf_rev: Float,
+    delta: bool,
+}
+
+impl<'a> Vertex<'a> {
+    fn camera(ray: Ray) -> Self {
+        Self {
+            kind: Kind::Camera,
+            point: ray.origin(),
+            normal: Vector3::zeros(),
+            hit: None,
+            incoming: ray,
+            emitted: BLACK,
+            beta: WHITE,
+            pdf_fwd: 1.,
+            pdf_rev: 0.,
+            delta: false,
+        }
+    }
+
+    /// Absolute cosine between the normal and `direction` (one at the camera).
+    fn cos(&self, direction: &Vector3<Float>) -> Float {
+        match self.kind {
+            Kind::Camera => 1.,
+            _ => self.normal.dot(direction).abs(),
+        }
+    }
+
+    /// BSDF times cosine for light arriving from `next` and leaving towards the previous vertex.
+    fn value(&self, next: &Vertex) -> Option<Color> {
+        let hit = self.hit.as_ref()?;
+        let direction = (next.point - self.point).normalize();
+        hit.material()
+            .evaluate(self.incoming, hit, direction)
+            .map(|(value, _)| value)
+    }
+
+    /// Probability density (with respect to area) of sampling `next` from this vertex, if it was reached from `prev`.
+    fn pdf(&self, prev: Option<&Vertex>, next: &Vertex) -> Float {
+        let direction = (next.point - self.point).normalize();
+        let pdf = match (self.kind, &self.hit, prev) {
+            (Kind::Light, _, _) => emission_pdf(self, &direction),
+            (Kind::Surface, Some(hit), Some(prev)) => {
+                let incoming =
+                    Ray::new(prev.point, self.point - prev.point).with_time(self.incoming.time());
+                hit.material()
+                    .evaluate(incoming, hit, direction)
+                    .map_or(0., |(_, pdf)| pdf)
+            }
+            _ => 0.,
+        };
+        convert_density(pdf, self, next)
+    }
+}
+
+/// Probability density (with respect to solid angle) of a light emitting into `direction`, see [`random_emission_direction`].
+fn emission_pdf(light: &Vertex, direction: &Vector3<Float>) -> Float {
+    light.cos(direction) / (2. * PI)
+}
+
+/// Convert a probability density with respect to solid angle at `from` into one with respect to area at `to`.
+fn convert_density(pdf: Float, from: &Vertex, to: &Vertex) -> Float {
+    let offset = to.point - from.point;
+    let distance_squared = offset.norm_squared();
+    if distance_squared <= 0. {
+        return 0.;
+    }
+    pdf * to.cos(&(offset / distance_squared.sqrt())) / distance_squared
+}
+
+/// Whether nothing lies between two vertices.
+fn visible(world: &HittableListOptions, a: &Vertex, b: &Vertex) -> bool {
+    let offset = b.point - a.point;
+    let distance = offset.norm();
+    let ray = Ray::new(a.point, offset / distance)
+        .with_time(a.incoming.time())
+        .with_kind(RayKind::Shadow);
+    world.hit(ray, T_MIN, distance - T_MIN).is_none()
+}
+
+/// Sample a uniformly chosen area light and a point on it.
+fn sample_light<'a>(scene: &Scene<'a>, time: Float) -> Option<Vertex<'a>> {
+    let emission = scene.lights.sample_emission(time)?;
+    if emission.emitted == BLACK {
+        return None;
+    }
+
+    Some(Vertex {
+        kind: Kind::Light,
+        point: emission.point,
+        normal: emission.normal,
+        hit: None,
+        incoming: Ray::new(emission.point, emission.normal).with_time(time),
+        emitted: emission.emitted,
+        beta: emission.emitted / emission.pdf,
+        pdf_fwd: emission.pdf,
+        pdf_rev: 0.,
+        delta: false,
+    })
+}
+
+/// Extend a subpath by following scattered [`Ray`]s until it reaches `max_vertices` vertices.
+///
+/// Returns the [`Ray`] and the throughput if the subpath escapes the scene.
+fn random_walk<'a>(
+    world: &'a HittableListOptions,
+    mut ray: Ray,
+    mut beta: Color,
+    mut pdf_dir: Float,
+    max_vertices: usize,
+    settings: &IntegratorSettings,
+    path: &mut Vec<Vertex<'a>>,
+) -> Option<(Ray, Color)> {
+    while path.len() < max_vertices {
+        let Some(hit) = world.hit(ray, T_MIN, Float::INFINITY) else {
+            return Some((ray, beta));
+        };
+        let material = hit.material();
+
+        let mut vertex = Vertex {
+            kind: Kind::Surface,
+            point: hit.point,
+            normal: hit.normal,
+            hit: Some(hit.clone()),
+            incoming: ray,
+            emitted: material.emit(hit.u, hit.v, hit.point),
+            beta,
+            pdf_fwd: 0.,
+            pdf_rev: 0.,
+            delta: material.evaluate(ray, &hit, hit.normal).is_none(),
+        };
+        let prev = path.len() - 1;
+        vertex.pdf_fwd = convert_density(pdf_dir, &path[prev], &vertex);
+
+        let Some((scattered, attenuation)) = material.scatter(ray, hit.clone()) else {
+            path.push(vertex);
+            break;
+        };
+
+        let (pdf_fwd, pdf_rev) = if vertex.delta {
+            (0., 0.)
+        } else {
+            let reverse = Ray::new(scattered.at(1.), -scattered.direction()).with_time(ray.time());
+            (
+                material
+                    .evaluate(ray, &hit, scattered.direction())
+                    .map_or(0., |(_, pdf)| pdf),
+                material
+                    .evaluate(reverse, &hit, -ray.direction())
+                    .map_or(0., |(_, pdf)| pdf),
+            )
+        };
+        path[prev].pdf_rev = convert_density(pdf_rev, &vertex, &path[prev]);
+        let kind = if vertex.delta {
+            RayKind::Specular
+        } else {
+            RayKind::Indirect
+        };
+        path.push(vertex);
+
+        beta *= attenuation;
+        let continue_probability = settings.continue_probability(path.len() as u16 - 1);
+        if continue_probability < 1. {
+            if crate::random::random::<Float>() >= continue_probability {
+                break;
+            }
+            beta /= continue_probability;
+        }
+
+        ray = scattered.with_kind(kind);
+        pdf_dir = pdf_fwd;
+    }
+
+    None
+}
+
+/// Probability density (with respect to area) of sampling the emitting vertex `light` with [`sample_light`], as seen from `prev`.
+fn light_origin_pdf(scene: &Scene, prev: &Vertex, light: &Vertex) -> Float {
+    let offset = light.point - prev.point;
+    let distance_squared = offset.norm_squared();
+    let direction = offset / distance_squared.sqrt();
+    scene
+        .lights
+        .surface_pdf_value(prev.point, direction, prev.incoming.time())
+        * light.cos(&direction)
+        / distance_squared
+}
+
+/// Weight of the strategy that uses `s` light and `t` camera vertices relative to all other strategies creating the same path.
+///
+/// `sampled` is the freshly sampled light vertex for `s == 1`.
+fn mis_weight(
+    scene: &Scene,
+    light_path: &[Vertex],
+    camera_path: &[Vertex],
+    sampled: Option<&Vertex>,
+    s: usize,
+    t: usize,
+) -> Float {
+    // Probability densities and delta flags of all vertices, with those around the connection replaced.
+    let mut camera: Vec<(Float, Float, bool)> = camera_path[..t]
+        .iter()
+        .map(|vertex| (vertex.pdf_fwd, vertex.pdf_rev, vertex.delta))
+        .collect();
+    let mut light: Vec<(Float, Float, bool)> = light_path[..s]
+        .iter()
+        .map(|vertex| (vertex.pdf_fwd, vertex.pdf_rev, vertex.delta))
+        .collect();
+
+    let z = &camera_path[t - 1];
+    let z_prev = &camera_path[t - 2];
+    let y = match s {
+        0 => None,
+        1 => sampled,
+        _ => Some(&light_path[s - 1]),
+    };
+    let y_prev = (s >= 2).then(|| &light_path[s - 2]);
+
+    match y {
+        Some(y) => {
+            if s == 1 {
+                light[0] = (y.pdf_fwd, 0., false);
+            }
+            camera[t - 1].1 = y.pdf(y_prev, z);
+            camera[t - 2].1 = z.pdf(Some(y), z_prev);
+            light[s - 1].1 = z.pdf(Some(z_prev), y);
+            light[s - 1].2 = false;
+            if let Some(y_prev) = y_prev {
+                light[s - 2].1 = y.pdf(Some(z), y_prev);
+            }
+        }
+        None => {
+            let pdf = light_origin_pdf(scene, z_prev, z);
+            if pdf <= 0. {
+                // The emitter is not an area light that could have been sampled.
+                return 1.;
+            }
+            camera[t - 1].1 = pdf;
+            camera[t - 2].1 = convert_density(
+                emission_pdf(z, &(z_prev.point - z.point).normalize()),
+                z,
+                z_prev,
+            );
+        }
+    }
+    camera[t - 1].2 = false;
+
+    let remap = |pdf: Float| if pdf > 0. { pdf } else { 1. };
+    let mut sum = 0.;
+
+    let mut ratio = 1.;
+    for i in (2..t).rev() {
+        ratio *= remap(camera[i].1) / remap(camera[i].0);
+        if !camera[i].2 && !camera[i - 1].2 {
+            sum += ratio;
+        }
+    }
+
+    let mut ratio = 1.;
+    for i in (0..s).rev() {
+        ratio *= remap(light[i].1) / remap(light[i].0);
+        if !light[i].2 && (i == 0 || !light[i - 1].2) {
+            sum += ratio;
+        }
+    }
+
+    1. / (1. + sum)
+}
+
+/// Weighted contribution of the strategy that connects the first `s` light vertices with the first `t` camera vertices.
+fn connect(
+    scene: &Scene,
+    light_path: &[Vertex],
+    camera_path: &[Vertex],
+    s: usize,
+    t: usize,
+) -> Color {
+    let z = &camera_path[t - 1];
+    let mut sampled = None;
+
+    let contribution = match s {
+        0 => z.beta * z.emitted,
+        1 => {
+            if z.delta {
+                return BLACK;
+            }
+            let Some(y) = sample_light(scene, z.incoming.time()) else {
+                return BLACK;
+            };
+            let Some(value) = z.value(&y) else {
+                return BLACK;
+            };
+            let offset = z.point - y.point;
+            let distance_squared = offset.norm_squared();
+            let contribution = z.beta
+                * value
+                * y.beta
+                * (y.cos(&(offset / distance_squared.sqrt())) / distance_squared);
+            sampled = Some(y);
+            contribution
+        }
+        _ => {
+            let y = &light_path[s - 1];
+            if z.delta || y.delta {
+                return BLACK;
+            }
+            let (Some(z_value), Some(y_value)) = (z.value(y), y.value(z)) else {
+                return BLACK;
+            };
+            let distance_squared = (z.point - y.point).norm_squared();
+            z.beta * z_value * y_value * y.beta / distance_squared
+        }
+    };
+    if contribution == BLACK {
+        return BLACK;
+    }
+
+    if s > 0 {
+        let y = sampled.as_ref().unwrap_or_else(|| &light_path[s - 1]);
+        if !visible(scene.world, z, y) {
+            return BLACK;
+        }
+    }
+
+    mis_weight(scene, light_path, camera_path, sampled.as_ref(), s, t) * contribution
+}
+
+/// Bidirectional path tracer.
+pub(crate) fn bdpt(
+    scene: &Scene,
+    ray: Ray,
+    max_depth: u16,
+    settings: &IntegratorSettings,
+) -> Color {
+    let max_depth = max_depth as usize;
+    let mut radiance = BLACK;
+
+    let mut camera_path = vec![Vertex::camera(ray)];
+    if let Some((escaped, beta)) = random_walk(
+        scene.world,
+        ray,
+        WHITE,
+        1.,
+        max_depth + 1,
+        settings,
+        &mut camera_path,
+    ) {
+        radiance += beta * scene.background.color(escaped.direction());
+    }
+
+    let mut light_path = Vec::new();
+    if let Some(origin) = sample_light(scene, ray.time()) {
+        let direction = random_emission_direction(&origin.normal);
+        let pdf_dir = emission_pdf(&origin, &direction);
+        let beta = origin.beta * (origin.cos(&direction) / pdf_dir);
+        let light_ray = Ray::new(origin.point, direction)
+            .with_time(ray.time())
+            .with_kind(RayKind::Indirect);
+
+        light_path.push(origin);
+        random_walk(
+            scene.world,
+            light_ray,
+            beta,
+            pdf_dir,
+            max_depth,
+            settings,
+            &mut light_path,
+        );
+    }
+
+    for vertex in camera_path.iter().skip(1).filter(|vertex| !vertex.delta) {
+        if let Some(hit) = &vertex.hit {
+            radiance += vertex.beta * sample_analytic_lights(scene, vertex.incoming, hit);
+        }
+    }
+
+    for t in 2..=camera_path.len() {
+        for s in 0..=light_path.len() {
+            if s + t - 2 > max_depth {
+                continue;
+            }
+            radiance += connect(scene, &light_path, &camera_path, s, t);
+        }
+    }
+
+    radiance
+}
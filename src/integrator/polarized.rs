@@ -0,0 +1,258 @@
+//! Path tracing of polarized light.
+//!
+//! Instead of a color, every path carries the weights of the Stokes parameters (see [`polarization`](crate::polarization)) of the light arriving along it, for each color channel.
+//! They start as the first row of the Mueller matrix of the filter in front of the camera and are multiplied with the Mueller matrices of the Fresnel [`Interface`]s that the path is reflected or refracted by.
+//! All other materials and the lights are treated as unpolarized, so a path that scatters there continues like in the [path tracer](super::path_step) with the weight of the intensity alone.
+
+use nalgebra::{Matrix4, Vector4};
+use rand::Rng;
+
+use super::{path_step, IntegratorSettings, PathState, RayOffset, Scene};
+use crate::hitrecord::HitRecord;
+use crate::polarization::{fresnel_transmission, linear_polarizer, rotation, Interface};
+use crate::ray::{Ray, RayKind};
+use crate::vec3::{reflect, refract};
+use crate::*;
+
+/// Weights of the Stokes parameters of the light arriving along a path.
+///
+/// # Fields
+/// - `channels`: Weights for each color channel. The light the path contributes is their dot product with the Stokes parameters.
+/// - `reference`: x axis of the frame of the Stokes parameters, perpendicular to the [`Ray`] of the path.
+#[derive(Clone, Copy, Debug)]
+struct StokesWeight {
+    channels: [Vector4<Float>; 3],
+    reference: Vector3<Float>,
+}
+
+impl StokesWeight {
+    /// Weights of a camera [`Ray`] seen through a linear polarizer at `polarizer`, or of unpolarized light without one.
+    fn camera(ray: Ray, polarizer: Option<Float>) -> Self {
+        let direction = ray.direction().normalize();
+        // The horizontal of an upright camera
+        let mut reference = direction.cross(&Vector3::y());
+        if reference.norm_squared() < 1e-12 {
+            reference = direction.cross(&Vector3::x());
+        }
+        let weight = match polarizer {
+            Some(angle) => linear_polarizer(angle).row(0).transpose(),
+            None => Vector4::new(1., 0., 0., 0.),
+        };
+        Self {
+            channels: [weight; 3],
+            reference: reference.normalize(),
+        }
+    }
+
+    /// Weight of the intensity for each color channel.
+    fn intensity(&self) -> Color {
+        Color::new(self.channels[0].x, self.channels[1].x, self.channels[2].x)
+    }
+
+    /// Continue after light is depolarized, with the weight `intensity`.
+    fn depolarize(&mut self, intensity: Color, direction: Vector3<Float>) {
+        for (channel, weight) in self.channels.iter_mut().enumerate() {
+            *weight = Vector4::new(intensity[channel as u8], 0., 0., 0.);
+        }
+        let (reference, _) = vec3::orthonormal_basis(&direction.normalize());
+        self.reference = reference;
+    }
+
+    /// Change the frame to one with the x axis `reference`, for light traveling in `propagation`.
+    fn rotate(&mut self, reference: Vector3<Float>, propagation: Vector3<Float>) {
+        let angle = propagation
+            .dot(&self.reference.cross(&reference))
+            .atan2(self.reference.dot(&reference));
+        let rotation = rotation(angle);
+        for weight in &mut self.channels {
+            *weight = rotation * *weight;
+        }
+        self.reference = reference;
+    }
+
+    fn scale(&mut self, factor: Float) {
+        for weight in &mut self.channels {
+            *weight *= factor;
+        }
+    }
+
+    /// Apply the Mueller matrices of each channel, given in the current frame.
+    fn apply(&mut self, matrices: [Matrix4<Float>; 3], attenuation: Color) {
+        for (channel, weight) in self.channels.iter_mut().enumerate() {
+            *weight = attenuation[channel as u8] * matrices[channel].transpose() * *weight;
+        }
+    }
+}
+
+/// Path tracer that follows the polarization of light through Fresnel [`Interface`]s, seen through a linear polarizer at the angle `polarizer` (or none).
+pub(crate) fn polarized_path_trace(
+    scene: &Scene,
+    ray: Ray,
+    max_depth: u16,
+    settings: &IntegratorSettings,
+    polarizer: Option<Float>,
+) -> Color {
+    let mut weight = StokesWeight::camera(ray, polarizer);
+    let mut state = PathState::new(ray);
+    state.throughput = weight.intensity();
+
+    while state.depth < max_depth {
+        let hit = scene.hit(state.ray, scene.offset.t_min(), Float::INFINITY);
+        let fresnel = hit
+            .as_ref()
+            .and_then(|hit| hit.material().fresnel(state.ray, hit));
+        match (hit, fresnel) {
+            (Some(hit), Some((interface, attenuation))) => {
+                if !fresnel_step(
+                    &mut state,
+                    &mut weight,
+                    &hit,
+                    interface,
+                    attenuation,
+                    scene.offset,
+                ) {
+                    break;
+                }
+                let Some(path) = settings.advance(state.path, true) else {
+                    break;
+                };
+                state.path = path;
+                let continue_probability = settings.continue_probability(state.depth);
+                if continue_probability < 1. {
+                    if crate::random::random::<Float>() >= continue_probability {
+                        break;
+                    }
+                    weight.scale(1. / continue_probability);
+                }
+                state.throughput = weight.intensity();
+            }
+            (hit, _) => {
+                let depolarizes = hit.as_ref().is_some_and(|hit| !hit.material().is_null());
+                state.throughput = weight.intensity();
+                if !path_step(scene, &mut state, hit, settings) {
+                    break;
+                }
+                if depolarizes {
+                    weight.depolarize(state.throughput, state.ray.direction());
+                }
+            }
+        }
+    }
+
+    state.radiance
+}
+
+/// Reflect or refract the path at the Fresnel `interface` of `hit`.
+///
+/// Returns whether the path continues.
+fn fresnel_step(
+    state: &mut PathState,
+    weight: &mut StokesWeight,
+    hit: &HitRecord,
+    interface: Interface,
+    attenuation: Color,
+    offset: RayOffset,
+) -> bool {
+    let ray = state.ray;
+    let direction = ray.direction().normalize();
+
+    let scattered = match interface {
+        Interface::Dielectric { ior } => {
+            let eta = if hit.front_face { ior } else { 1. / ior };
+            let cos_i = (-direction.dot(&hit.normal)).min(1.);
+            rotate_to_plane(weight, direction, hit.normal);
+            let reflection = interface.reflection(cos_i, !hit.front_face);
+            let reflectance = reflection[0][(0, 0)];
+            match fresnel_transmission(cos_i, eta) {
+                Some(transmission) if crate::random::rng().gen::<Float>() >= reflectance => {
+                    weight.apply([transmission; 3], attenuation / (1. - reflectance));
+                    refract(&direction, &hit.normal, 1. / eta)
+                }
+                _ => {
+                    weight.apply(reflection, attenuation / reflectance.max(Float::EPSILON));
+                    reflect(&direction, &hit.normal)
+                }
+            }
+        }
+        Interface::Conductor { .. } => {
+            // The scattered direction includes the roughness, so it is reflected by a microfacet halfway to it.
+            let Some((scattered, _)) = hit.material().scatter(ray, hit.clone()) else {
+                return false;
+            };
+            let scattered = scattered.direction().normalize();
+            let microfacet = (scattered - direction).normalize();
+            let cos_i = (-direction.dot(&microfacet)).clamp(0., 1.);
+            rotate_to_plane(weight, direction, microfacet);
+            weight.apply(interface.reflection(cos_i, false), attenuation);
+            scattered
+        }
+    };
+
+    // The plane of incidence contains the scattered ray, so the frame stays perpendicular to it.
+    let scattered = Ray::new(hit.point, scattered)
+        .with_time(ray.time())
+        .with_kind(RayKind::Specular);
+    state.ray = offset.leave(ray, hit, hit.scatter_differentials(ray, scattered));
+    state.bsdf_pdf = None;
+    state.depth += 1;
+    true
+}
+
+/// Rotate the frame of `weight` so that its x axis is perpendicular to the plane of incidence of `direction` on a surface with `normal`.
+fn rotate_to_plane(weight: &mut StokesWeight, direction: Vector3<Float>, normal: Vector3<Float>) {
+    let perpendicular = direction.cross(&normal);
+    // At normal incidence, every frame is perpendicular to the plane of incidence.
+    if perpendicular.norm_squared() > 1e-12 {
+        weight.rotate(perpendicular.normalize(), -direction);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::{BLACK, WHITE};
+    use crate::integrator::Integrator;
+    use crate::materials::{Dielectric, Lambertian};
+    use crate::shapes::{Plane, Rectangle};
+
+    #[test]
+    fn brewster_reflection() {
+        // Looking at glass under Brewster's angle, the reflection is polarized horizontally.
+        let angle = Float::atan(1.5);
+        let render = |polarizer| {
+            let camera = Camera::new(
+                vector![0., 0., 0.],
+                vector![0., -angle.cos(), -angle.sin()],
+                vector![0., 1., 0.],
+                0.01,
+                1.,
+                0.,
+                1.,
+            );
+            let mut raytracer = Raytracer::new(camera, WHITE, 3, 3, 100, 4)
+                .with_integrator(Integrator::Polarized { polarizer });
+            raytracer.world.push(Rectangle::new(
+                Plane::XZ,
+                vector![0., -1., 0.],
+                1000.,
+                1000.,
+                Dielectric::new(1.5),
+            ));
+            raytracer.world.push(Rectangle::new(
+                Plane::XZ,
+                vector![0., -2., 0.],
+                1000.,
+                1000.,
+                Lambertian::solid_color(BLACK),
+            ));
+            let image = raytracer.render().unwrap();
+            image.colors().iter().map(|color| color.g()).sum::<Float>() / 9.
+        };
+
+        // Only the part polarized perpendicular to the plane of incidence is reflected.
+        let reflectance = 0.1479 / 2.;
+        assert!((render(None) - reflectance).abs() < 0.04);
+        assert!((render(Some(0.)) - reflectance).abs() < 0.04);
+        assert!(render(Some(float::consts::FRAC_PI_2)) < 1e-4);
+    }
+}
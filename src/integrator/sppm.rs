@@ -0,0 +1,320 @@
+//! Stochastic progressive photon mapping.
+//!
+//! Every iteration first emits photons from the area lights and stores them in a hash grid wherever they hit a diffuse surface after at least one bounce.
+//! Then one camera [`Ray`] per pixel is followed through specular bounces to its first diffuse surface (its visible point), where the stored photons within a radius are gathered.
+//! The radius of each pixel shrinks over the iterations, so the result converges to the correct image.
+//!
+//! Direct lighting is computed with next-event estimation instead of photons.
+//! Caustics (e.g. light focused by a glass sphere) are found by the photons, which pure path tracing only finds by chance.
+
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use super::{sample_analytic_lights, sample_lights, IntegratorSettings, Scene, T_MIN};
+use crate::color::{BLACK, WHITE};
+use crate::hitrecord::HitRecord;
+use crate::lights::random_emission_direction;
+use crate::platform::*;
+use crate::progress::RenderProgress;
+use crate::ray::{Ray, RayKind};
+use crate::raytracer::RenderRegion;
+use crate::*;
+
+/// Fraction of the new photons that is kept in each iteration.
+const ALPHA: Float = 2. / 3.;
+
+/// A photon stored in the [`PhotonGrid`].
+///
+/// # Fields
+/// - `point`: Where it hit a surface.
+/// - `direction`: Direction it traveled in.
+/// - `power`: Its flux.
+struct Photon {
+    point: Vector3<Float>,
+    direction: Vector3<Float>,
+    power: Color,
+}
+
+/// Photons sorted into cubic cells of a uniform grid.
+struct PhotonGrid {
+    cell_size: Float,
+    photons: Vec<Photon>,
+    cells: HashMap<[i32; 3], Vec<usize>>,
+}
+
+impl PhotonGrid {
+    fn new(photons: Vec<Photon>, cell_size: Float) -> Self {
+        let mut cells: HashMap<[i32; 3], Vec<usize>> = HashMap::new();
+        for (index, photon) in photons.iter().enumerate() {
+            cells
+                .entry(Self::cell(cell_size, &photon.point))
+                .or_default()
+                .push(index);
+        }
+
+        Self {
+            cell_size,
+            photons,
+            cells,
+        }
+    }
+
+    fn cell(cell_size: Float, point: &Vector3<Float>) -> [i32; 3] {
+        [
+            (point.x / cell_size).floor() as i32,
+            (point.y / cell_size).floor() as i32,
+            (point.z / cell_size).floor() as i32,
+        ]
+    }
+
+    /// Call `f` for every photon closer than `radius` to `point`.
+    fn for_each_near(&self, point: &Vector3<Float>, radius: Float, mut f: impl FnMut(&Photon)) {
+        let offset = vector![radius, radius, radius];
+        let min = Self::cell(self.cell_size, &(point - offset));
+        let max = Self::cell(self.cell_size, &(point + offset));
+
+        for x in min[0]..=max[0] {
+            for y in min[1]..=max[1] {
+                for z in min[2]..=max[2] {
+                    let Some(indices) = self.cells.get(&[x, y, z]) else {
+                        continue;
+                    };
+                    for photon in indices.iter().map(|&index| &self.photons[index]) {
+                        if (photon.point - point).norm_squared() < radius * radius {
+                            f(photon);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Statistics of a pixel accumulated over all iterations.
+///
+/// # Fields
+/// - `direct`: Sum of the directly computed light.
+/// - `radius`: Current gathering radius.
+/// - `photons`: Number of photons gathered so far (reduced for each shrinking of the radius).
+/// - `flux`: Flux of the gathered photons.
+/// - `coverage`: Number of camera [`Ray`]s that hit something.
+#[derive(Clone, Copy)]
+struct Pixel {
+    direct: Color,
+    radius: Float,
+    photons: Float,
+    flux: Color,
+    coverage: Float,
+}
+
+/// Settings of a photon mapping render.
+///
+/// # Fields
+/// - `photons_per_iteration`: Number of photons emitted in each iteration.
+/// - `initial_radius`: Gathering radius of each pixel in the first iteration.
+/// - `max_depth`: How often photons and camera [`Ray`]s bounce at most.
+/// - `settings`: [`IntegratorSettings`] for Russian roulette and clamping.
+pub(crate) struct PhotonMapper<'a> {
+    pub photons_per_iteration: usize,
+    pub initial_radius: Float,
+    pub max_depth: u16,
+    pub settings: &'a IntegratorSettings,
+}
+
+impl PhotonMapper<'_> {
+    /// Render the pixels inside `region` of an image of `image_size` (width and height) with `iterations` iterations.
+    ///
+    /// Returns the linear colors of the pixels, row by row from the top, and which fraction of the camera [`Ray`]s of each pixel hit something.
+    /// `progress` advances by one for every iteration of a pixel.
+    pub fn render(
+        &self,
+        scene: &Scene,
+        camera: &Camera,
+        (image_width, image_height): (u32, u32),
+        iterations: u16,
+        region: &RenderRegion,
+        progress: Option<&dyn RenderProgress>,
+    ) -> (Vec<Color>, Vec<Float>) {
+        let mut pixels = vec![
+            Pixel {
+                direct: BLACK,
+                radius: self.initial_radius,
+                photons: 0.,
+                flux: BLACK,
+                coverage: 0.,
+            };
+            image_width as usize * image_height as usize
+        ];
+
+        for _ in 0..iterations {
+            let photons = (0..self.photons_per_iteration)
+                .into_par_iter()
+                .flat_map_iter(|_| self.trace_photon(scene, camera))
+                .collect();
+            let cell_size = pixels
+                .iter()
+                .map(|pixel| pixel.radius)
+                .fold(Float::MIN_POSITIVE, Float::max);
+            let grid = PhotonGrid::new(photons, cell_size);
+
+            pixels
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(index, pixel)| {
+                    if !region.contains_index(index, image_width) {
+                        return;
+                    }
+                    let mut rng = crate::random::rng();
+                    let i = index % image_width as usize;
+                    let j = image_height as usize - index / image_width as usize - 1;
+                    let u = (i as Float + rng.gen::<Float>()) / (image_width - 1).max(1) as Float;
+                    let v = (j as Float + rng.gen::<Float>()) / (image_height - 1).max(1) as Float;
+
+                    self.gather(scene, camera.get_ray(u, v), &grid, pixel);
+
+                    if let Some(progress) = progress {
+                        progress.advance(1);
+                    }
+                });
+        }
+
+        let emitted = iterations as Float * self.photons_per_iteration as Float;
+        pixels
+            .iter()
+            .map(|pixel| {
+                let area = float::consts::PI * pixel.radius * pixel.radius;
+                (
+                    pixel.direct / iterations as Float + pixel.flux / (emitted * area),
+                    pixel.coverage / iterations as Float,
+                )
+            })
+            .unzip()
+    }
+
+    /// Emit a photon from a random area light and return everywhere it is stored.
+    fn trace_photon(&self, scene: &Scene, camera: &Camera) -> Vec<Photon> {
+        let mut photons = Vec::new();
+        let time = match camera.time() {
+            Some((start, end)) => start + crate::random::random::<Float>() * (end - start),
+            None => 0.,
+        };
+
+        let Some(emission) = scene.lights.sample_emission(time) else {
+            return photons;
+        };
+        let direction = random_emission_direction(&emission.normal);
+        // The cosine of the emission cancels with the probability density of the direction.
+        let mut power = 2. * float::consts::PI / emission.pdf * emission.emitted;
+        let mut ray = Ray::new(emission.point, direction)
+            .with_time(time)
+            .with_kind(RayKind::Indirect);
+
+        for depth in 0..self.max_depth {
+            let Some(hit) = scene.world.hit(ray, T_MIN, Float::INFINITY) else {
+                break;
+            };
+            let material = hit.material();
+
+            let diffuse = material.evaluate(ray, &hit, hit.normal).is_some();
+            // Photons arriving directly from the light would duplicate the direct lighting.
+            if depth > 0 && diffuse {
+                photons.push(Photon {
+                    point: hit.point,
+                    direction: ray.direction().normalize(),
+                    power,
+                });
+            }
+
+            let kind = if diffuse {
+                RayKind::Indirect
+            } else {
+                RayKind::Specular
+            };
+            let Some((scattered, attenuation)) = material.scatter(ray, hit) else {
+                break;
+            };
+            power *= attenuation;
+
+            let continue_probability = self.settings.continue_probability(depth + 1);
+            if continue_probability < 1. {
+                if crate::random::random::<Float>() >= continue_probability {
+                    break;
+                }
+                power /= continue_probability;
+            }
+            ray = scattered.with_kind(kind);
+        }
+
+        photons
+    }
+
+    /// Follow `ray` to its visible point, gather the photons there, and update `pixel`.
+    fn gather(&self, scene: &Scene, mut ray: Ray, grid: &PhotonGrid, pixel: &mut Pixel) {
+        let mut throughput = WHITE;
+        let mut direct = BLACK;
+        let mut visible: Option<HitRecord> = None;
+
+        for depth in 0..self.max_depth {
+            let Some(hit) = scene.world.hit(ray, T_MIN, Float::INFINITY) else {
+                if depth > 0 || scene.camera_background {
+                    direct += throughput * scene.background.color(ray.direction());
+                }
+                break;
+            };
+            if depth == 0 {
+                pixel.coverage += super::coverage(scene, ray, Some(&hit));
+            }
+            let material = hit.material();
+            if material.is_shadow_catcher() {
+                if depth > 0 || scene.camera_background {
+                    direct += throughput * super::shadow_catcher_radiance(scene, ray, &hit);
+                }
+                break;
+            }
+            direct += throughput * material.emit(hit.u, hit.v, hit.point);
+
+            if material.evaluate(ray, &hit, hit.normal).is_some() {
+                direct += throughput
+                    * (sample_lights(scene, ray, &hit, false)
+                        + sample_analytic_lights(scene, ray, &hit));
+                visible = Some(hit);
+                break;
+            }
+
+            let Some((scattered, attenuation)) = material.scatter(ray, hit) else {
+                break;
+            };
+            throughput *= attenuation;
+            // Only specular bounces get here.
+            ray = scattered.with_kind(RayKind::Specular);
+        }
+        pixel.direct += self.settings.clamp(direct);
+
+        let Some(hit) = visible else {
+            return;
+        };
+        let mut flux = BLACK;
+        let mut count = 0.;
+        grid.for_each_near(&hit.point, pixel.radius, |photon| {
+            let incoming = -photon.direction;
+            let cos = hit.normal.dot(&incoming).abs();
+            if cos < 1e-4 {
+                return;
+            }
+            if let Some((value, _)) = hit.material().evaluate(ray, &hit, incoming) {
+                flux += value / cos * photon.power;
+                count += 1.;
+            }
+        });
+
+        if count > 0. {
+            let photons = pixel.photons + ALPHA * count;
+            let radius = pixel.radius * (photons / (pixel.photons + count)).sqrt();
+            let shrink = (radius * radius) / (pixel.radius * pixel.radius);
+            pixel.flux = (pixel.flux + throughput * flux) * shrink;
+            pixel.photons = photons;
+            pixel.radius = radius;
+        }
+    }
+}
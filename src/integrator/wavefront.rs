@@ -0,0 +1,287 @@
+//! Wavefront rendering for the path tracer.
+//!
+//! Instead of following each path to its end before starting the next one, the paths of one sample per pixel advance in lockstep.
+//! Every wave generates one camera [`Ray`] per pixel, then repeatedly intersects all live [`Ray`]s with the world, shades all hits, and queues the scattered [`Ray`]s for the next bounce.
+//! The queue is stored as a structure of arrays, so every stage works on contiguous memory (which allows batching the stages, e.g. for SIMD or a GPU).
+//! The waves run over chunks of at most [`WAVE_SIZE`] pixels, so the memory of the queue does not grow with the resolution.
+
+use rand::Rng;
+
+use super::{path_step, IntegratorSettings, PathState, Scene};
+use crate::color::BLACK;
+use crate::filter::{Film, Filter};
+use crate::hitrecord::HitRecord;
+use crate::passes::SampleImage;
+use crate::platform::*;
+use crate::progress::RenderProgress;
+use crate::random;
+use crate::ray::{Ray, RayDifferentials, RayKind};
+use crate::raytracer::RenderRegion;
+use crate::stats::RenderStats;
+use crate::*;
+
+/// Maximum number of paths in a wave.
+const WAVE_SIZE: usize = 1 << 16;
+
+/// Live paths of a wave, stored as a structure of arrays.
+///
+/// The paths in the queue have been advanced equally often, but [`Null`](crate::materials::Null) materials do not count as bounces.
+#[derive(Default)]
+struct PathQueue {
+    origins: Vec<Vector3<Float>>,
+    directions: Vec<Vector3<Float>>,
+    times: Vec<Float>,
+    differentials: Vec<Option<RayDifferentials>>,
+    kinds: Vec<Option<RayKind>>,
+    throughputs: Vec<Color>,
+    radiances: Vec<Color>,
+    bsdf_pdfs: Vec<Option<Float>>,
+    depths: Vec<u16>,
+    paths: Vec<u8>,
+    /// Index of the pixel in the current chunk.
+    pixels: Vec<u32>,
+}
+
+impl PathQueue {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            origins: Vec::with_capacity(capacity),
+            directions: Vec::with_capacity(capacity),
+            times: Vec::with_capacity(capacity),
+            differentials: Vec::with_capacity(capacity),
+            kinds: Vec::with_capacity(capacity),
+            throughputs: Vec::with_capacity(capacity),
+            radiances: Vec::with_capacity(capacity),
+            bsdf_pdfs: Vec::with_capacity(capacity),
+            depths: Vec::with_capacity(capacity),
+            paths: Vec::with_capacity(capacity),
+            pixels: Vec::with_capacity(capacity),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.pixels.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pixels.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.origins.clear();
+        self.directions.clear();
+        self.times.clear();
+        self.differentials.clear();
+        self.kinds.clear();
+        self.throughputs.clear();
+        self.radiances.clear();
+        self.bsdf_pdfs.clear();
+        self.depths.clear();
+        self.paths.clear();
+        self.pixels.clear();
+    }
+
+    fn push(&mut self, state: &PathState, pixel: u32) {
+        self.origins.push(state.ray.origin());
+        self.directions.push(state.ray.direction());
+        self.times.push(state.ray.time());
+        self.differentials.push(state.ray.differentials());
+        self.kinds.push(state.ray.kind());
+        self.throughputs.push(state.throughput);
+        self.radiances.push(state.radiance);
+        self.bsdf_pdfs.push(state.bsdf_pdf);
+        self.depths.push(state.depth);
+        self.paths.push(state.path);
+        self.pixels.push(pixel);
+    }
+
+    fn ray(&self, index: usize) -> Ray {
+        let mut ray =
+            Ray::new(self.origins[index], self.directions[index]).with_time(self.times[index]);
+        if let Some(kind) = self.kinds[index] {
+            ray = ray.with_kind(kind);
+        }
+        match self.differentials[index] {
+            Some(differentials) => ray.with_differentials(differentials),
+            None => ray,
+        }
+    }
+
+    fn state(&self, index: usize) -> PathState {
+        PathState {
+            ray: self.ray(index),
+            throughput: self.throughputs[index],
+            radiance: self.radiances[index],
+            bsdf_pdf: self.bsdf_pdfs[index],
+            depth: self.depths[index],
+            path: self.paths[index],
+        }
+    }
+}
+
+/// Settings of a path tracing render.
+///
+/// # Fields
+/// - `samples_per_pixel`: Number of waves per chunk, each with one sample per pixel.
+/// - `max_depth`: How often a [`Ray`] bounces at most.
+/// - `settings`: [`IntegratorSettings`] for Russian roulette and clamping.
+/// - `filter`: [`Filter`] the samples are splatted with.
+/// - `statistics`: Whether to record the [`SampleImage`] of the render.
+/// - `seed`: Seed of the generators of the paths, if the render is reproducible.
+pub(crate) struct Wavefront<'a> {
+    pub samples_per_pixel: u16,
+    pub max_depth: u16,
+    pub settings: &'a IntegratorSettings,
+    pub filter: Filter,
+    pub statistics: bool,
+    pub seed: Option<u64>,
+}
+
+impl Wavefront<'_> {
+    /// Render the pixels inside `region` of an image of `image_size` (width and height).
+    ///
+    /// Returns the linear colors of the pixels, row by row from the top, which fraction of the camera [`Ray`]s of each pixel hit something, and the [`SampleImage`] if it is recorded.
+    /// `progress` advances by one for every sample of a pixel.
+    pub fn render(
+        &self,
+        scene: &Scene,
+        camera: &Camera,
+        (image_width, image_height): (u32, u32),
+        region: &RenderRegion,
+        progress: Option<&dyn RenderProgress>,
+        stats: &mut RenderStats,
+    ) -> (Vec<Color>, Vec<Float>, Option<SampleImage>) {
+        let pixel_count = image_width as usize * image_height as usize;
+        let mut film = Film::new(self.filter, (image_width, image_height), *region)
+            .with_statistics(self.statistics);
+        let pixels: Vec<u32> = (0..pixel_count)
+            .filter(|&index| region.contains_index(index, image_width))
+            .map(|index| index as u32)
+            .collect();
+        // Coverage of the pixels in the region.
+        let mut region_coverage = vec![0.; pixels.len()];
+
+        let pixel_size = (
+            1. / (image_width - 1).max(1) as Float,
+            1. / (image_height - 1).max(1) as Float,
+        );
+        let mut queue = PathQueue::with_capacity(WAVE_SIZE.min(pixels.len()));
+        let mut next = PathQueue::with_capacity(WAVE_SIZE.min(pixels.len()));
+        // Position of the current sample inside each pixel of the chunk.
+        let mut offsets = Vec::with_capacity(WAVE_SIZE.min(pixels.len()));
+        for (chunk, coverage) in pixels
+            .chunks(WAVE_SIZE)
+            .zip(region_coverage.chunks_mut(WAVE_SIZE))
+        {
+            for sample in 0..self.samples_per_pixel as u64 {
+                // Generate the camera rays.
+                let primary: Vec<(Option<PathState>, (Float, Float))> = chunk
+                    .par_iter()
+                    .map(|&index| {
+                        random::seeded(self.seed, &[index as u64, sample], || {
+                            let mut rng = random::rng();
+                            let i = index as usize % image_width as usize;
+                            let j =
+                                image_height as usize - index as usize / image_width as usize - 1;
+                            let offset: (Float, Float) = (rng.gen(), rng.gen());
+                            let u = (i as Float + offset.0) / (image_width - 1).max(1) as Float;
+                            let v = (j as Float + offset.1) / (image_height - 1).max(1) as Float;
+                            let ray = camera.try_get_ray_with_differentials(u, v, pixel_size);
+                            (ray.map(PathState::new), offset)
+                        })
+                    })
+                    .collect();
+                stats.paths += chunk.len() as u64;
+                queue.clear();
+                offsets.clear();
+                for (slot, ((state, offset), &pixel)) in primary.iter().zip(chunk).enumerate() {
+                    offsets.push(*offset);
+                    // Rays blocked by a lens system are black.
+                    match state {
+                        Some(state) => queue.push(state, slot as u32),
+                        None => film.add_sample(pixel as usize, *offset, BLACK),
+                    }
+                }
+
+                for depth in 0..self.max_depth {
+                    if queue.is_empty() {
+                        break;
+                    }
+
+                    let traversal = Instant::now();
+                    let hits: Vec<Option<HitRecord>> = (0..queue.len())
+                        .into_par_iter()
+                        .map(|index| {
+                            let pixel = chunk[queue.pixels[index] as usize] as u64;
+                            random::seeded(self.seed, &[pixel, sample, depth as u64, 0], || {
+                                scene.hit(queue.ray(index), scene.offset.t_min(), Float::INFINITY)
+                            })
+                        })
+                        .collect();
+                    stats.traversal += traversal.elapsed();
+                    stats.rays += hits.len() as u64;
+                    if depth == 0 {
+                        for (index, (hit, &slot)) in hits.iter().zip(&queue.pixels).enumerate() {
+                            let pixel = chunk[slot as usize] as u64;
+                            coverage[slot as usize] +=
+                                random::seeded(self.seed, &[pixel, sample, 0, 2], || {
+                                    super::coverage(scene, queue.ray(index), hit.as_ref())
+                                });
+                        }
+                    }
+
+                    let shading = Instant::now();
+                    let shaded: Vec<(PathState, bool)> = hits
+                        .into_par_iter()
+                        .enumerate()
+                        .map(|(index, hit)| {
+                            let pixel = chunk[queue.pixels[index] as usize] as u64;
+                            random::seeded(self.seed, &[pixel, sample, depth as u64, 1], || {
+                                let mut state = queue.state(index);
+                                let alive = path_step(scene, &mut state, hit, self.settings);
+                                (state, alive)
+                            })
+                        })
+                        .collect();
+
+                    next.clear();
+                    for ((state, alive), &slot) in shaded.iter().zip(&queue.pixels) {
+                        if *alive {
+                            next.push(state, slot);
+                        } else {
+                            let slot = slot as usize;
+                            film.add_sample(
+                                chunk[slot] as usize,
+                                offsets[slot],
+                                self.settings.clamp(state.radiance),
+                            );
+                        }
+                    }
+                    std::mem::swap(&mut queue, &mut next);
+                    stats.shading += shading.elapsed();
+                }
+
+                // Paths that reached the maximum depth.
+                for (radiance, &slot) in queue.radiances.iter().zip(&queue.pixels) {
+                    let slot = slot as usize;
+                    film.add_sample(
+                        chunk[slot] as usize,
+                        offsets[slot],
+                        self.settings.clamp(*radiance),
+                    );
+                }
+
+                if let Some(progress) = progress {
+                    progress.advance(chunk.len() as u64);
+                }
+            }
+        }
+
+        let mut coverage = vec![0.; pixel_count];
+        for (&pixel, region_coverage) in pixels.iter().zip(region_coverage) {
+            coverage[pixel as usize] = region_coverage / self.samples_per_pixel as Float;
+        }
+        let samples = film.sample_image();
+        (film.into_colors(), coverage, samples)
+    }
+}
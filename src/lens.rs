@@ -0,0 +1,306 @@
+//! Realistic camera lenses made of a stack of spherical elements, see [`Camera::with_lens_system`].
+//!
+//! A [`LensSystem`] is described like in lens patents and optics textbooks: surface by surface from the front (facing the scene) to the back (facing the film).
+//! Camera [`Ray`](crate::ray::Ray)s are traced from the film through every surface, refracted by Snell's law and blocked by the rims of the elements.
+//! Unlike the thin lens of the default [`Camera`], this produces the optical vignetting, distortion, and depth of field of the real lens.
+//!
+//! In the coordinates of a [`LensSystem`], the film lies in the xy plane and the optical axis is +z, pointing towards the scene.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// A spherical surface of a [`LensSystem`].
+///
+/// A flat surface with `radius` 0 and the `ior` of the medium in front of it (usually air) is an aperture stop.
+///
+/// # Fields
+/// - `radius`: Radius of curvature. It is positive if the center of curvature lies behind the surface (towards the film), and 0 for a flat surface.
+/// - `thickness`: Distance along the optical axis to the next surface, or to the film for the last one.
+/// - `ior`: Refractive index of the medium behind the surface, 1 for air.
+/// - `aperture`: Diameter of the surface. [`Ray`](crate::ray::Ray)s outside of it are blocked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LensElement {
+    pub radius: Float,
+    pub thickness: Float,
+    pub ior: Float,
+    pub aperture: Float,
+}
+
+/// A stack of [`LensElement`]s from the front to the back of a lens.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::lens::LensSystem;
+/// // The 50 mm double Gauss lens in meters
+/// let lens = LensSystem::double_gauss().scaled(0.001);
+/// assert!((lens.focal_length().unwrap() - 0.05).abs() < 0.002);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LensSystem {
+    elements: Vec<LensElement>,
+}
+
+impl LensSystem {
+    /// Create a lens from its surfaces, listed from the front to the back.
+    pub fn new(elements: Vec<LensElement>) -> Self {
+        Self { elements }
+    }
+
+    /// A double Gauss lens with a focal length of about 50 mm (in millimeters), a classic design of standard lenses for 35 mm film.
+    ///
+    /// The distance of the last surface to the film is found when the lens is [focused](LensSystem::focused).
+    pub fn double_gauss() -> Self {
+        let element = |radius, thickness, ior, aperture| LensElement {
+            radius,
+            thickness,
+            ior,
+            aperture,
+        };
+        Self::new(vec![
+            element(29.475, 3.76, 1.67, 25.2),
+            element(84.83, 0.12, 1., 25.2),
+            element(19.275, 4.025, 1.67, 23.),
+            element(40.77, 3.275, 1.699, 23.),
+            element(12.75, 5.705, 1., 18.),
+            element(0., 4.5, 1., 17.1),
+            element(-14.495, 1.18, 1.603, 17.),
+            element(40.77, 6.065, 1.658, 20.),
+            element(-20.385, 0.19, 1., 20.),
+            element(437.065, 3.22, 1.717, 20.),
+            element(-39.73, 0., 1., 20.),
+        ])
+    }
+
+    /// Consume `self` and scale all lengths by `factor`, e.g. 0.001 for a lens given in millimeters in a scene measured in meters.
+    pub fn scaled(mut self, factor: Float) -> Self {
+        for element in &mut self.elements {
+            element.radius *= factor;
+            element.thickness *= factor;
+            element.aperture *= factor;
+        }
+        self
+    }
+
+    /// Consume `self` and change the diameter of all aperture stops, e.g. to stop the lens down.
+    pub fn with_stop(mut self, diameter: Float) -> Self {
+        for element in &mut self.elements {
+            if element.radius == 0. {
+                element.aperture = diameter;
+            }
+        }
+        self
+    }
+
+    pub fn elements(&self) -> &[LensElement] {
+        &self.elements
+    }
+
+    /// Effective focal length of the lens, from a paraxial [`Ray`](crate::ray::Ray) coming in parallel to the optical axis.
+    ///
+    /// Returns [`None`] if the lens does not refract such a ray, or blocks it.
+    pub fn focal_length(&self) -> Option<Float> {
+        let height = self.paraxial_height()?;
+        let origin = vector![height, 0., self.length() + 1.];
+        let (_, direction) = self.trace(origin, vector![0., 0., -1.], false)?;
+        let focal_length = height * direction.z / direction.x;
+        focal_length.is_finite().then_some(focal_length)
+    }
+
+    /// A copy of the lens moved along the optical axis, so that objects at `distance` in front of the film are in focus.
+    ///
+    /// `distance` may be infinite. If there is no such position, the lens is returned unchanged.
+    pub fn focused(&self, distance: Float) -> Self {
+        let mut lens = self.clone();
+        let tolerance = 1e-5 * self.length().max(Float::EPSILON);
+        // Moving the lens moves the image by about the same distance, unless the object is very close.
+        for _ in 0..64 {
+            let Some(image) = lens.image_position(distance) else {
+                return self.clone();
+            };
+            let Some(last) = lens.elements.last_mut() else {
+                return self.clone();
+            };
+            last.thickness -= image;
+            if last.thickness <= 0. {
+                return self.clone();
+            }
+            if image.abs() < tolerance {
+                return lens;
+            }
+        }
+        lens
+    }
+
+    /// Trace the [`Ray`](crate::ray::Ray) from `film` (in the xy plane) towards `rear` (in the unit disk) on the rear surface through the lens.
+    ///
+    /// Returns its origin and direction where it leaves the front surface, or [`None`] if it is blocked.
+    pub(crate) fn ray_from_film(
+        &self,
+        film: (Float, Float),
+        rear: (Float, Float),
+    ) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        let last = self.elements.last()?;
+        let radius = last.aperture / 2.;
+        let target = vector![rear.0 * radius, rear.1 * radius, last.thickness];
+        let origin = vector![film.0, film.1, 0.];
+        self.trace(origin, target - origin, true)
+    }
+
+    /// Distance from the film to the front surface.
+    fn length(&self) -> Float {
+        self.elements.iter().map(|element| element.thickness).sum()
+    }
+
+    /// Height of a ray close to the optical axis, for which the lens behaves like an ideal one.
+    fn paraxial_height(&self) -> Option<Float> {
+        let smallest = self
+            .elements
+            .iter()
+            .map(|element| element.aperture)
+            .min_by(Float::total_cmp)?;
+        Some(0.01 * smallest / 2.)
+    }
+
+    /// Position on the optical axis where the lens images a point on the axis at `distance` in front of the film.
+    fn image_position(&self, distance: Float) -> Option<Float> {
+        let height = self.paraxial_height()?;
+        let front = self.length();
+        let (origin, direction) = if distance.is_infinite() {
+            (vector![height, 0., front + 1.], vector![0., 0., -1.])
+        } else if distance > front {
+            (
+                vector![0., 0., distance],
+                vector![height, 0., front - distance],
+            )
+        } else {
+            return None;
+        };
+        let (origin, direction) = self.trace(origin, direction, false)?;
+        let t = -origin.x / direction.x;
+        t.is_finite().then(|| origin.z + t * direction.z)
+    }
+
+    /// Trace a ray through all surfaces, starting at the film if `from_film` and at the scene otherwise.
+    ///
+    /// Returns the origin and direction where the ray leaves the last surface, or [`None`] if it is blocked or totally reflected.
+    fn trace(
+        &self,
+        mut origin: Vector3<Float>,
+        direction: Vector3<Float>,
+        from_film: bool,
+    ) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        let mut direction = direction.normalize();
+        let count = self.elements.len();
+        let mut z = if from_film { 0. } else { self.length() };
+        for step in 0..count {
+            let index = if from_film { count - 1 - step } else { step };
+            let element = &self.elements[index];
+            if from_film {
+                z += element.thickness;
+            }
+
+            let (t, normal) = intersect(origin, direction, z, element.radius)?;
+            origin += t * direction;
+            if origin.x * origin.x + origin.y * origin.y > element.aperture * element.aperture / 4.
+            {
+                return None;
+            }
+            let front = match index {
+                0 => 1.,
+                _ => self.elements[index - 1].ior,
+            };
+            let ratio = match from_film {
+                true => element.ior / front,
+                false => front / element.ior,
+            };
+            direction = refract(direction, normal, ratio)?;
+
+            if !from_film {
+                z -= element.thickness;
+            }
+        }
+        Some((origin, direction))
+    }
+}
+
+/// Intersect the ray with the surface with vertex `z` on the optical axis and radius of curvature `radius` (0 for a flat one).
+///
+/// Returns the ray parameter and the normal of the surface there.
+fn intersect(
+    origin: Vector3<Float>,
+    direction: Vector3<Float>,
+    z: Float,
+    radius: Float,
+) -> Option<(Float, Vector3<Float>)> {
+    if radius == 0. {
+        let t = (z - origin.z) / direction.z;
+        return (t.is_finite() && t > 0.).then(|| (t, Vector3::z()));
+    }
+
+    // Only the half of the sphere that contains the vertex is part of the surface.
+    let center = vector![0., 0., z - radius];
+    let oc = origin - center;
+    let half_b = oc.dot(&direction);
+    let c = oc.norm_squared() - radius * radius;
+    let discriminant = half_b * half_b - c;
+    if discriminant < 0. {
+        return None;
+    }
+    let sqrt = discriminant.sqrt();
+    [-half_b - sqrt, -half_b + sqrt]
+        .into_iter()
+        .find(|&t| t > 0. && (origin.z + t * direction.z - center.z) * radius > 0.)
+        .map(|t| (t, (origin + t * direction - center) / radius))
+}
+
+/// Refract the unit `direction` at a surface with unit `normal` (on either side) and the ratio `eta` of the refractive indices before and after it.
+///
+/// Returns [`None`] for total internal reflection.
+fn refract(
+    direction: Vector3<Float>,
+    normal: Vector3<Float>,
+    eta: Float,
+) -> Option<Vector3<Float>> {
+    let normal = if normal.dot(&direction) > 0. {
+        -normal
+    } else {
+        normal
+    };
+    let cos_i = -normal.dot(&direction);
+    let sin2_t = eta * eta * (1. - cos_i * cos_i);
+    if sin2_t > 1. {
+        return None;
+    }
+    Some(eta * direction + (eta * cos_i - (1. - sin2_t).sqrt()) * normal)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn focus() {
+        let lens = LensSystem::double_gauss();
+        let focal_length = lens.focal_length().unwrap();
+        assert!((focal_length - 50.).abs() < 2., "{focal_length}");
+
+        // Focused at infinity, the film lies in the focal plane.
+        let infinity = lens.focused(Float::INFINITY);
+        assert!(infinity.image_position(Float::INFINITY).unwrap().abs() < 1e-2);
+        let near = lens.focused(1000.);
+        assert!(near.image_position(1000.).unwrap().abs() < 1e-2);
+        // Closer objects need a lens farther away from the film.
+        assert!(near.length() > infinity.length());
+
+        // The ray from the center of the film through the center of the rear surface stays on the axis.
+        let (origin, direction) = near.ray_from_film((0., 0.), (0., 0.)).unwrap();
+        assert!((origin.z - near.length()).abs() < 1e-3);
+        assert!((direction - Vector3::z()).norm() < 1e-4);
+        // Rays far off the axis are blocked.
+        assert!(near.ray_from_film((100., 0.), (0., 0.)).is_none());
+    }
+}
@@ -3,24 +3,86 @@
 //! As the coordinate system is arbitrary, it was chosen in this way:
 //!  - y axis points up
 //!  - z axis points towards the camera
+//!
 //! In order to create a ray-traced image, one needs to create a [`Camera`], then a [`Raytracer`] and add [`Hittable`]s to its `world`.
 
+pub mod accelerator;
+pub mod arena;
+pub mod background;
+pub mod baking;
+pub mod bezier;
 pub mod camera;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod clipping;
 pub mod color;
+pub mod csg;
+pub mod distributed;
+pub mod dithering;
+pub mod error;
+pub mod filter;
+pub mod float;
+pub mod flythrough;
+pub mod framebuffer;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+#[cfg(test)]
+mod golden;
+pub mod grading;
+pub mod hair;
+pub mod heightfield;
 pub mod hitrecord;
 pub mod hittable;
+pub mod ies;
+pub mod instancing;
+pub mod integrator;
+pub mod lens;
+pub mod lights;
+pub mod lpe;
 pub mod materials;
+pub mod mesh;
+pub mod metadata;
+pub mod metrics;
+pub mod output;
+pub mod passes;
 pub mod perlin;
+pub mod planet;
+mod platform;
+pub mod pointcloud;
+pub mod polarization;
+pub mod postprocess;
 pub mod ppm;
+pub mod presets;
+pub mod probes;
+pub mod progress;
+mod random;
 pub mod ray;
 pub mod raytracer;
+pub mod registry;
+pub mod scenegraph;
+pub mod scenes;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod sdf;
 pub mod shapes;
+pub mod stats;
+pub mod streaming;
+pub mod subdivision;
 pub mod textures;
+pub mod transient;
+pub mod usd;
 #[macro_use]
 pub mod vec3;
+#[cfg(feature = "video")]
+pub mod video;
+pub mod volume;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod world;
 
 pub use camera::Camera;
 pub use color::Color;
+pub use float::Float;
 pub use hittable::{Hittable, HittableList};
 pub use nalgebra::{vector, Vector3};
 pub use raytracer::Raytracer;
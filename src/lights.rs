@@ -0,0 +1,496 @@
+//! Light sources that are sampled directly.
+//!
+//! Without light sampling, a path only picks up light if it happens to hit a light source by chance, which is rare for small lights (e.g. in the Cornell box).
+//! With a [`Lights`] registry, every diffuse bounce additionally shoots shadow rays towards the lights (next-event estimation).
+//!
+//! There are two kinds of lights:
+//! - Area lights are emissive [`Hittable`]s. Sampling them is combined with following the scattered [`Ray`](crate::ray::Ray) via multiple importance sampling (power heuristic), so neither small nor large lights produce excessive noise.
+//!   Most shapes are sampled by choosing a point on their surface. [`Sphere`](crate::shapes::Sphere)s and [`Disk`](crate::shapes::Disk)s instead sample the cone of directions in which they are seen, which is much less noisy for nearby large lights.
+//! - Analytic lights ([`DirectionalLight`], [`PointLight`], [`SpotLight`]) implement [`Light`]. They cannot be hit by rays and are therefore only found by sampling them explicitly.
+//!   Point and spot lights can be given the [`IesProfile`] of a real fixture.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use nalgebra::Rotation3;
+use rand::Rng;
+
+use crate::float::consts::PI;
+use crate::hittable::{surface_pdf_value, HittableArc};
+use crate::ies::IesProfile;
+use crate::ray::Ray;
+use crate::vec3::{near_zero, random_unit_vector_in_unit_sphere, random_vector_in_cone};
+use crate::*;
+
+/// A sample of a [`Light`] seen from a point.
+///
+/// # Fields
+/// - `direction`: Unit vector from the point towards the light.
+/// - `distance`: Distance to the light (infinite for [`DirectionalLight`]s).
+/// - `radiance`: Light arriving at the point if it is not occluded.
+#[derive(Clone, Copy, Debug)]
+pub struct LightSample {
+    pub direction: Vector3<Float>,
+    pub distance: Float,
+    pub radiance: Color,
+}
+
+/// An abstraction over analytic light sources that cannot be hit by rays.
+///
+/// `Send + Sync` is necessary for multithreading.
+pub trait Light: Debug + Send + Sync {
+    /// Sample the light as seen from `point`.
+    ///
+    /// Returns [`None`] if no light arrives at `point`.
+    fn sample(&self, point: Vector3<Float>) -> Option<LightSample>;
+}
+
+/// A light infinitely far away that illuminates the whole scene from one direction (e.g. the sun).
+///
+/// # Fields
+/// - `direction`: Unit direction the light travels in.
+/// - `radiance`: Color (and strength) of the light.
+/// - `cos_angular_radius`: Cosine of the angular radius of the light source. Values below one produce soft shadows.
+#[derive(Clone, Debug)]
+pub struct DirectionalLight {
+    direction: Vector3<Float>,
+    radiance: Color,
+    cos_angular_radius: Float,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector3<Float>, radiance: Color) -> Self {
+        Self {
+            direction: direction.normalize(),
+            radiance,
+            cos_angular_radius: 1.,
+        }
+    }
+
+    /// Consume `self` and give the light source an angular radius (in radians) for soft shadows.
+    ///
+    /// The sun has an angular radius of about 0.0047.
+    pub fn with_angular_radius(mut self, angular_radius: Float) -> Self {
+        self.cos_angular_radius = angular_radius.cos();
+        self
+    }
+
+    pub fn direction(&self) -> Vector3<Float> {
+        self.direction
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: Vector3<Float>) -> Option<LightSample> {
+        let direction = if self.cos_angular_radius < 1. {
+            random_vector_in_cone(&-self.direction, self.cos_angular_radius)
+        } else {
+            -self.direction
+        };
+        Some(LightSample {
+            direction,
+            distance: Float::INFINITY,
+            radiance: self.radiance,
+        })
+    }
+}
+
+/// A light emitting equally into all directions from a point (or a small sphere).
+///
+/// # Fields
+/// - `position`: Its position.
+/// - `intensity`: Color (and strength) of the light. The light arriving at a point falls off with the squared distance.
+/// - `radius`: Radius of the light source. Values above zero produce soft shadows.
+/// - `profile`: [`IesProfile`] that scales the intensity, together with the rotation from world coordinates into those of the profile.
+#[derive(Clone, Debug)]
+pub struct PointLight {
+    position: Vector3<Float>,
+    intensity: Color,
+    radius: Float,
+    profile: Option<(Arc<IesProfile>, Rotation3<Float>)>,
+}
+
+impl PointLight {
+    pub fn new(position: Vector3<Float>, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+            radius: 0.,
+            profile: None,
+        }
+    }
+
+    /// Consume `self` and give the light source a radius for soft shadows.
+    pub fn with_radius(mut self, radius: Float) -> Self {
+        self.radius = radius.abs();
+        self
+    }
+
+    /// Consume `self` and let the intensity vary with the direction like that of a real fixture.
+    ///
+    /// `rotation` orients the fixture; without rotation its nadir points down (−y). The intensity is that of the brightest direction.
+    pub fn with_profile(mut self, profile: IesProfile, rotation: Rotation3<Float>) -> Self {
+        self.profile = Some((Arc::new(profile), rotation.inverse()));
+        self
+    }
+
+    pub fn position(&self) -> Vector3<Float> {
+        self.position
+    }
+}
+
+/// Sample a point light of some `radius` and optional `profile` from `point`.
+fn sample_point(
+    position: Vector3<Float>,
+    radius: Float,
+    intensity: Color,
+    profile: &Option<(Arc<IesProfile>, Rotation3<Float>)>,
+    point: Vector3<Float>,
+) -> Option<LightSample> {
+    let position = position + radius * random_unit_vector_in_unit_sphere();
+    let to_light = position - point;
+    let distance = to_light.norm();
+    if distance <= 0. {
+        return None;
+    }
+    let factor = match profile {
+        Some((profile, to_profile)) => profile.intensity(to_profile * -to_light),
+        None => 1.,
+    };
+    if factor <= 0. {
+        return None;
+    }
+    Some(LightSample {
+        direction: to_light / distance,
+        distance,
+        radiance: factor * intensity / (distance * distance),
+    })
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: Vector3<Float>) -> Option<LightSample> {
+        sample_point(
+            self.position,
+            self.radius,
+            self.intensity,
+            &self.profile,
+            point,
+        )
+    }
+}
+
+/// A point light that only emits into a cone.
+///
+/// The intensity falls off smoothly between the inner and the outer angle of the cone.
+///
+/// # Fields
+/// - `position`: Its position.
+/// - `direction`: Unit direction of the axis of the cone.
+/// - `intensity`: Color (and strength) of the light on the axis.
+/// - `cos_inner`: Cosine of the angle up to which the light has its full intensity.
+/// - `cos_outer`: Cosine of the angle beyond which no light is emitted.
+/// - `radius`: Radius of the light source. Values above zero produce soft shadows.
+/// - `profile`: [`IesProfile`] that scales the intensity, together with the rotation from world coordinates into those of the profile.
+#[derive(Clone, Debug)]
+pub struct SpotLight {
+    position: Vector3<Float>,
+    direction: Vector3<Float>,
+    intensity: Color,
+    cos_inner: Float,
+    cos_outer: Float,
+    radius: Float,
+    profile: Option<(Arc<IesProfile>, Rotation3<Float>)>,
+}
+
+impl SpotLight {
+    /// Create a new [`SpotLight`] with a hard edge at `angle` (in radians, measured from the axis).
+    pub fn new(
+        position: Vector3<Float>,
+        direction: Vector3<Float>,
+        intensity: Color,
+        angle: Float,
+    ) -> Self {
+        Self {
+            position,
+            direction: direction.normalize(),
+            intensity,
+            cos_inner: angle.cos(),
+            cos_outer: angle.cos(),
+            radius: 0.,
+            profile: None,
+        }
+    }
+
+    /// Consume `self` and let the intensity fall off smoothly from the angle `inner_angle` (in radians) to the edge.
+    pub fn with_falloff(mut self, inner_angle: Float) -> Self {
+        self.cos_inner = inner_angle.cos().max(self.cos_outer);
+        self
+    }
+
+    /// Consume `self` and give the light source a radius for soft shadows.
+    pub fn with_radius(mut self, radius: Float) -> Self {
+        self.radius = radius.abs();
+        self
+    }
+
+    /// Consume `self` and let the intensity vary with the direction like that of a real fixture.
+    ///
+    /// The nadir of the fixture points along the axis of the cone, which still limits the emitted light. The intensity is that of the brightest direction.
+    pub fn with_profile(mut self, profile: IesProfile) -> Self {
+        let down = -Vector3::y();
+        let rotation = Rotation3::rotation_between(&down, &self.direction)
+            .unwrap_or_else(|| Rotation3::from_axis_angle(&Vector3::x_axis(), PI));
+        self.profile = Some((Arc::new(profile), rotation.inverse()));
+        self
+    }
+
+    /// Fraction of the intensity emitted at an angle with cosine `cos` to the axis.
+    fn falloff(&self, cos: Float) -> Float {
+        if cos >= self.cos_inner {
+            return 1.;
+        }
+        if cos <= self.cos_outer {
+            return 0.;
+        }
+        let t = (cos - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        t * t * (3. - 2. * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: Vector3<Float>) -> Option<LightSample> {
+        let mut sample = sample_point(
+            self.position,
+            self.radius,
+            self.intensity,
+            &self.profile,
+            point,
+        )?;
+        let falloff = self.falloff(-sample.direction.dot(&self.direction));
+        if falloff <= 0. {
+            return None;
+        }
+        sample.radiance *= falloff;
+        Some(sample)
+    }
+}
+
+/// A point sampled on an area light, see [`Lights::sample_emission`].
+///
+/// # Fields
+/// - `point`: The sampled point.
+/// - `normal`: Outward normal at `point`.
+/// - `emitted`: Light emitted at `point`.
+/// - `pdf`: Probability density (with respect to area) of sampling `point`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Emission {
+    pub point: Vector3<Float>,
+    pub normal: Vector3<Float>,
+    pub emitted: Color,
+    pub pdf: Float,
+}
+
+/// Sample a direction from a point on an area light with `normal`.
+///
+/// Area lights emit with a cosine distribution on both sides, so the probability density (with respect to solid angle) is `|cos| / 2π`.
+pub(crate) fn random_emission_direction(normal: &Vector3<Float>) -> Vector3<Float> {
+    let side = if crate::random::random() {
+        *normal
+    } else {
+        -normal
+    };
+    let direction = side + random_unit_vector_in_unit_sphere();
+    if near_zero(&direction) {
+        side
+    } else {
+        direction.normalize()
+    }
+}
+
+/// A registry of lights that are sampled directly.
+///
+/// Only [`Hittable`]s that can be [sampled](Hittable::sample_surface) (i.e. have a non-zero [area](Hittable::area)) are useful as area lights.
+/// Every area light also has to be part of the world in order to be hit by [`Ray`](crate::ray::Ray)s, see [`Raytracer::push_light`].
+///
+/// # Fields
+/// - `area_lights`: Emissive [`Hittable`]s.
+/// - `analytic_lights`: [`Light`]s that cannot be hit.
+#[derive(Clone, Debug, Default)]
+pub struct Lights {
+    area_lights: Vec<HittableArc>,
+    analytic_lights: Vec<Arc<dyn Light>>,
+}
+
+impl Lights {
+    /// Create an empty [`Lights`] registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add all emissive [`Hittable`]s of a [`HittableList`] that can be sampled as area lights.
+    ///
+    /// This does not descend into nested [`HittableList`]s.
+    pub fn collect_from_world(&mut self, world: &HittableList) {
+        self.area_lights.extend(
+            world
+                .iter()
+                .filter(|hittable| hittable.is_emissive() && hittable.area() > 0.)
+                .cloned(),
+        );
+    }
+
+    /// Push a new area light to the end.
+    ///
+    /// Note that this does not add the light to the world.
+    pub fn push<H: Hittable + 'static>(&mut self, light: H) {
+        self.area_lights.push(Arc::new(light));
+    }
+
+    pub(crate) fn push_shared(&mut self, light: HittableArc) {
+        self.area_lights.push(light);
+    }
+
+    /// Push a new analytic [`Light`] to the end.
+    pub fn push_analytic<L: Light + 'static>(&mut self, light: L) {
+        self.analytic_lights.push(Arc::new(light));
+    }
+
+    /// Whether there are no lights at all.
+    pub fn is_empty(&self) -> bool {
+        self.area_lights.is_empty() && self.analytic_lights.is_empty()
+    }
+
+    /// Whether there are area lights.
+    pub fn has_area_lights(&self) -> bool {
+        !self.area_lights.is_empty()
+    }
+
+    /// Number of lights.
+    pub fn len(&self) -> usize {
+        self.area_lights.len() + self.analytic_lights.len()
+    }
+
+    /// Clear the registry.
+    pub fn clear(&mut self) {
+        self.area_lights.clear();
+        self.analytic_lights.clear();
+    }
+
+    pub(crate) fn area_lights(&self) -> &[HittableArc] {
+        &self.area_lights
+    }
+
+    /// Sample a uniformly chosen area light and a uniformly distributed point on it.
+    pub(crate) fn sample_emission(&self, time: Float) -> Option<Emission> {
+        if self.area_lights.is_empty() {
+            return None;
+        }
+        let light = &self.area_lights[crate::random::rng().gen_range(0..self.area_lights.len())];
+        let (point, normal) = light.sample_surface(time)?;
+
+        // Hit the light right at the sampled point in order to find its material.
+        let probe = Ray::new(point + 1e-3 * normal, -normal).with_time(time);
+        let hit = light.hit(probe, 0., 2e-3)?;
+        let emitted = hit.material().emit(hit.u, hit.v, hit.point);
+
+        Some(Emission {
+            point,
+            normal,
+            emitted,
+            pdf: 1. / (self.area_lights.len() as Float * light.area()),
+        })
+    }
+
+    /// Iterate over the analytic [`Light`]s.
+    pub fn analytic_lights(&self) -> impl Iterator<Item = &dyn Light> {
+        self.analytic_lights.iter().map(|light| light.as_ref())
+    }
+
+    /// Probability density (with respect to solid angle) that [`random_direction`](Lights::random_direction) returns `direction` from `origin`.
+    ///
+    /// Each area light is chosen with the same probability.
+    pub fn pdf_value(
+        &self,
+        origin: Vector3<Float>,
+        direction: Vector3<Float>,
+        time: Float,
+    ) -> Float {
+        if self.area_lights.is_empty() {
+            return 0.;
+        }
+
+        self.area_lights
+            .iter()
+            .map(|light| light.pdf_value(origin, direction, time))
+            .sum::<Float>()
+            / self.area_lights.len() as Float
+    }
+
+    /// Probability density (with respect to solid angle) of the direction from `origin` to a point sampled with [`sample_emission`](Lights::sample_emission).
+    ///
+    /// Unlike [`pdf_value`](Lights::pdf_value), this is always that of the points on the surfaces, even for lights that sample directions by solid angle.
+    pub(crate) fn surface_pdf_value(
+        &self,
+        origin: Vector3<Float>,
+        direction: Vector3<Float>,
+        time: Float,
+    ) -> Float {
+        if self.area_lights.is_empty() {
+            return 0.;
+        }
+
+        self.area_lights
+            .iter()
+            .map(|light| surface_pdf_value(light.as_ref(), origin, direction, time))
+            .sum::<Float>()
+            / self.area_lights.len() as Float
+    }
+
+    /// Sample a direction from `origin` towards a random area light.
+    pub fn random_direction(&self, origin: Vector3<Float>, time: Float) -> Option<Vector3<Float>> {
+        if self.area_lights.is_empty() {
+            return None;
+        }
+
+        let index = crate::random::rng().gen_range(0..self.area_lights.len());
+        self.area_lights[index].random_direction(origin, time)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::materials::DiffuseLight;
+    use crate::shapes::{Disk, Plane, Sphere};
+
+    #[test]
+    fn solid_angle_sampling() {
+        let light = DiffuseLight::solid_color(color![1., 1., 1.]);
+        let sphere = Sphere::new(vector![0., 0., -2.], 1., light.clone());
+        let disk = Disk::new(Plane::XY, vector![0., 0., -2.], 1., light);
+        let origin = vector![0.2, 0.1, 0.];
+
+        // Every sampled direction hits the sphere, and the densities integrate to one over all directions.
+        let samples = 20000;
+        for _ in 0..100 {
+            let direction = sphere.random_direction(origin, 0.).unwrap();
+            assert!(sphere
+                .hit(Ray::new(origin, direction), 0., Float::INFINITY)
+                .is_some());
+        }
+        for hittable in [&sphere as &dyn Hittable, &disk] {
+            let integral = (0..samples)
+                .map(|_| hittable.pdf_value(origin, random_unit_vector_in_unit_sphere(), 0.))
+                .sum::<Float>()
+                * 4.
+                * PI
+                / samples as Float;
+            assert!((integral - 1.).abs() < 0.1, "{integral}");
+        }
+
+        // From inside, the area is sampled instead.
+        let inside = vector![0., 0., -2.5];
+        let direction = sphere.random_direction(inside, 0.).unwrap();
+        assert!(sphere.pdf_value(inside, direction, 0.) > 0.);
+    }
+}
@@ -0,0 +1,463 @@
+//! Light path expressions that restrict which paths of the path tracer contribute to an image, see [`IntegratorSettings::with_path_filter`](crate::integrator::IntegratorSettings::with_path_filter).
+//!
+//! A path is written in Heckbert's notation as the sequence of its vertices from the light to the camera:
+//! - `L`: the light, i.e. an emissive surface, an analytic light, or the background,
+//! - `S`: a specular bounce that cannot be [evaluated](crate::materials::Material::evaluate), e.g. on glass or a mirror,
+//! - `D`: any other bounce, e.g. on a diffuse or glossy surface or inside a medium,
+//! - `E`: the eye, i.e. the camera.
+//!
+//! An expression is a regular expression over these letters with `.` for `D` or `S`, classes like `[DS]`, groups, alternatives `|`, and the quantifiers `*`, `+` and `?`.
+//! For example, `LE` only keeps lights seen directly, `LDE` direct lighting, `LD?E` both, and `LS+DE` caustics. Rendering the same scene with several expressions gives separate passes for compositing.
+
+use std::str::FromStr;
+
+use crate::error::Error;
+
+/// Most states of the automaton of an expression.
+const MAX_STATES: usize = 32;
+
+/// The letters of a path as bits, so that classes are unions of them.
+const EYE: u8 = 1;
+const DIFFUSE: u8 = 2;
+const SPECULAR: u8 = 4;
+const LIGHT: u8 = 8;
+
+/// A compiled light path expression.
+///
+/// It is a deterministic automaton that reads a path from the camera towards the light, so the path tracer only keeps a small state per path.
+/// Paths whose state cannot lead to a match anymore are terminated early.
+///
+/// # Fields
+/// - `transitions`: Next state after a diffuse and after a specular bounce, for each state.
+/// - `accepting`: States whose path matches when it reaches a light, as bits.
+/// - `alive`: States from which a match can still be reached, as bits.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::lpe::LightPathExpression;
+/// let caustics: LightPathExpression = "LS+DE".parse().unwrap();
+/// assert!(caustics.matches("LSSDE"));
+/// assert!(!caustics.matches("LDE"));
+///
+/// // Everything except caustics
+/// let rest = caustics.inverted();
+/// assert!(rest.matches("LDE") && !rest.matches("LSDE"));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LightPathExpression {
+    transitions: [[u8; 2]; MAX_STATES],
+    accepting: u32,
+    alive: u32,
+}
+
+impl LightPathExpression {
+    /// Compile `expression`.
+    ///
+    /// This fails for invalid syntax and for expressions that are too complex.
+    pub fn parse(expression: &str) -> Result<Self, Error> {
+        let letters: Vec<char> = expression.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut parser = Parser {
+            letters: &letters,
+            position: 0,
+        };
+        let node = parser.alternatives()?;
+        if parser.position < letters.len() {
+            return Err(parser.error("unexpected character"));
+        }
+
+        // The path tracer reads paths from the camera, i.e. backwards.
+        let mut nfa = Nfa::default();
+        let (start, end) = nfa.build(&node.reversed())?;
+        nfa.compile(start, end)
+    }
+
+    /// The complement: paths that do not match `self`.
+    pub fn inverted(&self) -> Self {
+        Self {
+            transitions: self.transitions,
+            accepting: !self.accepting,
+            alive: u32::MAX,
+        }
+    }
+
+    /// Whether the path written in Heckbert's notation (e.g. `LSDE`) matches.
+    ///
+    /// Paths that do not start with `L` and end with `E` never match.
+    pub fn matches(&self, path: &str) -> bool {
+        let [b'L', bounces @ .., b'E'] = path.as_bytes() else {
+            return false;
+        };
+        let mut state = 0;
+        for &letter in bounces.iter().rev() {
+            state = match letter {
+                b'D' => self.step(state, false),
+                b'S' => self.step(state, true),
+                _ => return false,
+            };
+        }
+        self.accepts(state)
+    }
+
+    /// State of a path after a diffuse or `specular` bounce in `state`. Paths start in state 0 at the camera.
+    pub(crate) fn step(&self, state: u8, specular: bool) -> u8 {
+        self.transitions[state as usize][specular as usize]
+    }
+
+    /// Whether a path in `state` matches if it reaches a light now.
+    pub(crate) fn accepts(&self, state: u8) -> bool {
+        self.accepting & (1 << state) != 0
+    }
+
+    /// Whether a path in `state` can still match.
+    pub(crate) fn is_alive(&self, state: u8) -> bool {
+        self.alive & (1 << state) != 0
+    }
+}
+
+impl FromStr for LightPathExpression {
+    type Err = Error;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        Self::parse(expression)
+    }
+}
+
+/// Syntax tree of an expression.
+#[derive(Clone, Debug)]
+enum Node {
+    /// One of the letters, as bits.
+    Letters(u8),
+    Sequence(Vec<Node>),
+    Alternatives(Vec<Node>),
+    /// A node that is `required` at least once and may be repeated if `unbounded`.
+    Repeat {
+        node: Box<Node>,
+        required: bool,
+        unbounded: bool,
+    },
+}
+
+impl Node {
+    /// The node matching the reversed paths.
+    fn reversed(&self) -> Self {
+        match self {
+            Node::Letters(letters) => Node::Letters(*letters),
+            Node::Sequence(nodes) => {
+                Node::Sequence(nodes.iter().rev().map(Node::reversed).collect())
+            }
+            Node::Alternatives(nodes) => {
+                Node::Alternatives(nodes.iter().map(Node::reversed).collect())
+            }
+            Node::Repeat {
+                node,
+                required,
+                unbounded,
+            } => Node::Repeat {
+                node: Box::new(node.reversed()),
+                required: *required,
+                unbounded: *unbounded,
+            },
+        }
+    }
+}
+
+/// Recursive descent parser of expressions.
+struct Parser<'a> {
+    letters: &'a [char],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.letters.get(self.position).copied()
+    }
+
+    fn error(&self, message: &str) -> Error {
+        let expression: String = self.letters.iter().collect();
+        Error::Config(format!(
+            "{message} at position {} of light path expression {expression:?}",
+            self.position
+        ))
+    }
+
+    fn alternatives(&mut self) -> Result<Node, Error> {
+        let mut nodes = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.position += 1;
+            nodes.push(self.sequence()?);
+        }
+        Ok(match nodes.len() {
+            1 => nodes.remove(0),
+            _ => Node::Alternatives(nodes),
+        })
+    }
+
+    fn sequence(&mut self) -> Result<Node, Error> {
+        let mut nodes = Vec::new();
+        while let Some(letter) = self.peek() {
+            if letter == '|' || letter == ')' {
+                break;
+            }
+            let mut node = self.atom()?;
+            while let Some(quantifier @ ('*' | '+' | '?')) = self.peek() {
+                self.position += 1;
+                node = Node::Repeat {
+                    node: Box::new(node),
+                    required: quantifier == '+',
+                    unbounded: quantifier != '?',
+                };
+            }
+            nodes.push(node);
+        }
+        Ok(Node::Sequence(nodes))
+    }
+
+    fn atom(&mut self) -> Result<Node, Error> {
+        let letter = self.peek().ok_or_else(|| self.error("missing letter"))?;
+        self.position += 1;
+        match letter {
+            '(' => {
+                let node = self.alternatives()?;
+                if self.peek() != Some(')') {
+                    return Err(self.error("missing ')'"));
+                }
+                self.position += 1;
+                Ok(node)
+            }
+            '[' => {
+                let mut letters = 0;
+                while let Some(letter) = self.peek() {
+                    self.position += 1;
+                    if letter == ']' {
+                        return Ok(Node::Letters(letters));
+                    }
+                    letters |= self.letter(letter)?;
+                }
+                Err(self.error("missing ']'"))
+            }
+            '.' => Ok(Node::Letters(DIFFUSE | SPECULAR)),
+            letter => Ok(Node::Letters(self.letter(letter)?)),
+        }
+    }
+
+    fn letter(&self, letter: char) -> Result<u8, Error> {
+        match letter {
+            'E' => Ok(EYE),
+            'D' => Ok(DIFFUSE),
+            'S' => Ok(SPECULAR),
+            'L' => Ok(LIGHT),
+            _ => Err(self.error("unknown letter")),
+        }
+    }
+}
+
+/// Nondeterministic automaton with empty transitions, built from a [`Node`] (Thompson's construction).
+///
+/// # Fields
+/// - `letters`: Transitions on any of the letters (as bits) to a state, for each state.
+/// - `empty`: Empty transitions, for each state.
+#[derive(Default)]
+struct Nfa {
+    letters: Vec<Vec<(u8, usize)>>,
+    empty: Vec<Vec<usize>>,
+}
+
+impl Nfa {
+    fn state(&mut self) -> Result<usize, Error> {
+        if self.letters.len() == u64::BITS as usize {
+            return Err(Error::Config(
+                "light path expression is too complex".to_string(),
+            ));
+        }
+        self.letters.push(Vec::new());
+        self.empty.push(Vec::new());
+        Ok(self.letters.len() - 1)
+    }
+
+    /// Add the states of `node` and return its start and end.
+    fn build(&mut self, node: &Node) -> Result<(usize, usize), Error> {
+        let start = self.state()?;
+        let end = self.state()?;
+        match node {
+            Node::Letters(letters) => self.letters[start].push((*letters, end)),
+            Node::Sequence(nodes) => {
+                let mut last = start;
+                for node in nodes {
+                    let (first, next) = self.build(node)?;
+                    self.empty[last].push(first);
+                    last = next;
+                }
+                self.empty[last].push(end);
+            }
+            Node::Alternatives(nodes) => {
+                for node in nodes {
+                    let (first, last) = self.build(node)?;
+                    self.empty[start].push(first);
+                    self.empty[last].push(end);
+                }
+            }
+            Node::Repeat {
+                node,
+                required,
+                unbounded,
+            } => {
+                let (first, last) = self.build(node)?;
+                self.empty[start].push(first);
+                self.empty[last].push(end);
+                if !*required {
+                    self.empty[start].push(end);
+                }
+                if *unbounded {
+                    self.empty[last].push(first);
+                }
+            }
+        }
+        Ok((start, end))
+    }
+
+    /// The states reachable from `states` by empty transitions.
+    fn closure(&self, mut states: u64) -> u64 {
+        let mut stack: Vec<usize> = (0..self.letters.len())
+            .filter(|state| states & (1 << state) != 0)
+            .collect();
+        while let Some(state) = stack.pop() {
+            for &next in &self.empty[state] {
+                if states & (1 << next) == 0 {
+                    states |= 1 << next;
+                    stack.push(next);
+                }
+            }
+        }
+        states
+    }
+
+    /// The states reachable from `states` by reading `letter`.
+    fn read(&self, states: u64, letter: u8) -> u64 {
+        let mut next = 0;
+        for (state, transitions) in self.letters.iter().enumerate() {
+            if states & (1 << state) != 0 {
+                for &(letters, target) in transitions {
+                    if letters & letter != 0 {
+                        next |= 1 << target;
+                    }
+                }
+            }
+        }
+        self.closure(next)
+    }
+
+    /// Turn the automaton from `start` to `end` into a deterministic one that starts after the eye (subset construction).
+    fn compile(&self, start: usize, end: usize) -> Result<LightPathExpression, Error> {
+        let initial = self.read(self.closure(1 << start), EYE);
+        let mut sets = vec![initial];
+        let mut transitions = [[0; 2]; MAX_STATES];
+        let mut index = 0;
+        while index < sets.len() {
+            for (specular, letter) in [DIFFUSE, SPECULAR].into_iter().enumerate() {
+                let next = self.read(sets[index], letter);
+                let target = match sets.iter().position(|&set| set == next) {
+                    Some(target) => target,
+                    None if sets.len() < MAX_STATES => {
+                        sets.push(next);
+                        sets.len() - 1
+                    }
+                    None => {
+                        return Err(Error::Config(
+                            "light path expression is too complex".to_string(),
+                        ))
+                    }
+                };
+                transitions[index][specular] = target as u8;
+            }
+            index += 1;
+        }
+
+        let accepting = sets
+            .iter()
+            .enumerate()
+            .filter(|(_, &set)| self.read(set, LIGHT) & (1 << end) != 0)
+            .fold(0, |bits, (state, _)| bits | (1 << state));
+        // States from which an accepting state can be reached
+        let mut alive: u32 = accepting;
+        loop {
+            let next = (0..sets.len())
+                .filter(|&state| {
+                    transitions[state]
+                        .iter()
+                        .any(|&target| alive & (1 << target) != 0)
+                })
+                .fold(alive, |bits, state| bits | (1 << state));
+            if next == alive {
+                break;
+            }
+            alive = next;
+        }
+
+        Ok(LightPathExpression {
+            transitions,
+            accepting,
+            alive,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::WHITE;
+    use crate::integrator::IntegratorSettings;
+    use crate::materials::Lambertian;
+    use crate::shapes::{Plane, Rectangle};
+    use crate::*;
+
+    #[test]
+    fn expressions() {
+        let direct: LightPathExpression = "L D? E".parse().unwrap();
+        assert!(direct.matches("LE") && direct.matches("LDE"));
+        assert!(!direct.matches("LSE") && !direct.matches("LDDE"));
+
+        let glossy = LightPathExpression::parse("L(D|S)*[DS]E").unwrap();
+        assert!(glossy.matches("LSDSE") && !glossy.matches("LE"));
+
+        // Once the camera saw a specular surface, no path of "L.*DE" is left.
+        let diffuse = LightPathExpression::parse("L.*DE").unwrap();
+        let state = diffuse.step(0, true);
+        assert!(!diffuse.is_alive(state));
+        assert!(diffuse.is_alive(diffuse.step(0, false)));
+
+        assert!(LightPathExpression::parse("L(DE").is_err());
+        assert!(LightPathExpression::parse("LXE").is_err());
+    }
+
+    #[test]
+    fn filtered_render() {
+        // A gray floor under a white sky fills the view.
+        let render = |expression: &str| {
+            let camera = Camera::new(
+                vector![0., 1., 0.],
+                vector![0., 0., 0.],
+                vector![0., 0., -1.],
+                0.5,
+                1.,
+                0.,
+                1.,
+            );
+            let settings = IntegratorSettings::new().with_path_filter(expression.parse().unwrap());
+            let mut raytracer =
+                Raytracer::new(camera, WHITE, 4, 4, 4, 4).with_integrator_settings(settings);
+            raytracer.world.push(Rectangle::new(
+                Plane::XZ,
+                vector![0., 0., 0.],
+                100.,
+                100.,
+                Lambertian::solid_color(color![0.5, 0.5, 0.5]),
+            ));
+            let image = raytracer.render().unwrap();
+            image.colors().iter().map(|color| color.g()).sum::<Float>() / 16.
+        };
+
+        assert_eq!(render("LE"), 0.);
+        assert!(render("LDE") > 0.1);
+        assert_eq!(render("LSDE"), 0.);
+    }
+}
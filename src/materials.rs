@@ -1,11 +1,14 @@
 //! Collection of materials of [`Hittable`]s.
 
+use crate::float::consts::{FRAC_PI_2, PI};
 use std::fmt::Debug;
+use std::sync::Arc;
 
 use rand::Rng;
 
-use crate::color::BLACK;
+use crate::color::{BLACK, WHITE};
 use crate::hitrecord::HitRecord;
+use crate::polarization::Interface;
 use crate::ray::Ray;
 use crate::textures::{SolidColor, Texture};
 use crate::vec3::*;
@@ -19,7 +22,107 @@ pub trait Material: Debug + Send + Sync {
     fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)>;
 
     /// Checks if and what color light is emitted at a certain point.
-    fn emit(&self, u: f32, v: f32, point: Vector3<f32>) -> Color;
+    fn emit(&self, u: Float, v: Float, point: Vector3<Float>) -> Color;
+
+    /// Evaluates how much light arriving from `direction` is scattered along the incoming [`Ray`] (towards its origin).
+    ///
+    /// Returns the [`Color`] of the BSDF times the cosine to the normal as well as the probability density of [`scatter`](Material::scatter) choosing `direction`.
+    /// This is used for sampling [`Lights`](crate::lights::Lights) directly. Materials that only scatter into discrete directions (like mirrors) return [`None`] and are never light sampled.
+    fn evaluate(
+        &self,
+        _ray: Ray,
+        _hit: &HitRecord,
+        _direction: Vector3<Float>,
+    ) -> Option<(Color, Float)> {
+        None
+    }
+
+    /// Whether the material ever emits light.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Whether [`Ray`]s pass through the material unchanged, see [`Null`].
+    fn is_null(&self) -> bool {
+        false
+    }
+
+    /// Whether the material only catches shadows, see [`ShadowCatcher`].
+    fn is_shadow_catcher(&self) -> bool {
+        false
+    }
+
+    /// Constant albedo of the material, if it has one.
+    ///
+    /// This is only used to [validate](crate::raytracer::Raytracer::stats) scenes.
+    fn albedo(&self) -> Option<Color> {
+        None
+    }
+
+    /// Perfectly specular [`Ray`]s leaving the hit point (e.g. mirror reflection and refraction) together with their weights.
+    ///
+    /// This is used by the [Whitted](crate::integrator::Integrator::Whitted) integrator. Materials that are not specular return no [`Ray`]s.
+    fn specular(&self, _ray: Ray, _hit: &HitRecord) -> Vec<(Ray, Color)> {
+        Vec::new()
+    }
+
+    /// Smooth [`Interface`] whose Fresnel reflection polarizes light, together with the [`Color`] that the light reaching `hit` along `ray` is attenuated with.
+    ///
+    /// This is used by the [polarized](crate::integrator::Integrator::Polarized) path tracer. Materials without an interface depolarize light.
+    fn fresnel(&self, _ray: Ray, _hit: &HitRecord) -> Option<(Interface, Color)> {
+        None
+    }
+    /// ID of the material for [ID passes](crate::raytracer::Raytracer::render_ids), see [`NamedMaterial`].
+    fn id(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// Shared materials, e.g. for the many [triangles](crate::shapes::Triangle) of a single object.
+impl<M: Material + ?Sized> Material for Arc<M> {
+    fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)> {
+        self.as_ref().scatter(ray, hit)
+    }
+
+    fn emit(&self, u: Float, v: Float, point: Vector3<Float>) -> Color {
+        self.as_ref().emit(u, v, point)
+    }
+
+    fn evaluate(
+        &self,
+        ray: Ray,
+        hit: &HitRecord,
+        direction: Vector3<Float>,
+    ) -> Option<(Color, Float)> {
+        self.as_ref().evaluate(ray, hit, direction)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.as_ref().is_emissive()
+    }
+
+    fn is_null(&self) -> bool {
+        self.as_ref().is_null()
+    }
+
+    fn is_shadow_catcher(&self) -> bool {
+        self.as_ref().is_shadow_catcher()
+    }
+
+    fn albedo(&self) -> Option<Color> {
+        self.as_ref().albedo()
+    }
+
+    fn specular(&self, ray: Ray, hit: &HitRecord) -> Vec<(Ray, Color)> {
+        self.as_ref().specular(ray, hit)
+    }
+
+    fn fresnel(&self, ray: Ray, hit: &HitRecord) -> Option<(Interface, Color)> {
+        self.as_ref().fresnel(ray, hit)
+    }
+    fn id(&self) -> Option<u32> {
+        self.as_ref().id()
+    }
 }
 
 /// A realistic perfectly diffusive material.
@@ -53,30 +156,45 @@ impl<T: Texture> Material for Lambertian<T> {
         }
 
         let scattered = Ray::new(hit.point, scatter_direction).with_time(ray.time());
-        Some((scattered, self.albedo.color_at(hit.u, hit.v, hit.point)))
+        Some((scattered, self.albedo.color_at_hit(&hit)))
     }
 
-    fn emit(&self, _u: f32, _v: f32, _hit_point: Vector3<f32>) -> Color {
+    fn emit(&self, _u: Float, _v: Float, _hit_point: Vector3<Float>) -> Color {
         BLACK
     }
+
+    fn evaluate(
+        &self,
+        _ray: Ray,
+        hit: &HitRecord,
+        direction: Vector3<Float>,
+    ) -> Option<(Color, Float)> {
+        let cosine = hit.normal.dot(&direction.normalize()).max(0.);
+        let pdf = cosine / PI;
+        Some((pdf * self.albedo.color_at_hit(hit), pdf))
+    }
+
+    fn albedo(&self) -> Option<Color> {
+        self.albedo.constant_color()
+    }
 }
 
 /// A fuzzy reflective material (metal).
 #[derive(Clone, Debug)]
 pub struct Metal<T: Texture> {
     albedo: T,
-    fuzz: f32,
+    fuzz: Float,
 }
 
 impl<T: Texture> Metal<T> {
-    pub fn new(albedo: T, fuzz: f32) -> Self {
+    pub fn new(albedo: T, fuzz: Float) -> Self {
         let fuzz = if fuzz < 1. { fuzz } else { 1. };
         Self { albedo, fuzz }
     }
 }
 
 impl Metal<SolidColor> {
-    pub fn solid_color(albedo: Color, fuzz: f32) -> Self {
+    pub fn solid_color(albedo: Color, fuzz: Float) -> Self {
         let albedo = SolidColor::new(albedo);
         Self { albedo, fuzz }
     }
@@ -91,30 +209,81 @@ impl<T: Texture> Material for Metal<T> {
         )
         .with_time(ray.time());
         if scattered.direction().dot(&hit.normal) > 0. {
-            return Some((scattered, self.albedo.color_at(hit.u, hit.v, hit.point)));
+            return Some((scattered, self.albedo.color_at_hit(&hit)));
         }
         None
     }
 
-    fn emit(&self, _u: f32, _v: f32, _hit_point: Vector3<f32>) -> Color {
+    fn emit(&self, _u: Float, _v: Float, _hit_point: Vector3<Float>) -> Color {
         BLACK
     }
+
+    fn albedo(&self) -> Option<Color> {
+        self.albedo.constant_color()
+    }
+
+    /// Reflect perfectly, ignoring the fuzz.
+    fn specular(&self, ray: Ray, hit: &HitRecord) -> Vec<(Ray, Color)> {
+        let reflected = reflect(&ray.direction().normalize(), &hit.normal);
+        vec![(
+            Ray::new(hit.point, reflected).with_time(ray.time()),
+            self.albedo.color_at_hit(hit),
+        )]
+    }
+
+    /// A [metal](Interface::metal) with the albedo as its reflectance.
+    fn fresnel(&self, _ray: Ray, hit: &HitRecord) -> Option<(Interface, Color)> {
+        Some((Interface::metal(self.albedo.color_at_hit(hit)), WHITE))
+    }
 }
 
 /// A transparent material.
+///
+/// # Fields
+/// - `index_of_refraction`: Its index of refraction.
+/// - `absorption`: Absorption coefficient per unit length for each channel. Light traveling a distance `d` through the material is attenuated by `exp(-absorption * d)` (Beer–Lambert law), which gives colored glass.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Dielectric};
+/// // Green glass: red and blue are absorbed
+/// let glass = Dielectric::new(1.5).with_absorption(color![2., 0.2, 2.]);
+/// ```
 #[derive(Clone, Debug)]
 pub struct Dielectric {
-    index_of_refraction: f32,
+    index_of_refraction: Float,
+    absorption: Color,
 }
 
 impl Dielectric {
-    pub fn new(index_of_refraction: f32) -> Self {
+    pub fn new(index_of_refraction: Float) -> Self {
         Self {
             index_of_refraction,
+            absorption: BLACK,
         }
     }
 
-    fn reflectance(cos: f32, refraction_ratio: f32) -> f32 {
+    /// Consume `self` and set the absorption coefficient per unit length.
+    pub fn with_absorption(mut self, absorption: Color) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    /// Fraction of the light that is not absorbed on the way to `hit`.
+    ///
+    /// Only rays hitting the back face traveled through the material.
+    fn transmittance(&self, ray: Ray, hit: &HitRecord) -> Color {
+        if hit.front_face || self.absorption == BLACK {
+            return color![1., 1., 1.];
+        }
+        let distance = hit.t * ray.direction().norm();
+        self.absorption
+            .into_iter()
+            .map(|absorption| (-absorption * distance).exp())
+            .collect()
+    }
+
+    fn reflectance(cos: Float, refraction_ratio: Float) -> Float {
         let mut r0 = (1. - refraction_ratio) / (1. + refraction_ratio);
         r0 *= r0;
         r0 + (1. - r0) * (1. - cos).powi(5)
@@ -123,7 +292,7 @@ impl Dielectric {
 
 impl Material for Dielectric {
     fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)> {
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
 
         let refraction_ratio = if hit.front_face {
             1. / self.index_of_refraction
@@ -132,7 +301,7 @@ impl Material for Dielectric {
         };
 
         let unit_direction = ray.direction().normalize();
-        let cos_theta = f32::min(-unit_direction.dot(&hit.normal), 1.);
+        let cos_theta = Float::min(-unit_direction.dot(&hit.normal), 1.);
         let sin_theta = (1. - cos_theta.powi(2)).sqrt();
 
         let cannot_refrect = refraction_ratio * sin_theta > 1.;
@@ -144,12 +313,52 @@ impl Material for Dielectric {
             };
 
         let scattered = Ray::new(hit.point, direction).with_time(ray.time());
-        Some((scattered, color![1., 1., 1.]))
+        Some((scattered, self.transmittance(ray, &hit)))
     }
 
-    fn emit(&self, _u: f32, _v: f32, _hit_point: Vector3<f32>) -> Color {
+    fn emit(&self, _u: Float, _v: Float, _hit_point: Vector3<Float>) -> Color {
         BLACK
     }
+
+    /// Both reflect and refract, weighted by the reflectance.
+    fn specular(&self, ray: Ray, hit: &HitRecord) -> Vec<(Ray, Color)> {
+        let refraction_ratio = if hit.front_face {
+            1. / self.index_of_refraction
+        } else {
+            self.index_of_refraction
+        };
+
+        let unit_direction = ray.direction().normalize();
+        let cos_theta = Float::min(-unit_direction.dot(&hit.normal), 1.);
+        let sin_theta = (1. - cos_theta.powi(2)).sqrt();
+        let reflected =
+            Ray::new(hit.point, reflect(&unit_direction, &hit.normal)).with_time(ray.time());
+
+        let transmittance = self.transmittance(ray, hit);
+        if refraction_ratio * sin_theta > 1. {
+            return vec![(reflected, transmittance)];
+        }
+
+        let reflectance = Dielectric::reflectance(cos_theta, refraction_ratio);
+        let refracted = Ray::new(
+            hit.point,
+            refract(&unit_direction, &hit.normal, refraction_ratio),
+        )
+        .with_time(ray.time());
+        vec![
+            (reflected, reflectance * transmittance),
+            (refracted, (1. - reflectance) * transmittance),
+        ]
+    }
+
+    fn fresnel(&self, ray: Ray, hit: &HitRecord) -> Option<(Interface, Color)> {
+        Some((
+            Interface::Dielectric {
+                ior: self.index_of_refraction,
+            },
+            self.transmittance(ray, hit),
+        ))
+    }
 }
 
 /// A diffusive light-emitting material.
@@ -178,9 +387,13 @@ impl<T: Texture> Material for DiffuseLight<T> {
         None
     }
 
-    fn emit(&self, u: f32, v: f32, hit_point: Vector3<f32>) -> Color {
+    fn emit(&self, u: Float, v: Float, hit_point: Vector3<Float>) -> Color {
         self.texture.color_at(u, v, hit_point)
     }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
 }
 
 /// An isotropically scattering material.
@@ -205,11 +418,832 @@ impl Isotropic<SolidColor> {
 impl<T: Texture> Material for Isotropic<T> {
     fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)> {
         let scattered = Ray::new(hit.point, random_vector_in_unit_sphere()).with_time(ray.time());
-        let attenuation = self.albedo.color_at(hit.u, hit.v, hit.point);
+        let attenuation = self.albedo.color_at_hit(&hit);
+        Some((scattered, attenuation))
+    }
+
+    fn emit(&self, _u: Float, _v: Float, _point: Vector3<Float>) -> Color {
+        BLACK
+    }
+
+    fn evaluate(
+        &self,
+        _ray: Ray,
+        hit: &HitRecord,
+        _direction: Vector3<Float>,
+    ) -> Option<(Color, Float)> {
+        let pdf = 1. / (4. * PI);
+        Some((pdf * self.albedo.color_at_hit(hit), pdf))
+    }
+
+    fn albedo(&self) -> Option<Color> {
+        self.albedo.constant_color()
+    }
+}
+
+/// A material of media that scatters anisotropically with the Henyey-Greenstein phase function.
+///
+/// The asymmetry `g` in (-1,1) is the mean cosine of the scattering angle: positive values scatter forward (e.g. 0.85 for clouds), negative values backward and 0 is [`Isotropic`].
+///
+/// # Fields
+/// - `albedo`: Fraction of the light that is scattered instead of absorbed.
+/// - `g`: Asymmetry of the scattering.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::HenyeyGreenstein};
+/// let cloud = HenyeyGreenstein::solid_color(color![0.9, 0.9, 0.9], 0.85);
+/// assert!(cloud.phase(1.) > cloud.phase(-1.));
+/// ```
+#[derive(Clone, Debug)]
+pub struct HenyeyGreenstein<T: Texture> {
+    albedo: T,
+    g: Float,
+}
+
+impl<T: Texture> HenyeyGreenstein<T> {
+    /// # Panics
+    /// If `g` is not in (-1,1).
+    pub fn new(albedo: T, g: Float) -> Self {
+        assert!(g.abs() < 1., "the asymmetry {g} is not in (-1,1)");
+        Self { albedo, g }
+    }
+
+    pub fn g(&self) -> Float {
+        self.g
+    }
+
+    /// Probability density of scattering by an angle with cosine `cos_theta` to the direction of propagation.
+    pub fn phase(&self, cos_theta: Float) -> Float {
+        let g = self.g;
+        let denominator = 1. + g * g - 2. * g * cos_theta;
+        (1. - g * g) / (4. * PI * denominator * denominator.sqrt())
+    }
+
+    /// Cosine of a random scattering angle distributed according to the [`phase`](HenyeyGreenstein::phase).
+    fn sample_cos_theta(&self) -> Float {
+        let g = self.g;
+        let xi = crate::random::rng().gen::<Float>();
+        if g.abs() < 1e-3 {
+            return 1. - 2. * xi;
+        }
+        let square = (1. - g * g) / (1. - g + 2. * g * xi);
+        ((1. + g * g - square * square) / (2. * g)).clamp(-1., 1.)
+    }
+}
+
+impl HenyeyGreenstein<SolidColor> {
+    pub fn solid_color(albedo: Color, g: Float) -> Self {
+        Self::new(SolidColor::new(albedo), g)
+    }
+}
+
+impl<T: Texture> Material for HenyeyGreenstein<T> {
+    fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)> {
+        let forward = ray.direction().normalize();
+        let cos_theta = self.sample_cos_theta();
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * PI * crate::random::rng().gen::<Float>();
+        let (tangent, bitangent) = orthonormal_basis(&forward);
+        let direction = sin_theta * phi.cos() * tangent
+            + sin_theta * phi.sin() * bitangent
+            + cos_theta * forward;
+
+        let scattered = Ray::new(hit.point, direction).with_time(ray.time());
+        let attenuation = self.albedo.color_at_hit(&hit);
         Some((scattered, attenuation))
     }
 
-    fn emit(&self, _u: f32, _v: f32, _point: Vector3<f32>) -> Color {
+    fn emit(&self, _u: Float, _v: Float, _point: Vector3<Float>) -> Color {
+        BLACK
+    }
+
+    fn evaluate(
+        &self,
+        ray: Ray,
+        hit: &HitRecord,
+        direction: Vector3<Float>,
+    ) -> Option<(Color, Float)> {
+        // Light arriving from `direction` continues along the ray if it is scattered forward.
+        let cos_theta = ray
+            .direction()
+            .normalize()
+            .dot(&direction.normalize())
+            .clamp(-1., 1.);
+        let pdf = self.phase(cos_theta);
+        Some((pdf * self.albedo.color_at_hit(hit), pdf))
+    }
+
+    fn albedo(&self) -> Option<Color> {
+        self.albedo.constant_color()
+    }
+}
+
+/// A simplified Marschner hair model for [`BezierCurve`](crate::bezier::BezierCurve)s, see [`hair`](crate::hair).
+///
+/// Light is scattered by three lobes around the cone of directions that a smooth fiber would reflect into:
+/// - R: reflection at the surface, which is white and slightly shifted towards the root,
+/// - TT: transmission through the fiber, which is tinted by the color once and continues forward,
+/// - TRT: an internal reflection, which is tinted twice and shifted towards the tip.
+///
+/// Each lobe is a Gaussian in the longitudinal angle and a trimmed logistic distribution in the azimuthal angle around the fiber, so it can be sampled exactly.
+/// The fiber axis is the tangent of the hit.
+///
+/// # Fields
+/// - `color`: Color of the light that passes through the fiber once.
+/// - `longitudinal_roughness`: Standard deviation of the longitudinal angle of the R lobe in radians.
+/// - `azimuthal_roughness`: Scale of the azimuthal distribution of the TT lobe in radians. The other lobes are much wider.
+/// - `scale_angle`: Tilt of the scales on the surface of the fiber in radians, which shifts the lobes along it.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Hair};
+/// let blond = Hair::solid_color(color![0.85, 0.65, 0.35]).with_longitudinal_roughness(0.2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Hair<T: Texture> {
+    color: T,
+    longitudinal_roughness: Float,
+    azimuthal_roughness: Float,
+    scale_angle: Float,
+}
+
+/// Weight, longitudinal shift, longitudinal roughness, azimuthal center and azimuthal scale of a lobe of [`Hair`].
+type HairLobe = (Color, Float, Float, Float, Float);
+
+impl<T: Texture> Hair<T> {
+    pub fn new(color: T) -> Self {
+        Self {
+            color,
+            longitudinal_roughness: 0.15,
+            azimuthal_roughness: 0.3,
+            scale_angle: 0.05,
+        }
+    }
+
+    /// Consume `self` and set the longitudinal roughness (0.15 by default), i.e. the width of the highlights along the fiber.
+    pub fn with_longitudinal_roughness(mut self, roughness: Float) -> Self {
+        self.longitudinal_roughness = roughness.max(1e-3);
+        self
+    }
+
+    /// Consume `self` and set the azimuthal roughness (0.3 by default), i.e. how much the transmitted light spreads around the fiber.
+    pub fn with_azimuthal_roughness(mut self, roughness: Float) -> Self {
+        self.azimuthal_roughness = roughness.max(1e-3);
+        self
+    }
+
+    /// Consume `self` and set the tilt of the scales (0.05 by default).
+    pub fn with_scale_angle(mut self, angle: Float) -> Self {
+        self.scale_angle = angle;
+        self
+    }
+
+    /// The R, TT and TRT lobes for the fiber `color`. Their weights sum to at most 1, so no energy is created.
+    fn lobes(&self, color: Color) -> [HairLobe; 3] {
+        let (alpha, beta, s) = (
+            self.scale_angle,
+            self.longitudinal_roughness,
+            self.azimuthal_roughness,
+        );
+        [
+            (0.1 * WHITE, -2. * alpha, beta, 0., 4. * s),
+            (0.5 * color, alpha, beta / 2., PI, s),
+            (0.4 * color * color, 3. * alpha, 2. * beta, 0., 4. * s),
+        ]
+    }
+
+    /// Probability of sampling each lobe, proportional to the mean of its weight.
+    fn lobe_probabilities(lobes: &[HairLobe; 3]) -> Option<[Float; 3]> {
+        let means = lobes.map(|(weight, ..)| (weight.r() + weight.g() + weight.b()) / 3.);
+        let sum: Float = means.iter().sum();
+        (sum > 0.).then(|| means.map(|mean| mean / sum))
+    }
+
+    /// Longitudinal angle and azimuth of the unit vector `direction` around the fiber of `hit`.
+    fn angles(hit: &HitRecord, direction: Vector3<Float>) -> (Float, Float) {
+        let (first, second) = orthonormal_basis(&hit.tangent);
+        let theta = direction.dot(&hit.tangent).clamp(-1., 1.).asin();
+        let phi = direction.dot(&second).atan2(direction.dot(&first));
+        (theta, phi)
+    }
+
+    /// BSDF times cosine and probability density of scattering light from `incoming` towards `outgoing` (both unit vectors pointing away from the fiber).
+    fn evaluate_directions(
+        &self,
+        hit: &HitRecord,
+        outgoing: Vector3<Float>,
+        incoming: Vector3<Float>,
+    ) -> Option<(Color, Float)> {
+        let lobes = self.lobes(self.color.color_at_hit(hit));
+        let probabilities = Self::lobe_probabilities(&lobes)?;
+        let (theta_o, phi_o) = Self::angles(hit, outgoing);
+        let (theta_i, phi_i) = Self::angles(hit, incoming);
+
+        let mut bsdf = BLACK;
+        let mut pdf = 0.;
+        for ((weight, shift, beta, center, scale), probability) in
+            lobes.into_iter().zip(probabilities)
+        {
+            // Density in the longitudinal angle and azimuth of the incoming direction
+            let theta_h = (theta_i + theta_o) / 2.;
+            let longitudinal = gaussian(theta_h - shift, beta) / 2.;
+            let azimuthal = trimmed_logistic(wrap_angle(phi_i - phi_o - center), scale);
+            bsdf += longitudinal * azimuthal * weight;
+            pdf += probability * longitudinal * azimuthal;
+        }
+        // Convert from the density in the angles to one in solid angle.
+        let cos_theta = theta_i.cos().max(1e-4);
+        Some((bsdf / cos_theta, pdf / cos_theta))
+    }
+}
+
+impl Hair<SolidColor> {
+    pub fn solid_color(color: Color) -> Self {
+        Self::new(SolidColor::new(color))
+    }
+}
+
+impl<T: Texture> Material for Hair<T> {
+    fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)> {
+        let outgoing = -ray.direction().normalize();
+        let lobes = self.lobes(self.color.color_at_hit(&hit));
+        let probabilities = Self::lobe_probabilities(&lobes)?;
+
+        let mut rng = crate::random::rng();
+        let choice: Float = rng.gen();
+        let lobe = if choice < probabilities[0] {
+            0
+        } else if choice < probabilities[0] + probabilities[1] {
+            1
+        } else {
+            2
+        };
+        let (_, shift, beta, center, scale) = lobes[lobe];
+
+        let (theta_o, phi_o) = Self::angles(&hit, outgoing);
+        // Box-Muller transform for the Gaussian
+        let normal =
+            (-2. * (1. - rng.gen::<Float>()).ln()).sqrt() * (2. * PI * rng.gen::<Float>()).cos();
+        let theta_i = 2. * (shift + beta * normal) - theta_o;
+        if theta_i.abs() >= FRAC_PI_2 {
+            return None;
+        }
+        let phi_i = phi_o + center + sample_trimmed_logistic(rng.gen(), scale);
+
+        let (first, second) = orthonormal_basis(&hit.tangent);
+        let direction = theta_i.sin() * hit.tangent
+            + theta_i.cos() * (phi_i.cos() * first + phi_i.sin() * second);
+        let (bsdf, pdf) = self.evaluate_directions(&hit, outgoing, direction)?;
+        if pdf <= 0. {
+            return None;
+        }
+        let scattered = Ray::new(hit.point, direction).with_time(ray.time());
+        Some((scattered, bsdf / pdf))
+    }
+
+    fn emit(&self, _u: Float, _v: Float, _point: Vector3<Float>) -> Color {
         BLACK
     }
+
+    fn evaluate(
+        &self,
+        ray: Ray,
+        hit: &HitRecord,
+        direction: Vector3<Float>,
+    ) -> Option<(Color, Float)> {
+        self.evaluate_directions(hit, -ray.direction().normalize(), direction.normalize())
+    }
+
+    fn albedo(&self) -> Option<Color> {
+        self.color.constant_color()
+    }
+}
+
+/// Density of a Gaussian with mean 0 and standard deviation `sigma` at `x`.
+fn gaussian(x: Float, sigma: Float) -> Float {
+    (-x * x / (2. * sigma * sigma)).exp() / (sigma * (2. * PI).sqrt())
+}
+
+/// The angle `x` wrapped into \[-π,π\].
+fn wrap_angle(x: Float) -> Float {
+    (x + PI).rem_euclid(2. * PI) - PI
+}
+
+/// Cumulative distribution function of the logistic distribution with scale `s`.
+fn logistic_cdf(x: Float, s: Float) -> Float {
+    1. / (1. + (-x / s).exp())
+}
+
+/// Density of the logistic distribution with scale `s`, restricted to \[-π,π\].
+fn trimmed_logistic(x: Float, s: Float) -> Float {
+    let e = (-x.abs() / s).exp();
+    let logistic = e / (s * (1. + e) * (1. + e));
+    logistic / (logistic_cdf(PI, s) - logistic_cdf(-PI, s))
+}
+
+/// Sample the [`trimmed_logistic`] distribution with the uniform random number `u`.
+fn sample_trimmed_logistic(u: Float, s: Float) -> Float {
+    let (low, high) = (logistic_cdf(-PI, s), logistic_cdf(PI, s));
+    let x = -s * (1. / (low + u * (high - low)) - 1.).ln();
+    x.clamp(-PI, PI)
+}
+
+/// A thin transparent film on top of another material, e.g. a soap bubble or an oil slick.
+///
+/// Light reflected at the top and the bottom of the film interferes, so the reflections are tinted depending on the thickness of the film and the view angle.
+/// Transmitted light is not changed. The tint for red, green and blue is evaluated at 650 nm, 532 nm and 450 nm.
+///
+/// # Fields
+/// - `material`: The coated [`Material`].
+/// - `thickness`: Thickness of the film in nanometers.
+/// - `thickness_texture`: Scales `thickness` by the mean of its channels, so the thickness can vary over the surface.
+/// - `index_of_refraction`: Index of refraction of the film.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::{Dielectric, ThinFilm}, textures::PerlinNoiseTexture};
+/// let soap_bubble = ThinFilm::new(Dielectric::new(1.), 400., 1.33)
+///     .with_thickness_texture(PerlinNoiseTexture::new(2.));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ThinFilm<M: Material, T: Texture = SolidColor> {
+    material: M,
+    thickness: Float,
+    thickness_texture: T,
+    index_of_refraction: Float,
+}
+
+impl<M: Material> ThinFilm<M> {
+    pub fn new(material: M, thickness: Float, index_of_refraction: Float) -> Self {
+        Self {
+            material,
+            thickness,
+            thickness_texture: SolidColor::new(color![1., 1., 1.]),
+            index_of_refraction,
+        }
+    }
+}
+
+impl<M: Material, T: Texture> ThinFilm<M, T> {
+    /// Consume `self` and scale the thickness by a [`Texture`].
+    pub fn with_thickness_texture<U: Texture>(self, thickness_texture: U) -> ThinFilm<M, U> {
+        ThinFilm {
+            material: self.material,
+            thickness: self.thickness,
+            thickness_texture,
+            index_of_refraction: self.index_of_refraction,
+        }
+    }
+
+    /// Tint of light reflected towards `direction` (pointing away from the surface).
+    ///
+    /// The two reflected waves have the same amplitude, so the tint lies in \[0,2\] and is one on average.
+    fn tint(&self, hit: &HitRecord, direction: Vector3<Float>) -> Color {
+        let scale = self.thickness_texture.color_at_hit(hit);
+        let thickness = self.thickness * (scale.r() + scale.g() + scale.b()) / 3.;
+
+        let cos_incident = hit.normal.dot(&direction.normalize()).clamp(0., 1.);
+        let sin_transmitted = (1. - cos_incident.powi(2)).sqrt() / self.index_of_refraction;
+        let cos_transmitted = (1. - sin_transmitted.powi(2)).max(0.).sqrt();
+        let path_difference = 2. * self.index_of_refraction * thickness * cos_transmitted;
+
+        // The reflection at the top is phase shifted by half a wavelength.
+        [650., 532., 450.]
+            .into_iter()
+            .map(|wavelength: Float| 1. - (2. * PI * path_difference / wavelength).cos())
+            .collect()
+    }
+}
+
+impl<M: Material, T: Texture> Material for ThinFilm<M, T> {
+    fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)> {
+        let tint = self.tint(&hit, -ray.direction());
+        let normal = hit.normal;
+        let (scattered, attenuation) = self.material.scatter(ray, hit)?;
+        if scattered.direction().dot(&normal) > 0. {
+            Some((scattered, tint * attenuation))
+        } else {
+            Some((scattered, attenuation))
+        }
+    }
+
+    fn emit(&self, u: Float, v: Float, point: Vector3<Float>) -> Color {
+        self.material.emit(u, v, point)
+    }
+
+    fn evaluate(
+        &self,
+        ray: Ray,
+        hit: &HitRecord,
+        direction: Vector3<Float>,
+    ) -> Option<(Color, Float)> {
+        let (color, pdf) = self.material.evaluate(ray, hit, direction)?;
+        if direction.dot(&hit.normal) > 0. {
+            Some((self.tint(hit, -ray.direction()) * color, pdf))
+        } else {
+            Some((color, pdf))
+        }
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn specular(&self, ray: Ray, hit: &HitRecord) -> Vec<(Ray, Color)> {
+        let tint = self.tint(hit, -ray.direction());
+        self.material
+            .specular(ray, hit)
+            .into_iter()
+            .map(|(scattered, weight)| {
+                if scattered.direction().dot(&hit.normal) > 0. {
+                    (scattered, tint * weight)
+                } else {
+                    (scattered, weight)
+                }
+            })
+            .collect()
+    }
+}
+
+/// A layered car paint: a clear coat on top of a base that is partly diffuse and partly metallic with sparkling flakes.
+///
+/// The clear coat reflects according to the Fresnel equations (with an index of refraction of 1.5), the remaining light reaches the base.
+/// The flakes are small randomly tilted mirrors in the metallic part of the base.
+/// Like [`Metal`], it is not evaluated for sampling lights directly, as it consists of glossy lobes.
+///
+/// # Fields
+/// - `base`: Color of the base.
+/// - `metallic`: Fraction of the base that reflects like a metal (in \[0,1\]), the rest is diffuse.
+/// - `roughness`: Fuzz of the metallic reflection.
+/// - `clearcoat`: Strength of the clear coat (in \[0,1\]).
+/// - `clearcoat_roughness`: Fuzz of the reflection of the clear coat.
+/// - `flake_scale`: Number of flakes per unit length.
+/// - `flake_intensity`: How much the flakes are tilted. Zero disables them.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::CarPaint};
+/// let paint = CarPaint::solid_color(color![0.6, 0.05, 0.05])
+///     .with_metallic(0.6, 0.3)
+///     .with_clearcoat(1., 0.02)
+///     .with_flakes(200., 0.3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CarPaint<T: Texture> {
+    base: T,
+    metallic: Float,
+    roughness: Float,
+    clearcoat: Float,
+    clearcoat_roughness: Float,
+    flake_scale: Float,
+    flake_intensity: Float,
+}
+
+impl<T: Texture> CarPaint<T> {
+    pub fn new(base: T) -> Self {
+        Self {
+            base,
+            metallic: 0.,
+            roughness: 0.3,
+            clearcoat: 1.,
+            clearcoat_roughness: 0.,
+            flake_scale: 100.,
+            flake_intensity: 0.,
+        }
+    }
+
+    /// Consume `self` and set the fraction and fuzz of the metallic part of the base.
+    pub fn with_metallic(mut self, metallic: Float, roughness: Float) -> Self {
+        self.metallic = metallic.clamp(0., 1.);
+        self.roughness = roughness;
+        self
+    }
+
+    /// Consume `self` and set the strength and fuzz of the clear coat.
+    pub fn with_clearcoat(mut self, clearcoat: Float, roughness: Float) -> Self {
+        self.clearcoat = clearcoat.clamp(0., 1.);
+        self.clearcoat_roughness = roughness;
+        self
+    }
+
+    /// Consume `self` and set the number of flakes per unit length and how much they are tilted.
+    pub fn with_flakes(mut self, scale: Float, intensity: Float) -> Self {
+        self.flake_scale = scale;
+        self.flake_intensity = intensity;
+        self
+    }
+
+    /// Probability that light is reflected by the clear coat.
+    fn clearcoat_reflectance(&self, ray: Ray, hit: &HitRecord) -> Float {
+        let cos = (-ray.direction().normalize().dot(&hit.normal)).clamp(0., 1.);
+        self.clearcoat * Dielectric::reflectance(cos, 1. / 1.5)
+    }
+
+    /// Normal of the flake at `point`.
+    fn flake_normal(&self, point: Vector3<Float>, normal: Vector3<Float>) -> Vector3<Float> {
+        if self.flake_intensity <= 0. {
+            return normal;
+        }
+
+        // Hash the cell of the flake into a random tilt, so each flake keeps its orientation.
+        let cell = (self.flake_scale * point).map(|c| c.floor() as i32 as u32);
+        let mut hash = cell.iter().fold(0x811c_9dc5_u32, |hash, &c| {
+            (hash ^ c).wrapping_mul(0x0100_0193)
+        });
+        let mut random = || {
+            hash ^= hash << 13;
+            hash ^= hash >> 17;
+            hash ^= hash << 5;
+            hash as Float / u32::MAX as Float * 2. - 1.
+        };
+        let tilt = vector![random(), random(), random()];
+
+        let flake = (normal + self.flake_intensity * tilt).normalize();
+        if flake.dot(&normal) > 0. {
+            flake
+        } else {
+            normal
+        }
+    }
+}
+
+impl CarPaint<SolidColor> {
+    pub fn solid_color(base: Color) -> Self {
+        Self::new(SolidColor::new(base))
+    }
+}
+
+impl<T: Texture> Material for CarPaint<T> {
+    fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)> {
+        let mut rng = crate::random::rng();
+        let unit_direction = ray.direction().normalize();
+
+        let (direction, attenuation) = if rng.gen::<Float>() < self.clearcoat_reflectance(ray, &hit)
+        {
+            let reflected = reflect(&unit_direction, &hit.normal);
+            (
+                reflected + self.clearcoat_roughness * random_vector_in_unit_sphere(),
+                color![1., 1., 1.],
+            )
+        } else if rng.gen::<Float>() < self.metallic {
+            let normal = self.flake_normal(hit.point, hit.normal);
+            let reflected = reflect(&unit_direction, &normal);
+            (
+                reflected + self.roughness * random_vector_in_unit_sphere(),
+                self.base.color_at_hit(&hit),
+            )
+        } else {
+            let mut direction = hit.normal + random_unit_vector_in_unit_sphere();
+            if near_zero(&direction) {
+                direction = hit.normal;
+            }
+            (direction, self.base.color_at_hit(&hit))
+        };
+
+        if direction.dot(&hit.normal) <= 0. {
+            return None;
+        }
+        Some((
+            Ray::new(hit.point, direction).with_time(ray.time()),
+            attenuation,
+        ))
+    }
+
+    fn emit(&self, _u: Float, _v: Float, _hit_point: Vector3<Float>) -> Color {
+        BLACK
+    }
+
+    fn albedo(&self) -> Option<Color> {
+        self.base.constant_color()
+    }
+
+    /// Reflect perfectly at the clear coat and the metallic part of the base, ignoring the fuzz.
+    fn specular(&self, ray: Ray, hit: &HitRecord) -> Vec<(Ray, Color)> {
+        let reflectance = self.clearcoat_reflectance(ray, hit);
+        let reflected = Ray::new(
+            hit.point,
+            reflect(&ray.direction().normalize(), &hit.normal),
+        )
+        .with_time(ray.time());
+        let weight = reflectance * color![1., 1., 1.]
+            + (1. - reflectance) * self.metallic * self.base.color_at_hit(hit);
+        vec![(reflected, weight)]
+    }
+}
+
+/// A material that lets [`Ray`]s pass through unchanged.
+///
+/// This is useful for shells that bound something without being visible, e.g. the boundary of a [`ConstantMedium`](crate::shapes::ConstantMedium).
+/// The path tracers and shadow [`Ray`]s skip it entirely.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Null;
+
+impl Material for Null {
+    fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)> {
+        Some((
+            Ray::new(hit.point, ray.direction()).with_time(ray.time()),
+            color![1., 1., 1.],
+        ))
+    }
+
+    fn emit(&self, _u: Float, _v: Float, _hit_point: Vector3<Float>) -> Color {
+        BLACK
+    }
+
+    fn is_null(&self) -> bool {
+        true
+    }
+
+    fn specular(&self, ray: Ray, hit: &HitRecord) -> Vec<(Ray, Color)> {
+        vec![(
+            Ray::new(hit.point, ray.direction()).with_time(ray.time()),
+            color![1., 1., 1.],
+        )]
+    }
+}
+
+/// A material that is invisible apart from the shadows cast onto it, e.g. for compositing a render onto a photograph.
+///
+/// Seen directly by the camera with a transparent background (see [`Alpha`](crate::raytracer::Alpha)), it is black with the fraction of blocked light as alpha.
+/// Otherwise it shows the background, darkened by the shadows.
+/// Only the path tracer and photon mapping support it; the other integrators render it black.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::{Lambertian, ShadowCatcher}, raytracer::Alpha, shapes::Sphere};
+/// # let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+/// let mut raytracer = Raytracer::new(camera, color![0.5, 0.7, 1.], 16, 16, 4, 4)
+///     .with_alpha(Alpha::Transparent { show_background: false });
+/// raytracer.world.push(Sphere::new(vector![0., 0., -1.], 0.5, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+/// raytracer.world.push(Sphere::new(vector![0., -100.5, -1.], 100., ShadowCatcher));
+/// let image = raytracer.render().unwrap().into_rgba_image().unwrap();
+/// // The sky is transparent and the floor is partially transparent next to the sphere.
+/// assert_eq!(image.get_pixel(0, 0).0[3], 0);
+/// assert!(image.pixels().any(|pixel| pixel.0[3] > 0 && pixel.0[3] < 255));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ShadowCatcher;
+
+impl Material for ShadowCatcher {
+    fn scatter(&self, _ray: Ray, _hit: HitRecord) -> Option<(Ray, Color)> {
+        None
+    }
+
+    fn emit(&self, _u: Float, _v: Float, _hit_point: Vector3<Float>) -> Color {
+        BLACK
+    }
+
+    fn is_shadow_catcher(&self) -> bool {
+        true
+    }
+}
+
+/// A material with different materials on the front and the back of a surface.
+///
+/// The front is the side the normal of the [`Hittable`] points to (e.g. the outside of a sphere).
+/// Light is only emitted by the front material, as emission does not know the side.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::{Lambertian, Metal, TwoSided}};
+/// // A sheet that is red on top and a mirror at the bottom.
+/// let material = TwoSided::new(Lambertian::solid_color(color![0.8, 0.1, 0.1]), Metal::solid_color(color![0.9, 0.9, 0.9], 0.));
+/// ```
+#[derive(Clone, Debug)]
+pub struct TwoSided<F: Material, B: Material> {
+    front: F,
+    back: B,
+}
+
+impl<F: Material, B: Material> TwoSided<F, B> {
+    pub fn new(front: F, back: B) -> Self {
+        Self { front, back }
+    }
+
+    fn side(&self, hit: &HitRecord) -> &dyn Material {
+        if hit.front_face {
+            &self.front
+        } else {
+            &self.back
+        }
+    }
+}
+
+impl<F: Material, B: Material> Material for TwoSided<F, B> {
+    fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)> {
+        self.side(&hit).scatter(ray, hit)
+    }
+
+    fn emit(&self, u: Float, v: Float, point: Vector3<Float>) -> Color {
+        self.front.emit(u, v, point)
+    }
+
+    fn evaluate(
+        &self,
+        ray: Ray,
+        hit: &HitRecord,
+        direction: Vector3<Float>,
+    ) -> Option<(Color, Float)> {
+        self.side(hit).evaluate(ray, hit, direction)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.front.is_emissive()
+    }
+
+    fn specular(&self, ray: Ray, hit: &HitRecord) -> Vec<(Ray, Color)> {
+        self.side(hit).specular(ray, hit)
+    }
+
+    fn fresnel(&self, ray: Ray, hit: &HitRecord) -> Option<(Interface, Color)> {
+        self.side(hit).fresnel(ray, hit)
+    }
+}
+
+/// A [`Material`] with an ID and a name, e.g. to separate it in an [ID pass](crate::raytracer::Raytracer::render_ids).
+///
+/// Apart from the ID, it behaves exactly like the wrapped material.
+///
+/// # Fields
+/// - `id`: Its ID.
+/// - `name`: Its name.
+/// - `material`: The wrapped [`Material`].
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::{Lambertian, Material, NamedMaterial}};
+/// let paint = NamedMaterial::new(3, "red paint", Lambertian::solid_color(color![0.8, 0.1, 0.1]));
+/// assert_eq!(paint.id(), Some(3));
+/// ```
+#[derive(Clone, Debug)]
+pub struct NamedMaterial<M: Material> {
+    id: u32,
+    name: String,
+    material: M,
+}
+
+impl<M: Material> NamedMaterial<M> {
+    pub fn new(id: u32, name: impl Into<String>, material: M) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            material,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn material(&self) -> &M {
+        &self.material
+    }
+}
+
+impl<M: Material> Material for NamedMaterial<M> {
+    fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)> {
+        self.material.scatter(ray, hit)
+    }
+
+    fn emit(&self, u: Float, v: Float, point: Vector3<Float>) -> Color {
+        self.material.emit(u, v, point)
+    }
+
+    fn evaluate(
+        &self,
+        ray: Ray,
+        hit: &HitRecord,
+        direction: Vector3<Float>,
+    ) -> Option<(Color, Float)> {
+        self.material.evaluate(ray, hit, direction)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn is_null(&self) -> bool {
+        self.material.is_null()
+    }
+
+    fn is_shadow_catcher(&self) -> bool {
+        self.material.is_shadow_catcher()
+    }
+
+    fn albedo(&self) -> Option<Color> {
+        self.material.albedo()
+    }
+
+    fn specular(&self, ray: Ray, hit: &HitRecord) -> Vec<(Ray, Color)> {
+        self.material.specular(ray, hit)
+    }
+
+    fn fresnel(&self, ray: Ray, hit: &HitRecord) -> Option<(Interface, Color)> {
+        self.material.fresnel(ray, hit)
+    }
+
+    fn id(&self) -> Option<u32> {
+        Some(self.id)
+    }
 }
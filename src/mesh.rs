@@ -0,0 +1,507 @@
+//! Triangle meshes, e.g. loaded from Wavefront OBJ files.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+use nalgebra::Rotation3;
+
+use crate::arena::{Primitives, TriangleArena, Triangles};
+use crate::hitrecord::HitRecord;
+use crate::hittable::Aabb;
+use crate::materials::Material;
+use crate::ray::Ray;
+use crate::shapes::{Movable, Offset};
+use crate::stats::SceneStats;
+use crate::textures::Texture;
+use crate::*;
+
+/// How the normals of a [`Mesh`] are computed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Shading {
+    /// Interpolate the normals at the vertices, so curved surfaces look smooth.
+    #[default]
+    Smooth,
+    /// Use the normal of each triangle, so the facets are visible.
+    Flat,
+}
+
+/// A surface made of triangles that share their vertices.
+///
+/// The triangles are stored in a [`TriangleArena`] (with one material), which is sorted into its hierarchy once at construction.
+/// With [`Shading::Smooth`], the normals at the vertices are taken from the input or, if there are none, averaged over the adjacent triangles (weighted by their area).
+///
+/// # Fields
+/// - `center`: Its [`Offset`].
+/// - `shading`: Its [`Shading`].
+/// - `triangles`: [`TriangleArena`] of its triangles.
+/// - `vertices`: Positions of its vertices, which are kept for [displacing](Mesh::with_displacement) it.
+/// - `faces`: Indices of the vertex at each corner of each triangle (and of the normal, if it has one).
+/// - `material`: Material of all triangles.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, mesh::{Mesh, Shading}, ray::Ray};
+/// // An octahedron
+/// let obj = "
+/// v 1 0 0\nv -1 0 0\nv 0 1 0\nv 0 -1 0\nv 0 0 1\nv 0 0 -1
+/// f 1 3 5\nf 3 2 5\nf 2 4 5\nf 4 1 5\nf 3 1 6\nf 2 3 6\nf 4 2 6\nf 1 4 6
+/// ";
+/// let material = Lambertian::solid_color(color![0.8, 0.8, 0.8]);
+/// let mesh = Mesh::from_obj(vector![0., 0., 0.], obj.as_bytes(), Shading::Smooth, material).unwrap();
+/// assert_eq!(mesh.triangle_count(), 8);
+///
+/// let ray = Ray::new(vector![0.2, 0.2, 5.], vector![0., 0., -1.]);
+/// let hit = mesh.hit(ray, 0., Float::INFINITY).unwrap();
+/// assert!(hit.shading_normal() != hit.geometric_normal());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    center: Offset,
+    shading: Shading,
+    triangles: TriangleArena,
+    vertices: Vec<Vector3<Float>>,
+    faces: Vec<[(usize, Option<usize>); 3]>,
+    material: Arc<dyn Material>,
+}
+
+impl Mesh {
+    /// Create a [`Mesh`] from vertices and the indices of the vertices of each triangle.
+    ///
+    /// # Parameters
+    /// - `center`: Origin the vertices are relative to.
+    /// - `vertices`: Positions of the vertices.
+    /// - `faces`: Three indices into `vertices` per triangle, counterclockwise seen from the front.
+    /// - `shading`: Whether the normals are interpolated.
+    /// - `material`: Material of the whole mesh.
+    ///
+    /// # Panics
+    /// If an index is out of bounds or there are no faces.
+    pub fn new<M: Material + 'static>(
+        center: Vector3<Float>,
+        vertices: &[Vector3<Float>],
+        faces: &[[usize; 3]],
+        shading: Shading,
+        material: M,
+    ) -> Self {
+        let corners = faces
+            .iter()
+            .map(|face| face.map(|index| (index, None)))
+            .collect::<Vec<_>>();
+        Self::from_corners(center, vertices, &[], &corners, shading, material)
+    }
+
+    /// Read a [`Mesh`] from a Wavefront OBJ file.
+    ///
+    /// Only vertices (`v`), normals (`vn`), and faces (`f`) are read, texture coordinates are ignored. Polygons are split into a fan of triangles.
+    /// Normals are only used if every face has them; otherwise they are generated from the adjacent faces.
+    pub fn from_obj<R: BufRead, M: Material + 'static>(
+        center: Vector3<Float>,
+        reader: R,
+        shading: Shading,
+        material: M,
+    ) -> io::Result<Self> {
+        let obj = read_obj(reader)?;
+        let faces: Vec<_> = obj
+            .polygons
+            .iter()
+            .flat_map(|polygon| {
+                (1..polygon.len() - 1).map(|i| [polygon[0], polygon[i], polygon[i + 1]])
+            })
+            .collect();
+        Ok(Self::from_corners(
+            center,
+            &obj.vertices,
+            &obj.normals,
+            &faces,
+            shading,
+            material,
+        ))
+    }
+
+    /// Open a Wavefront OBJ file and create a [`Mesh`] via [`from_obj`](Mesh::from_obj).
+    pub fn open<P: AsRef<Path>, M: Material + 'static>(
+        center: Vector3<Float>,
+        path: P,
+        shading: Shading,
+        material: M,
+    ) -> io::Result<Self> {
+        Self::from_obj(center, BufReader::new(File::open(path)?), shading, material)
+    }
+
+    pub fn shading(&self) -> Shading {
+        self.shading
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.primitives().len()
+    }
+
+    /// Positions of the vertices relative to the center.
+    pub fn vertices(&self) -> &[Vector3<Float>] {
+        &self.vertices
+    }
+
+    /// Indices of the vertex and (optionally) the normal of each corner of each triangle.
+    pub(crate) fn faces(&self) -> &[[(usize, Option<usize>); 3]] {
+        &self.faces
+    }
+
+    /// Consume `self` and turn it into displaced geometry, see [`Displacement`].
+    ///
+    /// Every triangle is split into four [`subdivisions`](Displacement::with_subdivisions) times, sharing the new vertices on the edges.
+    /// Then every vertex is moved along the normal averaged over the adjacent faces, so the surface stays closed even at hard edges, and the normals are generated again.
+    /// Meshes have no texture coordinates, so the texture is evaluated with (u, v) = (0, 0) at the position of the vertex relative to the center (e.g. a solid [`PerlinNoiseTexture`](crate::textures::PerlinNoiseTexture)).
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, mesh::{Displacement, Mesh, Shading}, textures::PerlinNoiseTexture};
+    /// let material = Lambertian::solid_color(color![0.8, 0.8, 0.8]);
+    /// let vertices = [vector![1., 0., 0.], vector![0., 1., 0.], vector![0., 0., 1.], vector![0., 0., 0.]];
+    /// let faces = [[0, 1, 2], [0, 3, 1], [1, 3, 2], [2, 3, 0]];
+    /// let rock = Mesh::new(vector![0., 0., 0.], &vertices, &faces, Shading::Smooth, material)
+    ///     .with_displacement(&Displacement::new(PerlinNoiseTexture::new(8.), 0.05).with_subdivisions(3));
+    /// assert_eq!(rock.triangle_count(), 4 * 64);
+    /// ```
+    pub fn with_displacement<T: Texture>(self, displacement: &Displacement<T>) -> Self {
+        let faces: Vec<_> = self
+            .faces
+            .iter()
+            .map(|face| face.map(|(vertex, _)| vertex))
+            .collect();
+        let mut normals = vertex_normals(&self.vertices, &self.faces);
+        let mut vertices = self.vertices;
+        let faces = subdivide(
+            &mut vertices,
+            &mut normals,
+            faces,
+            displacement.subdivisions,
+        );
+        for (vertex, normal) in vertices.iter_mut().zip(&normals) {
+            *vertex += displacement.height(0., 0., *vertex) * normal;
+        }
+
+        let faces = faces
+            .into_iter()
+            .map(|face| face.map(|vertex| (vertex, None)))
+            .collect();
+        Self::build(
+            self.center,
+            vertices,
+            &[],
+            faces,
+            self.shading,
+            self.material,
+        )
+    }
+
+    /// Build the triangles from the indices of the vertex and (optionally) the normal of each corner.
+    fn from_corners<M: Material + 'static>(
+        center: Vector3<Float>,
+        vertices: &[Vector3<Float>],
+        normals: &[Vector3<Float>],
+        faces: &[[(usize, Option<usize>); 3]],
+        shading: Shading,
+        material: M,
+    ) -> Self {
+        Self::build(
+            Offset::new(center),
+            vertices.to_vec(),
+            normals,
+            faces.to_vec(),
+            shading,
+            Arc::new(material),
+        )
+    }
+
+    /// Build the triangles and sort them into the [`TriangleArena`].
+    fn build(
+        center: Offset,
+        vertices: Vec<Vector3<Float>>,
+        normals: &[Vector3<Float>],
+        faces: Vec<[(usize, Option<usize>); 3]>,
+        shading: Shading,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        let has_normals = faces
+            .iter()
+            .all(|face| face.iter().all(|(_, normal)| normal.is_some()));
+        let generated = match shading {
+            Shading::Smooth if !has_normals => Some(vertex_normals(&vertices, &faces)),
+            _ => None,
+        };
+
+        let mut triangles = Triangles::default();
+        for face in &faces {
+            let normals = match (shading, &generated) {
+                (Shading::Flat, _) => None,
+                (Shading::Smooth, Some(generated)) => {
+                    Some(face.map(|(vertex, _)| generated[vertex]))
+                }
+                (Shading::Smooth, None) => Some(face.map(|(_, normal)| normals[normal.unwrap()])),
+            };
+            triangles.push(face.map(|(vertex, _)| vertices[vertex]), normals, 0);
+        }
+
+        Self {
+            center,
+            shading,
+            triangles: TriangleArena::new(vector![0., 0., 0.], triangles, vec![material.clone()])
+                .expect("meshes have faces"),
+            vertices,
+            faces,
+            material,
+        }
+    }
+}
+
+/// Displacement of a surface along its normal by a [`Texture`], see [`Mesh::with_displacement`] and [`Sphere::displaced`](crate::shapes::Sphere::displaced).
+///
+/// Unlike a normal map, this changes the actual geometry, so the silhouette and the shadows show the detail.
+/// The surface is tessellated into many small triangles before they are sorted into a hierarchy, so the detail is limited by the number of [`subdivisions`](Displacement::with_subdivisions).
+///
+/// # Fields
+/// - `texture`: The mean of its channels is the height.
+/// - `scale`: Distance a height of 1 moves the surface outwards. Negative values move it inwards.
+/// - `subdivisions`: How often the surface is subdivided before it is displaced.
+#[derive(Clone, Debug)]
+pub struct Displacement<T: Texture> {
+    texture: T,
+    scale: Float,
+    subdivisions: u8,
+}
+
+impl<T: Texture> Displacement<T> {
+    pub fn new(texture: T, scale: Float) -> Self {
+        Self {
+            texture,
+            scale,
+            subdivisions: 2,
+        }
+    }
+
+    /// Consume `self` and set how often the surface is subdivided (2 by default).
+    ///
+    /// Each subdivision of a [`Mesh`] quadruples the number of its triangles.
+    pub fn with_subdivisions(mut self, subdivisions: u8) -> Self {
+        self.subdivisions = subdivisions;
+        self
+    }
+
+    pub fn subdivisions(&self) -> u8 {
+        self.subdivisions
+    }
+
+    /// Distance the surface is moved along its normal at (`u`, `v`) and `point`.
+    pub(crate) fn height(&self, u: Float, v: Float, point: Vector3<Float>) -> Float {
+        let color = self.texture.color_at(u, v, point);
+        self.scale * (color.r() + color.g() + color.b()) / 3.
+    }
+}
+
+/// Split every triangle `subdivisions` times into four at the midpoints of its edges.
+///
+/// The positions and normals of the new vertices are interpolated linearly. Adjacent triangles share the vertices on their common edge.
+fn subdivide(
+    vertices: &mut Vec<Vector3<Float>>,
+    normals: &mut Vec<Vector3<Float>>,
+    mut faces: Vec<[usize; 3]>,
+    subdivisions: u8,
+) -> Vec<[usize; 3]> {
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: usize, b: usize| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                vertices.push((vertices[a] + vertices[b]) / 2.);
+                let normal = normals[a] + normals[b];
+                normals.push(normal.try_normalize(0.).unwrap_or(normals[a]));
+                vertices.len() - 1
+            })
+        };
+
+        faces = faces
+            .into_iter()
+            .flat_map(|[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+            })
+            .collect();
+    }
+    faces
+}
+
+/// Normal at each vertex, averaged over the adjacent faces weighted by their area.
+pub(crate) fn vertex_normals(
+    vertices: &[Vector3<Float>],
+    faces: &[[(usize, Option<usize>); 3]],
+) -> Vec<Vector3<Float>> {
+    let mut normals = vec![Vector3::zeros(); vertices.len()];
+    for face in faces {
+        let [a, b, c] = face.map(|(vertex, _)| vertex);
+        // The length of the cross product is twice the area.
+        let normal = (vertices[b] - vertices[a]).cross(&(vertices[c] - vertices[a]));
+        for vertex in [a, b, c] {
+            normals[vertex] += normal;
+        }
+    }
+
+    normals
+        .into_iter()
+        .map(|normal| normal.try_normalize(0.).unwrap_or_else(Vector3::y))
+        .collect()
+}
+
+/// Geometry read from a Wavefront OBJ file.
+///
+/// # Fields
+/// - `vertices`: Positions of the vertices.
+/// - `normals`: Normals referenced by the corners.
+/// - `texture_coordinates`: Texture coordinates (u, v) referenced by the corners.
+/// - `polygons`: Indices of the vertex and (optionally) the normal of each corner of each face, with at least three corners per face.
+/// - `texture_polygons`: Indices of the texture coordinates of each corner of each face, if it has them.
+pub(crate) struct Obj {
+    pub vertices: Vec<Vector3<Float>>,
+    pub normals: Vec<Vector3<Float>>,
+    pub texture_coordinates: Vec<(Float, Float)>,
+    pub polygons: Vec<Vec<(usize, Option<usize>)>>,
+    pub texture_polygons: Vec<Vec<Option<usize>>>,
+}
+
+/// Read the vertices (`v`), normals (`vn`), texture coordinates (`vt`), and faces (`f`) of a Wavefront OBJ file.
+///
+/// This fails if there are no faces.
+pub(crate) fn read_obj<R: BufRead>(reader: R) -> io::Result<Obj> {
+    let mut vertices = Vec::new();
+    let mut normals = Vec::new();
+    let mut texture_coordinates = Vec::new();
+    let mut polygons = Vec::new();
+    let mut texture_polygons = Vec::new();
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let invalid = |message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {message}", number + 1),
+            )
+        };
+
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                vertices.push(parse_vector(tokens).ok_or_else(|| invalid("invalid vertex"))?)
+            }
+            Some("vn") => {
+                normals.push(parse_vector(tokens).ok_or_else(|| invalid("invalid normal"))?)
+            }
+            Some("vt") => {
+                let mut coordinate = || tokens.next()?.parse::<Float>().ok();
+                let uv = coordinate().zip(coordinate());
+                texture_coordinates.push(uv.ok_or_else(|| invalid("invalid texture coordinates"))?)
+            }
+            Some("f") => {
+                let (corners, texture_corners): (Vec<_>, Vec<_>) = tokens
+                    .map(|token| {
+                        parse_corner(
+                            token,
+                            vertices.len(),
+                            texture_coordinates.len(),
+                            normals.len(),
+                        )
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| invalid("invalid face"))?
+                    .into_iter()
+                    .map(|(vertex, texture, normal)| ((vertex, normal), texture))
+                    .unzip();
+                if corners.len() < 3 {
+                    return Err(invalid("face with less than three vertices"));
+                }
+                polygons.push(corners);
+                texture_polygons.push(texture_corners);
+            }
+            _ => {}
+        }
+    }
+
+    if polygons.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "no faces"));
+    }
+    Ok(Obj {
+        vertices,
+        normals,
+        texture_coordinates,
+        polygons,
+        texture_polygons,
+    })
+}
+
+/// Parse the three coordinates of a `v` or `vn` line.
+fn parse_vector<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<Vector3<Float>> {
+    let mut coordinate = || tokens.next()?.parse::<Float>().ok();
+    Some(vector![coordinate()?, coordinate()?, coordinate()?])
+}
+
+/// Parse a corner `v`, `v/vt`, `v//vn`, or `v/vt/vn` of a face into zero-based indices of the vertex, the texture coordinates, and the normal.
+///
+/// Negative indices count backwards from the last vertex, texture coordinates, or normal read so far.
+fn parse_corner(
+    token: &str,
+    vertices: usize,
+    texture_coordinates: usize,
+    normals: usize,
+) -> Option<(usize, Option<usize>, Option<usize>)> {
+    let index = |part: &str, count: usize| -> Option<usize> {
+        let index = part.parse::<isize>().ok()?;
+        let index = match index {
+            1.. => index as usize - 1,
+            ..=-1 => count.checked_sub(index.unsigned_abs())?,
+            0 => return None,
+        };
+        (index < count).then_some(index)
+    };
+
+    let mut parts = token.split('/');
+    let vertex = index(parts.next()?, vertices)?;
+    let texture = match parts.next() {
+        Some(part) if !part.is_empty() => Some(index(part, texture_coordinates)?),
+        _ => None,
+    };
+    let normal = match parts.next() {
+        Some(part) if !part.is_empty() => Some(index(part, normals)?),
+        _ => None,
+    };
+    Some((vertex, texture, normal))
+}
+
+impl Hittable for Mesh {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        self.triangles.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.triangles.bounding_box(time0, time1)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        self.triangles.collect_stats(stats);
+    }
+}
+
+impl Movable for Mesh {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
@@ -0,0 +1,196 @@
+//! Metadata of a render that is embedded into the saved images, so finished images document how they were made.
+//!
+//! [`RenderMetadata`] is attached to an image with [`RaytracedImage::with_metadata`] (or to a [`RenderOutput`](crate::output::RenderOutput) with [`with_metadata`](crate::output::RenderOutput::with_metadata)).
+//! PNGs store it as `tEXt` chunks and OpenEXR files as string attributes of the header. Other formats are saved without it.
+
+use std::fmt::{self, Debug, Write as _};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::Duration;
+
+use image::error::EncodingError;
+use image::{ImageError, ImageFormat};
+
+use crate::error::Error;
+use crate::raytracer::RaytracedImage;
+
+/// Information about a render to embed into the saved images.
+///
+/// # Fields
+/// - `samples_per_pixel`: Samples per pixel of the render.
+/// - `seed`: Seed the scene was generated with (see [`SceneSettings::seed`](crate::scenes::SceneSettings)), if it is random.
+/// - `scene_hash`: [`Raytracer::scene_hash`](crate::Raytracer::scene_hash) of the rendered scene.
+/// - `render_time`: Total time of the render.
+/// - `version`: Name and version of the crate that rendered the image.
+///
+/// # Example
+/// ```no_run
+/// # use ray_tracing_in_one_weekend::{*, metadata::RenderMetadata, scenes::{self, SceneSettings}};
+/// let settings = SceneSettings::default().with_seed(7);
+/// let raytracer = scenes::random_spheres(&settings);
+/// let scene_hash = raytracer.scene_hash();
+///
+/// let image = raytracer.render().unwrap();
+/// let metadata = RenderMetadata::new(&image).with_seed(settings.seed).with_scene_hash(scene_hash);
+/// image.with_metadata(metadata).save("random_spheres.png").unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderMetadata {
+    pub samples_per_pixel: u16,
+    pub seed: Option<u64>,
+    pub scene_hash: Option<u64>,
+    pub render_time: Duration,
+    pub version: String,
+}
+
+impl RenderMetadata {
+    /// Metadata with the samples per pixel and the render time of `image`.
+    pub fn new(image: &RaytracedImage) -> Self {
+        Self {
+            samples_per_pixel: image.samples_per_pixel(),
+            seed: None,
+            scene_hash: None,
+            render_time: image.render_stats().total,
+            version: concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")).to_string(),
+        }
+    }
+
+    /// Consume `self` and set the seed of the scene.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Consume `self` and set the hash of the scene.
+    pub fn with_scene_hash(mut self, scene_hash: u64) -> Self {
+        self.scene_hash = Some(scene_hash);
+        self
+    }
+
+    /// Keys and values as they are written into the files. The hash is written in hexadecimal and the render time in seconds.
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![
+            ("Software", self.version.clone()),
+            ("SamplesPerPixel", self.samples_per_pixel.to_string()),
+        ];
+        if let Some(seed) = self.seed {
+            entries.push(("Seed", seed.to_string()));
+        }
+        if let Some(scene_hash) = self.scene_hash {
+            entries.push(("SceneHash", format!("{scene_hash:016x}")));
+        }
+        entries.push((
+            "RenderTime",
+            format!("{:.3}", self.render_time.as_secs_f64()),
+        ));
+        entries
+    }
+}
+
+/// 64 bit FNV-1a hash of the [`Debug`] representation of `values`.
+///
+/// Unlike [`DefaultHasher`](std::collections::hash_map::DefaultHasher), it is the same for every build, so hashes stay comparable.
+pub(crate) fn debug_hash(values: &[&dyn Debug]) -> u64 {
+    struct Fnv(u64);
+
+    impl fmt::Write for Fnv {
+        fn write_str(&mut self, text: &str) -> fmt::Result {
+            for byte in text.bytes() {
+                self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+            }
+            Ok(())
+        }
+    }
+
+    let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+    for value in values {
+        write!(hasher, "{value:?}").expect("hashing does not fail");
+    }
+    hasher.0
+}
+
+/// Save 8 or 16 bit `samples` (big endian for 16 bits) with `channels` channels as a PNG with `metadata` as `tEXt` chunks.
+pub(crate) fn save_png<P: AsRef<Path>>(
+    path: P,
+    (width, height): (u32, u32),
+    channels: usize,
+    sixteen_bits: bool,
+    samples: &[u8],
+    metadata: &RenderMetadata,
+) -> Result<(), Error> {
+    let encoding_error = |error| {
+        Error::Image(ImageError::Encoding(EncodingError::new(
+            ImageFormat::Png.into(),
+            error,
+        )))
+    };
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(match channels {
+        4 => png::ColorType::Rgba,
+        _ => png::ColorType::Rgb,
+    });
+    encoder.set_depth(match sixteen_bits {
+        true => png::BitDepth::Sixteen,
+        false => png::BitDepth::Eight,
+    });
+    for (key, value) in metadata.entries() {
+        encoder
+            .add_text_chunk(key.to_string(), value)
+            .map_err(encoding_error)?;
+    }
+    let mut writer = encoder.write_header().map_err(encoding_error)?;
+    writer.write_image_data(samples).map_err(encoding_error)?;
+    writer.finish().map_err(encoding_error)
+}
+
+/// Whether `path` has the extension of a PNG.
+pub(crate) fn is_png(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn png_text_chunks() {
+        let image = RaytracedImage::new(vec![color![0.25, 0.5, 1.]; 6], 3, 2);
+        let metadata = RenderMetadata::new(&image)
+            .with_seed(42)
+            .with_scene_hash(debug_hash(&[&"scene"]));
+        assert_eq!(
+            metadata.entries()[0].1,
+            format!("ray-tracing-in-one-weekend {}", env!("CARGO_PKG_VERSION"))
+        );
+
+        let path = std::env::temp_dir().join("metadata_png_text_chunks.png");
+        image.with_metadata(metadata.clone()).save(&path).unwrap();
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let reader = decoder.read_info().unwrap();
+        let text: Vec<_> = reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.as_str(), chunk.text.clone()))
+            .collect();
+        assert_eq!(text, metadata.entries());
+        assert_eq!(reader.info().width, 3);
+        std::fs::remove_file(path).unwrap();
+
+        let path = std::env::temp_dir().join("metadata_exr_attributes.exr");
+        let image = RaytracedImage::new(vec![color![0.25, 0.5, 1.]; 6], 3, 2);
+        image.with_metadata(metadata).save_exr(&path).unwrap();
+        let meta = exr::meta::MetaData::read_from_file(&path, false).unwrap();
+        let attributes = &meta.headers[0].own_attributes.other;
+        assert_eq!(
+            attributes.get(&exr::prelude::Text::from("Seed")),
+            Some(&exr::prelude::AttributeValue::Text("42".into()))
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+//! Error metrics between two [`RaytracedImage`]s, e.g. to compare samplers or integrator settings or a render against a reference.
+//!
+//! The [mean squared error](mse) and its [root](rmse) are computed on the linear radiance, while the [structural similarity](ssim) and the [difference image](difference_image) use the gamma-corrected colors to resemble what is seen.
+//!
+//! # Example
+//! ```
+//! # use ray_tracing_in_one_weekend::{*, metrics, scenes::{self, SceneSettings}};
+//! let settings = SceneSettings::new(32, 18).with_samples(4, 4);
+//! let a = scenes::cornell_box(&settings).render().unwrap();
+//! let b = scenes::cornell_box(&settings.with_samples(16, 4)).render().unwrap();
+//!
+//! assert!(metrics::rmse(&a, &b).unwrap().into_iter().all(|error| error >= 0.));
+//! assert_eq!(metrics::ssim(&a, &a).unwrap(), 1.);
+//! let difference = metrics::difference_image(&a, &b, 0.1).unwrap();
+//! assert_eq!(difference.dimensions(), (32, 18));
+//! ```
+
+use image::RgbImage;
+
+use crate::color::BLACK;
+use crate::error::Error;
+use crate::raytracer::RaytracedImage;
+use crate::*;
+
+/// Side length of the square windows the [structural similarity](ssim) is computed in.
+const SSIM_WINDOW: u32 = 8;
+
+/// Stabilizing constants of the [structural similarity](ssim) for values in \[0,1\].
+const SSIM_C1: Float = 0.01 * 0.01;
+const SSIM_C2: Float = 0.03 * 0.03;
+
+/// Ensure that `a` and `b` have the same size and return it.
+fn check_dimensions(a: &RaytracedImage, b: &RaytracedImage) -> Result<(u32, u32), Error> {
+    if a.dimensions() != b.dimensions() {
+        return Err(Error::Config(format!(
+            "cannot compare images of sizes {:?} and {:?}",
+            a.dimensions(),
+            b.dimensions()
+        )));
+    }
+    Ok(a.dimensions())
+}
+
+/// Gamma-corrected (with gamma 2) and clamped luminance of `color`.
+fn display_luminance(color: Color) -> Float {
+    let [r, g, b] = [color.r(), color.g(), color.b()].map(|c| c.clamp(0., 1.).sqrt());
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// Mean squared error of each channel of the linear radiance.
+pub fn mse(a: &RaytracedImage, b: &RaytracedImage) -> Result<Color, Error> {
+    let (width, height) = check_dimensions(a, b)?;
+    let mut sum = BLACK;
+    for (&a, &b) in a.colors().iter().zip(b.colors()) {
+        let difference = a - b;
+        sum += difference * difference;
+    }
+    Ok(sum / (width as Float * height as Float))
+}
+
+/// Root mean squared error of each channel of the linear radiance.
+pub fn rmse(a: &RaytracedImage, b: &RaytracedImage) -> Result<Color, Error> {
+    Ok(mse(a, b)?.into_iter().map(Float::sqrt).collect())
+}
+
+/// Mean structural similarity of the gamma-corrected luminance.
+///
+/// This compares the mean, variance and covariance in overlapping windows of 8 × 8 pixels (or the whole image if it is smaller).
+/// It is 1 for identical images and decreases towards 0 (or even below) the less similar their structure is, which matches the perceived difference better than the [`mse`].
+pub fn ssim(a: &RaytracedImage, b: &RaytracedImage) -> Result<Float, Error> {
+    let (width, height) = check_dimensions(a, b)?;
+    let a: Vec<Float> = a.colors().iter().map(|&c| display_luminance(c)).collect();
+    let b: Vec<Float> = b.colors().iter().map(|&c| display_luminance(c)).collect();
+
+    let window_width = SSIM_WINDOW.min(width);
+    let window_height = SSIM_WINDOW.min(height);
+    let stride = (SSIM_WINDOW / 2) as usize;
+    let mut sum = 0.;
+    let mut windows = 0;
+    for y0 in (0..=height - window_height).step_by(stride) {
+        for x0 in (0..=width - window_width).step_by(stride) {
+            let indices = (y0..y0 + window_height)
+                .flat_map(|y| (x0..x0 + window_width).map(move |x| (y * width + x) as usize));
+            let n = (window_width * window_height) as Float;
+
+            let (mut mean_a, mut mean_b) = (0., 0.);
+            for index in indices.clone() {
+                mean_a += a[index];
+                mean_b += b[index];
+            }
+            mean_a /= n;
+            mean_b /= n;
+
+            let (mut variance_a, mut variance_b, mut covariance) = (0., 0., 0.);
+            for index in indices {
+                let (da, db) = (a[index] - mean_a, b[index] - mean_b);
+                variance_a += da * da;
+                variance_b += db * db;
+                covariance += da * db;
+            }
+            variance_a /= n;
+            variance_b /= n;
+            covariance /= n;
+
+            sum += (2. * mean_a * mean_b + SSIM_C1) * (2. * covariance + SSIM_C2)
+                / ((mean_a * mean_a + mean_b * mean_b + SSIM_C1)
+                    * (variance_a + variance_b + SSIM_C2));
+            windows += 1;
+        }
+    }
+    Ok(sum / windows as Float)
+}
+
+/// False-color image of the absolute difference of the gamma-corrected colors, averaged over the channels.
+///
+/// Identical pixels are blue, and the colors go over green and yellow to red for a difference of at least `max_error`.
+pub fn difference_image(
+    a: &RaytracedImage,
+    b: &RaytracedImage,
+    max_error: Float,
+) -> Result<RgbImage, Error> {
+    let (width, height) = check_dimensions(a, b)?;
+    let gamma = |color: Color| -> Color { color.into_iter().map(|c| c.max(0.).sqrt()).collect() };
+
+    let pixels = a
+        .colors()
+        .iter()
+        .zip(b.colors())
+        .flat_map(|(&a, &b)| {
+            let error = (gamma(a) - gamma(b))
+                .into_iter()
+                .map(Float::abs)
+                .sum::<Float>()
+                / 3.;
+            Into::<[u8; 3]>::into(Color::heatmap(error / max_error))
+        })
+        .collect();
+    Ok(RgbImage::from_vec(width, height, pixels).expect("the size was checked"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::WHITE;
+
+    fn image(width: u32, height: u32, color: impl Fn(u32, u32) -> Color) -> RaytracedImage {
+        let colors = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| color(x, y))
+            .collect();
+        RaytracedImage::new(colors, width, height)
+    }
+
+    #[test]
+    fn constant_offset() {
+        let a = image(16, 16, |_, _| color![0.2, 0.4, 0.6]);
+        let b = image(16, 16, |_, _| color![0.3, 0.4, 0.2]);
+        let mse = mse(&a, &b).unwrap();
+        assert!((mse.r() - 0.01).abs() < 1e-6);
+        assert_eq!(mse.g(), 0.);
+        assert!((rmse(&a, &b).unwrap().b() - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ssim_prefers_structure() {
+        let checker = |x: u32, y: u32| {
+            if (x + y).is_multiple_of(2) {
+                WHITE
+            } else {
+                BLACK
+            }
+        };
+        let a = image(16, 16, checker);
+        let brighter = image(16, 16, |x, y| checker(x, y) * 0.8 + color![0.1, 0.1, 0.1]);
+        let flat = image(16, 16, |_, _| color![0.25, 0.25, 0.25]);
+
+        assert!((ssim(&a, &a).unwrap() - 1.).abs() < 1e-6);
+        assert!(ssim(&a, &brighter).unwrap() > ssim(&a, &flat).unwrap());
+    }
+
+    #[test]
+    fn different_sizes() {
+        let a = image(4, 4, |_, _| BLACK);
+        let b = image(4, 5, |_, _| BLACK);
+        assert!(matches!(mse(&a, &b), Err(Error::Config(_))));
+        assert!(difference_image(&a, &b, 1.).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+//! Bundles of several passes of the same render that are saved together, e.g. for compositing.
+//!
+//! A [`RenderOutput`] collects the beauty image and any auxiliary passes (see [`passes`](crate::passes)) as named layers and writes them into a single multi-layer OpenEXR file.
+//! The channels of a layer are prefixed with its name (e.g. `depth.Z`), except for the beauty image, whose channels are the unprefixed `R`, `G`, `B` (and `A`) that every viewer shows by default.
+
+use std::path::Path;
+
+use exr::prelude::{
+    AnyChannel, AnyChannels, AttributeValue, Encoding, FlatSamples, Image, Layer, LayerAttributes,
+    SmallVec, Text, WritableImage,
+};
+use image::error::EncodingError;
+use image::{ImageError, ImageFormat};
+
+use crate::error::Error;
+use crate::metadata::RenderMetadata;
+use crate::passes::{DepthImage, IdImage, SampleImage};
+use crate::platform::log_event;
+use crate::raytracer::RaytracedImage;
+use crate::*;
+
+/// Full name and samples of a channel.
+type Channel = (String, Vec<f32>);
+
+/// Named layers of a render that are saved into one multi-layer OpenEXR file.
+///
+/// All layers have the size of the output. The samples are stored as 32 bit floats, row by row from the top.
+///
+/// # Fields
+/// - `layers`: Names of the layers and their channels, in the order they were added.
+/// - `image_width`: Width of all layers.
+/// - `image_height`: Height of all layers.
+/// - `metadata`: [`RenderMetadata`] written into the header.
+///
+/// # Example
+/// ```no_run
+/// # use ray_tracing_in_one_weekend::{*, output::RenderOutput, passes::IdKind, scenes::{self, SceneSettings}};
+/// let settings = SceneSettings::default();
+/// let mut output = RenderOutput::new(settings.image_width, settings.image_height);
+/// output.add_beauty(&scenes::cornell_box(&settings).render().unwrap()).unwrap();
+/// output.add_color("normal", &scenes::cornell_box(&settings).render_normals().unwrap()).unwrap();
+/// output.add_depth("depth", &scenes::cornell_box(&settings).render_depth().unwrap()).unwrap();
+/// output.add_ids("CryptoMaterial", &scenes::cornell_box(&settings).render_ids(IdKind::Material).unwrap()).unwrap();
+/// output.save_exr("cornell_box.exr").unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderOutput {
+    layers: Vec<(String, Vec<Channel>)>,
+    image_width: u32,
+    image_height: u32,
+    metadata: Option<RenderMetadata>,
+}
+
+impl RenderOutput {
+    /// Create an empty output of the given size.
+    pub fn new(image_width: u32, image_height: u32) -> Self {
+        Self {
+            layers: Vec::new(),
+            image_width,
+            image_height,
+            metadata: None,
+        }
+    }
+
+    /// Consume `self` and write `metadata` into the header as string attributes.
+    pub fn with_metadata(mut self, metadata: RenderMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Width and height of all layers.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.image_width, self.image_height)
+    }
+
+    /// Names of the layers in the order they were added. The beauty image is called `""`.
+    pub fn layer_names(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Full names of all channels, e.g. `depth.Z`.
+    pub fn channel_names(&self) -> impl Iterator<Item = &str> {
+        self.layers
+            .iter()
+            .flat_map(|(_, channels)| channels.iter().map(|(name, _)| name.as_str()))
+    }
+
+    /// Add the layer `name` with the channels `channels`, each consisting of a name and one sample per pixel.
+    ///
+    /// This fails if `name` is already taken, a channel does not have one sample per pixel, or a name is not representable in OpenEXR (which only supports Latin-1 characters).
+    pub fn add_layer(
+        &mut self,
+        name: impl Into<String>,
+        channels: Vec<(String, Vec<Float>)>,
+    ) -> Result<(), Error> {
+        let name = name.into();
+        if self.layers.iter().any(|(layer, _)| *layer == name) {
+            return Err(Error::Config(format!("layer {name:?} already exists")));
+        }
+
+        let pixel_count = self.image_width as usize * self.image_height as usize;
+        let mut layer = Vec::with_capacity(channels.len());
+        for (channel, samples) in channels {
+            let channel = if name.is_empty() {
+                channel
+            } else {
+                format!("{name}.{channel}")
+            };
+            if Text::new_or_none(&channel).is_none() {
+                return Err(Error::Config(format!(
+                    "channel name {channel:?} contains characters that OpenEXR does not support"
+                )));
+            }
+            if samples.len() != pixel_count {
+                return Err(Error::Config(format!(
+                    "channel {channel:?} has {} samples instead of one for each of the {pixel_count} pixels",
+                    samples.len()
+                )));
+            }
+            // The cast is only necessary if `Float` is `f64`.
+            #[allow(clippy::unnecessary_cast)]
+            let samples = samples.into_iter().map(|sample| sample as f32).collect();
+            layer.push((channel, samples));
+        }
+        self.layers.push((name, layer));
+        Ok(())
+    }
+
+    /// Add the linear radiance of `image` as the channels `name.R`, `name.G` and `name.B`, e.g. for a normal or albedo pass.
+    pub fn add_color(
+        &mut self,
+        name: impl Into<String>,
+        image: &RaytracedImage,
+    ) -> Result<(), Error> {
+        self.add_layer(name, color_channels(image))
+    }
+
+    /// Add the linear radiance of the beauty render `image` as the unprefixed channels `R`, `G` and `B`, and its alpha as `A` if it has one.
+    pub fn add_beauty(&mut self, image: &RaytracedImage) -> Result<(), Error> {
+        let mut channels = color_channels(image);
+        if let Some(alpha) = image.alpha() {
+            channels.push(("A".to_string(), alpha.to_vec()));
+        }
+        self.add_layer("", channels)
+    }
+
+    /// Add the unnormalized distances of `depth` as the channel `name.Z`. Missed pixels are infinite.
+    pub fn add_depth(&mut self, name: impl Into<String>, depth: &DepthImage) -> Result<(), Error> {
+        self.add_layer(name, vec![("Z".to_string(), depth.depths().to_vec())])
+    }
+
+    /// Add the number of samples and the variances of `samples` as the channels `name.N` and `name.V`.
+    pub fn add_samples(
+        &mut self,
+        name: impl Into<String>,
+        samples: &SampleImage,
+    ) -> Result<(), Error> {
+        let counts = samples
+            .sample_counts()
+            .iter()
+            .map(|&count| count as Float)
+            .collect();
+        self.add_layer(
+            name,
+            vec![
+                ("N".to_string(), counts),
+                ("V".to_string(), samples.variances().to_vec()),
+            ],
+        )
+    }
+
+    /// Add the coverages of `ids` as Cryptomatte-like ranks of (ID, coverage, ID, coverage) in the channels `name00.R` to `name00.A`, `name01.R` and so on.
+    ///
+    /// There are as many ranks as needed for the pixel with the most IDs. The IDs are stored as their value, not hashed.
+    pub fn add_ids(&mut self, name: impl Into<String>, ids: &IdImage) -> Result<(), Error> {
+        let name = name.into();
+        let (width, height) = ids.dimensions();
+        // Check the size in advance, so that no ranks are added if it does not match.
+        if ids.dimensions() != self.dimensions() {
+            return Err(Error::Config(format!(
+                "cannot add an ID image of size {:?} to an output of size {:?}",
+                ids.dimensions(),
+                self.dimensions()
+            )));
+        }
+        let pixels: Vec<_> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| ids.pixel(x, y))
+            .collect();
+        let ranks = pixels.iter().map(|pixel| pixel.len()).max().unwrap_or(0);
+
+        for group in 0..ranks.div_ceil(2).max(1) {
+            let channels = ["R", "G", "B", "A"]
+                .into_iter()
+                .enumerate()
+                .map(|(channel, channel_name)| {
+                    let rank = 2 * group + channel / 2;
+                    let samples = pixels
+                        .iter()
+                        .map(|pixel| {
+                            pixel.get(rank).map_or(0., |&(id, coverage)| {
+                                if channel.is_multiple_of(2) {
+                                    id as Float
+                                } else {
+                                    coverage
+                                }
+                            })
+                        })
+                        .collect();
+                    (channel_name.to_string(), samples)
+                })
+                .collect();
+            self.add_layer(format!("{name}{group:02}"), channels)?;
+        }
+        Ok(())
+    }
+
+    /// Save all layers into one multi-layer OpenEXR file.
+    pub fn save_exr<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        log_event!(debug, "saving {}", path.as_ref().display());
+        if self.layers.is_empty() {
+            return Err(Error::Config("render output has no layers".to_string()));
+        }
+
+        let channels: SmallVec<_> = self
+            .layers
+            .iter()
+            .flat_map(|(_, channels)| channels)
+            .map(|(name, samples)| {
+                AnyChannel::new(name.as_str(), FlatSamples::F32(samples.clone()))
+            })
+            .collect();
+        let mut attributes = LayerAttributes::default();
+        for (key, value) in self.metadata.iter().flat_map(RenderMetadata::entries) {
+            attributes.other.insert(
+                Text::from(key),
+                AttributeValue::Text(Text::from(value.as_str())),
+            );
+        }
+        let layer = Layer::new(
+            (self.image_width as usize, self.image_height as usize),
+            attributes,
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(channels),
+        );
+        Image::from_layer(layer)
+            .write()
+            .to_file(path)
+            .map_err(|error| match error {
+                exr::error::Error::Io(error) => Error::Io(error),
+                error => Error::Image(ImageError::Encoding(EncodingError::new(
+                    ImageFormat::OpenExr.into(),
+                    error,
+                ))),
+            })
+    }
+}
+
+/// The red, green and blue channels of `image`.
+fn color_channels(image: &RaytracedImage) -> Vec<(String, Vec<Float>)> {
+    ["R", "G", "B"]
+        .into_iter()
+        .enumerate()
+        .map(|(channel, name)| {
+            let samples = image
+                .colors()
+                .iter()
+                .map(|color| color[channel as u8])
+                .collect();
+            (name.to_string(), samples)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn layers() {
+        let mut output = RenderOutput::new(2, 1);
+        output
+            .add_beauty(&RaytracedImage::new(vec![color![1., 2., 3.]; 2], 2, 1))
+            .unwrap();
+        output
+            .add_depth("depth", &DepthImage::new(vec![1., Float::INFINITY], 2, 1))
+            .unwrap();
+        output
+            .add_ids(
+                "CryptoObject",
+                &IdImage::new(vec![vec![(4, 0.5), (7, 0.25), (9, 0.25)], vec![]], 2, 1),
+            )
+            .unwrap();
+
+        assert_eq!(
+            output.layer_names().collect::<Vec<_>>(),
+            ["", "depth", "CryptoObject00", "CryptoObject01"]
+        );
+        assert_eq!(
+            output.channel_names().take(5).collect::<Vec<_>>(),
+            ["R", "G", "B", "depth.Z", "CryptoObject00.R"]
+        );
+        assert!(matches!(
+            output.add_depth("depth", &DepthImage::new(vec![1., 1.], 2, 1)),
+            Err(Error::Config(_))
+        ));
+        assert!(output
+            .add_layer("short", vec![("Y".to_string(), vec![0.])])
+            .is_err());
+    }
+}
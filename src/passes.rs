@@ -0,0 +1,415 @@
+//! Auxiliary render passes, e.g. for compositing or datasets.
+//!
+//! Unlike the beauty image of [`render`](crate::Raytracer::render), they record what the camera [`Ray`](crate::ray::Ray)s hit first instead of the light arriving along them.
+//! The [`SampleImage`] of a beauty render instead shows where its samples went and how noisy they are.
+
+use std::path::Path;
+
+use image::{ImageBuffer, ImageFormat, Luma, Rgb32FImage, RgbImage, Rgba32FImage};
+
+use crate::color::BLACK;
+use crate::error::Error;
+use crate::*;
+
+/// How distances are mapped to \[0,1\] in a [`DepthImage`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthScale {
+    /// Proportional to the distance.
+    #[default]
+    Linear,
+    /// Proportional to the logarithm of the distance, which keeps more detail close to the camera.
+    Logarithmic,
+}
+
+/// Distance from the camera to the first hit of each pixel, see [`Raytracer::render_depth`].
+///
+/// # Fields
+/// - `depths`: Distance of each pixel, row by row from the top. Pixels without a hit or outside of the [`RenderRegion`](crate::raytracer::RenderRegion) are infinitely far away.
+/// - `image_width`: Width of the image.
+/// - `image_height`: Height of the image.
+///
+/// # Example
+/// ```no_run
+/// # use ray_tracing_in_one_weekend::{*, passes::DepthScale, scenes::{self, SceneSettings}};
+/// let depth = scenes::random_spheres(&SceneSettings::default()).render_depth().unwrap();
+/// depth.save_png16("depth.png", 1., 100., DepthScale::Logarithmic).unwrap();
+/// depth.save_exr("depth.exr").unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepthImage {
+    depths: Vec<Float>,
+    image_width: u32,
+    image_height: u32,
+}
+
+impl DepthImage {
+    pub(crate) fn new(depths: Vec<Float>, image_width: u32, image_height: u32) -> Self {
+        Self {
+            depths,
+            image_width,
+            image_height,
+        }
+    }
+
+    /// Width and height of the image.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.image_width, self.image_height)
+    }
+
+    /// Distances of the pixels, row by row from the top.
+    pub fn depths(&self) -> &[Float] {
+        &self.depths
+    }
+
+    /// Distances mapped to \[0,1\], where `near` (and closer) is 0 and `far` (and farther, including missed pixels) is 1.
+    pub fn normalized(&self, near: Float, far: Float, scale: DepthScale) -> Vec<Float> {
+        let map = |depth: Float| match scale {
+            DepthScale::Linear => (depth - near) / (far - near),
+            DepthScale::Logarithmic => {
+                let near = near.max(Float::EPSILON);
+                (depth.max(near) / near).ln() / (far / near).ln()
+            }
+        };
+        self.depths
+            .iter()
+            .map(|&depth| {
+                if depth.is_finite() {
+                    map(depth).clamp(0., 1.)
+                } else {
+                    1.
+                }
+            })
+            .collect()
+    }
+
+    /// Convert the [normalized](DepthImage::normalized) distances to a grayscale image with 16 bits per channel.
+    pub fn to_luma16_image(
+        &self,
+        near: Float,
+        far: Float,
+        scale: DepthScale,
+    ) -> ImageBuffer<Luma<u16>, Vec<u16>> {
+        let pixels = self
+            .normalized(near, far, scale)
+            .into_iter()
+            .map(|depth| (depth * u16::MAX as Float).round() as u16)
+            .collect();
+        ImageBuffer::from_vec(self.image_width, self.image_height, pixels)
+            .expect("there is one depth per pixel")
+    }
+
+    /// Save the [normalized](DepthImage::normalized) distances as a grayscale PNG with 16 bits per channel.
+    pub fn save_png16<P: AsRef<Path>>(
+        &self,
+        path: P,
+        near: Float,
+        far: Float,
+        scale: DepthScale,
+    ) -> Result<(), Error> {
+        let image = self.to_luma16_image(near, far, scale);
+        Ok(image.save_with_format(path, ImageFormat::Png)?)
+    }
+
+    /// Save the unnormalized distances in all three channels of an OpenEXR image with 32 bit floats. Missed pixels are infinite.
+    // The cast is only necessary if `Float` is `f64`.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn save_exr<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let pixels = self
+            .depths
+            .iter()
+            .flat_map(|&depth| [depth as f32; 3])
+            .collect();
+        let image = Rgb32FImage::from_vec(self.image_width, self.image_height, pixels)
+            .expect("there is one depth per pixel");
+        Ok(image.save_with_format(path, ImageFormat::OpenExr)?)
+    }
+}
+
+/// Which ID an [`IdImage`] records.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdKind {
+    /// The [`object_id`](crate::hittable::Hittable::object_id) of objects pushed via [`push_named`](crate::Raytracer::push_named).
+    #[default]
+    Object,
+    /// The [`id`](crate::materials::Material::id) of materials wrapped in a [`NamedMaterial`](crate::materials::NamedMaterial).
+    Material,
+}
+
+/// Stable pseudo-random color of the ID `id`, so that the same object or material has the same color in every render.
+pub fn id_color(id: u32) -> Color {
+    // Finalizer of MurmurHash3, which spreads neighboring IDs over the whole range.
+    let mut hash = id;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^= hash >> 16;
+
+    let [r, g, b, _] = hash.to_le_bytes();
+    color![r as Float, g as Float, b as Float] / 255.
+}
+
+/// Anti-aliased coverage of the objects or materials in each pixel, see [`Raytracer::render_ids`].
+///
+/// Like a Cryptomatte, it stores for each pixel every ID that was hit together with the fraction of the samples that hit it, so that a matte of any object or material with soft edges can be extracted in compositing.
+/// Samples that hit the background or something without an ID are not recorded, therefore the coverages of a pixel can sum to less than 1.
+///
+/// # Fields
+/// - `coverages`: IDs and their coverage in each pixel, sorted by descending coverage and row by row from the top.
+/// - `image_width`: Width of the image.
+/// - `image_height`: Height of the image.
+///
+/// # Example
+/// ```no_run
+/// # use ray_tracing_in_one_weekend::{*, passes::IdKind, scenes::{self, SceneSettings}};
+/// let ids = scenes::cornell_box(&SceneSettings::default()).render_ids(IdKind::Material).unwrap();
+/// ids.to_rgb_image().save("ids.png").unwrap();
+/// ids.save_exr("ids.exr").unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdImage {
+    coverages: Vec<Vec<(u32, Float)>>,
+    image_width: u32,
+    image_height: u32,
+}
+
+impl IdImage {
+    pub(crate) fn new(
+        mut coverages: Vec<Vec<(u32, Float)>>,
+        image_width: u32,
+        image_height: u32,
+    ) -> Self {
+        for pixel in &mut coverages {
+            pixel.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        }
+        Self {
+            coverages,
+            image_width,
+            image_height,
+        }
+    }
+
+    /// Width and height of the image.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.image_width, self.image_height)
+    }
+
+    /// IDs and their coverage in the pixel (`x`, `y`) with `y` counted from the top, sorted by descending coverage.
+    ///
+    /// # Panics
+    /// If the pixel is outside of the image.
+    pub fn pixel(&self, x: u32, y: u32) -> &[(u32, Float)] {
+        assert!(
+            x < self.image_width && y < self.image_height,
+            "pixel ({x}, {y}) is outside of the image"
+        );
+        &self.coverages[y as usize * self.image_width as usize + x as usize]
+    }
+
+    /// Coverage of `id` in each pixel, row by row from the top.
+    pub fn matte(&self, id: u32) -> Vec<Float> {
+        self.coverages
+            .iter()
+            .map(|pixel| {
+                pixel
+                    .iter()
+                    .find(|&&(pixel_id, _)| pixel_id == id)
+                    .map_or(0., |&(_, coverage)| coverage)
+            })
+            .collect()
+    }
+
+    /// Preview with the [color](id_color) of each ID, mixed by coverage on a black background.
+    pub fn to_rgb_image(&self) -> RgbImage {
+        let pixels = self
+            .coverages
+            .iter()
+            .flat_map(|pixel| {
+                let color = pixel.iter().fold(BLACK, |color, &(id, coverage)| {
+                    color + coverage * id_color(id)
+                });
+                Into::<[u8; 3]>::into(color)
+            })
+            .collect();
+        RgbImage::from_vec(self.image_width, self.image_height, pixels)
+            .expect("there is one list of coverages per pixel")
+    }
+
+    /// Save the two IDs with the highest coverage of each pixel as an OpenEXR image with 32 bit floats.
+    ///
+    /// The channels are (ID, coverage, ID, coverage). Pixels with fewer IDs have a coverage of 0 in the remaining channels.
+    // The cast is only necessary if `Float` is `f64`.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn save_exr<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let pixels = self
+            .coverages
+            .iter()
+            .flat_map(|pixel| {
+                let rank = |rank: usize| {
+                    pixel
+                        .get(rank)
+                        .map_or([0., 0.], |&(id, coverage)| [id as f32, coverage as f32])
+                };
+                let ([id0, coverage0], [id1, coverage1]) = (rank(0), rank(1));
+                [id0, coverage0, id1, coverage1]
+            })
+            .collect();
+        let image = Rgba32FImage::from_vec(self.image_width, self.image_height, pixels)
+            .expect("there is one list of coverages per pixel");
+        Ok(image.save_with_format(path, ImageFormat::OpenExr)?)
+    }
+}
+
+/// Number of samples and variance of each pixel of a beauty render, see [`Raytracer::with_sample_statistics`].
+///
+/// The variance is the estimated variance of the mean luminance of the samples taken inside each pixel, before they are splatted with the [`Filter`](crate::filter::Filter).
+/// It shrinks with the number of samples, so it shows where more samples would pay off, e.g. to pick the tolerance of adaptive sampling.
+/// Until the renderer samples adaptively, every traced pixel has `samples_per_pixel` samples.
+///
+/// # Fields
+/// - `counts`: Number of samples of each pixel, row by row from the top.
+/// - `variances`: Variance of the mean of each pixel. Pixels with fewer than two samples have a variance of 0.
+/// - `image_width`: Width of the image.
+/// - `image_height`: Height of the image.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, shapes::Sphere};
+/// let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+/// let mut raytracer = Raytracer::new(camera, color![0.5, 0.7, 1.], 9, 9, 16, 8)
+///     .with_sample_statistics()
+///     .with_seed(1);
+/// raytracer.world.push(Sphere::new(vector![0., 0., -1.], 0.5, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+///
+/// let image = raytracer.render().unwrap();
+/// let samples = image.sample_image().unwrap();
+/// assert_eq!(samples.sample_counts()[0], 16);
+/// // The diffuse sphere in the center is noisier than the sky in the corner.
+/// assert!(samples.variances()[4 * 9 + 4] > samples.variances()[0]);
+/// let heatmap = samples.variance_heatmap(samples.max_variance());
+/// assert_eq!(heatmap.dimensions(), (9, 9));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleImage {
+    counts: Vec<u32>,
+    variances: Vec<Float>,
+    image_width: u32,
+    image_height: u32,
+}
+
+impl SampleImage {
+    /// Image with the number of samples, the sum, and the sum of squares of the luminance of each pixel.
+    pub(crate) fn from_moments(
+        moments: &[(u32, Float, Float)],
+        image_width: u32,
+        image_height: u32,
+    ) -> Self {
+        let variances = moments
+            .iter()
+            .map(|&(count, sum, squares)| {
+                if count < 2 {
+                    return 0.;
+                }
+                let n = count as Float;
+                ((squares - sum * sum / n) / (n - 1.)).max(0.) / n
+            })
+            .collect();
+        Self {
+            counts: moments.iter().map(|&(count, _, _)| count).collect(),
+            variances,
+            image_width,
+            image_height,
+        }
+    }
+
+    /// The rows `y0..y1` (counted from the top) of the image.
+    pub(crate) fn rows(&self, y0: u32, y1: u32) -> Self {
+        let rows = y0 as usize * self.image_width as usize..y1 as usize * self.image_width as usize;
+        Self {
+            counts: self.counts[rows.clone()].to_vec(),
+            variances: self.variances[rows].to_vec(),
+            image_width: self.image_width,
+            image_height: y1 - y0,
+        }
+    }
+
+    /// Width and height of the image.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.image_width, self.image_height)
+    }
+
+    /// Number of samples of the pixels, row by row from the top.
+    pub fn sample_counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// Variances of the means of the pixels, row by row from the top.
+    pub fn variances(&self) -> &[Float] {
+        &self.variances
+    }
+
+    /// Highest variance of all pixels.
+    pub fn max_variance(&self) -> Float {
+        self.variances.iter().copied().fold(0., Float::max)
+    }
+
+    /// False colors of the number of samples, from blue for none to red for the most samples of any pixel.
+    pub fn sample_heatmap(&self) -> RgbImage {
+        let max = self.counts.iter().copied().max().unwrap_or(0).max(1) as Float;
+        self.heatmap(self.counts.iter().map(|&count| count as Float / max))
+    }
+
+    /// False colors of the variances, from blue for 0 to red for `max_variance` and above.
+    pub fn variance_heatmap(&self, max_variance: Float) -> RgbImage {
+        let max = max_variance.max(Float::MIN_POSITIVE);
+        self.heatmap(self.variances.iter().map(|&variance| variance / max))
+    }
+
+    fn heatmap(&self, values: impl Iterator<Item = Float>) -> RgbImage {
+        let pixels = values
+            .flat_map(|value| Into::<[u8; 3]>::into(Color::heatmap(value)))
+            .collect();
+        RgbImage::from_vec(self.image_width, self.image_height, pixels)
+            .expect("there is one value per pixel")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalization() {
+        let depth = DepthImage::new(vec![0.5, 1., 10., 100., Float::INFINITY], 5, 1);
+        assert_eq!(
+            depth.normalized(1., 100., DepthScale::Linear),
+            vec![0., 0., 9. / 99., 1., 1.]
+        );
+        let logarithmic = depth.normalized(1., 100., DepthScale::Logarithmic);
+        assert_eq!(logarithmic[1], 0.);
+        assert!((logarithmic[2] - 0.5).abs() < 1e-6);
+        assert_eq!(logarithmic[4], 1.);
+    }
+
+    #[test]
+    fn id_coverage() {
+        let ids = IdImage::new(vec![vec![(1, 0.25), (2, 0.75)], vec![]], 2, 1);
+        assert_eq!(ids.pixel(0, 0), &[(2, 0.75), (1, 0.25)]);
+        assert_eq!(ids.matte(1), vec![0.25, 0.]);
+        assert_eq!(id_color(7), id_color(7));
+        assert_ne!(id_color(0), id_color(1));
+        assert_eq!(ids.to_rgb_image().get_pixel(1, 0).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn sample_variance() {
+        // Samples 0, 2, 4 have a variance of 4, so their mean has a variance of 4 / 3.
+        let samples = SampleImage::from_moments(&[(3, 6., 20.), (1, 1., 1.), (0, 0., 0.)], 3, 1);
+        assert!((samples.variances()[0] - 4. / 3.).abs() < 1e-6);
+        assert_eq!(&samples.variances()[1..], &[0., 0.]);
+        assert_eq!(samples.sample_counts(), &[3, 1, 0]);
+        let heatmap = samples.sample_heatmap();
+        assert_eq!(heatmap.get_pixel(0, 0).0, [255, 0, 0]);
+        assert_eq!(heatmap.get_pixel(2, 0).0, [0, 0, 255]);
+    }
+}
@@ -1,6 +1,6 @@
-use rand::rngs::ThreadRng;
 use rand::Rng;
 
+use crate::random::RenderRng;
 use crate::vec3::random_vector_in_range;
 use crate::*;
 
@@ -9,7 +9,7 @@ const POINT_COUNT: usize = 256;
 /// Wrapper for Perlin generation.
 #[derive(Clone, Debug)]
 pub struct Perlin {
-    random_points: [Vector3<f32>; POINT_COUNT],
+    random_points: [Vector3<Float>; POINT_COUNT],
     permutation_x: [usize; POINT_COUNT],
     permutation_y: [usize; POINT_COUNT],
     permutation_z: [usize; POINT_COUNT],
@@ -22,7 +22,7 @@ impl Perlin {
 
     /// Generate Perlin noise.
     #[allow(clippy::needless_range_loop)]
-    pub fn noise(&self, point: Vector3<f32>) -> f32 {
+    pub fn noise(&self, point: Vector3<Float>) -> Float {
         let u = point.x - point.x.floor();
         let v = point.y - point.y.floor();
         let w = point.z - point.z.floor();
@@ -50,7 +50,7 @@ impl Perlin {
         Perlin::trilinear_interpolation(&c, u, v, w)
     }
 
-    pub fn turbulance(&self, mut point: Vector3<f32>, depth: u8) -> f32 {
+    pub fn turbulance(&self, mut point: Vector3<Float>, depth: u8) -> Float {
         let mut accum = 0.;
         let mut weight = 1.;
 
@@ -63,7 +63,7 @@ impl Perlin {
         accum.abs()
     }
 
-    fn generate_permutation(rng: &mut ThreadRng) -> [usize; POINT_COUNT] {
+    fn generate_permutation(rng: &mut RenderRng) -> [usize; POINT_COUNT] {
         let mut permutation: [usize; POINT_COUNT] =
             (0..POINT_COUNT).collect::<Vec<_>>().try_into().unwrap();
 
@@ -72,7 +72,7 @@ impl Perlin {
         permutation
     }
 
-    fn permute(permutation: &mut [usize], rng: &mut ThreadRng) {
+    fn permute(permutation: &mut [usize], rng: &mut RenderRng) {
         for i in (1..POINT_COUNT).rev() {
             let rand = rng.gen_range(0..=i);
             permutation.swap(i, rand);
@@ -80,20 +80,25 @@ impl Perlin {
     }
 
     #[allow(clippy::needless_range_loop)]
-    fn trilinear_interpolation(c: &[[[Vector3<f32>; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
+    fn trilinear_interpolation(
+        c: &[[[Vector3<Float>; 2]; 2]; 2],
+        u: Float,
+        v: Float,
+        w: Float,
+    ) -> Float {
         let u = u * u * (3. - 2. * u);
         let v = v * v * (3. - 2. * v);
         let w = w * w * (3. - 2. * w);
 
-        let mut accum: f32 = 0.;
+        let mut accum: Float = 0.;
 
         for i in 0..2 {
             for j in 0..2 {
                 for k in 0..2 {
-                    let weight_vector = vector![u - i as f32, v - j as f32, w - k as f32];
-                    accum += (i as f32 * u + (1 - i) as f32 * (1. - u))
-                        * (j as f32 * v + (1 - j) as f32 * (1. - v))
-                        * (k as f32 * w + (1 - k) as f32 * (1. - w))
+                    let weight_vector = vector![u - i as Float, v - j as Float, w - k as Float];
+                    accum += (i as Float * u + (1 - i) as Float * (1. - u))
+                        * (j as Float * v + (1 - j) as Float * (1. - v))
+                        * (k as Float * w + (1 - k) as Float * (1. - w))
                         * c[i][j][k].dot(&weight_vector);
                 }
             }
@@ -105,7 +110,7 @@ impl Perlin {
 
 impl Default for Perlin {
     fn default() -> Self {
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::random::rng();
 
         let mut random_points = [vector![0., 0., 0.]; POINT_COUNT];
         for i in &mut random_points {
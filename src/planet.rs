@@ -0,0 +1,453 @@
+//! Planets seen from space: a textured sphere inside an atmosphere that is lit by a sun.
+//!
+//! The [`Atmosphere`] scatters sunlight like the one of the Earth: by air molecules (Rayleigh scattering, mostly blue light) and by aerosols (Mie scattering, gray and strongly forward).
+//! Their densities fall off exponentially with the altitude. Only single scattering is computed, by marching along the [`Ray`]s (Nishita et al. 1993):
+//! - A [`Ray`] entering the atmosphere picks up the sunlight scattered towards it on its way through, and continues with what the atmosphere transmits of the light behind it.
+//! - The sun dims and reddens the light it sends through the atmosphere, e.g. to the surface of the planet near the terminator.
+//!
+//! Light reflected by the surface of the planet leaves through the atmosphere unaffected, so the camera should be outside of it.
+
+use crate::float::consts::PI;
+
+use crate::color::{BLACK, WHITE};
+use crate::hitrecord::HitRecord;
+use crate::hittable::{Aabb, HittableList};
+use crate::lights::{Light, LightSample, Lights};
+use crate::materials::{Lambertian, Material};
+use crate::ray::{Ray, RayKind};
+use crate::shapes::{Offset, Sphere};
+use crate::textures::Texture;
+use crate::*;
+
+/// Steps of the ray marching towards the camera.
+const VIEW_STEPS: usize = 16;
+/// Steps of the ray marching towards the sun.
+const SUN_STEPS: usize = 8;
+
+/// The scattering shell around a planet.
+///
+/// All lengths are in the units of the scene and all coefficients are per unit length.
+///
+/// # Fields
+/// - `radius`: Radius of the planet, where the atmosphere starts.
+/// - `height`: Thickness of the atmosphere.
+/// - `rayleigh`: Scattering coefficients of the air molecules at the surface.
+/// - `rayleigh_height`: Altitude at which the density of the molecules has fallen to 1/e.
+/// - `mie`: Scattering coefficient of the aerosols at the surface. They also absorb a tenth of the light they extinguish.
+/// - `mie_height`: Altitude at which the density of the aerosols has fallen to 1/e.
+/// - `mie_anisotropy`: Asymmetry parameter of the [Henyey-Greenstein](crate::materials::HenyeyGreenstein) phase function of the aerosols.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, planet::Atmosphere};
+/// let atmosphere = Atmosphere::earth(1.);
+/// // Looking straight up from the ground, blue light is scattered away the most.
+/// let transmittance = atmosphere.transmittance(vector![0., 1., 0.], vector![0., 1., 0.]);
+/// assert!(transmittance.b() < transmittance.g() && transmittance.g() < transmittance.r());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Atmosphere {
+    radius: Float,
+    height: Float,
+    rayleigh: Color,
+    rayleigh_height: Float,
+    mie: Float,
+    mie_height: Float,
+    mie_anisotropy: Float,
+}
+
+impl Atmosphere {
+    /// The atmosphere of the Earth, scaled to a planet of `radius`.
+    pub fn earth(radius: Float) -> Self {
+        // Per meter of the Earth with a radius of 6360 km
+        let scale = radius / 6.36e6;
+        Self {
+            radius,
+            height: 6e4 * scale,
+            rayleigh: color![5.8e-6, 13.5e-6, 33.1e-6] / scale,
+            rayleigh_height: 8e3 * scale,
+            mie: 21e-6 / scale,
+            mie_height: 1.2e3 * scale,
+            mie_anisotropy: 0.76,
+        }
+    }
+
+    /// Consume `self` and multiply the densities of the molecules and aerosols by `factor`, e.g. for a thicker atmosphere.
+    pub fn with_density(mut self, factor: Float) -> Self {
+        self.rayleigh *= factor;
+        self.mie *= factor;
+        self
+    }
+
+    /// Consume `self` and set the asymmetry parameter of the phase function of the aerosols (0 for isotropic scattering, towards 1 for haze).
+    pub fn with_mie_anisotropy(mut self, anisotropy: Float) -> Self {
+        self.mie_anisotropy = anisotropy;
+        self
+    }
+
+    pub fn radius(&self) -> Float {
+        self.radius
+    }
+
+    pub fn height(&self) -> Float {
+        self.height
+    }
+
+    /// Fraction of the light that the atmosphere transmits from `point` (relative to the center of the planet) in `direction` out to space.
+    ///
+    /// This is black if the planet is in the way.
+    pub fn transmittance(&self, point: Vector3<Float>, direction: Vector3<Float>) -> Color {
+        let direction = direction.normalize();
+        if self.blocked(point, direction) {
+            return BLACK;
+        }
+        let length = self.exit(point, direction).unwrap_or(0.);
+        self.extinction(self.optical_depth(point, direction, length, SUN_STEPS))
+    }
+
+    /// Sunlight scattered towards `point` (relative to the center of the planet) along the unit `direction`, on the way through the atmosphere.
+    ///
+    /// `sun` is the unit direction the sunlight travels in and `radiance` its strength. Returns the scattered light together with the [`transmittance`](Atmosphere::transmittance) along the way, which ends at the planet or in space.
+    fn inscattered(
+        &self,
+        point: Vector3<Float>,
+        direction: Vector3<Float>,
+        sun: Vector3<Float>,
+        radiance: Color,
+    ) -> (Color, Color) {
+        let length = self.segment(point, direction);
+        let step = length / VIEW_STEPS as Float;
+        let cos = -direction.dot(&sun);
+        let rayleigh_phase = 3. / (16. * PI) * (1. + cos * cos);
+        let g = self.mie_anisotropy;
+        let mie_phase = (1. - g * g) / (4. * PI * (1. + g * g - 2. * g * cos).powf(1.5));
+
+        let mut scattered = BLACK;
+        let mut depth = (0., 0.);
+        for i in 0..VIEW_STEPS {
+            let sample = point + (i as Float + 0.5) * step * direction;
+            let (rayleigh, mie) = self.densities(sample);
+            let half = (rayleigh * step / 2., mie * step / 2.);
+            depth = (depth.0 + half.0, depth.1 + half.1);
+            let to_sun = self.transmittance(sample, -sun);
+            if to_sun != BLACK {
+                let scattering =
+                    rayleigh * rayleigh_phase * self.rayleigh + mie * mie_phase * self.mie * WHITE;
+                scattered += step * self.extinction(depth) * to_sun * scattering;
+            }
+            depth = (depth.0 + half.0, depth.1 + half.1);
+        }
+        (scattered * radiance, self.extinction(depth))
+    }
+
+    /// Relative densities of the molecules and aerosols at `point`.
+    fn densities(&self, point: Vector3<Float>) -> (Float, Float) {
+        let altitude = (point.norm() - self.radius).max(0.);
+        (
+            (-altitude / self.rayleigh_height).exp(),
+            (-altitude / self.mie_height).exp(),
+        )
+    }
+
+    /// Integrated relative densities of the molecules and aerosols from `point` along the unit `direction` for `length`.
+    fn optical_depth(
+        &self,
+        point: Vector3<Float>,
+        direction: Vector3<Float>,
+        length: Float,
+        steps: usize,
+    ) -> (Float, Float) {
+        let step = length / steps as Float;
+        (0..steps)
+            .map(|i| self.densities(point + (i as Float + 0.5) * step * direction))
+            .fold((0., 0.), |(rayleigh, mie), density| {
+                (rayleigh + density.0 * step, mie + density.1 * step)
+            })
+    }
+
+    /// Transmittance of the optical `depth` of the molecules and aerosols.
+    fn extinction(&self, depth: (Float, Float)) -> Color {
+        let mie = self.mie / 0.9 * depth.1;
+        (-(depth.0 * self.rayleigh + mie * WHITE))
+            .into_iter()
+            .map(Float::exp)
+            .collect()
+    }
+
+    /// Whether the planet blocks the unit `direction` from `point` on or above its surface.
+    fn blocked(&self, point: Vector3<Float>, direction: Vector3<Float>) -> bool {
+        let along = point.dot(&direction);
+        along < 0. && point.norm_squared() - along * along < self.radius * self.radius
+    }
+
+    /// Distance from `point` along the unit `direction` to where it leaves the atmosphere.
+    fn exit(&self, point: Vector3<Float>, direction: Vector3<Float>) -> Option<Float> {
+        let outer = self.radius + self.height;
+        let along = point.dot(&direction);
+        let discriminant = along * along - point.norm_squared() + outer * outer;
+        (discriminant >= 0.).then(|| (-along + discriminant.sqrt()).max(0.))
+    }
+
+    /// Length of the way from `point` along the unit `direction` through the atmosphere, until it hits the planet or leaves.
+    fn segment(&self, point: Vector3<Float>, direction: Vector3<Float>) -> Float {
+        let exit = self.exit(point, direction).unwrap_or(0.);
+        if !self.blocked(point, direction) {
+            return exit;
+        }
+        let along = point.dot(&direction);
+        let discriminant = along * along - point.norm_squared() + self.radius * self.radius;
+        (-along - discriminant.max(0.).sqrt()).clamp(0., exit)
+    }
+}
+
+/// A planet with a textured surface, an optional [`Atmosphere`] and an optional sun.
+///
+/// # Fields
+/// - `center`: Center of the planet.
+/// - `radius`: Radius of the planet.
+/// - `texture`: [`Texture`] of the diffuse surface, e.g. a map of the planet in equirectangular projection.
+/// - `atmosphere`: [`Atmosphere`] around the planet.
+/// - `sun`: Unit direction the sunlight travels in and its strength.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, planet::{Atmosphere, Planet}, textures::SolidColor};
+/// let camera = Camera::new(vector![0., 0., 4.], vector![0., 0., 0.], vector![0., 1., 0.], 0.6, 1., 0., 4.);
+/// let mut raytracer = Raytracer::new(camera, color::BLACK, 8, 8, 4, 4);
+/// Planet::new(vector![0., 0., 0.], 1., SolidColor::new(color![0.1, 0.3, 0.6]))
+///     .with_atmosphere(Atmosphere::earth(1.))
+///     .with_sun(vector![-1., 0., -1.], color![20., 20., 20.])
+///     .add_to(&mut raytracer.world, &mut raytracer.lights);
+/// let image = raytracer.render().unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Planet<T: Texture> {
+    center: Vector3<Float>,
+    radius: Float,
+    texture: T,
+    atmosphere: Option<Atmosphere>,
+    sun: Option<(Vector3<Float>, Color)>,
+}
+
+impl<T: Texture + Clone + 'static> Planet<T> {
+    pub fn new(center: Vector3<Float>, radius: Float, texture: T) -> Self {
+        Self {
+            center,
+            radius,
+            texture,
+            atmosphere: None,
+            sun: None,
+        }
+    }
+
+    /// Consume `self` and surround the planet with `atmosphere`.
+    ///
+    /// The atmosphere starts at the surface, whatever radius it was created with.
+    pub fn with_atmosphere(mut self, mut atmosphere: Atmosphere) -> Self {
+        atmosphere.radius = self.radius;
+        self.atmosphere = Some(atmosphere);
+        self
+    }
+
+    /// Consume `self` and light the planet with a sun infinitely far away, whose light travels in `direction` with the strength `radiance`.
+    pub fn with_sun(mut self, direction: Vector3<Float>, radiance: Color) -> Self {
+        self.sun = Some((direction.normalize(), radiance));
+        self
+    }
+
+    /// Add the surface and the atmosphere to `world` and the sun to `lights`, e.g. those of a [`Raytracer`].
+    pub fn add_to(self, world: &mut HittableList, lights: &mut Lights) {
+        world.push(Sphere::new(
+            self.center,
+            self.radius,
+            Lambertian::new(self.texture),
+        ));
+        if let Some(atmosphere) = self.atmosphere {
+            world.push(AtmosphereShell {
+                center: Offset::new(self.center),
+                material: Scattering {
+                    center: self.center,
+                    atmosphere,
+                    sun: self.sun,
+                },
+            });
+        }
+        if let Some((direction, radiance)) = self.sun {
+            lights.push_analytic(Sun {
+                center: self.center,
+                direction,
+                radiance,
+                atmosphere: self.atmosphere,
+            });
+        }
+    }
+}
+
+/// The outer boundary of an [`Atmosphere`], which [`Ray`]s from outside hit where they enter it.
+///
+/// Shadow [`Ray`]s pass through, because the [`Sun`] is already attenuated by the atmosphere.
+#[derive(Clone, Debug)]
+struct AtmosphereShell {
+    center: Offset,
+    material: Scattering,
+}
+
+impl Hittable for AtmosphereShell {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        if ray.kind() == Some(RayKind::Shadow) {
+            return None;
+        }
+        let atmosphere = &self.material.atmosphere;
+        let outer = atmosphere.radius + atmosphere.height;
+        let a = ray.direction().norm_squared();
+        let half_b = ray.origin().dot(&ray.direction());
+        let c = ray.origin().norm_squared() - outer * outer;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0. {
+            return None;
+        }
+
+        // Only entering counts, since the whole way through is handled there.
+        let t = (-half_b - discriminant.sqrt()) / a;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+        let point = ray.at(t);
+        let (u, v) = encode_direction(ray.direction().normalize());
+        Some(HitRecord::new(
+            point,
+            u,
+            v,
+            point / outer,
+            t,
+            true,
+            &self.material,
+        ))
+    }
+
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
+        let atmosphere = &self.material.atmosphere;
+        let outer = Vector3::repeat(atmosphere.radius + atmosphere.height);
+        Some(Aabb::new(-outer, outer))
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+}
+
+/// The [`Material`] of an [`AtmosphereShell`]: it emits the sunlight scattered along the [`Ray`] and passes it on with the transmittance.
+///
+/// [`Material::emit`] does not know the [`Ray`], so the hit records of the shell store its direction in `u` and `v` (see [`encode_direction`]).
+#[derive(Clone, Debug)]
+struct Scattering {
+    center: Vector3<Float>,
+    atmosphere: Atmosphere,
+    sun: Option<(Vector3<Float>, Color)>,
+}
+
+impl Material for Scattering {
+    fn scatter(&self, ray: Ray, hit: HitRecord) -> Option<(Ray, Color)> {
+        let point = hit.point - self.center;
+        let direction = ray.direction().normalize();
+        let length = self.atmosphere.segment(point, direction);
+        let depth = self
+            .atmosphere
+            .optical_depth(point, direction, length, VIEW_STEPS);
+        Some((ray.continued(hit.point), self.atmosphere.extinction(depth)))
+    }
+
+    fn emit(&self, u: Float, v: Float, point: Vector3<Float>) -> Color {
+        let Some((sun, radiance)) = self.sun else {
+            return BLACK;
+        };
+        let direction = decode_direction(u, v);
+        let (scattered, _) =
+            self.atmosphere
+                .inscattered(point - self.center, direction, sun, radiance);
+        scattered
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.sun.is_some()
+    }
+}
+
+/// The sun of a [`Planet`], whose light is attenuated on its way through the [`Atmosphere`].
+#[derive(Clone, Debug)]
+struct Sun {
+    center: Vector3<Float>,
+    direction: Vector3<Float>,
+    radiance: Color,
+    atmosphere: Option<Atmosphere>,
+}
+
+impl Light for Sun {
+    fn sample(&self, point: Vector3<Float>) -> Option<LightSample> {
+        let radiance = match &self.atmosphere {
+            Some(atmosphere) => {
+                self.radiance * atmosphere.transmittance(point - self.center, -self.direction)
+            }
+            None => self.radiance,
+        };
+        (radiance != BLACK).then_some(LightSample {
+            direction: -self.direction,
+            distance: Float::INFINITY,
+            radiance,
+        })
+    }
+}
+
+/// Surface coordinates that store the unit `direction` as its longitude and latitude.
+fn encode_direction(direction: Vector3<Float>) -> (Float, Float) {
+    (
+        0.5 + direction.z.atan2(direction.x) / (2. * PI),
+        direction.y.clamp(-1., 1.).acos() / PI,
+    )
+}
+
+/// The unit direction stored by [`encode_direction`].
+fn decode_direction(u: Float, v: Float) -> Vector3<Float> {
+    let (sin_phi, cos_phi) = ((u - 0.5) * 2. * PI).sin_cos();
+    let (sin_theta, cos_theta) = (v * PI).sin_cos();
+    vector![sin_theta * cos_phi, cos_theta, sin_theta * sin_phi]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::textures::SolidColor;
+
+    #[test]
+    fn limb() {
+        let mut world = HittableList::default();
+        let mut lights = Lights::new();
+        Planet::new(vector![0., 0., 0.], 1., SolidColor::new(WHITE))
+            .with_atmosphere(Atmosphere::earth(1.))
+            .with_sun(vector![0., 0., -1.], WHITE)
+            .add_to(&mut world, &mut lights);
+
+        // A ray grazing the planet through the atmosphere, lit from behind the camera, looks blue.
+        let height = Atmosphere::earth(1.).height();
+        let ray = Ray::new(vector![-2., 1. + height / 2., 0.], vector![1., 0., 0.]);
+        let hit = world.hit(ray, 0.001, Float::INFINITY).unwrap();
+        let scattered = hit.material().emit(hit.u, hit.v, hit.point);
+        assert!(scattered.b() > scattered.g() && scattered.g() > scattered.r());
+        assert!(scattered.r() > 0.);
+        let (_, transmittance) = hit.material().scatter(ray, hit.clone()).unwrap();
+        assert!(transmittance.r() < 1. && transmittance.b() < transmittance.r());
+
+        // Shadow rays only see the planet.
+        let shadow = ray.with_kind(RayKind::Shadow);
+        assert!(world.hit(shadow, 0.001, Float::INFINITY).is_none());
+
+        // The sun sets reddish at the terminator and is dark behind the planet.
+        let sun = lights.analytic_lights().next().unwrap();
+        let dusk = sun.sample(vector![0., 1., 0.]).unwrap();
+        assert!(dusk.radiance.r() > dusk.radiance.b());
+        assert!(sun.sample(vector![0., 0., -1.5]).is_none());
+
+        let (u, v) = encode_direction(vector![0.6, -0.8, 0.]);
+        assert!((decode_direction(u, v) - vector![0.6, -0.8, 0.]).norm() < 1e-5);
+    }
+}
@@ -0,0 +1,74 @@
+//! Stand-ins for the optional dependencies, so the renderer also builds without threads or a system clock, e.g. for WebAssembly.
+//!
+//! Without the `parallel` feature, the parallel iterators of [`rayon`] are replaced by the sequential ones of [`std`] with the same names.
+//! With the `wasm` feature, time is measured with the clock of the browser.
+//! With the `log` feature, [`log_event`] forwards to the [`log`](https://docs.rs/log) crate.
+
+#[cfg(feature = "wasm")]
+pub(crate) use instant::Instant;
+#[cfg(feature = "parallel")]
+pub(crate) use rayon::{join, prelude::*};
+#[cfg(not(feature = "wasm"))]
+pub(crate) use std::time::Instant;
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) use self::sequential::*;
+
+#[cfg(not(feature = "parallel"))]
+mod sequential {
+    use std::iter::FlatMap;
+    use std::slice::{Iter, IterMut};
+
+    pub(crate) trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+
+    impl<I: IntoIterator> IntoParallelIterator for I {}
+
+    pub(crate) trait ParallelSlice<T> {
+        fn par_iter(&self) -> Iter<'_, T>;
+
+        fn par_iter_mut(&mut self) -> IterMut<'_, T>;
+    }
+
+    impl<T> ParallelSlice<T> for [T] {
+        fn par_iter(&self) -> Iter<'_, T> {
+            self.iter()
+        }
+
+        fn par_iter_mut(&mut self) -> IterMut<'_, T> {
+            self.iter_mut()
+        }
+    }
+
+    pub(crate) trait ParallelIterator: Iterator + Sized {
+        fn flat_map_iter<U: IntoIterator, F: FnMut(Self::Item) -> U>(
+            self,
+            f: F,
+        ) -> FlatMap<Self, U, F> {
+            self.flat_map(f)
+        }
+    }
+
+    impl<I: Iterator> ParallelIterator for I {}
+
+    pub(crate) fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA,
+        B: FnOnce() -> RB,
+    {
+        (a(), b())
+    }
+}
+
+/// Log a message at `level` (e.g. `debug`) if the `log` feature is enabled. Without it, the arguments are not evaluated.
+macro_rules! log_event {
+    ($level:ident, $($arguments:tt)+) => {
+        #[cfg(feature = "log")]
+        ::log::$level!($($arguments)+);
+    };
+}
+
+pub(crate) use log_event;
@@ -0,0 +1,465 @@
+//! Point clouds (e.g. from 3D scans) rendered as small spheres or oriented disks.
+//!
+//! Points can be read from PLY files with [`read_ply`], which understands the ASCII and the binary formats.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use nalgebra::Rotation3;
+
+use crate::color::WHITE;
+use crate::error::Error;
+use crate::hitrecord::HitRecord;
+use crate::hittable::{Aabb, Bvh};
+use crate::materials::Material;
+use crate::ray::Ray;
+use crate::shapes::{Movable, Offset, Sphere};
+use crate::stats::SceneStats;
+use crate::vec3::orthonormal_basis;
+use crate::*;
+
+/// A single point of a [`PointCloud`].
+///
+/// # Fields
+/// - `position`: Position relative to the center of the cloud.
+/// - `normal`: Orientation of the surface at the point, if known.
+/// - `color`: Color of the point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub position: Vector3<Float>,
+    pub normal: Option<Vector3<Float>>,
+    pub color: Color,
+}
+
+impl Point {
+    /// Create a white point without a normal.
+    pub fn new(position: Vector3<Float>) -> Self {
+        Self {
+            position,
+            normal: None,
+            color: WHITE,
+        }
+    }
+
+    /// Consume `self` and set the normal.
+    pub fn with_normal(mut self, normal: Vector3<Float>) -> Self {
+        self.normal = Some(normal);
+        self
+    }
+
+    /// Consume `self` and set the color.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// Shape each point of a [`PointCloud`] is rendered as.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Splat {
+    /// A sphere around every point.
+    Sphere,
+    /// A disk perpendicular to the normal of every point, which covers surfaces without gaps and bumps. Points without a normal become spheres.
+    #[default]
+    Disk,
+}
+
+/// A cloud of points, each rendered as a small [`Splat`] with its own color and sorted into a [`Bvh`].
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, pointcloud::{Point, PointCloud, Splat}, ray::Ray};
+/// // A scanned floor
+/// let points: Vec<_> = (0..100)
+///     .map(|i| Point::new(vector![(i % 10) as Float, 0., (i / 10) as Float] / 10.)
+///         .with_normal(vector![0., 1., 0.])
+///         .with_color(color![0.5, 0.5, 0.5]))
+///     .collect();
+/// let cloud = PointCloud::new(vector![0., 0., 0.], &points, 0.08, Splat::Disk, Lambertian::solid_color).unwrap();
+///
+/// let hit = cloud.hit(Ray::new(vector![0.45, 1., 0.45], vector![0., -1., 0.]), 0., Float::INFINITY).unwrap();
+/// assert!(hit.point.y.abs() < 1e-5);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PointCloud {
+    center: Offset,
+    point_count: usize,
+    splats: Bvh,
+}
+
+impl PointCloud {
+    /// Create a [`PointCloud`].
+    ///
+    /// # Parameters
+    /// - `center`: Origin the points are relative to.
+    /// - `points`: The points of the cloud.
+    /// - `radius`: Radius of every [`Splat`].
+    /// - `splat`: Shape of the points.
+    /// - `material`: Creates the material of a point from its color, e.g. [`Lambertian::solid_color`](crate::materials::Lambertian::solid_color).
+    pub fn new<M: Material + Clone + 'static>(
+        center: Vector3<Float>,
+        points: &[Point],
+        radius: Float,
+        splat: Splat,
+        material: impl Fn(Color) -> M,
+    ) -> Result<Self, Error> {
+        if points.is_empty() {
+            return Err(Error::Config("point cloud without points".to_string()));
+        }
+        let mut splats = HittableList::default();
+        for point in points {
+            let material = material(point.color);
+            match (splat, point.normal) {
+                (Splat::Disk, Some(normal)) => {
+                    splats.push(Disk::new(point.position, normal, radius, material))
+                }
+                _ => splats.push(Sphere::new(point.position, radius, material)),
+            }
+        }
+
+        Ok(Self {
+            center: Offset::new(center),
+            point_count: points.len(),
+            splats: Bvh::new(splats, 0., 0.)?,
+        })
+    }
+
+    /// Read the points from a PLY file via [`read_ply`] and create a [`PointCloud`] via [`new`](PointCloud::new).
+    pub fn open<P: AsRef<Path>, M: Material + Clone + 'static>(
+        center: Vector3<Float>,
+        path: P,
+        radius: Float,
+        splat: Splat,
+        material: impl Fn(Color) -> M,
+    ) -> Result<Self, Error> {
+        let points = read_ply(BufReader::new(File::open(path)?))?;
+        Self::new(center, &points, radius, splat, material)
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.point_count
+    }
+}
+
+impl Hittable for PointCloud {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        self.splats.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.splats.bounding_box(time0, time1)
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        self.splats.collect_stats(stats);
+    }
+}
+
+impl Movable for PointCloud {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+/// A flat, two-sided disk, see [`Splat::Disk`].
+#[derive(Clone, Debug)]
+struct Disk<M: Material> {
+    center: Offset,
+    normal: Vector3<Float>,
+    radius: Float,
+    material: M,
+}
+
+impl<M: Material> Disk<M> {
+    fn new(center: Vector3<Float>, normal: Vector3<Float>, radius: Float, material: M) -> Self {
+        Self {
+            center: Offset::new(center),
+            normal: normal.try_normalize(0.).unwrap_or_else(Vector3::y),
+            radius,
+            material,
+        }
+    }
+}
+
+impl<M: Material> Hittable for Disk<M> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let denominator = self.normal.dot(&ray.direction());
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+        let t = -self.normal.dot(&ray.origin()) / denominator;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+        let point = ray.at(t);
+        if point.norm_squared() > self.radius * self.radius {
+            return None;
+        }
+
+        // Polar coordinates on the disk
+        let (tangent, bitangent) = orthonormal_basis(&self.normal);
+        let u = (point.dot(&bitangent).atan2(point.dot(&tangent)) / (2. * float::consts::PI))
+            .rem_euclid(1.);
+        let v = point.norm() / self.radius;
+        Some(HitRecord::from_ray(
+            point,
+            u,
+            v,
+            self.normal,
+            t,
+            &self.material,
+            ray,
+        ))
+    }
+
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
+        // The extent along each axis shrinks the more the disk faces it.
+        let extent = self
+            .normal
+            .map(|n| self.radius * (1. - n * n).max(0.).sqrt() + 1e-4);
+        Some(Aabb::new(-extent, extent))
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        let degenerate = (self.radius <= 0.).then_some("disk with non-positive radius");
+        stats.add_primitive(
+            std::mem::size_of_val(self),
+            Some(&self.material),
+            degenerate,
+        );
+    }
+}
+
+/// Encoding of the data of a PLY file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PlyFormat {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+/// An element of a PLY file with its properties.
+struct PlyElement {
+    name: String,
+    count: usize,
+    /// Name and size in bytes (0 for lists) of each property.
+    properties: Vec<(String, usize)>,
+    /// Whether each property is a float.
+    floats: Vec<bool>,
+}
+
+/// Read the vertices of a PLY file as [`Point`]s.
+///
+/// The properties `x`, `y`, `z`, `nx`, `ny`, `nz`, and `red`, `green`, `blue` are used; other properties and elements (e.g. faces) are ignored.
+/// Integer colors are divided by 255 and float colors are taken as they are. Normals are only set if all three are present, colors default to white.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, pointcloud::read_ply};
+/// let ply = "ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nproperty uchar red\nproperty uchar green\nproperty uchar blue\nend_header\n0 0 0 255 0 0\n1 2 3 0 0 255\n";
+/// let points = read_ply(ply.as_bytes()).unwrap();
+/// assert_eq!(points[1].position, vector![1., 2., 3.]);
+/// assert_eq!(points[1].color, color![0., 0., 1.]);
+/// ```
+pub fn read_ply<R: BufRead>(mut reader: R) -> io::Result<Vec<Point>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if line.trim() != "ply" {
+        return Err(invalid("not a PLY file".to_string()));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("missing end_header".to_string()));
+        }
+        let tokens: Vec<_> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", "ascii", _] => format = Some(PlyFormat::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(PlyFormat::LittleEndian),
+            ["format", "binary_big_endian", _] => format = Some(PlyFormat::BigEndian),
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid element count {count}")))?,
+                properties: Vec::new(),
+                floats: Vec::new(),
+            }),
+            ["property", "list", .., name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| invalid("property without element".to_string()))?;
+                element.properties.push((name.to_string(), 0));
+                element.floats.push(false);
+            }
+            ["property", kind, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| invalid("property without element".to_string()))?;
+                let (size, float) = match *kind {
+                    "char" | "uchar" | "int8" | "uint8" => (1, false),
+                    "short" | "ushort" | "int16" | "uint16" => (2, false),
+                    "int" | "uint" | "int32" | "uint32" => (4, false),
+                    "float" | "float32" => (4, true),
+                    "double" | "float64" => (8, true),
+                    _ => return Err(invalid(format!("unknown property type {kind}"))),
+                };
+                element.properties.push((name.to_string(), size));
+                element.floats.push(float);
+            }
+            ["end_header"] => break,
+            _ => {}
+        }
+    }
+    let format = format.ok_or_else(|| invalid("missing format".to_string()))?;
+
+    let mut points = Vec::new();
+    for element in &elements {
+        let is_vertex = element.name == "vertex";
+        let has_lists = element.properties.iter().any(|&(_, size)| size == 0);
+        if format != PlyFormat::Ascii && has_lists {
+            // Binary lists (e.g. faces) have no fixed size, so everything after them is ignored.
+            if is_vertex {
+                return Err(invalid("vertices with list properties".to_string()));
+            }
+            break;
+        }
+        let index = |name: &str| {
+            element
+                .properties
+                .iter()
+                .position(|(property, _)| property == name)
+        };
+        let position = [index("x"), index("y"), index("z")];
+        let normal = [index("nx"), index("ny"), index("nz")];
+        let color = [index("red"), index("green"), index("blue")];
+        if is_vertex && position.iter().any(Option::is_none) {
+            return Err(invalid("vertices without position".to_string()));
+        }
+
+        for number in 0..element.count {
+            let values = match format {
+                PlyFormat::Ascii => {
+                    line.clear();
+                    reader.read_line(&mut line)?;
+                    let values = line
+                        .split_whitespace()
+                        .map(str::parse::<f64>)
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|error| invalid(format!("{} {number}: {error}", element.name)))?;
+                    if values.len() < element.properties.len() {
+                        return Err(invalid(format!("{} {number} is too short", element.name)));
+                    }
+                    values
+                }
+                _ => element
+                    .properties
+                    .iter()
+                    .zip(&element.floats)
+                    .map(|(&(_, size), &float)| read_binary(&mut reader, size, float, format))
+                    .collect::<io::Result<Vec<_>>>()?,
+            };
+            if !is_vertex {
+                continue;
+            }
+
+            let get = |indices: [Option<usize>; 3]| -> Option<Vector3<Float>> {
+                Some(indices.map(|index| index.map(|index| values[index] as Float)))
+                    .and_then(|[x, y, z]| Some(vector![x?, y?, z?]))
+            };
+            let mut point = Point::new(get(position).unwrap());
+            point.normal = get(normal);
+            if let (Some(channels), Some(first)) = (get(color), color[0]) {
+                // Integer colors range up to 255.
+                let channels = if element.floats[first] {
+                    channels
+                } else {
+                    channels / 255.
+                };
+                point.color = color![channels.x, channels.y, channels.z];
+            }
+            points.push(point);
+        }
+    }
+
+    if points.is_empty() {
+        return Err(invalid("no vertices".to_string()));
+    }
+    Ok(points)
+}
+
+/// Read a single binary value of `size` bytes.
+fn read_binary<R: Read>(
+    reader: &mut R,
+    size: usize,
+    float: bool,
+    format: PlyFormat,
+) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes[..size])?;
+    if format == PlyFormat::BigEndian {
+        bytes[..size].reverse();
+    }
+    // Signedness is ignored, since coordinates are floats and colors unsigned.
+    Ok(match (size, float) {
+        (4, true) => f32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+        (8, true) => f64::from_le_bytes(bytes),
+        (1, _) => bytes[0] as f64,
+        (2, _) => u16::from_le_bytes(bytes[..2].try_into().unwrap()) as f64,
+        _ => u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn binary_ply() {
+        let mut ply = b"ply\nformat binary_little_endian 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nproperty float nx\nproperty float ny\nproperty float nz\nproperty uchar red\nproperty uchar green\nproperty uchar blue\nelement face 0\nproperty list uchar int vertex_indices\nend_header\n".to_vec();
+        for value in [1f32, 2., 3., 0., 0., 1.] {
+            ply.extend(value.to_le_bytes());
+        }
+        ply.extend([255, 0, 51]);
+
+        let points = read_ply(ply.as_slice()).unwrap();
+        assert_eq!(
+            points,
+            vec![Point::new(vector![1., 2., 3.])
+                .with_normal(vector![0., 0., 1.])
+                .with_color(color![1., 0., 0.2])]
+        );
+    }
+
+    #[test]
+    fn without_points() {
+        let cloud = PointCloud::new(
+            vector![0., 0., 0.],
+            &[],
+            0.1,
+            Splat::Sphere,
+            crate::materials::Lambertian::solid_color,
+        );
+        assert!(matches!(cloud, Err(Error::Config(_))));
+    }
+}
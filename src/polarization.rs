@@ -0,0 +1,210 @@
+//! Mueller calculus for the [polarized path tracer](crate::integrator::Integrator::Polarized).
+//!
+//! The polarization of light is described by its Stokes parameters (I, Q, U, V): the intensity, the excess of linear polarization along the x axis over the y axis, the same along the diagonals, and the circular polarization.
+//! They are measured in a frame perpendicular to the direction of propagation, whose x axis is a reference direction and whose y axis is the direction of propagation × the reference.
+//! Optical elements and interfaces transform them linearly with 4×4 Mueller matrices.
+
+use nalgebra::{Complex, ComplexField, Matrix4};
+
+use crate::*;
+
+/// A smooth surface whose Fresnel reflection polarizes light, see [`Material::fresnel`](crate::materials::Material::fresnel).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interface {
+    /// A transparent material with the index of refraction `ior`.
+    Dielectric { ior: Float },
+    /// A metal with the complex index of refraction `eta` + i`k` for each color channel.
+    Conductor { eta: Color, k: Color },
+}
+
+impl Interface {
+    /// A metal that reflects the fraction `reflectance` at normal incidence.
+    ///
+    /// The complex index of refraction is fitted to the reflectance, with an edge tint equal to it (Gulbrandsen 2014).
+    pub fn metal(reflectance: Color) -> Self {
+        let (eta, k) = reflectance
+            .into_iter()
+            .map(|r| {
+                let r = r.clamp(0., 0.99);
+                let sqrt = r.sqrt();
+                let eta = r * (1. - r) / (1. + r) + (1. - r) * (1. + sqrt) / (1. - sqrt);
+                let k2 = (r * (eta + 1.).powi(2) - (eta - 1.).powi(2)) / (1. - r);
+                (eta, k2.max(0.).sqrt())
+            })
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        Self::Conductor {
+            eta: eta.into_iter().collect(),
+            k: k.into_iter().collect(),
+        }
+    }
+
+    /// Mueller matrices of the reflection for each color channel, for light hitting the interface at an angle with cosine `cos_i` to the normal.
+    ///
+    /// `inside` is whether the light comes from inside a [dielectric](Interface::Dielectric). The x axis of both frames is perpendicular to the plane of incidence.
+    pub fn reflection(&self, cos_i: Float, inside: bool) -> [Matrix4<Float>; 3] {
+        match *self {
+            Self::Dielectric { ior } => {
+                let eta = if inside { 1. / ior } else { ior };
+                [fresnel_reflection(cos_i, Complex::new(eta, 0.)); 3]
+            }
+            Self::Conductor { eta, k } => [0, 1, 2]
+                .map(|channel| fresnel_reflection(cos_i, Complex::new(eta[channel], k[channel]))),
+        }
+    }
+}
+
+/// Mueller matrix of the reflection at an interface with the relative (complex) index of refraction `eta`, for light hitting it at an angle with cosine `cos_i` to the normal.
+///
+/// The x axis of both frames is perpendicular to the plane of incidence. This also covers total internal reflection, which changes the phase between both polarizations.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, polarization::fresnel_reflection};
+/// # use nalgebra::{Complex, Vector4};
+/// // At Brewster's angle, the reflection of glass is completely polarized perpendicular to the plane of incidence.
+/// let brewster = Float::atan(1.5);
+/// let reflected = fresnel_reflection(brewster.cos(), Complex::new(1.5, 0.)) * Vector4::new(1., 0., 0., 0.);
+/// assert!((reflected[1] - reflected[0]).abs() < 1e-5);
+/// ```
+pub fn fresnel_reflection(cos_i: Float, eta: Complex<Float>) -> Matrix4<Float> {
+    let cos_i = cos_i.clamp(0., 1.);
+    let cos_t = cos_transmitted(cos_i, eta);
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let (rs2, rp2) = (rs.norm_sqr(), rp.norm_sqr());
+    let cross = rs * rp.conj();
+    0.5 * Matrix4::new(
+        rs2 + rp2,
+        rs2 - rp2,
+        0.,
+        0.,
+        rs2 - rp2,
+        rs2 + rp2,
+        0.,
+        0.,
+        0.,
+        0.,
+        2. * cross.re,
+        2. * cross.im,
+        0.,
+        0.,
+        -2. * cross.im,
+        2. * cross.re,
+    )
+}
+
+/// Mueller matrix of the refraction into a transparent material with the relative index of refraction `eta`, for light hitting it at an angle with cosine `cos_i` to the normal.
+///
+/// Returns [`None`] for total internal reflection. The x axis of both frames is perpendicular to the plane of incidence.
+pub fn fresnel_transmission(cos_i: Float, eta: Float) -> Option<Matrix4<Float>> {
+    let cos_i = cos_i.clamp(0., 1.);
+    let sin2_t = (1. - cos_i * cos_i) / (eta * eta);
+    if sin2_t > 1. {
+        return None;
+    }
+    let cos_t = (1. - sin2_t).sqrt();
+    // Fraction of the power, including the change of the cross section of the beam.
+    let factor = eta * cos_t / cos_i.max(Float::EPSILON);
+    let ts = factor * (2. * cos_i / (cos_i + eta * cos_t)).powi(2);
+    let tp = factor * (2. * cos_i / (eta * cos_i + cos_t)).powi(2);
+    let cross = (ts * tp).sqrt();
+    Some(
+        0.5 * Matrix4::new(
+            ts + tp,
+            ts - tp,
+            0.,
+            0.,
+            ts - tp,
+            ts + tp,
+            0.,
+            0.,
+            0.,
+            0.,
+            2. * cross,
+            0.,
+            0.,
+            0.,
+            0.,
+            2. * cross,
+        ),
+    )
+}
+
+/// Mueller matrix of an ideal linear polarizer that transmits light polarized at `angle` (in radians) from the x axis towards the y axis.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, polarization::linear_polarizer};
+/// # use nalgebra::Vector4;
+/// // Malus's law: light polarized along x behind a polarizer at 60°
+/// let transmitted = linear_polarizer(float::consts::FRAC_PI_3) * Vector4::new(1., 1., 0., 0.);
+/// assert!((transmitted[0] - 0.25).abs() < 1e-5);
+/// ```
+pub fn linear_polarizer(angle: Float) -> Matrix4<Float> {
+    let (sin, cos) = (2. * angle).sin_cos();
+    0.5 * Matrix4::new(
+        1.,
+        cos,
+        sin,
+        0.,
+        cos,
+        cos * cos,
+        cos * sin,
+        0.,
+        sin,
+        cos * sin,
+        sin * sin,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+    )
+}
+
+/// Mueller matrix that converts Stokes parameters to a frame whose x axis is rotated by `angle` (in radians) from the x axis towards the y axis.
+pub fn rotation(angle: Float) -> Matrix4<Float> {
+    let (sin, cos) = (2. * angle).sin_cos();
+    Matrix4::new(
+        1., 0., 0., 0., 0., cos, sin, 0., 0., -sin, cos, 0., 0., 0., 0., 1.,
+    )
+}
+
+/// Cosine of the angle of the refracted wave to the normal, which is complex for total internal reflection and for metals.
+fn cos_transmitted(cos_i: Float, eta: Complex<Float>) -> Complex<Float> {
+    let sin2_t = Complex::new(1. - cos_i * cos_i, 0.) / (eta * eta);
+    (Complex::new(1., 0.) - sin2_t).sqrt()
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Vector4;
+
+    use super::*;
+
+    #[test]
+    fn energy() {
+        let unpolarized = Vector4::new(1., 0., 0., 0.);
+        for cos_i in [1., 0.7, 0.2] {
+            let reflected = fresnel_reflection(cos_i, Complex::new(1.5, 0.)) * unpolarized;
+            let transmitted = fresnel_transmission(cos_i, 1.5).unwrap() * unpolarized;
+            assert!((reflected[0] + transmitted[0] - 1.).abs() < 1e-4);
+        }
+        // Total internal reflection keeps all light.
+        assert!(fresnel_transmission(0.2, 1. / 1.5).is_none());
+        let reflected = fresnel_reflection(0.2, Complex::new(1. / 1.5, 0.)) * unpolarized;
+        assert!((reflected[0] - 1.).abs() < 1e-4);
+
+        // The fitted metal reflects its reflectance at normal incidence.
+        let gold = color![1., 0.78, 0.34];
+        let matrices = Interface::metal(gold).reflection(1., false);
+        for channel in 0..3 {
+            assert!((matrices[channel][(0, 0)] - gold[channel as u8].min(0.99)).abs() < 1e-3);
+        }
+
+        // Rotating the frame by 45° turns diagonal into horizontal polarization.
+        let diagonal = Vector4::new(1., 0., 1., 0.);
+        let rotated = rotation(float::consts::FRAC_PI_4) * diagonal;
+        assert!((rotated - Vector4::new(1., 1., 0., 0.)).norm() < 1e-5);
+    }
+}
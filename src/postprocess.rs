@@ -0,0 +1,348 @@
+//! Post-processing of the linear radiance of rendered images, for effects of real cameras that are cheaper to add afterwards than to trace.
+//!
+//! Unlike the [tone mapping](crate::raytracer::RaytracedImage::with_exposure), these effects change the [`colors`](crate::raytracer::RaytracedImage::colors) of the image, so they also show in the float images.
+
+use crate::color::BLACK;
+use crate::*;
+
+/// Glow around bright lights and highlights, like the scattering in the lens and the eye.
+///
+/// The radiance above `threshold` is blurred at `levels` scales, each half the size of the previous one, and the sum is added back with the weight `intensity`.
+/// Since the blur is wider than a pixel, light sources that are much brighter than white get a visible halo in 8 bit images instead of a hard edge.
+///
+/// # Fields
+/// - `threshold`: Luminance above which pixels glow.
+/// - `intensity`: Weight of the glow that is added to the image.
+/// - `levels`: Number of scales of the blur, each twice as wide as the previous one.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::DiffuseLight, postprocess::Bloom, shapes::Sphere};
+/// let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+/// let mut raytracer = Raytracer::new(camera, color![0., 0., 0.], 32, 32, 4, 4);
+/// raytracer.world.push(Sphere::new(vector![0., 0., -1.], 0.1, DiffuseLight::solid_color(color![50., 50., 50.])));
+///
+/// let image = raytracer.render().unwrap();
+/// let corner = image.colors()[32 * 10 + 10];
+/// let image = image.with_bloom(Bloom::default());
+/// assert!(image.colors()[32 * 10 + 10].r() > corner.r());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bloom {
+    pub threshold: Float,
+    pub intensity: Float,
+    pub levels: u32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            threshold: 1.,
+            intensity: 0.1,
+            levels: 5,
+        }
+    }
+}
+
+impl Bloom {
+    /// Consume `self` and set the `threshold`.
+    pub fn with_threshold(mut self, threshold: Float) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Consume `self` and set the `intensity`.
+    pub fn with_intensity(mut self, intensity: Float) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Consume `self` and set the number of `levels`.
+    pub fn with_levels(mut self, levels: u32) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    /// Add the glow to the `colors` of an image of `image_width` × `image_height` pixels.
+    pub(crate) fn apply(&self, colors: &mut [Color], image_width: u32, image_height: u32) {
+        // Only the radiance above the threshold glows, keeping the hue.
+        let bright = colors
+            .iter()
+            .map(|&color| {
+                let luminance = color.luminance();
+                match luminance > self.threshold {
+                    true => color * ((luminance - self.threshold) / luminance),
+                    false => BLACK,
+                }
+            })
+            .collect();
+        let mut level = Plane {
+            width: image_width as usize,
+            height: image_height as usize,
+            pixels: bright,
+        };
+
+        let mut levels = Vec::new();
+        for _ in 0..self.levels {
+            if level.width < 2 && level.height < 2 {
+                break;
+            }
+            level = level.downsample();
+            levels.push(level.blur());
+        }
+        if levels.is_empty() {
+            return;
+        }
+
+        let weight = self.intensity / levels.len() as Float;
+        for (index, color) in colors.iter_mut().enumerate() {
+            let (x, y) = (index % image_width as usize, index / image_width as usize);
+            for (number, level) in levels.iter().enumerate() {
+                let scale = (2 << number) as Float;
+                let glow = level.sample(
+                    (x as Float + 0.5) / scale - 0.5,
+                    (y as Float + 0.5) / scale - 0.5,
+                );
+                *color += weight * glow;
+            }
+        }
+    }
+}
+
+/// Imperfections of real lenses that depend on the distance from the center of the image.
+///
+/// All effects are measured in the radius `r` from the center, which is 1 in the corners.
+/// Pixels are moved by resampling the image bilinearly, and the alpha channel moves with the green channel.
+///
+/// # Fields
+/// - `vignette`: Darkening towards the corners, which are scaled by 1 / (1 + `vignette` r²)² like the cos⁴ falloff of a wide lens.
+/// - `chromatic_aberration`: Lateral chromatic aberration, the red channel is magnified by 1 + `chromatic_aberration` and the blue channel by 1 - `chromatic_aberration`.
+/// - `distortion`: Radial distortion, each pixel shows the point at 1 + `distortion` r² times its radius. Positive values give a barrel and negative ones a pincushion distortion.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, postprocess::LensEffects};
+/// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.5, 0.5], 9, 9, 1, 1);
+/// let image = raytracer.render().unwrap().with_lens_effects(LensEffects::default().with_vignette(1.));
+/// assert!((image.colors()[4 * 9 + 4].r() - 0.5).abs() < 1e-6);
+/// assert!((image.colors()[0].r() - 0.125).abs() < 1e-6);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LensEffects {
+    pub vignette: Float,
+    pub chromatic_aberration: Float,
+    pub distortion: Float,
+}
+
+impl LensEffects {
+    /// Consume `self` and set the `vignette`.
+    pub fn with_vignette(mut self, vignette: Float) -> Self {
+        self.vignette = vignette;
+        self
+    }
+
+    /// Consume `self` and set the `chromatic_aberration`.
+    pub fn with_chromatic_aberration(mut self, chromatic_aberration: Float) -> Self {
+        self.chromatic_aberration = chromatic_aberration;
+        self
+    }
+
+    /// Consume `self` and set the `distortion`.
+    pub fn with_distortion(mut self, distortion: Float) -> Self {
+        self.distortion = distortion;
+        self
+    }
+
+    /// Apply the effects to the `colors` and `alpha` of an image of `image_width` × `image_height` pixels.
+    pub(crate) fn apply(
+        &self,
+        colors: &mut [Color],
+        alpha: Option<&mut [Float]>,
+        image_width: u32,
+        image_height: u32,
+    ) {
+        let (width, height) = (image_width as usize, image_height as usize);
+        let center = ((width as Float - 1.) / 2., (height as Float - 1.) / 2.);
+        let radius = (center.0 * center.0 + center.1 * center.1).sqrt().max(0.5);
+        let magnifications = [
+            1. + self.chromatic_aberration,
+            1.,
+            1. - self.chromatic_aberration,
+        ];
+        let moves = self.chromatic_aberration != 0. || self.distortion != 0.;
+
+        // Where each pixel samples the image for a magnification, and how much it is darkened
+        let source = |index: usize, magnification: Float| {
+            let (dx, dy) = (
+                (index % width) as Float - center.0,
+                (index / width) as Float - center.1,
+            );
+            let r2 = (dx * dx + dy * dy) / (radius * radius);
+            let scale = (1. + self.distortion * r2) / magnification;
+            let falloff = 1. / (1. + self.vignette * r2).powi(2);
+            ((center.0 + dx * scale, center.1 + dy * scale), falloff)
+        };
+
+        let plane = Plane {
+            width,
+            height,
+            pixels: colors.to_vec(),
+        };
+        for (index, color) in colors.iter_mut().enumerate() {
+            *color = (0..3)
+                .map(|channel| {
+                    let ((x, y), falloff) = source(index, magnifications[channel as usize]);
+                    let value = match moves {
+                        true => plane.sample(x, y)[channel],
+                        false => plane.pixels[index][channel],
+                    };
+                    falloff * value
+                })
+                .collect();
+        }
+
+        if let (Some(alpha), true) = (alpha, moves) {
+            let plane = Plane {
+                width,
+                height,
+                pixels: alpha.iter().map(|&a| color![a, a, a]).collect(),
+            };
+            for (index, alpha) in alpha.iter_mut().enumerate() {
+                let ((x, y), _) = source(index, 1.);
+                *alpha = plane.sample(x, y).g();
+            }
+        }
+    }
+}
+
+/// Colors of an image, row by row from the top.
+struct Plane {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Plane {
+    /// The pixel (`x`, `y`), clamped to the edges.
+    fn pixel(&self, x: isize, y: isize) -> Color {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    /// Average blocks of 2 × 2 pixels.
+    fn downsample(&self) -> Plane {
+        let (width, height) = (self.width.div_ceil(2), self.height.div_ceil(2));
+        let pixels = (0..width * height)
+            .map(|index| {
+                let (x, y) = (
+                    (2 * (index % width)) as isize,
+                    (2 * (index / width)) as isize,
+                );
+                (self.pixel(x, y)
+                    + self.pixel(x + 1, y)
+                    + self.pixel(x, y + 1)
+                    + self.pixel(x + 1, y + 1))
+                    / 4.
+            })
+            .collect();
+        Plane {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Blur with the binomial kernel 1 4 6 4 1, first horizontally and then vertically.
+    fn blur(&self) -> Plane {
+        const KERNEL: [Float; 5] = [1. / 16., 4. / 16., 6. / 16., 4. / 16., 1. / 16.];
+        let pass = |plane: &Plane, (dx, dy): (isize, isize)| {
+            let pixels = (0..plane.pixels.len())
+                .map(|index| {
+                    let (x, y) = (
+                        (index % plane.width) as isize,
+                        (index / plane.width) as isize,
+                    );
+                    KERNEL
+                        .iter()
+                        .enumerate()
+                        .map(|(offset, weight)| {
+                            let offset = offset as isize - 2;
+                            *weight * plane.pixel(x + offset * dx, y + offset * dy)
+                        })
+                        .fold(BLACK, |sum, color| sum + color)
+                })
+                .collect();
+            Plane {
+                width: plane.width,
+                height: plane.height,
+                pixels,
+            }
+        };
+        pass(&pass(self, (1, 0)), (0, 1))
+    }
+
+    /// Bilinear interpolation at (`x`, `y`) in pixel coordinates, where the centers are at integers.
+    fn sample(&self, x: Float, y: Float) -> Color {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        let top = (1. - tx) * self.pixel(x0, y0) + tx * self.pixel(x0 + 1, y0);
+        let bottom = (1. - tx) * self.pixel(x0, y0 + 1) + tx * self.pixel(x0 + 1, y0 + 1);
+        (1. - ty) * top + ty * bottom
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::raytracer::RaytracedImage;
+
+    #[test]
+    fn bloom_spreads_highlights() {
+        let (width, height) = (16, 9);
+        let mut colors = vec![color![0.5, 0.5, 0.5]; width * height];
+        colors[4 * width + 8] = color![100., 80., 60.];
+        let image = RaytracedImage::new(colors, width as u32, height as u32)
+            .with_bloom(Bloom::default().with_levels(3));
+
+        // Dark pixels alone do not glow, but the neighbors of the highlight do, falling off with the distance.
+        let glow = |x: usize, y: usize| image.colors()[y * width + x].r() - 0.5;
+        assert!(glow(7, 4) > glow(4, 4) && glow(4, 4) > glow(0, 0));
+        assert!(glow(0, 0) >= 0.);
+        let hue = image.colors()[4 * width + 9];
+        assert!(hue.r() > hue.g() && hue.g() > hue.b());
+
+        let dark =
+            RaytracedImage::new(vec![color![0.5, 0.5, 0.5]; 4], 2, 2).with_bloom(Bloom::default());
+        assert_eq!(dark.colors(), &[color![0.5, 0.5, 0.5]; 4]);
+    }
+
+    #[test]
+    fn lens_effects() {
+        // A white square in the center of a black image
+        let (width, height) = (21, 21);
+        let colors = (0..width * height)
+            .map(|index: usize| {
+                match (index % width).abs_diff(10) <= 4 && (index / width).abs_diff(10) <= 4 {
+                    true => color![1., 1., 1.],
+                    false => BLACK,
+                }
+            })
+            .collect();
+        let image = RaytracedImage::new(colors, width as u32, height as u32);
+        let image = image.with_lens_effects(LensEffects::default().with_chromatic_aberration(0.1));
+
+        // The red channel is magnified, so the square is larger in red and smaller in blue.
+        let (outside, inside) = (
+            image.colors()[10 * width + 15],
+            image.colors()[10 * width + 14],
+        );
+        assert!(
+            outside.r() > 0.4 && outside.g() == 0. && outside.b() == 0.,
+            "{outside:?}"
+        );
+        assert!(inside.b() < 0.6 && inside.g() == 1., "{inside:?}");
+        assert_eq!(image.colors()[10 * width + 10], color![1., 1., 1.]);
+    }
+}
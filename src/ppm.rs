@@ -14,12 +14,12 @@ use crate::color::Color;
 /// - `image_height`: Height of the image
 pub struct PPM {
     colors: Vec<Color>,
-    image_width: u16,
-    image_height: u16,
+    image_width: u32,
+    image_height: u32,
 }
 
 impl PPM {
-    pub fn new(colors: Vec<Color>, image_width: u16, image_height: u16) -> Self {
+    pub fn new(colors: Vec<Color>, image_width: u32, image_height: u32) -> Self {
         Self {
             colors,
             image_width,
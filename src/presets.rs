@@ -0,0 +1,473 @@
+//! Named render presets, e.g. a quick `preview` and a high quality `final`, so programs do not hard-code resolutions and sample counts.
+//!
+//! Presets are read from a TOML profile with one table per preset. Keys that are left out keep the values of the built-in preset of the same name, or of `final`:
+//! ```toml
+//! [preview]
+//! resolution = [480, 270]
+//! samples_per_pixel = 4
+//! max_depth = 8
+//! integrator = "path_tracer"
+//! filter = "box"
+//! clamp = 10.0
+//!
+//! [print]
+//! resolution = [4961, 3508]  # A4 at 600 dpi
+//! samples_per_pixel = 2048
+//! exposure = 0.5
+//! gamma = 2.2
+//! dithering = "blue_noise"
+//! ```
+//! Programs can select a preset by name on the command line with [`RenderPresets::from_args`].
+//! Only this subset of TOML is supported: tables, comments, and values that are integers, floats, booleans, strings, or arrays of numbers.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::background::Background;
+use crate::dithering::Dithering;
+use crate::error::Error;
+use crate::filter::Filter;
+use crate::integrator::{Integrator, IntegratorSettings};
+use crate::raytracer::RaytracedImage;
+use crate::scenes::SceneSettings;
+use crate::*;
+
+/// Settings of a render that are independent of the scene.
+///
+/// # Fields
+/// - `image_width`, `image_height`: Resolution of the image.
+/// - `samples_per_pixel`, `max_depth`: Samples per pixel and maximum depth of the paths.
+/// - `exposure`, `white_point`, `gamma`: Tone mapping of the displayable image, see [`RaytracedImage::with_exposure`].
+/// - `dithering`: [`Dithering`] of 8 bit images, `none`, `ordered`, or `blue_noise` in a profile.
+/// - `integrator`: The [`Integrator`], `path_tracer`, `bdpt`, or `whitted` in a profile.
+/// - `filter`: The reconstruction [`Filter`], `box`, `triangle`, `gaussian`, `mitchell_netravali`, or `blackman_harris` in a profile.
+/// - `integrator_settings`: Russian roulette and clamping (`russian_roulette = [depth, probability]` and `clamp = radiance` in a profile).
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, presets::RenderPreset};
+/// let preset = RenderPreset::named("preview").unwrap();
+/// let raytracer = preset.raytracer(Camera::default(), color![0.5, 0.7, 1.]);
+/// assert_eq!(raytracer.image_size(), (preset.image_width, preset.image_height));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderPreset {
+    pub image_width: u32,
+    pub image_height: u32,
+    pub samples_per_pixel: u16,
+    pub max_depth: u16,
+    pub exposure: Float,
+    pub white_point: Float,
+    pub gamma: Float,
+    pub dithering: Dithering,
+    pub integrator: Integrator,
+    pub filter: Filter,
+    pub integrator_settings: IntegratorSettings,
+}
+
+impl Default for RenderPreset {
+    fn default() -> Self {
+        Self::final_quality()
+    }
+}
+
+impl RenderPreset {
+    /// 480 × 270 pixels with 4 samples per pixel, Russian roulette, and clamping to judge a scene quickly.
+    pub fn preview() -> Self {
+        Self {
+            image_width: 480,
+            image_height: 270,
+            samples_per_pixel: 4,
+            max_depth: 8,
+            integrator_settings: IntegratorSettings::preview(),
+            ..Self::final_quality()
+        }
+    }
+
+    /// 1920 × 1080 pixels with 256 samples per pixel and a Mitchell-Netravali filter.
+    pub fn final_quality() -> Self {
+        Self {
+            image_width: 1920,
+            image_height: 1080,
+            samples_per_pixel: 256,
+            max_depth: 50,
+            exposure: 0.,
+            white_point: 1.,
+            gamma: 2.,
+            dithering: Dithering::None,
+            integrator: Integrator::PathTracer,
+            filter: mitchell_netravali(),
+            integrator_settings: IntegratorSettings::new(),
+        }
+    }
+
+    /// 3840 × 2160 pixels with 1024 samples per pixel, e.g. for printing.
+    pub fn print() -> Self {
+        Self {
+            image_width: 3840,
+            image_height: 2160,
+            samples_per_pixel: 1024,
+            ..Self::final_quality()
+        }
+    }
+
+    /// The built-in preset `preview`, `final`, or `print`.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "preview" => Some(Self::preview()),
+            "final" => Some(Self::final_quality()),
+            "print" => Some(Self::print()),
+            _ => None,
+        }
+    }
+
+    /// Aspect ratio of the image, e.g. for the [`Camera`].
+    pub fn aspect_ratio(&self) -> Float {
+        self.image_width as Float / self.image_height as Float
+    }
+
+    /// A [`Raytracer`] with the settings of the preset and an empty world.
+    pub fn raytracer(&self, camera: Camera, background: impl Into<Background>) -> Raytracer {
+        Raytracer::new(
+            camera,
+            background,
+            self.image_width,
+            self.image_height,
+            self.samples_per_pixel,
+            self.max_depth,
+        )
+        .with_integrator(self.integrator)
+        .with_integrator_settings(self.integrator_settings)
+        .with_filter(self.filter)
+    }
+
+    /// Settings for the [example scenes](crate::scenes).
+    pub fn scene_settings(&self) -> SceneSettings {
+        SceneSettings::new(self.image_width, self.image_height)
+            .with_samples(self.samples_per_pixel, self.max_depth)
+    }
+
+    /// Consume `image` and apply the tone mapping and dithering of the preset.
+    pub fn tone_map(&self, image: RaytracedImage) -> RaytracedImage {
+        image
+            .with_exposure(self.exposure)
+            .with_white_point(self.white_point)
+            .with_gamma(self.gamma)
+            .with_dithering(self.dithering)
+    }
+
+    fn set(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        match key {
+            "resolution" => {
+                let [width, height] = value.numbers::<2>()?;
+                self.image_width = integer(width)?;
+                self.image_height = integer(height)?;
+            }
+            "samples_per_pixel" => self.samples_per_pixel = integer(value.number()?)?,
+            "max_depth" => self.max_depth = integer(value.number()?)?,
+            "exposure" => self.exposure = value.number()?,
+            "white_point" => self.white_point = value.number()?,
+            "gamma" => self.gamma = value.number()?,
+            "dithering" => {
+                self.dithering = match value.string()? {
+                    "none" => Dithering::None,
+                    "ordered" => Dithering::Ordered,
+                    "blue_noise" => Dithering::BlueNoise,
+                    other => return Err(format!("unknown dithering {other:?}")),
+                }
+            }
+            "integrator" => {
+                self.integrator = match value.string()? {
+                    "path_tracer" => Integrator::PathTracer,
+                    "bdpt" => Integrator::Bdpt,
+                    "whitted" => Integrator::Whitted,
+                    other => return Err(format!("unknown integrator {other:?}")),
+                }
+            }
+            "filter" => {
+                self.filter = match value.string()? {
+                    "box" => Filter::Box,
+                    "triangle" => Filter::Triangle { radius: 1. },
+                    "gaussian" => Filter::Gaussian {
+                        radius: 1.5,
+                        alpha: 2.,
+                    },
+                    "mitchell_netravali" => mitchell_netravali(),
+                    "blackman_harris" => Filter::BlackmanHarris { radius: 1.5 },
+                    other => return Err(format!("unknown filter {other:?}")),
+                }
+            }
+            "russian_roulette" => {
+                let [depth, probability] = value.numbers::<2>()?;
+                self.integrator_settings = self
+                    .integrator_settings
+                    .with_russian_roulette(integer(depth)?, probability);
+            }
+            "clamp" => {
+                self.integrator_settings =
+                    self.integrator_settings.with_max_radiance(value.number()?)
+            }
+            _ => return Err(format!("unknown key {key:?}")),
+        }
+        Ok(())
+    }
+}
+
+fn mitchell_netravali() -> Filter {
+    Filter::MitchellNetravali {
+        radius: 2.,
+        b: 1. / 3.,
+        c: 1. / 3.,
+    }
+}
+
+/// The presets of a TOML profile together with the built-in ones.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, presets::RenderPresets};
+/// let presets = RenderPresets::parse(r#"
+/// [draft]
+/// resolution = [320, 180]
+/// samples_per_pixel = 1
+/// "#).unwrap();
+/// let draft = presets.get("draft").unwrap();
+/// assert_eq!((draft.image_width, draft.samples_per_pixel), (320, 1));
+/// // Built-in presets are still available unless the profile overrides them.
+/// assert_eq!(presets.get("print").unwrap().samples_per_pixel, 1024);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderPresets {
+    presets: HashMap<String, RenderPreset>,
+}
+
+impl RenderPresets {
+    /// Read the presets from a TOML file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse the presets from a TOML profile.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut presets = HashMap::new();
+        let mut current: Option<(String, RenderPreset)> = None;
+        for (number, line) in text.lines().enumerate() {
+            let invalid = |message: String| {
+                Error::Config(format!(
+                    "invalid preset profile: line {}: {message}",
+                    number + 1
+                ))
+            };
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                presets.extend(current.take());
+                let name = name.trim().trim_matches('"').to_string();
+                let preset = RenderPreset::named(&name).unwrap_or_default();
+                current = Some((name, preset));
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(invalid(format!("expected `key = value`, found {line:?}")));
+            };
+            let Some((_, preset)) = &mut current else {
+                return Err(invalid("keys have to be inside of a [preset]".to_string()));
+            };
+            let value = Value::parse(value.trim()).map_err(invalid)?;
+            preset.set(key.trim(), &value).map_err(invalid)?;
+        }
+        presets.extend(current);
+        Ok(Self { presets })
+    }
+
+    /// The preset `name` from the profile, or the built-in one (see [`RenderPreset::named`]).
+    pub fn get(&self, name: &str) -> Option<RenderPreset> {
+        self.presets
+            .get(name)
+            .copied()
+            .or_else(|| RenderPreset::named(name))
+    }
+
+    /// Names of the presets of the profile.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.presets.keys().map(String::as_str)
+    }
+
+    /// Select a preset from command line arguments (without the program name), e.g. [`std::env::args().skip(1)`](std::env::args).
+    ///
+    /// `--preset <name>` selects the preset (`final` by default) and `--presets <file>` reads a profile. Other arguments are ignored.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, presets::RenderPresets};
+    /// let preset = RenderPresets::from_args(["--preset", "preview"].map(String::from)).unwrap();
+    /// assert_eq!(preset.samples_per_pixel, 4);
+    /// assert!(RenderPresets::from_args(["--preset", "unknown"].map(String::from)).is_err());
+    /// ```
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<RenderPreset, Error> {
+        let mut name = "final".to_string();
+        let mut presets = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let flag = arg.as_str();
+            if !matches!(flag, "--preset" | "--presets") {
+                continue;
+            }
+            let Some(value) = args.next() else {
+                return Err(Error::Config(format!("{flag} needs a value")));
+            };
+            match flag {
+                "--preset" => name = value,
+                _ => presets = Self::open(value)?,
+            }
+        }
+        presets
+            .get(&name)
+            .ok_or_else(|| Error::Config(format!("unknown preset {name:?}")))
+    }
+}
+
+/// A value of the supported subset of TOML.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Number(Float),
+    Boolean(bool),
+    String(String),
+    Array(Vec<Float>),
+}
+
+impl Value {
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        if let Some(string) = text
+            .strip_prefix('"')
+            .and_then(|text| text.strip_suffix('"'))
+        {
+            return Ok(Value::String(string.to_string()));
+        }
+        if let Some(array) = text
+            .strip_prefix('[')
+            .and_then(|text| text.strip_suffix(']'))
+        {
+            return array
+                .split(',')
+                .map(str::trim)
+                .filter(|element| !element.is_empty())
+                .map(parse_number)
+                .collect::<Result<_, _>>()
+                .map(Value::Array);
+        }
+        match text {
+            "true" => Ok(Value::Boolean(true)),
+            "false" => Ok(Value::Boolean(false)),
+            _ => parse_number(text).map(Value::Number),
+        }
+    }
+
+    pub(crate) fn number(&self) -> Result<Float, String> {
+        match self {
+            Value::Number(number) => Ok(*number),
+            other => Err(format!("expected a number, found {other:?}")),
+        }
+    }
+
+    pub(crate) fn numbers<const N: usize>(&self) -> Result<[Float; N], String> {
+        match self {
+            Value::Array(numbers) => numbers
+                .as_slice()
+                .try_into()
+                .map_err(|_| format!("expected {N} numbers, found {}", numbers.len())),
+            other => Err(format!("expected an array, found {other:?}")),
+        }
+    }
+
+    pub(crate) fn string(&self) -> Result<&str, String> {
+        match self {
+            Value::String(string) => Ok(string),
+            other => Err(format!("expected a string, found {other:?}")),
+        }
+    }
+}
+
+fn parse_number(text: &str) -> Result<Float, String> {
+    text.replace('_', "")
+        .parse()
+        .map_err(|_| format!("invalid number {text:?}"))
+}
+
+/// A non-negative integer that fits into `T`.
+fn integer<T: TryFrom<u64>>(number: Float) -> Result<T, String> {
+    if number < 0. || number.fract() != 0. {
+        return Err(format!("expected a non-negative integer, found {number}"));
+    }
+    T::try_from(number as u64).map_err(|_| format!("{number} is too large"))
+}
+
+/// The line without a comment, ignoring `#` inside of strings.
+pub(crate) fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn profile() {
+        let presets = RenderPresets::parse(
+            r#"
+# Overrides the built-in preview
+[preview]
+resolution = [640, 360]  # 16:9
+integrator = "bdpt"
+filter = "gaussian"
+russian_roulette = [2, 0.5]
+
+["final"]
+exposure = -0.5
+dithering = "blue_noise"
+"#,
+        )
+        .unwrap();
+        let preview = presets.get("preview").unwrap();
+        assert_eq!((preview.image_width, preview.image_height), (640, 360));
+        // Unset keys keep the values of the built-in preset of the same name.
+        assert_eq!(preview.samples_per_pixel, 4);
+        assert_eq!(preview.integrator, Integrator::Bdpt);
+        assert_eq!(
+            preview.integrator_settings.russian_roulette_depth(),
+            Some(2)
+        );
+        assert_eq!(presets.get("final").unwrap().exposure, -0.5);
+        assert_eq!(
+            presets.get("final").unwrap().dithering,
+            Dithering::BlueNoise
+        );
+
+        for (profile, message) in [
+            ("samples_per_pixel = 4", "inside of a [preset]"),
+            ("[a]\nsamples = 4", "unknown key"),
+            ("[a]\nmax_depth = 1.5", "integer"),
+            ("[a]\nsamples_per_pixel = 100000", "too large"),
+            ("[a]\nresolution = [1, 2, 3]", "2 numbers"),
+        ] {
+            let Err(Error::Config(error)) = RenderPresets::parse(profile) else {
+                panic!("{profile:?} should be invalid");
+            };
+            assert!(error.contains(message), "{error}");
+        }
+    }
+}
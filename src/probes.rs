@@ -0,0 +1,322 @@
+//! Light probes that store the light arriving at points of a scene, e.g. for the indirect lighting and reflections of moving objects in a game.
+//!
+//! [`Raytracer::bake_sh_probes`] projects the incoming radiance onto spherical harmonics up to the second band (9 coefficients per color channel), which is how most engines store irradiance probes.
+//! [`Raytracer::bake_cube_maps`] renders small cube maps instead, e.g. for reflection probes.
+//! The probes only see the world, so they should lie outside of all objects.
+
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use rand::Rng;
+
+use crate::baking::Baker;
+use crate::color::BLACK;
+use crate::error::Error;
+use crate::integrator::{path_trace, Scene};
+use crate::platform::*;
+use crate::progress::RenderProgress;
+use crate::ray::{Ray, RayKind};
+use crate::raytracer::RaytracedImage;
+use crate::vec3::random_unit_vector;
+use crate::*;
+
+/// Number of spherical harmonics up to the second band.
+pub const SH_COEFFICIENTS: usize = 9;
+
+/// Convolution of the first three bands with the clamped cosine, to turn radiance into irradiance.
+#[allow(clippy::unnecessary_cast)]
+const COSINE_BANDS: [Float; 3] = [PI as Float, (2. * PI / 3.) as Float, (PI / 4.) as Float];
+
+/// Real spherical harmonics up to the second band in the direction of the unit vector `direction`.
+fn sh_basis(direction: Vector3<Float>) -> [Float; SH_COEFFICIENTS] {
+    let (x, y, z) = (direction.x, direction.y, direction.z);
+    [
+        0.282_095,
+        0.488_603 * y,
+        0.488_603 * z,
+        0.488_603 * x,
+        1.092_548 * x * y,
+        1.092_548 * y * z,
+        0.315_392 * (3. * z * z - 1.),
+        1.092_548 * x * z,
+        0.546_274 * (x * x - y * y),
+    ]
+}
+
+/// Band of the spherical harmonic with index `index`.
+fn sh_band(index: usize) -> usize {
+    match index {
+        0 => 0,
+        1..=3 => 1,
+        _ => 2,
+    }
+}
+
+/// A light probe with the incoming radiance as spherical harmonics.
+///
+/// The coefficients are ordered by band and then from the lowest to highest order, i.e. `Y00, Y1-1, Y10, Y11, Y2-2, Y2-1, Y20, Y21, Y22`, for the real spherical harmonics in world space.
+///
+/// # Fields
+/// - `position`: Where the probe was placed.
+/// - `coefficients`: Coefficients of the incoming radiance for all three color channels.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::*;
+/// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 1, 1, 64, 4);
+///
+/// let probes = raytracer.bake_sh_probes(&[vector![0., 0., 0.]]).unwrap();
+/// // Every direction contributes the sky color to the constant term, which is √(4π) times the average radiance.
+/// let average = probes[0].coefficients[0] / (4. * std::f64::consts::PI as Float).sqrt();
+/// assert!((average.r() - 0.5).abs() < 1e-4 && (average.b() - 1.).abs() < 1e-4);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShProbe {
+    pub position: Vector3<Float>,
+    pub coefficients: [Color; SH_COEFFICIENTS],
+}
+
+impl ShProbe {
+    /// Radiance arriving at the probe from `direction`.
+    pub fn radiance(&self, direction: Vector3<Float>) -> Color {
+        let basis = sh_basis(direction.normalize());
+        self.coefficients
+            .iter()
+            .zip(basis)
+            .fold(BLACK, |radiance, (&coefficient, basis)| {
+                radiance + coefficient * basis
+            })
+    }
+
+    /// Irradiance at the probe on a surface with the normal `normal`.
+    ///
+    /// Divide by π for the light reflected by a white diffuse surface.
+    pub fn irradiance(&self, normal: Vector3<Float>) -> Color {
+        let basis = sh_basis(normal.normalize());
+        self.coefficients.iter().zip(basis).enumerate().fold(
+            BLACK,
+            |irradiance, (index, (&coefficient, basis))| {
+                irradiance + coefficient * (basis * COSINE_BANDS[sh_band(index)])
+            },
+        )
+    }
+}
+
+/// Write `probes` as text, one probe per line with the position followed by the red, green, and blue value of each coefficient.
+pub fn write_sh_probes<W: Write>(probes: &[ShProbe], mut writer: W) -> io::Result<()> {
+    writeln!(
+        writer,
+        "# x y z, then r g b of {SH_COEFFICIENTS} spherical harmonics coefficients"
+    )?;
+    for probe in probes {
+        write!(
+            writer,
+            "{} {} {}",
+            probe.position.x, probe.position.y, probe.position.z
+        )?;
+        for coefficient in probe.coefficients {
+            write!(
+                writer,
+                " {} {} {}",
+                coefficient.r(),
+                coefficient.g(),
+                coefficient.b()
+            )?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// Save `probes` to `path` in the format of [`write_sh_probes`].
+pub fn save_sh_probes<P: AsRef<Path>>(probes: &[ShProbe], path: P) -> Result<(), Error> {
+    write_sh_probes(probes, BufWriter::new(File::create(path)?))?;
+    Ok(())
+}
+
+/// A light probe with the incoming radiance as a cube map.
+///
+/// The faces are ordered `+x, -x, +y, -y, +z, -z` and oriented like OpenGL cube maps, in world space.
+///
+/// # Fields
+/// - `position`: Where the probe was placed.
+/// - `size`: Width and height of each face.
+/// - `faces`: Colors of the faces, row by row from the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeMap {
+    pub position: Vector3<Float>,
+    pub size: u32,
+    pub faces: [Vec<Color>; 6],
+}
+
+impl CubeMap {
+    /// Unit direction through the texel at (`column`, `row`) of `face`, offset by (`dx`, `dy`) within the texel.
+    fn direction(
+        size: u32,
+        face: usize,
+        column: u32,
+        row: u32,
+        (dx, dy): (Float, Float),
+    ) -> Vector3<Float> {
+        let s = 2. * (column as Float + dx) / size as Float - 1.;
+        let t = 2. * (row as Float + dy) / size as Float - 1.;
+        match face {
+            0 => vector![1., -t, -s],
+            1 => vector![-1., -t, s],
+            2 => vector![s, 1., t],
+            3 => vector![s, -1., -t],
+            4 => vector![s, -t, 1.],
+            _ => vector![-s, -t, -1.],
+        }
+        .normalize()
+    }
+
+    /// Convert the cube map into an image with the faces side by side, which most engines can import as a cube map.
+    pub fn into_strip(self) -> RaytracedImage {
+        let size = self.size as usize;
+        let mut colors = Vec::with_capacity(6 * size * size);
+        for row in 0..size {
+            for face in &self.faces {
+                colors.extend_from_slice(&face[row * size..(row + 1) * size]);
+            }
+        }
+        RaytracedImage::new(colors, 6 * self.size, self.size)
+    }
+}
+
+impl Baker<'_> {
+    /// Project the light arriving at each of `positions` onto spherical harmonics.
+    pub(crate) fn sh_probes(
+        &self,
+        scene: &Scene,
+        positions: &[Vector3<Float>],
+        progress: Option<&dyn RenderProgress>,
+    ) -> Vec<ShProbe> {
+        if let Some(progress) = progress {
+            progress.start(positions.len() as u64);
+        }
+        positions
+            .par_iter()
+            .map(|&position| {
+                let mut coefficients = [BLACK; SH_COEFFICIENTS];
+                for _ in 0..self.samples_per_pixel {
+                    let direction = random_unit_vector();
+                    let radiance = self.trace(scene, position, direction);
+                    for (coefficient, basis) in coefficients.iter_mut().zip(sh_basis(direction)) {
+                        *coefficient += radiance * basis;
+                    }
+                }
+                // Monte Carlo estimate with the uniform density 1 / 4π on the sphere
+                #[allow(clippy::unnecessary_cast)]
+                let weight = 4. * PI as Float / self.samples_per_pixel as Float;
+                if let Some(progress) = progress {
+                    progress.advance(1);
+                }
+                ShProbe {
+                    position,
+                    coefficients: coefficients.map(|coefficient| coefficient * weight),
+                }
+            })
+            .collect()
+    }
+
+    /// Render a cube map with faces of `size` at each of `positions`.
+    pub(crate) fn cube_maps(
+        &self,
+        scene: &Scene,
+        positions: &[Vector3<Float>],
+        size: u32,
+        progress: Option<&dyn RenderProgress>,
+    ) -> Vec<CubeMap> {
+        let texels = (size * size) as usize;
+        if let Some(progress) = progress {
+            progress.start((positions.len() * 6 * texels) as u64);
+        }
+        positions
+            .iter()
+            .map(|&position| {
+                let colors: Vec<Color> = (0..6 * texels)
+                    .into_par_iter()
+                    .map(|index| {
+                        let (face, texel) = (index / texels, (index % texels) as u32);
+                        let mut rng = crate::random::rng();
+                        let mut radiance = BLACK;
+                        for _ in 0..self.samples_per_pixel {
+                            let offset = (rng.gen(), rng.gen());
+                            let direction =
+                                CubeMap::direction(size, face, texel % size, texel / size, offset);
+                            radiance += self.trace(scene, position, direction);
+                        }
+                        if let Some(progress) = progress {
+                            progress.advance(1);
+                        }
+                        radiance / self.samples_per_pixel as Float
+                    })
+                    .collect();
+                let mut faces = colors.chunks_exact(texels).map(<[Color]>::to_vec);
+                CubeMap {
+                    position,
+                    size,
+                    faces: std::array::from_fn(|_| faces.next().expect("six faces")),
+                }
+            })
+            .collect()
+    }
+
+    /// Radiance arriving at `position` from `direction`.
+    fn trace(&self, scene: &Scene, position: Vector3<Float>, direction: Vector3<Float>) -> Color {
+        let ray = Ray::new(position, direction).with_kind(RayKind::Indirect);
+        self.settings
+            .clamp(path_trace(scene, ray, self.max_depth, self.settings))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cube_map_layout() {
+        // The centers of the faces point along the axes.
+        let axes = [
+            vector![1., 0., 0.],
+            vector![-1., 0., 0.],
+            vector![0., 1., 0.],
+            vector![0., -1., 0.],
+            vector![0., 0., 1.],
+            vector![0., 0., -1.],
+        ];
+        for (face, axis) in axes.into_iter().enumerate() {
+            assert!((CubeMap::direction(2, face, 1, 1, (0., 0.)) - axis).norm() < 1e-6);
+        }
+        // The top row of the side faces looks up.
+        assert!(CubeMap::direction(2, 4, 0, 0, (0.5, 0.5)).y > 0.);
+
+        let color = |face: usize| Color::new(face as Float, face as Float, face as Float);
+        let cube_map = CubeMap {
+            position: vector![0., 0., 0.],
+            size: 2,
+            faces: std::array::from_fn(|face| vec![color(face); 4]),
+        };
+        let strip = cube_map.into_strip();
+        assert_eq!(strip.dimensions(), (12, 2));
+        assert_eq!(strip.colors()[13], color(0));
+        assert_eq!(strip.colors()[23], color(5));
+
+        // A constant radiance of 1 gives an irradiance of π.
+        let mut coefficients = [BLACK; SH_COEFFICIENTS];
+        #[allow(clippy::unnecessary_cast)]
+        let constant = (4. * PI).sqrt() as Float;
+        coefficients[0] = Color::new(constant, constant, constant);
+        let probe = ShProbe {
+            position: vector![0., 0., 0.],
+            coefficients,
+        };
+        assert!((probe.radiance(vector![0., 0., 1.]).r() - 1.).abs() < 1e-4);
+        #[allow(clippy::unnecessary_cast)]
+        let pi = PI as Float;
+        assert!((probe.irradiance(vector![0., 1., 0.]).r() - pi).abs() < 1e-3);
+    }
+}
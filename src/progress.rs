@@ -0,0 +1,114 @@
+//! Reporting the progress of a render, e.g. to a progress bar in the terminal or a GUI.
+//!
+//! A [`Raytracer`] reports to the [`RenderProgress`] given to [`with_progress`](Raytracer::with_progress).
+//! With the `progressbar` feature, [`indicatif::ProgressBar`] implements it (see [`with_progressbar`](Raytracer::with_progressbar)).
+//! Embedders that need no output can poll a [`ProgressCounter`] instead.
+
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::*;
+
+/// Something that is notified about the progress of a render.
+///
+/// The work is counted in pixel samples (or, for some integrators, pixels), and [`advance`](RenderProgress::advance) is called from several threads at once.
+pub trait RenderProgress: Debug + Send + Sync {
+    /// The render starts with `total` units of work.
+    fn start(&self, total: u64) {
+        let _ = total;
+    }
+
+    /// `delta` more units of work are done.
+    fn advance(&self, delta: u64);
+
+    /// The render is done.
+    fn finish(&self) {}
+}
+
+impl<P: RenderProgress + ?Sized> RenderProgress for Arc<P> {
+    fn start(&self, total: u64) {
+        self.as_ref().start(total);
+    }
+
+    fn advance(&self, delta: u64) {
+        self.as_ref().advance(delta);
+    }
+
+    fn finish(&self) {
+        self.as_ref().finish();
+    }
+}
+
+#[cfg(feature = "progressbar")]
+impl RenderProgress for indicatif::ProgressBar {
+    fn start(&self, total: u64) {
+        self.set_length(total);
+    }
+
+    fn advance(&self, delta: u64) {
+        self.inc(delta);
+    }
+
+    fn finish(&self) {
+        indicatif::ProgressBar::finish(self);
+    }
+}
+
+/// A [`RenderProgress`] that only counts, so another thread can poll it.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, progress::ProgressCounter};
+/// # use std::sync::Arc;
+/// let counter = Arc::new(ProgressCounter::default());
+/// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 8, 4, 2, 4).with_progress(counter.clone());
+/// raytracer.render().unwrap();
+/// assert_eq!(counter.fraction(), 1.);
+/// assert!(counter.is_finished());
+/// ```
+#[derive(Debug, Default)]
+pub struct ProgressCounter {
+    total: AtomicU64,
+    done: AtomicU64,
+    finished: AtomicBool,
+}
+
+impl ProgressCounter {
+    /// Units of work done so far and in total.
+    pub fn progress(&self) -> (u64, u64) {
+        (
+            self.done.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Fraction of the work that is done, between 0 and 1.
+    pub fn fraction(&self) -> Float {
+        match self.progress() {
+            (_, 0) => 0.,
+            (done, total) => (done as Float / total as Float).min(1.),
+        }
+    }
+
+    /// Whether the render has finished.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+}
+
+impl RenderProgress for ProgressCounter {
+    fn start(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+        self.done.store(0, Ordering::Relaxed);
+        self.finished.store(false, Ordering::Relaxed);
+    }
+
+    fn advance(&self, delta: u64) {
+        self.done.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,97 @@
+//! Random numbers of the renderer.
+//!
+//! Every thread draws from [`rand::thread_rng`], unless it works inside [`seeded`].
+//! A render with a seed runs each pixel (and bounce of the wavefront) inside [`seeded`] with a generator derived from the seed and the indices of the work item, so the samples do not depend on how rayon distributes the work over the threads.
+//! Work that is split with [`join`] passes generators on to both halves.
+
+use std::cell::RefCell;
+
+use rand::distributions::{Distribution, Standard};
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+
+use crate::platform;
+
+thread_local! {
+    static SEEDED: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+/// Handle to the random number generator of the current thread.
+pub(crate) struct RenderRng;
+
+impl RngCore for RenderRng {
+    fn next_u32(&mut self) -> u32 {
+        SEEDED.with_borrow_mut(|seeded| match seeded {
+            Some(rng) => rng.next_u32(),
+            None => rand::thread_rng().next_u32(),
+        })
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        SEEDED.with_borrow_mut(|seeded| match seeded {
+            Some(rng) => rng.next_u64(),
+            None => rand::thread_rng().next_u64(),
+        })
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        SEEDED.with_borrow_mut(|seeded| match seeded {
+            Some(rng) => rng.fill_bytes(dest),
+            None => rand::thread_rng().fill_bytes(dest),
+        })
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// The random number generator of the current thread.
+pub(crate) fn rng() -> RenderRng {
+    RenderRng
+}
+
+/// A random value from the generator of the current thread, like [`rand::random`].
+pub(crate) fn random<T>() -> T
+where
+    Standard: Distribution<T>,
+{
+    rng().gen()
+}
+
+/// Run `f` with the generator of the current thread seeded from `seed` and `stream`, or unchanged if `seed` is `None`.
+pub(crate) fn seeded<R>(seed: Option<u64>, stream: &[u64], f: impl FnOnce() -> R) -> R {
+    let Some(seed) = seed else {
+        return f();
+    };
+    // SplitMix64 finalizer, so that neighboring pixels get unrelated generators.
+    let mix = |mut z: u64| {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    let state = stream.iter().fold(mix(seed), |state, &value| {
+        mix(state ^ value.wrapping_add(0x9e37_79b9_7f4a_7c15))
+    });
+    let previous = SEEDED.replace(Some(StdRng::seed_from_u64(state)));
+    let result = f();
+    SEEDED.set(previous);
+    result
+}
+
+/// Run `a` and `b` like [`join`](crate::platform::join), but if the current thread is [`seeded`], each of them with its own generator drawn from it.
+pub(crate) fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    let seeds = SEEDED
+        .with_borrow_mut(|seeded| seeded.as_mut().map(|rng| (rng.next_u64(), rng.next_u64())));
+    platform::join(
+        || seeded(seeds.map(|seeds| seeds.0), &[], a),
+        || seeded(seeds.map(|seeds| seeds.1), &[], b),
+    )
+}
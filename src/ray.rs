@@ -2,40 +2,88 @@
 
 use nalgebra::Vector3;
 
+use crate::Float;
+
+/// Two auxiliary [`Ray`]s offset by one pixel in x and y direction of the image.
+///
+/// Where they hit the surface shows how large the footprint of a pixel is there, which [textures](crate::textures::ImageTexture) use to choose how much to blur.
+///
+/// # Fields
+/// - `x_origin`, `x_direction`: The [`Ray`] of the neighboring pixel in x direction.
+/// - `y_origin`, `y_direction`: The [`Ray`] of the neighboring pixel in y direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayDifferentials {
+    pub x_origin: Vector3<Float>,
+    pub x_direction: Vector3<Float>,
+    pub y_origin: Vector3<Float>,
+    pub y_direction: Vector3<Float>,
+}
+
+/// What an integrator traces a [`Ray`] for, which decides which objects it can hit (see [`Visible`](crate::hittable::Visible)).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RayKind {
+    /// A ray from the camera, or one continued through a [`Null`](crate::materials::Null) material.
+    Camera,
+    /// A ray testing whether a light is visible from a point.
+    Shadow,
+    /// A ray reflected or refracted by a specular material, e.g. a [`Metal`](crate::materials::Metal) or [`Dielectric`](crate::materials::Dielectric).
+    Specular,
+    /// A ray scattered into a random direction by any other material, or emitted by a light.
+    Indirect,
+}
+
 /// A ray starting at `origin` at `time` pointing in `direction`.
 ///
 /// # Fields
 /// - `origin`: Point of emission.
 /// - `direction`: direction.
 /// - `time`: time.
+/// - `differentials`: [`RayDifferentials`] of camera rays and their specular bounces.
+/// - `kind`: What the ray is traced for. Rays without a [`RayKind`] hit every object.
 #[derive(Clone, Copy)]
 pub struct Ray {
-    origin: Vector3<f32>,
-    direction: Vector3<f32>,
-    time: f32,
+    origin: Vector3<Float>,
+    direction: Vector3<Float>,
+    time: Float,
+    differentials: Option<RayDifferentials>,
+    kind: Option<RayKind>,
 }
 
 impl Ray {
     /// Create a ray without a time.
-    pub fn new(origin: Vector3<f32>, direction: Vector3<f32>) -> Self {
+    pub fn new(origin: Vector3<Float>, direction: Vector3<Float>) -> Self {
         Self {
             origin,
             direction,
             time: 0.,
+            differentials: None,
+            kind: None,
         }
     }
 
     /// Consume `self` and creates a [Ray] with a time.
-    pub fn with_time(mut self, time: f32) -> Self {
+    pub fn with_time(mut self, time: Float) -> Self {
         self.time = time;
         self
     }
 
-    pub fn origin(&self) -> Vector3<f32> {
+    /// Consume `self` and set the [`RayDifferentials`].
+    pub fn with_differentials(mut self, differentials: RayDifferentials) -> Self {
+        self.differentials = Some(differentials);
+        self
+    }
+
+    /// Consume `self` and set the [`RayKind`].
+    pub fn with_kind(mut self, kind: RayKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub fn origin(&self) -> Vector3<Float> {
         self.origin
     }
 
-    pub fn direction(&self) -> Vector3<f32> {
+    pub fn direction(&self) -> Vector3<Float> {
         self.direction
     }
 
@@ -49,11 +97,34 @@ impl Ray {
     /// let ray = Ray::new(origin, direction);
     /// assert_eq!(ray.at(1.), vector![1., 1., 0.]);
     /// ```
-    pub fn at(&self, t: f32) -> Vector3<f32> {
+    pub fn at(&self, t: Float) -> Vector3<Float> {
         self.origin + t * self.direction
     }
 
-    pub fn time(&self) -> f32 {
+    pub fn time(&self) -> Float {
         self.time
     }
+
+    pub fn differentials(&self) -> Option<RayDifferentials> {
+        self.differentials
+    }
+
+    pub fn kind(&self) -> Option<RayKind> {
+        self.kind
+    }
+
+    /// Consume `self` and move its origin by `offset`, keeping everything else (e.g. to leave a surface).
+    pub(crate) fn moved(mut self, offset: Vector3<Float>) -> Self {
+        self.origin += offset;
+        self
+    }
+
+    /// A [`Ray`] with the same direction, time, and [`RayKind`] starting at `origin`, e.g. to continue behind a surface.
+    pub(crate) fn continued(&self, origin: Vector3<Float>) -> Self {
+        Self {
+            origin,
+            differentials: None,
+            ..*self
+        }
+    }
 }
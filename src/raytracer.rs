@@ -1,275 +1,2190 @@
 //! Central struct for creating a ray tracer and rendering an image.
 
+use std::fs::File;
+use std::io::BufWriter;
 use std::path::Path;
+use std::sync::Arc;
 
-use image::{ImageError, RgbImage};
+use image::error::EncodingError;
+use image::{ImageBuffer, ImageError, ImageFormat, Rgb, Rgb32FImage, RgbImage, RgbaImage};
+#[cfg(feature = "progressbar")]
 use indicatif::{ProgressBar, ProgressStyle};
 use rand::Rng;
-use rayon::prelude::*;
+use tiff::encoder::colortype::RGB32Float;
+use tiff::encoder::TiffEncoder;
 
+use crate::accelerator::{Accelerator, Grid, KdTree};
+use crate::background::Background;
+use crate::baking::{Baker, UvSurface};
 use crate::color::BLACK;
-use crate::hittable::{Bvh, HittableListOptions};
+use crate::dithering::Dithering;
+use crate::error::Error;
+use crate::filter::{Film, Filter};
+use crate::framebuffer::Framebuffer;
+use crate::grading::ColorGrading;
+use crate::hittable::{Aabb, Bvh, BvhBuildStrategy, HittableListOptions, Named};
+use crate::integrator::sppm::PhotonMapper;
+use crate::integrator::wavefront::Wavefront;
+use crate::integrator::{Integrator, IntegratorSettings, Scene};
+use crate::lights::Lights;
+use crate::metadata::{debug_hash, is_png, save_png, RenderMetadata};
+use crate::output::RenderOutput;
+use crate::passes::{DepthImage, IdImage, IdKind, SampleImage};
+use crate::platform::*;
+use crate::postprocess::{Bloom, LensEffects};
 use crate::ppm::PPM;
-use crate::ray::Ray;
+use crate::probes::{CubeMap, ShProbe};
+use crate::progress::RenderProgress;
+use crate::random;
+use crate::stats::{Issue, RenderStats, SceneStats};
+use crate::transient::{self, TransientImage, TransientSettings};
+use crate::volume::Fog;
 use crate::*;
 
+/// Number of rows of pixels that are traced before their samples are splatted.
+const RENDER_CHUNK_ROWS: usize = 16;
+
 /// Central ray tracing struct.
 ///
 /// This struct allows setting attributes of the ray tracer, creating the world, and then rendering and saving it.
 ///
 /// # Fields
 /// - `world`: World of objects. Will be created automatically.
-/// - `resources`: Collection of textures and materials. Will be created automatically.
+/// - `lights`: [`Lights`] that are sampled directly. If it contains no area lights, all emissive objects of `world` that can be sampled are used.
 /// - `camera`: [`Camera`].
+/// - `background`: [`Background`] seen by [`Ray`](crate::ray::Ray)s that do not hit anything. The sun of a [`Sky`](crate::background::Sky) is added to the `lights` when rendering.
 /// - `image_width`: Width of the resulting image.
 /// - `image_height`: Height of the resulting image.
 /// - `samples_per_pixel`: How many samples to take for each pixel for the purpose of anti-aliasing.
-/// - `max_depth`: How often a [`Ray`] should bounce at most.
+/// - `max_depth`: How often a [`Ray`](crate::ray::Ray) should bounce at most.
+/// - `integrator`: [`Integrator`] that computes the color of each sample.
+/// - `integrator_settings`: [`IntegratorSettings`] for Russian roulette and clamping.
+/// - `names`: Names of the objects pushed via [`push_named`](Raytracer::push_named), indexed by their IDs.
+/// - `region`: [`RenderRegion`] of the pixels that are traced.
+/// - `alpha`: [`Alpha`] mode of the rendered image.
+/// - `accelerator`: [`Accelerator`] built from `world`.
+/// - `bvh_strategy`: [`BvhBuildStrategy`] of the [`Bvh`] built from `world`.
+/// - `filter`: Reconstruction [`Filter`] of the pixels.
+/// - `fog`: [`Fog`] filling the scene, which only the [path tracer](Integrator::PathTracer) renders.
+/// - `sample_statistics`: Whether renders record a [`SampleImage`].
+/// - `seed`: Seed of the samples, if the renders are reproducible.
+/// - `changes`: Bounding boxes of the named objects that changed since the last [`clear_changes`](Raytracer::clear_changes), or [`None`] for unbounded ones.
 #[derive(Clone, Debug)]
 pub struct Raytracer {
     pub world: HittableList,
+    pub lights: Lights,
     camera: Camera,
-    background: Color,
-    image_width: u16,
-    image_height: u16,
+    background: Background,
+    image_width: u32,
+    image_height: u32,
     samples_per_pixel: u16,
     max_depth: u16,
-    progressbar: Option<ProgressBar>,
+    integrator: Integrator,
+    integrator_settings: IntegratorSettings,
+    names: Vec<Option<String>>,
+    region: RenderRegion,
+    alpha: Alpha,
+    accelerator: Accelerator,
+    bvh_strategy: BvhBuildStrategy,
+    filter: Filter,
+    fog: Option<Fog>,
+    sample_statistics: bool,
+    seed: Option<u64>,
+    changes: Vec<Option<Aabb>>,
+    progress: Option<Arc<dyn RenderProgress>>,
 }
 
 impl Raytracer {
     pub fn new(
         camera: Camera,
-        background: Color,
-        image_width: u16,
-        image_height: u16,
+        background: impl Into<Background>,
+        image_width: u32,
+        image_height: u32,
         samples_per_pixel: u16,
         max_depth: u16,
     ) -> Self {
         Self {
             world: HittableList::default(),
+            lights: Lights::default(),
             camera,
-            background,
+            background: background.into(),
             image_width,
             image_height,
             samples_per_pixel,
             max_depth,
-            progressbar: None,
+            integrator: Integrator::default(),
+            integrator_settings: IntegratorSettings::default(),
+            names: Vec::new(),
+            region: RenderRegion::new(0, 0, image_width, image_height),
+            alpha: Alpha::default(),
+            accelerator: Accelerator::default(),
+            bvh_strategy: BvhBuildStrategy::default(),
+            filter: Filter::default(),
+            fog: None,
+            sample_statistics: false,
+            seed: None,
+            changes: Vec::new(),
+            progress: None,
         }
     }
 
-    /// Consume `self` and add a progressbar.
+    /// Consume `self` and report the progress of the render to `progress`.
+    pub fn with_progress<P: RenderProgress + 'static>(mut self, progress: P) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Consume `self` and add a progressbar in the terminal.
+    ///
+    /// Without the `progressbar` feature, this does nothing.
     pub fn with_progressbar(self) -> Self {
-        let progressbar = ProgressBar::new(self.image_height as u64 * self.image_width as u64);
-        progressbar.set_style(
-            ProgressStyle::with_template(
+        #[cfg(feature = "progressbar")]
+        {
+            let progressbar = ProgressBar::new(self.image_height as u64 * self.image_width as u64);
+            // Falls back to the default style instead of failing.
+            if let Ok(style) = ProgressStyle::with_template(
                 "{spinner:.green} [{elapsed}] [{wide_bar:.cyan/blue}] {pos}/{len} ({eta})",
-            )
-            .unwrap()
-            .progress_chars("#>-"),
+            ) {
+                progressbar.set_style(style.progress_chars("#>-"));
+            }
+            self.with_progress(progressbar)
+        }
+        #[cfg(not(feature = "progressbar"))]
+        self
+    }
+
+    /// Consume `self` and set the [`Integrator`].
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// Consume `self` and set the [`IntegratorSettings`].
+    pub fn with_integrator_settings(mut self, integrator_settings: IntegratorSettings) -> Self {
+        self.integrator_settings = integrator_settings;
+        self
+    }
+
+    /// Consume `self` and set the [`Alpha`] mode, e.g. to composite the image over another one.
+    pub fn with_alpha(mut self, alpha: Alpha) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Consume `self` and set the [`Accelerator`] used to find hits in the `world`.
+    pub fn with_accelerator(mut self, accelerator: Accelerator) -> Self {
+        self.accelerator = accelerator;
+        self
+    }
+
+    /// Consume `self` and set the [`BvhBuildStrategy`], e.g. [`Sah`](BvhBuildStrategy::Sah) for large meshes.
+    pub fn with_bvh_strategy(mut self, bvh_strategy: BvhBuildStrategy) -> Self {
+        self.bvh_strategy = bvh_strategy;
+        self
+    }
+
+    /// Consume `self` and set the reconstruction [`Filter`] of the pixels.
+    ///
+    /// The [stochastic progressive photon mapping](Integrator::Sppm) always uses the [`Box`](Filter::Box) filter.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Consume `self` and fill the scene with [`Fog`], which makes the light of bright lights visible as shafts.
+    pub fn with_fog(mut self, fog: Fog) -> Self {
+        self.fog = Some(fog);
+        self
+    }
+
+    /// Consume `self` and record the number of samples and the variance of each pixel in the [`SampleImage`] of the rendered images, e.g. to see where the noise is.
+    ///
+    /// This costs 12 or 20 additional bytes per pixel (with the `f64` feature). The [photon mapper](Integrator::Sppm) records none.
+    pub fn with_sample_statistics(mut self) -> Self {
+        self.sample_statistics = true;
+        self
+    }
+
+    /// Consume `self` and draw the samples of the [path tracer](Integrator::PathTracer) and the integrators that trace each pixel on its own from generators seeded with `seed`.
+    ///
+    /// Renders with the same seed are then reproducible, independent of the number of threads. The other integrators and the construction of the scene stay random.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Push a light source to both the `world` and the `lights`.
+    pub fn push_light<H: Hittable + 'static>(&mut self, light: H) {
+        let light: Arc<dyn Hittable> = Arc::new(light);
+        self.world.push_shared(light.clone());
+        self.lights.push_shared(light);
+    }
+
+    /// Only trace the pixels from (`x0`, `y0`) (included) to (`x1`, `y1`) (excluded), counted from the top left.
+    ///
+    /// The other pixels stay black. This saves time when only a part of a large image is of interest, e.g. while tuning a material.
+    /// The region is clamped to the image.
+    pub fn set_render_region(&mut self, x0: u32, y0: u32, x1: u32, y1: u32) {
+        self.region = RenderRegion::new(
+            x0.min(self.image_width),
+            y0.min(self.image_height),
+            x1.min(self.image_width),
+            y1.min(self.image_height),
         );
-        Self {
-            world: self.world,
-            camera: self.camera,
-            background: self.background,
-            image_width: self.image_width,
-            image_height: self.image_height,
-            samples_per_pixel: self.samples_per_pixel,
-            max_depth: self.max_depth,
-            progressbar: Some(progressbar),
+    }
+
+    /// Trace all pixels again after [`set_render_region`](Raytracer::set_render_region).
+    pub fn clear_render_region(&mut self) {
+        self.region = RenderRegion::new(0, 0, self.image_width, self.image_height);
+    }
+
+    /// Width and height of the image.
+    pub fn image_size(&self) -> (u32, u32) {
+        (self.image_width, self.image_height)
+    }
+
+    pub fn render_region(&self) -> RenderRegion {
+        self.region
+    }
+
+    /// Push a [`Hittable`] with a name to the `world` and return its ID.
+    ///
+    /// The IDs are counted up from zero. They are found in the [`HitRecord`](crate::hitrecord::HitRecord)s of hits and in the result of [`pick`](Raytracer::pick).
+    pub fn push_named<H: Hittable + 'static>(
+        &mut self,
+        name: impl Into<String>,
+        hittable: H,
+    ) -> u32 {
+        let id = self.names.len() as u32;
+        let name = name.into();
+        self.record_change(&hittable);
+        self.world.push(Named::new(id, name.clone(), hittable));
+        self.names.push(Some(name));
+        id
+    }
+
+    /// Name of the object with ID `id` that was pushed via [`push_named`](Raytracer::push_named).
+    pub fn object_name(&self, id: u32) -> Option<&str> {
+        self.names.get(id as usize)?.as_deref()
+    }
+
+    /// Remove the object with ID `id` that was pushed via [`push_named`](Raytracer::push_named) from the `world` and return whether it was found.
+    ///
+    /// The ID is not reused.
+    pub fn remove_named(&mut self, id: u32) -> bool {
+        let Some(removed) = self.world.remove_by_id(id) else {
+            return false;
+        };
+        self.record_change(removed.as_ref());
+        self.names[id as usize] = None;
+        true
+    }
+
+    /// Replace the object with ID `id` that was pushed via [`push_named`](Raytracer::push_named) by `hittable`, keeping its name and ID, and return whether it was found.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, shapes::Sphere};
+    /// let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+    /// let mut raytracer = Raytracer::new(camera, color![0., 0., 0.], 101, 101, 10, 10);
+    /// let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+    /// let id = raytracer.push_named("ball", Sphere::new(vector![0., 0., -1.], 0.5, material.clone()));
+    ///
+    /// assert!(raytracer.replace_named(id, Sphere::new(vector![0., 0., -2.], 0.5, material)));
+    /// assert!((raytracer.pick(50, 50).unwrap().distance - 2.5).abs() < 1e-3);
+    ///
+    /// assert!(raytracer.remove_named(id));
+    /// assert!(raytracer.pick(50, 50).is_none());
+    /// assert_eq!(raytracer.object_name(id), None);
+    /// ```
+    pub fn replace_named<H: Hittable + 'static>(&mut self, id: u32, hittable: H) -> bool {
+        let Some(Some(name)) = self.names.get(id as usize).cloned() else {
+            return false;
+        };
+        let Some(old) = self
+            .world
+            .iter()
+            .find(|other| other.object_id() == Some(id))
+            .cloned()
+        else {
+            return false;
+        };
+        self.record_change(old.as_ref());
+        self.record_change(&hittable);
+        let named = Named::new(id, name, hittable);
+        self.world.replace_by_id(id, named)
+    }
+
+    /// Remember the bounding box of `hittable`, which was added, removed, or replaced, for [`changed_region`](Raytracer::changed_region).
+    fn record_change(&mut self, hittable: &dyn Hittable) {
+        let (time0, time1) = self.camera.time().unwrap_or((0., 0.));
+        self.changes.push(hittable.bounding_box(time0, time1));
+    }
+
+    /// Forget the changes of the named objects, e.g. after the scene was rendered.
+    pub fn clear_changes(&mut self) {
+        self.changes.clear();
+    }
+
+    /// Smallest [`RenderRegion`] that contains every pixel in which the named objects that were pushed, removed, or replaced since the last [`clear_changes`](Raytracer::clear_changes) are seen.
+    ///
+    /// The old and new bounding boxes of the objects are projected through the camera, widened by the blur of the depth of field and the reach of the `filter`.
+    /// Unbounded objects and objects that are not completely in front of the camera cover the whole image.
+    /// Returns [`None`] if nothing changed or all changes are outside of the image.
+    pub fn changed_region(&self) -> Option<RenderRegion> {
+        let (width, height) = (self.image_width as Float, self.image_height as Float);
+        let margin = (self.filter.radius() - 0.5).max(0.).ceil();
+
+        let mut changed: Option<RenderRegion> = None;
+        for change in &self.changes {
+            let Some((u0, v0, u1, v1)) = change.and_then(|aabb| self.camera.footprint(&aabb))
+            else {
+                return Some(RenderRegion::new(0, 0, self.image_width, self.image_height));
+            };
+            // Pixel x is sampled at u in [x, x + 1) / (width - 1), and the rows are counted from the bottom.
+            let pixel = |t: Float, size: Float| (t.clamp(-1., 2.) * (size - 1.)).floor();
+            let x0 = (pixel(u0, width) - margin).clamp(0., width) as u32;
+            let x1 = (pixel(u1, width) + 1. + margin).clamp(0., width) as u32;
+            let y0 = (height - 1. - pixel(v1, height) - margin).clamp(0., height) as u32;
+            let y1 = (height - pixel(v0, height) + margin).clamp(0., height) as u32;
+            if x0 >= x1 || y0 >= y1 {
+                continue;
+            }
+            changed = Some(match changed {
+                Some(region) => RenderRegion::new(
+                    region.x0.min(x0),
+                    region.y0.min(y0),
+                    region.x1.max(x1),
+                    region.y1.max(y1),
+                ),
+                None => RenderRegion::new(x0, y0, x1, y1),
+            });
+        }
+        changed
+    }
+
+    /// Find the object seen in the pixel (`x`, `y`), which is counted from the top left.
+    ///
+    /// A single [`Ray`](crate::ray::Ray) is shot through the center of the pixel and the lens.
+    /// Returns [`None`] if the pixel is outside of the image or the [`Ray`](crate::ray::Ray) does not hit anything.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, shapes::Sphere};
+    /// let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+    /// let mut raytracer = Raytracer::new(camera, color![0., 0., 0.], 101, 101, 10, 10);
+    /// let id = raytracer.push_named("ball", Sphere::new(vector![0., 0., -1.], 0.5, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+    ///
+    /// let pick = raytracer.pick(50, 50).unwrap();
+    /// assert_eq!(pick.object_id, Some(id));
+    /// assert_eq!(raytracer.object_name(id), Some("ball"));
+    /// assert!((pick.distance - 1.5).abs() < 1e-3);
+    /// assert!(raytracer.pick(0, 0).is_none());
+    /// ```
+    pub fn pick(&self, x: u32, y: u32) -> Option<PickResult> {
+        if x >= self.image_width || y >= self.image_height {
+            return None;
+        }
+        let u = x as Float / (self.image_width - 1).max(1) as Float;
+        let v = (self.image_height - y - 1) as Float / (self.image_height - 1).max(1) as Float;
+        let ray = self.camera.get_center_ray(u, v);
+
+        let hit = self.world.hit(
+            ray,
+            self.integrator_settings.ray_offset().t_min(),
+            Float::INFINITY,
+        )?;
+        Some(PickResult {
+            object_id: hit.object_id,
+            distance: hit.t * ray.direction().norm(),
+            point: hit.point,
+            normal: hit.geometric_normal,
+        })
+    }
+
+    /// Hash of the scene, i.e. the world, the lights, the camera, the background, and the fog, e.g. for the [`RenderMetadata`] of the images.
+    ///
+    /// The hash is the same for every build of the same version of the crate. Computing it takes time proportional to the size of the scene, including all textures.
+    pub fn scene_hash(&self) -> u64 {
+        debug_hash(&[
+            &self.world,
+            &self.lights,
+            &self.camera,
+            &self.background,
+            &self.fog,
+        ])
+    }
+
+    /// Count the objects of the scene and look for [`Issue`]s that would make the render fail or look wrong.
+    ///
+    /// This does not build the [`Bvh`]; its depth and number of nodes are in the [`RenderStats`] of a render.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, materials::{DiffuseLight, Lambertian}, shapes::Sphere, stats::Issue};
+    /// # let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+    /// let mut raytracer = Raytracer::new(camera, color![0., 0., 0.], 100, 100, 10, 10);
+    /// raytracer.world.push(Sphere::new(vector![0., 0., -1.], 0., Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+    /// raytracer.world.push(Sphere::new(vector![0., 2., -1.], 1., DiffuseLight::solid_color(color![4., 4., 4.])));
+    ///
+    /// let stats = raytracer.stats();
+    /// assert_eq!(stats.primitives, 2);
+    /// assert_eq!(stats.emissive, 1);
+    /// assert!(matches!(stats.issues[..], [Issue::DegeneratePrimitive { object: 0, .. }]));
+    /// ```
+    pub fn stats(&self) -> SceneStats {
+        let mut stats = SceneStats::default();
+        for (object, hittable) in self.world.iter().enumerate() {
+            stats.collect_object(object, hittable.as_ref());
+        }
+        stats.add_memory(self.world.len() * std::mem::size_of::<Arc<dyn Hittable>>());
+
+        if self.world.is_empty() {
+            stats.report(Issue::EmptyWorld);
+        }
+        if self.image_width < 2 || self.image_height < 2 {
+            stats.report(Issue::ImageTooSmall {
+                width: self.image_width,
+                height: self.image_height,
+            });
+        }
+        if self.samples_per_pixel == 0 {
+            stats.report(Issue::NoSamples);
+        }
+        if stats.emissive == 0 && self.lights.is_empty() && self.background.is_black() {
+            stats.report(Issue::NoLight);
+        }
+
+        stats
+    }
+
+    /// Return the [`SceneStats`] if no [`Issue`]s were found and the [`Issue`]s otherwise.
+    pub fn validate(&self) -> Result<SceneStats, Vec<Issue>> {
+        let stats = self.stats();
+        match stats.is_valid() {
+            true => Ok(stats),
+            false => Err(stats.issues),
         }
     }
 
     /// Render to a [`RaytracedImage`].
     ///
-    /// Tries to optimize `world` into a [`Bvh`], but falls back to the slower implementation if not possible (i.e. [`Bvh::new`] return [`BoundingBoxError`]).
-    /// This function uses multithreading with the help of the [`rayon`] crate.
-    pub fn render(self) -> RaytracedImage {
-        let image_width = self.image_width;
-        let image_height = self.image_height;
-        let image = self.render_multithreaded();
+    /// Tries to optimize `world` into the [`Accelerator`] (a [`Bvh`] by default), but falls back to the slower implementation if not possible (i.e. [`Bvh::new`] return [`BoundingBoxError`]).
+    /// With the `parallel` feature (enabled by default), this function uses multithreading with the help of the [`rayon`] crate.
+    ///
+    /// Returns [`Error::Config`] if the image has no pixels or no samples are taken.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, error::Error};
+    /// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 0, 100, 10, 10);
+    /// assert!(matches!(raytracer.render(), Err(Error::Config(_))));
+    /// ```
+    pub fn render(self) -> Result<RaytracedImage, Error> {
+        self.render_multithreaded(true)
+    }
 
-        RaytracedImage {
-            image,
-            image_width,
-            image_height,
+    pub fn render_without_bvh(self) -> Result<RaytracedImage, Error> {
+        self.render_multithreaded(false)
+    }
+
+    /// Render and return the pixels as 8 bit RGBA, row by row from the top, e.g. to fill the `ImageData` of an HTML canvas.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::*;
+    /// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 8, 4, 1, 1);
+    /// let buffer = raytracer.render_to_rgba_buffer().unwrap();
+    /// assert_eq!(buffer.len(), 8 * 4 * 4);
+    /// assert_eq!(buffer[3], 255);
+    /// ```
+    pub fn render_to_rgba_buffer(self) -> Result<Vec<u8>, Error> {
+        let image = self.render()?.into_rgba_image().ok_or_else(buffer_error)?;
+        Ok(image.into_raw())
+    }
+
+    /// Render the image in horizontal bands of `band_height` rows from the top and pass each band to `write_band` as soon as it is done, e.g. to a [`BandWriter`](crate::streaming::BandWriter).
+    ///
+    /// Only one band is kept in memory, so this is meant for images that are too large for [`render`](Raytracer::render). The [`Accelerator`] and the lights are built once for all bands.
+    /// Every band is traced with a few extra rows for the `filter`, so there are no seams between the bands. The bands always cover the full width, and pixels outside of the render region are black.
+    /// The returned [`RenderStats`] are those of the whole render; the bands have none.
+    ///
+    /// Returns [`Error::Config`] if the image has no pixels, no samples are taken, or `band_height` is zero, and the first error of `write_band`.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::*;
+    /// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 16, 10, 1, 1);
+    /// let mut heights = Vec::new();
+    /// raytracer.render_bands(4, |band| Ok(heights.push(band.dimensions().1))).unwrap();
+    /// assert_eq!(heights, [4, 4, 2]);
+    /// ```
+    pub fn render_bands(
+        mut self,
+        band_height: u32,
+        mut write_band: impl FnMut(RaytracedImage) -> Result<(), Error>,
+    ) -> Result<RenderStats, Error> {
+        self.check_config()?;
+        if band_height == 0 {
+            return Err(Error::Config("bands without rows".to_string()));
         }
+
+        let start = Instant::now();
+        let mut stats = RenderStats::default();
+        let (world, lights) = self.prepare(true, &mut stats)?;
+        let scene = self.scene(&world, &lights);
+
+        let (width, height) = (self.image_width, self.image_height);
+        // Samples are splatted at most this many rows into the neighboring bands.
+        #[allow(clippy::unnecessary_cast)]
+        let margin = (self.filter.radius() - 0.5).max(0.).ceil() as u32;
+        // The rows of each band and the rows that are traced for it.
+        let bands: Vec<_> = (0..height)
+            .step_by(band_height as usize)
+            .map(|y0| {
+                let y1 = (y0 + band_height).min(height);
+                ((y0, y1), traced_rows((y0, y1), margin, height))
+            })
+            .collect();
+        let region = |(y0, y1): (u32, u32)| {
+            RenderRegion::new(
+                self.region.x0,
+                self.region.y0.clamp(y0, y1) - y0,
+                self.region.x1,
+                self.region.y1.clamp(y0, y1) - y0,
+            )
+        };
+
+        let progress = self.progress.as_deref();
+        if let Some(progress) = progress {
+            let steps = bands
+                .iter()
+                .map(|&(_, traced)| self.progress_steps(&region(traced)));
+            progress.start(steps.sum());
+        }
+
+        for ((y0, y1), (t0, t1)) in bands {
+            log_event!(debug, "rendering rows {y0} to {y1}");
+            let camera = self.band_camera((t0, t1));
+            let (colors, coverage, samples) = self.trace(
+                &scene,
+                &camera,
+                (width, t1 - t0),
+                &region((t0, t1)),
+                progress,
+                &mut stats,
+            );
+
+            let rows = (y0 - t0) as usize * width as usize..(y1 - t0) as usize * width as usize;
+            let mut band = RaytracedImage::new(colors[rows.clone()].to_vec(), width, y1 - y0);
+            band.samples_per_pixel = self.samples_per_pixel;
+            band.region = region((y0, y1));
+            if self.alpha != Alpha::Opaque {
+                band.alpha = Some(coverage[rows].to_vec());
+            }
+            band.samples = samples.map(|samples| samples.rows(y0 - t0, y1 - t0));
+            write_band(band)?;
+        }
+
+        stats.total = start.elapsed();
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+        log_event!(info, "rendered in bands in {:?}", stats.total);
+        Ok(stats)
     }
 
-    pub fn render_without_bvh(self) -> RaytracedImage {
-        let image_width = self.image_width;
-        let image_height = self.image_height;
-        let image = self.render_multithreaded_without_bvh();
+    /// Render the tiles returned by `next_tile` until it returns [`None`], and pass each one to `write_tile` as soon as it is done, e.g. to send it to the [`Coordinator`](crate::distributed::Coordinator) of a distributed render.
+    ///
+    /// Like for [`render_bands`](Raytracer::render_bands), the [`Accelerator`] and the lights are built once for all tiles, and every tile is traced with a few extra pixels for the `filter`, so there are no seams between the tiles.
+    /// Only the tiles are traced, and the images passed to `write_tile` have their size. The render region and the progress are ignored, and the tiles have no [`SampleImage`].
+    ///
+    /// Returns [`Error::Config`] if the image has no pixels, no samples are taken, or a tile is empty or not inside the image, and the first error of `next_tile` or `write_tile`.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, raytracer::RenderRegion};
+    /// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 16, 10, 1, 1);
+    /// let mut tiles = vec![RenderRegion::new(8, 4, 16, 5), RenderRegion::new(0, 0, 8, 10)];
+    /// let mut sizes = Vec::new();
+    /// raytracer
+    ///     .render_tiles(|| Ok(tiles.pop()), |tile| Ok(sizes.push(tile.dimensions())))
+    ///     .unwrap();
+    /// assert_eq!(sizes, [(8, 10), (8, 1)]);
+    /// ```
+    pub fn render_tiles(
+        mut self,
+        mut next_tile: impl FnMut() -> Result<Option<RenderRegion>, Error>,
+        mut write_tile: impl FnMut(RaytracedImage) -> Result<(), Error>,
+    ) -> Result<RenderStats, Error> {
+        self.check_config()?;
 
-        RaytracedImage {
-            image,
-            image_width,
-            image_height,
+        let start = Instant::now();
+        let mut stats = RenderStats::default();
+        let (world, lights) = self.prepare(true, &mut stats)?;
+        let scene = self.scene(&world, &lights);
+
+        let (width, height) = (self.image_width, self.image_height);
+        // Samples are splatted at most this many pixels into the neighboring tiles.
+        #[allow(clippy::unnecessary_cast)]
+        let margin = (self.filter.radius() - 0.5).max(0.).ceil() as u32;
+        while let Some(tile) = next_tile()? {
+            if tile.pixel_count() == 0 || tile.x1 > width || tile.y1 > height {
+                return Err(Error::Config(format!(
+                    "tile {tile:?} is not inside the image of size {width}x{height}"
+                )));
+            }
+            log_event!(debug, "rendering tile {tile:?}");
+            let (t0, t1) = traced_rows((tile.y0, tile.y1), margin, height);
+            let traced = RenderRegion::new(
+                tile.x0.saturating_sub(margin),
+                0,
+                (tile.x1 + margin).min(width),
+                t1 - t0,
+            );
+            let (colors, coverage, _) = self.trace(
+                &scene,
+                &self.band_camera((t0, t1)),
+                (width, t1 - t0),
+                &traced,
+                None,
+                &mut stats,
+            );
+
+            let pixels = (tile.y0..tile.y1).flat_map(|y| {
+                let row = (y - t0) as usize * width as usize;
+                row + tile.x0 as usize..row + tile.x1 as usize
+            });
+            let mut image = RaytracedImage::new(
+                pixels.clone().map(|index| colors[index]).collect(),
+                tile.width(),
+                tile.height(),
+            );
+            image.samples_per_pixel = self.samples_per_pixel;
+            if self.alpha != Alpha::Opaque {
+                image.alpha = Some(pixels.map(|index| coverage[index]).collect());
+            }
+            write_tile(image)?;
         }
+
+        stats.total = start.elapsed();
+        log_event!(info, "rendered the tiles in {:?}", stats.total);
+        Ok(stats)
     }
 
-    fn render_multithreaded(self) -> Vec<Color> {
-        let world = match Bvh::check_hittable_list(&self.world) {
-            true => HittableListOptions::Bvh(Bvh::new(self.world, 0., 0.).expect("creating BVH")),
-            false => HittableListOptions::HittableList(self.world),
+    /// Trace the samples of the render region like [`render`](Raytracer::render), but pass each one to `sink` instead of accumulating them into an image.
+    ///
+    /// This is the basis for custom accumulation, displays that show the image while it converges, or collecting training data.
+    /// The samples are traced in passes over the region, one sample per pixel each. They are [clamped](IntegratorSettings::with_max_radiance) like in an image, and [`Ray`](crate::ray::Ray)s blocked by a [lens system](Camera::with_lens_system) give black samples.
+    /// The `filter` and the `alpha` mode are ignored.
+    ///
+    /// Returns [`Error::Config`] if the image has no pixels, no samples are taken, or the `integrator` is the [photon mapper](Integrator::Sppm), which only renders whole images.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::sync::Mutex;
+    /// # use ray_tracing_in_one_weekend::*;
+    /// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 8, 4, 3, 1);
+    /// let sums = Mutex::new(vec![(0, 0.); 8 * 4]);
+    /// raytracer
+    ///     .render_samples(&|x: u32, y: u32, color: Color| {
+    ///         let mut sums = sums.lock().unwrap();
+    ///         let pixel = &mut sums[(y * 8 + x) as usize];
+    ///         *pixel = (pixel.0 + 1, pixel.1 + color.b());
+    ///     })
+    ///     .unwrap();
+    /// assert!(sums.into_inner().unwrap().iter().all(|&(count, sum)| count == 3 && sum > 0.));
+    /// ```
+    pub fn render_samples<S: SampleSink + ?Sized>(
+        mut self,
+        sink: &S,
+    ) -> Result<RenderStats, Error> {
+        self.check_config()?;
+        if matches!(self.integrator, Integrator::Sppm { .. }) {
+            return Err(Error::Config(
+                "the photon mapper cannot render individual samples".to_string(),
+            ));
+        }
+
+        let start = Instant::now();
+        let mut stats = RenderStats::default();
+        let (world, lights) = self.prepare(true, &mut stats)?;
+        let scene = self.scene(&world, &lights);
+
+        let region = self.region;
+        let progress = self.progress.as_deref();
+        if let Some(progress) = progress {
+            progress.start(region.pixel_count() as u64 * self.samples_per_pixel as u64);
+        }
+
+        let (width, height) = (self.image_width, self.image_height);
+        let pixel_size = (
+            1. / (width - 1).max(1) as Float,
+            1. / (height - 1).max(1) as Float,
+        );
+        let trace_start = Instant::now();
+        for _ in 0..self.samples_per_pixel {
+            (region.y0..region.y1)
+                .into_par_iter()
+                .flat_map_iter(|y| (region.x0..region.x1).map(move |x| (x, y)))
+                .for_each(|(x, y)| {
+                    let mut rng = crate::random::rng();
+                    let u = (x as Float + rng.gen::<Float>()) * pixel_size.0;
+                    let v = ((height - 1 - y) as Float + rng.gen::<Float>()) * pixel_size.1;
+                    let sample = match self.camera.try_get_ray_with_differentials(u, v, pixel_size)
+                    {
+                        Some(ray) => self.integrator_settings.clamp(self.integrator.radiance(
+                            &scene,
+                            ray,
+                            self.max_depth,
+                            &self.integrator_settings,
+                        )),
+                        None => BLACK,
+                    };
+                    sink.add_sample(x, y, sample);
+                    if let Some(progress) = progress {
+                        progress.advance(1);
+                    }
+                });
+        }
+
+        stats.shading = trace_start.elapsed();
+        stats.paths = region.pixel_count() as u64 * self.samples_per_pixel as u64;
+        stats.rays = stats.paths;
+        stats.total = start.elapsed();
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+        log_event!(
+            info,
+            "traced {} samples per pixel in {:?}",
+            self.samples_per_pixel,
+            stats.total
+        );
+        Ok(stats)
+    }
+
+    /// Render only the pixels of the [`changed_region`](Raytracer::changed_region) within the render region again and take the other pixels from `previous`, a render of the scene before the changes.
+    ///
+    /// This is meant for interactive editing, where a single object is moved at a time. Only what the changed objects cover is updated,
+    /// so e.g. their shadows and reflections elsewhere, and changes of the lights, the camera, or objects that are not [named](Raytracer::push_named) are not.
+    /// The region of the result covers the regions of `previous` and the pixels that were rendered again.
+    ///
+    /// Returns [`Error::Config`] if `previous` has another size than the image, besides the errors of [`render`](Raytracer::render).
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, shapes::Sphere};
+    /// let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+    /// let mut raytracer = Raytracer::new(camera, color![0.5, 0.7, 1.], 40, 20, 4, 4);
+    /// let material = Lambertian::solid_color(color![0.8, 0.2, 0.2]);
+    /// let id = raytracer.push_named("ball", Sphere::new(vector![-1., 0., -2.], 0.25, material.clone()));
+    /// raytracer.clear_changes();
+    /// let previous = raytracer.clone().render().unwrap();
+    ///
+    /// raytracer.replace_named(id, Sphere::new(vector![-1., 0.5, -2.], 0.25, material));
+    /// let region = raytracer.changed_region().unwrap();
+    /// assert!(region.x1 <= 20 && region.pixel_count() < 40 * 20 / 4);
+    /// let image = raytracer.render_changes(&previous).unwrap();
+    /// assert_eq!(image.dimensions(), (40, 20));
+    /// ```
+    pub fn render_changes(mut self, previous: &RaytracedImage) -> Result<RaytracedImage, Error> {
+        if previous.dimensions() != (self.image_width, self.image_height) {
+            return Err(Error::Config(format!(
+                "previous image of size {}x{} does not match the image of size {}x{}",
+                previous.image_width, previous.image_height, self.image_width, self.image_height
+            )));
+        }
+
+        let changed = self
+            .changed_region()
+            .unwrap_or(RenderRegion::new(0, 0, 0, 0));
+        let region = RenderRegion::new(
+            changed.x0.max(self.region.x0),
+            changed.y0.max(self.region.y0),
+            changed.x1.min(self.region.x1),
+            changed.y1.min(self.region.y1),
+        );
+        log_event!(debug, "rendering {} changed pixels", region.pixel_count());
+        self.region = region;
+        let mut image = self.render()?;
+
+        for (index, color) in image.image.iter_mut().enumerate() {
+            if !region.contains_index(index, image.image_width) {
+                *color = previous.image[index];
+            }
+        }
+        if let Some(alpha) = &mut image.alpha {
+            for (index, alpha) in alpha.iter_mut().enumerate() {
+                if !region.contains_index(index, image.image_width) {
+                    *alpha = previous
+                        .alpha
+                        .as_ref()
+                        .map_or(1., |previous| previous[index]);
+                }
+            }
+        }
+        image.region = match region.pixel_count() {
+            0 => previous.region,
+            _ => RenderRegion::new(
+                region.x0.min(previous.region.x0),
+                region.y0.min(previous.region.y0),
+                region.x1.max(previous.region.x1),
+                region.y1.max(previous.region.y1),
+            ),
         };
+        Ok(image)
+    }
 
-        let mut colors =
-            vec![color![0., 0., 0.]; self.image_height as usize * self.image_width as usize];
+    /// Render a stereo image for VR headsets or 3D displays with the two eyes next to or above each other (see [`StereoLayout`]).
+    ///
+    /// The eyes are placed with [`Camera::stereo_pair`], so they are `interpupillary_distance` apart and their views coincide at the distance `convergence`.
+    /// Each eye has the full image size, so the result is twice as wide or twice as high.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, raytracer::StereoLayout};
+    /// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 16, 9, 1, 1);
+    /// let image = raytracer.render_stereo(0.064, 2., StereoLayout::TopBottom).unwrap();
+    /// assert_eq!(image.dimensions(), (16, 18));
+    /// ```
+    pub fn render_stereo(
+        self,
+        interpupillary_distance: Float,
+        convergence: Float,
+        layout: StereoLayout,
+    ) -> Result<RaytracedImage, Error> {
+        let (left_camera, right_camera) = self
+            .camera
+            .stereo_pair(interpupillary_distance, convergence);
+        let mut left = self.clone();
+        left.camera = left_camera;
+        let mut right = self;
+        right.camera = right_camera;
+        let left = left.render()?;
+        let right = right.render()?;
 
-        colors
-            .par_iter_mut()
-            .enumerate()
-            .for_each(|(index, color)| {
-                let mut rng = rand::thread_rng();
+        let (width, height) = left.dimensions();
+        let colors = join_stereo(&left.image, &right.image, width, layout);
+        // A region has to be a rectangle, so for a partial render it also covers the untraced pixels between the copies of the region.
+        let (image_width, image_height, region) = match layout {
+            StereoLayout::SideBySide => (
+                2 * width,
+                height,
+                RenderRegion::new(
+                    left.region.x0,
+                    left.region.y0,
+                    width + left.region.x1,
+                    left.region.y1,
+                ),
+            ),
+            StereoLayout::TopBottom => (
+                width,
+                2 * height,
+                RenderRegion::new(
+                    left.region.x0,
+                    left.region.y0,
+                    left.region.x1,
+                    height + left.region.y1,
+                ),
+            ),
+        };
+
+        let mut image = RaytracedImage::new(colors, image_width, image_height);
+        if let (Some(left_alpha), Some(right_alpha)) = (&left.alpha, &right.alpha) {
+            image.alpha = Some(join_stereo(left_alpha, right_alpha, width, layout));
+        }
+        image.samples_per_pixel = left.samples_per_pixel;
+        image.region = region;
+        image.stats = left.stats;
+        image.stats += right.stats;
+        Ok(image)
+    }
+
+    /// Render the distance from the camera to the first hit of each pixel, e.g. for depth-of-field in post-processing.
+    ///
+    /// Every pixel inside the [`RenderRegion`] is traced once through its center from the center of the lens, so depths are not mixed at edges.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, shapes::Sphere};
+    /// let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+    /// let mut raytracer = Raytracer::new(camera, color![0.5, 0.7, 1.], 9, 9, 1, 1);
+    /// raytracer.world.push(Sphere::new(vector![0., 0., -1.], 0.9, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+    ///
+    /// let depth = raytracer.render_depth().unwrap();
+    /// // The sphere is about 1.1 away.
+    /// assert!((depth.depths()[4 * 9 + 4] - 1.1).abs() < 0.05);
+    /// assert_eq!(depth.depths()[0], Float::INFINITY);
+    /// ```
+    pub fn render_depth(self) -> Result<DepthImage, Error> {
+        self.check_config()?;
+        let world = accelerate(self.world, true, self.accelerator, self.bvh_strategy)?;
+
+        let pixel_count = self.image_height as usize * self.image_width as usize;
+        let depths = (0..pixel_count)
+            .into_par_iter()
+            .map(|index| {
+                if !self.region.contains_index(index, self.image_width) {
+                    return Float::INFINITY;
+                }
+                let i = index % self.image_width as usize;
+                let j = self.image_height as usize - index / self.image_width as usize - 1;
+                let u = (i as Float + 0.5) / (self.image_width - 1).max(1) as Float;
+                let v = (j as Float + 0.5) / (self.image_height - 1).max(1) as Float;
+                let ray = self.camera.get_center_ray(u, v);
+
+                world
+                    .hit(
+                        ray,
+                        self.integrator_settings.ray_offset().t_min(),
+                        Float::INFINITY,
+                    )
+                    .map_or(Float::INFINITY, |hit| hit.t * ray.direction().norm())
+            })
+            .collect();
+        Ok(DepthImage::new(depths, self.image_width, self.image_height))
+    }
+
+    /// Render the coverage of each object or material of the first hits, e.g. to extract mattes for compositing.
+    ///
+    /// Every pixel inside the [`RenderRegion`] is traced with `samples_per_pixel` jittered camera [`Ray`]s like the beauty render, so the coverage is anti-aliased at edges and under defocus blur.
+    /// Only objects pushed via [`push_named`](Raytracer::push_named) or materials wrapped in a [`NamedMaterial`](crate::materials::NamedMaterial) have an ID, depending on `kind`.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, passes::IdKind, shapes::Sphere};
+    /// let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+    /// let mut raytracer = Raytracer::new(camera, color![0.5, 0.7, 1.], 9, 9, 16, 1);
+    /// let id = raytracer.push_named("ball", Sphere::new(vector![0., 0., -1.], 0.9, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+    ///
+    /// let ids = raytracer.render_ids(IdKind::Object).unwrap();
+    /// assert_eq!(ids.pixel(4, 4), &[(id, 1.)]);
+    /// assert!(ids.pixel(0, 0).is_empty());
+    /// ```
+    pub fn render_ids(self, kind: IdKind) -> Result<IdImage, Error> {
+        self.check_config()?;
+        let world = accelerate(self.world, true, self.accelerator, self.bvh_strategy)?;
+
+        let pixel_count = self.image_height as usize * self.image_width as usize;
+        let coverages = (0..pixel_count)
+            .into_par_iter()
+            .map(|index| {
+                if !self.region.contains_index(index, self.image_width) {
+                    return Vec::new();
+                }
+                let mut rng = crate::random::rng();
                 let i = index % self.image_width as usize;
                 let j = self.image_height as usize - index / self.image_width as usize - 1;
 
+                // Number of samples that hit each ID.
+                let mut counts: Vec<(u32, u32)> = Vec::new();
                 for _ in 0..self.samples_per_pixel {
-                    let u = (i as f32 + rng.gen::<f32>()) / (self.image_width - 1) as f32;
-                    let v = (j as f32 + rng.gen::<f32>()) / (self.image_height - 1) as f32;
-                    *color += Raytracer::ray_color(
+                    let u =
+                        (i as Float + rng.gen::<Float>()) / (self.image_width - 1).max(1) as Float;
+                    let v =
+                        (j as Float + rng.gen::<Float>()) / (self.image_height - 1).max(1) as Float;
+                    let ray = self.camera.get_ray(u, v);
+
+                    let Some(hit) = crate::integrator::hit_visible(
                         &world,
-                        self.camera.get_ray(u, v),
-                        self.background,
-                        self.max_depth,
-                    );
+                        ray,
+                        self.integrator_settings.ray_offset(),
+                        Float::INFINITY,
+                    ) else {
+                        continue;
+                    };
+                    let id = match kind {
+                        IdKind::Object => hit.object_id(),
+                        IdKind::Material => hit.material().id(),
+                    };
+                    if let Some(id) = id {
+                        match counts.iter_mut().find(|(counted, _)| *counted == id) {
+                            Some((_, count)) => *count += 1,
+                            None => counts.push((id, 1)),
+                        }
+                    }
                 }
 
-                if let Some(bar) = &self.progressbar {
-                    bar.inc(1);
+                counts
+                    .into_iter()
+                    .map(|(id, count)| (id, count as Float / self.samples_per_pixel as Float))
+                    .collect()
+            })
+            .collect();
+        Ok(IdImage::new(coverages, self.image_width, self.image_height))
+    }
+
+    /// Render the shading normals of the first hits in world space, e.g. as a layer of a [`RenderOutput`](crate::output::RenderOutput).
+    ///
+    /// The x, y and z components of the normals are stored in the red, green and blue channels without any mapping, i.e. in \[-1,1\].
+    /// Like the beauty render, every pixel is averaged over `samples_per_pixel` jittered camera [`Ray`]s. Missed pixels and pixels outside of the [`RenderRegion`] are 0.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, shapes::Sphere};
+    /// let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+    /// let mut raytracer = Raytracer::new(camera, color![0.5, 0.7, 1.], 9, 9, 1, 1);
+    /// raytracer.world.push(Sphere::new(vector![0., 0., -1.], 0.9, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+    ///
+    /// let normals = raytracer.render_normals().unwrap();
+    /// // The center of the sphere faces the camera.
+    /// assert!(normals.colors()[4 * 9 + 4].b() > 0.9);
+    /// ```
+    pub fn render_normals(self) -> Result<RaytracedImage, Error> {
+        self.check_config()?;
+        let world = accelerate(self.world, true, self.accelerator, self.bvh_strategy)?;
+
+        let pixel_count = self.image_height as usize * self.image_width as usize;
+        let normals = (0..pixel_count)
+            .into_par_iter()
+            .map(|index| {
+                if !self.region.contains_index(index, self.image_width) {
+                    return BLACK;
                 }
+                let mut rng = crate::random::rng();
+                let i = index % self.image_width as usize;
+                let j = self.image_height as usize - index / self.image_width as usize - 1;
 
-                *color = color
-                    .into_iter()
-                    .map(|color| (color / self.samples_per_pixel as f32).sqrt())
-                    .collect();
-            });
+                let mut sum = Vector3::zeros();
+                for _ in 0..self.samples_per_pixel {
+                    let u =
+                        (i as Float + rng.gen::<Float>()) / (self.image_width - 1).max(1) as Float;
+                    let v =
+                        (j as Float + rng.gen::<Float>()) / (self.image_height - 1).max(1) as Float;
+                    let ray = self.camera.get_ray(u, v);
+                    if let Some(hit) = crate::integrator::hit_visible(
+                        &world,
+                        ray,
+                        self.integrator_settings.ray_offset(),
+                        Float::INFINITY,
+                    ) {
+                        sum += hit.normal;
+                    }
+                }
+                let normal = sum / self.samples_per_pixel as Float;
+                color![normal.x, normal.y, normal.z]
+            })
+            .collect();
 
-        colors
+        let mut image = RaytracedImage::new(normals, self.image_width, self.image_height);
+        image.samples_per_pixel = self.samples_per_pixel;
+        image.region = self.region;
+        Ok(image)
     }
 
-    fn render_multithreaded_without_bvh(self) -> Vec<Color> {
-        let world = HittableListOptions::HittableList(self.world);
-
-        let mut colors = vec![BLACK; self.image_height as usize * self.image_width as usize];
+    /// Render the light arriving at each pixel resolved by its time of flight, see [`transient`](crate::transient).
+    ///
+    /// Every pixel inside the [`RenderRegion`] is traced with `samples_per_pixel` jittered camera [`Ray`]s and the path tracer, regardless of the [`Integrator`]. The [`Filter`] is not applied.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, color::BLACK, lights::PointLight, materials::Lambertian, shapes::{Plane, Rectangle}, transient::TransientSettings};
+    /// let camera = Camera::new(vector![0., 0., 0.], vector![0., 0., -1.], vector![0., 1., 0.], 0.2, 1., 0., 1.);
+    /// let mut raytracer = Raytracer::new(camera, BLACK, 3, 3, 4, 1);
+    /// raytracer.world.push(Rectangle::new(Plane::XY, vector![0., 0., -2.], 10., 10., Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+    /// raytracer.lights.push_analytic(PointLight::new(vector![0., 0., 0.], color![4., 4., 4.]));
+    ///
+    /// // The light travels 2 to the wall and 2 back to the camera.
+    /// let transient = raytracer.render_transient(TransientSettings::new(0.25, 0.5, 10)).unwrap();
+    /// let pixel = transient.pixel(1, 1);
+    /// assert!(pixel[7].r() > 0.);
+    /// assert_eq!(pixel.iter().filter(|color| color.r() > 0.).count(), 1);
+    /// ```
+    pub fn render_transient(
+        mut self,
+        settings: TransientSettings,
+    ) -> Result<TransientImage, Error> {
+        self.check_config()?;
+        let (world, lights) = self.prepare(true, &mut RenderStats::default())?;
+        let scene = self.scene(&world, &lights);
 
-        colors
-            .par_iter_mut()
-            .enumerate()
-            .for_each(|(index, color)| {
-                let mut rng = rand::thread_rng();
+        let pixel_count = self.image_height as usize * self.image_width as usize;
+        let pixels = (0..pixel_count)
+            .into_par_iter()
+            .map(|index| {
+                let mut frames = vec![BLACK; settings.frames];
+                if !self.region.contains_index(index, self.image_width) {
+                    return frames;
+                }
+                let mut rng = crate::random::rng();
                 let i = index % self.image_width as usize;
                 let j = self.image_height as usize - index / self.image_width as usize - 1;
 
                 for _ in 0..self.samples_per_pixel {
-                    let u = (i as f32 + rng.gen::<f32>()) / (self.image_width - 1) as f32;
-                    let v = (j as f32 + rng.gen::<f32>()) / (self.image_height - 1) as f32;
-                    *color += Raytracer::ray_color(
-                        &world,
-                        self.camera.get_ray(u, v),
-                        self.background,
+                    let u =
+                        (i as Float + rng.gen::<Float>()) / (self.image_width - 1).max(1) as Float;
+                    let v =
+                        (j as Float + rng.gen::<Float>()) / (self.image_height - 1).max(1) as Float;
+                    let ray = self.camera.get_ray(u, v);
+                    transient::trace(
+                        &scene,
+                        ray,
                         self.max_depth,
+                        &self.integrator_settings,
+                        &settings,
+                        &mut frames,
                     );
                 }
-
-                if let Some(bar) = &self.progressbar {
-                    bar.inc(1);
+                for color in &mut frames {
+                    *color /= self.samples_per_pixel as Float;
                 }
+                frames
+            })
+            .collect();
+        Ok(TransientImage::new(
+            settings,
+            pixels,
+            self.image_width,
+            self.image_height,
+        ))
+    }
 
-                *color = color
-                    .into_iter()
-                    .map(|color| (color / self.samples_per_pixel as f32).sqrt())
-                    .collect();
-            });
+    /// Check the settings that would make the render fail.
+    fn check_config(&self) -> Result<(), Error> {
+        if self.image_width == 0 || self.image_height == 0 {
+            return Err(Error::Config(format!(
+                "image of size {}x{} has no pixels",
+                self.image_width, self.image_height
+            )));
+        }
+        if self.samples_per_pixel == 0 {
+            return Err(Error::Config("no samples per pixel".to_string()));
+        }
+        Ok(())
+    }
 
-        colors
-    }
-
-    /// Colors the [`Ray`] according to hits.
-    fn ray_color(
-        world_option: &HittableListOptions,
-        ray: Ray,
-        background: Color,
-        depth: u16,
-    ) -> Color {
-        if depth == 0 {
-            return BLACK;
-        }
-
-        match world_option {
-            HittableListOptions::Bvh(world) => {
-                if let Some(hit) = world.hit(ray, 0.001, f32::INFINITY) {
-                    let emitted = hit.material().emit(hit.u, hit.v, hit.point);
-                    if let Some((scattered, attenuation)) = hit.material().scatter(ray, hit) {
-                        return emitted
-                            + attenuation
-                                * Raytracer::ray_color(
-                                    world_option,
-                                    scattered,
-                                    background,
-                                    depth - 1,
-                                );
-                    }
-                    return emitted;
+    /// Bake the light arriving at `surface` into a lightmap in its UV layout, see [`baking`](crate::baking).
+    ///
+    /// The lightmap has the size of the image, and every texel is traced with `samples_per_pixel` paths of the [path tracer](Integrator::PathTracer), whatever the `integrator`.
+    /// The camera, the render region, the filter, and the alpha mode are ignored. The alpha of the lightmap is the fraction of each texel that `surface` covers.
+    pub fn bake(self, surface: &UvSurface) -> Result<RaytracedImage, Error> {
+        let start = Instant::now();
+        let (width, height) = (self.image_width, self.image_height);
+        let samples_per_pixel = self.samples_per_pixel;
+        let (colors, coverage) = self.bake_scene(|scene, baker, progress| {
+            baker.bake(scene, surface, (width, height), progress)
+        })?;
+        log_event!(info, "baked a lightmap in {:?}", start.elapsed());
+
+        let mut image = RaytracedImage::new(colors, width, height);
+        image.samples_per_pixel = samples_per_pixel;
+        image.alpha = Some(coverage);
+        image.stats.total = start.elapsed();
+        Ok(image)
+    }
+
+    /// Bake [`ShProbe`]s at `positions`, see [`probes`](crate::probes).
+    ///
+    /// Each probe traces `samples_per_pixel` paths in random directions, so this should be much higher than for a render, e.g. a few thousand. The camera and image size are ignored.
+    pub fn bake_sh_probes(self, positions: &[Vector3<Float>]) -> Result<Vec<ShProbe>, Error> {
+        let probes =
+            self.bake_scene(|scene, baker, progress| baker.sh_probes(scene, positions, progress))?;
+        log_event!(info, "baked {} light probes", probes.len());
+        Ok(probes)
+    }
+
+    /// Bake [`CubeMap`]s with faces of `size` × `size` texels at `positions`, see [`probes`](crate::probes).
+    ///
+    /// Each texel traces `samples_per_pixel` paths. The camera and image size are ignored.
+    pub fn bake_cube_maps(
+        self,
+        positions: &[Vector3<Float>],
+        size: u32,
+    ) -> Result<Vec<CubeMap>, Error> {
+        if size == 0 {
+            return Err(Error::Config("the cube maps have no texels".to_string()));
+        }
+        let cube_maps = self.bake_scene(|scene, baker, progress| {
+            baker.cube_maps(scene, positions, size, progress)
+        })?;
+        log_event!(info, "baked {} cube maps", cube_maps.len());
+        Ok(cube_maps)
+    }
+
+    /// Build the [`Scene`] with all lights for baking and pass it to `bake`.
+    fn bake_scene<T>(
+        self,
+        bake: impl FnOnce(&Scene, &Baker, Option<&dyn RenderProgress>) -> T,
+    ) -> Result<T, Error> {
+        self.check_config()?;
+
+        let lights = render_lights(self.lights, &self.world, &self.background);
+        let world = accelerate(self.world, true, self.accelerator, self.bvh_strategy)?;
+        let scene = Scene {
+            world: &world,
+            lights: &lights,
+            background: &self.background,
+            camera_background: true,
+            fog: self.fog.as_ref(),
+            offset: self.integrator_settings.ray_offset(),
+        };
+
+        let baker = Baker {
+            samples_per_pixel: self.samples_per_pixel,
+            max_depth: self.max_depth,
+            settings: &self.integrator_settings,
+        };
+        let progress = self.progress.as_deref();
+        let result = bake(&scene, &baker, progress);
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+        Ok(result)
+    }
+
+    fn render_multithreaded(mut self, use_bvh: bool) -> Result<RaytracedImage, Error> {
+        self.check_config()?;
+
+        let start = Instant::now();
+        let mut stats = RenderStats::default();
+        let (world, lights) = self.prepare(use_bvh, &mut stats)?;
+        let scene = self.scene(&world, &lights);
+
+        let progress = self.progress.as_deref();
+        if let Some(progress) = progress {
+            progress.start(self.progress_steps(&self.region));
+        }
+
+        let (colors, coverage, samples) = self.trace(
+            &scene,
+            &self.camera,
+            (self.image_width, self.image_height),
+            &self.region,
+            progress,
+            &mut stats,
+        );
+
+        let mut image = RaytracedImage::new(colors, self.image_width, self.image_height);
+        image.samples_per_pixel = self.samples_per_pixel;
+        image.region = self.region;
+        if self.alpha != Alpha::Opaque {
+            image.alpha = Some(coverage);
+        }
+        image.samples = samples;
+        stats.total = start.elapsed();
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+        log_event!(
+            info,
+            "rendered {} samples per pixel in {:?}",
+            self.samples_per_pixel,
+            stats.total
+        );
+        image.stats = stats;
+        Ok(image)
+    }
+
+    /// Collect the lights and build the [`Accelerator`] of the `world`, which is left empty.
+    fn prepare(
+        &mut self,
+        use_bvh: bool,
+        stats: &mut RenderStats,
+    ) -> Result<(HittableListOptions, Lights), Error> {
+        // The construction of the BVH is random, too.
+        random::seeded(self.seed, &[], || {
+            let lights = render_lights(
+                std::mem::take(&mut self.lights),
+                &self.world,
+                &self.background,
+            );
+
+            let bvh_start = Instant::now();
+            let world = accelerate(
+                std::mem::take(&mut self.world),
+                use_bvh,
+                self.accelerator,
+                self.bvh_strategy,
+            )?;
+            stats.bvh_build = bvh_start.elapsed();
+            if let HittableListOptions::Bvh(bvh) = &world {
+                stats.bvh_depth = Some(bvh.depth());
+                stats.bvh_nodes = Some(bvh.node_count());
+            }
+            log_event!(
+                debug,
+                "built the {:?} in {:?}",
+                self.accelerator,
+                stats.bvh_build
+            );
+            Ok((world, lights))
+        })
+    }
+
+    /// The camera that sees only the rows `y0..y1` of the image, see [`traced_rows`].
+    fn band_camera(&self, (y0, y1): (u32, u32)) -> Camera {
+        match (y0, y1) == (0, self.image_height) {
+            true => self.camera.clone(),
+            false => self.camera.band((y0, y1), self.image_height),
+        }
+    }
+
+    /// The [`Scene`] seen by the camera, with the `world` and `lights` from [`prepare`](Raytracer::prepare).
+    fn scene<'a>(&'a self, world: &'a HittableListOptions, lights: &'a Lights) -> Scene<'a> {
+        Scene {
+            world,
+            lights,
+            background: &self.background,
+            camera_background: !matches!(
+                self.alpha,
+                Alpha::Transparent {
+                    show_background: false
                 }
+            ),
+            fog: self.fog.as_ref(),
+            offset: self.integrator_settings.ray_offset(),
+        }
+    }
+
+    /// Number of steps of the `progress` while [tracing](Raytracer::trace) the pixels of `region`.
+    fn progress_steps(&self, region: &RenderRegion) -> u64 {
+        let passes = match self.integrator {
+            // Both advance once per pass over the image.
+            Integrator::PathTracer | Integrator::Sppm { .. } => self.samples_per_pixel as u64,
+            _ => 1,
+        };
+        region.pixel_count() as u64 * passes
+    }
+
+    /// Trace the pixels of `region` in an image of `image_width` × `image_height` pixels seen through `camera` with the `integrator`.
+    ///
+    /// Returns the colors and the coverage of all pixels, and their [`SampleImage`] if it is recorded. The colors of partially covered pixels are not premultiplied with their alpha.
+    fn trace(
+        &self,
+        scene: &Scene,
+        camera: &Camera,
+        (image_width, image_height): (u32, u32),
+        region: &RenderRegion,
+        progress: Option<&dyn RenderProgress>,
+        stats: &mut RenderStats,
+    ) -> (Vec<Color>, Vec<Float>, Option<SampleImage>) {
+        let trace_start = Instant::now();
+        let (mut colors, coverage, samples) = match self.integrator {
+            Integrator::PathTracer => {
+                let wavefront = Wavefront {
+                    samples_per_pixel: self.samples_per_pixel,
+                    max_depth: self.max_depth,
+                    settings: &self.integrator_settings,
+                    filter: self.filter,
+                    statistics: self.sample_statistics,
+                    seed: self.seed,
+                };
+                wavefront.render(
+                    scene,
+                    camera,
+                    (image_width, image_height),
+                    region,
+                    progress,
+                    stats,
+                )
             }
-            HittableListOptions::HittableList(world) => {
-                if let Some(hit) = world.hit(ray, 0.001, f32::INFINITY) {
-                    let emitted = hit.material().emit(hit.u, hit.v, hit.point);
-                    if let Some((scattered, attenuation)) = hit.material().scatter(ray, hit) {
-                        return emitted
-                            + attenuation
-                                * Raytracer::ray_color(
-                                    world_option,
-                                    scattered,
-                                    background,
-                                    depth - 1,
-                                );
+            Integrator::Sppm {
+                photons_per_iteration,
+                initial_radius,
+            } => {
+                let photon_mapper = PhotonMapper {
+                    photons_per_iteration,
+                    initial_radius,
+                    max_depth: self.max_depth,
+                    settings: &self.integrator_settings,
+                };
+                let (colors, coverage) = photon_mapper.render(
+                    scene,
+                    camera,
+                    (image_width, image_height),
+                    self.samples_per_pixel,
+                    region,
+                    progress,
+                );
+                (colors, coverage, None)
+            }
+            _ => {
+                let pixel_count = image_height as usize * image_width as usize;
+                let mut film = Film::new(self.filter, (image_width, image_height), *region)
+                    .with_statistics(self.sample_statistics);
+                let mut coverage = vec![0.; pixel_count];
+
+                let pixel_size = (
+                    1. / (image_width - 1).max(1) as Float,
+                    1. / (image_height - 1).max(1) as Float,
+                );
+                // Trace a few rows at a time, so that the samples of all pixels do not have to be stored before splatting them.
+                let chunk = RENDER_CHUNK_ROWS * image_width as usize;
+                for start in (0..pixel_count).step_by(chunk) {
+                    let end = (start + chunk).min(pixel_count);
+                    log_event!(trace, "tracing pixels {start} to {end}");
+                    let traced: Vec<_> = (start..end)
+                        .into_par_iter()
+                        .map(|index| {
+                            random::seeded(self.seed, &[index as u64], || {
+                                if !region.contains_index(index, image_width) {
+                                    return (Vec::new(), 0.);
+                                }
+                                let mut rng = crate::random::rng();
+                                let i = index % image_width as usize;
+                                let j = image_height as usize - index / image_width as usize - 1;
+
+                                let mut samples =
+                                    Vec::with_capacity(self.samples_per_pixel as usize);
+                                let mut coverage = 0.;
+                                for _ in 0..self.samples_per_pixel {
+                                    let offset: (Float, Float) = (rng.gen(), rng.gen());
+                                    let u =
+                                        (i as Float + offset.0) / (image_width - 1).max(1) as Float;
+                                    let v = (j as Float + offset.1)
+                                        / (image_height - 1).max(1) as Float;
+                                    // Rays blocked by a lens system are black.
+                                    let Some(ray) =
+                                        camera.try_get_ray_with_differentials(u, v, pixel_size)
+                                    else {
+                                        samples.push((offset, BLACK));
+                                        continue;
+                                    };
+
+                                    // Only the transparent background needs to know whether the camera ray hits something.
+                                    if self.alpha != Alpha::Opaque {
+                                        let Some(hit) = scene.world.hit(
+                                            ray,
+                                            scene.offset.t_min(),
+                                            Float::INFINITY,
+                                        ) else {
+                                            let background = if scene.camera_background {
+                                                self.background.color(ray.direction())
+                                            } else {
+                                                BLACK
+                                            };
+                                            samples.push((offset, background));
+                                            continue;
+                                        };
+                                        coverage +=
+                                            crate::integrator::coverage(scene, ray, Some(&hit));
+                                    }
+
+                                    let sample = self.integrator.radiance(
+                                        scene,
+                                        ray,
+                                        self.max_depth,
+                                        &self.integrator_settings,
+                                    );
+                                    samples.push((offset, self.integrator_settings.clamp(sample)));
+                                }
+
+                                if let Some(progress) = progress {
+                                    progress.advance(1);
+                                }
+
+                                (samples, coverage / self.samples_per_pixel as Float)
+                            })
+                        })
+                        .collect();
+
+                    for (index, (samples, pixel_coverage)) in (start..end).zip(traced) {
+                        for (offset, color) in samples {
+                            film.add_sample(index, offset, color);
+                        }
+                        coverage[index] = pixel_coverage;
                     }
-                    return emitted;
                 }
+
+                let samples = film.sample_image();
+                (film.into_colors(), coverage, samples)
             }
+        };
+
+        if !matches!(self.integrator, Integrator::PathTracer) {
+            stats.shading += trace_start.elapsed();
+            let paths = region.pixel_count() as u64 * self.samples_per_pixel as u64;
+            stats.paths += paths;
+            stats.rays += paths;
         }
 
-        background
+        if !scene.camera_background {
+            // Without the background, the colors of partially covered pixels are premultiplied with their alpha.
+            colors
+                .par_iter_mut()
+                .zip(&coverage)
+                .filter(|(_, &coverage)| coverage > 0.)
+                .for_each(|(color, &coverage)| *color /= coverage);
+        }
+        (colors, coverage, samples)
     }
 }
 
+/// Receiver of the individual samples of [`Raytracer::render_samples`].
+///
+/// `Sync` is necessary because the samples of different pixels arrive from several threads at once. Closures taking the pixel and the [`Color`] of the sample are sinks.
+pub trait SampleSink: Sync {
+    /// Receive the `color` of a sample of the pixel (`x`, `y`), counted from the top left.
+    fn add_sample(&self, x: u32, y: u32, color: Color);
+}
+
+impl<F: Fn(u32, u32, Color) + Sync> SampleSink for F {
+    fn add_sample(&self, x: u32, y: u32, color: Color) {
+        self(x, y, color);
+    }
+}
+
+/// Arrangement of the two eyes of a [stereo image](Raytracer::render_stereo).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StereoLayout {
+    /// Left eye on the left, right eye on the right.
+    #[default]
+    SideBySide,
+    /// Left eye on top, right eye below.
+    TopBottom,
+}
+
+/// How the alpha channel of a rendered image is computed.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, raytracer::Alpha, shapes::Sphere};
+/// let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+/// let mut raytracer = Raytracer::new(camera, color![0.5, 0.7, 1.], 9, 9, 4, 4)
+///     .with_alpha(Alpha::Transparent { show_background: false });
+/// raytracer.world.push(Sphere::new(vector![0., 0., -1.], 0.9, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+///
+/// let image = raytracer.render().unwrap().into_rgba_image().unwrap();
+/// assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 0]);
+/// assert_eq!(image.get_pixel(4, 4).0[3], 255);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Alpha {
+    /// Every pixel is opaque.
+    #[default]
+    Opaque,
+    /// The alpha of a pixel is the fraction of its camera [`Ray`](crate::ray::Ray)s that hit something, so the image can be composited over another one.
+    ///
+    /// If `show_background` is `false`, camera [`Ray`](crate::ray::Ray)s that miss everything are black instead of the [`Background`] (which still lights the scene).
+    Transparent { show_background: bool },
+}
+
+/// A rectangle of pixels from (`x0`, `y0`) (included) to (`x1`, `y1`) (excluded), counted from the top left.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::raytracer::RenderRegion;
+/// let region = RenderRegion::new(2, 1, 4, 3);
+/// assert_eq!(region.pixel_count(), 4);
+/// assert!(region.contains(3, 2));
+/// assert!(!region.contains(4, 2));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderRegion {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+impl RenderRegion {
+    pub fn new(x0: u32, y0: u32, x1: u32, y1: u32) -> Self {
+        Self { x0, y0, x1, y1 }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.x1.saturating_sub(self.x0)
+    }
+
+    pub fn height(&self) -> u32 {
+        self.y1.saturating_sub(self.y0)
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.width() as usize * self.height() as usize
+    }
+
+    /// Whether the pixel (`x`, `y`) lies inside.
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        (self.x0..self.x1).contains(&x) && (self.y0..self.y1).contains(&y)
+    }
+
+    /// Whether the pixel with `index` in an image of width `image_width` (stored row by row from the top) lies inside.
+    pub(crate) fn contains_index(&self, index: usize, image_width: u32) -> bool {
+        let x = index % image_width as usize;
+        let y = index / image_width as usize;
+        (self.x0 as usize..self.x1 as usize).contains(&x)
+            && (self.y0 as usize..self.y1 as usize).contains(&y)
+    }
+}
+
+/// The object seen in a pixel, see [`Raytracer::pick`].
+///
+/// # Fields
+/// - `object_id`: ID of the object, if it has one (see [`Named`]).
+/// - `distance`: Distance from the camera to `point`.
+/// - `point`: Point where the object was hit.
+/// - `normal`: Geometric normal of the object at `point`, facing the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickResult {
+    pub object_id: Option<u32>,
+    pub distance: Float,
+    pub point: Vector3<Float>,
+    pub normal: Vector3<Float>,
+}
+
 /// A result of a raytraced render.
 ///
 /// This is a wrapper around the result of [`render`](Raytracer::render) in order to allow for interoperability with different image formats.
 pub struct RaytracedImage {
     image: Vec<Color>,
-    image_width: u16,
-    image_height: u16,
+    alpha: Option<Vec<Float>>,
+    image_width: u32,
+    image_height: u32,
+    stats: RenderStats,
+    exposure: Float,
+    white_point: Float,
+    gamma: Float,
+    grading: Option<ColorGrading>,
+    dithering: Dithering,
+    metadata: Option<RenderMetadata>,
+    samples: Option<SampleImage>,
+    samples_per_pixel: u16,
+    region: RenderRegion,
 }
 
 impl RaytracedImage {
+    pub(crate) fn new(image: Vec<Color>, image_width: u32, image_height: u32) -> Self {
+        Self {
+            image,
+            alpha: None,
+            image_width,
+            image_height,
+            stats: RenderStats::default(),
+            exposure: 0.,
+            white_point: 1.,
+            gamma: 2.,
+            grading: None,
+            dithering: Dithering::None,
+            metadata: None,
+            samples: None,
+            samples_per_pixel: 1,
+            region: RenderRegion::new(0, 0, image_width, image_height),
+        }
+    }
+
+    /// Consume `self` and set the gamma of the displayable image (2 by default).
+    pub fn with_gamma(mut self, gamma: Float) -> Self {
+        self.gamma = gamma.max(Float::EPSILON);
+        self
+    }
+
+    /// Consume `self` and grade the displayable image with `grading` after gamma correction, see [`grading`](crate::grading).
+    pub fn with_grading(mut self, grading: ColorGrading) -> Self {
+        self.grading = Some(grading);
+        self
+    }
+
+    /// Consume `self` and add the glow of a [`Bloom`] to the bright pixels, see [`postprocess`](crate::postprocess).
+    pub fn with_bloom(mut self, bloom: Bloom) -> Self {
+        bloom.apply(&mut self.image, self.image_width, self.image_height);
+        self
+    }
+
+    /// Consume `self` and apply [`LensEffects`] like a vignette or chromatic aberration, see [`postprocess`](crate::postprocess).
+    pub fn with_lens_effects(mut self, effects: LensEffects) -> Self {
+        let alpha = self.alpha.as_deref_mut();
+        effects.apply(&mut self.image, alpha, self.image_width, self.image_height);
+        self
+    }
+
+    /// Consume `self` and set the [`Dithering`] when converting to 8 bits per channel (none by default).
+    ///
+    /// This applies to [`into_image`](RaytracedImage::into_image), [`into_rgba_image`](RaytracedImage::into_rgba_image), [`into_ppm`](RaytracedImage::into_ppm), and the functions saving them.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, dithering::Dithering};
+    /// // A gray between two levels after gamma correction
+    /// let raytracer = Raytracer::new(Camera::default(), color![0.1, 0.1, 0.1], 8, 8, 1, 1);
+    /// let image = raytracer.render().unwrap().with_dithering(Dithering::Ordered).into_image().unwrap();
+    /// let levels: Vec<u8> = image.pixels().map(|pixel| pixel.0[0]).collect();
+    /// assert!(levels.contains(&80) && levels.contains(&81));
+    /// ```
+    pub fn with_dithering(mut self, dithering: Dithering) -> Self {
+        self.dithering = dithering;
+        self
+    }
+
+    /// Consume `self` and embed `metadata` when saving the image as a PNG or OpenEXR, see [`metadata`](crate::metadata).
+    pub fn with_metadata(mut self, metadata: RenderMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// [`RenderMetadata`] embedded when saving, if it was set.
+    pub fn metadata(&self) -> Option<&RenderMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Convert the image to a [`Framebuffer`] to accumulate further renders of the same scene.
+    ///
+    /// Only the pixels of the [`RenderRegion`] count as sampled, each with the `samples_per_pixel` of the render.
+    pub fn into_framebuffer(self) -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(self.image_width, self.image_height);
+        framebuffer.accumulate(&self).expect("the sizes match");
+        framebuffer
+    }
+
+    /// How many samples each pixel of the [`render_region`](RaytracedImage::render_region) received.
+    pub fn samples_per_pixel(&self) -> u16 {
+        self.samples_per_pixel
+    }
+
+    /// [`RenderRegion`] of the pixels that were traced. The others are black.
+    pub fn render_region(&self) -> RenderRegion {
+        self.region
+    }
+
+    /// Consume `self` and brighten (or darken for negative values) the image by `exposure` stops.
+    ///
+    /// This scales the linear radiance by 2^`exposure` when converting it to a displayable image, so dark renders can be adjusted without rendering them again.
+    /// The [`colors`](RaytracedImage::colors) and the [32 bit float image](RaytracedImage::into_rgb32f_image) stay unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, raytracer::RaytracedImage};
+    /// let raytracer = Raytracer::new(Camera::default(), color![0.04, 0.04, 0.04], 4, 4, 1, 1);
+    /// let image = raytracer.render().unwrap().with_exposure(2.).into_image().unwrap();
+    /// // 0.04 · 2² = 0.16 is 0.4 after gamma correction
+    /// assert_eq!(image.get_pixel(0, 0).0, [102, 102, 102]);
+    /// ```
+    pub fn with_exposure(mut self, exposure: Float) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    /// Consume `self` and set the linear radiance that is displayed as white (1 by default).
+    ///
+    /// Like the [exposure](RaytracedImage::with_exposure), this is applied before clamping and gamma correction, so highlights up to `white_point` keep their detail.
+    pub fn with_white_point(mut self, white_point: Float) -> Self {
+        self.white_point = white_point.max(Float::EPSILON);
+        self
+    }
+
+    /// [`RenderStats`] of the render that produced the image.
+    pub fn render_stats(&self) -> &RenderStats {
+        &self.stats
+    }
+
+    /// Read an 8 bit image, e.g. a reference render saved with [`save`](RaytracedImage::save), by undoing the gamma correction.
+    pub fn from_rgb_image(image: &RgbImage) -> Self {
+        let colors = image
+            .pixels()
+            .map(|&pixel| Color::from(pixel).into_iter().map(|c| c * c).collect())
+            .collect();
+        Self::new(colors, image.width(), image.height())
+    }
+
+    /// Width and height of the image.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.image_width, self.image_height)
+    }
+
+    /// Linear colors of the pixels (before gamma correction), row by row from the top.
+    pub fn colors(&self) -> &[Color] {
+        &self.image
+    }
+
+    /// Alpha of the pixels, row by row from the top, if the image was rendered with a transparent [`Alpha`] mode.
+    pub fn alpha(&self) -> Option<&[Float]> {
+        self.alpha.as_deref()
+    }
+
+    /// Number of samples and variance of the pixels, if the image was rendered [`with_sample_statistics`](Raytracer::with_sample_statistics).
+    pub fn sample_image(&self) -> Option<&SampleImage> {
+        self.samples.as_ref()
+    }
+
+    /// Colors of the pixels after the exposure, the white point, gamma correction, and the [`ColorGrading`].
+    fn gamma_corrected(&self) -> impl Iterator<Item = Color> + '_ {
+        let scale = self.exposure.exp2() / self.white_point;
+        let exponent = 1. / self.gamma;
+        self.image.iter().map(move |color| {
+            let color = color
+                .into_iter()
+                .map(|c| (c * scale).max(0.).powf(exponent))
+                .collect();
+            match &self.grading {
+                Some(grading) => grading.apply(color),
+                None => color,
+            }
+        })
+    }
+
+    /// Colors of the pixels after [gamma correction](RaytracedImage::gamma_corrected) and [`Dithering`] to 8 bits.
+    fn quantized(&self) -> impl Iterator<Item = Color> + '_ {
+        self.quantized_at(0)
+    }
+
+    /// Colors of the pixels like [`quantized`](RaytracedImage::quantized), with the dithering pattern placed as if the image started at row `first_row` of a larger one.
+    pub(crate) fn quantized_at(&self, first_row: u32) -> impl Iterator<Item = Color> + '_ {
+        let (width, dithering) = (self.image_width, self.dithering);
+        self.gamma_corrected()
+            .enumerate()
+            .map(move |(index, color)| {
+                let (x, y) = (index as u32 % width, first_row + index as u32 / width);
+                color
+                    .into_iter()
+                    .map(|c| dithering.quantize(c, x, y))
+                    .collect()
+            })
+    }
+
     /// Save the image.
     ///
     /// Defaults to [`image`] as the backend.
-    pub fn save<P: AsRef<Path>>(self, path: P) -> Result<(), ImageError> {
-        let image = self.into_image().expect("creating image");
-        image.save(path)
+    pub fn save<P: AsRef<Path>>(self, path: P) -> Result<(), Error> {
+        log_event!(debug, "saving {}", path.as_ref().display());
+        let metadata = self.metadata.clone();
+        let image = self.into_image().ok_or_else(buffer_error)?;
+        match metadata {
+            Some(metadata) if is_png(path.as_ref()) => {
+                save_png(path, image.dimensions(), 3, false, &image, &metadata)
+            }
+            _ => Ok(image.save(path)?),
+        }
     }
 
     /// Convert the image to a [`RgbImage`].
     ///
     /// Returns [`None`] if the [`Vec`] of [`Color`]s is not long enough.
     pub fn into_image(self) -> Option<RgbImage> {
+        let image: Vec<u8> = self.quantized().flat_map(Into::<[u8; 3]>::into).collect();
+        RgbImage::from_vec(self.image_width, self.image_height, image)
+    }
+
+    /// Convert the image to a [`RgbaImage`].
+    ///
+    /// Images rendered without a transparent [`Alpha`] mode are opaque.
+    /// Returns [`None`] if the [`Vec`] of [`Color`]s is not long enough.
+    pub fn into_rgba_image(self) -> Option<RgbaImage> {
         let image: Vec<u8> = self
-            .image
-            .iter()
-            .flat_map(|color| Into::<[u8; 3]>::into(*color))
+            .quantized()
+            .enumerate()
+            .flat_map(|(index, color)| {
+                let alpha = self.alpha.as_ref().map_or(1., |alpha| alpha[index]);
+                let [r, g, b] = Into::<[u8; 3]>::into(color);
+                [r, g, b, (256. * alpha.clamp(0., 0.999)) as u8]
+            })
             .collect();
-        RgbImage::from_vec(self.image_width.into(), self.image_height.into(), image)
+        RgbaImage::from_vec(self.image_width, self.image_height, image)
+    }
+
+    /// Save the image with its alpha channel, e.g. as a PNG.
+    pub fn save_rgba<P: AsRef<Path>>(self, path: P) -> Result<(), Error> {
+        let metadata = self.metadata.clone();
+        let image = self.into_rgba_image().ok_or_else(buffer_error)?;
+        match metadata {
+            Some(metadata) if is_png(path.as_ref()) => {
+                save_png(path, image.dimensions(), 4, false, &image, &metadata)
+            }
+            _ => Ok(image.save(path)?),
+        }
+    }
+
+    /// Convert the image to 16 bits per channel, which avoids banding in smooth gradients.
+    ///
+    /// Returns [`None`] if the [`Vec`] of [`Color`]s is not long enough.
+    pub fn into_rgb16_image(self) -> Option<ImageBuffer<Rgb<u16>, Vec<u16>>> {
+        let image: Vec<u16> = self
+            .gamma_corrected()
+            .flat_map(|color| {
+                color
+                    .into_iter()
+                    .map(|c| (65536. * c.clamp(0., 0.99999)) as u16)
+            })
+            .collect();
+        ImageBuffer::from_vec(self.image_width, self.image_height, image)
+    }
+
+    /// Save the image as a PNG with 16 bits per channel.
+    pub fn save_png16<P: AsRef<Path>>(self, path: P) -> Result<(), Error> {
+        let metadata = self.metadata.clone();
+        let image = self.into_rgb16_image().ok_or_else(buffer_error)?;
+        match metadata {
+            Some(metadata) => {
+                let samples: Vec<u8> = image.iter().flat_map(|c| c.to_be_bytes()).collect();
+                save_png(path, image.dimensions(), 3, true, &samples, &metadata)
+            }
+            None => Ok(image.save_with_format(path, ImageFormat::Png)?),
+        }
+    }
+
+    /// Convert the image to 32 bit floats without gamma correction or clamping, i.e. the linear radiance.
+    ///
+    /// Returns [`None`] if the [`Vec`] of [`Color`]s is not long enough.
+    // The cast is only necessary if `Float` is `f64`.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn into_rgb32f_image(self) -> Option<Rgb32FImage> {
+        let image: Vec<f32> = self.image.into_iter().flatten().map(|c| c as f32).collect();
+        Rgb32FImage::from_vec(self.image_width, self.image_height, image)
+    }
+
+    /// Save the linear radiance as a TIFF with 32 bit floats per channel.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, shapes::Sphere};
+    /// let camera = Camera::new(vector![0., 0., 1.], vector![0., 0., 0.], vector![0., 1., 0.], float::consts::FRAC_PI_2, 1., 0., 1.);
+    /// let mut raytracer = Raytracer::new(camera, color![0.5, 0.7, 1.], 100, 100, 10, 10);
+    /// raytracer.world.push(Sphere::new(vector![0., 0., -1.], 0.5, Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+    /// raytracer.render().unwrap().save_tiff32f("image.tiff").unwrap();
+    /// ```
+    pub fn save_tiff32f<P: AsRef<Path>>(self, path: P) -> Result<(), Error> {
+        let encoding_error = |error| {
+            Error::Image(ImageError::Encoding(EncodingError::new(
+                ImageFormat::Tiff.into(),
+                error,
+            )))
+        };
+        let image = self.into_rgb32f_image().ok_or_else(buffer_error)?;
+
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = TiffEncoder::new(file).map_err(encoding_error)?;
+        encoder
+            .write_image::<RGB32Float>(image.width(), image.height(), image.as_raw())
+            .map_err(encoding_error)
+    }
+
+    /// Save the linear radiance as an OpenEXR image with 32 bit floats per channel.
+    pub fn save_exr<P: AsRef<Path>>(self, path: P) -> Result<(), Error> {
+        if let Some(metadata) = self.metadata.clone() {
+            let mut output = RenderOutput::new(self.image_width, self.image_height);
+            output.add_beauty(&self)?;
+            return output.with_metadata(metadata).save_exr(path);
+        }
+        let image = self.into_rgb32f_image().ok_or_else(buffer_error)?;
+        Ok(image.save_with_format(path, ImageFormat::OpenExr)?)
     }
 
     /// Convert the image to a [`PPM`].
     ///
     /// Saving the image as an [`image`](RaytracedImage::into_image) should be preferred as other image formats are much smaller and the resulting [`RgbImage`] has more possible functions.
     pub fn into_ppm(self) -> PPM {
-        PPM::new(self.image, self.image_width, self.image_height)
+        PPM::new(
+            self.quantized().collect(),
+            self.image_width,
+            self.image_height,
+        )
+    }
+}
+
+/// The `lights` sampled directly when rendering `world`: the emissive objects of `world` if there are no area lights, and the sun of the `background`.
+fn render_lights(mut lights: Lights, world: &HittableList, background: &Background) -> Lights {
+    if !lights.has_area_lights() {
+        lights.collect_from_world(world);
+    }
+    if let Some(sun) = background.sun() {
+        lights.push_analytic(sun);
+    }
+    lights
+}
+
+/// The rows that are traced for the rows `y0..y1` of an image with `image_height` rows: `margin` more on both sides for the filter, but at least two for the [band camera](Raytracer::band_camera).
+fn traced_rows((y0, y1): (u32, u32), margin: u32, image_height: u32) -> (u32, u32) {
+    let (mut t0, mut t1) = (y0.saturating_sub(margin), (y1 + margin).min(image_height));
+    if t1 - t0 < 2 && image_height >= 2 {
+        if t1 < image_height {
+            t1 += 1;
+        } else {
+            t0 -= 1;
+        }
+    }
+    (t0, t1)
+}
+
+/// Build the [`Accelerator`] for `world`, or use it as is if `use_bvh` is false or it contains unbounded objects.
+pub(crate) fn accelerate(
+    world: HittableList,
+    use_bvh: bool,
+    accelerator: Accelerator,
+    bvh_strategy: BvhBuildStrategy,
+) -> Result<HittableListOptions, Error> {
+    Ok(
+        match (use_bvh && Bvh::check_hittable_list(&world), accelerator) {
+            (true, Accelerator::Bvh) => {
+                HittableListOptions::Bvh(Bvh::build(world, 0., 0., bvh_strategy)?)
+            }
+            (true, Accelerator::KdTree) => HittableListOptions::KdTree(KdTree::new(world, 0., 0.)?),
+            (true, Accelerator::Grid) => HittableListOptions::Grid(Grid::new(world, 0., 0.)?),
+            (false, _) => HittableListOptions::HittableList(world),
+        },
+    )
+}
+
+/// Join the pixels of the two eyes of a stereo image of width `width` according to `layout`.
+fn join_stereo<T: Copy>(left: &[T], right: &[T], width: u32, layout: StereoLayout) -> Vec<T> {
+    match layout {
+        StereoLayout::SideBySide => left
+            .chunks(width as usize)
+            .zip(right.chunks(width as usize))
+            .flat_map(|(left, right)| left.iter().chain(right))
+            .copied()
+            .collect(),
+        StereoLayout::TopBottom => left.iter().chain(right).copied().collect(),
+    }
+}
+
+/// [`Error`] for a pixel buffer that does not match the size of the [`RaytracedImage`].
+pub(crate) fn buffer_error() -> Error {
+    Error::Config("pixel buffer does not match the image size".to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render_empty(width: u32, height: u32, region: Option<RenderRegion>) -> RaytracedImage {
+        let mut raytracer = Raytracer::new(
+            Camera::default(),
+            color![0.5, 0.5, 0.5],
+            width,
+            height,
+            1,
+            1,
+        );
+        if let Some(region) = region {
+            raytracer.set_render_region(region.x0, region.y0, region.x1, region.y1);
+        }
+        raytracer.render().unwrap()
+    }
+
+    #[test]
+    fn render_4k() {
+        // Only trace the bottom rows to keep the memory of the paths small.
+        let region = RenderRegion::new(0, 2150, 3840, 2160);
+        let image = render_empty(3840, 2160, Some(region));
+        assert_eq!(image.colors().len(), 3840 * 2160);
+        assert!(image.colors()[3840 * 2150..]
+            .iter()
+            .all(|&color| color == color![0.5, 0.5, 0.5]));
+        assert_eq!(image.render_stats().paths, 3840 * 10);
+
+        let image = image.into_image().unwrap();
+        assert_eq!(image.dimensions(), (3840, 2160));
+    }
+
+    #[test]
+    fn render_8k() {
+        // Only trace the bottom right corner to keep the test fast, the pixel coordinates there do not fit into a u16 product.
+        let region = RenderRegion::new(7000, 4300, 7680, 4320);
+        let image = render_empty(7680, 4320, Some(region));
+        assert_eq!(image.colors().len(), 7680 * 4320);
+        assert_eq!(image.colors()[7680 * 4320 - 1], color![0.5, 0.5, 0.5]);
+        assert_eq!(image.colors()[0], BLACK);
+        assert_eq!(image.render_stats().paths, 680 * 20);
+    }
+
+    #[test]
+    fn single_row_or_column() {
+        use crate::materials::Lambertian;
+        use crate::shapes::Sphere;
+
+        for integrator in [
+            Integrator::PathTracer,
+            Integrator::Whitted,
+            Integrator::Sppm {
+                photons_per_iteration: 16,
+                initial_radius: 0.1,
+            },
+        ] {
+            for (width, height) in [(1, 4), (4, 1), (1, 1)] {
+                let mut raytracer = Raytracer::new(
+                    Camera::default(),
+                    color![0.5, 0.5, 0.5],
+                    width,
+                    height,
+                    1,
+                    4,
+                )
+                .with_integrator(integrator)
+                .with_filter(Filter::Box);
+                // A black sphere around the camera
+                let sphere = Sphere::new(vector![0., 0., 0.], 10., Lambertian::solid_color(BLACK));
+                raytracer.world.push(sphere);
+                let image = raytracer.render().unwrap();
+                assert!(
+                    image.colors().iter().all(|&color| color == BLACK),
+                    "{integrator:?} {width}x{height}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invisible_to_camera() {
+        use crate::hittable::{Visibility, Visible};
+        use crate::materials::Lambertian;
+        use crate::shapes::Sphere;
+
+        let render = |visibility| {
+            let mut raytracer =
+                Raytracer::new(Camera::default(), color![0.5, 0.5, 0.5], 4, 4, 1, 4)
+                    .with_filter(Filter::Box);
+            // A black sphere around the camera
+            let sphere = Sphere::new(vector![0., 0., 0.], 10., Lambertian::solid_color(BLACK));
+            raytracer.world.push(Visible::new(sphere, visibility));
+            raytracer.render().unwrap()
+        };
+
+        let image = render(Visibility::ALL);
+        assert!(image.colors().iter().all(|&color| color == BLACK));
+        let image = render(Visibility {
+            camera: false,
+            ..Default::default()
+        });
+        assert!(image
+            .colors()
+            .iter()
+            .all(|&color| color == color![0.5, 0.5, 0.5]));
+    }
+
+    #[test]
+    fn ray_offset() {
+        use crate::integrator::RayOffset;
+        use crate::lights::DirectionalLight;
+        use crate::materials::Lambertian;
+        use crate::shapes::Sphere;
+
+        // A sphere far from the origin, where the rounding errors of hit points exceed the fixed offset
+        let center = vector![3e4, 1e4, -3e4];
+        let render = |ray_offset| {
+            let camera = Camera::new(
+                center + vector![0., 0., 3e3],
+                center,
+                vector![0., 1., 0.],
+                0.3,
+                1.,
+                0.,
+                1.,
+            );
+            let mut raytracer = Raytracer::new(camera, BLACK, 32, 32, 1, 1)
+                .with_integrator(Integrator::Whitted)
+                .with_integrator_settings(IntegratorSettings::new().with_ray_offset(ray_offset));
+            raytracer.world.push(Sphere::new(
+                center,
+                1e3,
+                Lambertian::solid_color(color![0.5, 0.5, 0.5]),
+            ));
+            raytracer.lights.push_analytic(DirectionalLight::new(
+                vector![0., 0., -1.],
+                color![1., 1., 1.],
+            ));
+            let image = raytracer.render().unwrap();
+            // The sphere fills the image and faces the light, so dark pixels are shadow acne.
+            image
+                .colors()
+                .iter()
+                .filter(|color| color.g() < 1e-3)
+                .count()
+        };
+
+        // Double precision is exact enough for the fixed offset.
+        #[cfg(not(feature = "f64"))]
+        assert!(render(RayOffset::default()) > 0);
+        assert_eq!(render(RayOffset::Normal { epsilon: 1e-4 }), 0);
+        assert_eq!(render(RayOffset::Scaled { epsilon: 1e-4 }), 0);
     }
 }
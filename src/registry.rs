@@ -0,0 +1,394 @@
+//! Materials and textures in files, with constructors looked up by name, so user-written materials can be used in files without changing this crate.
+//!
+//! A material library is written in the same subset of TOML as the [presets](crate::presets), with one table per texture or material.
+//! The `texture` or `material` key names the constructor in the [`Registry`], and the other keys are its parameters.
+//! Texture parameters are either colors or the names of textures defined above them:
+//! ```toml
+//! [tiles]
+//! texture = "checker"
+//! even = [0.2, 0.3, 0.1]
+//! odd = [0.9, 0.9, 0.9]
+//!
+//! [floor]
+//! material = "lambertian"
+//! albedo = "tiles"
+//!
+//! [glass]
+//! material = "dielectric"
+//! index_of_refraction = 1.5
+//! ```
+//!
+//! The built-in constructors are the textures `solid` (`color`), `checker` (`even`, `odd`), `noise` (`scale`), and `image` (`path`),
+//! and the materials `lambertian` (`albedo`), `metal` (`albedo`, `fuzz`), `dielectric` (`index_of_refraction`, `absorption`), and `diffuse_light` (`emit`).
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::color::{BLACK, WHITE};
+use crate::error::Error;
+use crate::materials::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::presets::{strip_comment, Value};
+use crate::textures::{CheckerTexture, ImageTexture, PerlinNoiseTexture, SolidColor, Texture};
+use crate::*;
+
+/// Constructor of a [`Material`] from the [`Parameters`] of its table.
+pub type MaterialConstructor =
+    dyn Fn(&Parameters) -> Result<Arc<dyn Material>, Error> + Send + Sync;
+
+/// Constructor of a [`Texture`] from the [`Parameters`] of its table.
+pub type TextureConstructor = dyn Fn(&Parameters) -> Result<Arc<dyn Texture>, Error> + Send + Sync;
+
+/// Constructors of materials and textures by name.
+///
+/// [`Registry::default`] has the built-in constructors (see [`registry`](crate::registry)) and [`Registry::empty`] has none.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::{Lambertian, Material}, registry::Registry};
+/// # use std::sync::Arc;
+/// let registry = Registry::default().with_material("clay", |parameters| {
+///     let wetness = parameters.number("wetness", 0.)?;
+///     Ok(Arc::new(Lambertian::solid_color(color![0.6, 0.4, 0.3] * (1. - 0.5 * wetness))) as Arc<dyn Material>)
+/// });
+/// let library = registry.parse("[pot]\nmaterial = \"clay\"\nwetness = 1.0\n").unwrap();
+/// let albedo = library.material("pot").unwrap().albedo().unwrap();
+/// assert!((albedo.r() - 0.3).abs() < 1e-6);
+/// assert!(registry.parse("[pot]\nmaterial = \"porcelain\"\n").is_err());
+/// ```
+pub struct Registry {
+    materials: HashMap<String, Box<MaterialConstructor>>,
+    textures: HashMap<String, Box<TextureConstructor>>,
+}
+
+impl Registry {
+    /// A registry without any constructors.
+    pub fn empty() -> Self {
+        Self {
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+        }
+    }
+
+    /// Consume `self` and register the material constructor `kind`, replacing a constructor of the same name.
+    pub fn with_material<F>(mut self, kind: impl Into<String>, constructor: F) -> Self
+    where
+        F: Fn(&Parameters) -> Result<Arc<dyn Material>, Error> + Send + Sync + 'static,
+    {
+        self.materials.insert(kind.into(), Box::new(constructor));
+        self
+    }
+
+    /// Consume `self` and register the texture constructor `kind`, replacing a constructor of the same name.
+    pub fn with_texture<F>(mut self, kind: impl Into<String>, constructor: F) -> Self
+    where
+        F: Fn(&Parameters) -> Result<Arc<dyn Texture>, Error> + Send + Sync + 'static,
+    {
+        self.textures.insert(kind.into(), Box::new(constructor));
+        self
+    }
+
+    /// Names of the registered material constructors.
+    pub fn material_kinds(&self) -> impl Iterator<Item = &str> {
+        self.materials.keys().map(String::as_str)
+    }
+
+    /// Names of the registered texture constructors.
+    pub fn texture_kinds(&self) -> impl Iterator<Item = &str> {
+        self.textures.keys().map(String::as_str)
+    }
+
+    /// Read a [`MaterialLibrary`] from a TOML file.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<MaterialLibrary, Error> {
+        self.parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse a [`MaterialLibrary`] from TOML, constructing the tables from the top.
+    ///
+    /// Returns [`Error::Config`] for invalid lines, unknown constructors, and the errors of the constructors.
+    pub fn parse(&self, text: &str) -> Result<MaterialLibrary, Error> {
+        let mut library = MaterialLibrary::default();
+        // Name, line, and values of the current table
+        let mut current: Option<(String, usize, HashMap<String, Value>)> = None;
+        for (number, line) in text.lines().enumerate() {
+            let invalid = |message: String| {
+                Error::Config(format!(
+                    "invalid material library: line {}: {message}",
+                    number + 1
+                ))
+            };
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                if let Some(table) = current.take() {
+                    self.construct(table, &mut library)?;
+                }
+                let name = name.trim().trim_matches('"').to_string();
+                current = Some((name, number + 1, HashMap::new()));
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(invalid(format!("expected `key = value`, found {line:?}")));
+            };
+            let Some((_, _, values)) = &mut current else {
+                return Err(invalid("keys have to be inside of a [table]".to_string()));
+            };
+            let value = Value::parse(value.trim()).map_err(invalid)?;
+            values.insert(key.trim().to_string(), value);
+        }
+        if let Some(table) = current {
+            self.construct(table, &mut library)?;
+        }
+        Ok(library)
+    }
+
+    /// Construct the texture or material of a table and add it to `library`.
+    fn construct(
+        &self,
+        (name, line, values): (String, usize, HashMap<String, Value>),
+        library: &mut MaterialLibrary,
+    ) -> Result<(), Error> {
+        let parameters = Parameters {
+            name: &name,
+            line,
+            values,
+            library,
+        };
+        let kind = |key: &str| {
+            parameters
+                .values
+                .get(key)
+                .map(|value| value.string().map_err(|message| parameters.error(message)))
+                .transpose()
+        };
+        let (texture, material) = match (kind("texture")?, kind("material")?) {
+            (Some(kind), None) => {
+                let constructor = self
+                    .textures
+                    .get(kind)
+                    .ok_or_else(|| parameters.error(format!("unknown texture {kind:?}")))?;
+                (Some(constructor(&parameters)?), None)
+            }
+            (None, Some(kind)) => {
+                let constructor = self
+                    .materials
+                    .get(kind)
+                    .ok_or_else(|| parameters.error(format!("unknown material {kind:?}")))?;
+                (None, Some(constructor(&parameters)?))
+            }
+            _ => return Err(parameters.error("expected either a `texture` or a `material` key")),
+        };
+        if let Some(texture) = texture {
+            library.textures.insert(name.clone(), texture);
+        }
+        if let Some(material) = material {
+            library.materials.insert(name, material);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::empty()
+            .with_texture("solid", |parameters| {
+                Ok(Arc::new(SolidColor::new(parameters.color("color", WHITE)?)))
+            })
+            .with_texture("checker", |parameters| {
+                let even = parameters.texture("even", BLACK)?;
+                let odd = parameters.texture("odd", WHITE)?;
+                Ok(Arc::new(CheckerTexture::new(even, odd)))
+            })
+            .with_texture("noise", |parameters| {
+                Ok(Arc::new(PerlinNoiseTexture::new(
+                    parameters.number("scale", 1.)?,
+                )))
+            })
+            .with_texture("image", |parameters| {
+                let path = parameters.string("path")?;
+                Ok(Arc::new(ImageTexture::open(path)?))
+            })
+            .with_material("lambertian", |parameters| {
+                let albedo = parameters.texture("albedo", color![0.5, 0.5, 0.5])?;
+                Ok(Arc::new(Lambertian::new(albedo)))
+            })
+            .with_material("metal", |parameters| {
+                let albedo = parameters.texture("albedo", color![0.8, 0.8, 0.8])?;
+                Ok(Arc::new(Metal::new(albedo, parameters.number("fuzz", 0.)?)))
+            })
+            .with_material("dielectric", |parameters| {
+                let material = Dielectric::new(parameters.number("index_of_refraction", 1.5)?)
+                    .with_absorption(parameters.color("absorption", BLACK)?);
+                Ok(Arc::new(material))
+            })
+            .with_material("diffuse_light", |parameters| {
+                let emit = parameters.texture("emit", WHITE)?;
+                Ok(Arc::new(DiffuseLight::new(emit)))
+            })
+    }
+}
+
+/// The keys of a table in a material library, passed to its constructor.
+///
+/// # Fields
+/// - `name`: Name of the table.
+/// - `line`: Line of the table header, for messages.
+/// - `values`: Values of the keys of the table.
+/// - `library`: Textures and materials of the tables above.
+pub struct Parameters<'a> {
+    name: &'a str,
+    line: usize,
+    values: HashMap<String, Value>,
+    library: &'a MaterialLibrary,
+}
+
+impl Parameters<'_> {
+    /// Name of the table.
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// [`Error::Config`] with `message` about this table, for constructors to return.
+    pub fn error(&self, message: impl std::fmt::Display) -> Error {
+        Error::Config(format!(
+            "invalid material library: [{}] in line {}: {message}",
+            self.name, self.line
+        ))
+    }
+
+    /// Whether the table has `key`.
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// The number `key`, or `default` if it is missing.
+    pub fn number(&self, key: &str, default: Float) -> Result<Float, Error> {
+        match self.values.get(key) {
+            Some(value) => value
+                .number()
+                .map_err(|message| self.key_error(key, message)),
+            None => Ok(default),
+        }
+    }
+
+    /// The color `key`, an array of three numbers, or `default` if it is missing.
+    pub fn color(&self, key: &str, default: Color) -> Result<Color, Error> {
+        match self.values.get(key) {
+            Some(value) => {
+                let [r, g, b] = value
+                    .numbers()
+                    .map_err(|message| self.key_error(key, message))?;
+                Ok(color![r, g, b])
+            }
+            None => Ok(default),
+        }
+    }
+
+    /// The string `key`.
+    pub fn string(&self, key: &str) -> Result<&str, Error> {
+        self.values
+            .get(key)
+            .ok_or_else(|| self.key_error(key, "missing"))?
+            .string()
+            .map_err(|message| self.key_error(key, message))
+    }
+
+    /// The texture `key`, which is either a color or the name of a texture above, or a [`SolidColor`] of `default` if it is missing.
+    pub fn texture(&self, key: &str, default: Color) -> Result<Arc<dyn Texture>, Error> {
+        match self.values.get(key) {
+            Some(Value::String(name)) => self
+                .library
+                .texture(name)
+                .ok_or_else(|| self.key_error(key, format!("unknown texture {name:?}"))),
+            _ => Ok(Arc::new(SolidColor::new(self.color(key, default)?))),
+        }
+    }
+
+    /// The material `key`, the name of a material above, e.g. for materials that wrap others.
+    pub fn material(&self, key: &str) -> Result<Arc<dyn Material>, Error> {
+        let name = self.string(key)?;
+        self.library
+            .material(name)
+            .ok_or_else(|| self.key_error(key, format!("unknown material {name:?}")))
+    }
+
+    fn key_error(&self, key: &str, message: impl std::fmt::Display) -> Error {
+        self.error(format!("`{key}`: {message}"))
+    }
+}
+
+/// Textures and materials read from a file by a [`Registry`], by the names of their tables.
+#[derive(Clone, Debug, Default)]
+pub struct MaterialLibrary {
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+}
+
+impl MaterialLibrary {
+    /// The material of the table `name`.
+    pub fn material(&self, name: &str) -> Option<Arc<dyn Material>> {
+        self.materials.get(name).cloned()
+    }
+
+    /// The texture of the table `name`.
+    pub fn texture(&self, name: &str) -> Option<Arc<dyn Texture>> {
+        self.textures.get(name).cloned()
+    }
+
+    /// Names of the materials.
+    pub fn material_names(&self) -> impl Iterator<Item = &str> {
+        self.materials.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn library() {
+        let library = Registry::default()
+            .parse(
+                r#"
+                # Textures have to be defined before the materials that use them.
+                [tiles]
+                texture = "checker"
+                even = [0.2, 0.3, 0.1]
+                odd = [0.9, 0.9, 0.9]
+
+                [floor]
+                material = "lambertian"
+                albedo = "tiles"
+
+                [lamp]
+                material = "diffuse_light"
+                emit = [4.0, 4.0, 4.0]
+                "#,
+            )
+            .unwrap();
+        assert!(library.texture("tiles").is_some());
+        assert!(library.material("floor").unwrap().albedo().is_none());
+        assert!(library.material("lamp").unwrap().is_emissive());
+        assert_eq!(library.material_names().count(), 2);
+
+        let error = Registry::default()
+            .parse("[floor]\nmaterial = \"lambertian\"\nalbedo = \"tiles\"\n")
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("unknown texture \"tiles\""),
+            "{error}"
+        );
+        assert!(Registry::default()
+            .parse("[floor]\nalbedo = [1, 1, 1]\n")
+            .is_err());
+        assert!(Registry::empty()
+            .parse("[glass]\nmaterial = \"dielectric\"\n")
+            .is_err());
+    }
+}
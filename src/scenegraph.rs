@@ -0,0 +1,279 @@
+//! A lightweight scene graph with hierarchical transforms.
+//!
+//! Each [`Node`] has a position and rotation relative to its parent and can carry geometry, [`Camera`]s, and analytic [`Light`]s.
+//! Before rendering, the graph is [flattened](Node::flatten) into [`Instance`]s, so moving a parent moves everything attached to it without composing transforms by hand.
+
+use std::sync::Arc;
+
+use nalgebra::Rotation3;
+
+use crate::hittable::{BoundingBoxError, Bvh};
+use crate::instancing::{Instance, Tlas};
+use crate::lights::{Light, LightSample, Lights};
+use crate::shapes::Offset;
+use crate::*;
+
+/// A node of a scene graph.
+///
+/// # Fields
+/// - `position`: Position relative to the parent node.
+/// - `rotation`: Rotation around `position`, in the same sense as [`Movable::with_rotation`](crate::shapes::Movable::with_rotation).
+/// - `motion`: End position and time interval of a linear motion, see [`Movable::moving`](crate::shapes::Movable::moving).
+/// - `geometry`: Shared [`Bvh`]s in the coordinates of the node.
+/// - `cameras`: [`Camera`]s in the coordinates of the node.
+/// - `lights`: Analytic [`Light`]s in the coordinates of the node.
+/// - `children`: Child nodes, whose transforms are relative to this one.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, ray::Ray, scenegraph::Node, shapes::Sphere};
+/// # use nalgebra::Rotation3;
+/// // A moon orbiting a planet, which is itself moved to x = 10
+/// let gray = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+/// let moon = Node::new(vector![3., 0., 0.]).with_hittable(Sphere::new(vector![0., 0., 0.], 0.5, gray.clone()));
+/// let planet = Node::new(vector![10., 0., 0.])
+///     .with_rotation(Rotation3::from_axis_angle(&Vector3::y_axis(), float::consts::FRAC_PI_2))
+///     .with_hittable(Sphere::new(vector![0., 0., 0.], 1., gray))
+///     .with_child(moon);
+///
+/// let scene = planet.flatten();
+/// assert_eq!(scene.instances.len(), 2);
+/// let tlas = scene.tlas(0., 0.).unwrap();
+/// // The moon is rotated around the planet to the z axis.
+/// let ray = Ray::new(vector![10., 10., 3.], vector![0., -1., 0.]);
+/// let hit = tlas.hit(ray, 0.001, Float::INFINITY).unwrap();
+/// assert!((hit.point - vector![10., 0.5, 3.]).norm() < 1e-3);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Node {
+    position: Vector3<Float>,
+    rotation: Option<Rotation3<Float>>,
+    motion: Option<(Vector3<Float>, Float, Float)>,
+    geometry: Vec<Arc<Bvh>>,
+    cameras: Vec<Camera>,
+    lights: Vec<Arc<dyn Light>>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    /// Create an empty [`Node`] at `position` relative to its parent.
+    pub fn new(position: Vector3<Float>) -> Self {
+        Self {
+            position,
+            ..Default::default()
+        }
+    }
+
+    /// Consume `self` and rotate it (and its children) around its position.
+    pub fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    /// Consume `self` and move it linearly to `position_end` between `time_start` and `time_end`.
+    pub fn moving(
+        mut self,
+        position_end: Vector3<Float>,
+        time_start: Float,
+        time_end: Float,
+    ) -> Self {
+        self.motion = Some((position_end, time_start, time_end));
+        self
+    }
+
+    /// Consume `self` and attach shared geometry, which can be attached to several nodes.
+    pub fn with_geometry(mut self, geometry: Arc<Bvh>) -> Self {
+        self.geometry.push(geometry);
+        self
+    }
+
+    /// Consume `self` and attach a single [`Hittable`].
+    ///
+    /// # Panics
+    /// If `hittable` has no bounding box.
+    pub fn with_hittable<H: Hittable + 'static>(self, hittable: H) -> Self {
+        let mut list = HittableList::default();
+        list.push(hittable);
+        let geometry =
+            Bvh::new(list, 0., 0.).expect("hittables in a scene graph need a bounding box");
+        self.with_geometry(Arc::new(geometry))
+    }
+
+    /// Consume `self` and attach a [`Camera`].
+    pub fn with_camera(mut self, camera: Camera) -> Self {
+        self.cameras.push(camera);
+        self
+    }
+
+    /// Consume `self` and attach an analytic [`Light`].
+    pub fn with_light<L: Light + 'static>(mut self, light: L) -> Self {
+        self.lights.push(Arc::new(light));
+        self
+    }
+
+    /// Consume `self` and add a child.
+    pub fn with_child(mut self, child: Node) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Move the node relative to its parent, e.g. to animate it for the next frame.
+    pub fn set_position(&mut self, position: Vector3<Float>) {
+        self.position = position;
+    }
+
+    /// Replace the rotation, e.g. to animate the node for the next frame.
+    pub fn set_rotation(&mut self, rotation: Rotation3<Float>) {
+        self.rotation = Some(rotation);
+    }
+
+    /// Get the child at `index` to change it.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn child_mut(&mut self, index: usize) -> &mut Node {
+        &mut self.children[index]
+    }
+
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// Compose the transforms of all nodes and collect everything attached to them in world coordinates.
+    pub fn flatten(&self) -> FlatScene {
+        let mut scene = FlatScene::default();
+        self.flatten_into(&Offset::default(), &mut scene);
+        scene
+    }
+
+    /// The transform relative to the parent.
+    fn offset(&self) -> Offset {
+        // An `Offset` rotates after translating, so the translation is rotated along to keep the position.
+        let rotate = |position: Vector3<Float>| match self.rotation {
+            Some(rotation) => rotation * position,
+            None => position,
+        };
+        let mut offset = Offset::new(rotate(self.position));
+        if let Some(rotation) = self.rotation {
+            offset = offset.with_rotation(rotation);
+        }
+        if let Some((position_end, time_start, time_end)) = self.motion {
+            offset = offset.moving(rotate(position_end), time_start, time_end);
+        }
+        offset
+    }
+
+    fn flatten_into(&self, parent: &Offset, scene: &mut FlatScene) {
+        let transform = self.offset().then(parent);
+        for geometry in &self.geometry {
+            let mut instance = Instance::new(geometry.clone(), Vector3::zeros());
+            instance.set_transform(transform.clone());
+            scene.instances.push(instance);
+        }
+        scene.cameras.extend(
+            self.cameras
+                .iter()
+                .map(|camera| camera.transformed(&transform)),
+        );
+        scene
+            .lights
+            .extend(self.lights.iter().map(|light| TransformedLight {
+                transform: transform.clone(),
+                light: light.clone(),
+            }));
+        for child in &self.children {
+            child.flatten_into(&transform, scene);
+        }
+    }
+}
+
+/// Everything attached to a [`Node`] and its descendants in world coordinates, see [`Node::flatten`].
+///
+/// # Fields
+/// - `instances`: One [`Instance`] per attached geometry.
+/// - `cameras`: All [`Camera`]s in the order the nodes were visited (depth first).
+/// - `lights`: All analytic [`Light`]s.
+#[derive(Clone, Debug, Default)]
+pub struct FlatScene {
+    pub instances: Vec<Instance>,
+    pub cameras: Vec<Camera>,
+    pub lights: Vec<TransformedLight>,
+}
+
+impl FlatScene {
+    /// A [`Tlas`] over the `instances` whose [`Aabb`](crate::hittable::Aabb)s encompass them from `time0` to `time1`.
+    pub fn tlas(&self, time0: Float, time1: Float) -> Result<Tlas, BoundingBoxError> {
+        Tlas::new(self.instances.clone(), time0, time1)
+    }
+
+    /// Add the instances to `world` and the lights to `lights`, e.g. those of a [`Raytracer`].
+    pub fn add_to(
+        self,
+        world: &mut HittableList,
+        lights: &mut Lights,
+        time0: Float,
+        time1: Float,
+    ) -> Result<(), BoundingBoxError> {
+        if !self.instances.is_empty() {
+            world.push(self.tlas(time0, time1)?);
+        }
+        for light in self.lights {
+            lights.push_analytic(light);
+        }
+        Ok(())
+    }
+}
+
+/// A [`Light`] of a [`Node`] placed in world coordinates.
+#[derive(Clone, Debug)]
+pub struct TransformedLight {
+    transform: Offset,
+    light: Arc<dyn Light>,
+}
+
+impl Light for TransformedLight {
+    fn sample(&self, point: Vector3<Float>) -> Option<LightSample> {
+        let mut sample = self.light.sample(self.transform.to_local(point, 0.))?;
+        sample.direction = self.transform.normal_to_world(sample.direction);
+        Some(sample)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lights::PointLight;
+
+    #[test]
+    fn nested_transforms() {
+        let camera = Camera::new(
+            vector![0., 0., 0.],
+            vector![0., 0., -1.],
+            vector![0., 1., 0.],
+            1.,
+            1.,
+            0.,
+            1.,
+        );
+        let rotation = Rotation3::from_axis_angle(&Vector3::y_axis(), float::consts::FRAC_PI_2);
+        let root = Node::new(vector![0., 0., 5.])
+            .with_rotation(rotation)
+            .with_child(
+                Node::new(vector![1., 2., 0.])
+                    .with_camera(camera)
+                    .with_light(PointLight::new(vector![0., 0., 0.], color![1., 1., 1.])),
+            );
+
+        let scene = root.flatten();
+        // The child is rotated around the root, which is at (0, 0, 5).
+        let expected = rotation.inverse() * vector![1., 2., 0.] + vector![0., 0., 5.];
+        let ray = scene.cameras[0].get_center_ray(0.5, 0.5);
+        assert!((ray.origin() - expected).norm() < 1e-4);
+        let forward = rotation.inverse() * vector![0., 0., -1.];
+        assert!((ray.direction().normalize() - forward).norm() < 1e-4);
+
+        let sample = scene.lights[0].sample(Vector3::zeros()).unwrap();
+        assert!((sample.direction - expected.normalize()).norm() < 1e-4);
+        assert!((sample.distance - expected.norm()).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,681 @@
+//! Generators for standard scenes, e.g. the ones of the books.
+//!
+//! Every generator returns a ready [`Raytracer`] with a fitting [`Camera`], so a standard world is a single call away (e.g. for tests and benchmarks).
+//! Random scenes are generated from a seed and are therefore reproducible.
+
+use crate::float::consts::{FRAC_PI_2, FRAC_PI_6};
+
+use image::RgbImage;
+use std::sync::Arc;
+
+use nalgebra::{Matrix3, Rotation3};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::color::{BLACK, WHITE};
+use crate::hittable::Bvh;
+use crate::instancing::{Instance, Tlas};
+use crate::lights::DirectionalLight;
+use crate::materials::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::shapes::{ConstantMedium, Cuboid, Cylinder, Movable, Rectangle, Sphere};
+use crate::textures::{CheckerTexture, PerlinNoiseTexture, SolidColor};
+use crate::vec3::orthonormal_basis;
+use crate::*;
+
+/// Image and sampling settings shared by all scenes.
+///
+/// # Fields
+/// - `image_width`: Width of the image.
+/// - `image_height`: Height of the image.
+/// - `samples_per_pixel`: How many samples to take for each pixel.
+/// - `max_depth`: How often a [`Ray`](crate::ray::Ray) bounces at most.
+/// - `seed`: Seed of the random number generator for random scenes.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, scenes::{self, SceneSettings}};
+/// let settings = SceneSettings::new(32, 18).with_samples(4, 8).with_seed(42);
+/// let raytracer = scenes::random_spheres(&settings);
+/// assert!(raytracer.stats().is_valid());
+/// let image = raytracer.render().unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SceneSettings {
+    pub image_width: u32,
+    pub image_height: u32,
+    pub samples_per_pixel: u16,
+    pub max_depth: u16,
+    pub seed: u64,
+}
+
+impl SceneSettings {
+    pub fn new(image_width: u32, image_height: u32) -> Self {
+        Self {
+            image_width,
+            image_height,
+            ..Default::default()
+        }
+    }
+
+    /// Consume `self` and set the samples per pixel and the maximum depth.
+    pub fn with_samples(mut self, samples_per_pixel: u16, max_depth: u16) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Consume `self` and set the seed for random scenes.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    fn aspect_ratio(&self) -> Float {
+        self.image_width as Float / self.image_height as Float
+    }
+
+    fn raytracer(&self, camera: Camera, background: Color) -> Raytracer {
+        Raytracer::new(
+            camera,
+            background,
+            self.image_width,
+            self.image_height,
+            self.samples_per_pixel,
+            self.max_depth,
+        )
+    }
+}
+
+impl Default for SceneSettings {
+    fn default() -> Self {
+        Self {
+            image_width: 400,
+            image_height: 225,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            seed: 0,
+        }
+    }
+}
+
+/// A random color with each channel in \[`min`,`max`\).
+fn random_color(rng: &mut StdRng, min: Float, max: Float) -> Color {
+    color![
+        rng.gen_range(min..max),
+        rng.gen_range(min..max),
+        rng.gen_range(min..max)
+    ]
+}
+
+/// The final scene of the first book: a field of small random spheres around three large ones.
+pub fn random_spheres(settings: &SceneSettings) -> Raytracer {
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let camera = Camera::new(
+        vector![13., 2., 3.],
+        vector![0., 0., 0.],
+        vector![0., 1., 0.],
+        FRAC_PI_6 * 2. / 3.,
+        settings.aspect_ratio(),
+        0.1,
+        10.,
+    );
+    let mut raytracer = settings.raytracer(camera, color![0.7, 0.8, 1.]);
+    let world = &mut raytracer.world;
+
+    world.push(Sphere::new(
+        vector![0., -1000., 0.],
+        1000.,
+        Lambertian::new(CheckerTexture::solid_colors(
+            color![0.2, 0.3, 0.1],
+            color![0.9, 0.9, 0.9],
+        )),
+    ));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_material: Float = rng.gen();
+            let center = vector![
+                a as Float + 0.9 * rng.gen::<Float>(),
+                0.2,
+                b as Float + 0.9 * rng.gen::<Float>()
+            ];
+            if (center - vector![4., 0.2, 0.]).norm() <= 0.9 {
+                continue;
+            }
+
+            if choose_material < 0.8 {
+                let albedo = random_color(&mut rng, 0., 1.) * random_color(&mut rng, 0., 1.);
+                world.push(Sphere::new(center, 0.2, Lambertian::solid_color(albedo)));
+            } else if choose_material < 0.95 {
+                let albedo = random_color(&mut rng, 0.5, 1.);
+                let fuzz = rng.gen_range(0. ..0.5);
+                world.push(Sphere::new(center, 0.2, Metal::solid_color(albedo, fuzz)));
+            } else {
+                world.push(Sphere::new(center, 0.2, Dielectric::new(1.5)));
+            }
+        }
+    }
+
+    world.push(Sphere::new(vector![0., 1., 0.], 1., Dielectric::new(1.5)));
+    world.push(Sphere::new(
+        vector![-4., 1., 0.],
+        1.,
+        Lambertian::solid_color(color![0.4, 0.2, 0.1]),
+    ));
+    world.push(Sphere::new(
+        vector![4., 1., 0.],
+        1.,
+        Metal::solid_color(color![0.7, 0.6, 0.5], 0.),
+    ));
+
+    raytracer
+}
+
+/// The Cornell box: a white box with a red and a green wall, a light at the ceiling and two white boxes inside.
+///
+/// The box spans from -200 to 200 in each direction and the camera looks at it from the open front.
+pub fn cornell_box(settings: &SceneSettings) -> Raytracer {
+    let camera = Camera::new(
+        vector![0., 0., 600.],
+        vector![0., 0., 0.],
+        vector![0., 1., 0.],
+        Float::to_radians(40.),
+        settings.aspect_ratio(),
+        0.,
+        1.,
+    );
+    let mut raytracer = settings.raytracer(camera, BLACK);
+    let world = &mut raytracer.world;
+
+    let red = Lambertian::solid_color(color![0.65, 0.05, 0.05]);
+    let white = Lambertian::solid_color(color![0.73, 0.73, 0.73]);
+    let green = Lambertian::solid_color(color![0.12, 0.45, 0.15]);
+    let light = DiffuseLight::solid_color(15. * WHITE);
+
+    world.push(Rectangle::xz(
+        vector![0., -200., 0.],
+        400.,
+        400.,
+        white.clone(),
+    ));
+    world.push(Rectangle::xz(
+        vector![0., 200., 0.],
+        400.,
+        400.,
+        white.clone(),
+    ));
+    world.push(Rectangle::xy(
+        vector![0., 0., -200.],
+        400.,
+        400.,
+        white.clone(),
+    ));
+    world.push(Rectangle::yz(vector![-200., 0., 0.], 400., 400., green));
+    world.push(Rectangle::yz(vector![200., 0., 0.], 400., 400., red));
+    world.push(Rectangle::xz(vector![0., 199.9, 0.], 100., 100., light));
+
+    world.push(
+        Cuboid::new(vector![60., -125., 40.], 120., 150., 120., white.clone())
+            .with_rotation(Rotation3::new(Float::to_radians(-18.) * Vector3::y())),
+    );
+    world.push(
+        Cuboid::new(vector![-60., -50., -60.], 120., 300., 120., white)
+            .with_rotation(Rotation3::new(Float::to_radians(15.) * Vector3::y())),
+    );
+
+    raytracer
+}
+
+/// The final scene of the second book: a floor of random boxes, a light, a moving sphere, glass, metal, fog and a cluster of spheres.
+///
+/// The earth texture of the book is replaced by a marble [`PerlinNoiseTexture`], so no image file is needed.
+pub fn next_week_final(settings: &SceneSettings) -> Raytracer {
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let camera = Camera::new(
+        vector![478., 278., -600.],
+        vector![278., 278., 0.],
+        vector![0., 1., 0.],
+        Float::to_radians(40.),
+        settings.aspect_ratio(),
+        0.,
+        1.,
+    )
+    .with_time(0., 1.);
+    let mut raytracer = settings.raytracer(camera, BLACK);
+    let world = &mut raytracer.world;
+
+    let ground = Lambertian::solid_color(color![0.48, 0.83, 0.53]);
+    let boxes_per_side = 20;
+    let width = 100.;
+    for i in 0..boxes_per_side {
+        for j in 0..boxes_per_side {
+            let height = rng.gen_range(1. ..101.);
+            world.push(Cuboid::new(
+                vector![
+                    -1000. + (i as Float + 0.5) * width,
+                    height / 2.,
+                    -1000. + (j as Float + 0.5) * width
+                ],
+                width,
+                height,
+                width,
+                ground.clone(),
+            ));
+        }
+    }
+
+    world.push(Rectangle::xz(
+        vector![273., 554., 279.5],
+        300.,
+        265.,
+        DiffuseLight::solid_color(7. * WHITE),
+    ));
+
+    let center = vector![400., 400., 200.];
+    world.push(
+        Sphere::new(center, 50., Lambertian::solid_color(color![0.7, 0.3, 0.1])).moving(
+            center + vector![30., 0., 0.],
+            0.,
+            1.,
+        ),
+    );
+    world.push(Sphere::new(
+        vector![260., 150., 45.],
+        50.,
+        Dielectric::new(1.5),
+    ));
+    world.push(Sphere::new(
+        vector![0., 150., 145.],
+        50.,
+        Metal::solid_color(color![0.8, 0.8, 0.9], 1.),
+    ));
+
+    let boundary = Sphere::new(vector![360., 150., 145.], 70., Dielectric::new(1.5));
+    world.push(boundary.clone());
+    world.push(ConstantMedium::solid_color(
+        boundary,
+        color![0.2, 0.4, 0.9],
+        0.2,
+    ));
+    let boundary = Sphere::new(vector![0., 0., 0.], 5000., Dielectric::new(1.5));
+    world.push(ConstantMedium::solid_color(boundary, WHITE, 0.0001));
+
+    world.push(Sphere::new(
+        vector![400., 200., 400.],
+        100.,
+        Lambertian::new(PerlinNoiseTexture::new(0.05)),
+    ));
+    world.push(Sphere::new(
+        vector![220., 280., 300.],
+        80.,
+        Lambertian::new(PerlinNoiseTexture::new(0.2)),
+    ));
+
+    let white = Lambertian::solid_color(color![0.73, 0.73, 0.73]);
+    let mut cluster = HittableList::new(vector![-100., 270., 395.]);
+    for _ in 0..1000 {
+        let center = vector![
+            rng.gen_range(0. ..165.),
+            rng.gen_range(0. ..165.),
+            rng.gen_range(0. ..165.)
+        ];
+        cluster.push(Sphere::new(center, 10., white.clone()));
+    }
+    world.push(cluster.with_rotation(Rotation3::new(Float::to_radians(15.) * Vector3::y())));
+
+    raytracer
+}
+
+/// A `count` × `count` grid of spheres on a floor, which get rougher from left to right and change from diffuse to metal from front to back.
+///
+/// This is useful to compare materials and for benchmarks with a scalable number of objects.
+pub fn sphere_grid(settings: &SceneSettings, count: usize) -> Raytracer {
+    let count = count.max(1);
+    let size = count as Float;
+    let camera = Camera::new(
+        vector![0., size, 1.5 * size + 2.],
+        vector![0., 0., 0.],
+        vector![0., 1., 0.],
+        FRAC_PI_6 * 2.,
+        settings.aspect_ratio(),
+        0.,
+        1.,
+    );
+    let mut raytracer = settings.raytracer(camera, color![0.7, 0.8, 1.]);
+    let world = &mut raytracer.world;
+
+    world.push(Rectangle::xz(
+        vector![0., -0.5, 0.],
+        4. * size,
+        4. * size,
+        Lambertian::solid_color(color![0.5, 0.5, 0.5]),
+    ));
+
+    for i in 0..count {
+        for j in 0..count {
+            let roughness = i as Float / (size - 1.).max(1.);
+            let metallic = j as Float / (size - 1.).max(1.);
+            let center = vector![
+                i as Float - (size - 1.) / 2.,
+                0.,
+                (size - 1.) / 2. - j as Float
+            ];
+            let albedo = color![0.8, 0.3 + 0.5 * roughness, 0.2 + 0.6 * metallic];
+            if metallic < 0.5 {
+                world.push_sphere(center, 0.4, Lambertian::solid_color(albedo));
+            } else {
+                world.push_sphere(center, 0.4, Metal::solid_color(albedo, roughness));
+            }
+        }
+    }
+
+    raytracer
+}
+
+/// A sphere flake: a metal sphere with nine smaller spheres on its surface, each of which again has smaller spheres on it, up to `depth` levels.
+///
+/// The number of spheres grows like 9^`depth`, so this is a stress test for the [`Bvh`](crate::hittable::Bvh).
+pub fn sphere_flake(settings: &SceneSettings, depth: u8) -> Raytracer {
+    let mut raytracer = flake_stage(settings);
+    let material = Metal::solid_color(color![0.8, 0.8, 0.85], 0.05);
+    push_flake(
+        &mut raytracer.world,
+        vector![0., 0., 0.],
+        1.,
+        Vector3::y(),
+        depth,
+        &material,
+    );
+
+    raytracer
+}
+
+/// Camera and checkered floor of the sphere flakes, whose largest sphere has a radius of one and its center at the origin.
+fn flake_stage(settings: &SceneSettings) -> Raytracer {
+    let camera = Camera::new(
+        vector![3.5, 2.5, 3.5],
+        vector![0., 0.1, 0.],
+        vector![0., 1., 0.],
+        FRAC_PI_2 * 0.5,
+        settings.aspect_ratio(),
+        0.,
+        1.,
+    );
+    let mut raytracer = settings.raytracer(camera, color![0.7, 0.8, 1.]);
+    raytracer.world.push(Rectangle::xz(
+        vector![0., -1., 0.],
+        100.,
+        100.,
+        Lambertian::new(CheckerTexture::solid_colors(WHITE, BLACK)),
+    ));
+    raytracer
+}
+
+/// Push a sphere of the sphere flake and recursively its children, which grow away from `up`.
+fn push_flake(
+    world: &mut HittableList,
+    center: Vector3<Float>,
+    radius: Float,
+    up: Vector3<Float>,
+    depth: u8,
+    material: &Metal<SolidColor>,
+) {
+    world.push(Sphere::new(center, radius, material.clone()));
+    if depth == 0 {
+        return;
+    }
+
+    let child_radius = radius / 3.;
+    for direction in flake_directions(up) {
+        push_flake(
+            world,
+            center + (radius + child_radius) * direction,
+            child_radius,
+            direction,
+            depth - 1,
+            material,
+        );
+    }
+}
+
+/// Directions of the children of a sphere of the sphere flake: six around the equator and three at the top, when seen from `up`.
+fn flake_directions(up: Vector3<Float>) -> impl Iterator<Item = Vector3<Float>> {
+    let (tangent, bitangent) = orthonormal_basis(&up);
+    (0..6)
+        .map(|i| (i as Float * FRAC_PI_6 * 2., 0.))
+        .chain((0..3).map(|i| (i as Float * FRAC_PI_6 * 4. + FRAC_PI_6, 1.)))
+        .map(move |(angle, elevation)| {
+            (angle.cos() * tangent + angle.sin() * bitangent + elevation * up).normalize()
+        })
+}
+
+/// How many of the lowest levels of the instanced stress test scenes are sorted into one shared [`Bvh`](crate::hittable::Bvh).
+const SHARED_LEVELS: u8 = 3;
+
+/// The [`sphere_flake`] built from [`Instance`]s, so that it scales to millions of spheres.
+///
+/// All spheres of one level have the same radius, so the last levels are a single shared [`Bvh`](crate::hittable::Bvh) that is placed (translated and rotated) on every sphere above them.
+/// A `depth` of seven results in more than five million spheres, while only a few thousand of them are stored.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, scenes::{self, SceneSettings}};
+/// let raytracer = scenes::instanced_sphere_flake(&SceneSettings::new(32, 18).with_samples(1, 4), 4);
+/// // The floor and 1 + 9 + ... + 9^4 spheres
+/// assert_eq!(raytracer.stats().primitives, 1 + 7381);
+/// ```
+pub fn instanced_sphere_flake(settings: &SceneSettings, depth: u8) -> Raytracer {
+    let mut raytracer = flake_stage(settings);
+    let world = &mut raytracer.world;
+    let material = Metal::solid_color(color![0.8, 0.8, 0.85], 0.05);
+    let shared = depth.min(SHARED_LEVELS);
+    let top = depth - shared;
+    let mut geometry = HittableList::default();
+    push_flake(
+        &mut geometry,
+        vector![0., 0., 0.],
+        Float::powi(3., -(top as i32)),
+        Vector3::y(),
+        shared,
+        &material,
+    );
+    let blas = Arc::new(Bvh::new(geometry, 0., 0.).expect("spheres have bounding boxes"));
+
+    let mut instances = Vec::new();
+    push_instanced_flake(
+        world,
+        &mut instances,
+        &blas,
+        vector![0., 0., 0.],
+        1.,
+        Vector3::y(),
+        top,
+        &material,
+    );
+    world.push(Tlas::new(instances, 0., 0.).expect("spheres have bounding boxes"));
+
+    raytracer
+}
+
+/// Push a sphere of the upper levels of the [`instanced_sphere_flake`] and recursively its children, or an [`Instance`] of the shared lowest levels `blas` if `depth` is zero.
+#[allow(clippy::too_many_arguments)]
+fn push_instanced_flake(
+    world: &mut HittableList,
+    instances: &mut Vec<Instance>,
+    blas: &Arc<Bvh>,
+    center: Vector3<Float>,
+    radius: Float,
+    up: Vector3<Float>,
+    depth: u8,
+    material: &Metal<SolidColor>,
+) {
+    if depth == 0 {
+        // Rotate the frame of the shared flake, which grows along the y axis, onto the frame of `up`.
+        let frame = |up: Vector3<Float>| {
+            let (tangent, bitangent) = orthonormal_basis(&up);
+            Matrix3::from_columns(&[tangent, bitangent, up])
+        };
+        let rotation =
+            Rotation3::from_matrix_unchecked(frame(up) * frame(Vector3::y()).transpose());
+        instances.push(
+            Instance::new(blas.clone(), rotation.inverse() * center)
+                .with_rotation(rotation.inverse()),
+        );
+        return;
+    }
+
+    world.push(Sphere::new(center, radius, material.clone()));
+    let child_radius = radius / 3.;
+    for direction in flake_directions(up) {
+        push_instanced_flake(
+            world,
+            instances,
+            blas,
+            center + (radius + child_radius) * direction,
+            child_radius,
+            direction,
+            depth - 1,
+            material,
+        );
+    }
+}
+
+/// A Menger sponge of `depth` levels, cut from a cube with a side length of two that stands on a checkered floor.
+///
+/// Each level divides every cube into 27 smaller ones and keeps the 20 of them that are not in the center of a face or of the cube.
+/// The sponge is built from [`Instance`]s of a shared [`Bvh`](crate::hittable::Bvh) of the last levels, so that it scales to millions of [`Cuboid`]s: a `depth` of five results in 20^5 = 3 200 000 of them.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, scenes::{self, SceneSettings}};
+/// let raytracer = scenes::menger_sponge(&SceneSettings::new(32, 18).with_samples(1, 4), 4);
+/// // The floor and the six sides of every cuboid
+/// assert_eq!(raytracer.stats().primitives, 1 + 6 * 20usize.pow(4));
+/// let image = raytracer.render().unwrap();
+/// ```
+pub fn menger_sponge(settings: &SceneSettings, depth: u8) -> Raytracer {
+    let camera = Camera::new(
+        vector![3.5, 2.5, 4.5],
+        vector![0., 0., 0.],
+        vector![0., 1., 0.],
+        FRAC_PI_2 * 0.5,
+        settings.aspect_ratio(),
+        0.,
+        1.,
+    );
+    let mut raytracer = settings.raytracer(camera, color![0.7, 0.8, 1.]);
+    raytracer.lights.push_analytic(
+        DirectionalLight::new(vector![-1., -2., -0.5], 2. * color![1., 0.95, 0.85])
+            .with_angular_radius(0.05),
+    );
+    let world = &mut raytracer.world;
+
+    world.push(Rectangle::xz(
+        vector![0., -1., 0.],
+        100.,
+        100.,
+        Lambertian::new(CheckerTexture::solid_colors(WHITE, BLACK)),
+    ));
+
+    let material = Lambertian::solid_color(color![0.8, 0.45, 0.2]);
+    let shared = depth.min(SHARED_LEVELS);
+    let top = depth - shared;
+    // Side length of the cubes at the top of the shared levels and at the bottom
+    let shared_size = 2. * Float::powi(3., -(top as i32));
+    let size = 2. * Float::powi(3., -(depth as i32));
+
+    let mut geometry = HittableList::default();
+    for center in menger_cells(vector![0., 0., 0.], shared_size, shared) {
+        geometry.push(Cuboid::new(center, size, size, size, material.clone()));
+    }
+    let blas = Arc::new(Bvh::new(geometry, 0., 0.).expect("cuboids have bounding boxes"));
+
+    let instances = menger_cells(vector![0., 0., 0.], 2., top)
+        .into_iter()
+        .map(|center| Instance::new(blas.clone(), center))
+        .collect();
+    world.push(Tlas::new(instances, 0., 0.).expect("cuboids have bounding boxes"));
+
+    raytracer
+}
+
+/// Centers of the cubes of a Menger sponge of `depth` levels, cut from a cube at `center` with a side length of `size`.
+fn menger_cells(center: Vector3<Float>, size: Float, depth: u8) -> Vec<Vector3<Float>> {
+    if depth == 0 {
+        return vec![center];
+    }
+
+    let size = size / 3.;
+    (0..27)
+        .map(|i| vector![i % 3, i / 3 % 3, i / 9].map(|offset| offset as Float - 1.))
+        // Remove the center of every face and of the cube, where at least two offsets are zero.
+        .filter(|offset| offset.iter().filter(|&&o| o == 0.).count() < 2)
+        .flat_map(|offset| menger_cells(center + size * offset, size, depth - 1))
+        .collect()
+}
+
+/// A shader ball: a sphere with `material` on a pedestal above a checkered floor, lit by a warm key light from the upper left and a blue sky.
+///
+/// The sphere has a radius of one and its center at the origin. The scene is the same for every material, so renders of different materials can be compared, see [`preview_material`].
+pub fn shader_ball<M: Material + Clone + 'static>(
+    settings: &SceneSettings,
+    material: M,
+) -> Raytracer {
+    let camera = Camera::new(
+        vector![0., 1., 6.],
+        vector![0., -0.3, 0.],
+        vector![0., 1., 0.],
+        Float::to_radians(35.),
+        settings.aspect_ratio(),
+        0.,
+        1.,
+    );
+    let mut raytracer = settings.raytracer(camera, color![0.3, 0.4, 0.55]);
+    raytracer.lights.push_analytic(
+        DirectionalLight::new(vector![1., -1.5, -1.], 3. * color![1., 0.95, 0.85])
+            .with_angular_radius(0.05),
+    );
+    let world = &mut raytracer.world;
+
+    world.push(Rectangle::xz(
+        vector![0., -1.5, 0.],
+        100.,
+        100.,
+        Lambertian::new(CheckerTexture::solid_colors(
+            color![0.8, 0.8, 0.8],
+            color![0.3, 0.3, 0.3],
+        )),
+    ));
+    world.push(Cylinder::new(
+        vector![0., -1.25, 0.],
+        0.6,
+        0.5,
+        Lambertian::solid_color(color![0.2, 0.2, 0.2]),
+    ));
+    world.push(Sphere::new(vector![0., 0., 0.], 1., material));
+
+    raytracer
+}
+
+/// Render `material` on the [`shader_ball`] as a square image with a side length of `size`, e.g. to try out materials without building a scene.
+///
+/// The preview is traced with 64 samples per pixel and a maximum depth of 16.
+///
+/// # Panics
+/// If `size` is less than two.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Metal, scenes};
+/// let preview = scenes::preview_material(Metal::solid_color(color![0.9, 0.6, 0.3], 0.2), 16);
+/// assert_eq!(preview.dimensions(), (16, 16));
+/// ```
+pub fn preview_material<M: Material + Clone + 'static>(material: M, size: u32) -> RgbImage {
+    assert!(size >= 2, "a preview of size {size} has too few pixels");
+    let settings = SceneSettings::new(size, size).with_samples(64, 16);
+    shader_ball(&settings, material)
+        .render()
+        .expect("the shader ball is a valid scene")
+        .into_image()
+        .expect("the image has one color per pixel")
+}
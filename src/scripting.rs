@@ -0,0 +1,761 @@
+//! Procedural scenes from scripts that are read at runtime, for parametric scenes that would otherwise need a recompile for every change.
+//!
+//! Scripts are written in a small language with a syntax like Rust or Rhai:
+//! ```text
+//! // A ring of spheres around a glass ball
+//! let n = count;
+//! for i in 0..n {
+//!     let angle = 2 * PI * i / n;
+//!     let material = if i % 2 == 0 { lambertian(0.8, 0.2, 0.2) } else { "gold" };
+//!     sphere(3 * cos(angle), 0, 3 * sin(angle), 0.5, material);
+//! }
+//! sphere(0, 0, 0, 1, dielectric(1.5));
+//! ```
+//! Values are numbers, booleans, strings, and materials. Strings are looked up as materials in the [`MaterialLibrary`](crate::registry::MaterialLibrary) of the [`Script`].
+//! There are `let` bindings, assignments, `if`/`else` (also as expressions), and `for` loops over ranges of integers. There are no other loops, so every script terminates.
+//!
+//! The functions are:
+//! - Shapes, which are added to the world: `sphere(x, y, z, radius, material)`, `cuboid(x, y, z, width, height, depth, material)` and `cylinder(x, y, z, radius, height, material)`, all at their centers.
+//! - Materials: `lambertian(r, g, b)`, `metal(r, g, b, fuzz)`, `dielectric(index_of_refraction)`, and `light(r, g, b)`.
+//! - Math: `sin`, `cos`, `tan`, `atan2`, `sqrt`, `abs`, `floor`, `ceil`, `min`, `max`, `pow`, and the constant `PI`.
+//! - `random()` in \[0, 1) and `random(a, b)` in \[a, b), from the seed of the [`Script`].
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::error::Error;
+use crate::materials::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::registry::MaterialLibrary;
+use crate::shapes::{Cuboid, Cylinder, Sphere};
+use crate::*;
+
+/// A parsed scene script.
+///
+/// # Fields
+/// - `statements`: Statements of the script with their lines.
+/// - `variables`: Numbers that are defined before the script runs, e.g. parameters of the scene.
+/// - `library`: Materials that the script refers to by name.
+/// - `seed`: Seed of `random`.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, scripting::Script};
+/// let script = Script::parse(r#"
+///     for i in 0..count {
+///         sphere(i * 2, 0, -5, 0.5, lambertian(0.5, 0.5, random()));
+///     }
+/// "#).unwrap().with_variable("count", 3.);
+///
+/// let mut raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 40, 30, 1, 4);
+/// raytracer.world = script.run().unwrap();
+/// assert_eq!(raytracer.world.len(), 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Script {
+    statements: Vec<(Statement, usize)>,
+    variables: HashMap<String, Float>,
+    library: MaterialLibrary,
+    seed: u64,
+}
+
+impl Script {
+    /// Read a script from a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse a script.
+    ///
+    /// Returns [`Error::Config`] for syntax errors.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        let mut statements = Vec::new();
+        while parser.peek().is_some() {
+            statements.push(parser.statement()?);
+        }
+        Ok(Self {
+            statements,
+            variables: HashMap::new(),
+            library: MaterialLibrary::default(),
+            seed: 0,
+        })
+    }
+
+    /// Consume `self` and define the variable `name` as `value` before the script runs.
+    pub fn with_variable(mut self, name: impl Into<String>, value: Float) -> Self {
+        self.variables.insert(name.into(), value);
+        self
+    }
+
+    /// Consume `self` and set the [`MaterialLibrary`] whose materials the script refers to by name.
+    pub fn with_library(mut self, library: MaterialLibrary) -> Self {
+        self.library = library;
+        self
+    }
+
+    /// Consume `self` and set the seed of `random` (0 by default).
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Run the script and return the shapes it created.
+    ///
+    /// Returns [`Error::Config`] for errors while running, e.g. unknown variables or wrong arguments.
+    pub fn run(&self) -> Result<HittableList, Error> {
+        let mut globals: HashMap<_, _> = self
+            .variables
+            .iter()
+            .map(|(name, value)| (name.clone(), Value::Number(*value)))
+            .collect();
+        globals.insert("PI".to_string(), Value::Number(float::consts::PI));
+        let mut interpreter = Interpreter {
+            library: &self.library,
+            scopes: vec![globals],
+            rng: StdRng::seed_from_u64(self.seed),
+            world: HittableList::default(),
+            line: 0,
+        };
+        match interpreter.block(&self.statements) {
+            Ok(_) => Ok(interpreter.world),
+            Err(message) => Err(invalid(interpreter.line, &message)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(Float),
+    Text(String),
+    Word(String),
+    Symbol(&'static str),
+}
+
+/// Operators and punctuation, with the longer ones first.
+const SYMBOLS: [&str; 22] = [
+    "==", "!=", "<=", ">=", "&&", "||", "..", "(", ")", "{", "}", ",", ";", "=", "<", ">", "+",
+    "-", "*", "/", "%", "!",
+];
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let characters: Vec<char> = text.chars().collect();
+    let mut index = 0;
+
+    while index < characters.len() {
+        let character = characters[index];
+        let rest: String = characters[index..(index + 2).min(characters.len())]
+            .iter()
+            .collect();
+        if character == '\n' {
+            line += 1;
+            index += 1;
+        } else if character.is_whitespace() {
+            index += 1;
+        } else if rest == "//" {
+            while index < characters.len() && characters[index] != '\n' {
+                index += 1;
+            }
+        } else if character == '"' {
+            let start = index + 1;
+            index = start;
+            while index < characters.len() && characters[index] != '"' {
+                if characters[index] == '\n' {
+                    return Err(invalid(line, "unterminated string"));
+                }
+                index += 1;
+            }
+            if index == characters.len() {
+                return Err(invalid(line, "unterminated string"));
+            }
+            tokens.push((Token::Text(characters[start..index].iter().collect()), line));
+            index += 1;
+        } else if character.is_ascii_digit() {
+            let start = index;
+            while index < characters.len()
+                && (characters[index].is_ascii_digit()
+                    || characters[index] == '_'
+                    // A decimal point, but not the start of a range
+                    || (characters[index] == '.' && characters.get(index + 1) != Some(&'.')))
+            {
+                index += 1;
+            }
+            let number: String = characters[start..index]
+                .iter()
+                .filter(|&&character| character != '_')
+                .collect();
+            let number = number
+                .parse()
+                .map_err(|_| invalid(line, &format!("invalid number {number:?}")))?;
+            tokens.push((Token::Number(number), line));
+        } else if character.is_alphabetic() || character == '_' {
+            let start = index;
+            while index < characters.len()
+                && (characters[index].is_alphanumeric() || characters[index] == '_')
+            {
+                index += 1;
+            }
+            tokens.push((Token::Word(characters[start..index].iter().collect()), line));
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push((Token::Symbol(symbol), line));
+            index += symbol.len();
+        } else {
+            return Err(invalid(
+                line,
+                &format!("unexpected character {character:?}"),
+            ));
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Clone, Debug)]
+enum Statement {
+    Let(String, Expression),
+    Assign(String, Expression),
+    /// A loop over the integers from the first expression to the second, excluding it.
+    For(String, Expression, Expression, Vec<(Statement, usize)>),
+    Expression(Expression),
+}
+
+#[derive(Clone, Debug)]
+enum Expression {
+    Number(Float),
+    Text(String),
+    Boolean(bool),
+    Variable(String),
+    Unary(&'static str, Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+    Call(String, Vec<Expression>),
+    /// The value of an `if` is the value of the last expression of the branch that is taken.
+    If(
+        Box<Expression>,
+        Vec<(Statement, usize)>,
+        Vec<(Statement, usize)>,
+    ),
+}
+
+/// Binary operators from the lowest precedence to the highest.
+const PRECEDENCE: [&[&str]; 5] = [
+    &["||"],
+    &["&&"],
+    &["==", "!=", "<", "<=", ">", ">="],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position.min(self.tokens.len().saturating_sub(1)))
+            .map_or(0, |(_, line)| *line)
+    }
+
+    /// Whether the next token is `symbol`, which is consumed if it is.
+    fn eat(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(next)) if *next == symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), Error> {
+        match self.eat(symbol) {
+            true => Ok(()),
+            false => Err(invalid(
+                self.line(),
+                &format!("expected `{symbol}`, found {:?}", self.peek()),
+            )),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, Error> {
+        let line = self.line();
+        match self.next() {
+            Some(Token::Word(name)) => Ok(name),
+            other => Err(invalid(line, &format!("expected a name, found {other:?}"))),
+        }
+    }
+
+    fn statement(&mut self) -> Result<(Statement, usize), Error> {
+        let line = self.line();
+        let statement = match self.peek() {
+            Some(Token::Word(word)) if word == "let" => {
+                self.next();
+                let name = self.name()?;
+                self.expect("=")?;
+                Statement::Let(name, self.expression()?)
+            }
+            Some(Token::Word(word)) if word == "for" => {
+                self.next();
+                let name = self.name()?;
+                if self.next() != Some(Token::Word("in".to_string())) {
+                    return Err(invalid(line, "expected `in` after the loop variable"));
+                }
+                let start = self.expression()?;
+                self.expect("..")?;
+                let end = self.expression()?;
+                return Ok((Statement::For(name, start, end, self.block()?), line));
+            }
+            Some(Token::Word(word)) if word == "if" => {
+                // Conditionals do not need a semicolon.
+                let expression = self.primary()?;
+                self.eat(";");
+                return Ok((Statement::Expression(expression), line));
+            }
+            Some(Token::Word(_))
+                if matches!(
+                    self.tokens.get(self.position + 1),
+                    Some((Token::Symbol("="), _))
+                ) =>
+            {
+                let name = self.name()?;
+                self.next();
+                Statement::Assign(name, self.expression()?)
+            }
+            _ => Statement::Expression(self.expression()?),
+        };
+        // The semicolon may be left out after the last statement of a block.
+        if !matches!(self.peek(), None | Some(Token::Symbol("}"))) {
+            self.expect(";")?;
+        }
+        Ok((statement, line))
+    }
+
+    fn block(&mut self) -> Result<Vec<(Statement, usize)>, Error> {
+        self.expect("{")?;
+        let mut statements = Vec::new();
+        while !self.eat("}") {
+            if self.peek().is_none() {
+                return Err(invalid(self.line(), "expected `}`"));
+            }
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn expression(&mut self) -> Result<Expression, Error> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expression, Error> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Symbol(symbol)) if PRECEDENCE[level].contains(symbol) => *symbol,
+                _ => break,
+            };
+            self.next();
+            let right = self.binary(level + 1)?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expression, Error> {
+        for operator in ["-", "!"] {
+            if self.eat(operator) {
+                return Ok(Expression::Unary(operator, Box::new(self.unary()?)));
+            }
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expression, Error> {
+        let line = self.line();
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expression::Number(number)),
+            Some(Token::Text(text)) => Ok(Expression::Text(text)),
+            Some(Token::Symbol("(")) => {
+                let expression = self.expression()?;
+                self.expect(")")?;
+                Ok(expression)
+            }
+            Some(Token::Word(word)) => match word.as_str() {
+                "true" => Ok(Expression::Boolean(true)),
+                "false" => Ok(Expression::Boolean(false)),
+                "if" => {
+                    let condition = self.expression()?;
+                    let then = self.block()?;
+                    let otherwise = match self.peek() {
+                        Some(Token::Word(word)) if word == "else" => {
+                            self.next();
+                            match self.peek() {
+                                Some(Token::Word(word)) if word == "if" => {
+                                    let line = self.line();
+                                    vec![(Statement::Expression(self.primary()?), line)]
+                                }
+                                _ => self.block()?,
+                            }
+                        }
+                        _ => Vec::new(),
+                    };
+                    Ok(Expression::If(Box::new(condition), then, otherwise))
+                }
+                _ if self.eat("(") => {
+                    let mut arguments = Vec::new();
+                    while !self.eat(")") {
+                        arguments.push(self.expression()?);
+                        if !self.eat(",") {
+                            self.expect(")")?;
+                            break;
+                        }
+                    }
+                    Ok(Expression::Call(word, arguments))
+                }
+                _ => Ok(Expression::Variable(word)),
+            },
+            other => Err(invalid(line, &format!("expected a value, found {other:?}"))),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Number(Float),
+    Boolean(bool),
+    Text(String),
+    Material(Arc<dyn Material>),
+    /// The value of shapes and of `if`s without `else`.
+    Unit,
+}
+
+/// State of a running script.
+///
+/// # Fields
+/// - `library`: Materials that strings refer to.
+/// - `scopes`: Variables of the blocks that are running, from the outermost.
+/// - `rng`: Random number generator of `random`.
+/// - `world`: Shapes created so far.
+/// - `line`: Line of the statement that is running, for messages.
+struct Interpreter<'a> {
+    library: &'a MaterialLibrary,
+    scopes: Vec<HashMap<String, Value>>,
+    rng: StdRng,
+    world: HittableList,
+    line: usize,
+}
+
+impl Interpreter<'_> {
+    /// Run `statements` in a new scope and return the value of the last one.
+    fn block(&mut self, statements: &[(Statement, usize)]) -> Result<Value, String> {
+        self.scopes.push(HashMap::new());
+        let mut value = Value::Unit;
+        for (statement, line) in statements {
+            self.line = *line;
+            value = self.statement(statement)?;
+        }
+        self.scopes.pop();
+        Ok(value)
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<Value, String> {
+        match statement {
+            Statement::Let(name, expression) => {
+                let value = self.expression(expression)?;
+                let scope = self.scopes.last_mut().expect("there is a scope");
+                scope.insert(name.clone(), value);
+                Ok(Value::Unit)
+            }
+            Statement::Assign(name, expression) => {
+                let value = self.expression(expression)?;
+                let variable = self
+                    .scopes
+                    .iter_mut()
+                    .rev()
+                    .find_map(|scope| scope.get_mut(name))
+                    .ok_or_else(|| format!("unknown variable {name:?}"))?;
+                *variable = value;
+                Ok(Value::Unit)
+            }
+            Statement::For(name, start, end, body) => {
+                let start = number(&self.expression(start)?)?.ceil() as i64;
+                let end = number(&self.expression(end)?)?.ceil() as i64;
+                for i in start..end {
+                    self.scopes
+                        .push(HashMap::from([(name.clone(), Value::Number(i as Float))]));
+                    self.block(body)?;
+                    self.scopes.pop();
+                }
+                Ok(Value::Unit)
+            }
+            Statement::Expression(expression) => self.expression(expression),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<Value, String> {
+        match expression {
+            Expression::Number(number) => Ok(Value::Number(*number)),
+            Expression::Text(text) => Ok(Value::Text(text.clone())),
+            Expression::Boolean(boolean) => Ok(Value::Boolean(*boolean)),
+            Expression::Variable(name) => self
+                .scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(name))
+                .cloned()
+                .ok_or_else(|| format!("unknown variable {name:?}")),
+            Expression::Unary(operator, operand) => {
+                let operand = self.expression(operand)?;
+                match *operator {
+                    "-" => Ok(Value::Number(-number(&operand)?)),
+                    _ => Ok(Value::Boolean(!boolean(&operand)?)),
+                }
+            }
+            Expression::Binary(operator, left, right) => {
+                let left = self.expression(left)?;
+                // `&&` and `||` do not evaluate the right side if the left one decides.
+                match (*operator, boolean(&left)) {
+                    ("&&", Ok(false)) => return Ok(Value::Boolean(false)),
+                    ("||", Ok(true)) => return Ok(Value::Boolean(true)),
+                    _ => {}
+                }
+                let right = self.expression(right)?;
+                binary(operator, &left, &right)
+            }
+            Expression::Call(name, arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.expression(argument))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call(name, &arguments)
+            }
+            Expression::If(condition, then, otherwise) => {
+                let branch = match boolean(&self.expression(condition)?)? {
+                    true => then,
+                    false => otherwise,
+                };
+                self.block(branch)
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str, arguments: &[Value]) -> Result<Value, String> {
+        let numbers = |count: usize| -> Result<Vec<Float>, String> {
+            if arguments.len() != count {
+                return Err(format!(
+                    "{name} takes {count} arguments, but {} were given",
+                    arguments.len()
+                ));
+            }
+            arguments.iter().map(number).collect()
+        };
+        // Shapes take the material as the last argument.
+        let library = self.library;
+        let shape = |count: usize| -> Result<(Vec<Float>, Arc<dyn Material>), String> {
+            let Some((material, arguments)) = arguments.split_last() else {
+                return Err(format!("{name} takes {} arguments", count + 1));
+            };
+            if arguments.len() != count {
+                return Err(format!(
+                    "{name} takes {} arguments, but {} were given",
+                    count + 1,
+                    arguments.len() + 1
+                ));
+            }
+            let material = match material {
+                Value::Material(material) => material.clone(),
+                Value::Text(name) => library
+                    .material(name)
+                    .ok_or_else(|| format!("unknown material {name:?}"))?,
+                other => return Err(format!("expected a material, found {other:?}")),
+            };
+            Ok((
+                arguments.iter().map(number).collect::<Result<_, _>>()?,
+                material,
+            ))
+        };
+
+        let value = match name {
+            "sphere" => {
+                let (a, material) = shape(4)?;
+                self.world
+                    .push(Sphere::new(vector![a[0], a[1], a[2]], a[3], material));
+                Value::Unit
+            }
+            "cuboid" => {
+                let (a, material) = shape(6)?;
+                let center = vector![a[0], a[1], a[2]];
+                self.world
+                    .push(Cuboid::new(center, a[3], a[4], a[5], material));
+                Value::Unit
+            }
+            "cylinder" => {
+                let (a, material) = shape(5)?;
+                let center = vector![a[0], a[1], a[2]];
+                self.world.push(Cylinder::new(center, a[3], a[4], material));
+                Value::Unit
+            }
+            "lambertian" => {
+                let a = numbers(3)?;
+                Value::Material(Arc::new(Lambertian::solid_color(color![a[0], a[1], a[2]])))
+            }
+            "metal" => {
+                let a = numbers(4)?;
+                Value::Material(Arc::new(Metal::solid_color(color![a[0], a[1], a[2]], a[3])))
+            }
+            "dielectric" => Value::Material(Arc::new(Dielectric::new(numbers(1)?[0]))),
+            "light" => {
+                let a = numbers(3)?;
+                Value::Material(Arc::new(DiffuseLight::solid_color(color![
+                    a[0], a[1], a[2]
+                ])))
+            }
+            "random" => match arguments.len() {
+                0 => Value::Number(self.rng.gen()),
+                _ => {
+                    let a = numbers(2)?;
+                    Value::Number(a[0] + (a[1] - a[0]) * self.rng.gen::<Float>())
+                }
+            },
+            "atan2" | "min" | "max" | "pow" => {
+                let a = numbers(2)?;
+                Value::Number(match name {
+                    "atan2" => a[0].atan2(a[1]),
+                    "min" => a[0].min(a[1]),
+                    "max" => a[0].max(a[1]),
+                    _ => a[0].powf(a[1]),
+                })
+            }
+            _ => {
+                let function: fn(Float) -> Float = match name {
+                    "sin" => Float::sin,
+                    "cos" => Float::cos,
+                    "tan" => Float::tan,
+                    "sqrt" => Float::sqrt,
+                    "abs" => Float::abs,
+                    "floor" => Float::floor,
+                    "ceil" => Float::ceil,
+                    _ => return Err(format!("unknown function {name:?}")),
+                };
+                Value::Number(function(numbers(1)?[0]))
+            }
+        };
+        Ok(value)
+    }
+}
+
+fn binary(operator: &str, left: &Value, right: &Value) -> Result<Value, String> {
+    let value = match operator {
+        "&&" | "||" => Value::Boolean(boolean(right)?),
+        "==" | "!=" => {
+            let equal = match (left, right) {
+                (Value::Number(a), Value::Number(b)) => a == b,
+                (Value::Boolean(a), Value::Boolean(b)) => a == b,
+                (Value::Text(a), Value::Text(b)) => a == b,
+                _ => return Err(format!("cannot compare {left:?} and {right:?}")),
+            };
+            Value::Boolean(equal == (operator == "=="))
+        }
+        _ => {
+            let (a, b) = (number(left)?, number(right)?);
+            match operator {
+                "<" => Value::Boolean(a < b),
+                "<=" => Value::Boolean(a <= b),
+                ">" => Value::Boolean(a > b),
+                ">=" => Value::Boolean(a >= b),
+                "+" => Value::Number(a + b),
+                "-" => Value::Number(a - b),
+                "*" => Value::Number(a * b),
+                "/" => Value::Number(a / b),
+                _ => Value::Number(a.rem_euclid(b)),
+            }
+        }
+    };
+    Ok(value)
+}
+
+fn number(value: &Value) -> Result<Float, String> {
+    match value {
+        Value::Number(number) => Ok(*number),
+        other => Err(format!("expected a number, found {other:?}")),
+    }
+}
+
+fn boolean(value: &Value) -> Result<bool, String> {
+    match value {
+        Value::Boolean(boolean) => Ok(*boolean),
+        other => Err(format!("expected a boolean, found {other:?}")),
+    }
+}
+
+fn invalid(line: usize, message: &str) -> Error {
+    Error::Config(format!("invalid script: line {line}: {message}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::registry::Registry;
+
+    #[test]
+    fn procedural_scene() {
+        let library = Registry::default()
+            .parse("[gold]\nmaterial = \"metal\"\nalbedo = [1.0, 0.8, 0.3]\n")
+            .unwrap();
+        let script = Script::parse(
+            r#"
+            // A ring of spheres around a glass ball
+            let n = count;
+            let placed = 0;
+            for i in 0..n {
+                let angle = 2 * PI * i / n;
+                let material = if i % 2 == 0 { lambertian(0.8, 0.2, 0.2) } else { "gold" };
+                sphere(3 * cos(angle), 0, 3 * sin(angle), 0.5, material);
+                placed = placed + 1;
+            }
+            if placed == n && !(n < 2 || false) {
+                cuboid(0, -1.5, 0, 10, 1, 10, lambertian(0.5, 0.5, 0.5));
+            }
+            sphere(0, 0, 0, min(1, 2), dielectric(1.5))
+            "#,
+        )
+        .unwrap()
+        .with_library(library)
+        .with_variable("count", 6.);
+        let world = script.run().unwrap();
+        assert_eq!(world.len(), 8);
+        let aabb = world.bounding_box(0., 0.).unwrap();
+        assert!((aabb.maximum.y - 1.).abs() < 1e-4 && (aabb.minimum.y + 2.).abs() < 1e-4);
+
+        let error = |text: &str| Script::parse(text).and_then(|script| script.run());
+        let message = error("let a = 1;\nsphere(0, 0, 0, a, \"chrome\");")
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("line 2: unknown material"), "{message}");
+        assert!(error("for i in 0..3 { x = i; }").is_err());
+        assert!(error("let a = (1 + 2;").is_err());
+        assert!(error("sin(1, 2)").is_err());
+    }
+}
@@ -0,0 +1,377 @@
+//! Objects described by signed distance fields (SDFs).
+//!
+//! A signed distance field returns for every point the distance to the closest point on the surface, negative inside of the object.
+//! [`SdfObject`]s are intersected by sphere tracing: the [`Ray`] is advanced by the distance to the surface until it gets close enough to count as a hit.
+//! This allows rendering shapes that have no analytic intersection, like fractals or smoothly blended primitives.
+
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+use nalgebra::Rotation3;
+
+use crate::hitrecord::HitRecord;
+use crate::hittable::Aabb;
+use crate::materials::Material;
+use crate::ray::Ray;
+use crate::shapes::{Movable, Offset};
+use crate::*;
+
+/// Maximum number of sphere tracing steps per [`Ray`].
+const MAX_STEPS: usize = 256;
+
+/// Distance to the surface at which a point counts as a hit.
+const HIT_DISTANCE: Float = 1e-4;
+
+/// Distance behind the origin of a [`Ray`] at which sphere tracing starts at the earliest without bounds, e.g. for the infinite `t_min` of a [CSG](crate::csg) operand.
+const MAX_DISTANCE: Float = 1e4;
+
+/// Step used for estimating the gradient (normal) of the distance field.
+const GRADIENT_STEP: Float = 1e-4;
+
+type DistanceFunction = Arc<dyn Fn(Vector3<Float>) -> Float + Send + Sync>;
+
+/// A signed distance field.
+///
+/// Built-in primitives are centered at the origin and can be combined via [`SmoothUnion`](Sdf::SmoothUnion), [`Translate`](Sdf::Translate), and [`Scale`](Sdf::Scale).
+#[derive(Clone)]
+pub enum Sdf {
+    Sphere {
+        radius: Float,
+    },
+    /// An axis-aligned box with the given half extents along each axis.
+    Cuboid {
+        half_extents: Vector3<Float>,
+    },
+    /// A torus lying in the xz plane.
+    Torus {
+        major_radius: Float,
+        minor_radius: Float,
+    },
+    /// The Mandelbulb fractal (fits into a sphere of radius ~1.2).
+    Mandelbulb {
+        power: Float,
+        iterations: u16,
+    },
+    /// Union of two fields with their seam blended over the distance `smoothness`.
+    SmoothUnion(Box<Sdf>, Box<Sdf>, Float),
+    Translate(Box<Sdf>, Vector3<Float>),
+    Scale(Box<Sdf>, Float),
+    /// A user-provided distance function.
+    ///
+    /// It has no known bounds, so the [`SdfObject`] should be given some via [`with_bounds`](SdfObject::with_bounds).
+    Custom(DistanceFunction),
+}
+
+impl Sdf {
+    /// Create an [`Sdf`] from a closure.
+    pub fn custom<F: Fn(Vector3<Float>) -> Float + Send + Sync + 'static>(distance: F) -> Self {
+        Self::Custom(Arc::new(distance))
+    }
+
+    /// Signed distance of `point` to the surface.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, sdf::Sdf};
+    /// let sphere = Sdf::Sphere { radius: 1. };
+    /// assert_eq!(sphere.distance(vector![2., 0., 0.]), 1.);
+    /// assert_eq!(sphere.distance(vector![0., 0., 0.]), -1.);
+    /// ```
+    pub fn distance(&self, point: Vector3<Float>) -> Float {
+        match self {
+            Sdf::Sphere { radius } => point.norm() - radius,
+            Sdf::Cuboid { half_extents } => {
+                let q = point.abs() - half_extents;
+                q.sup(&Vector3::zeros()).norm() + q.max().min(0.)
+            }
+            Sdf::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let ring = vector![point.x, point.z].norm() - major_radius;
+                vector![ring, point.y].norm() - minor_radius
+            }
+            Sdf::Mandelbulb { power, iterations } => mandelbulb(point, *power, *iterations),
+            Sdf::SmoothUnion(a, b, smoothness) => {
+                let a = a.distance(point);
+                let b = b.distance(point);
+                if *smoothness <= 0. {
+                    return a.min(b);
+                }
+                let h = (0.5 + 0.5 * (b - a) / smoothness).clamp(0., 1.);
+                b + (a - b) * h - smoothness * h * (1. - h)
+            }
+            Sdf::Translate(sdf, offset) => sdf.distance(point - offset),
+            Sdf::Scale(sdf, scale) => scale * sdf.distance(point / *scale),
+            Sdf::Custom(distance) => distance(point),
+        }
+    }
+
+    /// Estimate the outward normal at `point` from the gradient of the field (tetrahedron technique).
+    pub fn normal(&self, point: Vector3<Float>) -> Vector3<Float> {
+        let k = [
+            vector![1., -1., -1.],
+            vector![-1., -1., 1.],
+            vector![-1., 1., -1.],
+            vector![1., 1., 1.],
+        ];
+        k.iter()
+            .map(|k| *k * self.distance(point + GRADIENT_STEP * *k))
+            .sum::<Vector3<Float>>()
+            .normalize()
+    }
+
+    /// Return the [`Aabb`] that encompasses the surface or [`None`] if it is unknown.
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        match self {
+            Sdf::Sphere { radius } => {
+                let r = vector![radius.abs(), radius.abs(), radius.abs()];
+                Some(Aabb::new(-r, r))
+            }
+            Sdf::Cuboid { half_extents } => {
+                Some(Aabb::new(-half_extents.abs(), half_extents.abs()))
+            }
+            Sdf::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let outer = major_radius.abs() + minor_radius.abs();
+                let r = vector![outer, minor_radius.abs(), outer];
+                Some(Aabb::new(-r, r))
+            }
+            Sdf::Mandelbulb { .. } => {
+                let r = vector![1.2, 1.2, 1.2];
+                Some(Aabb::new(-r, r))
+            }
+            Sdf::SmoothUnion(a, b, smoothness) => {
+                let aabb = a.bounding_box()?.surrounding(&b.bounding_box()?);
+                let s = vector![smoothness.abs(), smoothness.abs(), smoothness.abs()];
+                Some(Aabb::new(aabb.minimum() - s, aabb.maximum() + s))
+            }
+            Sdf::Translate(sdf, offset) => {
+                let aabb = sdf.bounding_box()?;
+                Some(Aabb::new(aabb.minimum() + offset, aabb.maximum() + offset))
+            }
+            Sdf::Scale(sdf, scale) => {
+                let aabb = sdf.bounding_box()?;
+                Some(Aabb::new(
+                    scale.abs() * aabb.minimum(),
+                    scale.abs() * aabb.maximum(),
+                ))
+            }
+            Sdf::Custom(_) => None,
+        }
+    }
+}
+
+impl Debug for Sdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sdf::Sphere { radius } => f.debug_struct("Sphere").field("radius", radius).finish(),
+            Sdf::Cuboid { half_extents } => f
+                .debug_struct("Cuboid")
+                .field("half_extents", half_extents)
+                .finish(),
+            Sdf::Torus {
+                major_radius,
+                minor_radius,
+            } => f
+                .debug_struct("Torus")
+                .field("major_radius", major_radius)
+                .field("minor_radius", minor_radius)
+                .finish(),
+            Sdf::Mandelbulb { power, iterations } => f
+                .debug_struct("Mandelbulb")
+                .field("power", power)
+                .field("iterations", iterations)
+                .finish(),
+            Sdf::SmoothUnion(a, b, smoothness) => f
+                .debug_tuple("SmoothUnion")
+                .field(a)
+                .field(b)
+                .field(smoothness)
+                .finish(),
+            Sdf::Translate(sdf, offset) => {
+                f.debug_tuple("Translate").field(sdf).field(offset).finish()
+            }
+            Sdf::Scale(sdf, scale) => f.debug_tuple("Scale").field(sdf).field(scale).finish(),
+            Sdf::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// Distance estimator of the Mandelbulb fractal.
+fn mandelbulb(point: Vector3<Float>, power: Float, iterations: u16) -> Float {
+    let mut z = point;
+    let mut dr = 1.;
+    let mut r = 0.;
+
+    for _ in 0..iterations {
+        r = z.norm();
+        if r > 2. {
+            break;
+        }
+
+        let theta = (z.z / r).acos() * power;
+        let phi = z.y.atan2(z.x) * power;
+        dr = r.powf(power - 1.) * power * dr + 1.;
+
+        z = r.powf(power)
+            * vector![
+                theta.sin() * phi.cos(),
+                phi.sin() * theta.sin(),
+                theta.cos()
+            ]
+            + point;
+    }
+
+    if r == 0. {
+        return 0.;
+    }
+    0.5 * r.ln() * r / dr
+}
+
+/// Parameter range in which a [`Ray`] lies inside an [`Aabb`].
+fn aabb_interval(aabb: &Aabb, ray: Ray, t_min: Float, t_max: Float) -> Option<(Float, Float)> {
+    let mut t_min = t_min;
+    let mut t_max = t_max;
+    for axis in 0..3 {
+        let inverse_direction = 1. / ray.direction()[axis];
+        let mut t0 = (aabb.minimum()[axis] - ray.origin()[axis]) * inverse_direction;
+        let mut t1 = (aabb.maximum()[axis] - ray.origin()[axis]) * inverse_direction;
+        if inverse_direction < 0. {
+            (t0, t1) = (t1, t0);
+        }
+        t_min = t_min.max(t0);
+        t_max = t_max.min(t1);
+        if t_max < t_min {
+            return None;
+        }
+    }
+    Some((t_min, t_max))
+}
+
+/// A [`Hittable`] whose surface is given by a signed distance field.
+///
+/// # Fields
+/// - `center`: Its [`Offset`].
+/// - `sdf`: Its [`Sdf`].
+/// - `bounds`: [`Aabb`] that sphere tracing is restricted to.
+/// - `material`: Its material.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, ray::Ray, sdf::*};
+/// let blob = Sdf::SmoothUnion(
+///     Box::new(Sdf::Sphere { radius: 1. }),
+///     Box::new(Sdf::Translate(Box::new(Sdf::Sphere { radius: 1. }), vector![1.5, 0., 0.])),
+///     0.5,
+/// );
+/// let object = SdfObject::new(vector![0., 0., 0.], blob, Lambertian::solid_color(color![0.5, 0.5, 0.5]));
+///
+/// let ray = Ray::new(vector![0., 0., 5.], vector![0., 0., -1.]);
+/// let hit = object.hit(ray, 0., Float::INFINITY).unwrap();
+/// assert!((hit.point.z - 1.).abs() < 1e-2);
+///
+/// // Without bounds, sphere tracing also starts at a finite distance for an infinite `t_min`.
+/// let plane = SdfObject::new(vector![0., 0., 0.], Sdf::custom(|point| point.z), Lambertian::solid_color(color![0.5, 0.5, 0.5]));
+/// let hit = plane.hit(ray, Float::NEG_INFINITY, Float::INFINITY).unwrap();
+/// assert!(hit.point.z.abs() < 1e-2 && hit.t.is_finite());
+/// ```
+#[derive(Clone, Debug)]
+pub struct SdfObject<M: Material> {
+    center: Offset,
+    sdf: Sdf,
+    bounds: Option<Aabb>,
+    material: M,
+}
+
+impl<M: Material> SdfObject<M> {
+    pub fn new(center: Vector3<Float>, sdf: Sdf, material: M) -> Self {
+        let bounds = sdf.bounding_box();
+        Self {
+            center: Offset::new(center),
+            sdf,
+            bounds,
+            material,
+        }
+    }
+
+    /// Consume `self` and restrict sphere tracing to an [`Aabb`] (relative to the center).
+    ///
+    /// This is necessary for [`Sdf::Custom`] in order to be sorted into the bounding volume hierarchy.
+    pub fn with_bounds(mut self, bounds: Aabb) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    pub fn sdf(&self) -> &Sdf {
+        &self.sdf
+    }
+
+    pub fn material(&self) -> &M {
+        &self.material
+    }
+}
+
+impl<M: Material + Clone + 'static> Hittable for SdfObject<M> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let ray_length = ray.direction().norm();
+        let (t_start, t_end) = match &self.bounds {
+            Some(bounds) => aabb_interval(bounds, ray, t_min, t_max)?,
+            None => (t_min.max(-MAX_DISTANCE / ray_length), t_max),
+        };
+
+        let mut t = t_start;
+
+        for _ in 0..MAX_STEPS {
+            if t > t_end {
+                return None;
+            }
+
+            let point = ray.at(t);
+            let distance = self.sdf.distance(point).abs();
+            if distance < HIT_DISTANCE {
+                if t <= t_min {
+                    // Started on the surface: step through it.
+                    t += 2. * HIT_DISTANCE / ray_length;
+                    continue;
+                }
+                let normal = self.sdf.normal(point);
+                return Some(HitRecord::from_ray(
+                    point,
+                    0.,
+                    0.,
+                    normal,
+                    t,
+                    &self.material,
+                    ray,
+                ));
+            }
+
+            t += distance / ray_length;
+        }
+
+        None
+    }
+
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
+        self.bounds
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+}
+
+impl<M: Material + Clone + 'static> Movable for SdfObject<M> {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
@@ -1,43 +1,94 @@
 //! Collection of [hittable](`Hittable`) shapes .
 
-use std::f32::consts::{FRAC_PI_2, PI};
+use crate::float::consts::{FRAC_PI_2, PI};
 use std::fmt::Debug;
+use std::sync::Arc;
 
 use nalgebra::Rotation3;
 use rand::Rng;
 
 use crate::hitrecord::HitRecord;
-use crate::hittable::Aabb;
+use crate::hittable::{surface_pdf_value, Aabb};
 use crate::materials::{Isotropic, Material};
+use crate::mesh::{Displacement, Mesh, Shading};
 use crate::ray::Ray;
+use crate::stats::SceneStats;
 use crate::textures::{SolidColor, Texture};
+use crate::vec3::{near_zero, random_unit_vector_in_unit_sphere, random_vector_in_cone};
 use crate::*;
 
 /// Marks an object to support movement and rotation via [`Offset`].
 pub trait Movable: Clone + Debug + Hittable {
     /// Consumes `self` and returns a rotated version.
-    fn with_rotation(self, rotation: Rotation3<f32>) -> Self;
+    fn with_rotation(self, rotation: Rotation3<Float>) -> Self;
 
     /// Consumes `self` and returns a moving version (translatory).
-    fn moving(self, offset_end: Vector3<f32>, time_start: f32, time_end: f32) -> Self;
+    fn moving(self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self;
+}
+
+/// The cone of directions from a point towards a sphere, used for sampling lights by solid angle.
+///
+/// # Fields
+/// - `axis`: Unit vector from the point to the center of the sphere.
+/// - `cos_max`: Cosine of the opening angle.
+/// - `solid_angle`: Solid angle of the cone.
+#[derive(Clone, Copy, Debug)]
+struct Cone {
+    axis: Vector3<Float>,
+    cos_max: Float,
+    solid_angle: Float,
+}
+
+impl Cone {
+    /// The cone from `origin` towards the sphere at `center` with `radius`, or [`None`] if `origin` is inside of it.
+    fn towards(center: Vector3<Float>, radius: Float, origin: Vector3<Float>) -> Option<Self> {
+        let to_center = center - origin;
+        let distance_squared = to_center.norm_squared();
+        let sin_squared = radius * radius / distance_squared;
+        if sin_squared.is_nan() || sin_squared >= 1. {
+            return None;
+        }
+        let cos_max = (1. - sin_squared).sqrt();
+        // 1 - cos_max without cancellation, so that small and distant spheres keep a finite density
+        let solid_angle = 2. * PI * sin_squared / (1. + cos_max);
+        (solid_angle > 0.).then_some(Self {
+            axis: to_center / distance_squared.sqrt(),
+            cos_max,
+            solid_angle,
+        })
+    }
+
+    /// Probability density (with respect to solid angle) of sampling `direction`.
+    fn pdf(&self, direction: Vector3<Float>) -> Float {
+        if self.axis.dot(&direction.normalize()) >= self.cos_max {
+            1. / self.solid_angle
+        } else {
+            0.
+        }
+    }
+
+    /// Sample a uniformly distributed direction.
+    fn sample(&self) -> Vector3<Float> {
+        random_vector_in_cone(&self.axis, self.cos_max)
+    }
 }
 
 /// Marks an object as moving (translatory).
 #[derive(Clone, Default, Debug)]
 struct Moving {
-    pub offset_end: Vector3<f32>,
-    pub time_start: f32,
-    pub time_end: f32,
+    pub offset_end: Vector3<Float>,
+    pub time_start: Float,
+    pub time_end: Float,
 }
 
 #[derive(Clone, Default, Debug)]
 pub struct Offset {
-    offset_start: Vector3<f32>,
-    rotation: Option<Rotation3<f32>>,
+    offset_start: Vector3<Float>,
+    rotation: Option<Rotation3<Float>>,
     moving: Option<Moving>,
 }
 impl Offset {
-    pub fn new(offset: Vector3<f32>) -> Self {
+    pub fn new(offset: Vector3<Float>) -> Self {
         Self {
             offset_start: offset,
             rotation: None,
@@ -45,12 +96,17 @@ impl Offset {
         }
     }
 
-    pub fn with_rotation(mut self, rotation: Rotation3<f32>) -> Self {
+    pub fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
         self.rotation = Some(rotation);
         self
     }
 
-    pub fn moving(mut self, offset_end: Vector3<f32>, time_start: f32, time_end: f32) -> Self {
+    pub fn moving(
+        mut self,
+        offset_end: Vector3<Float>,
+        time_start: Float,
+        time_end: Float,
+    ) -> Self {
         self.moving = Some(Moving {
             offset_end,
             time_start,
@@ -59,7 +115,7 @@ impl Offset {
         self
     }
 
-    fn offset(&self, time: f32) -> Vector3<f32> {
+    fn offset(&self, time: Float) -> Vector3<Float> {
         match &self.moving {
             Some(moving) => {
                 self.offset_start
@@ -70,13 +126,62 @@ impl Offset {
         }
     }
 
-    pub(crate) fn hit<'a, H: Hittable + ?Sized>(
-        &'a self,
-        hittable: &'a H,
-        ray: Ray,
-        t_min: f32,
-        t_max: f32,
-    ) -> Option<HitRecord<'a>> {
+    /// Transform a point relative to the [`Offset`] into world coordinates.
+    pub(crate) fn to_world(&self, point: Vector3<Float>, time: Float) -> Vector3<Float> {
+        let point = point + self.offset(time);
+        match self.rotation {
+            Some(rotation) => rotation.inverse() * point,
+            None => point,
+        }
+    }
+
+    /// Transform a normal relative to the [`Offset`] into world coordinates.
+    pub(crate) fn normal_to_world(&self, normal: Vector3<Float>) -> Vector3<Float> {
+        match self.rotation {
+            Some(rotation) => rotation.inverse() * normal,
+            None => normal,
+        }
+    }
+
+    /// Transform a point in world coordinates into one relative to the [`Offset`].
+    pub(crate) fn to_local(&self, point: Vector3<Float>, time: Float) -> Vector3<Float> {
+        let point = match self.rotation {
+            Some(rotation) => rotation * point,
+            None => point,
+        };
+        point - self.offset(time)
+    }
+
+    /// The [`Offset`] that first applies `self` and then `parent`, e.g. for a child in a [scene graph](crate::scenegraph).
+    ///
+    /// If both move, the time interval of `self` is used.
+    pub(crate) fn then(&self, parent: &Offset) -> Offset {
+        let rotation = match (self.rotation, parent.rotation) {
+            (Some(child), Some(parent)) => Some(child * parent),
+            (child, parent) => child.or(parent),
+        };
+        // The offset of the parent is applied after the rotation of the child.
+        let offset = |time: Float| {
+            let parent_offset = parent.offset(time);
+            self.offset(time)
+                + self
+                    .rotation
+                    .map_or(parent_offset, |rotation| rotation * parent_offset)
+        };
+        let moving = self.moving.as_ref().or(parent.moving.as_ref());
+        Offset {
+            offset_start: offset(moving.map_or(0., |moving| moving.time_start)),
+            rotation,
+            moving: moving.map(|moving| Moving {
+                offset_end: offset(moving.time_end),
+                time_start: moving.time_start,
+                time_end: moving.time_end,
+            }),
+        }
+    }
+
+    /// Transform a [`Ray`] in world coordinates into one relative to the [`Offset`].
+    pub(crate) fn ray_to_local(&self, ray: Ray) -> Ray {
         // Rotation
         let rotated_ray = match self.rotation {
             Some(rotation) => {
@@ -86,19 +191,36 @@ impl Offset {
         };
 
         // Translation
-        let offset_ray = Ray::new(
+        let local = Ray::new(
             rotated_ray.origin() - self.offset(rotated_ray.time()),
             rotated_ray.direction(),
         )
         .with_time(rotated_ray.time());
+        match ray.kind() {
+            Some(kind) => local.with_kind(kind),
+            None => local,
+        }
+    }
 
-        let mut hit_record_option = hittable.hit_origin(offset_ray, t_min, t_max);
+    pub(crate) fn hit<'a, H: Hittable + ?Sized>(
+        &'a self,
+        hittable: &'a H,
+        ray: Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<HitRecord<'a>> {
+        let mut hit_record_option = hittable.hit_origin(self.ray_to_local(ray), t_min, t_max);
 
         if let Some(hit_record) = &mut hit_record_option {
             hit_record.point += self.offset(ray.time());
             if let Some(rotation) = self.rotation {
                 hit_record.point = rotation.inverse() * hit_record.point;
                 hit_record.normal = rotation.inverse() * hit_record.normal;
+                hit_record.geometric_normal = rotation.inverse() * hit_record.geometric_normal;
+                hit_record.tangent = rotation.inverse() * hit_record.tangent;
+                hit_record.uv_gradient = hit_record
+                    .uv_gradient
+                    .map(|gradient| gradient.map(|gradient| rotation.inverse() * gradient));
             }
         }
 
@@ -108,8 +230,8 @@ impl Offset {
     pub(crate) fn bounding_box<'a, H: Hittable + ?Sized>(
         &'a self,
         hittable: &'a H,
-        time0: f32,
-        time1: f32,
+        time0: Float,
+        time1: Float,
     ) -> Option<Aabb> {
         let mut aabb_option = hittable.bounding_box_origin(time0, time1);
         if let Some(aabb) = &mut aabb_option {
@@ -125,34 +247,137 @@ impl Offset {
     }
 }
 
+/// How the surface coordinates (u, v) of a [`Sphere`] are computed from the unit vector pointing to the hit point.
+#[derive(Clone, Default)]
+pub enum UvMapping {
+    /// Spherical coordinates: u = phi/(2pi), v = theta/pi.
+    ///
+    /// Image textures are pinched at the poles and have a seam where u wraps around (at -x, see [`Sphere::with_seam_angle`]).
+    #[default]
+    Spherical,
+    /// Project onto the faces of a cube, which are arranged in an atlas of three columns and two rows.
+    ///
+    /// The bottom row (v < 0.5) holds the faces +x, -x, +y and the top row -y, +z, -z (from left to right).
+    /// Each face is seen from the outside, with the side faces upright and +y and -y having -z and +z at their top, respectively.
+    /// This avoids the pinching at the poles.
+    CubeMap,
+    /// A user-provided function.
+    Custom(Arc<dyn Fn(Vector3<Float>) -> (Float, Float) + Send + Sync>),
+}
+
+impl UvMapping {
+    /// Surface coordinates (u, v) for the unit vector `direction`.
+    pub fn map(&self, direction: Vector3<Float>) -> (Float, Float) {
+        match self {
+            UvMapping::Spherical => {
+                let phi = direction.z.atan2(direction.x);
+                let theta = direction.y.clamp(-1., 1.).asin();
+                let u = 1. - (phi + PI) / (2. * PI);
+                let v = (theta + FRAC_PI_2) / PI;
+                (u, v)
+            }
+            UvMapping::CubeMap => {
+                let (x, y, z) = (direction.x, direction.y, direction.z);
+                let (face, s, t, major) = if x.abs() >= y.abs() && x.abs() >= z.abs() {
+                    if x > 0. {
+                        (0, -z, y, x)
+                    } else {
+                        (1, z, y, -x)
+                    }
+                } else if y.abs() >= z.abs() {
+                    if y > 0. {
+                        (2, x, -z, y)
+                    } else {
+                        (3, x, z, -y)
+                    }
+                } else if z > 0. {
+                    (4, x, y, z)
+                } else {
+                    (5, -x, y, -z)
+                };
+                let s = (s / major + 1.) / 2.;
+                let t = (t / major + 1.) / 2.;
+                (
+                    ((face % 3) as Float + s) / 3.,
+                    ((face / 3) as Float + t) / 2.,
+                )
+            }
+            UvMapping::Custom(function) => function(direction),
+        }
+    }
+}
+
+impl Debug for UvMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UvMapping::Spherical => write!(f, "Spherical"),
+            UvMapping::CubeMap => write!(f, "CubeMap"),
+            UvMapping::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
 /// A sphere.
 ///
 /// # Fields
 /// - `center`: Center of the sphere.
 /// - `radius`: Radius of the sphere.
 /// - `material`: Material of the sphere.
+/// - `uv_mapping`: How its surface coordinates are computed.
+/// - `seam_rotation`: Rotation around the y axis that is applied before the [`UvMapping`], if there is one.
+///
+/// # Example
+/// ```
+/// # use std::sync::Arc;
+/// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, ray::Ray, shapes::{Sphere, UvMapping}};
+/// let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+/// let sphere = Sphere::new(vector![0., 0., 0.], 1., material)
+///     .with_uv_mapping(UvMapping::Custom(Arc::new(|direction| (direction.x.abs(), direction.y.abs()))));
+///
+/// let ray = Ray::new(vector![0., 0., 5.], vector![0., 0., -1.]);
+/// let hit = sphere.hit(ray, 0., Float::INFINITY).unwrap();
+/// assert_eq!(hit.uv(), (0., 0.));
+/// ```
 #[derive(Clone, Debug)]
 pub struct Sphere<M: Material> {
     center: Offset,
-    radius: f32,
+    radius: Float,
     material: M,
+    uv_mapping: UvMapping,
+    seam_rotation: Option<Rotation3<Float>>,
 }
 
 impl<M: Material> Sphere<M> {
     /// Create a new stationary [`Sphere`].
-    pub fn new(center: Vector3<f32>, radius: f32, material: M) -> Self {
+    pub fn new(center: Vector3<Float>, radius: Float, material: M) -> Self {
         Self {
             center: Offset::new(center),
             radius,
             material,
+            uv_mapping: UvMapping::default(),
+            seam_rotation: None,
         }
     }
 
-    pub fn position(&self, time: f32) -> Vector3<f32> {
+    /// Consume `self` and set how the surface coordinates are computed.
+    pub fn with_uv_mapping(mut self, uv_mapping: UvMapping) -> Self {
+        self.uv_mapping = uv_mapping;
+        self
+    }
+
+    /// Consume `self` and rotate the texture by `angle` (in radians) around the y axis.
+    ///
+    /// This moves the seam of the [`UvMapping`] (or the edges of the cube map), e.g. to the back of the sphere.
+    pub fn with_seam_angle(mut self, angle: Float) -> Self {
+        self.seam_rotation = Some(Rotation3::from_axis_angle(&Vector3::y_axis(), -angle));
+        self
+    }
+
+    pub fn position(&self, time: Float) -> Vector3<Float> {
         self.center.offset(time)
     }
 
-    pub fn radius(&self) -> f32 {
+    pub fn radius(&self) -> Float {
         self.radius
     }
 
@@ -160,20 +385,136 @@ impl<M: Material> Sphere<M> {
         &self.material
     }
 
-    /// Get the surface coordinates (u, v) on the sphere from a [`Vector3<f32>`].
+    pub fn uv_mapping(&self) -> &UvMapping {
+        &self.uv_mapping
+    }
+
+    /// The [`Cone`] of directions from `origin` towards the sphere at `time`.
+    fn cone(&self, origin: Vector3<Float>, time: Float) -> Option<Cone> {
+        let center = self.center.to_world(Vector3::zeros(), time);
+        Cone::towards(center, self.radius.abs(), origin)
+    }
+
+    /// Tessellate the sphere into a [`Mesh`] whose vertices are moved along the normal by `displacement`.
+    ///
+    /// The sphere is split into 8·2^[`subdivisions`](Displacement::with_subdivisions) rings of latitude with twice as many segments each.
+    /// The texture is evaluated with the surface coordinates of the [`UvMapping`] at the point relative to the center, so image textures can be used.
+    /// The [`Mesh`] is at the position of the sphere at time 0.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, mesh::Displacement, ray::Ray, shapes::Sphere, textures::CheckerTexture};
+    /// let material = Lambertian::solid_color(color![0.8, 0.8, 0.8]);
+    /// let checker = CheckerTexture::solid_colors(color![0., 0., 0.], color![1., 1., 1.]);
+    /// let bumpy = Sphere::new(vector![0., 0., 0.], 1., material).displaced(&Displacement::new(checker, 0.1));
+    ///
+    /// let hit = bumpy.hit(Ray::new(vector![0., 0., 5.], vector![0., 0., -1.]), 0., Float::INFINITY).unwrap();
+    /// assert!(hit.front_face);
+    /// assert!((0.9..1.11).contains(&hit.point.z));
+    /// ```
+    pub fn displaced<T: Texture>(&self, displacement: &Displacement<T>) -> Mesh
+    where
+        M: Clone + 'static,
+    {
+        let rings = 8usize << displacement.subdivisions().min(12);
+        let segments = 2 * rings;
+        let vertex = |direction: Vector3<Float>| {
+            let (u, v) = self.get_surface_coordinates(direction);
+            let height = displacement.height(u, v, self.radius * direction);
+            (self.radius + height) * direction
+        };
+
+        // The poles and then the rings from the top, where φ goes from +x to +z.
+        let mut vertices = vec![vertex(Vector3::y())];
+        for ring in 1..rings {
+            let theta = PI * ring as Float / rings as Float;
+            for segment in 0..segments {
+                let phi = 2. * PI * segment as Float / segments as Float;
+                let direction = vector![
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin()
+                ];
+                vertices.push(vertex(direction));
+            }
+        }
+        vertices.push(vertex(-Vector3::y()));
+
+        let bottom = vertices.len() - 1;
+        let ring_vertex =
+            |ring: usize, segment: usize| 1 + (ring - 1) * segments + segment % segments;
+        let mut faces = Vec::with_capacity(2 * rings * segments);
+        for segment in 0..segments {
+            faces.push([0, ring_vertex(1, segment + 1), ring_vertex(1, segment)]);
+            for ring in 1..rings - 1 {
+                let (upper, upper_next) =
+                    (ring_vertex(ring, segment), ring_vertex(ring, segment + 1));
+                let (lower, lower_next) = (
+                    ring_vertex(ring + 1, segment),
+                    ring_vertex(ring + 1, segment + 1),
+                );
+                faces.push([upper, lower_next, lower]);
+                faces.push([upper, upper_next, lower_next]);
+            }
+            faces.push([
+                bottom,
+                ring_vertex(rings - 1, segment),
+                ring_vertex(rings - 1, segment + 1),
+            ]);
+        }
+
+        Mesh::new(
+            self.position(0.),
+            &vertices,
+            &faces,
+            Shading::Smooth,
+            self.material.clone(),
+        )
+    }
+
+    /// Gradients of `u` and `v` at the unit vector `direction` with the surface coordinates `uv`.
     ///
-    /// The pair (u, v) is defined by the angles in spherical coordinates via u = phi/(2pi), v = theta/pi.
-    fn get_surface_coordinates(&self, point: Vector3<f32>) -> (f32, f32) {
-        let phi = point.z.atan2(point.x);
-        let theta = point.y.asin();
-        let u = 1. - (phi + PI) / (2. * PI);
-        let v = (theta + FRAC_PI_2) / PI;
-        (u, v)
+    /// The [`UvMapping`] can be arbitrary, so they are estimated by finite differences along two tangents. Jumps at the seam are wrapped around.
+    fn uv_gradient(
+        &self,
+        direction: Vector3<Float>,
+        (u, v): (Float, Float),
+    ) -> Option<[Vector3<Float>; 2]> {
+        const STEP: Float = 1e-3;
+        let tangent = vector![direction.z, 0., -direction.x];
+        if tangent.norm_squared() < STEP * STEP {
+            // Close to the poles
+            return None;
+        }
+        let tangent = tangent.normalize();
+        let bitangent = direction.cross(&tangent);
+
+        let wrap = |difference: Float| difference - difference.round();
+        let derivative = |axis: Vector3<Float>| {
+            let (u_step, v_step) =
+                self.get_surface_coordinates((direction + STEP * axis).normalize());
+            let length = STEP * self.radius.abs();
+            (wrap(u_step - u) / length, wrap(v_step - v) / length)
+        };
+        let (du_tangent, dv_tangent) = derivative(tangent);
+        let (du_bitangent, dv_bitangent) = derivative(bitangent);
+        Some([
+            du_tangent * tangent + du_bitangent * bitangent,
+            dv_tangent * tangent + dv_bitangent * bitangent,
+        ])
+    }
+
+    /// Get the surface coordinates (u, v) on the sphere from a unit [`Vector3<Float>`] via its [`UvMapping`].
+    fn get_surface_coordinates(&self, point: Vector3<Float>) -> (Float, Float) {
+        match self.seam_rotation {
+            Some(rotation) => self.uv_mapping.map(rotation * point),
+            None => self.uv_mapping.map(point),
+        }
     }
 }
 
 impl<M: Material + Clone + 'static> Hittable for Sphere<M> {
-    fn hit_origin(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
         let oc = ray.origin();
         let a = ray.direction().norm_squared();
         let b_halves = oc.dot(&ray.direction());
@@ -196,18 +537,15 @@ impl<M: Material + Clone + 'static> Hittable for Sphere<M> {
         let normal = point / self.radius;
         let (u, v) = self.get_surface_coordinates(normal);
 
-        Some(HitRecord::from_ray(
-            point,
-            u,
-            v,
-            normal,
-            root,
-            self.material(),
-            ray,
-        ))
+        let hit = HitRecord::from_ray(point, u, v, normal, root, self.material(), ray)
+            .with_tangent(vector![normal.z, 0., -normal.x]);
+        Some(match self.uv_gradient(normal, (u, v)) {
+            Some([du, dv]) => hit.with_uv_gradient(du, dv),
+            None => hit,
+        })
     }
 
-    fn bounding_box_origin(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
         Some(Aabb::new(
             -vector![self.radius.abs(), self.radius.abs(), self.radius.abs()],
             vector![self.radius.abs(), self.radius.abs(), self.radius.abs()],
@@ -217,15 +555,55 @@ impl<M: Material + Clone + 'static> Hittable for Sphere<M> {
     fn center(&self) -> &Offset {
         &self.center
     }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        let degenerate = (!self.radius.is_normal()).then_some("sphere with zero or invalid radius");
+        stats.add_primitive(
+            std::mem::size_of_val(self),
+            Some(&self.material),
+            degenerate,
+        );
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn area(&self) -> Float {
+        4. * PI * self.radius.powi(2)
+    }
+
+    fn sample_surface_origin(&self) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        let direction = random_unit_vector_in_unit_sphere();
+        Some((self.radius * direction, direction * self.radius.signum()))
+    }
+
+    /// Uniform in the cone of directions towards the sphere if `origin` is outside of it, otherwise by the area.
+    fn pdf_value(&self, origin: Vector3<Float>, direction: Vector3<Float>, time: Float) -> Float {
+        match self.cone(origin, time) {
+            Some(cone) => cone.pdf(direction),
+            None => surface_pdf_value(self, origin, direction, time),
+        }
+    }
+
+    fn random_direction(&self, origin: Vector3<Float>, time: Float) -> Option<Vector3<Float>> {
+        match self.cone(origin, time) {
+            Some(cone) => Some(cone.sample()),
+            None => {
+                let (point, _) = self.sample_surface(time)?;
+                Some(point - origin)
+            }
+        }
+    }
 }
 
 impl<M: Material + Clone + 'static> Movable for Sphere<M> {
-    fn with_rotation(mut self, rotation: Rotation3<f32>) -> Self {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
         self.center = self.center.with_rotation(rotation);
         self
     }
 
-    fn moving(mut self, offset_end: Vector3<f32>, time_start: f32, time_end: f32) -> Self {
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
         self.center = self.center.moving(offset_end, time_start, time_end);
         self
     }
@@ -243,14 +621,14 @@ impl<M: Material + Clone + 'static> Movable for Sphere<M> {
 #[derive(Clone, Debug)]
 pub struct Cylinder<M: Material> {
     center: Offset,
-    radius: f32,
-    height: f32,
+    radius: Float,
+    height: Float,
     material: M,
 }
 
 impl<M: Material> Cylinder<M> {
     /// Create a new stationary [`Cylinder`].
-    pub fn new(center: Vector3<f32>, radius: f32, height: f32, material: M) -> Self {
+    pub fn new(center: Vector3<Float>, radius: Float, height: Float, material: M) -> Self {
         Self {
             center: Offset::new(center),
             radius,
@@ -259,7 +637,7 @@ impl<M: Material> Cylinder<M> {
         }
     }
 
-    pub fn moving(self, position_end: Vector3<f32>, time_start: f32, time_end: f32) -> Self {
+    pub fn moving(self, position_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
         let center = self.center.moving(position_end, time_start, time_end);
         Self {
             center,
@@ -269,15 +647,15 @@ impl<M: Material> Cylinder<M> {
         }
     }
 
-    pub fn position(&self, time: f32) -> Vector3<f32> {
+    pub fn position(&self, time: Float) -> Vector3<Float> {
         self.center.offset(time)
     }
 
-    pub fn radius(&self) -> f32 {
+    pub fn radius(&self) -> Float {
         self.radius
     }
 
-    pub fn height(&self) -> f32 {
+    pub fn height(&self) -> Float {
         self.height
     }
 
@@ -287,7 +665,7 @@ impl<M: Material> Cylinder<M> {
 }
 
 impl<M: Material + Clone + 'static> Hittable for Cylinder<M> {
-    fn hit_origin(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
         let oc = vector![ray.origin().x, 0., ray.origin().z];
         let a = ray.direction().x.powi(2) + ray.direction().z.powi(2);
         let b_halves = oc.dot(&ray.direction());
@@ -310,8 +688,8 @@ impl<M: Material + Clone + 'static> Hittable for Cylinder<M> {
         let upper_bound = self.height / 2.;
         let lower_bound = -self.height / 2.;
 
-        let mut point: Vector3<f32>;
-        let mut root: f32;
+        let mut point: Vector3<Float>;
+        let mut root: Float;
 
         if point1.y > upper_bound {
             if point2.y > upper_bound {
@@ -367,7 +745,7 @@ impl<M: Material + Clone + 'static> Hittable for Cylinder<M> {
         ))
     }
 
-    fn bounding_box_origin(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
         Some(Aabb::new(
             -vector![self.radius.abs(), self.height.abs() / 2., self.radius.abs()],
             vector![self.radius.abs(), self.height.abs() / 2., self.radius.abs()],
@@ -377,15 +755,29 @@ impl<M: Material + Clone + 'static> Hittable for Cylinder<M> {
     fn center(&self) -> &Offset {
         &self.center
     }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        let degenerate = (!self.radius.is_normal() || !self.height.is_normal())
+            .then_some("cylinder with zero or invalid radius or height");
+        stats.add_primitive(
+            std::mem::size_of_val(self),
+            Some(&self.material),
+            degenerate,
+        );
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
 }
 
 impl<M: Material + Clone + 'static> Movable for Cylinder<M> {
-    fn with_rotation(mut self, rotation: Rotation3<f32>) -> Self {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
         self.center = self.center.with_rotation(rotation);
         self
     }
 
-    fn moving(mut self, offset_end: Vector3<f32>, time_start: f32, time_end: f32) -> Self {
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
         self.center = self.center.moving(offset_end, time_start, time_end);
         self
     }
@@ -422,17 +814,17 @@ impl Plane {
 pub struct Rectangle<M: Material> {
     orientation: Plane,
     center: Offset,
-    width: f32,
-    height: f32,
+    width: Float,
+    height: Float,
     material: M,
 }
 
 impl<M: Material> Rectangle<M> {
     pub fn new(
         orientation: Plane,
-        center: Vector3<f32>,
-        width: f32,
-        height: f32,
+        center: Vector3<Float>,
+        width: Float,
+        height: Float,
         material: M,
     ) -> Self {
         let center = Offset::new(center);
@@ -445,7 +837,7 @@ impl<M: Material> Rectangle<M> {
         }
     }
 
-    pub fn xy(center: Vector3<f32>, width: f32, height: f32, material: M) -> Self {
+    pub fn xy(center: Vector3<Float>, width: Float, height: Float, material: M) -> Self {
         let orientation = Plane::XY;
         let center = Offset::new(center);
         Self {
@@ -457,7 +849,7 @@ impl<M: Material> Rectangle<M> {
         }
     }
 
-    pub fn yz(center: Vector3<f32>, width: f32, height: f32, material: M) -> Self {
+    pub fn yz(center: Vector3<Float>, width: Float, height: Float, material: M) -> Self {
         let orientation = Plane::YZ;
         let center = Offset::new(center);
         Self {
@@ -469,7 +861,7 @@ impl<M: Material> Rectangle<M> {
         }
     }
 
-    pub fn xz(center: Vector3<f32>, width: f32, height: f32, material: M) -> Self {
+    pub fn xz(center: Vector3<Float>, width: Float, height: Float, material: M) -> Self {
         let orientation = Plane::XZ;
         let center = Offset::new(center);
         Self {
@@ -481,7 +873,7 @@ impl<M: Material> Rectangle<M> {
         }
     }
 
-    pub fn moving(self, position_end: Vector3<f32>, time_start: f32, time_end: f32) -> Self {
+    pub fn moving(self, position_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
         let center = self.center.moving(position_end, time_start, time_end);
         Self {
             orientation: self.orientation,
@@ -492,15 +884,15 @@ impl<M: Material> Rectangle<M> {
         }
     }
 
-    pub fn position(&self, time: f32) -> Vector3<f32> {
+    pub fn position(&self, time: Float) -> Vector3<Float> {
         self.center.offset(time)
     }
 
-    pub fn width(&self) -> f32 {
+    pub fn width(&self) -> Float {
         self.width
     }
 
-    pub fn height(&self) -> f32 {
+    pub fn height(&self) -> Float {
         self.height
     }
 
@@ -510,7 +902,7 @@ impl<M: Material> Rectangle<M> {
 }
 
 impl<M: Material + Clone + 'static> Hittable for Rectangle<M> {
-    fn hit_origin(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
         let (a_index, b_index, c_index) = self.orientation.axes();
         let a_min = -self.width / 2.;
         let a_max = self.width / 2.;
@@ -533,36 +925,247 @@ impl<M: Material + Clone + 'static> Hittable for Rectangle<M> {
         let v = (b - b_min) / (b_max - b_min);
         let mut normal = vector![0., 0., 0.];
         normal[c_index] = 1.;
-
-        Some(HitRecord::from_ray(
-            point,
-            u,
-            v,
-            normal,
-            t,
-            &self.material,
-            ray,
-        ))
+        let mut tangent = vector![0., 0., 0.];
+        tangent[a_index] = 1.;
+        let mut bitangent = vector![0., 0., 0.];
+        bitangent[b_index] = 1.;
+
+        Some(
+            HitRecord::from_ray(point, u, v, normal, t, &self.material, ray)
+                .with_tangent(tangent)
+                .with_uv_gradient(tangent / self.width, bitangent / self.height),
+        )
     }
 
-    fn bounding_box_origin(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
-        let minimum = -vector![self.width / 2., self.height / 2., 0.0001];
-        let maximum = vector![self.width / 2., self.height / 2., 0.0001];
-        Some(Aabb::new(minimum, maximum))
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
+        let (a_index, b_index, c_index) = self.orientation.axes();
+        let mut maximum = Vector3::zeros();
+        maximum[a_index] = self.width.abs() / 2.;
+        maximum[b_index] = self.height.abs() / 2.;
+        maximum[c_index] = 0.0001;
+        Some(Aabb::new(-maximum, maximum))
     }
 
     fn center(&self) -> &Offset {
         &self.center
     }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        let degenerate = (!self.width.is_normal() || !self.height.is_normal())
+            .then_some("rectangle with zero or invalid size");
+        stats.add_primitive(
+            std::mem::size_of_val(self),
+            Some(&self.material),
+            degenerate,
+        );
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn area(&self) -> Float {
+        (self.width * self.height).abs()
+    }
+
+    fn sample_surface_origin(&self) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        let mut rng = crate::random::rng();
+        let (a_index, b_index, c_index) = self.orientation.axes();
+        let mut point = Vector3::zeros();
+        point[a_index] = self.width * (rng.gen::<Float>() - 0.5);
+        point[b_index] = self.height * (rng.gen::<Float>() - 0.5);
+        let mut normal = Vector3::zeros();
+        normal[c_index] = 1.;
+        Some((point, normal))
+    }
 }
 
 impl<M: Material + Clone + 'static> Movable for Rectangle<M> {
-    fn with_rotation(mut self, rotation: Rotation3<f32>) -> Self {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+/// A flat disk along one of the axis-aligned planes.
+///
+/// As an area light, it is sampled uniformly in the cone of directions towards its bounding sphere where it is seen from the front or back, and by its area where it is seen edge-on or from very close.
+///
+/// # Fields
+/// - `orientation`: Along which [`Plane`] it should be oriented.
+/// - `center`: Its center.
+/// - `radius`: Its radius.
+/// - `material`: Its material.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::DiffuseLight, ray::Ray, shapes::{Disk, Plane}};
+/// let light = DiffuseLight::solid_color(color![4., 4., 4.]);
+/// let disk = Disk::new(Plane::XZ, vector![0., 2., 0.], 1., light);
+///
+/// let ray = Ray::new(vector![0.5, 0., 0.5], vector![0., 1., 0.]);
+/// let hit = disk.hit(ray, 0., Float::INFINITY).unwrap();
+/// assert!((hit.t - 2.).abs() < 1e-5);
+/// assert!(disk.hit(Ray::new(vector![1., 0., 1.], vector![0., 1., 0.]), 0., Float::INFINITY).is_none());
+///
+/// // Every sampled direction has the density of the cone towards the bounding sphere.
+/// let origin = vector![0., 0., 0.];
+/// let direction = disk.random_direction(origin, 0.).unwrap();
+/// let solid_angle = 2. * float::consts::PI * (1. - (3. as Float / 4.).sqrt());
+/// assert!((disk.pdf_value(origin, direction, 0.) * solid_angle - 1.).abs() < 1e-3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Disk<M: Material> {
+    orientation: Plane,
+    center: Offset,
+    radius: Float,
+    material: M,
+}
+
+impl<M: Material> Disk<M> {
+    pub fn new(orientation: Plane, center: Vector3<Float>, radius: Float, material: M) -> Self {
+        Self {
+            orientation,
+            center: Offset::new(center),
+            radius,
+            material,
+        }
+    }
+
+    pub fn position(&self, time: Float) -> Vector3<Float> {
+        self.center.offset(time)
+    }
+
+    pub fn radius(&self) -> Float {
+        self.radius
+    }
+
+    pub fn material(&self) -> &M {
+        &self.material
+    }
+
+    /// Unit normal in world coordinates.
+    fn normal(&self) -> Vector3<Float> {
+        let mut normal = Vector3::zeros();
+        normal[self.orientation.axes().2] = 1.;
+        self.center.normal_to_world(normal)
+    }
+
+    /// The [`Cone`] of directions from `origin` towards the bounding sphere at `time`.
+    ///
+    /// Seen at a grazing angle, most directions of the cone would miss the disk, so there is none if the angle to the normal is above 60°.
+    fn cone(&self, origin: Vector3<Float>, time: Float) -> Option<Cone> {
+        let center = self.center.to_world(Vector3::zeros(), time);
+        let cone = Cone::towards(center, self.radius.abs(), origin)?;
+        (cone.axis.dot(&self.normal()).abs() >= 0.5).then_some(cone)
+    }
+}
+
+impl<M: Material + Clone + 'static> Hittable for Disk<M> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let (a_index, b_index, c_index) = self.orientation.axes();
+        let t = -ray.origin()[c_index] / ray.direction()[c_index];
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let point = ray.at(t);
+        let (a, b) = (point[a_index], point[b_index]);
+        if a * a + b * b > self.radius * self.radius {
+            return None;
+        }
+
+        let diameter = 2. * self.radius.abs();
+        let u = a / diameter + 0.5;
+        let v = b / diameter + 0.5;
+        let mut normal = vector![0., 0., 0.];
+        normal[c_index] = 1.;
+        let mut tangent = vector![0., 0., 0.];
+        tangent[a_index] = 1.;
+        let mut bitangent = vector![0., 0., 0.];
+        bitangent[b_index] = 1.;
+
+        Some(
+            HitRecord::from_ray(point, u, v, normal, t, &self.material, ray)
+                .with_tangent(tangent)
+                .with_uv_gradient(tangent / diameter, bitangent / diameter),
+        )
+    }
+
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
+        let (a_index, b_index, c_index) = self.orientation.axes();
+        let mut maximum = Vector3::zeros();
+        maximum[a_index] = self.radius.abs();
+        maximum[b_index] = self.radius.abs();
+        maximum[c_index] = 0.0001;
+        Some(Aabb::new(-maximum, maximum))
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        let degenerate = (!self.radius.is_normal()).then_some("disk with zero or invalid radius");
+        stats.add_primitive(
+            std::mem::size_of_val(self),
+            Some(&self.material),
+            degenerate,
+        );
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn area(&self) -> Float {
+        PI * self.radius * self.radius
+    }
+
+    fn sample_surface_origin(&self) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        let mut rng = crate::random::rng();
+        let (a_index, b_index, c_index) = self.orientation.axes();
+        let distance = self.radius.abs() * rng.gen::<Float>().sqrt();
+        let angle = 2. * PI * rng.gen::<Float>();
+        let mut point = Vector3::zeros();
+        point[a_index] = distance * angle.cos();
+        point[b_index] = distance * angle.sin();
+        let mut normal = Vector3::zeros();
+        normal[c_index] = 1.;
+        Some((point, normal))
+    }
+
+    /// Uniform in the [`Cone`] towards the bounding sphere, including the directions that miss the disk, or by the area if there is none.
+    fn pdf_value(&self, origin: Vector3<Float>, direction: Vector3<Float>, time: Float) -> Float {
+        match self.cone(origin, time) {
+            Some(cone) => cone.pdf(direction),
+            None => surface_pdf_value(self, origin, direction, time),
+        }
+    }
+
+    fn random_direction(&self, origin: Vector3<Float>, time: Float) -> Option<Vector3<Float>> {
+        match self.cone(origin, time) {
+            Some(cone) => Some(cone.sample()),
+            None => {
+                let (point, _) = self.sample_surface(time)?;
+                Some(point - origin)
+            }
+        }
+    }
+}
+
+impl<M: Material + Clone + 'static> Movable for Disk<M> {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
         self.center = self.center.with_rotation(rotation);
         self
     }
 
-    fn moving(mut self, offset_end: Vector3<f32>, time_start: f32, time_end: f32) -> Self {
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
         self.center = self.center.moving(offset_end, time_start, time_end);
         self
     }
@@ -579,15 +1182,21 @@ impl<M: Material + Clone + 'static> Movable for Rectangle<M> {
 #[derive(Clone, Debug)]
 pub struct Cuboid<M: Material> {
     center: Offset,
-    width: f32,
-    height: f32,
-    depth: f32,
+    width: Float,
+    height: Float,
+    depth: Float,
     rectangles: HittableList,
     material: M,
 }
 
 impl<M: Material + Clone + 'static> Cuboid<M> {
-    pub fn new(center: Vector3<f32>, width: f32, height: f32, depth: f32, material: M) -> Self {
+    pub fn new(
+        center: Vector3<Float>,
+        width: Float,
+        height: Float,
+        depth: Float,
+        material: M,
+    ) -> Self {
         let mut rectangles = HittableList::default();
 
         let bottom = Rectangle::xz(
@@ -630,7 +1239,7 @@ impl<M: Material + Clone + 'static> Cuboid<M> {
         }
     }
 
-    pub fn position(&self, time: f32) -> Vector3<f32> {
+    pub fn position(&self, time: Float) -> Vector3<Float> {
         self.center.offset(time)
     }
 
@@ -640,11 +1249,11 @@ impl<M: Material + Clone + 'static> Cuboid<M> {
 }
 
 impl<M: Material + Clone + 'static> Hittable for Cuboid<M> {
-    fn hit_origin(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
         self.rectangles.hit(ray, t_min, t_max)
     }
 
-    fn bounding_box_origin(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
         Some(Aabb::new(
             -vector![self.width / 2., self.height / 2., self.depth / 2.],
             vector![self.width / 2., self.height / 2., self.depth / 2.],
@@ -654,15 +1263,235 @@ impl<M: Material + Clone + 'static> Hittable for Cuboid<M> {
     fn center(&self) -> &Offset {
         &self.center
     }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        self.rectangles.collect_stats(stats);
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
 }
 
 impl<M: Material + Clone + 'static> Movable for Cuboid<M> {
-    fn with_rotation(mut self, rotation: Rotation3<f32>) -> Self {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+/// A flat triangle.
+///
+/// The vertices are given relative to its [`Offset`], which is the origin for a new [`Triangle`].
+/// The surface coordinates (u, v) are the barycentric coordinates of the hit point with respect to the second and third vertex.
+///
+/// # Fields
+/// - `center`: Its [`Offset`].
+/// - `vertices`: Its three vertices. Their order defines the front face (counterclockwise).
+/// - `normals`: Normals at the vertices that are interpolated for smooth shading, if there are any.
+/// - `uvs`: Texture coordinates at the vertices, if there are any. Otherwise, (u, v) are the barycentric coordinates of the second and third vertex.
+/// - `material`: Its material.
+#[derive(Clone, Debug)]
+pub struct Triangle<M: Material> {
+    center: Offset,
+    vertices: [Vector3<Float>; 3],
+    normals: Option<[Vector3<Float>; 3]>,
+    uvs: Option<[(Float, Float); 3]>,
+    material: M,
+}
+
+impl<M: Material> Triangle<M> {
+    pub fn new(a: Vector3<Float>, b: Vector3<Float>, c: Vector3<Float>, material: M) -> Self {
+        Self {
+            center: Offset::default(),
+            vertices: [a, b, c],
+            normals: None,
+            uvs: None,
+            material,
+        }
+    }
+
+    /// Consume `self` and set the normals at the vertices.
+    ///
+    /// The shading normal of a hit is interpolated between them, so a mesh of [`Triangle`]s looks like a curved surface.
+    pub fn with_normals(mut self, normals: [Vector3<Float>; 3]) -> Self {
+        self.normals = Some(normals.map(|normal| normal.normalize()));
+        self
+    }
+
+    /// Consume `self` and set the texture coordinates at the vertices, which are interpolated for the hits.
+    ///
+    /// # Example
+    /// ```
+    /// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, ray::Ray, shapes::Triangle};
+    /// let triangle = Triangle::new(vector![0., 0., 0.], vector![2., 0., 0.], vector![0., 2., 0.], Lambertian::solid_color(color![0.5, 0.5, 0.5]))
+    ///     .with_uvs([(0.5, 0.5), (1., 0.5), (0.5, 1.)]);
+    /// let hit = triangle.hit(Ray::new(vector![1., 0.5, 1.], vector![0., 0., -1.]), 0., Float::INFINITY).unwrap();
+    /// assert!((hit.u - 0.75).abs() < 1e-5 && (hit.v - 0.625).abs() < 1e-5);
+    /// ```
+    pub fn with_uvs(mut self, uvs: [(Float, Float); 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    pub fn vertices(&self) -> &[Vector3<Float>; 3] {
+        &self.vertices
+    }
+
+    pub fn normals(&self) -> Option<&[Vector3<Float>; 3]> {
+        self.normals.as_ref()
+    }
+
+    pub fn uvs(&self) -> Option<&[(Float, Float); 3]> {
+        self.uvs.as_ref()
+    }
+
+    pub fn material(&self) -> &M {
+        &self.material
+    }
+
+    /// Unnormalized normal of the front face.
+    fn face_normal(&self) -> Vector3<Float> {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(&(c - a))
+    }
+}
+
+impl<M: Material + Clone + 'static> Hittable for Triangle<M> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        // Möller–Trumbore intersection
+        let [a, b, c] = self.vertices;
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = ray.direction().cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < 1e-8 {
+            return None;
+        }
+        let inverse_determinant = 1. / determinant;
+
+        let s = ray.origin() - a;
+        let u = s.dot(&p) * inverse_determinant;
+        if !(0. ..=1.).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let v = ray.direction().dot(&q) * inverse_determinant;
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+
+        let t = edge2.dot(&q) * inverse_determinant;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let normal = self.face_normal();
+        let area_squared = normal.norm_squared();
+        // Gradients of the barycentric coordinates of `b` and `c` along the surface
+        let gradient_b = edge2.cross(&normal) / area_squared;
+        let gradient_c = normal.cross(&edge1) / area_squared;
+        let point = ray.at(t);
+        let hit = match self.uvs {
+            Some([(ua, va), (ub, vb), (uc, vc)]) => {
+                let gradient_u = (ub - ua) * gradient_b + (uc - ua) * gradient_c;
+                let gradient_v = (vb - va) * gradient_b + (vc - va) * gradient_c;
+                let (tex_u, tex_v) = (
+                    (1. - u - v) * ua + u * ub + v * uc,
+                    (1. - u - v) * va + u * vb + v * vc,
+                );
+                HitRecord::from_ray(
+                    point,
+                    tex_u,
+                    tex_v,
+                    normal.normalize(),
+                    t,
+                    &self.material,
+                    ray,
+                )
+                .with_tangent(if near_zero(&gradient_u) {
+                    edge1
+                } else {
+                    gradient_u
+                })
+                .with_uv_gradient(gradient_u, gradient_v)
+            }
+            // The surface coordinates are the barycentric coordinates of `b` and `c`.
+            None => HitRecord::from_ray(point, u, v, normal.normalize(), t, &self.material, ray)
+                .with_tangent(edge1)
+                .with_uv_gradient(gradient_b, gradient_c),
+        };
+
+        Some(match self.normals {
+            Some([na, nb, nc]) => hit.with_shading_normal((1. - u - v) * na + u * nb + v * nc),
+            None => hit,
+        })
+    }
+
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
+        let [a, b, c] = self.vertices;
+        let padding = vector![0.0001, 0.0001, 0.0001];
+        Some(Aabb::new(
+            a.inf(&b).inf(&c) - padding,
+            a.sup(&b).sup(&c) + padding,
+        ))
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+
+    fn collect_stats(&self, stats: &mut SceneStats) {
+        let degenerate = if self
+            .vertices
+            .iter()
+            .any(|vertex| !vertex.iter().all(|x| x.is_finite()))
+        {
+            Some("triangle with invalid vertices")
+        } else if self.face_normal().norm_squared() == 0. {
+            Some("triangle with zero area")
+        } else {
+            None
+        };
+        stats.add_primitive(
+            std::mem::size_of_val(self),
+            Some(&self.material),
+            degenerate,
+        );
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn area(&self) -> Float {
+        self.face_normal().norm() / 2.
+    }
+
+    fn sample_surface_origin(&self) -> Option<(Vector3<Float>, Vector3<Float>)> {
+        let mut rng = crate::random::rng();
+        let [a, b, c] = self.vertices;
+        let r1 = rng.gen::<Float>().sqrt();
+        let r2 = rng.gen::<Float>();
+        let point = (1. - r1) * a + r1 * (1. - r2) * b + r1 * r2 * c;
+        Some((point, self.face_normal().normalize()))
+    }
+}
+
+impl<M: Material + Clone + 'static> Movable for Triangle<M> {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
         self.center = self.center.with_rotation(rotation);
         self
     }
 
-    fn moving(mut self, offset_end: Vector3<f32>, time_start: f32, time_end: f32) -> Self {
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
         self.center = self.center.moving(offset_end, time_start, time_end);
         self
     }
@@ -673,11 +1502,11 @@ impl<M: Material + Clone + 'static> Movable for Cuboid<M> {
 pub struct ConstantMedium<H: Hittable, T: Texture> {
     boundary: H,
     phase_function: Isotropic<T>,
-    negative_inverse_density: f32,
+    negative_inverse_density: Float,
 }
 
 impl<H: Hittable, T: Texture> ConstantMedium<H, T> {
-    pub fn new(boundary: H, texture: T, density: f32) -> Self {
+    pub fn new(boundary: H, texture: T, density: Float) -> Self {
         Self {
             boundary,
             phase_function: Isotropic::new(texture),
@@ -687,7 +1516,7 @@ impl<H: Hittable, T: Texture> ConstantMedium<H, T> {
 }
 
 impl<H: Hittable> ConstantMedium<H, SolidColor> {
-    pub fn solid_color(boundary: H, color: Color, density: f32) -> Self {
+    pub fn solid_color(boundary: H, color: Color, density: Float) -> Self {
         Self {
             boundary,
             phase_function: Isotropic::solid_color(color),
@@ -701,11 +1530,11 @@ where
     H: Hittable + Clone + 'static,
     T: Texture + Clone + 'static,
 {
-    fn hit_origin(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let mut rng = rand::thread_rng();
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let mut rng = crate::random::rng();
 
-        let mut hit1 = self.boundary.hit(ray, -f32::INFINITY, f32::INFINITY)?;
-        let mut hit2 = self.boundary.hit(ray, hit1.t + 0.0001, f32::INFINITY)?;
+        let mut hit1 = self.boundary.hit(ray, -Float::INFINITY, Float::INFINITY)?;
+        let mut hit2 = self.boundary.hit(ray, hit1.t + 0.0001, Float::INFINITY)?;
 
         if hit1.t < t_min {
             hit1.t = t_min
@@ -724,7 +1553,7 @@ where
 
         let ray_length = ray.direction().norm();
         let distance_inside_boundary = (hit2.t - hit1.t) * ray_length;
-        let hit_distance = self.negative_inverse_density * rng.gen::<f32>().ln();
+        let hit_distance = self.negative_inverse_density * rng.gen::<Float>().ln();
 
         if hit_distance > distance_inside_boundary {
             return None;
@@ -743,7 +1572,7 @@ where
         ))
     }
 
-    fn bounding_box_origin(&self, time0: f32, time1: f32) -> Option<Aabb> {
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
         self.boundary.bounding_box_origin(time0, time1)
     }
 
@@ -0,0 +1,227 @@
+//! Statistics and validation of a scene before rendering it and statistics of a render.
+//!
+//! Large generated scenes can render black without any error, e.g. because a sphere has a radius of zero or an albedo greater than one lets the energy explode.
+//! [`Raytracer::stats`] walks through the `world` and reports such [`Issue`]s together with some numbers about the scene.
+//! After rendering, [`RenderStats`] tell where the time was spent.
+
+use std::fmt;
+use std::ops::AddAssign;
+use std::time::Duration;
+
+use crate::materials::Material;
+use crate::*;
+
+/// Something in a scene that is probably a mistake.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+    /// The `world` does not contain any objects.
+    EmptyWorld,
+    /// The image is too small to be rendered (it needs at least two pixels in each direction).
+    ImageTooSmall { width: u32, height: u32 },
+    /// No samples are taken per pixel.
+    NoSamples,
+    /// Nothing emits light and the background is black, so the image will be black.
+    NoLight,
+    /// The object with index `object` in the `world` contains a primitive that cannot be hit (e.g. a sphere with radius zero or a triangle with NaN vertices).
+    DegeneratePrimitive { object: usize, reason: &'static str },
+    /// The object with index `object` in the `world` has a material that reflects more light than it receives or has a negative albedo.
+    AlbedoOutOfRange { object: usize, albedo: Color },
+    /// The object with index `object` in the `world` has no bounding box, so no [`Bvh`](crate::hittable::Bvh) can be built.
+    Unbounded { object: usize },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::EmptyWorld => write!(f, "the world is empty"),
+            Issue::ImageTooSmall { width, height } => {
+                write!(f, "the image size {width}x{height} is too small")
+            }
+            Issue::NoSamples => write!(f, "no samples are taken per pixel"),
+            Issue::NoLight => write!(f, "nothing emits light and the background is black"),
+            Issue::DegeneratePrimitive { object, reason } => {
+                write!(f, "object {object} is degenerate: {reason}")
+            }
+            Issue::AlbedoOutOfRange { object, albedo } => write!(
+                f,
+                "object {object} has an albedo of ({}, {}, {}) outside of [0, 1]",
+                albedo.r(),
+                albedo.g(),
+                albedo.b()
+            ),
+            Issue::Unbounded { object } => write!(f, "object {object} has no bounding box"),
+        }
+    }
+}
+
+/// Statistics of a scene, see [`Raytracer::stats`].
+///
+/// # Fields
+/// - `objects`: Number of objects pushed to the `world`.
+/// - `primitives`: Number of primitives (e.g. spheres and triangles) inside all objects.
+/// - `emissive`: Number of emissive primitives.
+/// - `estimated_memory`: Rough estimate of the memory used by the objects in bytes.
+/// - `issues`: Every [`Issue`] found.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SceneStats {
+    pub objects: usize,
+    pub primitives: usize,
+    pub emissive: usize,
+    pub estimated_memory: usize,
+    pub issues: Vec<Issue>,
+    object: usize,
+}
+
+impl SceneStats {
+    /// Whether no [`Issue`]s were found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Collect the statistics of `hittable`, which is the object with index `object` in the `world`.
+    pub(crate) fn collect_object(&mut self, object: usize, hittable: &dyn Hittable) {
+        self.object = object;
+        self.objects += 1;
+        if hittable.bounding_box(0., 0.).is_none() {
+            self.issues.push(Issue::Unbounded { object });
+        }
+        hittable.collect_stats(self);
+    }
+
+    /// Count a primitive with a [`Material`] and check both.
+    ///
+    /// `degenerate` describes why the primitive is degenerate, if it is.
+    pub fn add_primitive(
+        &mut self,
+        size: usize,
+        material: Option<&dyn Material>,
+        degenerate: Option<&'static str>,
+    ) {
+        self.primitives += 1;
+        self.add_memory(size);
+        if let Some(reason) = degenerate {
+            self.report(Issue::DegeneratePrimitive {
+                object: self.object,
+                reason,
+            });
+        }
+
+        let Some(material) = material else {
+            return;
+        };
+        if material.is_emissive() {
+            self.emissive += 1;
+        }
+        if let Some(albedo) = material.albedo() {
+            if albedo.into_iter().any(|c| !(0. ..=1.).contains(&c)) {
+                self.report(Issue::AlbedoOutOfRange {
+                    object: self.object,
+                    albedo,
+                });
+            }
+        }
+    }
+
+    /// Add memory that is not part of a primitive (e.g. of a container).
+    pub fn add_memory(&mut self, size: usize) {
+        self.estimated_memory += size;
+    }
+
+    /// Add an [`Issue`], but only once per object and not for each of its triangles.
+    pub(crate) fn report(&mut self, issue: Issue) {
+        if !self.issues.contains(&issue) {
+            self.issues.push(issue);
+        }
+    }
+}
+
+/// Timings and counts of a render, see [`RaytracedImage::render_stats`](crate::raytracer::RaytracedImage::render_stats).
+///
+/// The intersection and shading stages are only timed separately by the path tracer (as it renders in [waves](crate::integrator::Integrator::PathTracer)); for the other integrators, everything counts as `shading`.
+/// Only the [`Ray`](crate::ray::Ray)s following the paths are counted, not the shadow [`Ray`]s of sampling the lights.
+///
+/// # Fields
+/// - `bvh_build`: Time spent building the [`Bvh`](crate::hittable::Bvh).
+/// - `bvh_depth`: Depth of the [`Bvh`](crate::hittable::Bvh) of the `world`, if the render built one.
+/// - `bvh_nodes`: Number of nodes of the [`Bvh`](crate::hittable::Bvh) of the `world`, if the render built one.
+/// - `traversal`: Time spent intersecting [`Ray`](crate::ray::Ray)s with the world.
+/// - `shading`: Time spent shading the hits and scattering.
+/// - `total`: Time of the whole render.
+/// - `rays`: Number of [`Ray`](crate::ray::Ray)s intersected with the world.
+/// - `paths`: Number of camera samples.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, scenes::{self, SceneSettings}};
+/// let image = scenes::cornell_box(&SceneSettings::new(16, 16).with_samples(2, 4)).render().unwrap();
+/// let stats = image.render_stats();
+/// assert_eq!(stats.paths, 16 * 16 * 2);
+/// assert!(stats.rays >= stats.paths);
+/// assert!(stats.bvh_depth.is_some_and(|depth| depth > 1));
+/// println!("{stats}");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    pub bvh_build: Duration,
+    pub bvh_depth: Option<usize>,
+    pub bvh_nodes: Option<usize>,
+    pub traversal: Duration,
+    pub shading: Duration,
+    pub total: Duration,
+    pub rays: u64,
+    pub paths: u64,
+}
+
+impl RenderStats {
+    /// Rays intersected per second of the whole render.
+    pub fn rays_per_second(&self) -> f64 {
+        let seconds = self.total.as_secs_f64();
+        if seconds > 0. {
+            self.rays as f64 / seconds
+        } else {
+            0.
+        }
+    }
+
+    /// Average number of [`Ray`](crate::ray::Ray)s per path, i.e. the number of bounces plus one.
+    pub fn average_depth(&self) -> f64 {
+        if self.paths > 0 {
+            self.rays as f64 / self.paths as f64
+        } else {
+            0.
+        }
+    }
+}
+
+/// Combine the [`RenderStats`] of several renders, e.g. of the two eyes of a stereo image.
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, other: Self) {
+        self.bvh_build += other.bvh_build;
+        self.bvh_depth = self.bvh_depth.max(other.bvh_depth);
+        self.bvh_nodes = self.bvh_nodes.max(other.bvh_nodes);
+        self.traversal += other.traversal;
+        self.shading += other.shading;
+        self.total += other.total;
+        self.rays += other.rays;
+        self.paths += other.paths;
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "BVH build: {:>10.3?}", self.bvh_build)?;
+        if let (Some(depth), Some(nodes)) = (self.bvh_depth, self.bvh_nodes) {
+            writeln!(f, "BVH:       depth {depth}, {nodes} nodes")?;
+        }
+        writeln!(f, "traversal: {:>10.3?}", self.traversal)?;
+        writeln!(f, "shading:   {:>10.3?}", self.shading)?;
+        writeln!(f, "total:     {:>10.3?}", self.total)?;
+        writeln!(
+            f,
+            "{} rays ({:.2} Mrays/s), average depth {:.2}",
+            self.rays,
+            self.rays_per_second() / 1e6,
+            self.average_depth()
+        )
+    }
+}
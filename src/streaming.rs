@@ -0,0 +1,236 @@
+//! Writing images band by band, for renders that are too large to keep the whole image in memory.
+//!
+//! [`Raytracer::render_bands`](crate::Raytracer::render_bands) renders a few rows at a time, and a [`BandWriter`] appends them to a PNG or PPM file as they are done.
+//! Only the colors of one band and the encoder state are kept in memory, so e.g. a 16k × 16k poster needs a fraction of the memory of [`render`](crate::Raytracer::render).
+//!
+//! The bands are quantized to 8 bits like [`RaytracedImage::into_image`], with their gamma, exposure, and [`Dithering`](crate::dithering::Dithering); the alpha channel is dropped.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use image::error::EncodingError;
+use image::{ImageError, ImageFormat};
+
+use crate::error::Error;
+use crate::raytracer::RaytracedImage;
+
+/// File formats that a [`BandWriter`] can write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// PNG with 8 bits per channel, compressed row by row.
+    Png,
+    /// ASCII Portable Pixmap like [`PPM`](crate::ppm::PPM).
+    Ppm,
+}
+
+impl StreamFormat {
+    /// The format with the extension of `path`, if it is supported.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(StreamFormat::Png),
+            "ppm" => Some(StreamFormat::Ppm),
+            _ => None,
+        }
+    }
+}
+
+/// Encoder of a [`BandWriter`].
+enum Encoder<W: Write + 'static> {
+    Png(Box<png::StreamWriter<'static, W>>),
+    Ppm(W),
+}
+
+/// Writer that appends bands of rows to an image, from the top to the bottom.
+///
+/// # Fields
+/// - `encoder`: Encoder of the [`StreamFormat`] the image is written in.
+/// - `image_width`: Width of the image and every band.
+/// - `image_height`: Height of the whole image.
+/// - `rows`: Number of rows written so far.
+///
+/// # Example
+/// ```no_run
+/// # use ray_tracing_in_one_weekend::{*, streaming::BandWriter};
+/// let raytracer = Raytracer::new(Camera::default(), color![0.5, 0.7, 1.], 16384, 9216, 100, 50);
+/// let mut writer = BandWriter::create("poster.png", 16384, 9216).unwrap();
+/// raytracer.render_bands(64, |band| writer.write_band(&band)).unwrap();
+/// writer.finish().unwrap();
+/// ```
+pub struct BandWriter<W: Write + 'static> {
+    encoder: Encoder<W>,
+    image_width: u32,
+    image_height: u32,
+    rows: u32,
+}
+
+impl BandWriter<BufWriter<File>> {
+    /// Create the file at `path` for an image of `image_width` × `image_height` pixels, in the [`StreamFormat`] of its extension.
+    ///
+    /// Returns [`Error::Config`] if the extension is neither `png` nor `ppm`.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        image_width: u32,
+        image_height: u32,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let format = StreamFormat::from_path(path).ok_or_else(|| {
+            Error::Config(format!("cannot stream an image to {}", path.display()))
+        })?;
+        Self::new(
+            BufWriter::new(File::create(path)?),
+            format,
+            image_width,
+            image_height,
+        )
+    }
+}
+
+impl<W: Write + 'static> BandWriter<W> {
+    /// Write the header of an image of `image_width` × `image_height` pixels in `format` to `writer`.
+    pub fn new(
+        mut writer: W,
+        format: StreamFormat,
+        image_width: u32,
+        image_height: u32,
+    ) -> Result<Self, Error> {
+        let encoder = match format {
+            StreamFormat::Png => {
+                let mut encoder = png::Encoder::new(writer, image_width, image_height);
+                encoder.set_color(png::ColorType::Rgb);
+                encoder.set_depth(png::BitDepth::Eight);
+                let writer = encoder.write_header().map_err(encoding_error)?;
+                Encoder::Png(Box::new(
+                    writer.into_stream_writer().map_err(encoding_error)?,
+                ))
+            }
+            StreamFormat::Ppm => {
+                write!(writer, "P3\n{image_width} {image_height}\n255\n")?;
+                Encoder::Ppm(writer)
+            }
+        };
+        Ok(Self {
+            encoder,
+            image_width,
+            image_height,
+            rows: 0,
+        })
+    }
+
+    /// Append the rows of `band` below the rows written so far.
+    ///
+    /// Returns [`Error::Config`] if `band` is not as wide as the image or has more rows than are left.
+    pub fn write_band(&mut self, band: &RaytracedImage) -> Result<(), Error> {
+        let (width, height) = band.dimensions();
+        if width != self.image_width || self.rows + height > self.image_height {
+            return Err(Error::Config(format!(
+                "band of size {width}x{height} does not fit below row {} of an image of size {}x{}",
+                self.rows, self.image_width, self.image_height
+            )));
+        }
+
+        let colors = band.quantized_at(self.rows).map(<[u8; 3]>::from);
+        match &mut self.encoder {
+            Encoder::Png(writer) => {
+                let samples: Vec<u8> = colors.flatten().collect();
+                writer.write_all(&samples)?;
+            }
+            Encoder::Ppm(writer) => {
+                for [r, g, b] in colors {
+                    writeln!(writer, "{r} {g} {b}")?;
+                }
+            }
+        }
+        self.rows += height;
+        Ok(())
+    }
+
+    /// Number of rows written so far.
+    pub fn rows_written(&self) -> u32 {
+        self.rows
+    }
+
+    /// Finish the image and flush the writer.
+    ///
+    /// Returns [`Error::Config`] if not all rows were written.
+    pub fn finish(self) -> Result<(), Error> {
+        if self.rows != self.image_height {
+            return Err(Error::Config(format!(
+                "only {} of {} rows were written",
+                self.rows, self.image_height
+            )));
+        }
+        match self.encoder {
+            Encoder::Png(writer) => writer.finish().map_err(encoding_error),
+            Encoder::Ppm(mut writer) => Ok(writer.flush()?),
+        }
+    }
+}
+
+/// Wrap an error of the PNG encoder.
+fn encoding_error(error: png::EncodingError) -> Error {
+    Error::Image(ImageError::Encoding(EncodingError::new(
+        ImageFormat::Png.into(),
+        error,
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::filter::Filter;
+    use crate::*;
+
+    #[test]
+    fn bands_match_full_render() {
+        let camera = Camera::new(
+            vector![0., 0., 1.],
+            vector![0., 0., 0.],
+            vector![0., 1., 0.],
+            float::consts::FRAC_PI_2,
+            1.,
+            0.,
+            1.,
+        );
+        let filter = Filter::Gaussian {
+            radius: 1.5,
+            alpha: 2.,
+        };
+        let raytracer = || {
+            Raytracer::new(camera.clone(), color![0.5, 0.7, 1.], 6, 7, 256, 1).with_filter(filter)
+        };
+        let full = raytracer().render().unwrap().into_image().unwrap();
+
+        let path = std::env::temp_dir().join("streaming_bands.png");
+        let mut writer = BandWriter::create(&path, 6, 7).unwrap();
+        raytracer()
+            .render_bands(3, |band| writer.write_band(&band))
+            .unwrap();
+        assert_eq!(writer.rows_written(), 7);
+        writer.finish().unwrap();
+
+        // The bands are filtered across their borders, so they match the full render up to the noise.
+        let streamed = image::open(&path).unwrap().into_rgb8();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(streamed.dimensions(), (6, 7));
+        for (a, b) in streamed.pixels().zip(full.pixels()) {
+            for (a, b) in a.0.into_iter().zip(b.0) {
+                assert!(a.abs_diff(b) <= 2, "{a} {b}");
+            }
+        }
+
+        let path = std::env::temp_dir().join("streaming_bands.ppm");
+        let mut writer = BandWriter::create(&path, 6, 7).unwrap();
+        let band = RaytracedImage::new(vec![color![1., 0., 0.]; 6], 6, 1);
+        writer.write_band(&band).unwrap();
+        assert!(writer
+            .write_band(&RaytracedImage::new(Vec::new(), 5, 0))
+            .is_err());
+        assert!(writer.finish().is_err());
+        let ppm = std::fs::read_to_string(&path).unwrap();
+        assert!(ppm.starts_with("P3\n6 7\n255\n255 0 0\n"));
+        std::fs::remove_file(path).unwrap();
+        assert!(BandWriter::create("image.jpg", 6, 7).is_err());
+    }
+}
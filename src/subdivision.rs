@@ -0,0 +1,280 @@
+//! Catmull-Clark subdivision surfaces, which turn a coarse polygonal cage into a smooth [`Mesh`].
+//!
+//! Each subdivision splits every face with n vertices into n quadrilaterals and moves the vertices towards a smooth limit surface, which is why few levels are enough for low-poly models.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::materials::Material;
+use crate::mesh::{read_obj, Mesh, Shading};
+use crate::*;
+
+/// A polygonal cage that is subdivided with the Catmull-Clark scheme before rendering.
+///
+/// Faces can have any number of vertices, but quadrilaterals give the best results. Edges that only belong to one face are a boundary, which is subdivided like a cubic B-spline curve.
+///
+/// # Fields
+/// - `vertices`: Positions of the vertices of the cage.
+/// - `faces`: Indices into `vertices` of each face, counterclockwise seen from the front.
+/// - `level`: How often the cage is subdivided when it is turned into a [`Mesh`].
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, ray::Ray, subdivision::SubdivisionSurface};
+/// // A cube, which becomes almost a sphere
+/// let vertices = (0..8).map(|i| vector![(i & 1) as Float, (i >> 1 & 1) as Float, (i >> 2) as Float] * 2. - vector![1., 1., 1.]).collect();
+/// let faces = vec![vec![0, 2, 3, 1], vec![4, 5, 7, 6], vec![0, 1, 5, 4], vec![2, 6, 7, 3], vec![0, 4, 6, 2], vec![1, 3, 7, 5]];
+/// let cage = SubdivisionSurface::new(vertices, faces).with_level(3);
+///
+/// let mesh = cage.to_mesh(vector![0., 0., 0.], Lambertian::solid_color(color![0.8, 0.8, 0.8]));
+/// assert_eq!(mesh.triangle_count(), 2 * 6 * 4usize.pow(3));
+/// let hit = mesh.hit(Ray::new(vector![0., 0., 5.], vector![0., 0., -1.]), 0., Float::INFINITY).unwrap();
+/// assert!(hit.front_face && hit.point.z < 1.);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubdivisionSurface {
+    vertices: Vec<Vector3<Float>>,
+    faces: Vec<Vec<usize>>,
+    level: u8,
+}
+
+impl SubdivisionSurface {
+    /// Create a cage from vertices and the indices of the vertices of each face, which is subdivided twice by default.
+    ///
+    /// # Panics
+    /// If an index is out of bounds or a face has less than three vertices.
+    pub fn new(vertices: Vec<Vector3<Float>>, faces: Vec<Vec<usize>>) -> Self {
+        for face in &faces {
+            assert!(
+                face.len() >= 3,
+                "face {face:?} has less than three vertices"
+            );
+            assert!(
+                face.iter().all(|&vertex| vertex < vertices.len()),
+                "face {face:?} refers to a vertex that does not exist"
+            );
+        }
+        Self {
+            vertices,
+            faces,
+            level: 2,
+        }
+    }
+
+    /// Read a cage from a Wavefront OBJ file, keeping its polygons intact. Normals are ignored.
+    pub fn from_obj<R: BufRead>(reader: R) -> io::Result<Self> {
+        let obj = read_obj(reader)?;
+        let faces = obj
+            .polygons
+            .into_iter()
+            .map(|polygon| polygon.into_iter().map(|(vertex, _)| vertex).collect())
+            .collect();
+        Ok(Self::new(obj.vertices, faces))
+    }
+
+    /// Open a Wavefront OBJ file and read a cage via [`from_obj`](SubdivisionSurface::from_obj).
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_obj(BufReader::new(File::open(path)?))
+    }
+
+    /// Consume `self` and set how often it is subdivided. Each level quadruples the number of faces.
+    pub fn with_level(mut self, level: u8) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn vertices(&self) -> &[Vector3<Float>] {
+        &self.vertices
+    }
+
+    pub fn faces(&self) -> &[Vec<usize>] {
+        &self.faces
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Apply one step of the Catmull-Clark scheme, which gives a cage of quadrilaterals with the same `level`.
+    pub fn subdivided(&self) -> Self {
+        let vertex_count = self.vertices.len();
+        let face_points: Vec<_> = self
+            .faces
+            .iter()
+            .map(|face| {
+                face.iter()
+                    .map(|&vertex| self.vertices[vertex])
+                    .sum::<Vector3<Float>>()
+                    / face.len() as Float
+            })
+            .collect();
+
+        // The faces adjacent to each edge, with the edges in the order they are first found.
+        let key = |a: usize, b: usize| (a.min(b), a.max(b));
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (index, face) in self.faces.iter().enumerate() {
+            for (&a, &b) in face.iter().zip(face.iter().cycle().skip(1)) {
+                let adjacent = edge_faces.entry(key(a, b)).or_insert_with(|| {
+                    edges.push(key(a, b));
+                    Vec::new()
+                });
+                adjacent.push(index);
+            }
+        }
+
+        // Interior edge points are the mean of the ends and the adjacent face points, boundary edge points the midpoint.
+        let edge_points = edges.iter().map(|&(a, b)| {
+            let adjacent = &edge_faces[&(a, b)];
+            let ends = self.vertices[a] + self.vertices[b];
+            if adjacent.len() < 2 {
+                ends / 2.
+            } else {
+                let faces: Vector3<Float> = adjacent.iter().map(|&face| face_points[face]).sum();
+                (ends + faces) / (2 + adjacent.len()) as Float
+            }
+        });
+
+        // Sums of the adjacent face points, the midpoints of the adjacent edges and the neighbors along the boundary.
+        let mut face_sums = vec![(Vector3::zeros(), 0); vertex_count];
+        for (face, &point) in self.faces.iter().zip(&face_points) {
+            for &vertex in face {
+                face_sums[vertex].0 += point;
+                face_sums[vertex].1 += 1;
+            }
+        }
+        let mut edge_sums = vec![(Vector3::zeros(), 0); vertex_count];
+        let mut boundary_sums = vec![(Vector3::zeros(), 0); vertex_count];
+        for &(a, b) in &edges {
+            let midpoint = (self.vertices[a] + self.vertices[b]) / 2.;
+            for (vertex, other) in [(a, b), (b, a)] {
+                edge_sums[vertex].0 += midpoint;
+                edge_sums[vertex].1 += 1;
+                if edge_faces[&(a, b)].len() < 2 {
+                    boundary_sums[vertex].0 += self.vertices[other];
+                    boundary_sums[vertex].1 += 1;
+                }
+            }
+        }
+
+        let moved = (0..vertex_count).map(|vertex| {
+            let point = self.vertices[vertex];
+            let (face_sum, faces) = face_sums[vertex];
+            let (edge_sum, valence) = edge_sums[vertex];
+            match boundary_sums[vertex] {
+                // Not part of any face
+                _ if valence == 0 => point,
+                (_, 0) => {
+                    let n = valence as Float;
+                    let face_mean = face_sum / faces as Float;
+                    let edge_mean = edge_sum / n;
+                    (face_mean + 2. * edge_mean + (n - 3.) * point) / n
+                }
+                (neighbors, 2) => (6. * point + neighbors) / 8.,
+                // Corners where several boundaries meet stay in place.
+                _ => point,
+            }
+        });
+
+        // The new vertices are the moved ones, then the face points, then the edge points.
+        let mut vertices: Vec<_> = moved.collect();
+        vertices.extend(&face_points);
+        let edge_offset = vertices.len();
+        vertices.extend(edge_points);
+        let edge_indices: HashMap<_, _> = edges
+            .iter()
+            .enumerate()
+            .map(|(index, &edge)| (edge, edge_offset + index))
+            .collect();
+
+        let faces = self
+            .faces
+            .iter()
+            .enumerate()
+            .flat_map(|(index, face)| {
+                let n = face.len();
+                let edge_indices = &edge_indices;
+                (0..n).map(move |i| {
+                    let (previous, vertex, next) =
+                        (face[(i + n - 1) % n], face[i], face[(i + 1) % n]);
+                    vec![
+                        vertex,
+                        edge_indices[&key(vertex, next)],
+                        vertex_count + index,
+                        edge_indices[&key(previous, vertex)],
+                    ]
+                })
+            })
+            .collect();
+
+        Self {
+            vertices,
+            faces,
+            level: self.level,
+        }
+    }
+
+    /// Subdivide the cage `level` times and split the faces into a smoothly shaded [`Mesh`] of triangles.
+    pub fn to_mesh<M: Material + 'static>(&self, center: Vector3<Float>, material: M) -> Mesh {
+        let mut surface = self.clone();
+        for _ in 0..self.level {
+            surface = surface.subdivided();
+        }
+
+        let triangles: Vec<_> = surface
+            .faces
+            .iter()
+            .flat_map(|face| (1..face.len() - 1).map(|i| [face[0], face[i], face[i + 1]]))
+            .collect();
+        Mesh::new(
+            center,
+            &surface.vertices,
+            &triangles,
+            Shading::Smooth,
+            material,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn catmull_clark() {
+        // A tetrahedron has no boundary.
+        let vertices = vec![
+            vector![1., 1., 1.],
+            vector![1., -1., -1.],
+            vector![-1., 1., -1.],
+            vector![-1., -1., 1.],
+        ];
+        let faces = vec![vec![0, 1, 2], vec![0, 3, 1], vec![0, 2, 3], vec![1, 3, 2]];
+        let subdivided = SubdivisionSurface::new(vertices, faces).subdivided();
+        assert_eq!(subdivided.vertices().len(), 4 + 4 + 6);
+        assert_eq!(subdivided.faces().len(), 12);
+        assert!(subdivided.faces().iter().all(|face| face.len() == 4));
+        // Symmetric, so the vertices move towards the center by the same amount.
+        let norm = subdivided.vertices()[0].norm();
+        assert!(norm < (3. as Float).sqrt());
+        assert!(subdivided.vertices()[..4]
+            .iter()
+            .all(|vertex| (vertex.norm() - norm).abs() < 1e-5));
+
+        // A single quadrilateral only has boundary edges.
+        let quad = SubdivisionSurface::new(
+            vec![
+                vector![0., 0., 0.],
+                vector![1., 0., 0.],
+                vector![1., 1., 0.],
+                vector![0., 1., 0.],
+            ],
+            vec![vec![0, 1, 2, 3]],
+        )
+        .subdivided();
+        assert_eq!(quad.vertices()[4], vector![0.5, 0.5, 0.]);
+        assert_eq!(quad.vertices()[0], vector![0.25, 0.25, 0.] / 2.);
+    }
+}
@@ -1,13 +1,17 @@
 //! A way to apply textures to shapes.
 
+use std::collections::HashMap;
 use std::fmt::Debug;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use image::io::Reader as ImageReader;
-use image::{ImageError, RgbImage};
+use image::{ImageError, Rgb, RgbImage};
 
 use crate::color::WHITE;
+use crate::hitrecord::HitRecord;
 use crate::perlin::Perlin;
+use crate::vec3::Axis;
 use crate::*;
 
 /// An abstraction over all textures.
@@ -19,7 +23,34 @@ pub trait Texture: Debug + Send + Sync {
     /// # Parameters:
     /// - (`u`, `v`): Coordinates on the surface submanifold (lie inside \[0,1\]^2).
     /// - `hit_point`: Point where the [`ray::Ray`] hit the texture.
-    fn color_at(&self, u: f32, v: f32, hit_point: Vector3<f32>) -> Color;
+    fn color_at(&self, u: Float, v: Float, hit_point: Vector3<Float>) -> Color;
+
+    /// Calculate the color of the texture at a [`HitRecord`].
+    ///
+    /// Materials use this, so textures can also depend on e.g. the normal. Defaults to [`color_at`](Texture::color_at).
+    fn color_at_hit(&self, hit: &HitRecord) -> Color {
+        self.color_at(hit.u, hit.v, hit.point)
+    }
+
+    /// The color if it is the same everywhere.
+    fn constant_color(&self) -> Option<Color> {
+        None
+    }
+}
+
+/// Shared textures, e.g. for the [`Registry`](crate::registry::Registry), which only knows them as trait objects.
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn color_at(&self, u: Float, v: Float, hit_point: Vector3<Float>) -> Color {
+        self.as_ref().color_at(u, v, hit_point)
+    }
+
+    fn color_at_hit(&self, hit: &HitRecord) -> Color {
+        self.as_ref().color_at_hit(hit)
+    }
+
+    fn constant_color(&self) -> Option<Color> {
+        self.as_ref().constant_color()
+    }
 }
 
 /// A solid color texture.
@@ -35,9 +66,13 @@ impl SolidColor {
 }
 
 impl Texture for SolidColor {
-    fn color_at(&self, _u: f32, _v: f32, _hit_point: Vector3<f32>) -> Color {
+    fn color_at(&self, _u: Float, _v: Float, _hit_point: Vector3<Float>) -> Color {
         self.color
     }
+
+    fn constant_color(&self) -> Option<Color> {
+        Some(self.color)
+    }
 }
 
 /// A checkerboard texture.
@@ -68,7 +103,7 @@ impl CheckerTexture<SolidColor, SolidColor> {
 }
 
 impl<S: Texture, T: Texture> Texture for CheckerTexture<S, T> {
-    fn color_at(&self, u: f32, v: f32, hit_point: Vector3<f32>) -> Color {
+    fn color_at(&self, u: Float, v: Float, hit_point: Vector3<Float>) -> Color {
         let sin_product =
             (10. * hit_point.x).sin() * (10. * hit_point.y).sin() * (10. * hit_point.z).sin();
         if sin_product < 0. {
@@ -77,6 +112,215 @@ impl<S: Texture, T: Texture> Texture for CheckerTexture<S, T> {
             self.texture_even.color_at(u, v, hit_point)
         }
     }
+
+    fn color_at_hit(&self, hit: &HitRecord) -> Color {
+        let sin_product =
+            (10. * hit.point.x).sin() * (10. * hit.point.y).sin() * (10. * hit.point.z).sin();
+        if sin_product < 0. {
+            self.texture_odd.color_at_hit(hit)
+        } else {
+            self.texture_even.color_at_hit(hit)
+        }
+    }
+}
+
+/// A brick wall in the surface coordinates.
+///
+/// The rows of bricks run along `u`, and every other row is shifted by `row_offset` of a brick.
+///
+/// # Fields
+/// - `brick`: Color of the bricks.
+/// - `mortar`: Color of the mortar between them.
+/// - `scale`: Number of bricks along `u` and rows along `v`.
+/// - `mortar_width`: Width of the mortar as a fraction of the brick height.
+/// - `row_offset`: Shift of every other row as a fraction of the brick width.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, textures::{BrickTexture, Texture}};
+/// let bricks = BrickTexture::new(color![0.6, 0.2, 0.1], color![0.8, 0.8, 0.8]).with_scale(4., 8.);
+/// let origin = vector![0., 0., 0.];
+/// assert_eq!(bricks.color_at(0.125, 0.0625, origin), color![0.6, 0.2, 0.1]);
+/// // Joint between the first two rows
+/// assert_eq!(bricks.color_at(0.125, 0.125, origin), color![0.8, 0.8, 0.8]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BrickTexture {
+    brick: Color,
+    mortar: Color,
+    scale: (Float, Float),
+    mortar_width: Float,
+    row_offset: Float,
+}
+
+impl BrickTexture {
+    pub fn new(brick: Color, mortar: Color) -> Self {
+        Self {
+            brick,
+            mortar,
+            scale: (4., 8.),
+            mortar_width: 0.1,
+            row_offset: 0.5,
+        }
+    }
+
+    /// Consume `self` and set the number of bricks along `u` and rows along `v`.
+    pub fn with_scale(mut self, u: Float, v: Float) -> Self {
+        self.scale = (u, v);
+        self
+    }
+
+    /// Consume `self` and set the width of the mortar as a fraction of the brick height.
+    pub fn with_mortar_width(mut self, mortar_width: Float) -> Self {
+        self.mortar_width = mortar_width;
+        self
+    }
+
+    /// Consume `self` and set the shift of every other row as a fraction of the brick width.
+    pub fn with_row_offset(mut self, row_offset: Float) -> Self {
+        self.row_offset = row_offset;
+        self
+    }
+}
+
+impl Texture for BrickTexture {
+    fn color_at(&self, u: Float, v: Float, _hit_point: Vector3<Float>) -> Color {
+        let y = v * self.scale.1;
+        let row = y.floor();
+        let x = u * self.scale.0
+            + if row.rem_euclid(2.) == 1. {
+                self.row_offset
+            } else {
+                0.
+            };
+
+        // The mortar is centered on the joints, and it has the same absolute width in both directions.
+        let half_mortar = self.mortar_width / 2.;
+        let aspect = self.scale.0 / self.scale.1;
+        let in_mortar =
+            |t: Float, half_width: Float| t.rem_euclid(1.).min(1. - t.rem_euclid(1.)) < half_width;
+        if in_mortar(y, half_mortar) || in_mortar(x, half_mortar * aspect) {
+            self.mortar
+        } else {
+            self.brick
+        }
+    }
+}
+
+/// Parallel stripes perpendicular to an axis in space.
+///
+/// # Fields
+/// - `colors`: Colors of the two kinds of stripes.
+/// - `axis`: The [`Axis`](crate::vec3::Axis) along which the stripes alternate.
+/// - `scale`: Number of pairs of stripes per unit.
+/// - `width`: Fraction of each pair that has the first color.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, textures::{StripeTexture, Texture}, vec3::Axis};
+/// let stripes = StripeTexture::new(color![1., 1., 1.], color![0.8, 0., 0.], Axis::Y).with_scale(2.);
+/// assert_eq!(stripes.color_at(0., 0., vector![5., 0.1, 0.]), color![1., 1., 1.]);
+/// assert_eq!(stripes.color_at(0., 0., vector![5., 0.4, 0.]), color![0.8, 0., 0.]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct StripeTexture {
+    colors: (Color, Color),
+    axis: Axis,
+    scale: Float,
+    width: Float,
+}
+
+impl StripeTexture {
+    pub fn new(first: Color, second: Color, axis: Axis) -> Self {
+        Self {
+            colors: (first, second),
+            axis,
+            scale: 1.,
+            width: 0.5,
+        }
+    }
+
+    /// Consume `self` and set the number of pairs of stripes per unit.
+    pub fn with_scale(mut self, scale: Float) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Consume `self` and set the fraction of each pair that has the first color.
+    pub fn with_width(mut self, width: Float) -> Self {
+        self.width = width;
+        self
+    }
+}
+
+impl Texture for StripeTexture {
+    fn color_at(&self, _u: Float, _v: Float, hit_point: Vector3<Float>) -> Color {
+        if (hit_point[self.axis] * self.scale).rem_euclid(1.) < self.width {
+            self.colors.0
+        } else {
+            self.colors.1
+        }
+    }
+}
+
+/// Polka dots on a grid in the surface coordinates.
+///
+/// # Fields
+/// - `dot`: Color of the dots.
+/// - `background`: Color between the dots.
+/// - `scale`: Number of dots along `u` and `v`.
+/// - `radius`: Radius of the dots as a fraction of their spacing (at most 0.5 for separate dots).
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, textures::{DotTexture, Texture}};
+/// let dots = DotTexture::new(color![1., 1., 1.], color![0.1, 0.2, 0.6]).with_scale(10., 10.);
+/// let origin = vector![0., 0., 0.];
+/// assert_eq!(dots.color_at(0.05, 0.05, origin), color![1., 1., 1.]);
+/// assert_eq!(dots.color_at(0.1, 0.1, origin), color![0.1, 0.2, 0.6]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DotTexture {
+    dot: Color,
+    background: Color,
+    scale: (Float, Float),
+    radius: Float,
+}
+
+impl DotTexture {
+    pub fn new(dot: Color, background: Color) -> Self {
+        Self {
+            dot,
+            background,
+            scale: (8., 8.),
+            radius: 0.3,
+        }
+    }
+
+    /// Consume `self` and set the number of dots along `u` and `v`.
+    pub fn with_scale(mut self, u: Float, v: Float) -> Self {
+        self.scale = (u, v);
+        self
+    }
+
+    /// Consume `self` and set the radius of the dots as a fraction of their spacing.
+    pub fn with_radius(mut self, radius: Float) -> Self {
+        self.radius = radius;
+        self
+    }
+}
+
+impl Texture for DotTexture {
+    fn color_at(&self, u: Float, v: Float, _hit_point: Vector3<Float>) -> Color {
+        // Offset from the center of the nearest dot in units of the spacing
+        let x = (u * self.scale.0).rem_euclid(1.) - 0.5;
+        let y = (v * self.scale.1).rem_euclid(1.) - 0.5;
+        if x * x + y * y < self.radius * self.radius {
+            self.dot
+        } else {
+            self.background
+        }
+    }
 }
 
 /// A grayscale Perlin noise texture.
@@ -87,18 +331,18 @@ impl<S: Texture, T: Texture> Texture for CheckerTexture<S, T> {
 #[derive(Clone, Debug)]
 pub struct PerlinNoiseTexture {
     noise: Perlin,
-    scale: f32,
+    scale: Float,
 }
 
 impl PerlinNoiseTexture {
-    pub fn new(scale: f32) -> Self {
+    pub fn new(scale: Float) -> Self {
         let noise = Perlin::new();
         Self { noise, scale }
     }
 }
 
 impl Texture for PerlinNoiseTexture {
-    fn color_at(&self, _u: f32, _v: f32, hit_point: Vector3<f32>) -> Color {
+    fn color_at(&self, _u: Float, _v: Float, hit_point: Vector3<Float>) -> Color {
         WHITE
             * 0.5
             * (1. + (self.scale * hit_point.z + 10. * self.noise.turbulance(hit_point, 7)).sin())
@@ -106,33 +350,635 @@ impl Texture for PerlinNoiseTexture {
 }
 
 /// A image texture.
+///
+/// It keeps a mipmap, i.e. the image repeatedly halved in size, so that [`Ray`](ray::Ray)s with [`RayDifferentials`](ray::RayDifferentials) whose footprint covers several texels blend them instead of picking one that changes from sample to sample, which makes distant patterns shimmer.
+/// Hits without a footprint and magnified textures use the nearest texel of the full image.
+///
+/// Clones share the texels, so the same image can be used by many materials. A [`TextureCache`] additionally decodes each file only once.
+///
+/// # Fields
+/// - `levels`: The image and its halved versions down to 1 × 1.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, hitrecord::HitRecord, materials::Lambertian, textures::{ImageTexture, Texture}};
+/// # use image::{Rgb, RgbImage};
+/// // Black and white checkerboard of single texels
+/// let image = RgbImage::from_fn(8, 8, |x, y| Rgb([255 * ((x + y) % 2) as u8; 3]));
+/// let texture = ImageTexture::new(image);
+/// assert_eq!(texture.mip_levels(), 4);
+///
+/// let material = Lambertian::solid_color(color![0.5, 0.5, 0.5]);
+/// let mut hit = HitRecord::new(vector![0., 0., 0.], 0.3, 0.6, vector![0., 0., 1.], 1., true, &material);
+/// assert!(texture.color_at_hit(&hit) == color![0., 0., 0.] || texture.color_at_hit(&hit) == color![1., 1., 1.]);
+/// // A pixel covers the whole texture
+/// hit.duv_dx = (1., 0.);
+/// hit.duv_dy = (0., 1.);
+/// assert!((texture.color_at_hit(&hit).r() - 0.5).abs() < 0.01);
+/// ```
 #[derive(Clone, Debug)]
 pub struct ImageTexture {
-    image: RgbImage,
+    levels: Arc<[RgbImage]>,
 }
 
 impl ImageTexture {
     pub fn new(image: RgbImage) -> Self {
-        Self { image }
+        let mut levels = vec![image];
+        loop {
+            let previous = levels.last().expect("there is at least one level");
+            let (width, height) = previous.dimensions();
+            if width <= 1 && height <= 1 {
+                break;
+            }
+            levels.push(Self::downsample(previous));
+        }
+        Self {
+            levels: levels.into(),
+        }
     }
 
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ImageError> {
         let image: RgbImage = ImageReader::open(path)?.decode()?.into_rgb8();
-        Ok(Self { image })
+        Ok(Self::new(image))
+    }
+
+    /// The full image.
+    pub fn image(&self) -> &RgbImage {
+        &self.levels[0]
+    }
+
+    /// Whether `self` and `other` share the same texels, e.g. because they were loaded via the same [`TextureCache`].
+    pub fn shares_image_with(&self, other: &ImageTexture) -> bool {
+        Arc::ptr_eq(&self.levels, &other.levels)
+    }
+
+    /// Number of levels of the mipmap, including the full image.
+    pub fn mip_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Halve the size of `image` by averaging blocks of 2 × 2 texels. Odd sizes repeat the last row or column.
+    fn downsample(image: &RgbImage) -> RgbImage {
+        let (width, height) = image.dimensions();
+        RgbImage::from_fn((width / 2).max(1), (height / 2).max(1), |x, y| {
+            let mut sum = [0u32; 3];
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let texel =
+                    image.get_pixel((2 * x + dx).min(width - 1), (2 * y + dy).min(height - 1));
+                for (sum, &channel) in sum.iter_mut().zip(&texel.0) {
+                    *sum += channel as u32;
+                }
+            }
+            Rgb(sum.map(|sum| ((sum + 2) / 4) as u8))
+        })
+    }
+
+    /// Bilinearly interpolated color of the mipmap level `level`.
+    fn bilinear(&self, level: usize, u: Float, v: Float) -> Color {
+        let image = &self.levels[level];
+        let (width, height) = image.dimensions();
+        let x = u.clamp(0., 1.) * width as Float - 0.5;
+        let y = (1. - v.clamp(0., 1.)) * height as Float - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+
+        let texel = |x: Float, y: Float| -> Color {
+            let x = (x.max(0.) as u32).min(width - 1);
+            let y = (y.max(0.) as u32).min(height - 1);
+            (*image.get_pixel(x, y)).into()
+        };
+        (1. - ty) * ((1. - tx) * texel(x0, y0) + tx * texel(x0 + 1., y0))
+            + ty * ((1. - tx) * texel(x0, y0 + 1.) + tx * texel(x0 + 1., y0 + 1.))
     }
 }
 
 impl Texture for ImageTexture {
-    fn color_at(&self, u: f32, v: f32, _hit_point: Vector3<f32>) -> Color {
-        let mut i = (u.clamp(0., 1.) * self.image.width() as f32) as u32;
-        let mut j = ((1. - v.clamp(0., 1.)) * self.image.height() as f32) as u32;
-        if i >= self.image.width() {
-            i = self.image.width() - 1;
+    fn color_at(&self, u: Float, v: Float, _hit_point: Vector3<Float>) -> Color {
+        let image = &self.levels[0];
+        let mut i = (u.clamp(0., 1.) * image.width() as Float) as u32;
+        let mut j = ((1. - v.clamp(0., 1.)) * image.height() as Float) as u32;
+        if i >= image.width() {
+            i = image.width() - 1;
+        }
+        if j >= image.height() {
+            j = image.height() - 1;
         }
-        if j >= self.image.height() {
-            j = self.image.height() - 1;
+
+        (*image.get_pixel(i, j)).into()
+    }
+
+    /// Trilinear filtering between the two mipmap levels whose texels are closest to the footprint of the hit.
+    fn color_at_hit(&self, hit: &HitRecord) -> Color {
+        let (width, height) = self.levels[0].dimensions();
+        // Width of the footprint in texels of the full image
+        let footprint = [hit.duv_dx, hit.duv_dy]
+            .into_iter()
+            .map(|(du, dv)| {
+                (du * width as Float)
+                    .abs()
+                    .max((dv * height as Float).abs())
+            })
+            .fold(0., Float::max);
+        if footprint <= 1. {
+            return self.color_at(hit.u, hit.v, hit.point);
         }
 
-        (*self.image.get_pixel(i, j)).into()
+        let level = footprint.log2().min((self.levels.len() - 1) as Float);
+        let lower = level.floor() as usize;
+        let upper = (lower + 1).min(self.levels.len() - 1);
+        let t = level - lower as Float;
+        (1. - t) * self.bilinear(lower, hit.u, hit.v) + t * self.bilinear(upper, hit.u, hit.v)
+    }
+}
+
+/// Store of [`ImageTexture`]s keyed by their path, so that every file is decoded only once.
+///
+/// Opening a path again returns a clone that shares the texels of the first one, e.g. for meshes whose materials use the same textures.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, textures::TextureCache};
+/// # use image::RgbImage;
+/// let path = std::env::temp_dir().join("texture_cache_example.png");
+/// RgbImage::new(4, 4).save(&path).unwrap();
+///
+/// let mut cache = TextureCache::new();
+/// let a = cache.open(&path).unwrap();
+/// let b = cache.open(&path).unwrap();
+/// assert!(a.shares_image_with(&b));
+/// assert_eq!(cache.len(), 1);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TextureCache {
+    textures: HashMap<PathBuf, ImageTexture>,
+}
+
+impl TextureCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the image at `path`, or return the [`ImageTexture`] that was already loaded from it.
+    ///
+    /// Paths are compared after [canonicalization](std::fs::canonicalize), so different spellings of the same file share one texture.
+    pub fn open<P: AsRef<Path>>(&mut self, path: P) -> Result<ImageTexture, ImageError> {
+        let path = path.as_ref();
+        let key = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Some(texture) = self.textures.get(&key) {
+            return Ok(texture.clone());
+        }
+
+        let texture = ImageTexture::open(path)?;
+        self.textures.insert(key, texture.clone());
+        Ok(texture)
+    }
+
+    /// Number of distinct images in the cache.
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    /// Remove all images from the cache. Textures that were already handed out stay valid.
+    pub fn clear(&mut self) {
+        self.textures.clear();
+    }
+}
+
+/// A wrapper that transforms the surface coordinates (`u`, `v`) before passing them to another [`Texture`].
+///
+/// The coordinates are first scaled, then rotated counterclockwise, then offset and finally wrapped into \[0,1\]^2, so a scale of 8 repeats the texture 8 times.
+///
+/// # Fields
+/// - `texture`: The transformed [`Texture`].
+/// - `scale`: How often the texture is repeated in `u` and `v` direction.
+/// - `rotation`: Counterclockwise rotation in radians.
+/// - `offset`: Shift of the texture in `u` and `v` direction.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, textures::{ImageTexture, Texture, UvTransform}};
+/// # use image::{Rgb, RgbImage};
+/// // Left half black, right half white
+/// let image = RgbImage::from_fn(2, 1, |x, _| Rgb([255 * x as u8; 3]));
+/// let tiled = UvTransform::new(ImageTexture::new(image)).with_scale(2., 2.);
+/// assert_eq!(tiled.color_at(0.3, 0.5, vector![0., 0., 0.]), color![1., 1., 1.]);
+/// assert_eq!(tiled.color_at(0.6, 0.5, vector![0., 0., 0.]), color![0., 0., 0.]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct UvTransform<T: Texture> {
+    texture: T,
+    scale: (Float, Float),
+    rotation: Float,
+    offset: (Float, Float),
+}
+
+impl<T: Texture> UvTransform<T> {
+    pub fn new(texture: T) -> Self {
+        Self {
+            texture,
+            scale: (1., 1.),
+            rotation: 0.,
+            offset: (0., 0.),
+        }
+    }
+
+    /// Consume `self` and set how often the texture is repeated in `u` and `v` direction.
+    pub fn with_scale(mut self, u: Float, v: Float) -> Self {
+        self.scale = (u, v);
+        self
+    }
+
+    /// Consume `self` and set the counterclockwise rotation in radians.
+    pub fn with_rotation(mut self, angle: Float) -> Self {
+        self.rotation = angle;
+        self
+    }
+
+    /// Consume `self` and set the shift of the texture in `u` and `v` direction.
+    pub fn with_offset(mut self, u: Float, v: Float) -> Self {
+        self.offset = (u, v);
+        self
+    }
+
+    /// Transform the surface coordinates (`u`, `v`) into those of the inner texture.
+    pub fn transform(&self, u: Float, v: Float) -> (Float, Float) {
+        let (u, v) = (self.scale.0 * u, self.scale.1 * v);
+        let (sin, cos) = self.rotation.sin_cos();
+        let (u, v) = (cos * u - sin * v, sin * u + cos * v);
+        (
+            (u + self.offset.0).rem_euclid(1.),
+            (v + self.offset.1).rem_euclid(1.),
+        )
+    }
+
+    /// Transform a change of the surface coordinates, which is only scaled and rotated.
+    fn transform_derivative(&self, (du, dv): (Float, Float)) -> (Float, Float) {
+        let (du, dv) = (self.scale.0 * du, self.scale.1 * dv);
+        let (sin, cos) = self.rotation.sin_cos();
+        (cos * du - sin * dv, sin * du + cos * dv)
+    }
+}
+
+impl<T: Texture> Texture for UvTransform<T> {
+    fn color_at(&self, u: Float, v: Float, hit_point: Vector3<Float>) -> Color {
+        let (u, v) = self.transform(u, v);
+        self.texture.color_at(u, v, hit_point)
+    }
+
+    fn color_at_hit(&self, hit: &HitRecord) -> Color {
+        let (u, v) = self.transform(hit.u, hit.v);
+        self.texture.color_at_hit(&HitRecord {
+            u,
+            v,
+            duv_dx: self.transform_derivative(hit.duv_dx),
+            duv_dy: self.transform_derivative(hit.duv_dy),
+            ..hit.clone()
+        })
+    }
+
+    fn constant_color(&self) -> Option<Color> {
+        self.texture.constant_color()
+    }
+}
+
+/// The scalar a [`GradientTexture`] maps to a color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientInput {
+    /// The surface coordinate `u`.
+    U,
+    /// The surface coordinate `v`.
+    V,
+    /// The height (`y` coordinate) of the hit point, mapped from \[`min`,`max`\] to \[0,1\].
+    Height { min: Float, max: Float },
+    /// [`Perlin`] turbulence at the hit point scaled by `scale`.
+    Noise { scale: Float },
+}
+
+/// How a [`GradientTexture`] interpolates between its color stops.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Blend linearly.
+    #[default]
+    Linear,
+    /// Blend with a smoothstep, so the colors change slowly near the stops.
+    Smooth,
+    /// Keep the color of the lower stop, which gives bands (e.g. for toon shading).
+    Constant,
+}
+
+/// A texture mapping a scalar through a color ramp.
+///
+/// # Fields
+/// - `input`: The [`GradientInput`] which is mapped.
+/// - `stops`: Positions in \[0,1\] and colors of the ramp, sorted by position. Values outside of the stops get the color of the nearest one.
+/// - `interpolation`: The [`Interpolation`] between the stops.
+/// - `noise`: Stores the [`Perlin`] object for [`GradientInput::Noise`]. This is generated automatically.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, textures::{GradientInput, GradientTexture, Interpolation, Texture}};
+/// // Terrain: sand at the bottom, grass in the middle and snow at the top
+/// let terrain = GradientTexture::new(
+///     GradientInput::Height { min: 0., max: 10. },
+///     vec![(0.1, color![0.8, 0.7, 0.5]), (0.3, color![0.2, 0.5, 0.1]), (0.8, color![1., 1., 1.])],
+/// )
+/// .with_interpolation(Interpolation::Constant);
+/// assert_eq!(terrain.color_at(0., 0., vector![0., 0., 0.]), color![0.8, 0.7, 0.5]);
+/// assert_eq!(terrain.color_at(0., 0., vector![0., 5., 0.]), color![0.2, 0.5, 0.1]);
+/// assert_eq!(terrain.color_at(0., 0., vector![0., 9., 0.]), color![1., 1., 1.]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct GradientTexture {
+    input: GradientInput,
+    stops: Vec<(Float, Color)>,
+    interpolation: Interpolation,
+    noise: Perlin,
+}
+
+impl GradientTexture {
+    /// Create a [`GradientTexture`].
+    ///
+    /// # Panics
+    /// If there are no `stops`.
+    pub fn new(input: GradientInput, mut stops: Vec<(Float, Color)>) -> Self {
+        assert!(!stops.is_empty(), "gradient without color stops");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            input,
+            stops,
+            interpolation: Interpolation::default(),
+            noise: Perlin::new(),
+        }
+    }
+
+    /// Consume `self` and set the [`Interpolation`] between the stops.
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Color of the ramp at position `t`.
+    pub fn ramp(&self, t: Float) -> Color {
+        let upper = self.stops.partition_point(|&(position, _)| position <= t);
+        if upper == 0 {
+            return self.stops[0].1;
+        }
+        if upper == self.stops.len() {
+            return self.stops[upper - 1].1;
+        }
+
+        let (position0, color0) = self.stops[upper - 1];
+        let (position1, color1) = self.stops[upper];
+        let s = (t - position0) / (position1 - position0);
+        let s = match self.interpolation {
+            Interpolation::Linear => s,
+            Interpolation::Smooth => s * s * (3. - 2. * s),
+            Interpolation::Constant => 0.,
+        };
+        color0 * (1. - s) + color1 * s
+    }
+}
+
+impl Texture for GradientTexture {
+    fn color_at(&self, u: Float, v: Float, hit_point: Vector3<Float>) -> Color {
+        let t = match self.input {
+            GradientInput::U => u,
+            GradientInput::V => v,
+            GradientInput::Height { min, max } => (hit_point.y - min) / (max - min),
+            GradientInput::Noise { scale } => self.noise.turbulance(scale * hit_point, 7),
+        };
+        self.ramp(t)
+    }
+
+    fn constant_color(&self) -> Option<Color> {
+        (self.stops.len() == 1).then(|| self.stops[0].1)
+    }
+}
+
+/// How a [`BlendTexture`] combines its two textures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Interpolate between the first and the second texture.
+    #[default]
+    Mix,
+    /// Multiply the colors, which darkens (e.g. for dirt).
+    Multiply,
+    /// Add the colors, which brightens.
+    Add,
+    /// Multiply dark and screen light parts of the first texture, which increases the contrast.
+    Overlay,
+}
+
+impl BlendMode {
+    /// Combine the colors `a` and `b`.
+    pub fn blend(self, a: Color, b: Color) -> Color {
+        match self {
+            BlendMode::Mix => b,
+            BlendMode::Multiply => a * b,
+            BlendMode::Add => a + b,
+            BlendMode::Overlay => a
+                .into_iter()
+                .zip(b)
+                .map(|(a, b)| {
+                    if a < 0.5 {
+                        2. * a * b
+                    } else {
+                        1. - 2. * (1. - a) * (1. - b)
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A texture blending two textures with a mask.
+///
+/// The result is interpolated between the first texture and both textures combined with the [`BlendMode`], where the mask (the mean of the channels of its color) is the weight of the latter.
+///
+/// # Fields
+/// - `texture_a`: The first (base) texture.
+/// - `texture_b`: The second texture, which is blended onto the first.
+/// - `mask`: The weight of the blend.
+/// - `mode`: The [`BlendMode`].
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, textures::{BlendTexture, PerlinNoiseTexture, SolidColor, Texture}};
+/// // Mossy rock
+/// let rock = SolidColor::new(color![0.4, 0.4, 0.4]);
+/// let moss = SolidColor::new(color![0.2, 0.5, 0.1]);
+/// let mossy_rock = BlendTexture::new(rock, moss, PerlinNoiseTexture::new(4.));
+///
+/// let half = BlendTexture::with_factor(SolidColor::new(color![0., 0., 0.]), SolidColor::new(color![1., 1., 1.]), 0.5);
+/// assert_eq!(half.color_at(0., 0., vector![0., 0., 0.]), color![0.5, 0.5, 0.5]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BlendTexture<A: Texture, B: Texture, M: Texture> {
+    texture_a: A,
+    texture_b: B,
+    mask: M,
+    mode: BlendMode,
+}
+
+impl<A: Texture, B: Texture, M: Texture> BlendTexture<A, B, M> {
+    pub fn new(texture_a: A, texture_b: B, mask: M) -> Self {
+        Self {
+            texture_a,
+            texture_b,
+            mask,
+            mode: BlendMode::default(),
+        }
+    }
+
+    /// Consume `self` and set the [`BlendMode`].
+    pub fn with_mode(mut self, mode: BlendMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl<A: Texture, B: Texture> BlendTexture<A, B, SolidColor> {
+    /// Create a [`BlendTexture`] with the same weight `factor` everywhere.
+    pub fn with_factor(texture_a: A, texture_b: B, factor: Float) -> Self {
+        Self::new(
+            texture_a,
+            texture_b,
+            SolidColor::new(color![factor, factor, factor]),
+        )
+    }
+}
+
+impl<A: Texture, B: Texture, M: Texture> Texture for BlendTexture<A, B, M> {
+    fn color_at(&self, u: Float, v: Float, hit_point: Vector3<Float>) -> Color {
+        let weight = mask_weight(self.mask.color_at(u, v, hit_point));
+
+        let a = self.texture_a.color_at(u, v, hit_point);
+        if weight == 0. {
+            return a;
+        }
+        let b = self.texture_b.color_at(u, v, hit_point);
+        a * (1. - weight) + self.mode.blend(a, b) * weight
+    }
+
+    fn color_at_hit(&self, hit: &HitRecord) -> Color {
+        let weight = mask_weight(self.mask.color_at_hit(hit));
+
+        let a = self.texture_a.color_at_hit(hit);
+        if weight == 0. {
+            return a;
+        }
+        let b = self.texture_b.color_at_hit(hit);
+        a * (1. - weight) + self.mode.blend(a, b) * weight
+    }
+
+    fn constant_color(&self) -> Option<Color> {
+        let weight = mask_weight(self.mask.constant_color()?);
+        let a = self.texture_a.constant_color()?;
+        let b = self.texture_b.constant_color()?;
+        Some(a * (1. - weight) + self.mode.blend(a, b) * weight)
+    }
+}
+
+/// Weight of a [`BlendTexture`] given by the color of its mask.
+fn mask_weight(mask: Color) -> Float {
+    ((mask.r() + mask.g() + mask.b()) / 3.).clamp(0., 1.)
+}
+
+/// A texture projected along the three axes and blended by the normal.
+///
+/// This does not need (`u`, `v`) coordinates, so it also works for e.g. [meshes](crate::mesh::Mesh) without good UVs.
+/// The projected texture is repeated every `1 / scale` units in world space.
+/// Without a normal (in [`color_at`](Texture::color_at)), the three projections are weighted equally.
+///
+/// # Fields
+/// - `texture`: The projected [`Texture`], e.g. an [`ImageTexture`].
+/// - `scale`: How often the texture is repeated per unit.
+/// - `sharpness`: Exponent of the blend weights. Higher values give sharper transitions between the projections.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, mesh::{Mesh, Shading}, textures::{ImageTexture, TriplanarTexture}};
+/// # use image::RgbImage;
+/// # let image = RgbImage::new(16, 16);
+/// let rock = TriplanarTexture::new(ImageTexture::new(image)).with_scale(0.5);
+/// let triangle = Mesh::new(
+///     vector![0., 0., 0.],
+///     &[vector![0., 0., 0.], vector![1., 0., 0.], vector![0., 1., 0.]],
+///     &[[0, 1, 2]],
+///     Shading::Flat,
+///     Lambertian::new(rock),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct TriplanarTexture<T: Texture> {
+    texture: T,
+    scale: Float,
+    sharpness: Float,
+}
+
+impl<T: Texture> TriplanarTexture<T> {
+    pub fn new(texture: T) -> Self {
+        Self {
+            texture,
+            scale: 1.,
+            sharpness: 4.,
+        }
+    }
+
+    /// Consume `self` and set how often the texture is repeated per unit.
+    pub fn with_scale(mut self, scale: Float) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Consume `self` and set the exponent of the blend weights.
+    pub fn with_sharpness(mut self, sharpness: Float) -> Self {
+        self.sharpness = sharpness;
+        self
+    }
+
+    /// Blend the projections along the axes with the weights (which sum up to one).
+    fn blend(&self, point: Vector3<Float>, weights: Vector3<Float>) -> Color {
+        let scaled = self.scale * point;
+        let projections = [
+            (scaled.y, scaled.z),
+            (scaled.x, scaled.z),
+            (scaled.x, scaled.y),
+        ];
+
+        let mut color = color![0., 0., 0.];
+        for (weight, (u, v)) in weights.iter().zip(projections) {
+            if *weight > 0. {
+                color += self
+                    .texture
+                    .color_at(u.rem_euclid(1.), v.rem_euclid(1.), point)
+                    * *weight;
+            }
+        }
+        color
+    }
+}
+
+impl<T: Texture> Texture for TriplanarTexture<T> {
+    fn color_at(&self, _u: Float, _v: Float, hit_point: Vector3<Float>) -> Color {
+        self.blend(hit_point, Vector3::repeat(1. / 3.))
+    }
+
+    fn color_at_hit(&self, hit: &HitRecord) -> Color {
+        let weights = hit.normal.map(|n| n.abs().powf(self.sharpness));
+        let sum = weights.sum();
+        if sum > 0. {
+            self.blend(hit.point, weights / sum)
+        } else {
+            self.color_at(hit.u, hit.v, hit.point)
+        }
+    }
+
+    fn constant_color(&self) -> Option<Color> {
+        self.texture.constant_color()
     }
 }
@@ -0,0 +1,295 @@
+//! Experimental transient rendering, which resolves the light arriving at each pixel by its time of flight.
+//!
+//! [`Raytracer::render_transient`](crate::raytracer::Raytracer::render_transient) follows the same paths as the [path tracer](crate::integrator::Integrator::PathTracer), but adds the light of every path to the frame of its total length instead of a single image.
+//! The speed of light is one, so times are measured in the units of the scene.
+//! The result is a [`TransientImage`], a stack of frames that shows light spreading through the scene, e.g. for non-line-of-sight imaging research.
+//!
+//! Light that needs an infinite time, i.e. from the [`Background`](crate::background::Background) and [`DirectionalLight`](crate::lights::DirectionalLight)s, is not recorded, and [`Fog`](crate::volume::Fog) is ignored.
+
+use crate::color::{BLACK, WHITE};
+use crate::integrator::{hit_visible, power_heuristic, shadow_ray, IntegratorSettings, Scene};
+use crate::ray::{Ray, RayKind};
+use crate::raytracer::RaytracedImage;
+use crate::*;
+
+/// Which times of flight are recorded.
+///
+/// # Fields
+/// - `start`: Path length at which the first frame begins.
+/// - `frame_duration`: Range of path lengths of each frame.
+/// - `frames`: Number of frames.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransientSettings {
+    pub start: Float,
+    pub frame_duration: Float,
+    pub frames: usize,
+}
+
+impl TransientSettings {
+    pub fn new(start: Float, frame_duration: Float, frames: usize) -> Self {
+        Self {
+            start,
+            frame_duration,
+            frames,
+        }
+    }
+
+    /// Index of the frame that records light after a path of `length`.
+    fn frame(&self, length: Float) -> Option<usize> {
+        let position = (length - self.start) / self.frame_duration;
+        #[allow(clippy::unnecessary_cast)]
+        (position >= 0. && position < self.frames as Float).then_some(position as usize)
+    }
+}
+
+/// A stack of frames of the light arriving at the camera, see [`transient`](self).
+///
+/// # Fields
+/// - `settings`: [`TransientSettings`] of the render.
+/// - `frames`: Colors of all pixels of each frame, row by row from the top.
+/// - `image_width`: Width of the frames.
+/// - `image_height`: Height of the frames.
+#[derive(Clone, Debug)]
+pub struct TransientImage {
+    settings: TransientSettings,
+    frames: Vec<Vec<Color>>,
+    image_width: u32,
+    image_height: u32,
+}
+
+impl TransientImage {
+    /// Create a [`TransientImage`] from the time-resolved light of each pixel.
+    pub(crate) fn new(
+        settings: TransientSettings,
+        pixels: Vec<Vec<Color>>,
+        image_width: u32,
+        image_height: u32,
+    ) -> Self {
+        let frames = (0..settings.frames)
+            .map(|frame| pixels.iter().map(|pixel| pixel[frame]).collect())
+            .collect();
+        Self {
+            settings,
+            frames,
+            image_width,
+            image_height,
+        }
+    }
+
+    pub fn settings(&self) -> &TransientSettings {
+        &self.settings
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.image_width, self.image_height)
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Path length at which frame `index` begins.
+    pub fn time(&self, index: usize) -> Float {
+        self.settings.start + index as Float * self.settings.frame_duration
+    }
+
+    /// Colors of frame `index`, row by row from the top.
+    ///
+    /// # Panics
+    /// If there is no frame `index`.
+    pub fn colors(&self, index: usize) -> &[Color] {
+        &self.frames[index]
+    }
+
+    /// Frame `index` as a [`RaytracedImage`], e.g. for saving it.
+    ///
+    /// The light of a frame is much darker than that of the whole render, so it usually needs an [exposure](RaytracedImage::with_exposure).
+    ///
+    /// # Panics
+    /// If there is no frame `index`.
+    pub fn frame(&self, index: usize) -> RaytracedImage {
+        RaytracedImage::new(
+            self.frames[index].clone(),
+            self.image_width,
+            self.image_height,
+        )
+    }
+
+    /// The light of the pixel at column `x` and row `y` (from the top) in every frame.
+    pub fn pixel(&self, x: u32, y: u32) -> Vec<Color> {
+        let index = y as usize * self.image_width as usize + x as usize;
+        self.frames.iter().map(|frame| frame[index]).collect()
+    }
+
+    /// The sum of all frames, i.e. all recorded light regardless of its time of flight.
+    pub fn steady_state(&self) -> RaytracedImage {
+        let pixel_count = self.image_width as usize * self.image_height as usize;
+        let colors = (0..pixel_count)
+            .map(|index| {
+                self.frames
+                    .iter()
+                    .fold(BLACK, |sum, frame| sum + frame[index])
+            })
+            .collect();
+        RaytracedImage::new(colors, self.image_width, self.image_height)
+    }
+}
+
+/// Trace a path starting with the camera `ray` and add its light to the `frames` of its lengths.
+///
+/// This is the path tracer with next-event estimation, recording every contribution separately with the distance to the light it was found at.
+pub(crate) fn trace(
+    scene: &Scene,
+    mut ray: Ray,
+    max_depth: u16,
+    settings: &IntegratorSettings,
+    transient: &TransientSettings,
+    frames: &mut [Color],
+) {
+    let mut record = |length: Float, radiance: Color| {
+        if let Some(frame) = transient.frame(length) {
+            frames[frame] += settings.clamp(radiance);
+        }
+    };
+
+    let mut throughput = WHITE;
+    let mut length = 0.;
+    let mut bsdf_pdf = None;
+    let light_sampled = scene.lights.has_area_lights();
+    for depth in 0..max_depth {
+        let Some(hit) = hit_visible(scene.world, ray, scene.offset, Float::INFINITY) else {
+            return;
+        };
+        // The parameter of the hit is relative to the last Null material that was passed.
+        length += (hit.point - ray.origin()).norm();
+        let material = hit.material();
+
+        let emitted = material.emit(hit.u, hit.v, hit.point);
+        if emitted != BLACK {
+            let weight = match bsdf_pdf {
+                Some(bsdf_pdf) => {
+                    let light_pdf =
+                        scene
+                            .lights
+                            .pdf_value(ray.origin(), ray.direction(), ray.time());
+                    power_heuristic(bsdf_pdf, light_pdf)
+                }
+                None => 1.,
+            };
+            record(length, weight * throughput * emitted);
+        }
+
+        if light_sampled {
+            if let Some(direction) = scene.lights.random_direction(hit.point, ray.time()) {
+                if let Some((bsdf, pdf)) = material.evaluate(ray, &hit, direction) {
+                    let light_pdf = scene.lights.pdf_value(hit.point, direction, ray.time());
+                    let light_hit = hit_visible(
+                        scene.world,
+                        shadow_ray(scene, ray, &hit, direction),
+                        scene.offset,
+                        Float::INFINITY,
+                    );
+                    match light_hit {
+                        Some(light_hit) if light_pdf > 0. && pdf > 0. => {
+                            let emitted = light_hit.material().emit(
+                                light_hit.u,
+                                light_hit.v,
+                                light_hit.point,
+                            );
+                            let weight = power_heuristic(light_pdf, pdf);
+                            record(
+                                length + (light_hit.point - hit.point).norm(),
+                                weight / light_pdf * throughput * bsdf * emitted,
+                            );
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        for light in scene.lights.analytic_lights() {
+            let Some(sample) = light.sample(hit.point) else {
+                continue;
+            };
+            if !sample.distance.is_finite() {
+                continue;
+            }
+            let Some((bsdf, _)) = material.evaluate(ray, &hit, sample.direction) else {
+                continue;
+            };
+            let shadow_ray = shadow_ray(scene, ray, &hit, sample.direction);
+            if hit_visible(
+                scene.world,
+                shadow_ray,
+                scene.offset,
+                sample.distance - scene.offset.t_min(),
+            )
+            .is_none()
+            {
+                record(
+                    length + sample.distance,
+                    throughput * bsdf * sample.radiance,
+                );
+            }
+        }
+
+        let Some((scattered, attenuation)) = material.scatter(ray, hit.clone()) else {
+            return;
+        };
+        let pdf = material
+            .evaluate(ray, &hit, scattered.direction())
+            .map(|(_, pdf)| pdf);
+        bsdf_pdf = if light_sampled { pdf } else { None };
+        throughput *= attenuation;
+        let scattered = scattered.with_kind(match pdf {
+            Some(_) => RayKind::Indirect,
+            None => RayKind::Specular,
+        });
+        ray = scene.offset.leave(ray, &hit, scattered);
+
+        let continue_probability = settings.continue_probability(depth + 1);
+        if continue_probability < 1. {
+            if crate::random::random::<Float>() >= continue_probability {
+                return;
+            }
+            throughput /= continue_probability;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::materials::DiffuseLight;
+    use crate::shapes::{Plane, Rectangle};
+
+    #[test]
+    fn direct_emission_arrives_at_its_distance() {
+        let camera = Camera::new(
+            vector![0., 0., 0.],
+            vector![0., 0., -1.],
+            vector![0., 1., 0.],
+            0.2,
+            1.,
+            0.,
+            1.,
+        );
+        let mut raytracer = Raytracer::new(camera, BLACK, 3, 3, 4, 4);
+        raytracer.world.push(Rectangle::new(
+            Plane::XY,
+            vector![0., 0., -3.],
+            10.,
+            10.,
+            DiffuseLight::solid_color(color![1., 1., 1.]),
+        ));
+
+        let transient = raytracer
+            .render_transient(TransientSettings::new(0., 1., 5))
+            .unwrap();
+        assert_eq!(transient.frame_count(), 5);
+        let pixel = transient.pixel(1, 1);
+        assert!((pixel[3].r() - 1.).abs() < 1e-5);
+        assert!(pixel.iter().enumerate().all(|(i, c)| i == 3 || *c == BLACK));
+        assert!((transient.steady_state().colors()[4].g() - 1.).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,773 @@
+//! Minimal import of animated transforms and cameras from USD stages in the text format (`.usda`).
+//!
+//! Only the hierarchy of prims, their `xformOp`s (also as `timeSamples`), and cameras are read, so animation baked in another application can drive renders of several frames.
+//! Geometry still has to come from OBJ or glTF files. Composition (references, payloads, variants) and the binary formats (`.usdc`, Alembic) are not supported.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use nalgebra::{Matrix4, Quaternion, Rotation3, UnitQuaternion};
+
+use crate::error::Error;
+use crate::shapes::Offset;
+use crate::*;
+
+/// A value of an attribute.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Number(f64),
+    /// A tuple `(...)` or an array `[...]`.
+    List(Vec<Value>),
+    /// A string, token, or anything else that is not needed.
+    Text(String),
+}
+
+impl Value {
+    /// All numbers in the value, flattened.
+    fn numbers(&self) -> Vec<f64> {
+        match self {
+            Value::Number(number) => vec![*number],
+            Value::List(values) => values.iter().flat_map(Value::numbers).collect(),
+            Value::Text(_) => Vec::new(),
+        }
+    }
+
+    /// Linear interpolation between numeric values of the same shape, or `self` otherwise.
+    fn lerp(&self, other: &Value, t: f64) -> Value {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => Value::Number(a + t * (b - a)),
+            (Value::List(a), Value::List(b)) if a.len() == b.len() => {
+                Value::List(a.iter().zip(b).map(|(a, b)| a.lerp(b, t)).collect())
+            }
+            _ => self.clone(),
+        }
+    }
+}
+
+/// An attribute with a default value and time samples.
+#[derive(Clone, Debug, Default)]
+struct Attribute {
+    default: Option<Value>,
+    samples: Vec<(f64, Value)>,
+}
+
+impl Attribute {
+    /// The value at `time`, interpolated linearly between the time samples and held constant outside of them.
+    fn at(&self, time: f64) -> Option<Value> {
+        let samples = &self.samples;
+        let Some(first) = samples.first() else {
+            return self.default.clone();
+        };
+        let next = samples.partition_point(|(sample, _)| *sample <= time);
+        Some(match next {
+            0 => first.1.clone(),
+            _ if next == samples.len() => samples[next - 1].1.clone(),
+            _ => {
+                let (t0, a) = &samples[next - 1];
+                let (t1, b) = &samples[next];
+                a.lerp(b, (time - t0) / (t1 - t0))
+            }
+        })
+    }
+}
+
+/// A prim of a [`UsdStage`].
+#[derive(Clone, Debug)]
+struct Prim {
+    path: String,
+    type_name: String,
+    parent: Option<usize>,
+    attributes: HashMap<String, Attribute>,
+}
+
+/// The prims of a USD stage with their transforms and cameras, which can be evaluated at any time code.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, usd::UsdStage};
+/// let stage = UsdStage::parse(r#"#usda 1.0
+/// (
+///     startTimeCode = 1
+///     endTimeCode = 24
+/// )
+///
+/// def Xform "Rig"
+/// {
+///     double3 xformOp:translate.timeSamples = {
+///         1: (0, 0, 0),
+///         24: (0, 0, -23),
+///     }
+///     uniform token[] xformOpOrder = ["xformOp:translate"]
+///
+///     def Camera "Camera"
+///     {
+///         float focalLength = 35
+///         double3 xformOp:translate = (0, 1, 10)
+///         uniform token[] xformOpOrder = ["xformOp:translate"]
+///     }
+/// }
+/// "#).unwrap();
+///
+/// assert_eq!(stage.time_range(), Some((1., 24.)));
+/// let camera = stage.camera("/Rig/Camera", 12.5).unwrap();
+/// let ray = camera.get_center_ray(0.5, 0.5);
+/// assert!((ray.origin() - vector![0., 1., -1.5]).norm() < 1e-4);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct UsdStage {
+    prims: Vec<Prim>,
+    metadata: HashMap<String, f64>,
+}
+
+impl UsdStage {
+    /// Read a stage from a `.usda` file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse a stage in the USD text format.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        let mut stage = Self::default();
+
+        parser.skip_newlines();
+        if parser.peek() == Some(&Token::Punctuation('(')) {
+            for (key, value) in parser.metadata()? {
+                if let Value::Number(number) = value {
+                    stage.metadata.insert(key, number);
+                }
+            }
+        }
+        loop {
+            parser.skip_newlines();
+            match parser.peek() {
+                None => break,
+                Some(Token::Word(word)) if is_specifier(word) => {
+                    parser.prim(None, &mut stage.prims)?
+                }
+                Some(_) => {
+                    let start = parser.position;
+                    parser.skip_statement();
+                    if parser.position == start {
+                        return Err(invalid(parser.line(), "unexpected closing bracket"));
+                    }
+                }
+            }
+        }
+        Ok(stage)
+    }
+
+    /// Paths of all prims, e.g. `/World/Camera`.
+    pub fn prim_paths(&self) -> impl Iterator<Item = &str> {
+        self.prims.iter().map(|prim| prim.path.as_str())
+    }
+
+    /// Paths of all prims of type `Camera`.
+    pub fn cameras(&self) -> impl Iterator<Item = &str> {
+        self.prims
+            .iter()
+            .filter(|prim| prim.type_name == "Camera")
+            .map(|prim| prim.path.as_str())
+    }
+
+    /// The first and last time code of the animation, if the stage defines them.
+    pub fn time_range(&self) -> Option<(Float, Float)> {
+        let start = self.metadata.get("startTimeCode")?;
+        let end = self.metadata.get("endTimeCode")?;
+        Some((float(*start), float(*end)))
+    }
+
+    /// Time codes per second (24 by default), e.g. to convert frames into the times of [`Movable::moving`](crate::shapes::Movable::moving).
+    pub fn time_codes_per_second(&self) -> Float {
+        float(
+            self.metadata
+                .get("timeCodesPerSecond")
+                .or_else(|| self.metadata.get("framesPerSecond"))
+                .copied()
+                .unwrap_or(24.),
+        )
+    }
+
+    /// Transform of the prim at `path` relative to its parent at `time`, composed from its `xformOpOrder`.
+    pub fn local_transform(&self, path: &str, time: Float) -> Option<Matrix4<Float>> {
+        let prim = self.prim(path)?;
+        Some(local_transform(prim, double(time)).0)
+    }
+
+    /// Transform of the prim at `path` into world coordinates at `time`.
+    pub fn transform(&self, path: &str, time: Float) -> Option<Matrix4<Float>> {
+        let mut index = self.prims.iter().position(|prim| prim.path == path)?;
+        let mut transform = Matrix4::identity();
+        loop {
+            let prim = &self.prims[index];
+            let (local, reset) = local_transform(prim, double(time));
+            transform = local * transform;
+            match prim.parent {
+                Some(parent) if !reset => index = parent,
+                _ => return Some(transform),
+            }
+        }
+    }
+
+    /// The rotation and translation of the prim at `path` at `time` as an [`Offset`] for a [`Movable`](crate::shapes::Movable) or an [`Instance`](crate::instancing::Instance). Scaling is ignored.
+    pub fn offset(&self, path: &str, time: Float) -> Option<Offset> {
+        let transform = self.transform(path, time)?;
+        let translation = transform.fixed_view::<3, 1>(0, 3).into_owned();
+        let rotation = Rotation3::from_matrix(&transform.fixed_view::<3, 3>(0, 0).into_owned());
+        // An `Offset` rotates after translating, so the translation is rotated back.
+        let inverse = rotation.inverse();
+        Some(Offset::new(inverse * translation).with_rotation(inverse))
+    }
+
+    /// The camera prim at `path` at `time`.
+    ///
+    /// The field of view and aspect ratio come from the focal length and the apertures. With an `fStop`, the camera has depth of field focused at `focusDistance`.
+    pub fn camera(&self, path: &str, time: Float) -> Option<Camera> {
+        let prim = self.prim(path)?;
+        if prim.type_name != "Camera" {
+            return None;
+        }
+        let number = |name: &str, default: f64| {
+            prim.attributes
+                .get(name)
+                .and_then(|attribute| attribute.at(double(time)))
+                .and_then(|value| value.numbers().first().copied())
+                .unwrap_or(default)
+        };
+        // The lens is measured in tenths of scene units.
+        let focal_length = number("focalLength", 50.);
+        let horizontal_aperture = number("horizontalAperture", 20.955);
+        let vertical_aperture = number("verticalAperture", 15.2908);
+        let f_stop = number("fStop", 0.);
+        let focus_distance = number("focusDistance", 1.).max(1e-3);
+
+        let vertical_fov = 2. * (vertical_aperture / (2. * focal_length)).atan();
+        let aperture = if f_stop > 0. {
+            focal_length / f_stop / 10.
+        } else {
+            0.
+        };
+
+        let transform = self.transform(path, time)?;
+        let origin = transform
+            .transform_point(&vector![0., 0., 0.].into())
+            .coords;
+        let forward = transform.transform_vector(&vector![0., 0., -1.]);
+        let up = transform.transform_vector(&vector![0., 1., 0.]);
+        Some(Camera::new(
+            origin,
+            origin + forward,
+            up,
+            float(vertical_fov),
+            float(horizontal_aperture / vertical_aperture),
+            float(aperture),
+            float(focus_distance),
+        ))
+    }
+
+    fn prim(&self, path: &str) -> Option<&Prim> {
+        self.prims.iter().find(|prim| prim.path == path)
+    }
+}
+
+/// Compose the `xformOp`s of `prim` at `time` in the order of its `xformOpOrder` and return whether it resets the transform of the parent.
+fn local_transform(prim: &Prim, time: f64) -> (Matrix4<Float>, bool) {
+    let order = match prim
+        .attributes
+        .get("xformOpOrder")
+        .and_then(|order| order.at(time))
+    {
+        Some(Value::List(order)) => order,
+        _ => Vec::new(),
+    };
+
+    let mut transform = Matrix4::identity();
+    let mut reset = false;
+    for op in order {
+        let Value::Text(op) = op else { continue };
+        if op == "!resetXformStack!" {
+            reset = true;
+            transform = Matrix4::identity();
+            continue;
+        }
+        let (name, invert) = match op.strip_prefix("!invert!") {
+            Some(name) => (name, true),
+            None => (op.as_str(), false),
+        };
+        let Some(value) = prim.attributes.get(name).and_then(|value| value.at(time)) else {
+            continue;
+        };
+        let numbers: Vec<Float> = value.numbers().into_iter().map(float).collect();
+        // The kind of the op is the part after `xformOp:`, without a suffix like `:pivot`.
+        let kind = name.split(':').nth(1).unwrap_or_default();
+        let radians = |degrees: Float| degrees.to_radians();
+        let axis_rotation = |axis: usize, degrees: Float| {
+            let axis = Vector3::ith_axis(axis);
+            Rotation3::from_axis_angle(&axis, radians(degrees)).to_homogeneous()
+        };
+        let matrix = match (kind, numbers.as_slice()) {
+            ("translate", &[x, y, z]) => Matrix4::new_translation(&vector![x, y, z]),
+            ("scale", &[x, y, z]) => Matrix4::new_nonuniform_scaling(&vector![x, y, z]),
+            ("scale", &[s]) => Matrix4::new_scaling(s),
+            ("rotateX", &[angle]) => axis_rotation(0, angle),
+            ("rotateY", &[angle]) => axis_rotation(1, angle),
+            ("rotateZ", &[angle]) => axis_rotation(2, angle),
+            (kind, &[a, b, c])
+                if kind.len() == 9
+                    && kind.starts_with("rotate")
+                    && kind[6..].bytes().all(|axis| matches!(axis, b'X'..=b'Z')) =>
+            {
+                // The first axis is applied first.
+                kind[6..]
+                    .bytes()
+                    .zip([a, b, c])
+                    .fold(Matrix4::identity(), |matrix, (axis, angle)| {
+                        axis_rotation((axis - b'X') as usize, angle) * matrix
+                    })
+            }
+            ("orient", &[w, x, y, z]) => {
+                UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z)).to_homogeneous()
+            }
+            // Matrices are stored row by row for row vectors, so they are transposed.
+            ("transform", numbers) if numbers.len() == 16 => Matrix4::from_column_slice(numbers),
+            _ => continue,
+        };
+        let matrix = if invert {
+            matrix.try_inverse().unwrap_or(matrix)
+        } else {
+            matrix
+        };
+        transform *= matrix;
+    }
+    (transform, reset)
+}
+
+/// A token of the USD text format.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// Identifiers, numbers, and keywords.
+    Word(String),
+    /// Strings, paths `<...>`, and assets `@...@`.
+    Text(String),
+    Punctuation(char),
+    Newline,
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut characters = text.chars().peekable();
+    let is_word = |character: char| {
+        character.is_alphanumeric() || matches!(character, '_' | '.' | '-' | '+' | '!')
+    };
+
+    while let Some(character) = characters.next() {
+        match character {
+            '\n' => {
+                tokens.push((Token::Newline, line));
+                line += 1;
+            }
+            '#' => while characters.next_if(|&character| character != '\n').is_some() {},
+            '"' | '\'' | '<' | '@' => {
+                let end = match character {
+                    '<' => '>',
+                    other => other,
+                };
+                // Triple-quoted strings may span lines.
+                let triple = character != '<'
+                    && characters.next_if_eq(&character).is_some()
+                    && characters.next_if_eq(&character).is_some();
+                let mut content = String::new();
+                loop {
+                    let Some(next) = characters.next() else {
+                        return Err(invalid(line, "unterminated string"));
+                    };
+                    if next == '\n' {
+                        line += 1;
+                    }
+                    if next == end {
+                        if !triple {
+                            break;
+                        }
+                        if characters.next_if_eq(&end).is_some() {
+                            if characters.next_if_eq(&end).is_some() {
+                                break;
+                            }
+                            content.push(end);
+                        }
+                        content.push(end);
+                        continue;
+                    }
+                    if next == '\\' && !triple {
+                        if let Some(escaped) = characters.next() {
+                            content.push(escaped);
+                        }
+                        continue;
+                    }
+                    content.push(next);
+                }
+                tokens.push((Token::Text(content), line));
+            }
+            '(' | ')' | '[' | ']' | '{' | '}' | '=' | ',' | ';' | ':' => {
+                tokens.push((Token::Punctuation(character), line))
+            }
+            character if character.is_whitespace() => {}
+            character if is_word(character) => {
+                let mut word = character.to_string();
+                loop {
+                    if let Some(next) = characters.next_if(|&next| is_word(next)) {
+                        word.push(next);
+                    } else if characters.peek() == Some(&':') {
+                        // Namespaces like `xformOp:translate`, but not the colon after a time sample
+                        let mut lookahead = characters.clone();
+                        lookahead.next();
+                        match lookahead.peek() {
+                            Some(&next) if next.is_alphabetic() || next == '_' => {
+                                characters.next();
+                                word.push(':');
+                            }
+                            _ => break,
+                        }
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push((Token::Word(word), line));
+            }
+            other => return Err(invalid(line, &format!("unexpected character {other:?}"))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position.min(self.tokens.len().saturating_sub(1)))
+            .map_or(0, |(_, line)| *line)
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek() == Some(&Token::Newline) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        self.skip_newlines();
+        match self.next() {
+            Some(Token::Punctuation(character)) if character == expected => Ok(()),
+            other => Err(invalid(
+                self.line(),
+                &format!("expected {expected:?}, found {other:?}"),
+            )),
+        }
+    }
+
+    /// Skip the tokens up to the end of the line, including everything in brackets.
+    fn skip_statement(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Newline if depth == 0 => break,
+                Token::Punctuation('(' | '[' | '{') => depth += 1,
+                Token::Punctuation(')' | ']' | '}') if depth == 0 => break,
+                Token::Punctuation(')' | ']' | '}') => depth -= 1,
+                _ => {}
+            }
+            self.position += 1;
+        }
+    }
+
+    /// Parse metadata `( key = value ... )` and return the entries with simple keys.
+    fn metadata(&mut self) -> Result<Vec<(String, Value)>, Error> {
+        self.expect('(')?;
+        let mut entries = Vec::new();
+        loop {
+            self.skip_newlines();
+            match self.peek() {
+                Some(Token::Punctuation(')')) => {
+                    self.position += 1;
+                    return Ok(entries);
+                }
+                Some(Token::Word(key))
+                    if self.tokens.get(self.position + 1).map(|(token, _)| token)
+                        == Some(&Token::Punctuation('=')) =>
+                {
+                    let key = key.clone();
+                    self.position += 2;
+                    entries.push((key, self.value()?));
+                }
+                Some(Token::Punctuation(';')) => self.position += 1,
+                Some(_) => self.skip_statement(),
+                None => return Err(invalid(self.line(), "unterminated metadata")),
+            }
+        }
+    }
+
+    /// Parse `def Type "Name" (...) { ... }` and its children into `prims`.
+    fn prim(&mut self, parent: Option<usize>, prims: &mut Vec<Prim>) -> Result<(), Error> {
+        self.next();
+        let mut type_name = String::new();
+        let name = loop {
+            match self.next() {
+                Some(Token::Word(word)) => type_name = word,
+                Some(Token::Text(name)) => break name,
+                other => {
+                    return Err(invalid(
+                        self.line(),
+                        &format!("expected a prim name, found {other:?}"),
+                    ))
+                }
+            }
+        };
+        let path = match parent {
+            Some(parent) => format!("{}/{name}", prims[parent].path),
+            None => format!("/{name}"),
+        };
+        let index = prims.len();
+        prims.push(Prim {
+            path,
+            type_name,
+            parent,
+            attributes: HashMap::new(),
+        });
+
+        self.skip_newlines();
+        if self.peek() == Some(&Token::Punctuation('(')) {
+            self.metadata()?;
+        }
+        self.expect('{')?;
+        loop {
+            self.skip_newlines();
+            match self.peek() {
+                Some(Token::Punctuation('}')) => {
+                    self.position += 1;
+                    return Ok(());
+                }
+                Some(Token::Word(word)) if is_specifier(word) => self.prim(Some(index), prims)?,
+                Some(_) => self.attribute(&mut prims[index])?,
+                None => return Err(invalid(self.line(), "unterminated prim")),
+            }
+        }
+    }
+
+    /// Parse an attribute `[uniform] type name[.timeSamples] = value` into `prim`, skipping everything else.
+    fn attribute(&mut self, prim: &mut Prim) -> Result<(), Error> {
+        let start = self.position;
+        let mut name = None;
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Word(word) => name = Some(word.clone()),
+                Token::Punctuation('[' | ']') => {}
+                _ => break,
+            }
+            self.position += 1;
+        }
+        let is_variant_set =
+            matches!(&self.tokens[start].0, Token::Word(word) if word == "variantSet");
+        match (name, self.peek()) {
+            (Some(name), Some(Token::Punctuation('='))) if !is_variant_set => {
+                self.position += 1;
+                let (name, samples) = match name.strip_suffix(".timeSamples") {
+                    Some(name) => (name.to_string(), true),
+                    None => (name, false),
+                };
+                let attribute = prim.attributes.entry(name).or_default();
+                if samples {
+                    attribute.samples = self.time_samples()?;
+                } else {
+                    attribute.default = Some(self.value()?);
+                }
+                // Metadata of the attribute
+                self.skip_statement();
+            }
+            _ => {
+                // Relationships without targets, variant sets, and anything else
+                self.position = start;
+                self.skip_statement();
+                if self.position == start {
+                    return Err(invalid(
+                        self.line(),
+                        &format!("unexpected {:?}", self.peek()),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse `{ time: value, ... }`.
+    fn time_samples(&mut self) -> Result<Vec<(f64, Value)>, Error> {
+        self.expect('{')?;
+        let mut samples = Vec::new();
+        loop {
+            self.skip_newlines();
+            match self.next() {
+                Some(Token::Punctuation('}')) => break,
+                Some(Token::Punctuation(',')) => {}
+                Some(Token::Word(time)) => {
+                    let time: f64 = time
+                        .parse()
+                        .map_err(|_| invalid(self.line(), &format!("invalid time code {time}")))?;
+                    self.expect(':')?;
+                    samples.push((time, self.value()?));
+                }
+                other => {
+                    return Err(invalid(
+                        self.line(),
+                        &format!("invalid time sample {other:?}"),
+                    ))
+                }
+            }
+        }
+        samples.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Ok(samples)
+    }
+
+    /// Parse a number, tuple, array, string, or token.
+    fn value(&mut self) -> Result<Value, Error> {
+        self.skip_newlines();
+        match self.next() {
+            Some(Token::Word(word)) => Ok(match word.parse() {
+                Ok(number) => Value::Number(number),
+                Err(_) => Value::Text(word),
+            }),
+            Some(Token::Text(text)) => Ok(Value::Text(text)),
+            Some(Token::Punctuation(open @ ('(' | '['))) => {
+                let close = if open == '(' { ')' } else { ']' };
+                let mut values = Vec::new();
+                loop {
+                    self.skip_newlines();
+                    match self.peek() {
+                        Some(Token::Punctuation(character)) if *character == close => {
+                            self.position += 1;
+                            return Ok(Value::List(values));
+                        }
+                        Some(Token::Punctuation(',')) => self.position += 1,
+                        Some(_) => values.push(self.value()?),
+                        None => return Err(invalid(self.line(), "unterminated list")),
+                    }
+                }
+            }
+            Some(Token::Punctuation('{')) => {
+                // Dictionaries are not needed.
+                let mut depth = 1;
+                while depth > 0 {
+                    match self.next() {
+                        Some(Token::Punctuation('{')) => depth += 1,
+                        Some(Token::Punctuation('}')) => depth -= 1,
+                        None => return Err(invalid(self.line(), "unterminated dictionary")),
+                        _ => {}
+                    }
+                }
+                Ok(Value::Text(String::new()))
+            }
+            other => Err(invalid(
+                self.line(),
+                &format!("expected a value, found {other:?}"),
+            )),
+        }
+    }
+}
+
+fn is_specifier(word: &str) -> bool {
+    matches!(word, "def" | "over" | "class")
+}
+
+fn invalid(line: usize, message: &str) -> Error {
+    Error::Config(format!("invalid USD: line {line}: {message}"))
+}
+
+// The cast is only necessary if `Float` is `f32`.
+#[allow(clippy::unnecessary_cast)]
+fn float(value: f64) -> Float {
+    value as Float
+}
+
+// The cast is only necessary if `Float` is `f32`.
+#[allow(clippy::unnecessary_cast)]
+fn double(value: Float) -> f64 {
+    value as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn xform_ops() {
+        let stage = UsdStage::parse(
+            r#"#usda 1.0
+(
+    defaultPrim = "World"
+    timeCodesPerSecond = 30
+)
+
+def Xform "World" (
+    kind = "assembly"
+)
+{
+    float xformOp:rotateY.timeSamples = {
+        0: 0,
+        10: 90,
+    }
+    uniform token[] xformOpOrder = ["xformOp:rotateY"]
+
+    def Mesh "Ball"
+    {
+        point3f[] points = [(0, 0, 0), (1, 0, 0), (0, 1, 0)]
+        rel material:binding = </World/Looks/Red>
+        matrix4d xformOp:transform = ( (1, 0, 0, 0), (0, 1, 0, 0), (0, 0, 1, 0), (2, 0, 0, 1) )
+        uniform token[] xformOpOrder = ["xformOp:transform"]
+    }
+}
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            stage.prim_paths().collect::<Vec<_>>(),
+            ["/World", "/World/Ball"]
+        );
+        assert_eq!(stage.time_codes_per_second(), 30.);
+
+        // Halfway through the rotation around the y axis
+        let transform = stage.transform("/World/Ball", 5.).unwrap();
+        let position = transform
+            .transform_point(&vector![0., 0., 0.].into())
+            .coords;
+        let angle = float::consts::FRAC_PI_4;
+        assert!((position - 2. * vector![angle.cos(), 0., -angle.sin()]).norm() < 1e-5);
+
+        // The offset places things like the transform, apart from scaling.
+        let offset = stage.offset("/World/Ball", 10.).unwrap();
+        let point = vector![0., 1., 0.5];
+        let expected = stage
+            .transform("/World/Ball", 10.)
+            .unwrap()
+            .transform_point(&point.into());
+        assert!((offset.to_world(point, 0.) - expected.coords).norm() < 1e-5);
+    }
+}
@@ -1,8 +1,61 @@
 //! Utility functions on [`Vector3`].
+//!
+//! These are the building blocks of the [`materials`](crate::materials) and can be used for custom ones.
+
+use std::ops::{Index, IndexMut};
 
 use nalgebra::Vector3;
 use rand::Rng;
 
+use crate::Float;
+
+/// Axis of the coordinate system to index a [`Vector3`] with.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, vec3::*};
+/// let mut v = vector![1., 2., 3.];
+/// v[Axis::Z] = 4.;
+/// assert_eq!(v[Axis::Y], 2.);
+/// assert_eq!(Axis::ALL.map(|axis| v[axis]), [1., 2., 4.]);
+/// assert_eq!(Axis::longest(&v), Axis::Z);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    /// All axes in order.
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    /// Index of the axis (0 for x, 1 for y, and 2 for z).
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Axis along which `vec` has the largest absolute component.
+    pub fn longest(vec: &Vector3<Float>) -> Self {
+        Self::ALL[vec.iamax()]
+    }
+}
+
+impl Index<Axis> for Vector3<Float> {
+    type Output = Float;
+
+    fn index(&self, axis: Axis) -> &Float {
+        &self[axis.index()]
+    }
+}
+
+impl IndexMut<Axis> for Vector3<Float> {
+    fn index_mut(&mut self, axis: Axis) -> &mut Float {
+        &mut self[axis.index()]
+    }
+}
+
 /// Reflects the vector.
 ///
 /// The reflection follows the rule of equal angles with respect to `normal`.
@@ -14,7 +67,7 @@ use rand::Rng;
 /// let normal = v;
 /// assert!((reflect(&v, &normal) + v).norm() < 0.01);
 /// ```
-pub fn reflect(vec: &Vector3<f32>, normal: &Vector3<f32>) -> Vector3<f32> {
+pub fn reflect(vec: &Vector3<Float>, normal: &Vector3<Float>) -> Vector3<Float> {
     *vec - 2. * vec.dot(normal) * *normal
 }
 
@@ -29,8 +82,12 @@ pub fn reflect(vec: &Vector3<f32>, normal: &Vector3<f32>) -> Vector3<f32> {
 /// let normal = v;
 /// assert!((refract(&v, &normal, 1.) + normal).norm() < 0.01);
 /// ```
-pub fn refract(vec: &Vector3<f32>, normal: &Vector3<f32>, etai_over_etat: f32) -> Vector3<f32> {
-    let cos_theta = f32::min(-vec.dot(normal), 1.);
+pub fn refract(
+    vec: &Vector3<Float>,
+    normal: &Vector3<Float>,
+    etai_over_etat: Float,
+) -> Vector3<Float> {
+    let cos_theta = Float::min(-vec.dot(normal), 1.);
     let refracted_out_perp = etai_over_etat * (*vec + cos_theta * *normal);
     let refracted_out_parallel = -(1. - refracted_out_perp.norm_squared()).abs().sqrt() * *normal;
     refracted_out_perp + refracted_out_parallel
@@ -44,28 +101,48 @@ pub fn refract(vec: &Vector3<f32>, normal: &Vector3<f32>, etai_over_etat: f32) -
 /// let v = vector![1e-9, 1e-9, 1e-9];
 /// assert_eq!(near_zero(&v), true);
 /// ```
-pub fn near_zero(vec: &Vector3<f32>) -> bool {
+pub fn near_zero(vec: &Vector3<Float>) -> bool {
     let s = 1e-8;
     vec.x.abs() < s && vec.y.abs() < s && vec.z.abs() < s
 }
 
+/// Minimum of each component of `a` and `b`.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, vec3::*};
+/// let a = vector![1., 5., 3.];
+/// let b = vector![2., 4., 3.];
+/// assert_eq!(component_min(&a, &b), vector![1., 4., 3.]);
+/// assert_eq!(component_max(&a, &b), vector![2., 5., 3.]);
+/// ```
+pub fn component_min(a: &Vector3<Float>, b: &Vector3<Float>) -> Vector3<Float> {
+    a.inf(b)
+}
+
+/// Maximum of each component of `a` and `b`.
+pub fn component_max(a: &Vector3<Float>, b: &Vector3<Float>) -> Vector3<Float> {
+    a.sup(b)
+}
+
 /// Creates a random vector with each element between 0 and 1.
-pub fn random_vector() -> Vector3<f32> {
-    let mut rng = rand::thread_rng();
+pub fn random_vector() -> Vector3<Float> {
+    let mut rng = crate::random::rng();
     Vector3::new(rng.gen(), rng.gen(), rng.gen())
 }
 
 /// Creates a random vector with each element in a range.
-pub fn random_vector_in_range(min: f32, max: f32) -> Vector3<f32> {
-    let mut rng = rand::thread_rng();
+pub fn random_vector_in_range(min: Float, max: Float) -> Vector3<Float> {
+    let mut rng = crate::random::rng();
     Vector3::new(
-        min + rng.gen::<f32>() * (max - min),
-        min + rng.gen::<f32>() * (max - min),
-        min + rng.gen::<f32>() * (max - min),
+        min + rng.gen::<Float>() * (max - min),
+        min + rng.gen::<Float>() * (max - min),
+        min + rng.gen::<Float>() * (max - min),
     )
 }
 
-pub fn random_vector_in_unit_sphere() -> Vector3<f32> {
+/// Creates a random vector uniformly distributed inside the unit sphere.
+pub fn random_vector_in_unit_sphere() -> Vector3<Float> {
     loop {
         let rand = random_vector_in_range(-1., 1.);
         if rand.norm_squared() < 1. {
@@ -74,11 +151,25 @@ pub fn random_vector_in_unit_sphere() -> Vector3<f32> {
     }
 }
 
-pub fn random_unit_vector_in_unit_sphere() -> Vector3<f32> {
+/// Creates a random unit vector uniformly distributed on the unit sphere.
+pub fn random_unit_vector() -> Vector3<Float> {
     random_vector_in_unit_sphere().normalize()
 }
 
-pub fn random_vector_in_hemisphere(normal: &Vector3<f32>) -> Vector3<f32> {
+/// Creates a random unit vector uniformly distributed on the unit sphere (same as [`random_unit_vector`]).
+pub fn random_unit_vector_in_unit_sphere() -> Vector3<Float> {
+    random_unit_vector()
+}
+
+/// Creates a random vector inside the unit sphere on the side of `normal`.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, vec3::*};
+/// let normal = vector![0., 1., 0.];
+/// assert!(random_vector_in_hemisphere(&normal).dot(&normal) >= 0.);
+/// ```
+pub fn random_vector_in_hemisphere(normal: &Vector3<Float>) -> Vector3<Float> {
     let rand = random_vector_in_unit_sphere();
     if rand.dot(normal) > 0. {
         return rand;
@@ -86,12 +177,39 @@ pub fn random_vector_in_hemisphere(normal: &Vector3<f32>) -> Vector3<f32> {
     -rand
 }
 
-pub fn random_vector_in_unit_disk() -> Vector3<f32> {
-    let mut rng = rand::thread_rng();
+/// Creates a random vector uniformly distributed inside the unit disk in the xy plane.
+pub fn random_vector_in_unit_disk() -> Vector3<Float> {
+    let mut rng = crate::random::rng();
     loop {
-        let rand = Vector3::new(-1. + rng.gen::<f32>() * 2., -1. + rng.gen::<f32>() * 2., 0.);
+        let rand = Vector3::new(
+            -1. + rng.gen::<Float>() * 2.,
+            -1. + rng.gen::<Float>() * 2.,
+            0.,
+        );
         if rand.norm_squared() < 1. {
             return rand;
         }
     }
 }
+
+/// Creates two unit vectors that form an orthonormal basis together with the unit vector `normal`.
+pub fn orthonormal_basis(normal: &Vector3<Float>) -> (Vector3<Float>, Vector3<Float>) {
+    let helper = if normal.x.abs() > 0.9 {
+        Vector3::y()
+    } else {
+        Vector3::x()
+    };
+    let tangent = normal.cross(&helper).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
+
+/// Creates a random unit vector uniformly distributed inside the cone around the unit vector `axis` with opening angle `acos(cos_max)`.
+pub fn random_vector_in_cone(axis: &Vector3<Float>, cos_max: Float) -> Vector3<Float> {
+    let mut rng = crate::random::rng();
+    let cos_theta = 1. - rng.gen::<Float>() * (1. - cos_max);
+    let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+    let phi = 2. * crate::float::consts::PI * rng.gen::<Float>();
+    let (tangent, bitangent) = orthonormal_basis(axis);
+    sin_theta * phi.cos() * tangent + sin_theta * phi.sin() * bitangent + cos_theta * *axis
+}
@@ -0,0 +1,216 @@
+//! Encoding of rendered animations directly into a video file instead of one image per frame.
+//!
+//! Animated GIFs are written with [`image`]. All other formats (e.g. `.mp4`, `.webm`, `.mov`) are encoded by piping the raw frames into an `ffmpeg` subprocess, which has to be installed and in the `PATH`.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbImage};
+
+use crate::error::Error;
+use crate::raytracer::{buffer_error, RaytracedImage};
+use crate::*;
+
+enum Backend {
+    Gif(GifEncoder<BufWriter<File>>),
+    Ffmpeg(Child, ChildStdin),
+}
+
+/// Writes frames of the same size one after another into a video file.
+///
+/// The format is chosen by the extension of the path. The file is complete once [`finish`](VideoEncoder::finish) returns.
+///
+/// # Example
+/// ```no_run
+/// # use ray_tracing_in_one_weekend::{*, scenes::{self, SceneSettings}, video::VideoEncoder};
+/// let settings = SceneSettings::default();
+/// let mut video = VideoEncoder::create("cornell_box.mp4", settings.image_width, settings.image_height, 24.).unwrap();
+/// for _ in 0..48 {
+///     video.push_frame(scenes::cornell_box(&settings).render().unwrap()).unwrap();
+/// }
+/// video.finish().unwrap();
+/// ```
+pub struct VideoEncoder {
+    backend: Backend,
+    image_width: u32,
+    image_height: u32,
+    frames_per_second: Float,
+    frames: usize,
+}
+
+impl VideoEncoder {
+    /// Create the video at `path` for frames of `image_width` × `image_height` pixels shown at `frames_per_second`.
+    ///
+    /// Fails if the file cannot be created, `ffmpeg` cannot be started, or the settings are invalid.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        image_width: u32,
+        image_height: u32,
+        frames_per_second: Float,
+    ) -> Result<Self, Error> {
+        if image_width == 0 || image_height == 0 {
+            return Err(Error::Config(
+                "a video needs at least one pixel".to_string(),
+            ));
+        }
+        if frames_per_second <= 0. || !frames_per_second.is_finite() {
+            return Err(Error::Config(format!(
+                "invalid frame rate {frames_per_second}"
+            )));
+        }
+
+        let path = path.as_ref();
+        let is_gif = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+        let backend = if is_gif {
+            let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+            encoder.set_repeat(Repeat::Infinite)?;
+            Backend::Gif(encoder)
+        } else {
+            // Most players only support even sizes with chroma subsampling, so odd sizes are padded.
+            let mut child = Command::new("ffmpeg")
+                .args([
+                    "-y",
+                    "-loglevel",
+                    "error",
+                    "-f",
+                    "rawvideo",
+                    "-pix_fmt",
+                    "rgb24",
+                ])
+                .args(["-s", &format!("{image_width}x{image_height}")])
+                .args(["-r", &frames_per_second.to_string(), "-i", "-"])
+                .args([
+                    "-vf",
+                    "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+                    "-pix_fmt",
+                    "yuv420p",
+                ])
+                .arg(path)
+                .stdin(Stdio::piped())
+                .spawn()?;
+            let stdin = child.stdin.take().expect("stdin of ffmpeg is piped");
+            Backend::Ffmpeg(child, stdin)
+        };
+        Ok(Self {
+            backend,
+            image_width,
+            image_height,
+            frames_per_second,
+            frames: 0,
+        })
+    }
+
+    /// Append a rendered frame.
+    ///
+    /// Fails if the frame does not have the size of the video or cannot be encoded.
+    pub fn push_frame(&mut self, frame: RaytracedImage) -> Result<(), Error> {
+        let image = frame.into_image().ok_or_else(buffer_error)?;
+        self.push_image(&image)
+    }
+
+    /// Append a frame that is already tone mapped, e.g. one loaded from a PNG.
+    pub fn push_image(&mut self, image: &RgbImage) -> Result<(), Error> {
+        if image.dimensions() != (self.image_width, self.image_height) {
+            return Err(Error::Config(format!(
+                "frame of size {:?} does not match the video of size {:?}",
+                image.dimensions(),
+                (self.image_width, self.image_height)
+            )));
+        }
+        let delay = self.delay();
+        match &mut self.backend {
+            Backend::Gif(encoder) => {
+                let rgba = image::DynamicImage::ImageRgb8(image.clone()).into_rgba8();
+                encoder.encode_frame(Frame::from_parts(rgba, 0, 0, delay))?;
+            }
+            Backend::Ffmpeg(_, stdin) => stdin.write_all(image.as_raw())?,
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Number of frames pushed so far.
+    pub fn frame_count(&self) -> usize {
+        self.frames
+    }
+
+    /// Flush the remaining frames and close the file, waiting for `ffmpeg` to finish.
+    pub fn finish(self) -> Result<(), Error> {
+        match self.backend {
+            // The trailer is written when the encoder is dropped.
+            Backend::Gif(encoder) => drop(encoder),
+            Backend::Ffmpeg(mut child, stdin) => {
+                drop(stdin);
+                let status = child.wait()?;
+                if !status.success() {
+                    return Err(Error::Io(std::io::Error::other(format!(
+                        "ffmpeg exited with {status}"
+                    ))));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn delay(&self) -> Delay {
+        // In microseconds, as the frame rate does not have to be an integer.
+        let delay = (1_000_000. / self.frames_per_second).round() as u32;
+        Delay::from_numer_denom_ms(delay, 1000)
+    }
+}
+
+/// Render each [`Raytracer`] of `frames` and encode the images into the video at `path`, see [`VideoEncoder`].
+///
+/// The frames are rendered lazily, so a scene can be set up for each frame while iterating, e.g. from a [camera path](crate::usd::UsdStage::camera).
+pub fn render_animation<I, P>(frames: I, path: P, frames_per_second: Float) -> Result<(), Error>
+where
+    I: IntoIterator<Item = Raytracer>,
+    P: AsRef<Path>,
+{
+    let mut frames = frames.into_iter().peekable();
+    let Some(first) = frames.peek() else {
+        return Err(Error::Config(
+            "an animation needs at least one frame".to_string(),
+        ));
+    };
+    let (image_width, image_height) = first.image_size();
+    let mut video = VideoEncoder::create(path, image_width, image_height, frames_per_second)?;
+    for frame in frames {
+        video.push_frame(frame.render()?)?;
+    }
+    video.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+
+    use super::*;
+
+    #[test]
+    fn gif_frames() {
+        let path = std::env::temp_dir().join("video_gif_frames.gif");
+        let mut video = VideoEncoder::create(&path, 4, 3, 10.).unwrap();
+        for shade in [0, 128, 255] {
+            video
+                .push_image(&RgbImage::from_pixel(4, 3, image::Rgb([shade; 3])))
+                .unwrap();
+        }
+        assert!(video.push_image(&RgbImage::new(3, 3)).is_err());
+        assert_eq!(video.frame_count(), 3);
+        video.finish().unwrap();
+
+        let decoder = GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].delay().numer_denom_ms(), (100, 1));
+        assert_eq!(frames[2].buffer().get_pixel(1, 1).0, [255; 4]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,870 @@
+//! Heterogeneous volumes whose density varies in space, e.g. clouds, fog or smoke simulations.
+//!
+//! A [`DensityGrid`] is built in code or loaded from a raw file or an NRRD file. OpenVDB files are not supported directly, but most tools that write them (e.g. Blender or Houdini) can also export dense grids in one of these formats.
+//! A [`GridMedium`] fills a box with the grid and scatters [`Ray`]s inside it like a [`ConstantMedium`](crate::shapes::ConstantMedium) with varying density.
+//! A [`HeterogeneousMedium`] fills any convex boundary with a [`DensityField`], e.g. a grid or [`NoiseDensity`].
+//!
+//! Both sample the distance to the next collision by delta tracking and scatter there with a phase function, which is a [`Material`] like [`Isotropic`] or the anisotropic [`HenyeyGreenstein`](crate::materials::HenyeyGreenstein).
+//! The path tracer therefore handles them like any other [`Hittable`], including sampling lights from inside of them.
+//! [`Fog`] is a homogeneous medium that is not part of the world but fills the whole scene, so that light shafts become visible.
+
+use std::fmt::Debug;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use nalgebra::Rotation3;
+use rand::Rng;
+
+use crate::error::Error;
+use crate::hitrecord::HitRecord;
+use crate::hittable::Aabb;
+use crate::materials::{HenyeyGreenstein, Isotropic, Material};
+use crate::perlin::Perlin;
+use crate::ray::Ray;
+use crate::shapes::{Movable, Offset};
+use crate::textures::SolidColor;
+use crate::*;
+
+/// Densities on a regular grid of voxels in the unit cube.
+///
+/// The densities are stored with x changing fastest, then y, then z, and are interpolated trilinearly between the voxel centers.
+///
+/// # Fields
+/// - `resolution`: Number of voxels along x, y and z.
+/// - `densities`: Density of each voxel.
+/// - `max_density`: Largest density, which bounds the density everywhere.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, volume::DensityGrid};
+/// // A soft ball
+/// let grid = DensityGrid::from_fn((16, 16, 16), |point| (1. - 2. * (point - vector![0.5, 0.5, 0.5]).norm()).max(0.));
+/// assert!(grid.density_at(vector![0.5, 0.5, 0.5]) > 0.8);
+/// assert_eq!(grid.density_at(vector![0., 0., 0.]), 0.);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct DensityGrid {
+    resolution: (usize, usize, usize),
+    densities: Vec<Float>,
+    max_density: Float,
+}
+
+impl DensityGrid {
+    /// Create a grid from the densities of its voxels.
+    ///
+    /// Negative densities are clamped to 0. This fails if there is not exactly one density per voxel.
+    pub fn new(resolution: (usize, usize, usize), densities: Vec<Float>) -> Result<Self, Error> {
+        let Some(voxels) = resolution
+            .0
+            .checked_mul(resolution.1)
+            .and_then(|voxels| voxels.checked_mul(resolution.2))
+        else {
+            return Err(Error::Config(format!(
+                "density grid of resolution {resolution:?} has too many voxels"
+            )));
+        };
+        if voxels == 0 || densities.len() != voxels {
+            return Err(Error::Config(format!(
+                "density grid of resolution {resolution:?} needs {voxels} densities, but got {}",
+                densities.len()
+            )));
+        }
+
+        let densities: Vec<Float> = densities
+            .into_iter()
+            .map(|density| density.max(0.))
+            .collect();
+        let max_density = densities.iter().copied().fold(0., Float::max);
+        Ok(Self {
+            resolution,
+            densities,
+            max_density,
+        })
+    }
+
+    /// Create a grid by evaluating `density` at the center of every voxel in the unit cube.
+    pub fn from_fn(
+        resolution: (usize, usize, usize),
+        density: impl Fn(Vector3<Float>) -> Float,
+    ) -> Self {
+        let (nx, ny, nz) = resolution;
+        let densities = (0..nz)
+            .flat_map(|z| (0..ny).flat_map(move |y| (0..nx).map(move |x| (x, y, z))))
+            .map(|(x, y, z)| {
+                density(vector![
+                    (x as Float + 0.5) / nx as Float,
+                    (y as Float + 0.5) / ny as Float,
+                    (z as Float + 0.5) / nz as Float
+                ])
+            })
+            .collect();
+        Self::new(resolution, densities).expect("there is one density per voxel")
+    }
+
+    /// Read a grid of little-endian 32 bit floats without a header, with x changing fastest.
+    pub fn read_raw<R: Read>(
+        mut reader: R,
+        resolution: (usize, usize, usize),
+    ) -> Result<Self, Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::new(resolution, decode(&bytes, VoxelType::F32, true)?)
+    }
+
+    /// Open a three-dimensional NRRD file with raw encoding.
+    ///
+    /// The data can follow the header or be in a detached data file. Integer voxels are normalized to \[0,1\], float voxels are used as they are.
+    pub fn open_nrrd<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+        parse_nrrd(&bytes, path.parent())
+    }
+
+    /// Number of voxels along x, y and z.
+    pub fn resolution(&self) -> (usize, usize, usize) {
+        self.resolution
+    }
+
+    /// Largest density of all voxels.
+    pub fn max_density(&self) -> Float {
+        self.max_density
+    }
+
+    /// Density of the voxel (`x`, `y`, `z`), clamped to the grid.
+    fn voxel(&self, x: isize, y: isize, z: isize) -> Float {
+        let (nx, ny, nz) = self.resolution;
+        let clamp = |i: isize, n: usize| i.clamp(0, n as isize - 1) as usize;
+        self.densities[(clamp(z, nz) * ny + clamp(y, ny)) * nx + clamp(x, nx)]
+    }
+
+    /// Trilinearly interpolated density at `point` in the unit cube, or 0 outside of it.
+    pub fn density_at(&self, point: Vector3<Float>) -> Float {
+        if point
+            .iter()
+            .any(|&coordinate| !(0. ..=1.).contains(&coordinate))
+        {
+            return 0.;
+        }
+
+        let (nx, ny, nz) = self.resolution;
+        // Position relative to the voxel centers
+        let x = point.x * nx as Float - 0.5;
+        let y = point.y * ny as Float - 0.5;
+        let z = point.z * nz as Float - 0.5;
+        let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+        let (tx, ty, tz) = (x - x0, y - y0, z - z0);
+        let (x0, y0, z0) = (x0 as isize, y0 as isize, z0 as isize);
+
+        let lerp = |a: Float, b: Float, t: Float| a + t * (b - a);
+        let row = |y: isize, z: isize| lerp(self.voxel(x0, y, z), self.voxel(x0 + 1, y, z), tx);
+        let slice = |z: isize| lerp(row(y0, z), row(y0 + 1, z), ty);
+        lerp(slice(z0), slice(z0 + 1), tz)
+    }
+}
+
+/// Types of voxels in NRRD files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum VoxelType {
+    U8,
+    U16,
+    F32,
+    F64,
+}
+
+impl VoxelType {
+    fn size(self) -> usize {
+        match self {
+            VoxelType::U8 => 1,
+            VoxelType::U16 => 2,
+            VoxelType::F32 => 4,
+            VoxelType::F64 => 8,
+        }
+    }
+}
+
+/// Decode the voxels in `bytes`, normalizing integers to \[0,1\].
+// The casts are only necessary if `Float` is `f64`.
+#[allow(clippy::unnecessary_cast)]
+fn decode(bytes: &[u8], voxel_type: VoxelType, little_endian: bool) -> Result<Vec<Float>, Error> {
+    let size = voxel_type.size();
+    if !bytes.len().is_multiple_of(size) {
+        return Err(Error::Config(format!(
+            "{} bytes of voxel data are not a multiple of the voxel size {size}",
+            bytes.len()
+        )));
+    }
+
+    let chunks = bytes.chunks_exact(size);
+    Ok(match voxel_type {
+        VoxelType::U8 => chunks.map(|bytes| bytes[0] as Float / 255.).collect(),
+        VoxelType::U16 => chunks
+            .map(|bytes| {
+                let bytes = [bytes[0], bytes[1]];
+                let value = if little_endian {
+                    u16::from_le_bytes(bytes)
+                } else {
+                    u16::from_be_bytes(bytes)
+                };
+                value as Float / u16::MAX as Float
+            })
+            .collect(),
+        VoxelType::F32 => chunks
+            .map(|bytes| {
+                let bytes = bytes.try_into().expect("the chunks have 4 bytes");
+                let value = if little_endian {
+                    f32::from_le_bytes(bytes)
+                } else {
+                    f32::from_be_bytes(bytes)
+                };
+                value as Float
+            })
+            .collect(),
+        VoxelType::F64 => chunks
+            .map(|bytes| {
+                let bytes = bytes.try_into().expect("the chunks have 8 bytes");
+                let value = if little_endian {
+                    f64::from_le_bytes(bytes)
+                } else {
+                    f64::from_be_bytes(bytes)
+                };
+                value as Float
+            })
+            .collect(),
+    })
+}
+
+/// Parse an NRRD file, whose detached data file is relative to `directory`.
+fn parse_nrrd(bytes: &[u8], directory: Option<&Path>) -> Result<DensityGrid, Error> {
+    let invalid = |message: String| Error::Config(format!("invalid NRRD file: {message}"));
+
+    if !bytes.starts_with(b"NRRD") {
+        return Err(invalid("missing magic".to_string()));
+    }
+    // The header ends with an empty line.
+    let header_end = bytes
+        .windows(2)
+        .position(|window| window == b"\n\n")
+        .ok_or_else(|| invalid("missing end of header".to_string()))?;
+    let header = std::str::from_utf8(&bytes[..header_end])
+        .map_err(|_| invalid("header is not UTF-8".to_string()))?;
+
+    let mut voxel_type = None;
+    let mut sizes = None;
+    let mut little_endian = true;
+    let mut data_file = None;
+    for line in header.lines().skip(1) {
+        if line.starts_with('#') {
+            continue;
+        }
+        // Key-value pairs (`key:=value`) are metadata.
+        let Some((field, value)) = line.split_once(": ") else {
+            continue;
+        };
+        let value = value.trim();
+        match field.trim() {
+            "type" => {
+                voxel_type = Some(match value {
+                    "uchar" | "unsigned char" | "uint8" | "uint8_t" => VoxelType::U8,
+                    "ushort" | "unsigned short" | "unsigned short int" | "uint16" | "uint16_t" => {
+                        VoxelType::U16
+                    }
+                    "float" => VoxelType::F32,
+                    "double" => VoxelType::F64,
+                    _ => return Err(invalid(format!("unsupported type {value:?}"))),
+                })
+            }
+            "dimension" if value != "3" => {
+                return Err(invalid(format!("dimension {value} instead of 3")))
+            }
+            "sizes" => {
+                let parsed: Result<Vec<usize>, _> =
+                    value.split_whitespace().map(str::parse).collect();
+                match parsed.as_deref() {
+                    Ok(&[x, y, z]) => sizes = Some((x, y, z)),
+                    _ => return Err(invalid(format!("sizes {value:?}"))),
+                }
+            }
+            "encoding" if value != "raw" => {
+                return Err(invalid(format!("unsupported encoding {value:?}")))
+            }
+            "endian" => little_endian = value == "little",
+            "data file" | "datafile" => data_file = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    let voxel_type = voxel_type.ok_or_else(|| invalid("missing type".to_string()))?;
+    let sizes = sizes.ok_or_else(|| invalid("missing sizes".to_string()))?;
+    let densities = match data_file {
+        Some(file) => {
+            let path = directory.unwrap_or(Path::new("")).join(file);
+            decode(&fs::read(path)?, voxel_type, little_endian)?
+        }
+        None => decode(&bytes[header_end + 2..], voxel_type, little_endian)?,
+    };
+    DensityGrid::new(sizes, densities)
+}
+
+/// Spatially varying density of a [`HeterogeneousMedium`].
+pub trait DensityField: Debug + Send + Sync {
+    /// Density at `point`, which is never negative.
+    fn density_at(&self, point: Vector3<Float>) -> Float;
+
+    /// Upper bound of the density everywhere. The tighter it is, the fewer steps delta tracking takes.
+    fn max_density(&self) -> Float;
+}
+
+/// The grid fills the unit cube and is empty outside of it.
+impl DensityField for DensityGrid {
+    fn density_at(&self, point: Vector3<Float>) -> Float {
+        DensityGrid::density_at(self, point)
+    }
+
+    fn max_density(&self) -> Float {
+        DensityGrid::max_density(self)
+    }
+}
+
+/// Density following Perlin turbulence, e.g. for fog or wisps of smoke.
+///
+/// # Fields
+/// - `noise`: [`Perlin`] noise generator.
+/// - `scale`: Frequency of the noise.
+/// - `octaves`: Number of octaves of the turbulence.
+/// - `density`: Density where the turbulence is largest.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, volume::{DensityField, NoiseDensity}};
+/// let fog = NoiseDensity::new(4., 2.).with_octaves(3);
+/// let density = fog.density_at(vector![0.3, 0.7, 0.1]);
+/// assert!((0. ..=fog.max_density()).contains(&density));
+/// ```
+#[derive(Clone, Debug)]
+pub struct NoiseDensity {
+    noise: Perlin,
+    scale: Float,
+    octaves: u8,
+    density: Float,
+}
+
+impl NoiseDensity {
+    pub fn new(scale: Float, density: Float) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+            octaves: 7,
+            density,
+        }
+    }
+
+    /// Use `octaves` octaves (7 by default). Fewer octaves give smoother clouds.
+    pub fn with_octaves(mut self, octaves: u8) -> Self {
+        self.octaves = octaves;
+        self
+    }
+}
+
+impl DensityField for NoiseDensity {
+    fn density_at(&self, point: Vector3<Float>) -> Float {
+        // The turbulence can slightly exceed 1, which would break the majorant.
+        let turbulence = self.noise.turbulance(self.scale * point, self.octaves);
+        self.density * turbulence.min(1.)
+    }
+
+    fn max_density(&self) -> Float {
+        self.density
+    }
+}
+
+/// Parameter of the first collision of `ray` with a medium between `t0` and `t1`, found by delta tracking.
+///
+/// Tentative distances are sampled with the `majorant` and accepted with the ratio of the actual `density` to it.
+fn delta_tracking(
+    ray: Ray,
+    (t0, t1): (Float, Float),
+    majorant: Float,
+    density: impl Fn(Vector3<Float>) -> Float,
+) -> Option<Float> {
+    if majorant <= 0. {
+        return None;
+    }
+
+    let mut rng = crate::random::rng();
+    let step = 1. / (majorant * ray.direction().norm());
+    let mut t = t0;
+    loop {
+        t -= (1. - rng.gen::<Float>()).ln() * step;
+        if t >= t1 {
+            return None;
+        }
+        if rng.gen::<Float>() * majorant < density(ray.at(t)) {
+            return Some(t);
+        }
+    }
+}
+
+/// Fraction of the light that passes through a medium along `ray` between `t0` and `t1`, estimated by ratio tracking.
+///
+/// This steps through the medium like [`delta_tracking`] but multiplies the ratios of the densities instead of stopping at the first collision.
+fn ratio_tracking(
+    ray: Ray,
+    (t0, t1): (Float, Float),
+    majorant: Float,
+    density: impl Fn(Vector3<Float>) -> Float,
+) -> Float {
+    if majorant <= 0. {
+        return 1.;
+    }
+
+    let mut rng = crate::random::rng();
+    let step = 1. / (majorant * ray.direction().norm());
+    let mut transmittance = 1.;
+    let mut t = t0;
+    loop {
+        t -= (1. - rng.gen::<Float>()).ln() * step;
+        if t >= t1 {
+            return transmittance;
+        }
+        transmittance *= 1. - density(ray.at(t)) / majorant;
+    }
+}
+
+/// Hit record of a collision inside a medium, which has no normal or texture coordinates.
+fn collision<M: Material>(ray: Ray, t: Float, phase_function: &M) -> HitRecord<'_> {
+    HitRecord::new(ray.at(t), 0., 0., Vector3::zeros(), t, true, phase_function)
+}
+
+/// A box filled with a participating medium whose density is given by a [`DensityGrid`].
+///
+/// Collisions are found by delta tracking: tentative distances are sampled with the largest density in the grid and accepted with the ratio of the actual to the largest density.
+/// This is unbiased for any grid, but dense grids with a few very dense voxels need many steps.
+///
+/// # Fields
+/// - `grid`: The [`DensityGrid`], stretched over the box.
+/// - `size`: Size of the box.
+/// - `density`: Density the values of the grid are multiplied with.
+/// - `phase_function`: [`Material`] that scatters at collisions, e.g. [`Isotropic`] or [`HenyeyGreenstein`](crate::materials::HenyeyGreenstein).
+/// - `center`: Position of the minimum corner of the box.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, ray::Ray, volume::{DensityGrid, GridMedium}};
+/// let grid = DensityGrid::from_fn((8, 8, 8), |point| if point.y < 0.5 { 1. } else { 0. });
+/// let smoke = GridMedium::solid_color(grid, vector![-1., -1., -1.], vector![1., 1., 1.], color![0.8, 0.8, 0.8], 100.);
+///
+/// // The upper half is empty, the lower half almost opaque.
+/// assert!(smoke.hit(Ray::new(vector![-2., 0.5, 0.], vector![1., 0., 0.]), 0., Float::INFINITY).is_none());
+/// assert!(smoke.hit(Ray::new(vector![-2., -0.5, 0.], vector![1., 0., 0.]), 0., Float::INFINITY).is_some());
+/// assert!(smoke.transmittance(Ray::new(vector![-2., -0.5, 0.], vector![1., 0., 0.]), 0., Float::INFINITY) < 0.01);
+/// ```
+#[derive(Clone, Debug)]
+pub struct GridMedium<M: Material> {
+    grid: DensityGrid,
+    size: Vector3<Float>,
+    density: Float,
+    phase_function: M,
+    center: Offset,
+}
+
+impl<M: Material> GridMedium<M> {
+    /// Fill the box from `minimum` to `maximum` with `grid`, whose values are multiplied with `density`, and scatter with `phase_function`.
+    pub fn new(
+        grid: DensityGrid,
+        minimum: Vector3<Float>,
+        maximum: Vector3<Float>,
+        phase_function: M,
+        density: Float,
+    ) -> Self {
+        Self {
+            grid,
+            size: maximum - minimum,
+            density,
+            phase_function,
+            center: Offset::new(minimum),
+        }
+    }
+
+    pub fn grid(&self) -> &DensityGrid {
+        &self.grid
+    }
+
+    /// Density at `point` relative to the minimum corner of the box.
+    fn density_at(&self, point: Vector3<Float>) -> Float {
+        self.density * self.grid.density_at(point.component_div(&self.size))
+    }
+
+    /// Parameter range of `ray` relative to the box inside of it and the allowed range.
+    fn interval(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<(Float, Float)> {
+        Aabb::new(Vector3::zeros(), self.size).intersect(ray, t_min.max(0.), t_max)
+    }
+
+    /// Fraction of the light that passes through the medium along `ray` in world coordinates between `t_min` and `t_max`.
+    ///
+    /// This is estimated by ratio tracking, which steps through the medium like delta tracking but multiplies the ratios of the densities instead of stopping at the first collision.
+    /// Unlike testing for a [`hit`](Hittable::hit), this gives fractional values, so shadows of thin smoke are less noisy.
+    pub fn transmittance(&self, ray: Ray, t_min: Float, t_max: Float) -> Float {
+        let ray = self.center.ray_to_local(ray);
+        let Some(interval) = self.interval(ray, t_min, t_max) else {
+            return 1.;
+        };
+        let majorant = self.density * self.grid.max_density();
+        ratio_tracking(ray, interval, majorant, |point| self.density_at(point))
+    }
+}
+
+impl GridMedium<Isotropic<SolidColor>> {
+    pub fn solid_color(
+        grid: DensityGrid,
+        minimum: Vector3<Float>,
+        maximum: Vector3<Float>,
+        color: Color,
+        density: Float,
+    ) -> Self {
+        Self::new(
+            grid,
+            minimum,
+            maximum,
+            Isotropic::solid_color(color),
+            density,
+        )
+    }
+}
+
+impl<M: Material + Clone + 'static> Hittable for GridMedium<M> {
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let interval = self.interval(ray, t_min, t_max)?;
+        let majorant = self.density * self.grid.max_density();
+        let t = delta_tracking(ray, interval, majorant, |point| self.density_at(point))?;
+        Some(collision(ray, t, &self.phase_function))
+    }
+
+    fn bounding_box_origin(&self, _time0: Float, _time1: Float) -> Option<Aabb> {
+        Some(Aabb::new(Vector3::zeros(), self.size))
+    }
+
+    fn center(&self) -> &Offset {
+        &self.center
+    }
+}
+
+impl<M: Material + Clone + 'static> Movable for GridMedium<M> {
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.center = self.center.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.center = self.center.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+/// A participating medium inside a convex boundary whose density is given by any [`DensityField`].
+///
+/// Like [`ConstantMedium`](crate::shapes::ConstantMedium), it scatters [`Ray`]s at randomly sampled distances inside the boundary, which the path tracer handles like hits of a surface.
+/// The distances are sampled by delta tracking with the [`max_density`](DensityField::max_density) of the field, so the density can vary arbitrarily.
+///
+/// # Fields
+/// - `boundary`: Convex [`Hittable`] that encloses the medium. Its position and rotation are also those of the medium.
+/// - `density_field`: [`DensityField`] in the coordinates of the boundary, i.e. relative to its [`center`](Hittable::center).
+/// - `phase_function`: [`Material`] that scatters at collisions, e.g. [`Isotropic`] or [`HenyeyGreenstein`](crate::materials::HenyeyGreenstein).
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::HenyeyGreenstein, shapes::Sphere, volume::{HeterogeneousMedium, NoiseDensity}};
+/// let boundary = Sphere::new(vector![0., 1., 0.], 1., materials::Null);
+/// let cloud = HeterogeneousMedium::new(
+///     boundary,
+///     NoiseDensity::new(3., 5.),
+///     HenyeyGreenstein::solid_color(color![0.9, 0.9, 0.9], 0.7),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct HeterogeneousMedium<H: Hittable, D: DensityField, M: Material> {
+    boundary: H,
+    density_field: D,
+    phase_function: M,
+}
+
+impl<H: Hittable, D: DensityField, M: Material> HeterogeneousMedium<H, D, M> {
+    pub fn new(boundary: H, density_field: D, phase_function: M) -> Self {
+        Self {
+            boundary,
+            density_field,
+            phase_function,
+        }
+    }
+
+    pub fn density_field(&self) -> &D {
+        &self.density_field
+    }
+
+    /// Parameter range of `ray` relative to the boundary inside of it and the allowed range.
+    fn interval(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<(Float, Float)> {
+        // The ray is already relative to the boundary, because the medium shares its center.
+        let enter = self
+            .boundary
+            .hit_origin(ray, -Float::INFINITY, Float::INFINITY)?;
+        let exit = self
+            .boundary
+            .hit_origin(ray, enter.t + 0.0001, Float::INFINITY)?;
+
+        let t0 = enter.t.max(t_min).max(0.);
+        let t1 = exit.t.min(t_max);
+        (t0 < t1).then_some((t0, t1))
+    }
+
+    /// Fraction of the light that passes through the medium along `ray` in world coordinates between `t_min` and `t_max`, estimated by ratio tracking.
+    ///
+    /// See [`GridMedium::transmittance`].
+    pub fn transmittance(&self, ray: Ray, t_min: Float, t_max: Float) -> Float {
+        let ray = self.boundary.center().ray_to_local(ray);
+        let Some(interval) = self.interval(ray, t_min, t_max) else {
+            return 1.;
+        };
+        ratio_tracking(ray, interval, self.density_field.max_density(), |point| {
+            self.density_field.density_at(point)
+        })
+    }
+}
+
+impl<H, D, M> Hittable for HeterogeneousMedium<H, D, M>
+where
+    H: Hittable + Clone + 'static,
+    D: DensityField + Clone + 'static,
+    M: Material + Clone + 'static,
+{
+    fn hit_origin(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let interval = self.interval(ray, t_min, t_max)?;
+        let t = delta_tracking(ray, interval, self.density_field.max_density(), |point| {
+            self.density_field.density_at(point)
+        })?;
+        Some(collision(ray, t, &self.phase_function))
+    }
+
+    fn bounding_box_origin(&self, time0: Float, time1: Float) -> Option<Aabb> {
+        self.boundary.bounding_box_origin(time0, time1)
+    }
+
+    fn center(&self) -> &Offset {
+        self.boundary.center()
+    }
+}
+
+impl<H, D, M> Movable for HeterogeneousMedium<H, D, M>
+where
+    H: Movable + 'static,
+    D: DensityField + Clone + 'static,
+    M: Material + Clone + 'static,
+{
+    fn with_rotation(mut self, rotation: Rotation3<Float>) -> Self {
+        self.boundary = self.boundary.with_rotation(rotation);
+        self
+    }
+
+    fn moving(mut self, offset_end: Vector3<Float>, time_start: Float, time_end: Float) -> Self {
+        self.boundary = self.boundary.moving(offset_end, time_start, time_end);
+        self
+    }
+}
+
+/// Uniform fog filling the whole scene (or a box of it), e.g. for light shafts, see [`Raytracer::with_fog`](crate::Raytracer::with_fog).
+///
+/// Unlike a [`ConstantMedium`](crate::shapes::ConstantMedium), the fog also surrounds the camera and the lights and is not part of the world.
+/// The path tracer samples a collision with it before every hit, so [`Ray`]s that miss all objects still scatter light inside of it. Shadow [`Ray`]s are attenuated exactly by the [`transmittance`](Fog::transmittance).
+///
+/// # Fields
+/// - `density`: Optical density, i.e. the probability to scatter per unit length.
+/// - `phase_function`: [`HenyeyGreenstein`] phase function with the color of the scattered light.
+/// - `bounds`: [`Aabb`] outside of which there is no fog, or [`None`] for fog everywhere.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, hittable::Aabb, volume::Fog};
+/// // Fog up to a height of 3 that slightly prefers scattering forward
+/// let fog = Fog::new(color![0.9, 0.9, 0.9], 0.05)
+///     .with_anisotropy(0.3)
+///     .with_bounds(Aabb::new(vector![-100., -1., -100.], vector![100., 3., 100.]));
+/// let raytracer = Raytracer::new(Camera::default(), color![0., 0., 0.], 16, 9, 4, 4).with_fog(fog);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Fog {
+    density: Float,
+    phase_function: HenyeyGreenstein<SolidColor>,
+    bounds: Option<Aabb>,
+}
+
+impl Fog {
+    /// Create isotropically scattering fog of `color` and `density` everywhere.
+    pub fn new(color: Color, density: Float) -> Self {
+        Self {
+            density: density.max(0.),
+            phase_function: HenyeyGreenstein::solid_color(color, 0.),
+            bounds: None,
+        }
+    }
+
+    /// Consume `self` and scatter with the asymmetry `g` in (-1,1), see [`HenyeyGreenstein`].
+    ///
+    /// Values around 0.7 make the light shafts much brighter when looking towards the light.
+    ///
+    /// # Panics
+    /// If `g` is not in (-1,1).
+    pub fn with_anisotropy(mut self, g: Float) -> Self {
+        let albedo = self
+            .phase_function
+            .albedo()
+            .expect("the fog has a solid color");
+        self.phase_function = HenyeyGreenstein::solid_color(albedo, g);
+        self
+    }
+
+    /// Consume `self` and only fill `bounds` with fog, e.g. a layer close to the ground.
+    pub fn with_bounds(mut self, bounds: Aabb) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    pub fn density(&self) -> Float {
+        self.density
+    }
+
+    /// Parameter range of `ray` inside the fog and the allowed range.
+    fn interval(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<(Float, Float)> {
+        match &self.bounds {
+            Some(bounds) => bounds.intersect(ray, t_min, t_max),
+            None => (t_min < t_max).then_some((t_min, t_max)),
+        }
+    }
+
+    /// Fraction of the light that passes through the fog along `ray` between the parameters `t_min` and `t_max`.
+    ///
+    /// Without bounds, light from infinitely far away (like the sun of a [`Sky`](crate::background::Sky) or the background) is absorbed completely.
+    pub fn transmittance(&self, ray: Ray, t_min: Float, t_max: Float) -> Float {
+        if self.density <= 0. {
+            return 1.;
+        }
+        match self.interval(ray, t_min, t_max) {
+            Some((t0, t1)) => (-self.density * (t1 - t0) * ray.direction().norm()).exp(),
+            None => 1.,
+        }
+    }
+
+    /// Sample where `ray` scatters in the fog between `t_min` and `t_max`, if it does so before `t_max`.
+    pub(crate) fn collision(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        if self.density <= 0. {
+            return None;
+        }
+        let (t0, t1) = self.interval(ray, t_min, t_max)?;
+        let distance = -(1. - crate::random::rng().gen::<Float>()).ln() / self.density;
+        let t = t0 + distance / ray.direction().norm();
+        (t < t1).then(|| collision(ray, t, &self.phase_function))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nrrd() {
+        let mut file = b"NRRD0004\n# A 2x1x2 grid\ntype: uchar\ndimension: 3\nsizes: 2 1 2\nencoding: raw\nspace:=right-anterior-superior\n\n".to_vec();
+        file.extend([0, 255, 51, 102]);
+        let grid = parse_nrrd(&file, None).unwrap();
+        assert_eq!(grid.resolution(), (2, 1, 2));
+        assert_eq!(grid.max_density(), 1.);
+        assert_eq!(grid.density_at(vector![0.75, 0.5, 0.25]), 1.);
+        assert!((grid.density_at(vector![0.25, 0.5, 0.75]) - 0.2).abs() < 1e-6);
+        // Halfway between the voxel centers
+        assert!((grid.density_at(vector![0.5, 0.5, 0.25]) - 0.5).abs() < 1e-6);
+
+        let truncated = &file[..file.len() - 1];
+        assert!(matches!(parse_nrrd(truncated, None), Err(Error::Config(_))));
+
+        let huge = b"NRRD0004\ntype: uchar\ndimension: 3\nsizes: 4294967296 4294967296 4294967296\nencoding: raw\n\n";
+        assert!(matches!(parse_nrrd(huge, None), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn raw() {
+        let bytes: Vec<u8> = [0.5f32, 2.]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let grid = DensityGrid::read_raw(bytes.as_slice(), (1, 1, 2)).unwrap();
+        assert_eq!(grid.max_density(), 2.);
+        assert!(DensityGrid::read_raw(bytes.as_slice(), (2, 2, 2)).is_err());
+    }
+
+    #[derive(Clone, Debug)]
+    struct Constant(Float);
+
+    impl DensityField for Constant {
+        fn density_at(&self, _point: Vector3<Float>) -> Float {
+            self.0
+        }
+
+        fn max_density(&self) -> Float {
+            self.0
+        }
+    }
+
+    #[test]
+    fn heterogeneous_transmittance() {
+        let medium = HeterogeneousMedium::new(
+            crate::shapes::Sphere::new(vector![0., 0., 3.], 1., crate::materials::Null),
+            Constant(1.),
+            Isotropic::solid_color(color![1., 1., 1.]),
+        );
+        let ray = Ray::new(vector![0., 0., 0.], vector![0., 0., 1.]);
+        let samples = 4000;
+        let mean = (0..samples)
+            .map(|_| medium.transmittance(ray, 0., Float::INFINITY))
+            .sum::<Float>()
+            / samples as Float;
+        // Beer-Lambert law along the diameter
+        assert!((mean - (-2. as Float).exp()).abs() < 0.03);
+        assert_eq!(medium.transmittance(ray, 0., 1.), 1.);
+    }
+
+    #[test]
+    fn henyey_greenstein_mean_cosine() {
+        let phase_function = HenyeyGreenstein::solid_color(color![1., 1., 1.], 0.6);
+        let ray = Ray::new(vector![0., 0., 0.], vector![0., 2., 0.]);
+        let samples = 20000;
+        let mean = (0..samples)
+            .map(|_| {
+                let hit = collision(ray, 1., &phase_function);
+                let (scattered, _) = phase_function.scatter(ray, hit).unwrap();
+                let direction = scattered.direction().normalize();
+                let (_, pdf) = phase_function
+                    .evaluate(ray, &collision(ray, 1., &phase_function), direction)
+                    .unwrap();
+                assert!((pdf - phase_function.phase(direction.y)).abs() < 1e-3);
+                direction.y
+            })
+            .sum::<Float>()
+            / samples as Float;
+        assert!((mean - 0.6).abs() < 0.02);
+    }
+
+    #[test]
+    fn fog() {
+        let ray = Ray::new(vector![0., 0., 0.], vector![2., 0., 0.]);
+        let fog = Fog::new(color![1., 1., 1.], 0.5);
+        assert!((fog.transmittance(ray, 0., 1.) - (-1. as Float).exp()).abs() < 1e-6);
+        assert!(fog.collision(ray, 0., Float::INFINITY).is_some());
+
+        let bounded = fog.with_bounds(Aabb::new(vector![1., -1., -1.], vector![2., 1., 1.]));
+        assert!((bounded.transmittance(ray, 0., 10.) - (-0.5 as Float).exp()).abs() < 1e-6);
+        assert_eq!(bounded.transmittance(ray, 0., 0.25), 1.);
+        let collision = bounded.collision(ray, 0., Float::INFINITY);
+        assert!(collision.is_none_or(|hit| (1. ..=2.).contains(&hit.point.x)));
+        assert_eq!(
+            Fog::new(color![1., 1., 1.], 0.).transmittance(ray, 0., Float::INFINITY),
+            1.
+        );
+    }
+}
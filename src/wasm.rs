@@ -0,0 +1,35 @@
+//! Bindings for JavaScript, so the ray tracer can run as a demo in the browser.
+//!
+//! Build with `wasm-pack build --target web --no-default-features --features wasm`, which renders on a single thread without a progress bar.
+//! Saving images to files is not possible in the browser; the pixels are returned instead and can be drawn into a canvas:
+//! ```js
+//! import init, { render_demo } from "./pkg/ray_tracing_in_one_weekend.js";
+//!
+//! await init();
+//! const pixels = render_demo("cornell_box", 320, 320, 16);
+//! const image = new ImageData(new Uint8ClampedArray(pixels), 320, 320);
+//! canvas.getContext("2d").putImageData(image, 0, 0);
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::scenes::{self, SceneSettings};
+
+/// Render one of the [example scenes](crate::scenes) (`random_spheres`, `cornell_box`, or `next_week_final`) and return its pixels as 8 bit RGBA, row by row from the top.
+#[wasm_bindgen]
+pub fn render_demo(
+    scene: &str,
+    image_width: u32,
+    image_height: u32,
+    samples_per_pixel: u16,
+) -> Result<Vec<u8>, JsError> {
+    let settings = SceneSettings::new(image_width, image_height);
+    let settings = settings.with_samples(samples_per_pixel, settings.max_depth);
+    let raytracer = match scene {
+        "random_spheres" => scenes::random_spheres(&settings),
+        "cornell_box" => scenes::cornell_box(&settings),
+        "next_week_final" => scenes::next_week_final(&settings),
+        _ => return Err(JsError::new(&format!("unknown scene {scene:?}"))),
+    };
+    Ok(raytracer.render_to_rgba_buffer()?)
+}
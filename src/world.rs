@@ -0,0 +1,108 @@
+//! Ray queries against a scene without rendering it, so the crate can also be used for collision checks, lightmapping tools, or audio occlusion.
+//!
+//! A [`World`] builds the same acceleration structure as a render once and can then be queried from many threads.
+
+use crate::accelerator::Accelerator;
+use crate::error::Error;
+use crate::hitrecord::HitRecord;
+use crate::hittable::{BvhBuildStrategy, HittableListOptions};
+use crate::integrator::{hit_visible, RayOffset, T_MIN};
+use crate::ray::{Ray, RayKind};
+use crate::raytracer::accelerate;
+use crate::*;
+
+/// A [`HittableList`] prepared for ray queries.
+///
+/// Like the renderers, the queries pass through objects with a [`Null`](crate::materials::Null) material.
+///
+/// # Example
+/// ```
+/// # use ray_tracing_in_one_weekend::{*, materials::Lambertian, ray::Ray, shapes::Sphere, world::World};
+/// let mut objects = HittableList::default();
+/// objects.push(Sphere::new(vector![0., 0., 0.], 1., Lambertian::solid_color(color![0.5, 0.5, 0.5])));
+/// let world = World::new(objects).unwrap();
+///
+/// let hit = world.raycast(Ray::new(vector![0., 0., 5.], vector![0., 0., -1.]), 0., Float::INFINITY).unwrap();
+/// assert!((hit.t - 4.).abs() < 1e-4);
+/// assert!(world.occluded(vector![0., 0., 5.], vector![0., 0., -5.]));
+/// assert!(!world.occluded(vector![0., 2., 5.], vector![0., 2., -5.]));
+/// ```
+pub struct World {
+    hittables: HittableListOptions,
+}
+
+impl World {
+    /// Build a [`World`] with a [`Bvh`](crate::hittable::Bvh), or without acceleration if some objects are unbounded.
+    pub fn new(world: HittableList) -> Result<Self, Error> {
+        Self::with_accelerator(world, Accelerator::default(), BvhBuildStrategy::default())
+    }
+
+    /// Build a [`World`] with `accelerator`, using `bvh_strategy` for a [`Bvh`](crate::hittable::Bvh).
+    pub fn with_accelerator(
+        world: HittableList,
+        accelerator: Accelerator,
+        bvh_strategy: BvhBuildStrategy,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            hittables: accelerate(world, true, accelerator, bvh_strategy)?,
+        })
+    }
+
+    /// Nearest hit of `ray` with a parameter between `t_min` and `t_max`.
+    pub fn raycast(&self, ray: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        hit_visible(
+            &self.hittables,
+            ray,
+            RayOffset::Fixed { epsilon: t_min },
+            t_max,
+        )
+    }
+
+    /// Whether any object lies between the points `a` and `b`.
+    ///
+    /// The query is a shadow [`Ray`] (see [`RayKind::Shadow`]), so it ignores objects that do not cast shadows. Surfaces within a small distance of either point are not counted.
+    pub fn occluded(&self, a: Vector3<Float>, b: Vector3<Float>) -> bool {
+        let distance = (b - a).norm();
+        if distance <= 2. * T_MIN {
+            return false;
+        }
+        let ray = Ray::new(a, (b - a) / distance).with_kind(RayKind::Shadow);
+        self.raycast(ray, T_MIN, distance - T_MIN).is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hittable::{Visibility, Visible};
+    use crate::materials::{Lambertian, Null};
+    use crate::shapes::Sphere;
+
+    #[test]
+    fn occlusion() {
+        let mut objects = HittableList::default();
+        objects.push(Sphere::new(vector![0., 0., 0.], 1., Null));
+        let no_shadow = Visibility {
+            shadow: false,
+            ..Visibility::ALL
+        };
+        objects.push(Visible::new(
+            Sphere::new(
+                vector![0., 3., 0.],
+                1.,
+                Lambertian::solid_color(color![0.5, 0.5, 0.5]),
+            ),
+            no_shadow,
+        ));
+        let world =
+            World::with_accelerator(objects, Accelerator::Grid, BvhBuildStrategy::default())
+                .unwrap();
+
+        // Null materials are passed through.
+        assert!(!world.occluded(vector![0., 0., 5.], vector![0., 0., -5.]));
+        // Objects without shadows are hit by other rays, but do not occlude.
+        let ray = Ray::new(vector![0., 3., 5.], vector![0., 0., -1.]);
+        assert!(world.raycast(ray, 0., Float::INFINITY).is_some());
+        assert!(!world.occluded(vector![0., 3., 5.], vector![0., 3., -5.]));
+    }
+}